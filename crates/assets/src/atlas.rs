@@ -0,0 +1,368 @@
+//! Runtime texture atlases for asset textures shared by many elements.
+//!
+//! Every distinct texture is a separate GPU texture, which prevents Bevy from batching the
+//! sprites using them. Textures used by several elements are packed into shared atlas pages
+//! instead, so all sprites drawing from the same page share a single texture and batch together.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::{DynamicTextureAtlasBuilder, TextureAtlas, TextureAtlasLayout};
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+/// The texture an element is displayed with.
+///
+/// The element's [`Sprite`] is managed by the [`TextureAtlases`]: it points at the texture
+/// itself until the texture is packed into an atlas page, after which it points at the page.
+#[derive(Component, Debug, Clone)]
+#[require(Sprite)]
+pub struct AtlasTexture(pub Handle<Image>);
+
+/// Controls how textures are packed into atlas pages.
+#[derive(Debug, Clone)]
+pub struct AtlasSettings {
+    /// The size of a single atlas page.
+    pub page_size: UVec2,
+    /// The gap between textures in a page, preventing bleeding when sampling near edges.
+    pub padding: u32,
+    /// Textures larger than this in either dimension are never packed and keep their own
+    /// texture, so a handful of large textures can't exhaust the pages.
+    pub max_texture_size: UVec2,
+    /// The number of elements that need to use a texture before it's packed.
+    pub min_uses: usize,
+}
+
+impl Default for AtlasSettings {
+    fn default() -> Self {
+        Self {
+            page_size: UVec2::splat(4096),
+            padding: 2,
+            max_texture_size: UVec2::splat(1024),
+            min_uses: 2,
+        }
+    }
+}
+
+/// Where a texture ended up after it was considered for packing.
+#[derive(Debug, Clone)]
+pub enum AtlasSlot {
+    /// The texture was packed into an atlas page.
+    Packed {
+        /// The texture of the atlas page.
+        image: Handle<Image>,
+        /// The layout of the atlas page.
+        layout: Handle<TextureAtlasLayout>,
+        /// The index of the texture in the layout.
+        index: usize,
+    },
+    /// The texture couldn't be packed and is rendered from its own texture.
+    Standalone,
+}
+
+/// A single texture shared by all textures packed into it.
+struct AtlasPage {
+    /// Allocates space for new textures within the page.
+    builder: DynamicTextureAtlasBuilder,
+    /// The texture of the page.
+    image: Handle<Image>,
+    /// The location of each packed texture within the page.
+    layout: Handle<TextureAtlasLayout>,
+}
+
+/// Packs frequently used textures into shared atlas pages.
+#[derive(Resource, Default)]
+pub struct TextureAtlases {
+    /// Controls how textures are packed.
+    settings: AtlasSettings,
+    /// The atlas pages allocated so far.
+    pages: Vec<AtlasPage>,
+    /// The number of elements using each texture.
+    uses: HashMap<AssetId<Image>, usize>,
+    /// The texture each element is counted as a use of.
+    users: HashMap<Entity, AssetId<Image>>,
+    /// The textures that were considered for packing, and where they ended up.
+    slots: HashMap<AssetId<Image>, AtlasSlot>,
+}
+
+impl TextureAtlases {
+    /// Creates an empty set of atlases using `settings`.
+    #[must_use]
+    pub fn new(settings: AtlasSettings) -> Self {
+        Self {
+            settings,
+            ..Self::default()
+        }
+    }
+
+    /// The settings the atlases are packed with.
+    #[must_use]
+    pub fn settings(&self) -> &AtlasSettings {
+        &self.settings
+    }
+
+    /// Returns where `texture` ended up, or `None` if it hasn't been considered for packing.
+    #[must_use]
+    pub fn slot(&self, texture: impl Into<AssetId<Image>>) -> Option<&AtlasSlot> {
+        self.slots.get(&texture.into())
+    }
+
+    /// The number of elements currently using `texture`.
+    #[must_use]
+    pub fn uses(&self, texture: impl Into<AssetId<Image>>) -> usize {
+        self.uses.get(&texture.into()).copied().unwrap_or_default()
+    }
+
+    /// Counts `element` as a use of `texture`, no longer counting it as a use of the texture it
+    /// used before.
+    fn start_using(&mut self, element: Entity, texture: AssetId<Image>) {
+        if let Some(previous) = self.users.insert(element, texture) {
+            if previous == texture {
+                return;
+            }
+            self.release(previous);
+        }
+        *self.uses.entry(texture).or_default() += 1;
+    }
+
+    /// No longer counts `element` as a use of its texture.
+    fn stop_using(&mut self, element: Entity) {
+        if let Some(texture) = self.users.remove(&element) {
+            self.release(texture);
+        }
+    }
+
+    /// Takes a use of `texture` away, forgetting the texture once it's no longer used.
+    fn release(&mut self, texture: AssetId<Image>) {
+        if let Some(uses) = self.uses.get_mut(&texture) {
+            *uses = uses.saturating_sub(1);
+            if *uses == 0 {
+                self.uses.remove(&texture);
+            }
+        }
+    }
+
+    /// The number of atlas pages allocated so far.
+    #[must_use]
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Points `sprite` at the atlas page containing `texture`, or at `texture` itself if it
+    /// hasn't been packed.
+    fn apply(&self, texture: &Handle<Image>, sprite: &mut Sprite) {
+        match self.slots.get(&texture.id()) {
+            Some(AtlasSlot::Packed {
+                image,
+                layout,
+                index,
+            }) => {
+                sprite.image = image.clone();
+                sprite.texture_atlas = Some(TextureAtlas {
+                    layout: layout.clone(),
+                    index: *index,
+                });
+            }
+            Some(AtlasSlot::Standalone) | None => {
+                sprite.image = texture.clone();
+                sprite.texture_atlas = None;
+            }
+        }
+    }
+
+    /// Packs `texture` into the first page with enough space, allocating a new page if needed.
+    fn pack(
+        &mut self,
+        texture: &Image,
+        images: &mut Assets<Image>,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) -> AtlasSlot {
+        let size = texture.size();
+        if size.x > self.settings.max_texture_size.x
+            || size.y > self.settings.max_texture_size.y
+            || size.x + self.settings.padding > self.settings.page_size.x
+            || size.y + self.settings.padding > self.settings.page_size.y
+        {
+            return AtlasSlot::Standalone;
+        }
+
+        let Some(texture) = texture.convert(TextureFormat::Rgba8UnormSrgb) else {
+            return AtlasSlot::Standalone;
+        };
+
+        for page in &mut self.pages {
+            if let Some(slot) = page.add(&texture, images, layouts) {
+                return slot;
+            }
+        }
+
+        let mut page = AtlasPage::new(
+            self.settings.page_size,
+            self.settings.padding,
+            images,
+            layouts,
+        );
+        let slot = page
+            .add(&texture, images, layouts)
+            .unwrap_or(AtlasSlot::Standalone);
+        self.pages.push(page);
+
+        slot
+    }
+}
+
+impl AtlasPage {
+    /// Allocates an empty page of `size`.
+    fn new(
+        size: UVec2,
+        padding: u32,
+        images: &mut Assets<Image>,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) -> Self {
+        let image = Image::new_fill(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+
+        Self {
+            builder: DynamicTextureAtlasBuilder::new(size, padding),
+            image: images.add(image),
+            layout: layouts.add(TextureAtlasLayout::new_empty(size)),
+        }
+    }
+
+    /// Copies `texture` into the page, returning `None` if there's no space left.
+    fn add(
+        &mut self,
+        texture: &Image,
+        images: &mut Assets<Image>,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) -> Option<AtlasSlot> {
+        let layout = layouts.get_mut(&self.layout)?;
+        let image = images.get_mut(&self.image)?;
+        let index = self.builder.add_texture(layout, texture, image).ok()?;
+
+        Some(AtlasSlot::Packed {
+            image: self.image.clone(),
+            layout: self.layout.clone(),
+            index,
+        })
+    }
+}
+
+/// Counts the uses of textures by the elements that were given one, replaced theirs or stopped
+/// using theirs, and points the sprites of the changed elements at the texture's current location.
+pub(crate) fn track_atlas_usage(
+    mut atlases: ResMut<TextureAtlases>,
+    mut removed: RemovedComponents<AtlasTexture>,
+    mut query: Query<(Entity, &AtlasTexture, &mut Sprite), Changed<AtlasTexture>>,
+) {
+    // Removals come first, an element whose texture was removed then inserted again still uses it.
+    for element in removed.read() {
+        atlases.stop_using(element);
+    }
+    for (element, texture, mut sprite) in &mut query {
+        atlases.start_using(element, texture.0.id());
+        atlases.apply(&texture.0, &mut sprite);
+    }
+}
+
+/// Packs loaded textures that reached the usage threshold, and moves the sprites using them
+/// to their atlas page.
+pub(crate) fn pack_atlas_textures(
+    mut atlases: ResMut<TextureAtlases>,
+    mut images: ResMut<Assets<Image>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut query: Query<(&AtlasTexture, &mut Sprite)>,
+) {
+    let candidates = atlases
+        .uses
+        .iter()
+        .filter(|(id, uses)| {
+            **uses >= atlases.settings.min_uses && !atlases.slots.contains_key(*id)
+        })
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+
+    let mut packed = HashSet::new();
+    for id in candidates {
+        let Some(texture) = images.get(id).cloned() else {
+            continue;
+        };
+
+        let slot = atlases.pack(&texture, &mut images, &mut layouts);
+        if matches!(slot, AtlasSlot::Packed { .. }) {
+            packed.insert(id);
+        }
+        atlases.slots.insert(id, slot);
+    }
+
+    if packed.is_empty() {
+        return;
+    }
+
+    for (texture, mut sprite) in &mut query {
+        if packed.contains(&texture.0.id()) {
+            atlases.apply(&texture.0, &mut sprite);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Counts the uses of textures as elements are given, change and lose their texture.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+    use bevy::asset::uuid::Uuid;
+    use bevy::ecs::system::RunSystemOnce;
+
+    /// A texture identified by `id`, without loading anything.
+    fn texture(id: u128) -> Handle<Image> {
+        Handle::Uuid(Uuid::from_u128(id), std::marker::PhantomData)
+    }
+
+    /// Runs [`track_atlas_usage`] once.
+    fn track(world: &mut World) {
+        world
+            .run_system_once(track_atlas_usage)
+            .expect("the system runs");
+    }
+
+    /// Uses are counted once per element, and given back when the element stops using the texture.
+    #[test]
+    fn counts_current_uses() {
+        let mut world = World::new();
+        world.init_resource::<TextureAtlases>();
+        let (stone, wood) = (texture(1), texture(2));
+
+        let first = world.spawn(AtlasTexture(stone.clone())).id();
+        let second = world.spawn(AtlasTexture(stone.clone())).id();
+        track(&mut world);
+        assert_eq!(world.resource::<TextureAtlases>().uses(&stone), 2);
+
+        // Changing an element without replacing its texture doesn't count it again.
+        world.get_mut::<AtlasTexture>(first).unwrap().set_changed();
+        track(&mut world);
+        assert_eq!(world.resource::<TextureAtlases>().uses(&stone), 2);
+
+        world.entity_mut(first).insert(AtlasTexture(wood.clone()));
+        track(&mut world);
+        assert_eq!(world.resource::<TextureAtlases>().uses(&stone), 1);
+        assert_eq!(world.resource::<TextureAtlases>().uses(&wood), 1);
+
+        world.despawn(second);
+        world.entity_mut(first).remove::<AtlasTexture>();
+        track(&mut world);
+        let atlases = world.resource::<TextureAtlases>();
+        assert_eq!(atlases.uses(&stone), 0);
+        assert_eq!(atlases.uses(&wood), 0);
+        assert!(atlases.users.is_empty());
+    }
+}
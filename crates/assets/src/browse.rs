@@ -0,0 +1,314 @@
+//! Lists the assets of an [`AssetPack`] by file name, taking the place of the Tantivy index when
+//! the `search` feature is disabled.
+
+use crate::index::asset_extension;
+use crate::query::{category_counts, completion_distance, rank_completions};
+use crate::{
+    AssetHit, AssetPack, AssetQuery, AssetResults, IndexError, IndexSettings, PackChanges, UserTags,
+};
+use bevy::platform::collections::{HashMap, HashSet};
+use dungeonrs_utils::CancellationToken;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// An asset listed by an [`AssetPackIndex`].
+struct ListedAsset {
+    /// The path of the asset, relative to the pack's root.
+    path: PathBuf,
+    /// The name the asset is searched by.
+    name: String,
+    /// The category of the asset, as given by the pack's manifest.
+    category: Vec<String>,
+    /// The tags of the asset, as given by the pack's manifest.
+    tags: Vec<String>,
+}
+
+impl ListedAsset {
+    /// Lists the asset at `path`, relative to the root of `pack`.
+    fn new(pack: &AssetPack, path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            name: searchable_name(path),
+            category: pack.manifest.category(path),
+            tags: pack.manifest.tags(path),
+        }
+    }
+}
+
+/// The assets of a single [`AssetPack`], searched by matching their file names.
+///
+/// Nothing is stored on disk, the pack is listed again whenever it's opened. Clones share the same
+/// list of assets.
+#[derive(Clone)]
+pub struct AssetPackIndex {
+    /// The assets of the pack, ordered by path.
+    assets: Arc<RwLock<Vec<ListedAsset>>>,
+}
+
+impl AssetPackIndex {
+    /// Opens an empty list for the assets of `pack`, [`AssetPackIndex::rebuild`] lists them.
+    ///
+    /// # Errors
+    /// Never fails, matching the signature of the Tantivy index.
+    pub fn open(_pack: &AssetPack) -> Result<Self, IndexError> {
+        Ok(Self {
+            assets: Arc::default(),
+        })
+    }
+
+    /// Opens an empty list, nothing is stored in `path`.
+    ///
+    /// # Errors
+    /// Never fails, matching the signature of the Tantivy index.
+    pub fn open_in(_path: &Path) -> Result<Self, IndexError> {
+        Ok(Self {
+            assets: Arc::default(),
+        })
+    }
+
+    /// Replaces the listed assets with the assets currently in `pack`, the settings only apply
+    /// to the Tantivy index.
+    ///
+    /// `progress` is called with the number of files checked so far and the number of files in
+    /// the pack after each file. Cancelling `token` stops listing before the next file, leaving
+    /// the listed assets as they were.
+    ///
+    /// Returns the number of listed assets.
+    ///
+    /// # Errors
+    /// Returns an error if the pack can't be read.
+    pub fn rebuild(
+        &self,
+        pack: &AssetPack,
+        _settings: &IndexSettings,
+        token: &CancellationToken,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<usize, IndexError> {
+        let files = pack.files()?;
+        let total = files.len();
+        let mut assets = Vec::new();
+        for (checked, file) in files.into_iter().enumerate() {
+            token.check()?;
+            progress(checked + 1, total);
+            if asset_extension(&file.path).is_some() {
+                assets.push(ListedAsset::new(pack, &file.path));
+            }
+        }
+
+        let count = assets.len();
+        *self.assets.write().unwrap_or_else(PoisonError::into_inner) = assets;
+
+        Ok(count)
+    }
+
+    /// Applies `changes` to the files of `pack` to the listed assets, without listing the whole
+    /// pack again.
+    ///
+    /// Returns the number of listed assets afterwards.
+    ///
+    /// # Errors
+    /// Never fails, matching the signature of the Tantivy index.
+    pub fn update(&self, pack: &AssetPack, changes: &PackChanges) -> Result<u64, IndexError> {
+        let mut assets = self.assets.write().unwrap_or_else(PoisonError::into_inner);
+        // Removed directories take the assets they contained with them, and modified or added
+        // assets are listed again.
+        assets.retain(|asset| {
+            !changes
+                .removed
+                .iter()
+                .chain(&changes.added)
+                .chain(&changes.modified)
+                .any(|changed| asset.path.starts_with(changed))
+        });
+        for path in changes.added.iter().chain(&changes.modified) {
+            if asset_extension(path).is_some() && pack.root.join(path).is_file() {
+                assets.push(ListedAsset::new(pack, path));
+            }
+        }
+        assets.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(assets.len() as u64)
+    }
+
+    /// Returns the paths (relative to the pack's root) of the assets whose name contains every
+    /// word of `query`, ordered by path.
+    ///
+    /// A word is also contained when it begins one of the name's words with a few typos.
+    ///
+    /// # Errors
+    /// Never fails, matching the signature of the Tantivy index.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<PathBuf>, IndexError> {
+        let query = query.to_lowercase();
+        let words: Vec<_> = query.split_whitespace().collect();
+
+        Ok(self
+            .assets
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|asset| words.iter().all(|word| contains(&asset.name, word)))
+            .take(limit)
+            .map(|asset| asset.path.clone())
+            .collect())
+    }
+
+    /// Runs `query` against the assets of the pack identified by `pack`, ordered by path.
+    ///
+    /// The name matches like with [`AssetPackIndex::search`], and every match scores the same.
+    /// Assets have the tags given by the pack's manifest, along with their `user_tags`.
+    ///
+    /// # Errors
+    /// Never fails, matching the signature of the Tantivy index.
+    pub fn query(
+        &self,
+        pack: &str,
+        query: &AssetQuery,
+        user_tags: &UserTags,
+    ) -> Result<AssetResults, IndexError> {
+        if !query.includes_pack(pack) {
+            return Ok(AssetResults::default());
+        }
+
+        let text = query.search_text().to_lowercase();
+        let words: Vec<_> = text.split_whitespace().collect();
+        let tags = query.normalized_tags();
+        let tagged: Vec<HashSet<&Path>> = tags
+            .iter()
+            .map(|tag| user_tags.paths(pack, tag).collect())
+            .collect();
+        let categories = query.category_paths();
+
+        let mut results = AssetResults::default();
+        let mut counts = HashMap::default();
+        for asset in self
+            .assets
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            if !words.iter().all(|word| contains(&asset.name, word))
+                || !tags.iter().zip(&tagged).all(|(tag, tagged)| {
+                    asset.tags.contains(tag) || tagged.contains(asset.path.as_path())
+                })
+            {
+                continue;
+            }
+
+            // The categories are counted without filtering by category.
+            let category = &asset.category;
+            count_categories(category, &categories, &mut counts);
+            let in_category = |selected: &Vec<&str>| {
+                category.len() >= selected.len()
+                    && selected.iter().zip(category).all(|(a, b)| a == b)
+            };
+            if !categories.is_empty() && !categories.iter().any(in_category) {
+                continue;
+            }
+
+            if results.total >= query.offset && results.hits.len() < query.limit {
+                results.hits.push(AssetHit {
+                    pack: pack.to_owned(),
+                    path: asset.path.clone(),
+                    score: 1.0,
+                });
+            }
+            results.total += 1;
+        }
+        results.categories = category_counts(counts);
+
+        Ok(results)
+    }
+
+    /// Completes the last word of `prefix` with the words of the listed names, returning up to
+    /// `limit` completed texts for autocompletion.
+    ///
+    /// The names' words `prefix` ends with the beginning of come first, the ones it only
+    /// reaches with a typo after, most common first. Nothing is suggested once `prefix` ends with
+    /// a separator.
+    ///
+    /// # Errors
+    /// Never fails, matching the signature of the Tantivy index.
+    pub fn suggest(&self, prefix: &str, limit: usize) -> Result<Vec<String>, IndexError> {
+        let start = prefix.trim_end_matches(|character: char| !is_separator(character));
+        let word = prefix[start.len()..].to_lowercase();
+        if word.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut candidates = HashMap::default();
+        for asset in self
+            .assets
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            let mut words: Vec<_> = asset.name.split_whitespace().collect();
+            words.sort_unstable();
+            words.dedup();
+            for word in words {
+                *candidates.entry(word.to_owned()).or_default() += 1;
+            }
+        }
+
+        Ok(rank_completions(&word, candidates, limit)
+            .into_iter()
+            .map(|completion| format!("{start}{completion}"))
+            .collect())
+    }
+
+    /// The number of assets in the pack.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        let assets = self.assets.read().unwrap_or_else(PoisonError::into_inner);
+
+        assets.len() as u64
+    }
+
+    /// Returns whether the pack holds no assets.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The name the asset at `path` is searched by, its file name in lowercase with separators
+/// replaced by spaces.
+fn searchable_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| {
+            stem.to_string_lossy()
+                .replace(is_separator, " ")
+                .to_lowercase()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns whether the searchable `name` contains `word`, or one of its words begins with `word`
+/// give or take a few typos.
+fn contains(name: &str, word: &str) -> bool {
+    name.contains(word)
+        || name
+            .split_whitespace()
+            .any(|candidate| completion_distance(word, candidate).is_some())
+}
+
+/// Returns whether `character` separates the words of a name.
+fn is_separator(character: char) -> bool {
+    character.is_whitespace() || matches!(character, '_' | '-')
+}
+
+/// Counts an asset in `category` towards its top-level category, and towards the subcategory
+/// of each of the `searched` categories it lies in.
+fn count_categories(
+    category: &[String],
+    searched: &[Vec<&str>],
+    counts: &mut HashMap<String, u64>,
+) {
+    let parents = std::iter::once(&[][..]).chain(searched.iter().map(Vec::as_slice));
+    for parent in parents {
+        if category.len() > parent.len() && parent.iter().zip(category).all(|(a, b)| a == b) {
+            let child = category[..=parent.len()].join("/");
+            *counts.entry(child).or_default() += 1;
+        }
+    }
+}
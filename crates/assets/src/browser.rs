@@ -0,0 +1,345 @@
+//! Virtualises the thumbnail grid of the asset browser.
+//!
+//! A library can hold hundreds of thousands of assets, laying out (let alone loading) a cell for
+//! each of them every frame is far too slow. The [`BrowserGrid`] computes which cells intersect
+//! the visible part of the grid so only those are built, and thumbnails are requested through the
+//! [`TextureCache`] as their cell scrolls into view, showing a placeholder until they're loaded.
+//!
+//! The [`AssetBrowser`] holds the results of the search shown in the grid. They're queried a page
+//! at a time as the grid scrolls towards the end of the loaded results, so a search matching every
+//! asset of the library costs no more than one matching a handful.
+
+use crate::{
+    AssetHit, AssetLibrary, AssetQuery, CategoryCount, IndexError, PackAssetsChanged,
+    PackIndexReady, PackIndexes, TextureCache, TextureKind,
+};
+use bevy::asset::{AssetPath, LoadState};
+use bevy::prelude::*;
+use dungeonrs_macros::bevy_system;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// The layout of the asset browser's grid of thumbnails.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BrowserGrid {
+    /// The size of a single cell, including its label.
+    pub cell_size: Vec2,
+    /// The space between cells, both horizontally and vertically.
+    pub spacing: f32,
+    /// The number of rows above and below the viewport that are considered visible, so their
+    /// thumbnails are already loading by the time they scroll into view.
+    pub overscan: usize,
+}
+
+impl Default for BrowserGrid {
+    fn default() -> Self {
+        Self {
+            cell_size: Vec2::new(128.0, 148.0),
+            spacing: 8.0,
+            overscan: 2,
+        }
+    }
+}
+
+/// The cells of a [`BrowserGrid`] that intersect the viewport.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisibleCells {
+    /// The number of cells per row.
+    pub columns: usize,
+    /// The visible rows.
+    pub rows: Range<usize>,
+    /// The indices of the visible items.
+    pub items: Range<usize>,
+}
+
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss,
+    reason = "row and column counts are far below the range where f32 loses precision"
+)]
+impl BrowserGrid {
+    /// The number of cells per row that fit in `width`, at least one.
+    #[must_use]
+    pub fn columns(&self, width: f32) -> usize {
+        let stride = self.cell_size.x + self.spacing;
+        (((width + self.spacing) / stride).floor() as usize).max(1)
+    }
+
+    /// The number of rows needed to show `count` items in `width`.
+    #[must_use]
+    pub fn rows(&self, count: usize, width: f32) -> usize {
+        count.div_ceil(self.columns(width))
+    }
+
+    /// The height of the whole grid, used to size the scroll area without building any cells.
+    #[must_use]
+    pub fn content_height(&self, count: usize, width: f32) -> f32 {
+        let rows = self.rows(count, width) as f32;
+
+        (rows * (self.cell_size.y + self.spacing) - self.spacing).max(0.0)
+    }
+
+    /// The cells that intersect the viewport of `viewport_height` scrolled down by `scroll`,
+    /// extended by [`overscan`](Self::overscan) rows on either side.
+    #[must_use]
+    pub fn visible(
+        &self,
+        count: usize,
+        width: f32,
+        scroll: f32,
+        viewport_height: f32,
+    ) -> VisibleCells {
+        let columns = self.columns(width);
+        let rows = count.div_ceil(columns);
+        let stride = self.cell_size.y + self.spacing;
+
+        let first = (scroll.max(0.0) / stride).floor() as usize;
+        let last = ((scroll.max(0.0) + viewport_height.max(0.0)) / stride).ceil() as usize;
+        let start = first.saturating_sub(self.overscan).min(rows);
+        let end = last.saturating_add(self.overscan).min(rows);
+
+        VisibleCells {
+            columns,
+            rows: start..end,
+            items: (start * columns).min(count)..(end * columns).min(count),
+        }
+    }
+
+    /// The area of the cell showing the item at `index`, relative to the top left of the grid.
+    #[must_use]
+    pub fn cell_rect(&self, index: usize, width: f32) -> Rect {
+        let columns = self.columns(width);
+        let position = Vec2::new((index % columns) as f32, (index / columns) as f32)
+            * (self.cell_size + self.spacing);
+
+        Rect::from_corners(position, position + self.cell_size)
+    }
+}
+
+/// What to show in the cell of an asset.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Thumbnail {
+    /// The thumbnail is still loading, a placeholder should be shown.
+    Loading,
+    /// The thumbnail finished loading.
+    Ready(Handle<Image>),
+    /// The thumbnail couldn't be loaded.
+    Failed,
+}
+
+impl TextureCache {
+    /// Requests the thumbnail at `path` for a cell that's visible, loading it in the background
+    /// the first time it's requested.
+    pub fn thumbnail<'a>(
+        &mut self,
+        asset_server: &AssetServer,
+        path: impl Into<AssetPath<'a>>,
+    ) -> Thumbnail {
+        let handle = self.load(asset_server, path, TextureKind::Thumbnail);
+        match asset_server.load_state(&handle) {
+            LoadState::Loaded => Thumbnail::Ready(handle),
+            LoadState::Failed(_) => Thumbnail::Failed,
+            LoadState::NotLoaded | LoadState::Loading => Thumbnail::Loading,
+        }
+    }
+}
+
+/// The search shown in the asset browser and the results loaded so far.
+///
+/// The user interface changes the search with [`SearchAssets`] and reports the visible cells of
+/// its [`BrowserGrid`] with [`ScrollAssetBrowser`], and the results are loaded as they're needed.
+/// The search runs again whenever a pack's index opens or changes, or the user's tags change.
+#[derive(Resource, Debug, Clone)]
+pub struct AssetBrowser {
+    /// The number of results loaded at a time.
+    pub page_size: usize,
+    /// The shown search, its offset and limit are ignored.
+    query: AssetQuery,
+    /// The results loaded so far, best matches first.
+    hits: Vec<AssetHit>,
+    /// The number of assets matching the search.
+    total: usize,
+    /// The number of matching assets in each category, for the category filters.
+    categories: Vec<CategoryCount>,
+    /// The indices of the results in the visible cells.
+    visible: Range<usize>,
+    /// The thumbnails of the results in the visible cells.
+    thumbnails: Vec<Thumbnail>,
+    /// Whether the search has to run again from its first page.
+    outdated: bool,
+}
+
+impl Default for AssetBrowser {
+    fn default() -> Self {
+        Self {
+            page_size: 200,
+            query: AssetQuery::default(),
+            hits: Vec::new(),
+            total: 0,
+            categories: Vec::new(),
+            visible: 0..0,
+            thumbnails: Vec::new(),
+            outdated: true,
+        }
+    }
+}
+
+impl AssetBrowser {
+    /// The shown search.
+    #[must_use]
+    pub fn query(&self) -> &AssetQuery {
+        &self.query
+    }
+
+    /// The results loaded so far, best matches first.
+    #[must_use]
+    pub fn hits(&self) -> &[AssetHit] {
+        &self.hits
+    }
+
+    /// The result at `index`, `None` until its page is loaded.
+    #[must_use]
+    pub fn hit(&self, index: usize) -> Option<&AssetHit> {
+        self.hits.get(index)
+    }
+
+    /// The number of assets matching the search, which sizes the [`BrowserGrid`].
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The number of assets matching the search in each category, ignoring the category filters,
+    /// so the other categories can be offered.
+    #[must_use]
+    pub fn categories(&self) -> &[CategoryCount] {
+        &self.categories
+    }
+
+    /// The thumbnail of the result at `index`, `None` when its cell isn't visible.
+    #[must_use]
+    pub fn thumbnail(&self, index: usize) -> Option<&Thumbnail> {
+        let offset = index.checked_sub(self.visible.start)?;
+        self.thumbnails.get(offset)
+    }
+
+    /// The file of the result at `index`, such as to place it on the map.
+    ///
+    /// Returns `None` until its page is loaded or once its pack is no longer registered.
+    #[must_use]
+    pub fn asset_path(&self, library: &AssetLibrary, index: usize) -> Option<PathBuf> {
+        let hit = self.hit(index)?;
+        Some(library.pack(&hit.pack)?.root.join(&hit.path))
+    }
+
+    /// Replaces the shown search, dropping the loaded results.
+    fn search(&mut self, query: AssetQuery) {
+        self.query = query;
+        self.outdated = true;
+    }
+
+    /// The query loading the page after the loaded results.
+    fn next_page(&self) -> AssetQuery {
+        AssetQuery {
+            offset: self.hits.len(),
+            limit: self.page_size.max(1),
+            ..self.query.clone()
+        }
+    }
+}
+
+/// Replaces the search shown in the asset browser, such as when the user types or picks a pack or
+/// category filter.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct SearchAssets {
+    /// The search, its offset and limit are ignored.
+    pub query: AssetQuery,
+}
+
+/// Reports the cells of the asset browser that are visible, so their results and thumbnails are
+/// loaded.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct ScrollAssetBrowser {
+    /// The indices of the results in the visible cells, as given by [`VisibleCells::items`].
+    pub visible: Range<usize>,
+}
+
+/// Written when the search of the asset browser failed.
+#[derive(Message, Debug)]
+pub struct AssetSearchFailed {
+    /// The reason the search failed.
+    pub error: IndexError,
+}
+
+/// Runs the search of the asset browser, loading the pages up to the visible cells, and requests
+/// their thumbnails.
+#[bevy_system]
+#[allow(
+    clippy::too_many_arguments,
+    reason = "the browser is driven by its requests and by the packs, their indexes and textures"
+)]
+pub(crate) fn browse_assets(
+    mut browser: ResMut<AssetBrowser>,
+    mut searches: MessageReader<SearchAssets>,
+    mut scrolls: MessageReader<ScrollAssetBrowser>,
+    mut ready: MessageReader<PackIndexReady>,
+    mut changed: MessageReader<PackAssetsChanged>,
+    mut failures: MessageWriter<AssetSearchFailed>,
+    indexes: Res<PackIndexes>,
+    library: Res<AssetLibrary>,
+    mut textures: ResMut<TextureCache>,
+    asset_server: Res<AssetServer>,
+) {
+    if let Some(search) = searches.read().last() {
+        browser.search(search.query.clone());
+    }
+    if let Some(scroll) = scrolls.read().last() {
+        browser.visible = scroll.visible.clone();
+    }
+    if ready.read().count() > 0 || changed.read().count() > 0 || library.is_changed() {
+        browser.outdated = true;
+    }
+
+    // The results already shown are loaded again at once, so the grid doesn't jump back.
+    let wanted = if browser.outdated {
+        browser.outdated = false;
+        let shown = browser.hits.len();
+        browser.hits.clear();
+        browser.total = 0;
+        browser.categories.clear();
+        browser.visible.end.max(shown).max(1)
+    } else {
+        browser.visible.end.min(browser.total)
+    };
+    while browser.hits.len() < wanted {
+        let query = browser.next_page();
+        match indexes.query(&query, &library.user_tags) {
+            Ok(results) => {
+                let last = results.hits.len() < query.limit;
+                browser.total = results.total;
+                browser.categories = results.categories;
+                browser.hits.extend(results.hits);
+                if last || browser.hits.len() >= browser.total {
+                    break;
+                }
+            }
+            Err(error) => {
+                failures.write(AssetSearchFailed { error });
+                break;
+            }
+        }
+    }
+
+    // Only the loaded results of the visible cells get a thumbnail.
+    let end = browser.visible.end.min(browser.hits.len());
+    let start = browser.visible.start.min(end);
+    let thumbnails = browser.hits[start..end]
+        .iter()
+        .map(|hit| match library.pack(&hit.pack) {
+            Some(pack) => textures.thumbnail(&asset_server, pack.root.join(&hit.path)),
+            None => Thumbnail::Failed,
+        })
+        .collect();
+    browser.thumbnails = thumbnails;
+}
@@ -0,0 +1,95 @@
+//! Shares asset handles between all elements that reference the same asset.
+//!
+//! Loading the same path twice through the [`AssetServer`] already returns the same image, but
+//! materials built on top of it are separate assets. Without sharing, every element gets its own
+//! material, which defeats batching and multiplies GPU uploads.
+
+use crate::packs::load_texture;
+use bevy::asset::AssetPath;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use std::any::TypeId;
+use std::sync::Arc;
+
+/// Maps asset paths to the image and material handles created for them.
+///
+/// The cache keeps the handles alive for as long as any element uses them; handles that only the
+/// cache still holds are released at the end of the frame.
+#[derive(Resource, Default)]
+pub struct HandleCache {
+    /// The image loaded for each asset path.
+    images: HashMap<AssetPath<'static>, Handle<Image>>,
+    /// The material created for each asset path, per material type.
+    materials: HashMap<(TypeId, AssetPath<'static>), UntypedHandle>,
+}
+
+impl HandleCache {
+    /// Returns the image for `path`, loading it the first time it's requested.
+    pub fn image<'a>(
+        &mut self,
+        asset_server: &AssetServer,
+        path: impl Into<AssetPath<'a>>,
+    ) -> Handle<Image> {
+        let path = path.into().into_owned();
+        if let Some(handle) = self.images.get(&path) {
+            return handle.clone();
+        }
+
+        let handle = load_texture(asset_server, path.clone());
+        self.images.insert(path, handle.clone());
+        handle
+    }
+
+    /// Returns the material of type `M` for `path`, creating it with `create` from the image
+    /// the first time it's requested.
+    pub fn material<'a, M: Asset>(
+        &mut self,
+        asset_server: &AssetServer,
+        materials: &mut Assets<M>,
+        path: impl Into<AssetPath<'a>>,
+        create: impl FnOnce(Handle<Image>) -> M,
+    ) -> Handle<M> {
+        let path = path.into().into_owned();
+        let key = (TypeId::of::<M>(), path);
+        if let Some(handle) = self.materials.get(&key) {
+            return handle.clone().typed();
+        }
+
+        let image = self.image(asset_server, key.1.clone());
+        let handle = materials.add(create(image));
+        self.materials.insert(key, handle.clone().untyped());
+        handle
+    }
+
+    /// The number of images and materials in the cache.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.images.len() + self.materials.len()
+    }
+
+    /// Returns whether the cache holds no handles.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty() && self.materials.is_empty()
+    }
+
+    /// Drops the handles no element references anymore.
+    ///
+    /// Materials are released first so the images they reference can be released in the same
+    /// pass.
+    pub fn release_unused(&mut self) {
+        self.materials.retain(|_, handle| match handle {
+            UntypedHandle::Strong(handle) => Arc::strong_count(handle) > 1,
+            UntypedHandle::Uuid { .. } => true,
+        });
+        self.images.retain(|_, handle| match handle {
+            Handle::Strong(handle) => Arc::strong_count(handle) > 1,
+            Handle::Uuid(..) => true,
+        });
+    }
+}
+
+/// Releases the handles in the [`HandleCache`] that are no longer used.
+pub(crate) fn release_unused_handles(mut cache: ResMut<HandleCache>) {
+    cache.release_unused();
+}
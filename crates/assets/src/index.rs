@@ -0,0 +1,830 @@
+//! Full-text search over the assets of an [`AssetPack`], backed by Tantivy.
+//!
+//! Without the `search` feature, the [`AssetPackIndex`] in the `browse` module takes its place.
+
+#[cfg(feature = "search")]
+use crate::query::{category_counts, rank_completions, typo_tolerance};
+#[cfg(feature = "search")]
+use crate::{AssetHit, AssetPack, AssetQuery, AssetResults, PackChanges, PackFile, UserTags};
+#[cfg(feature = "search")]
+use bevy::platform::collections::HashMap;
+#[cfg(feature = "search")]
+use dungeonrs_utils::{CancellationToken, ContentHash, Retry, info_span};
+use dungeonrs_utils::{Cancelled, is_transient};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "search")]
+use std::fs::create_dir_all;
+use std::io;
+use std::path::Path;
+#[cfg(feature = "search")]
+use std::path::PathBuf;
+use std::thread::available_parallelism;
+#[cfg(feature = "search")]
+use tantivy::collector::{Count, DocSetCollector, FacetCollector, MultiCollector, TopDocs};
+#[cfg(feature = "search")]
+use tantivy::indexer::{LogMergePolicy, NoMergePolicy};
+#[cfg(feature = "search")]
+use tantivy::query::{
+    AllQuery, BooleanQuery, ConstScoreQuery, EmptyQuery, FuzzyTermQuery, Occur, Query, TermQuery,
+    TermSetQuery,
+};
+#[cfg(feature = "search")]
+use tantivy::schema::{
+    Facet, FacetOptions, Field, IndexRecordOption, STORED, STRING, Schema, TEXT, Value,
+};
+#[cfg(feature = "search")]
+use tantivy::tokenizer::{Token, TokenStream};
+#[cfg(feature = "search")]
+use tantivy::{
+    Index, IndexReader, IndexWriter, TantivyDocument, TantivyError, Term, directory::MmapDirectory,
+    doc,
+};
+use thiserror::Error;
+#[cfg(feature = "search")]
+use xxhash_rust::xxh3::xxh3_128;
+
+/// The minimum amount of memory Tantivy requires per writer thread.
+const MEMORY_PER_THREAD_MIN: usize = 15_000_000;
+
+/// The file extensions of the assets that are indexed.
+const ASSET_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+
+/// Errors that can occur while building or searching an index.
+#[derive(Error, Debug)]
+pub enum IndexError {
+    /// The index directory couldn't be created.
+    #[error("failed to create the index directory: {0}")]
+    Io(#[from] io::Error),
+    /// The pack's directory couldn't be traversed.
+    #[error("failed to read the asset pack: {0}")]
+    Walk(#[from] walkdir::Error),
+    /// The pack's archive couldn't be read.
+    #[error("failed to read the asset pack archive: {0}")]
+    Archive(#[source] io::Error),
+    /// The pack's [`PackManifest`](crate::PackManifest) couldn't be read.
+    #[error("failed to read the pack manifest: {0}")]
+    Manifest(#[from] dungeonrs_serialization::Error),
+    /// Tantivy failed to read or write the index.
+    #[cfg(feature = "search")]
+    #[error("index error: {0}")]
+    Tantivy(#[from] TantivyError),
+    /// Indexing was cancelled, such as when the pack was removed from the library.
+    #[error(transparent)]
+    Cancelled(#[from] Cancelled),
+}
+
+impl IndexError {
+    /// Returns whether the error is likely transient, such as the index being locked by another
+    /// writer or a network drive timing out, in which case writing the index again may succeed.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Io(error) | Self::Archive(error) => is_transient(error),
+            #[cfg(feature = "search")]
+            Self::Tantivy(TantivyError::IoError(error)) => is_transient(error),
+            #[cfg(feature = "search")]
+            Self::Tantivy(TantivyError::LockFailure(..)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Controls the resources used when (re)building an index.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexSettings {
+    /// The memory shared by all writer threads, in bytes.
+    ///
+    /// Each thread needs at least 15 MB, so the number of threads is lowered when the budget is
+    /// too small to accommodate them all.
+    pub writer_memory: usize,
+    /// The number of threads indexing documents, `None` picks one per core (up to 8).
+    pub writer_threads: Option<usize>,
+    /// Whether segments are merged in the background while indexing.
+    pub background_merges: bool,
+    /// Whether all segments are merged into one and obsolete files are removed after indexing,
+    /// trading indexing time for faster searches and less disk usage.
+    pub merge_after_indexing: bool,
+}
+
+impl Default for IndexSettings {
+    fn default() -> Self {
+        Self {
+            writer_memory: 100_000_000,
+            writer_threads: None,
+            background_merges: true,
+            merge_after_indexing: true,
+        }
+    }
+}
+
+impl IndexSettings {
+    /// The number of writer threads that fit in the memory budget.
+    #[must_use]
+    pub fn threads(&self) -> usize {
+        let requested = self
+            .writer_threads
+            .unwrap_or_else(|| available_parallelism().map_or(1, |cores| cores.get().min(8)));
+
+        requested
+            .min(self.writer_memory / MEMORY_PER_THREAD_MIN)
+            .max(1)
+    }
+
+    /// The memory budget, raised to the minimum required by the writer threads.
+    #[must_use]
+    pub fn memory(&self) -> usize {
+        self.writer_memory
+            .max(self.threads() * MEMORY_PER_THREAD_MIN)
+    }
+}
+
+/// The fields of the index schema.
+#[cfg(feature = "search")]
+#[derive(Debug, Copy, Clone)]
+struct Fields {
+    /// The path of the asset, relative to the pack's root.
+    path: Field,
+    /// The file name of the asset without extension, tokenized for searching.
+    name: Field,
+    /// The file extension of the asset.
+    extension: Field,
+    /// Identifies the version of the asset's file that was indexed, see [`fingerprint`].
+    fingerprint: Field,
+    /// The directory of the asset relative to the pack's root, as a facet.
+    category: Field,
+    /// The names of the asset's directories in lowercase, one term each.
+    tags: Field,
+}
+
+#[cfg(feature = "search")]
+impl Fields {
+    /// Builds the index schema and returns it along with its fields.
+    fn schema() -> (Schema, Self) {
+        let mut builder = Schema::builder();
+        let fields = Self {
+            path: builder.add_text_field("path", STRING | STORED),
+            name: builder.add_text_field("name", TEXT | STORED),
+            extension: builder.add_text_field("extension", STRING),
+            fingerprint: builder.add_text_field("fingerprint", STRING | STORED),
+            category: builder.add_facet_field("category", FacetOptions::default()),
+            tags: builder.add_text_field("tags", STRING),
+        };
+
+        (builder.build(), fields)
+    }
+}
+
+/// The search index of a single [`AssetPack`].
+///
+/// Clones share the same index, so it can be updated in the background while it's searched.
+#[cfg(feature = "search")]
+#[derive(Clone)]
+pub struct AssetPackIndex {
+    /// The Tantivy index.
+    index: Index,
+    /// Reads the latest committed state of the index.
+    reader: IndexReader,
+    /// The fields of the index schema.
+    fields: Fields,
+}
+
+#[cfg(feature = "search")]
+impl AssetPackIndex {
+    /// Opens the index of `pack`, creating an empty one if it doesn't exist yet.
+    ///
+    /// # Errors
+    /// Returns an error if the index directory can't be created or the index can't be opened.
+    pub fn open(pack: &AssetPack) -> Result<Self, IndexError> {
+        Self::open_in(&pack.index_path())
+    }
+
+    /// Opens the index in `path`, creating an empty one if it doesn't exist yet.
+    ///
+    /// # Errors
+    /// Returns an error if the directory can't be created or the index can't be opened.
+    pub fn open_in(path: &Path) -> Result<Self, IndexError> {
+        create_dir_all(path)?;
+        let (schema, fields) = Fields::schema();
+        let directory = MmapDirectory::open(path).map_err(TantivyError::from)?;
+        let index = match Index::open_or_create(directory.clone(), schema.clone()) {
+            // Indexes written with an older schema are started over, the next rebuild fills them.
+            Err(TantivyError::SchemaError(_)) => {
+                Index::create(directory, schema, tantivy::IndexSettings::default())?
+            }
+            index => index?,
+        };
+        let reader = index.reader()?;
+
+        Ok(Self {
+            index,
+            reader,
+            fields,
+        })
+    }
+
+    /// Brings the index up to date with the assets currently in `pack`.
+    ///
+    /// Only the assets whose file was added or changed since they were indexed are indexed
+    /// again, as told by their [`fingerprint`], and the assets whose file was removed are
+    /// dropped. The files themselves aren't read, so checking an unchanged pack is cheap.
+    ///
+    /// `progress` is called with the number of files checked so far and the number of files in
+    /// the pack after each file. It's called for every file, so callers reporting it elsewhere
+    /// should throttle it, for example with a [`ProgressReporter`](dungeonrs_utils::ProgressReporter).
+    /// Cancelling `token` stops indexing before the next file, leaving the index as it was.
+    ///
+    /// Returns the number of indexed assets. Transient failures, such as the index being locked by
+    /// another writer, are retried.
+    ///
+    /// # Errors
+    /// Returns an error if the pack can't be read or the index can't be written.
+    pub fn rebuild(
+        &self,
+        pack: &AssetPack,
+        settings: &IndexSettings,
+        token: &CancellationToken,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<usize, IndexError> {
+        // A writer whose commit failed can't be used anymore, each attempt starts over.
+        Retry::default().run_if(
+            |_| self.try_rebuild(pack, settings, token, &mut progress),
+            IndexError::is_transient,
+        )
+    }
+
+    /// Brings the index up to date with the assets currently in `pack`, once.
+    ///
+    /// # Errors
+    /// Returns an error if the pack can't be read or the index can't be written.
+    fn try_rebuild(
+        &self,
+        pack: &AssetPack,
+        settings: &IndexSettings,
+        token: &CancellationToken,
+        progress: &mut impl FnMut(usize, usize),
+    ) -> Result<usize, IndexError> {
+        let span = info_span!("index_pack", pack = %pack.id, files = _, indexed = _);
+        let _guard = span.enter();
+
+        let mut writer: IndexWriter = self
+            .index
+            .writer_with_num_threads(settings.threads(), settings.memory())?;
+        if settings.background_merges {
+            writer.set_merge_policy(Box::new(LogMergePolicy::default()));
+        } else {
+            writer.set_merge_policy(Box::new(NoMergePolicy));
+        }
+
+        let mut indexed = self.fingerprints()?;
+        let mut count = 0;
+        let mut changed = false;
+        let files = pack.files()?;
+        let total = files.len();
+        span.record("files", total);
+        for (checked, file) in files.into_iter().enumerate() {
+            // Dropping the writer without committing discards the documents added so far.
+            token.check()?;
+            progress(checked + 1, total);
+            let Some(document) = self.document(pack, &file) else {
+                continue;
+            };
+            count += 1;
+
+            let path = file.path.to_string_lossy().into_owned();
+            let fingerprint = document
+                .get_first(self.fields.fingerprint)
+                .and_then(|value| value.as_str());
+            if indexed.remove(&path).as_deref() == fingerprint {
+                continue;
+            }
+
+            writer.delete_term(Term::from_field_text(self.fields.path, &path));
+            writer.add_document(document)?;
+            changed = true;
+        }
+        // Whatever wasn't found while walking the pack was removed from it.
+        for path in indexed.keys() {
+            writer.delete_term(Term::from_field_text(self.fields.path, path));
+            changed = true;
+        }
+        span.record("indexed", count);
+        if !changed {
+            return Ok(count);
+        }
+        writer.commit()?;
+
+        if settings.merge_after_indexing {
+            let segments = self.index.searchable_segment_ids()?;
+            if segments.len() > 1 {
+                writer.merge(&segments).wait()?;
+            }
+            writer.garbage_collect_files().wait()?;
+        }
+        writer.wait_merging_threads()?;
+        self.reader.reload()?;
+
+        Ok(count)
+    }
+
+    /// Applies `changes` to the files of `pack` to the index, without indexing the whole pack
+    /// again.
+    ///
+    /// Returns the number of assets in the index afterwards. Transient failures, such as the index
+    /// being locked while it's rebuilt, are retried.
+    ///
+    /// # Errors
+    /// Returns an error if the index can't be written.
+    pub fn update(&self, pack: &AssetPack, changes: &PackChanges) -> Result<u64, IndexError> {
+        Retry::default().run_if(|_| self.try_update(pack, changes), IndexError::is_transient)
+    }
+
+    /// Applies `changes` to the files of `pack` to the index, once.
+    ///
+    /// # Errors
+    /// Returns an error if the index can't be written.
+    fn try_update(&self, pack: &AssetPack, changes: &PackChanges) -> Result<u64, IndexError> {
+        let span = info_span!(
+            "update_pack_index",
+            pack = %pack.id,
+            added = changes.added.len(),
+            modified = changes.modified.len(),
+            removed = changes.removed.len(),
+        );
+        let _guard = span.enter();
+
+        // Changes only touch a handful of files, a single writer thread is plenty.
+        let mut writer: IndexWriter = self
+            .index
+            .writer_with_num_threads(1, MEMORY_PER_THREAD_MIN)?;
+
+        // Removed directories take the assets they contained with them.
+        let directories: Vec<_> = changes
+            .removed
+            .iter()
+            .filter(|path| asset_extension(path).is_none())
+            .collect();
+        let mut removed = changes.removed.clone();
+        if !directories.is_empty() {
+            removed.extend(
+                self.fingerprints()?
+                    .into_keys()
+                    .map(PathBuf::from)
+                    .filter(|path| {
+                        directories
+                            .iter()
+                            .any(|directory| path.starts_with(directory))
+                    }),
+            );
+        }
+
+        // Modified assets are indexed again, and added ones may replace an asset of the same path.
+        for path in removed
+            .iter()
+            .chain(&changes.added)
+            .chain(&changes.modified)
+        {
+            writer.delete_term(Term::from_field_text(
+                self.fields.path,
+                &path.to_string_lossy(),
+            ));
+        }
+        for path in changes.added.iter().chain(&changes.modified) {
+            if let Ok(metadata) = pack.root.join(path).metadata()
+                && metadata.is_file()
+                && let Some(document) = self.document(pack, &PackFile::new(path, &metadata))
+            {
+                writer.add_document(document)?;
+            }
+        }
+        writer.commit()?;
+        writer.wait_merging_threads()?;
+        self.reader.reload()?;
+
+        Ok(self.len())
+    }
+
+    /// The document indexing `file` of `pack`.
+    ///
+    /// Returns `None` if `file` isn't an asset.
+    fn document(&self, pack: &AssetPack, file: &PackFile) -> Option<TantivyDocument> {
+        let path = file.path.as_path();
+        let extension = asset_extension(path)?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().replace(['_', '-'], " "))
+            .unwrap_or_default();
+
+        let category = pack.manifest.category(path);
+        let tags = pack.manifest.tags(path);
+        let mut document = doc!(
+            self.fields.path => path.to_string_lossy().into_owned(),
+            self.fields.name => name,
+            self.fields.extension => extension,
+            self.fields.fingerprint => fingerprint(file, &category, &tags),
+        );
+        if !category.is_empty() {
+            document.add_facet(self.fields.category, Facet::from_path(&category));
+        }
+        for tag in tags {
+            document.add_text(self.fields.tags, tag);
+        }
+
+        Some(document)
+    }
+
+    /// The [`fingerprint`] of every asset in the index, by path relative to the pack's root.
+    ///
+    /// # Errors
+    /// Returns an error if the index can't be read.
+    fn fingerprints(&self) -> Result<HashMap<String, String>, IndexError> {
+        let searcher = self.reader.searcher();
+
+        searcher
+            .search(&AllQuery, &DocSetCollector)?
+            .into_iter()
+            .map(|address| {
+                let document: TantivyDocument = searcher.doc(address)?;
+                let field = |field| {
+                    document
+                        .get_first(field)
+                        .and_then(|value| value.as_str())
+                        .unwrap_or_default()
+                        .to_owned()
+                };
+
+                Ok((field(self.fields.path), field(self.fields.fingerprint)))
+            })
+            .collect()
+    }
+
+    /// Returns the paths (relative to the pack's root) of the assets best matching `query`.
+    ///
+    /// Every word of `query` must match a word of the asset's name, either entirely, as its
+    /// beginning or with a few typos, in decreasing order of relevance.
+    ///
+    /// # Errors
+    /// Returns an error if the index can't be read.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<PathBuf>, IndexError> {
+        let searcher = self.reader.searcher();
+        let query = self
+            .text_query(query)?
+            .unwrap_or_else(|| Box::new(EmptyQuery));
+
+        searcher
+            .search(&query, &TopDocs::with_limit(limit))?
+            .into_iter()
+            .map(|(_, address)| {
+                let document: TantivyDocument = searcher.doc(address)?;
+                let path = document
+                    .get_first(self.fields.path)
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default();
+
+                Ok(PathBuf::from(path))
+            })
+            .collect()
+    }
+
+    /// Runs `query` against the index, whose pack is identified by `pack`.
+    ///
+    /// The name is searched like with [`AssetPackIndex::search`], while the categories and tags
+    /// only filter the assets without affecting how well they match. Assets have the tags of the
+    /// index along with their `user_tags`.
+    ///
+    /// # Errors
+    /// Returns an error if the index can't be read.
+    pub fn query(
+        &self,
+        pack: &str,
+        query: &AssetQuery,
+        user_tags: &UserTags,
+    ) -> Result<AssetResults, IndexError> {
+        if !query.includes_pack(pack) {
+            return Ok(AssetResults::default());
+        }
+
+        let searcher = self.reader.searcher();
+        let categories = query.category_paths();
+        let filtered = self.boolean_query(pack, query, &categories, user_tags)?;
+        let top = TopDocs::with_limit(query.limit.max(1)).and_offset(query.offset);
+        let (top, total) = searcher.search(&filtered, &(top, Count))?;
+
+        // The categories are counted without filtering by category, each counted facet needs its
+        // own collector as they're nested.
+        let mut counted = vec![Facet::root()];
+        counted.extend(categories.iter().map(Facet::from_path));
+        let mut collectors = MultiCollector::new();
+        let handles: Vec<_> = counted
+            .iter()
+            .map(|facet| {
+                let mut collector = FacetCollector::for_field("category");
+                collector.add_facet(facet.clone());
+                collectors.add_collector(collector)
+            })
+            .collect();
+        let unfiltered = self.boolean_query(pack, query, &[], user_tags)?;
+        let mut fruits = searcher.search(&unfiltered, &collectors)?;
+        let mut counts = HashMap::default();
+        for (facet, handle) in counted.iter().zip(handles) {
+            for (child, count) in handle.extract(&mut fruits).get(facet.clone()) {
+                counts.insert(child.to_path().join("/"), count);
+            }
+        }
+
+        let hits = top
+            .into_iter()
+            .take(query.limit)
+            .map(|(score, address)| {
+                let document: TantivyDocument = searcher.doc(address)?;
+                let path = document
+                    .get_first(self.fields.path)
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default();
+
+                Ok(AssetHit {
+                    pack: pack.to_owned(),
+                    path: PathBuf::from(path),
+                    score,
+                })
+            })
+            .collect::<Result<_, IndexError>>()?;
+
+        Ok(AssetResults {
+            hits,
+            total,
+            categories: category_counts(counts),
+        })
+    }
+
+    /// The Tantivy query matching the assets of `query` in any of `categories`, with the tags
+    /// of the index or the `user_tags` of the assets of `pack`.
+    ///
+    /// # Errors
+    /// Returns an error if the tokenizer of the name field isn't registered.
+    fn boolean_query(
+        &self,
+        pack: &str,
+        query: &AssetQuery,
+        categories: &[Vec<&str>],
+        user_tags: &UserTags,
+    ) -> Result<Box<dyn Query>, IndexError> {
+        // Filters don't score, so only the name decides how well an asset matches.
+        let filter = |query: Box<dyn Query>| -> Box<dyn Query> {
+            Box::new(ConstScoreQuery::new(query, 0.0))
+        };
+        let term = |term: Term| -> Box<dyn Query> {
+            Box::new(TermQuery::new(term, IndexRecordOption::Basic))
+        };
+
+        let mut clauses = Vec::new();
+        if let Some(text) = self.text_query(&query.search_text())? {
+            clauses.push((Occur::Must, text));
+        }
+        for tag in query.normalized_tags() {
+            // The user's tags aren't indexed, the assets they were given to are matched by path.
+            let tagged = user_tags
+                .paths(pack, &tag)
+                .map(|path| Term::from_field_text(self.fields.path, &path.to_string_lossy()));
+            let any = vec![
+                (
+                    Occur::Should,
+                    term(Term::from_field_text(self.fields.tags, &tag)),
+                ),
+                (
+                    Occur::Should,
+                    Box::new(TermSetQuery::new(tagged)) as Box<dyn Query>,
+                ),
+            ];
+            clauses.push((Occur::Must, filter(Box::new(BooleanQuery::new(any)))));
+        }
+        if !categories.is_empty() {
+            let any = categories
+                .iter()
+                .map(|category| {
+                    let facet = Facet::from_path(category);
+                    (
+                        Occur::Should,
+                        term(Term::from_facet(self.fields.category, &facet)),
+                    )
+                })
+                .collect();
+            clauses.push((Occur::Must, filter(Box::new(BooleanQuery::new(any)))));
+        }
+
+        if clauses.is_empty() {
+            return Ok(Box::new(AllQuery));
+        }
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    /// The query matching the assets whose name contains every word of `text`, or `None` if
+    /// `text` holds no words.
+    ///
+    /// Each word matches the name's words it is, begins or is a few typos away from. The ones it
+    /// is score the highest, followed by the ones it begins.
+    ///
+    /// # Errors
+    /// Returns an error if the tokenizer of the name field isn't registered.
+    fn text_query(&self, text: &str) -> Result<Option<Box<dyn Query>>, IndexError> {
+        let words = self.words(text)?;
+        if words.is_empty() {
+            return Ok(None);
+        }
+
+        let clauses = words
+            .into_iter()
+            .map(|word| {
+                let typos = typo_tolerance(word.text.chars().count());
+                let term = Term::from_field_text(self.fields.name, &word.text);
+                let mut any: Vec<(Occur, Box<dyn Query>)> = vec![
+                    (
+                        Occur::Should,
+                        Box::new(TermQuery::new(term.clone(), IndexRecordOption::WithFreqs)),
+                    ),
+                    (
+                        Occur::Should,
+                        Box::new(FuzzyTermQuery::new_prefix(term.clone(), 0, true)),
+                    ),
+                ];
+                if typos > 0 {
+                    any.push((
+                        Occur::Should,
+                        Box::new(FuzzyTermQuery::new_prefix(term, typos, true)),
+                    ));
+                }
+
+                (
+                    Occur::Must,
+                    Box::new(BooleanQuery::new(any)) as Box<dyn Query>,
+                )
+            })
+            .collect();
+
+        Ok(Some(Box::new(BooleanQuery::new(clauses))))
+    }
+
+    /// Splits `text` into words the way asset names are, in lowercase.
+    ///
+    /// # Errors
+    /// Returns an error if the tokenizer of the name field isn't registered.
+    fn words(&self, text: &str) -> Result<Vec<Token>, IndexError> {
+        let mut tokenizer = self.index.tokenizer_for_field(self.fields.name)?;
+        let mut words = Vec::new();
+        tokenizer
+            .token_stream(text)
+            .process(&mut |token| words.push(token.clone()));
+
+        Ok(words)
+    }
+
+    /// Completes the last word of `prefix` with the words of the indexed names, returning up to
+    /// `limit` completed texts for autocompletion.
+    ///
+    /// The names' words `prefix` ends with the beginning of come first, the ones it only
+    /// reaches with a typo after, most common first. Nothing is suggested once `prefix` ends with
+    /// a separator.
+    ///
+    /// # Errors
+    /// Returns an error if the index can't be read.
+    pub fn suggest(&self, prefix: &str, limit: usize) -> Result<Vec<String>, IndexError> {
+        let words = self.words(prefix)?;
+        let Some(word) = words.last().filter(|word| word.offset_to == prefix.len()) else {
+            return Ok(Vec::new());
+        };
+
+        // Asset names use few distinct words, so all of them are compared.
+        let mut candidates = HashMap::default();
+        for segment in self.reader.searcher().segment_readers() {
+            let index = segment.inverted_index(self.fields.name)?;
+            let mut terms = index.terms().stream()?;
+            while terms.advance() {
+                if let Ok(term) = std::str::from_utf8(terms.key()) {
+                    *candidates.entry(term.to_owned()).or_default() +=
+                        u64::from(terms.value().doc_freq);
+                }
+            }
+        }
+
+        let start = &prefix[..word.offset_from];
+        Ok(rank_completions(&word.text, candidates, limit)
+            .into_iter()
+            .map(|completion| format!("{start}{completion}"))
+            .collect())
+    }
+
+    /// The number of assets in the index.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.reader.searcher().num_docs()
+    }
+
+    /// Returns whether the index holds no assets.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Identifies the version of `file` from its path, size and modification time, along
+/// with the `category` and `tags` the pack's manifest gives it.
+///
+/// Hashing the contents of every asset of a huge pack takes longer than indexing them, while any
+/// edit to a file changes its modification time. Editing the manifest changes the category and
+/// tags, so the affected assets are indexed again.
+#[cfg(feature = "search")]
+fn fingerprint(file: &PackFile, category: &[String], tags: &[String]) -> String {
+    let mut bytes = file.path.as_os_str().as_encoded_bytes().to_vec();
+    bytes.extend(file.size.to_le_bytes());
+    bytes.extend(file.modified.to_le_bytes());
+    for name in category.iter().chain([&String::new()]).chain(tags) {
+        bytes.extend(name.as_bytes());
+        bytes.push(0);
+    }
+
+    ContentHash::Xxh3(xxh3_128(&bytes)).to_string()
+}
+
+/// Returns the lowercase extension of `path` if it's an indexed asset type.
+pub(crate) fn asset_extension(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+
+    ASSET_EXTENSIONS
+        .contains(&extension.as_str())
+        .then_some(extension)
+}
+
+#[cfg(all(test, feature = "search"))]
+mod tests {
+    //! Searches and completes the names of a small pack through its Tantivy index.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+    use dungeonrs_utils::TempWorkspace;
+    use std::fs;
+
+    /// Indexes a pack of a few props and walls in `workspace`.
+    fn index(workspace: &TempWorkspace) -> AssetPackIndex {
+        let root = workspace.path().join("pack");
+        for file in [
+            "props/torch.png",
+            "props/torchiere.png",
+            "props/treasure chest.png",
+            "props/chest.png",
+            "walls/stone wall.png",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, []).unwrap();
+        }
+
+        let index = AssetPackIndex::open_in(&workspace.path().join("index")).unwrap();
+        let pack = AssetPack::new("pack", "Pack", root);
+        index
+            .rebuild(
+                &pack,
+                &IndexSettings::default(),
+                &CancellationToken::default(),
+                |_, _| {},
+            )
+            .unwrap();
+
+        index
+    }
+
+    /// The last word is completed by prefix first, then with a typo, more common words first,
+    /// keeping the words before it. Nothing is completed after a separator.
+    #[test]
+    fn suggests_completions() {
+        let workspace = TempWorkspace::open().unwrap();
+        let index = index(&workspace);
+
+        assert_eq!(index.suggest("Tor", 5).unwrap(), ["torch", "torchiere"]);
+        assert_eq!(index.suggest("big chst", 5).unwrap(), ["big chest"]);
+        assert_eq!(index.suggest("stone", 1).unwrap(), ["stone"]);
+        assert!(index.suggest("stone ", 5).unwrap().is_empty());
+        assert!(index.suggest("", 5).unwrap().is_empty());
+    }
+
+    /// Quotes and dashes are separators rather than syntax, so the words between them are
+    /// searched.
+    #[test]
+    fn searches_quoted_and_negated_words() {
+        let workspace = TempWorkspace::open().unwrap();
+        let index = index(&workspace);
+
+        assert_eq!(
+            index.search("\"stone wall\"", 5).unwrap(),
+            [PathBuf::from("walls/stone wall.png")]
+        );
+        let mut found = index.search("-torch", 5).unwrap();
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                PathBuf::from("props/torch.png"),
+                PathBuf::from("props/torchiere.png")
+            ]
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//! Opens the indexes of the registered asset packs in the background.
+//!
+//! Opening a Tantivy index touches the disk and can take a while for large packs, doing so for
+//! every pack while the app starts delays the editor coming up. Instead each pack's index is
+//! opened in its own background task, and a [`PackIndexReady`] message announces when it can
+//! be searched. The pack's [`PackManifest`] is read first, as it gives the assets their category
+//! and tags.
+//!
+//! Once opened, the index is brought up to date with the pack's files, which only indexes the
+//! assets that changed since the pack was last opened. Indexing a large pack for the first time
+//! checks tens of thousands of files, so its progress is reported through [`PackIndexProgress`]
+//! at a limited rate.
+
+use crate::{AssetLibrary, AssetPack, AssetPackIndex, IndexError, PackManifest};
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use dungeonrs_macros::bevy_system;
+use dungeonrs_utils::{AsyncCommandsExt, CancellationToken, ProgressReporter};
+
+/// The indexes of the asset packs that finished opening.
+#[derive(Resource, Default)]
+pub struct PackIndexes {
+    /// The opened indexes, by pack identifier.
+    ready: HashMap<String, AssetPackIndex>,
+    /// The packs whose index is currently being opened, along with the token cancelling it.
+    pending: HashMap<String, CancellationToken>,
+}
+
+impl PackIndexes {
+    /// Returns the index of the pack identified by `id`, if it finished opening.
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<&AssetPackIndex> {
+        self.ready.get(id)
+    }
+
+    /// Returns whether the index of the pack identified by `id` finished opening.
+    #[must_use]
+    pub fn is_ready(&self, id: &str) -> bool {
+        self.ready.contains_key(id)
+    }
+
+    /// Returns whether any index is still being opened.
+    #[must_use]
+    pub fn is_loading(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Iterates over the opened indexes along with their pack identifier.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &AssetPackIndex)> {
+        self.ready.iter().map(|(id, index)| (id.as_str(), index))
+    }
+
+    /// Adds the index of the pack identified by `id`, opened without the
+    /// [`AssetsPlugin`](crate::AssetsPlugin), for example by a tool importing maps outside the
+    /// editor.
+    ///
+    /// Replaces the pack's previous index, if any.
+    pub fn insert(&mut self, id: impl Into<String>, index: AssetPackIndex) {
+        self.ready.insert(id.into(), index);
+    }
+}
+
+/// Written once the index of a pack was opened and can be searched.
+#[derive(Message, Debug, Clone)]
+pub struct PackIndexReady {
+    /// The identifier of the pack.
+    pub pack: String,
+}
+
+/// Written while a pack is being indexed, at most [`ProgressReporter::DEFAULT_RATE`] times per
+/// second.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct PackIndexProgress {
+    /// The identifier of the pack.
+    pub pack: String,
+    /// The number of the pack's files checked so far.
+    pub checked: usize,
+    /// The number of files in the pack.
+    pub total: usize,
+}
+
+/// Written when the index of a pack failed to open, or to update after its files changed. This
+/// includes the pack's manifest failing to be read.
+#[derive(Message, Debug)]
+pub struct PackIndexFailed {
+    /// The identifier of the pack.
+    pub pack: String,
+    /// Why the index couldn't be opened or updated.
+    pub error: IndexError,
+}
+
+/// Starts opening the index of every registered pack that isn't open or opening yet, and drops
+/// the indexes of packs that were removed from the library, cancelling those still opening.
+#[bevy_system]
+pub(crate) fn open_pack_indexes(
+    mut commands: Commands,
+    library: Res<AssetLibrary>,
+    mut indexes: ResMut<PackIndexes>,
+) {
+    indexes.ready.retain(|id, _| library.pack(id).is_some());
+    indexes.pending.retain(|id, token| {
+        let registered = library.pack(id).is_some();
+        if !registered {
+            token.cancel();
+        }
+
+        registered
+    });
+
+    for pack in &library.packs {
+        if indexes.ready.contains_key(&pack.id) || indexes.pending.contains_key(&pack.id) {
+            continue;
+        }
+
+        let id = pack.id.clone();
+        let mut pack = pack.clone();
+        let settings = library.index_settings(&pack);
+        let token = commands.spawn_async(move |context| async move {
+            let mut reporter = ProgressReporter::new(&context);
+            let result = read_manifest(&mut pack)
+                .and_then(|()| AssetPackIndex::open(&pack))
+                .and_then(|index| {
+                    index.rebuild(&pack, &settings, context.token(), |checked, total| {
+                        reporter.report(PackIndexProgress {
+                            pack: pack.id.clone(),
+                            checked,
+                            total,
+                        });
+                    })?;
+
+                    Ok(index)
+                });
+            // Delivers the last progress update before the index is announced as ready.
+            drop(reporter);
+            let AssetPack { id, manifest, .. } = pack;
+            context.queue(move |world: &mut World| {
+                // The pack may have been removed while its index was opening.
+                let registered = world.resource::<AssetLibrary>().pack(&id).is_some();
+                let mut indexes = world.resource_mut::<PackIndexes>();
+                indexes.pending.remove(&id);
+                if !registered {
+                    return;
+                }
+
+                match result {
+                    Ok(index) => {
+                        indexes.ready.insert(id.clone(), index);
+                        set_manifest(world, &id, manifest);
+                        world.write_message(PackIndexReady { pack: id });
+                    }
+                    Err(error) => {
+                        world.write_message(PackIndexFailed { pack: id, error });
+                    }
+                }
+            });
+        });
+        indexes.pending.insert(id, token);
+    }
+}
+
+/// Reads the [`PackManifest`] of `pack` into it, packs without one get the default manifest.
+///
+/// # Errors
+/// Returns an error if the manifest exists but can't be read or isn't valid.
+pub(crate) fn read_manifest(pack: &mut AssetPack) -> Result<(), IndexError> {
+    pack.manifest = PackManifest::read(&pack.root)?.unwrap_or_default();
+
+    Ok(())
+}
+
+/// Gives the pack identified by `id` in the [`AssetLibrary`] its `manifest`, unless it already
+/// has it.
+pub(crate) fn set_manifest(world: &mut World, id: &str, manifest: PackManifest) {
+    let mut library = world.resource_mut::<AssetLibrary>();
+    // Only actual changes flag the library as changed, running the systems reacting to it.
+    if library
+        .pack(id)
+        .is_some_and(|pack| pack.manifest != manifest)
+        && let Some(pack) = library.packs.iter_mut().find(|pack| pack.id == id)
+    {
+        pack.manifest = manifest;
+    }
+}
@@ -0,0 +1,42 @@
+#![doc = include_str!("../README.md")]
+
+mod atlas;
+#[cfg(not(feature = "search"))]
+mod browse;
+mod browser;
+mod handle_cache;
+mod index;
+mod index_loading;
+mod library;
+mod packs;
+mod plugin;
+mod prefabs;
+mod query;
+mod texture_cache;
+mod user_tags;
+mod watcher;
+
+pub use atlas::{AtlasSettings, AtlasSlot, AtlasTexture, TextureAtlases};
+#[cfg(not(feature = "search"))]
+pub use browse::AssetPackIndex;
+pub use browser::{
+    AssetBrowser, AssetSearchFailed, BrowserGrid, ScrollAssetBrowser, SearchAssets, Thumbnail,
+    VisibleCells,
+};
+pub use handle_cache::HandleCache;
+#[cfg(feature = "search")]
+pub use index::AssetPackIndex;
+pub use index::{IndexError, IndexSettings};
+pub use index_loading::{PackIndexFailed, PackIndexProgress, PackIndexReady, PackIndexes};
+pub use library::AssetLibrary;
+pub use packs::{
+    ARCHIVE_EXTENSIONS, AssetPack, DirectoryDefaults, License, LicenseConflict,
+    LicenseConflictKind, LicenseTerms, MAX_EXTRACTED_SIZE, PACK_MANIFEST_FILE, PackFile,
+    PackManifest, extract_asset,
+};
+pub use plugin::AssetsPlugin;
+pub use prefabs::{Prefab, PrefabElement, UserPrefabs};
+pub use query::{AssetHit, AssetQuery, AssetResults, CategoryCount};
+pub use texture_cache::{TextureCache, TextureKind, TextureMemory};
+pub use user_tags::{FAVOURITE_TAG, PackAsset, UserTags};
+pub use watcher::{PackAssetsChanged, PackChanges, PackWatchFailed, PackWatcher};
@@ -0,0 +1,147 @@
+//! Contains the [`AssetLibrary`], the collection of asset packs available to the editor.
+
+use crate::{
+    AssetPack, FAVOURITE_TAG, IndexSettings, License, LicenseConflict, PackAsset, UserPrefabs,
+    UserTags,
+};
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// The asset packs available to the editor, along with the settings shared between them.
+#[derive(Resource, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AssetLibrary {
+    /// The registered packs.
+    #[serde(default)]
+    pub packs: Vec<AssetPack>,
+    /// The index settings used by packs that don't override them.
+    #[serde(default)]
+    pub index: IndexSettings,
+    /// The tags the user gave to the assets, saved to their own file.
+    #[serde(skip)]
+    pub user_tags: UserTags,
+    /// The prefabs the user saved, saved to their own file.
+    #[serde(skip)]
+    pub prefabs: UserPrefabs,
+}
+
+impl AssetLibrary {
+    /// Registers `pack`, replacing any pack with the same identifier.
+    pub fn add_pack(&mut self, pack: AssetPack) {
+        self.remove_pack(&pack.id);
+        self.packs.push(pack);
+    }
+
+    /// Unregisters the pack identified by `id`, returning it if it was registered.
+    pub fn remove_pack(&mut self, id: &str) -> Option<AssetPack> {
+        let index = self.packs.iter().position(|pack| pack.id == id)?;
+
+        Some(self.packs.remove(index))
+    }
+
+    /// Returns the pack identified by `id`.
+    #[must_use]
+    pub fn pack(&self, id: &str) -> Option<&AssetPack> {
+        self.packs.iter().find(|pack| pack.id == id)
+    }
+
+    /// Returns the pack containing the asset at the absolute path `asset`, along with the path of
+    /// the asset relative to the pack's root.
+    ///
+    /// When packs are nested, the innermost pack contains the asset.
+    #[must_use]
+    pub fn pack_of<'a>(&self, asset: &'a Path) -> Option<(&AssetPack, &'a Path)> {
+        self.packs
+            .iter()
+            .filter_map(|pack| Some((pack, asset.strip_prefix(&pack.root).ok()?)))
+            .max_by_key(|(pack, _)| pack.root.components().count())
+    }
+
+    /// The packs the assets at the absolute paths `assets` come from, ordered by identifier,
+    /// so their authors can be credited.
+    pub fn packs_of<'a>(&self, assets: impl IntoIterator<Item = &'a Path>) -> Vec<&AssetPack> {
+        let mut packs: Vec<_> = assets
+            .into_iter()
+            .filter_map(|asset| self.pack_of(asset).map(|(pack, _)| pack))
+            .collect();
+        packs.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+        packs.dedup_by(|a, b| a.id == b.id);
+        packs
+    }
+
+    /// The conflicts between the licenses of the assets at the absolute paths `assets`, such as
+    /// the assets of a map about to be exported.
+    ///
+    /// Assets of the same pack never conflict, and assets outside of any pack or without a
+    /// license are left out.
+    pub fn license_conflicts<'a>(
+        &self,
+        assets: impl IntoIterator<Item = &'a Path>,
+    ) -> Vec<LicenseConflict> {
+        let licenses: BTreeSet<(&str, &License)> = assets
+            .into_iter()
+            .filter_map(|asset| {
+                let (pack, path) = self.pack_of(asset)?;
+                Some((pack.id.as_str(), pack.manifest.license(path)?))
+            })
+            .collect();
+
+        let mut conflicts = Vec::new();
+        for (index, (pack, license)) in licenses.iter().enumerate() {
+            for (other_pack, other_license) in licenses.iter().skip(index + 1) {
+                if pack == other_pack {
+                    continue;
+                }
+                if let Some(kind) = license.conflict(other_license) {
+                    conflicts.push(LicenseConflict {
+                        packs: [(*pack).to_owned(), (*other_pack).to_owned()],
+                        licenses: [(*license).clone(), (*other_license).clone()],
+                        kind,
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Tags `asset` with `tag`, which searches can then filter by.
+    ///
+    /// Returns whether the asset didn't have the tag yet.
+    pub fn tag(&mut self, asset: PackAsset, tag: &str) -> bool {
+        self.user_tags.add(asset, tag)
+    }
+
+    /// Removes `tag` from `asset`.
+    ///
+    /// Returns whether the asset had the tag.
+    pub fn untag(&mut self, asset: &PackAsset, tag: &str) -> bool {
+        self.user_tags.remove(asset, tag)
+    }
+
+    /// Marks `asset` as a favourite, tagging it with [`FAVOURITE_TAG`].
+    ///
+    /// Returns whether the asset wasn't a favourite yet.
+    pub fn favourite(&mut self, asset: PackAsset) -> bool {
+        self.tag(asset, FAVOURITE_TAG)
+    }
+
+    /// Removes `asset` from the favourites.
+    ///
+    /// Returns whether the asset was a favourite.
+    pub fn unfavourite(&mut self, asset: &PackAsset) -> bool {
+        self.untag(asset, FAVOURITE_TAG)
+    }
+
+    /// Returns whether `asset` is a favourite.
+    #[must_use]
+    pub fn is_favourite(&self, asset: &PackAsset) -> bool {
+        self.user_tags.has(asset, FAVOURITE_TAG)
+    }
+
+    /// The index settings that apply to `pack`.
+    #[must_use]
+    pub fn index_settings(&self, pack: &AssetPack) -> IndexSettings {
+        pack.index.clone().unwrap_or_else(|| self.index.clone())
+    }
+}
@@ -0,0 +1,348 @@
+//! Reads the asset packs distributed as a zip or 7z archive, so large packs don't need to be
+//! unpacked to disk.
+//!
+//! The assets of an archived pack have virtual paths through the archive, such as
+//! `Packs/Forest.zip/Trees/oak.png`. Listing them only reads the archive's directory, while an
+//! asset is extracted to the cache directory in the background the first time its texture is
+//! loaded. Extracted assets are removed from the cache at startup once they're a month old,
+//! they're extracted again when they're needed.
+
+use crate::PackFile;
+use bevy::asset::uuid::Uuid;
+use bevy::asset::{AssetPath, AssetServer, Handle, RenderAssetUsages};
+use bevy::image::{CompressedImageFormats, Image, ImageSampler, ImageType};
+use bevy::prelude::Commands;
+use dungeonrs_macros::bevy_system;
+use dungeonrs_utils::{AsyncCommandsExt, ContentHash, Directory, ensure_within, prune_cache};
+use sevenz_rust::{Archive, BlockDecoder};
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use xxhash_rust::xxh3::xxh3_128;
+use zip::ZipArchive;
+
+/// The directory of the [`Directory::Cache`] the assets of archived packs are extracted to.
+const EXTRACTED_ASSETS: &str = "archives";
+
+/// How long assets stay extracted before they're removed from the cache at startup.
+const EXTRACTED_ASSETS_MAX_AGE: Duration = Duration::from_hours(30 * 24);
+
+/// The largest file read from an archive, in bytes.
+///
+/// Archives can claim any size for their files and decompress to much more than they hold, so
+/// larger files are refused instead of filling the memory and the cache.
+pub const MAX_EXTRACTED_SIZE: u64 = 512 * 1024 * 1024;
+
+/// The file extensions of the archives asset packs can be read from.
+pub const ARCHIVE_EXTENSIONS: &[&str] = &["zip", "7z"];
+
+/// Returns whether `path` is named like an archive asset packs can be read from.
+pub(crate) fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ARCHIVE_EXTENSIONS
+                .iter()
+                .any(|archive| extension.eq_ignore_ascii_case(archive))
+        })
+}
+
+/// Returns whether `path` is named like a 7z archive rather than a zip archive.
+fn is_seven_zip(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("7z"))
+}
+
+/// Lists the files of the archive at `path`, without extracting them.
+///
+/// # Errors
+/// Returns an error if the archive can't be read.
+pub(crate) fn list_archive(path: &Path) -> io::Result<Vec<PackFile>> {
+    let mut files = if is_seven_zip(path) {
+        let archive = Archive::open(path).map_err(io::Error::other)?;
+        archive
+            .files
+            .iter()
+            .filter(|entry| entry.has_stream() && !entry.is_directory())
+            .filter_map(|entry| {
+                Some(PackFile {
+                    path: entry_path(entry.name())?,
+                    size: entry.size(),
+                    modified: u128::from(u64::from(entry.last_modified_date())),
+                })
+            })
+            .collect()
+    } else {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let mut files = Vec::with_capacity(archive.len());
+        for index in 0..archive.len() {
+            // The raw entry only holds what the directory of the archive says about it.
+            let entry = archive.by_index_raw(index)?;
+            if entry.is_dir() {
+                continue;
+            }
+            let Some(path) = entry_path(entry.name()) else {
+                continue;
+            };
+            let modified = entry.last_modified().map_or(0, |modified| {
+                (u128::from(modified.datepart()) << 16) | u128::from(modified.timepart())
+            });
+            files.push(PackFile {
+                path,
+                size: entry.size(),
+                modified,
+            });
+        }
+        files
+    };
+    files.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(files)
+}
+
+/// Reads the file at `entry`, relative to the root of the archive at `path`.
+///
+/// # Errors
+/// Returns an error of kind [`NotFound`](io::ErrorKind::NotFound) if the archive has no such
+/// file, of kind [`InvalidData`](io::ErrorKind::InvalidData) if it's larger than
+/// [`MAX_EXTRACTED_SIZE`], or any error reading the archive.
+pub(crate) fn read_archive(path: &Path, entry: &Path) -> io::Result<Vec<u8>> {
+    read_archive_limited(path, entry, MAX_EXTRACTED_SIZE)
+}
+
+/// Reads the file at `entry`, relative to the root of the archive at `path`, refusing files
+/// larger than `max` bytes.
+///
+/// # Errors
+/// See [`read_archive`].
+fn read_archive_limited(path: &Path, entry: &Path, max: u64) -> io::Result<Vec<u8>> {
+    let not_found = || {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("'{}' isn't in '{}'", entry.display(), path.display()),
+        )
+    };
+
+    let mut data = Vec::new();
+    if !is_seven_zip(path) {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let index = (0..archive.len())
+            .find(|index| {
+                archive
+                    .name_for_index(*index)
+                    .and_then(entry_path)
+                    .is_some_and(|name| name == entry)
+            })
+            .ok_or_else(not_found)?;
+        let file = archive.by_index(index)?;
+        return read_limited(file.size(), file, entry, max);
+    }
+
+    let archive = Archive::open(path).map_err(io::Error::other)?;
+    let index = archive
+        .files
+        .iter()
+        .position(|file| entry_path(file.name()).is_some_and(|name| name == entry))
+        .ok_or_else(not_found)?;
+    let Some(block) = archive.stream_map.file_folder_index[index] else {
+        // Empty files aren't stored in any block.
+        return Ok(data);
+    };
+
+    // Blocks are compressed as a whole, so the files before the entry are decompressed as well.
+    let mut source = File::open(path)?;
+    BlockDecoder::new(block, &archive, &[], &mut source)
+        .for_each_entries(&mut |file, reader| {
+            if entry_path(file.name()).is_some_and(|name| name == entry) {
+                data = read_limited(file.size(), reader, entry, max)?;
+                return Ok(false);
+            }
+            io::copy(reader, &mut io::sink())?;
+            Ok(true)
+        })
+        .map_err(io::Error::other)?;
+
+    Ok(data)
+}
+
+/// Reads `reader` to the end, refusing files larger than `max` bytes whatever `declared` size
+/// the archive gives them.
+///
+/// # Errors
+/// Returns an error of kind [`InvalidData`](io::ErrorKind::InvalidData) if the file is too large,
+/// or any error reading it.
+fn read_limited(declared: u64, reader: impl Read, entry: &Path, max: u64) -> io::Result<Vec<u8>> {
+    let too_large = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("'{}' is larger than {max} bytes", entry.display()),
+        )
+    };
+    if declared > max {
+        return Err(too_large());
+    }
+
+    let mut data = Vec::new();
+    reader.take(max + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > max {
+        return Err(too_large());
+    }
+
+    Ok(data)
+}
+
+/// The archive `path` lies within, if any.
+fn archive_of(path: &Path) -> Option<&Path> {
+    path.ancestors()
+        .skip(1)
+        .find(|ancestor| is_archive(ancestor) && ancestor.is_file())
+}
+
+/// Extracts the asset at `path` to the cache directory if it lies within an archive, unless it
+/// was extracted since the archive last changed.
+///
+/// The asset is written next to its final path and moved there once complete, so an interrupted
+/// extraction never leaves a truncated asset behind. This blocks until the asset is extracted,
+/// call it from a background task.
+///
+/// Returns the extracted file, or `path` itself when it doesn't lie within an archive.
+///
+/// # Errors
+/// Returns an error if the asset can't be read from the archive, is larger than
+/// [`MAX_EXTRACTED_SIZE`] or can't be written to the cache.
+pub fn extract_asset(path: &Path) -> io::Result<Cow<'_, Path>> {
+    let Some(archive) = archive_of(path) else {
+        return Ok(Cow::Borrowed(path));
+    };
+    let entry = path.strip_prefix(archive).unwrap_or(path);
+
+    // Each archive gets its own directory, named after its path, and entries never leave it.
+    let directory = ContentHash::Xxh3(xxh3_128(archive.as_os_str().as_encoded_bytes()));
+    let extracted = ensure_within(
+        Directory::Cache
+            .join(EXTRACTED_ASSETS)
+            .join(directory.to_string()),
+        entry,
+    )
+    .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    let changed = fs::metadata(archive)?.modified()?;
+    if fs::metadata(&extracted)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified >= changed)
+    {
+        return Ok(Cow::Owned(extracted));
+    }
+
+    let data = read_archive(archive, entry)?;
+    if let Some(parent) = extracted.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Concurrent extractions of the same asset each write their own file.
+    let mut partial = extracted.clone().into_os_string();
+    partial.push(format!(".{}.partial", Uuid::new_v4().simple()));
+    let partial = PathBuf::from(partial);
+    if let Err(error) = fs::write(&partial, data).and_then(|()| fs::rename(&partial, &extracted)) {
+        let _ = fs::remove_file(&partial);
+        return Err(error);
+    }
+
+    Ok(Cow::Owned(extracted))
+}
+
+/// Removes the extracted assets that are older than [`EXTRACTED_ASSETS_MAX_AGE`] in the
+/// background.
+#[bevy_system]
+pub(crate) fn prune_extracted_assets(mut commands: Commands) {
+    commands.spawn_async(|_| async {
+        // Pruning is best effort, whatever is left behind is retried at the next startup.
+        let _ = prune_cache(EXTRACTED_ASSETS, EXTRACTED_ASSETS_MAX_AGE);
+    });
+}
+
+/// Loads the texture at `path` through `asset_server`.
+///
+/// Assets of archived packs are extracted and decoded on the IO task pool instead, failing to
+/// load like any other texture when they can't be.
+pub(crate) fn load_texture(asset_server: &AssetServer, path: AssetPath<'static>) -> Handle<Image> {
+    if archive_of(path.path()).is_none() {
+        return asset_server.load(path);
+    }
+
+    let path = path.path().to_owned();
+    asset_server.add_async(async move {
+        let extracted = extract_asset(&path)?;
+        let data = fs::read(&extracted)?;
+        let extension = extracted
+            .extension()
+            .map(|extension| extension.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Image::from_buffer(
+            &data,
+            ImageType::Extension(&extension),
+            CompressedImageFormats::NONE,
+            true,
+            ImageSampler::Default,
+            RenderAssetUsages::default(),
+        )
+        .map_err(io::Error::other)
+    })
+}
+
+/// The path of the archive entry `name` relative to the archive's root, or `None` if it would
+/// escape the archive.
+fn entry_path(name: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(name.replace('\\', "/"));
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    //! Reads files of a zip archive written for the test.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+    use dungeonrs_utils::TempWorkspace;
+    use std::io::Write;
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    /// Writes a zip archive holding `files` to `path`.
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, contents) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    /// Files up to the limit are read whole, larger ones are refused.
+    #[test]
+    fn refuses_files_over_the_limit() {
+        let workspace = TempWorkspace::open().unwrap();
+        let archive = workspace.path().join("pack.zip");
+        write_zip(&archive, &[("small.txt", b"1234"), ("large.txt", &[0; 64])]);
+
+        let small = read_archive_limited(&archive, Path::new("small.txt"), 4).unwrap();
+        assert_eq!(small, b"1234");
+        let error = read_archive_limited(&archive, Path::new("large.txt"), 4).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = read_archive_limited(&archive, Path::new("missing.txt"), 4).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    /// Files declaring a smaller size than they decompress to are refused all the same.
+    #[test]
+    fn refuses_files_larger_than_declared() {
+        let entry = Path::new("large.txt");
+
+        assert_eq!(read_limited(0, &b"1234"[..], entry, 4).unwrap(), b"1234");
+        let error = read_limited(0, &b"12345"[..], entry, 4).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,132 @@
+//! Contains [`License`], the terms asset packs are distributed under, and the conflicts between
+//! them.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The license of an asset pack, as an SPDX identifier such as `CC-BY-4.0`.
+///
+/// The Creative Commons licenses, public domain dedications and common permissive licenses are
+/// recognised, other identifiers are kept as is but their terms are unknown.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct License(pub String);
+
+/// What a [`License`] requires from the maps using the assets it covers.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[allow(
+    clippy::struct_excessive_bools,
+    reason = "the terms are independent clauses of the license"
+)]
+pub struct LicenseTerms {
+    /// The author must be credited.
+    pub attribution: bool,
+    /// Derived works must be shared under the same license.
+    pub share_alike: bool,
+    /// The assets can't be used commercially.
+    pub non_commercial: bool,
+    /// The assets can't be modified or combined into derived works.
+    pub no_derivatives: bool,
+}
+
+/// Why two licenses can't be combined in a single map.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LicenseConflictKind {
+    /// One of the licenses forbids combining its assets with others.
+    NoDerivatives,
+    /// One of the licenses requires sharing the map under it, which the other forbids.
+    ShareAlike,
+    /// The terms of one of the licenses are unknown, so they can't be checked.
+    Unknown,
+}
+
+impl License {
+    /// The license identified by `id`.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    /// The terms of the license, or `None` if the license isn't recognised.
+    #[must_use]
+    pub fn terms(&self) -> Option<LicenseTerms> {
+        let id = self.0.trim().to_uppercase();
+        match id.as_str() {
+            "CC0" | "CC0-1.0" | "UNLICENSE" | "PUBLIC-DOMAIN" => {
+                return Some(LicenseTerms::default());
+            }
+            "MIT" | "ISC" | "ZLIB" | "APACHE-2.0" | "BSD-2-CLAUSE" | "BSD-3-CLAUSE" | "OFL-1.1" => {
+                return Some(LicenseTerms {
+                    attribution: true,
+                    ..LicenseTerms::default()
+                });
+            }
+            _ => {}
+        }
+
+        // Creative Commons licenses combine their terms, such as `CC-BY-NC-SA-4.0`.
+        let mut parts = id.strip_prefix("CC-")?.split('-');
+        if parts.next()? != "BY" {
+            return None;
+        }
+        let mut terms = LicenseTerms {
+            attribution: true,
+            ..LicenseTerms::default()
+        };
+        for part in parts {
+            match part {
+                "SA" => terms.share_alike = true,
+                "NC" => terms.non_commercial = true,
+                "ND" => terms.no_derivatives = true,
+                version
+                    if version
+                        .split('.')
+                        .all(|digits| digits.parse::<u8>().is_ok()) => {}
+                _ => return None,
+            }
+        }
+        Some(terms)
+    }
+
+    /// Why assets under this license and under `other`, from different packs, can't be combined
+    /// in a single map, or `None` if they can.
+    #[must_use]
+    pub fn conflict(&self, other: &Self) -> Option<LicenseConflictKind> {
+        let (Some(terms), Some(other_terms)) = (self.terms(), other.terms()) else {
+            return (!self.0.trim().eq_ignore_ascii_case(other.0.trim()))
+                .then_some(LicenseConflictKind::Unknown);
+        };
+
+        if terms.no_derivatives || other_terms.no_derivatives {
+            return Some(LicenseConflictKind::NoDerivatives);
+        }
+        // A share-alike license must govern the whole map, which can't happen if the other
+        // license also does, or restricts the map in ways it doesn't.
+        let share_alike = |terms: LicenseTerms, other: LicenseTerms| {
+            terms.share_alike
+                && (other.share_alike || (other.non_commercial && !terms.non_commercial))
+        };
+        if terms != other_terms
+            && (share_alike(terms, other_terms) || share_alike(other_terms, terms))
+        {
+            return Some(LicenseConflictKind::ShareAlike);
+        }
+        None
+    }
+}
+
+impl fmt::Display for License {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+/// Two packs whose assets are used together although their licenses conflict.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LicenseConflict {
+    /// The identifiers of the two packs.
+    pub packs: [String; 2],
+    /// The licenses of the used assets of each pack.
+    pub licenses: [License; 2],
+    /// Why the licenses conflict.
+    pub kind: LicenseConflictKind,
+}
@@ -0,0 +1,212 @@
+//! Contains the [`PackManifest`] describing an asset pack.
+
+use crate::License;
+use crate::packs::{is_archive, read_archive};
+use dungeonrs_serialization::{Error, Format, deserialize};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// The name of the file describing an asset pack, in the root of its directory.
+pub const PACK_MANIFEST_FILE: &str = "pack.toml";
+
+/// Describes an asset pack, read from the [`PACK_MANIFEST_FILE`] in its root.
+///
+/// Packs don't need a manifest, every field is optional.
+///
+/// ```toml
+/// name = "Forest Props"
+/// author = "Jane Doe"
+/// version = "1.2.0"
+/// license = "CC-BY-4.0"
+///
+/// [categories]
+/// "trees_png" = "Nature/Trees"
+///
+/// [directories."Props/Barrels"]
+/// tags = ["wood"]
+/// scale = 0.5
+/// license = "CC0-1.0"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackManifest {
+    /// The name of the pack, as its author calls it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Who made the assets, to credit them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// The version of the pack, informational only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The license the assets are distributed under.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<License>,
+    /// The category of the assets of each directory, by directory relative to the pack's root.
+    ///
+    /// Categories are paths such as `Nature/Trees`, the subdirectories of a mapped directory
+    /// become subcategories. Directories that aren't mapped are their own category.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub categories: BTreeMap<PathBuf, String>,
+    /// The defaults of the assets of each directory and its subdirectories, by directory
+    /// relative to the pack's root.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub directories: BTreeMap<PathBuf, DirectoryDefaults>,
+}
+
+/// The defaults of the assets in a directory of a pack.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DirectoryDefaults {
+    /// The tags the assets are searched by, besides the names of their directories.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// The scale the assets are placed at.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f32>,
+    /// The license of the assets, when it differs from the pack's.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<License>,
+}
+
+impl PackManifest {
+    /// Reads the manifest of the pack in `root`, a directory or an archive.
+    ///
+    /// Returns `None` if the pack has no manifest.
+    ///
+    /// # Errors
+    /// Returns an error if the manifest can't be read or isn't valid.
+    pub fn read(root: &Path) -> Result<Option<Self>, Error> {
+        let bytes = if is_archive(root) && root.is_file() {
+            read_archive(root, Path::new(PACK_MANIFEST_FILE))
+        } else {
+            fs::read(root.join(PACK_MANIFEST_FILE))
+        };
+        let bytes = match bytes {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        deserialize(&bytes, Format::Toml).map(Some)
+    }
+
+    /// The credit line of the pack, such as `Forest Props by Jane Doe (CC-BY-4.0)`, or `None` if
+    /// the manifest names neither the pack nor its author.
+    #[must_use]
+    pub fn attribution(&self) -> Option<String> {
+        let attribution = match (&self.name, &self.author) {
+            (Some(name), Some(author)) => format!("{name} by {author}"),
+            (Some(name), None) => name.clone(),
+            (None, Some(author)) => format!("By {author}"),
+            (None, None) => return None,
+        };
+        Some(match &self.license {
+            Some(license) => format!("{attribution} ({license})"),
+            None => attribution,
+        })
+    }
+
+    /// The category of the asset at `path`, relative to the pack's root, as its names.
+    ///
+    /// Assets at the root of the pack have no category, unless the root is mapped.
+    #[must_use]
+    pub fn category(&self, path: &Path) -> Vec<String> {
+        let directories = directory_names(path);
+        let mapping = self
+            .categories
+            .iter()
+            .filter_map(|(directory, category)| {
+                let mapped = names(directory);
+                directories
+                    .starts_with(&mapped)
+                    .then_some((mapped.len(), category))
+            })
+            .max_by_key(|(depth, _)| *depth);
+
+        match mapping {
+            Some((depth, category)) => category
+                .split('/')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_owned)
+                .chain(directories[depth..].iter().cloned())
+                .collect(),
+            None => directories,
+        }
+    }
+
+    /// The tags of the asset at `path`, relative to the pack's root: the names of its
+    /// directories and the tags of their [`DirectoryDefaults`], in lowercase.
+    #[must_use]
+    pub fn tags(&self, path: &Path) -> Vec<String> {
+        let mut tags: Vec<_> = directory_names(path)
+            .into_iter()
+            .chain(
+                self.defaults_of(path)
+                    .into_iter()
+                    .flat_map(|defaults| defaults.tags.iter().cloned()),
+            )
+            .map(|tag| tag.trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        tags.sort_unstable();
+        tags.dedup();
+        tags
+    }
+
+    /// The scale the asset at `path`, relative to the pack's root, is placed at by default.
+    #[must_use]
+    pub fn scale(&self, path: &Path) -> Option<f32> {
+        self.defaults_of(path)
+            .into_iter()
+            .filter_map(|defaults| defaults.scale)
+            .next_back()
+    }
+
+    /// The license of the asset at `path`, relative to the pack's root.
+    #[must_use]
+    pub fn license(&self, path: &Path) -> Option<&License> {
+        self.defaults_of(path)
+            .into_iter()
+            .filter_map(|defaults| defaults.license.as_ref())
+            .next_back()
+            .or(self.license.as_ref())
+    }
+
+    /// The [`DirectoryDefaults`] applying to the asset at `path`, from the outermost directory
+    /// to the innermost.
+    fn defaults_of(&self, path: &Path) -> Vec<&DirectoryDefaults> {
+        let directories = directory_names(path);
+        let mut defaults: Vec<_> = self
+            .directories
+            .iter()
+            .filter_map(|(directory, defaults)| {
+                let names = names(directory);
+                directories
+                    .starts_with(&names)
+                    .then_some((names.len(), defaults))
+            })
+            .collect();
+        defaults.sort_by_key(|(depth, _)| *depth);
+        defaults.into_iter().map(|(_, defaults)| defaults).collect()
+    }
+}
+
+/// The names of the directories of the file at `path`, relative to the pack's root.
+fn directory_names(path: &Path) -> Vec<String> {
+    path.parent().map(names).unwrap_or_default()
+}
+
+/// The names of the components of `path`, without the root or `.` and `..` components.
+fn names(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect()
+}
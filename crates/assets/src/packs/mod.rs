@@ -0,0 +1,128 @@
+//! Contains [`AssetPack`], a directory or archive of assets registered with the [`AssetLibrary`],
+//! and the [`PackManifest`] describing it.
+//!
+//! [`AssetLibrary`]: crate::AssetLibrary
+
+mod archive;
+mod license;
+mod manifest;
+
+pub use archive::{ARCHIVE_EXTENSIONS, MAX_EXTRACTED_SIZE, extract_asset};
+pub use license::{License, LicenseConflict, LicenseConflictKind, LicenseTerms};
+pub use manifest::{DirectoryDefaults, PACK_MANIFEST_FILE, PackManifest};
+
+pub(crate) use archive::{
+    is_archive, list_archive, load_texture, prune_extracted_assets, read_archive,
+};
+
+use crate::{IndexError, IndexSettings};
+use dungeonrs_utils::Directory;
+use serde::{Deserialize, Serialize};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+/// A directory or archive of assets that can be searched and placed in a project.
+///
+/// Packs whose root is a zip or 7z archive are read without unpacking them, see
+/// [`extract_asset`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssetPack {
+    /// Uniquely identifies the pack within the library.
+    pub id: String,
+    /// The name shown to the user.
+    pub name: String,
+    /// The directory containing the pack's assets, or the archive they're stored in.
+    pub root: PathBuf,
+    /// Overrides the library's index settings for this pack, for example for huge packs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<IndexSettings>,
+    /// The manifest in the pack's root, read when the pack's index is opened.
+    ///
+    /// Packs without a manifest have an empty one.
+    #[serde(skip)]
+    pub manifest: PackManifest,
+}
+
+impl AssetPack {
+    /// Creates a pack named `name` for the assets in `root`, a directory or an archive.
+    pub fn new(id: impl Into<String>, name: impl Into<String>, root: impl Into<PathBuf>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            root: root.into(),
+            index: None,
+            manifest: PackManifest::default(),
+        }
+    }
+
+    /// The directory the pack's search index is stored in.
+    ///
+    /// Indexes can always be rebuilt from the pack, so they live in the cache directory.
+    #[must_use]
+    pub fn index_path(&self) -> PathBuf {
+        Directory::Cache.join("index").join(&self.id)
+    }
+
+    /// Returns whether the pack's assets are stored in an archive rather than a directory.
+    #[must_use]
+    pub fn is_archive(&self) -> bool {
+        is_archive(&self.root) && self.root.is_file()
+    }
+
+    /// Lists the files of the pack ordered by path, walking its directory or reading the
+    /// directory of its archive.
+    ///
+    /// # Errors
+    /// Returns an error if the directory or the archive can't be read.
+    pub fn files(&self) -> Result<Vec<PackFile>, IndexError> {
+        if self.is_archive() {
+            return list_archive(&self.root).map_err(IndexError::Archive);
+        }
+
+        let mut files = Vec::new();
+        for entry in WalkDir::new(&self.root).sort_by_file_name() {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let path = entry
+                .path()
+                .strip_prefix(&self.root)
+                .unwrap_or(entry.path());
+            files.push(PackFile::new(path, &entry.metadata()?));
+        }
+
+        Ok(files)
+    }
+}
+
+/// A file of an [`AssetPack`], listed from its directory or its archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackFile {
+    /// The path of the file, relative to the pack's root.
+    pub path: PathBuf,
+    /// The size of the file, in bytes.
+    pub size: u64,
+    /// When the file was last modified, in a unit that depends on where it's stored: it's only
+    /// meant to tell whether the file changed.
+    pub modified: u128,
+}
+
+impl PackFile {
+    /// Describes the file at `path`, relative to its pack's root, from its `metadata`.
+    pub(crate) fn new(path: &Path, metadata: &Metadata) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .unwrap_or_default()
+                .as_nanos(),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Contains the [`AssetsPlugin`].
+
+use crate::AssetLibrary;
+use crate::atlas::{TextureAtlases, pack_atlas_textures, track_atlas_usage};
+use crate::browser::{
+    AssetBrowser, AssetSearchFailed, ScrollAssetBrowser, SearchAssets, browse_assets,
+};
+use crate::handle_cache::{HandleCache, release_unused_handles};
+use crate::index_loading::{
+    PackIndexFailed, PackIndexProgress, PackIndexReady, PackIndexes, open_pack_indexes,
+};
+use crate::packs::prune_extracted_assets;
+use crate::prefabs::{load_user_prefabs, save_user_prefabs};
+use crate::texture_cache::{TextureCache, enforce_texture_budget};
+use crate::user_tags::{load_user_tags, save_user_tags};
+use crate::watcher::{
+    PackAssetsChanged, PackWatchFailed, PackWatcher, update_changed_packs, watch_packs,
+};
+use bevy::prelude::{
+    App, IntoScheduleConfigs, Last, Plugin, PostUpdate, Startup, Update, resource_changed,
+};
+
+/// Registers the resources and systems that manage asset textures and pack indexes at runtime.
+///
+/// Pack indexes are opened and updated through async tasks, which requires the
+/// [`UtilsPlugin`](dungeonrs_utils::UtilsPlugin).
+pub struct AssetsPlugin;
+
+impl Plugin for AssetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AssetLibrary>()
+            .init_resource::<HandleCache>()
+            .init_resource::<TextureAtlases>()
+            .init_resource::<PackIndexes>()
+            .init_resource::<TextureCache>()
+            .init_resource::<PackWatcher>()
+            .init_resource::<AssetBrowser>()
+            .add_message::<PackIndexReady>()
+            .add_message::<PackIndexFailed>()
+            .add_message::<PackIndexProgress>()
+            .add_message::<PackAssetsChanged>()
+            .add_message::<PackWatchFailed>()
+            .add_message::<SearchAssets>()
+            .add_message::<ScrollAssetBrowser>()
+            .add_message::<AssetSearchFailed>()
+            .add_systems(
+                Startup,
+                (load_user_tags, load_user_prefabs, prune_extracted_assets),
+            )
+            .add_systems(
+                Update,
+                (
+                    (open_pack_indexes, watch_packs).run_if(resource_changed::<AssetLibrary>),
+                    update_changed_packs,
+                    browse_assets,
+                )
+                    .chain(),
+            )
+            .add_systems(PostUpdate, (track_atlas_usage, pack_atlas_textures).chain())
+            .add_systems(
+                Last,
+                (
+                    release_unused_handles,
+                    enforce_texture_budget,
+                    (save_user_tags, save_user_prefabs).run_if(resource_changed::<AssetLibrary>),
+                ),
+            );
+    }
+}
@@ -0,0 +1,192 @@
+//! Contains [`UserPrefabs`], the arrangements of assets the user saved to place them again.
+//!
+//! Like the [`UserTags`](crate::UserTags), prefabs belong to the user rather than to a pack, so
+//! they're saved to their own file in the config directory.
+
+use crate::AssetLibrary;
+use bevy::prelude::*;
+use dungeonrs_macros::bevy_system;
+use dungeonrs_serialization::{Error, Format, deserialize, serialize};
+use dungeonrs_utils::Directory;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read, write};
+use std::io;
+use std::path::PathBuf;
+
+/// A named arrangement of assets, such as a furnished table or a campfire with its logs, that
+/// can be placed as a single group.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Prefab {
+    /// The name of the prefab, which identifies it in the library.
+    pub name: String,
+    /// The elements of the prefab in drawing order, positioned relative to its center.
+    pub elements: Vec<PrefabElement>,
+}
+
+/// An element of a [`Prefab`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrefabElement {
+    /// The path of the asset displayed by the element.
+    pub asset: PathBuf,
+    /// The position of the element relative to the center of the prefab.
+    #[serde(with = "dungeonrs_serialization::compact::transform")]
+    pub transform: Transform,
+}
+
+impl Prefab {
+    /// Creates a prefab named `name` made of `elements`.
+    pub fn new(name: impl Into<String>, elements: Vec<PrefabElement>) -> Self {
+        Self {
+            name: name.into(),
+            elements,
+        }
+    }
+}
+
+/// The contents of the file the prefabs are saved to.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PrefabsFile {
+    /// The saved prefabs.
+    #[serde(default)]
+    prefabs: Vec<Prefab>,
+}
+
+/// The prefabs the user saved, browsed in the asset panel alongside the packs.
+///
+/// They're read from [`UserPrefabs::file`] when the app starts and saved to it whenever they
+/// change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserPrefabs {
+    /// The file the prefabs are saved to.
+    pub file: PathBuf,
+    /// The prefabs, by name.
+    prefabs: BTreeMap<String, Prefab>,
+    /// Whether the prefabs changed since they were last read or saved.
+    unsaved: bool,
+}
+
+impl Default for UserPrefabs {
+    fn default() -> Self {
+        Self::new(Directory::Config.join("prefabs.toml"))
+    }
+}
+
+impl UserPrefabs {
+    /// Saves the prefabs to `file` instead of the default location.
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: file.into(),
+            prefabs: BTreeMap::new(),
+            unsaved: false,
+        }
+    }
+
+    /// Adds `prefab`, returning the prefab it replaced if one had the same name.
+    pub fn add(&mut self, prefab: Prefab) -> Option<Prefab> {
+        self.unsaved = true;
+        self.prefabs.insert(prefab.name.clone(), prefab)
+    }
+
+    /// Removes the prefab named `name`, returning it if it existed.
+    pub fn remove(&mut self, name: &str) -> Option<Prefab> {
+        let prefab = self.prefabs.remove(name)?;
+        self.unsaved = true;
+
+        Some(prefab)
+    }
+
+    /// Returns the prefab named `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+
+    /// Iterates over the prefabs, in alphabetical order.
+    pub fn iter(&self) -> impl Iterator<Item = &Prefab> {
+        self.prefabs.values()
+    }
+
+    /// Iterates over the prefabs whose name contains `text`, ignoring case, in alphabetical
+    /// order.
+    pub fn matching<'a>(&'a self, text: &str) -> impl Iterator<Item = &'a Prefab> + use<'a> {
+        let text = text.trim().to_lowercase();
+        self.iter()
+            .filter(move |prefab| prefab.name.to_lowercase().contains(&text))
+    }
+
+    /// The number of prefabs.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.prefabs.len()
+    }
+
+    /// Returns whether no prefabs were saved.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.prefabs.is_empty()
+    }
+
+    /// Returns whether the prefabs changed since they were last read or saved.
+    #[must_use]
+    pub fn is_unsaved(&self) -> bool {
+        self.unsaved
+    }
+
+    /// Reads the prefabs from [`UserPrefabs::file`], replacing the ones added so far.
+    ///
+    /// # Errors
+    /// Returns an error if the file exists but can't be read or isn't valid.
+    pub fn reload(&mut self) -> Result<(), Error> {
+        self.prefabs.clear();
+        self.unsaved = false;
+        let bytes = match read(&self.file) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error.into()),
+        };
+
+        let file: PrefabsFile = deserialize(&bytes, Format::Toml)?;
+        self.prefabs = file
+            .prefabs
+            .into_iter()
+            .map(|prefab| (prefab.name.clone(), prefab))
+            .collect();
+        Ok(())
+    }
+
+    /// Writes the prefabs to [`UserPrefabs::file`].
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save(&mut self) -> Result<(), Error> {
+        let prefabs = self.prefabs.values().cloned().collect();
+        let bytes = serialize(&PrefabsFile { prefabs }, Format::Toml)?;
+        if let Some(parent) = self.file.parent() {
+            create_dir_all(parent)?;
+        }
+
+        write(&self.file, bytes)?;
+        self.unsaved = false;
+        Ok(())
+    }
+}
+
+/// Reads the prefabs saved by previous sessions.
+#[bevy_system]
+pub(crate) fn load_user_prefabs(mut library: ResMut<AssetLibrary>) {
+    // Unreadable prefabs are left out, reload clears them before reading the file.
+    let _ = library.prefabs.reload();
+}
+
+/// Saves the prefabs once they changed.
+#[bevy_system]
+pub(crate) fn save_user_prefabs(mut library: ResMut<AssetLibrary>) {
+    if !library.prefabs.is_unsaved() {
+        return;
+    }
+
+    // Prefabs that fail to save stay unsaved, so saving them is attempted again on the next
+    // change.
+    let _ = library.bypass_change_detection().prefabs.save();
+}
@@ -0,0 +1,368 @@
+//! Structured searches over the assets of several packs, filtered by category, pack and tag.
+//!
+//! The category of an asset is the directory it lies in, relative to its pack's root, and its
+//! tags are the names of the directories along that path, unless the pack's
+//! [`PackManifest`](crate::PackManifest) maps them otherwise. Results are paginated, and count the
+//! matching assets of each category so the asset browser can offer them as filters.
+//!
+//! Searched words also match the names they're the beginning of, and tolerate a typo or two
+//! depending on their length. The tags the user gave to assets through the
+//! [`AssetLibrary`](crate::AssetLibrary) are matched along with the tags of the indexes.
+
+use crate::user_tags::normalize;
+use crate::{IndexError, PackIndexes, UserTags};
+use bevy::platform::collections::HashMap;
+use std::cmp::Reverse;
+use std::path::PathBuf;
+
+/// Marks the words of [`AssetQuery::text`] that filter by tag, such as `tag:favorite`.
+const TAG_PREFIX: &str = "tag:";
+
+/// A search for assets, run with [`PackIndexes::query`] or
+/// [`AssetPackIndex::query`](crate::AssetPackIndex::query).
+///
+/// Every filter left empty matches all assets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetQuery {
+    /// The words the name of an asset is matched against, as complete words, prefixes or with
+    /// typos. Words such as `tag:favorite` are added to the tags instead.
+    pub text: String,
+    /// The categories the assets lie in, such as `Nature/Trees`, including their subcategories.
+    /// Assets in any of them match.
+    pub categories: Vec<String>,
+    /// The identifiers of the searched packs.
+    pub pack_ids: Vec<String>,
+    /// The tags the assets must all have, compared ignoring case.
+    pub tags: Vec<String>,
+    /// The number of matching assets skipped, for loading further pages.
+    pub offset: usize,
+    /// The maximum number of assets returned.
+    pub limit: usize,
+}
+
+impl Default for AssetQuery {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            categories: Vec::new(),
+            pack_ids: Vec::new(),
+            tags: Vec::new(),
+            offset: 0,
+            limit: 50,
+        }
+    }
+}
+
+impl AssetQuery {
+    /// Creates a query for the assets whose name matches `text`.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
+
+    /// The same query for the page following this one.
+    #[must_use]
+    pub fn next_page(&self) -> Self {
+        Self {
+            offset: self.offset + self.limit,
+            ..self.clone()
+        }
+    }
+
+    /// Returns whether the pack identified by `id` is searched.
+    #[must_use]
+    pub fn includes_pack(&self, id: &str) -> bool {
+        self.pack_ids.is_empty() || self.pack_ids.iter().any(|pack| pack == id)
+    }
+
+    /// The searched categories as their directory names, without duplicates.
+    pub(crate) fn category_paths(&self) -> Vec<Vec<&str>> {
+        let mut categories: Vec<Vec<&str>> = self
+            .categories
+            .iter()
+            .map(|category| category_path(category))
+            .filter(|path| !path.is_empty())
+            .collect();
+        categories.sort_unstable();
+        categories.dedup();
+        categories
+    }
+
+    /// The tags the assets must have, including the ones of the text, in lowercase.
+    pub(crate) fn normalized_tags(&self) -> Vec<String> {
+        let mut tags: Vec<_> = self
+            .tags
+            .iter()
+            .map(|tag| normalize(tag))
+            .chain(self.text.split_whitespace().filter_map(|word| {
+                let tag = word.get(..TAG_PREFIX.len())?;
+                tag.eq_ignore_ascii_case(TAG_PREFIX)
+                    .then(|| normalize(&word[TAG_PREFIX.len()..]))
+            }))
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        tags.sort_unstable();
+        tags.dedup();
+        tags
+    }
+
+    /// The words of the text matched against the names of the assets, without the tags.
+    pub(crate) fn search_text(&self) -> String {
+        self.text
+            .split_whitespace()
+            .filter(|word| {
+                !word
+                    .get(..TAG_PREFIX.len())
+                    .is_some_and(|tag| tag.eq_ignore_ascii_case(TAG_PREFIX))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// An asset matching an [`AssetQuery`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetHit {
+    /// The identifier of the asset's pack.
+    pub pack: String,
+    /// The path of the asset, relative to its pack's root.
+    pub path: PathBuf,
+    /// How well the asset matches the query, higher is better.
+    pub score: f32,
+}
+
+/// The number of assets matching an [`AssetQuery`] in a category.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CategoryCount {
+    /// The category, such as `Nature/Trees`.
+    pub category: String,
+    /// The number of matching assets in the category and its subcategories.
+    pub count: u64,
+}
+
+/// A page of the assets matching an [`AssetQuery`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AssetResults {
+    /// The matching assets of the requested page, best matches first.
+    pub hits: Vec<AssetHit>,
+    /// The number of matching assets across all pages.
+    pub total: usize,
+    /// The number of matching assets in each top-level category and in each subcategory of the
+    /// searched categories, ordered by category.
+    ///
+    /// The counts ignore the query's categories, so the other categories can still be offered.
+    pub categories: Vec<CategoryCount>,
+}
+
+impl AssetResults {
+    /// Returns whether more assets match `query` beyond this page.
+    #[must_use]
+    pub fn has_more(&self, query: &AssetQuery) -> bool {
+        query.offset + self.hits.len() < self.total
+    }
+}
+
+impl PackIndexes {
+    /// Runs `query` against the indexes of the searched packs that finished opening, merging
+    /// their results. Assets have the tags of their index along with their `user_tags`.
+    ///
+    /// Assets matching equally well are ordered by pack identifier, so pages stay consistent.
+    ///
+    /// # Errors
+    /// Returns an error if an index can't be read.
+    pub fn query(
+        &self,
+        query: &AssetQuery,
+        user_tags: &UserTags,
+    ) -> Result<AssetResults, IndexError> {
+        let mut packs: Vec<_> = self
+            .iter()
+            .filter(|(id, _)| query.includes_pack(id))
+            .collect();
+        packs.sort_unstable_by_key(|(id, _)| *id);
+
+        // Every pack could provide the whole page, so each one returns the pages up to it.
+        let pages = AssetQuery {
+            offset: 0,
+            limit: query.offset + query.limit,
+            ..query.clone()
+        };
+        let mut results = AssetResults::default();
+        let mut counts = HashMap::<String, u64>::default();
+        for (id, index) in packs {
+            let pack = index.query(id, &pages, user_tags)?;
+            results.hits.extend(pack.hits);
+            results.total += pack.total;
+            for CategoryCount { category, count } in pack.categories {
+                *counts.entry(category).or_default() += count;
+            }
+        }
+
+        // The sort is stable, keeping the order of each pack's assets.
+        results.hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        results.hits = results
+            .hits
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit)
+            .collect();
+        results.categories = category_counts(counts);
+
+        Ok(results)
+    }
+}
+
+/// Turns the number of assets in each category into [`CategoryCount`]s ordered by category.
+pub(crate) fn category_counts(counts: HashMap<String, u64>) -> Vec<CategoryCount> {
+    let mut counts: Vec<_> = counts
+        .into_iter()
+        .map(|(category, count)| CategoryCount { category, count })
+        .collect();
+    counts.sort_unstable_by(|a, b| a.category.cmp(&b.category));
+    counts
+}
+
+/// The directory names of `category`, such as `["Nature", "Trees"]` for `Nature/Trees`.
+pub(crate) fn category_path(category: &str) -> Vec<&str> {
+    category
+        .split(['/', '\\'])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// The number of typos tolerated in a searched word of `length` characters.
+///
+/// Short words are only matched exactly or as a prefix, as a single typo would already match
+/// most of the names.
+pub(crate) fn typo_tolerance(length: usize) -> u8 {
+    match length {
+        0..4 => 0,
+        4..8 => 1,
+        _ => 2,
+    }
+}
+
+/// The number of typos in `word` when taken as the beginning of `candidate`, `Some(0)` if
+/// `candidate` starts with `word`.
+///
+/// Returns `None` if `word` has more typos than its [`typo_tolerance`].
+pub(crate) fn completion_distance(word: &str, candidate: &str) -> Option<usize> {
+    if candidate.starts_with(word) {
+        return Some(0);
+    }
+
+    let length = word.chars().count();
+    let typos = usize::from(typo_tolerance(length));
+    if typos == 0 {
+        return None;
+    }
+    // Typos may add or remove characters, so the beginnings of a few lengths are compared.
+    (length.saturating_sub(typos)..=length + typos)
+        .map(|end| {
+            let start: String = candidate.chars().take(end).collect();
+            strsim::osa_distance(word, &start)
+        })
+        .min()
+        .filter(|distance| *distance <= typos)
+}
+
+/// The `candidates` completing `word`, along with the number of assets whose name contains them,
+/// ranked by their [`completion_distance`] then how common they are, up to `limit` of them.
+pub(crate) fn rank_completions(
+    word: &str,
+    candidates: HashMap<String, u64>,
+    limit: usize,
+) -> Vec<String> {
+    let mut completions: Vec<_> = candidates
+        .into_iter()
+        .filter_map(|(candidate, count)| {
+            let distance = completion_distance(word, &candidate)?;
+            Some((distance, Reverse(count), candidate))
+        })
+        .collect();
+    completions.sort_unstable();
+
+    completions
+        .into_iter()
+        .take(limit)
+        .map(|(_, _, candidate)| candidate)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    //! Splits the text of queries into searched words and tags, and their categories into paths.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+
+    /// Words prefixed with `tag:` are taken out of the text and merged with the tags, ignoring
+    /// case and duplicates.
+    #[test]
+    fn separates_tag_facets() {
+        let query = AssetQuery {
+            tags: vec!["Favorite".into(), " ".into()],
+            ..AssetQuery::new("TAG:favorite barrel tag:Dungeon   open tag:")
+        };
+
+        assert_eq!(query.search_text(), "barrel open");
+        assert_eq!(query.normalized_tags(), ["dungeon", "favorite"]);
+    }
+
+    /// The text has no syntax: quotes and leading dashes are kept as part of the words, which are
+    /// searched like any other, so half-typed text never fails to parse.
+    #[test]
+    fn keeps_quotes_and_dashes_in_words() {
+        let query = AssetQuery::new(" \"stone  wall\" -torch tag:-broken ");
+
+        assert_eq!(query.search_text(), "\"stone wall\" -torch");
+        assert_eq!(query.normalized_tags(), ["-broken"]);
+    }
+
+    /// Categories are split on either slash, ignoring blank and repeated ones.
+    #[test]
+    fn splits_categories() {
+        let query = AssetQuery {
+            categories: vec![
+                "Nature/Trees".into(),
+                " Nature\\Trees/ ".into(),
+                "/".into(),
+                "Walls".into(),
+            ],
+            ..AssetQuery::default()
+        };
+
+        assert_eq!(
+            query.category_paths(),
+            [vec!["Nature", "Trees"], vec!["Walls"]]
+        );
+    }
+
+    /// Pages follow each other until every matching asset was returned.
+    #[test]
+    fn pages_through_results() {
+        let query = AssetQuery {
+            limit: 2,
+            ..AssetQuery::new("barrel")
+        };
+        let page = AssetResults {
+            hits: vec![
+                AssetHit {
+                    pack: "props".into(),
+                    path: "barrel.png".into(),
+                    score: 1.0,
+                };
+                2
+            ],
+            total: 3,
+            categories: Vec::new(),
+        };
+
+        assert!(page.has_more(&query));
+        assert_eq!(query.next_page().offset, 2);
+        assert!(!page.has_more(&query.next_page()));
+    }
+}
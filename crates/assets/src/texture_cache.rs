@@ -0,0 +1,272 @@
+//! Keeps the memory used by asset textures and thumbnails within a budget.
+//!
+//! Browsing a large library loads thousands of textures that are only shown briefly. The
+//! [`TextureCache`] holds on to the textures it loaded, tracks how much CPU and GPU memory they
+//! use and releases the least recently used ones once the budget is exceeded.
+
+use crate::packs::load_texture;
+use bevy::asset::{AssetPath, RenderAssetUsages};
+use bevy::image::TextureFormatPixelInfo;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use dungeonrs_macros::bevy_system;
+use std::sync::Arc;
+
+/// What a cached texture is used for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TextureKind {
+    /// The full-size texture of an asset.
+    Asset,
+    /// A small preview shown in the asset browser.
+    Thumbnail,
+}
+
+/// An amount of texture memory, in bytes.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct TextureMemory {
+    /// Memory used by the pixel data kept in the main world.
+    pub cpu: usize,
+    /// Memory used by the texture uploaded to the GPU.
+    pub gpu: usize,
+}
+
+impl TextureMemory {
+    /// Estimates the memory used by `image`.
+    #[must_use]
+    pub fn of(image: &Image) -> Self {
+        let size = image.texture_descriptor.size;
+        let pixel_size = image.texture_descriptor.format.pixel_size().unwrap_or(4);
+        let texels =
+            size.width as usize * size.height as usize * size.depth_or_array_layers as usize;
+        // A full mip chain adds roughly a third to the base level.
+        let gpu = if image.texture_descriptor.mip_level_count > 1 {
+            texels * pixel_size * 4 / 3
+        } else {
+            texels * pixel_size
+        };
+
+        Self {
+            cpu: if image.asset_usage.contains(RenderAssetUsages::MAIN_WORLD) {
+                image.data.as_ref().map_or(0, Vec::len)
+            } else {
+                0
+            },
+            gpu: if image.asset_usage.contains(RenderAssetUsages::RENDER_WORLD) {
+                gpu
+            } else {
+                0
+            },
+        }
+    }
+
+    /// Returns whether either amount of memory exceeds the one in `budget`.
+    #[must_use]
+    pub fn exceeds(&self, budget: &TextureMemory) -> bool {
+        self.cpu > budget.cpu || self.gpu > budget.gpu
+    }
+}
+
+impl std::ops::AddAssign for TextureMemory {
+    fn add_assign(&mut self, other: Self) {
+        self.cpu += other.cpu;
+        self.gpu += other.gpu;
+    }
+}
+
+impl std::ops::SubAssign for TextureMemory {
+    fn sub_assign(&mut self, other: Self) {
+        self.cpu = self.cpu.saturating_sub(other.cpu);
+        self.gpu = self.gpu.saturating_sub(other.gpu);
+    }
+}
+
+/// A texture loaded through the [`TextureCache`].
+struct CachedTexture {
+    /// Keeps the texture loaded for as long as it's cached.
+    handle: Handle<Image>,
+    /// What the texture is used for.
+    kind: TextureKind,
+    /// The value of the cache's clock when the texture was last requested.
+    last_used: u64,
+    /// The memory used by the texture, known once it finished loading.
+    memory: Option<TextureMemory>,
+}
+
+impl CachedTexture {
+    /// Returns whether any handle outside of the cache keeps the texture alive.
+    fn is_shared(&self) -> bool {
+        match &self.handle {
+            Handle::Strong(handle) => Arc::strong_count(handle) > 1,
+            Handle::Uuid(..) => true,
+        }
+    }
+}
+
+/// Loads asset textures and thumbnails, releasing the least recently used ones when the memory
+/// they use exceeds the budget.
+///
+/// Only textures that aren't referenced outside of the cache can be released, so textures of
+/// placed elements stay loaded regardless of the budget and only count against it.
+#[derive(Resource)]
+pub struct TextureCache {
+    /// The memory the cached textures may use.
+    budget: TextureMemory,
+    /// Incremented every time a texture is requested, used to order textures by last use.
+    clock: u64,
+    /// The cached textures by path.
+    textures: HashMap<AssetPath<'static>, CachedTexture>,
+    /// The memory used by the cached textures that finished loading.
+    usage: TextureMemory,
+}
+
+impl Default for TextureCache {
+    fn default() -> Self {
+        Self::new(TextureCache::DEFAULT_BUDGET)
+    }
+}
+
+impl TextureCache {
+    /// The budget used by default, 1 GiB of both CPU and GPU memory.
+    pub const DEFAULT_BUDGET: TextureMemory = TextureMemory {
+        cpu: 1 << 30,
+        gpu: 1 << 30,
+    };
+
+    /// Creates an empty cache with the given `budget`.
+    #[must_use]
+    pub fn new(budget: TextureMemory) -> Self {
+        Self {
+            budget,
+            clock: 0,
+            textures: HashMap::default(),
+            usage: TextureMemory::default(),
+        }
+    }
+
+    /// Loads the texture at `path`, or returns the cached handle if it was loaded before.
+    ///
+    /// Either way the texture becomes the most recently used one.
+    pub fn load<'a>(
+        &mut self,
+        asset_server: &AssetServer,
+        path: impl Into<AssetPath<'a>>,
+        kind: TextureKind,
+    ) -> Handle<Image> {
+        self.clock += 1;
+        let path = path.into().into_owned();
+        let texture = self
+            .textures
+            .entry(path)
+            .or_insert_with_key(|path| CachedTexture {
+                handle: load_texture(asset_server, path.clone()),
+                kind,
+                last_used: 0,
+                memory: None,
+            });
+        texture.last_used = self.clock;
+
+        texture.handle.clone()
+    }
+
+    /// Releases the texture at `path`, for example because its file was removed.
+    ///
+    /// Returns whether the texture was cached.
+    pub fn forget<'a>(&mut self, path: impl Into<AssetPath<'a>>) -> bool {
+        let Some(texture) = self.textures.remove(&path.into().into_owned()) else {
+            return false;
+        };
+
+        self.usage -= texture.memory.unwrap_or_default();
+        true
+    }
+
+    /// The memory the cached textures may use.
+    #[must_use]
+    pub fn budget(&self) -> TextureMemory {
+        self.budget
+    }
+
+    /// Changes the memory the cached textures may use, textures exceeding it are released on the
+    /// next update.
+    pub fn set_budget(&mut self, budget: TextureMemory) {
+        self.budget = budget;
+    }
+
+    /// The memory used by all cached textures that finished loading.
+    #[must_use]
+    pub fn usage(&self) -> TextureMemory {
+        self.usage
+    }
+
+    /// The memory used by the cached textures of `kind` that finished loading.
+    #[must_use]
+    pub fn usage_of(&self, kind: TextureKind) -> TextureMemory {
+        let mut usage = TextureMemory::default();
+        for texture in self
+            .textures
+            .values()
+            .filter(|texture| texture.kind == kind)
+        {
+            usage += texture.memory.unwrap_or_default();
+        }
+
+        usage
+    }
+
+    /// The number of textures in the cache.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    /// Returns whether the cache holds no textures.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    /// Releases the least recently used textures that aren't referenced elsewhere until the
+    /// usage fits within the budget, or no more textures can be released.
+    fn evict(&mut self) {
+        if !self.usage.exceeds(&self.budget) {
+            return;
+        }
+
+        let mut candidates = self
+            .textures
+            .iter()
+            .filter(|(_, texture)| texture.memory.is_some() && !texture.is_shared())
+            .map(|(path, texture)| (texture.last_used, path.clone()))
+            .collect::<Vec<_>>();
+        candidates.sort_unstable_by_key(|(last_used, _)| *last_used);
+
+        for (_, path) in candidates {
+            if !self.usage.exceeds(&self.budget) {
+                break;
+            }
+
+            if let Some(texture) = self.textures.remove(&path) {
+                self.usage -= texture.memory.unwrap_or_default();
+            }
+        }
+    }
+}
+
+/// Records the memory used by cached textures that finished loading, and releases textures
+/// beyond the budget.
+#[bevy_system]
+pub(crate) fn enforce_texture_budget(mut cache: ResMut<TextureCache>, images: Res<Assets<Image>>) {
+    let mut loaded = TextureMemory::default();
+    for texture in cache.textures.values_mut() {
+        if texture.memory.is_none()
+            && let Some(image) = images.get(&texture.handle)
+        {
+            let memory = TextureMemory::of(image);
+            texture.memory = Some(memory);
+            loaded += memory;
+        }
+    }
+    cache.usage += loaded;
+
+    cache.evict();
+}
@@ -0,0 +1,241 @@
+//! Contains [`UserTags`], the tags the user gave to the assets of the library.
+//!
+//! Packs are usually shared and read-only, so the tags aren't written to them: they're saved to
+//! their own file in the config directory, and searches overlay them on the tags of the indexes.
+
+use crate::AssetLibrary;
+use bevy::prelude::*;
+use dungeonrs_macros::bevy_system;
+use dungeonrs_serialization::{Error, Format, deserialize, serialize};
+use dungeonrs_utils::Directory;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{create_dir_all, read, write};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The tag of the assets the user marked as favourite.
+pub const FAVOURITE_TAG: &str = "favorite";
+
+/// Identifies an asset of the library.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PackAsset {
+    /// The identifier of the asset's pack.
+    pub pack: String,
+    /// The path of the asset, relative to its pack's root.
+    pub path: PathBuf,
+}
+
+impl PackAsset {
+    /// Identifies the asset at `path` in the pack identified by `pack`.
+    pub fn new(pack: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            pack: pack.into(),
+            path: path.into(),
+        }
+    }
+}
+
+/// An asset and its tags, as saved to the tags file.
+#[derive(Debug, Serialize, Deserialize)]
+struct TaggedAsset {
+    /// The identifier of the asset's pack.
+    pack: String,
+    /// The path of the asset, relative to its pack's root.
+    path: PathBuf,
+    /// The tags of the asset.
+    tags: BTreeSet<String>,
+}
+
+/// The contents of the file the tags are saved to.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TagsFile {
+    /// The tagged assets.
+    #[serde(default)]
+    assets: Vec<TaggedAsset>,
+}
+
+/// The tags the user gave to assets, such as [`FAVOURITE_TAG`].
+///
+/// Tags are compared ignoring case and kept in lowercase. They're read from [`UserTags::file`]
+/// when the app starts and saved to it whenever they change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserTags {
+    /// The file the tags are saved to.
+    pub file: PathBuf,
+    /// The tags of each tagged asset.
+    assets: BTreeMap<PackAsset, BTreeSet<String>>,
+    /// Whether the tags changed since they were last read or saved.
+    unsaved: bool,
+}
+
+impl Default for UserTags {
+    fn default() -> Self {
+        Self::new(Directory::Config.join("asset_tags.toml"))
+    }
+}
+
+impl UserTags {
+    /// Saves the tags to `file` instead of the default location.
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: file.into(),
+            assets: BTreeMap::new(),
+            unsaved: false,
+        }
+    }
+
+    /// Tags `asset` with `tag`.
+    ///
+    /// Returns whether the asset didn't have the tag yet.
+    pub fn add(&mut self, asset: PackAsset, tag: &str) -> bool {
+        let tag = normalize(tag);
+        if tag.is_empty() || !self.assets.entry(asset).or_default().insert(tag) {
+            return false;
+        }
+
+        self.unsaved = true;
+        true
+    }
+
+    /// Removes `tag` from `asset`.
+    ///
+    /// Returns whether the asset had the tag.
+    pub fn remove(&mut self, asset: &PackAsset, tag: &str) -> bool {
+        let Some(tags) = self.assets.get_mut(asset) else {
+            return false;
+        };
+        if !tags.remove(&normalize(tag)) {
+            return false;
+        }
+
+        if tags.is_empty() {
+            self.assets.remove(asset);
+        }
+        self.unsaved = true;
+        true
+    }
+
+    /// Returns whether `asset` has `tag`.
+    #[must_use]
+    pub fn has(&self, asset: &PackAsset, tag: &str) -> bool {
+        self.assets
+            .get(asset)
+            .is_some_and(|tags| tags.contains(&normalize(tag)))
+    }
+
+    /// Iterates over the tags of `asset`, in alphabetical order.
+    pub fn tags(&self, asset: &PackAsset) -> impl Iterator<Item = &str> {
+        self.assets
+            .get(asset)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// Iterates over the assets tagged with `tag`.
+    pub fn tagged<'a>(&'a self, tag: &str) -> impl Iterator<Item = &'a PackAsset> + use<'a> {
+        let tag = normalize(tag);
+        self.assets
+            .iter()
+            .filter(move |(_, tags)| tags.contains(&tag))
+            .map(|(asset, _)| asset)
+    }
+
+    /// The paths of the assets of the pack identified by `pack` tagged with `tag`, which must be
+    /// in lowercase already.
+    pub(crate) fn paths<'a>(
+        &'a self,
+        pack: &'a str,
+        tag: &'a str,
+    ) -> impl Iterator<Item = &'a Path> + use<'a> {
+        self.assets
+            .iter()
+            .filter(move |(asset, tags)| asset.pack == pack && tags.contains(tag))
+            .map(|(asset, _)| asset.path.as_path())
+    }
+
+    /// Returns whether the tags changed since they were last read or saved.
+    #[must_use]
+    pub fn is_unsaved(&self) -> bool {
+        self.unsaved
+    }
+
+    /// Reads the tags from [`UserTags::file`], replacing the ones given so far.
+    ///
+    /// # Errors
+    /// Returns an error if the file exists but can't be read or isn't valid.
+    pub fn reload(&mut self) -> Result<(), Error> {
+        self.assets.clear();
+        self.unsaved = false;
+        let bytes = match read(&self.file) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error.into()),
+        };
+
+        let file: TagsFile = deserialize(&bytes, Format::Toml)?;
+        for asset in file.assets {
+            let tags: BTreeSet<_> = asset
+                .tags
+                .iter()
+                .map(|tag| normalize(tag))
+                .filter(|tag| !tag.is_empty())
+                .collect();
+            if !tags.is_empty() {
+                self.assets
+                    .entry(PackAsset::new(asset.pack, asset.path))
+                    .or_default()
+                    .extend(tags);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the tags to [`UserTags::file`].
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save(&mut self) -> Result<(), Error> {
+        let assets = self
+            .assets
+            .iter()
+            .map(|(asset, tags)| TaggedAsset {
+                pack: asset.pack.clone(),
+                path: asset.path.clone(),
+                tags: tags.clone(),
+            })
+            .collect();
+        let bytes = serialize(&TagsFile { assets }, Format::Toml)?;
+        if let Some(parent) = self.file.parent() {
+            create_dir_all(parent)?;
+        }
+
+        write(&self.file, bytes)?;
+        self.unsaved = false;
+        Ok(())
+    }
+}
+
+/// The form `tag` is stored and compared in.
+pub(crate) fn normalize(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Reads the tags given by previous sessions.
+#[bevy_system]
+pub(crate) fn load_user_tags(mut library: ResMut<AssetLibrary>) {
+    // Unreadable tags are left out, reload clears them before reading the file.
+    let _ = library.user_tags.reload();
+}
+
+/// Saves the tags once they changed.
+#[bevy_system]
+pub(crate) fn save_user_tags(mut library: ResMut<AssetLibrary>) {
+    if !library.user_tags.is_unsaved() {
+        return;
+    }
+
+    // Tags that fail to save stay unsaved, so saving them is attempted again on the next change.
+    let _ = library.bypass_change_detection().user_tags.save();
+}
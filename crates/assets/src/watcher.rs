@@ -0,0 +1,380 @@
+//! Watches the directories of the registered asset packs and keeps their indexes up to date.
+//!
+//! Files added to, removed from or modified in a pack while the editor runs are picked up without
+//! re-indexing the whole pack. Changes are collected until the pack's files stay unchanged for a
+//! moment, so copying a folder of assets results in a single update of the index.
+
+use crate::index::asset_extension;
+use crate::index_loading::{read_manifest, set_manifest};
+use crate::{
+    AssetLibrary, AssetPack, AssetPackIndex, IndexSettings, PACK_MANIFEST_FILE, PackIndexFailed,
+    PackIndexProgress, PackIndexes, TextureCache,
+};
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use crossbeam_channel::{Receiver, Sender, unbounded};
+use dungeonrs_macros::bevy_system;
+use dungeonrs_utils::{AsyncCommandsExt, CancellationToken, ProgressReporter};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+/// The changes to the files of an asset pack, relative to the pack's root.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackChanges {
+    /// The assets that were added.
+    pub added: Vec<PathBuf>,
+    /// The assets whose file was modified.
+    pub modified: Vec<PathBuf>,
+    /// The assets and directories that were removed.
+    pub removed: Vec<PathBuf>,
+}
+
+impl PackChanges {
+    /// Returns whether nothing changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Watches the directories of the packs in the [`AssetLibrary`] for changes.
+#[derive(Resource)]
+pub struct PackWatcher {
+    /// How long the files of a pack must stay unchanged before its index is updated.
+    pub debounce: Duration,
+    /// The watcher of each pack's root, by pack identifier.
+    watchers: HashMap<String, (PathBuf, RecommendedWatcher)>,
+    /// Sends the events of the watchers, along with the identifier of their pack.
+    sender: Sender<(String, notify::Result<Event>)>,
+    /// Receives the events of the watchers.
+    receiver: Receiver<(String, notify::Result<Event>)>,
+    /// The changes collected for each pack since its index was last updated.
+    pending: HashMap<String, PendingChanges>,
+    /// The packs whose index is being updated, along with the token cancelling the update.
+    updating: HashMap<String, CancellationToken>,
+}
+
+impl Default for PackWatcher {
+    fn default() -> Self {
+        let (sender, receiver) = unbounded();
+        Self {
+            debounce: Duration::from_millis(500),
+            watchers: HashMap::default(),
+            sender,
+            receiver,
+            pending: HashMap::default(),
+            updating: HashMap::default(),
+        }
+    }
+}
+
+impl PackWatcher {
+    /// Returns whether the directory of the pack identified by `id` is being watched.
+    #[must_use]
+    pub fn is_watching(&self, id: &str) -> bool {
+        self.watchers.contains_key(id)
+    }
+
+    /// Returns whether changes to the pack identified by `id` are waiting to be indexed.
+    #[must_use]
+    pub fn has_pending(&self, id: &str) -> bool {
+        self.pending.contains_key(id) || self.updating.contains_key(id)
+    }
+}
+
+/// The changes to a pack that weren't indexed yet.
+struct PendingChanges {
+    /// The absolute paths that changed, along with whether they were created or renamed to.
+    touched: HashMap<PathBuf, bool>,
+    /// Whether events were lost, in which case the whole pack is indexed again.
+    rescan: bool,
+    /// When the last change happened.
+    last: Instant,
+}
+
+/// Written once the index of a pack was updated with the changes to its files.
+#[derive(Message, Debug, Clone)]
+pub struct PackAssetsChanged {
+    /// The identifier of the pack.
+    pub pack: String,
+    /// The changes applied to the index, empty when the pack was indexed again entirely.
+    pub changes: PackChanges,
+    /// Whether the whole pack was indexed again, because the watcher lost track of some changes or
+    /// the pack's manifest changed.
+    pub rescanned: bool,
+}
+
+/// Written when the directory of a pack couldn't be watched.
+#[derive(Message, Debug)]
+pub struct PackWatchFailed {
+    /// The identifier of the pack.
+    pub pack: String,
+    /// Why the directory couldn't be watched.
+    pub error: notify::Error,
+}
+
+/// Starts watching the directory of every registered pack, and stops watching the packs that
+/// were removed from the library or moved, cancelling the updates of their index.
+#[bevy_system]
+pub(crate) fn watch_packs(
+    library: Res<AssetLibrary>,
+    mut watcher: ResMut<PackWatcher>,
+    mut failed: MessageWriter<PackWatchFailed>,
+) {
+    watcher
+        .watchers
+        .retain(|id, (root, _)| library.pack(id).is_some_and(|pack| pack.root == *root));
+    let PackWatcher {
+        watchers,
+        pending,
+        updating,
+        sender,
+        ..
+    } = &mut *watcher;
+    pending.retain(|id, _| library.pack(id).is_some());
+    updating.retain(|id, token| {
+        let registered = library.pack(id).is_some();
+        if !registered {
+            token.cancel();
+        }
+
+        registered
+    });
+
+    for pack in &library.packs {
+        // Archives are read as a whole, there's no directory to watch.
+        if watchers.contains_key(&pack.id) || pack.is_archive() {
+            continue;
+        }
+
+        let id = pack.id.clone();
+        let sender = sender.clone();
+        let result = notify::recommended_watcher(move |event| {
+            // The receiver only goes away along with the app.
+            let _ = sender.send((id.clone(), event));
+        })
+        .and_then(|mut directory| {
+            directory.watch(&pack.root, RecursiveMode::Recursive)?;
+            Ok(directory)
+        });
+
+        match result {
+            Ok(directory) => {
+                watchers.insert(pack.id.clone(), (pack.root.clone(), directory));
+            }
+            Err(error) => {
+                failed.write(PackWatchFailed {
+                    pack: pack.id.clone(),
+                    error,
+                });
+            }
+        }
+    }
+}
+
+/// Collects the changes reported by the watchers, and updates the index of each pack whose files
+/// stopped changing in the background.
+#[bevy_system]
+pub(crate) fn update_changed_packs(
+    mut commands: Commands,
+    mut watcher: ResMut<PackWatcher>,
+    library: Res<AssetLibrary>,
+    indexes: Res<PackIndexes>,
+) {
+    let now = Instant::now();
+    let PackWatcher {
+        debounce,
+        receiver,
+        pending,
+        updating,
+        ..
+    } = &mut *watcher;
+    for (id, event) in receiver.try_iter() {
+        let created = match &event {
+            Ok(event) => match event.kind {
+                EventKind::Access(_) | EventKind::Modify(ModifyKind::Metadata(_)) => continue,
+                EventKind::Create(_) => true,
+                EventKind::Modify(ModifyKind::Name(mode)) => mode != RenameMode::From,
+                _ => false,
+            },
+            Err(_) => false,
+        };
+        let changes = pending.entry(id).or_insert_with(|| PendingChanges {
+            touched: HashMap::default(),
+            rescan: false,
+            last: now,
+        });
+        changes.last = now;
+
+        match event {
+            Ok(event) if !event.need_rescan() => {
+                for path in event.paths {
+                    *changes.touched.entry(path).or_default() |= created;
+                }
+            }
+            // Changes were lost, the pack's files have to be listed again.
+            _ => changes.rescan = true,
+        }
+    }
+
+    let settled: Vec<_> = pending
+        .iter()
+        .filter(|(id, changes)| {
+            now.duration_since(changes.last) >= *debounce
+                && !updating.contains_key(*id)
+                && indexes.is_ready(id)
+        })
+        .map(|(id, _)| id.clone())
+        .collect();
+    for id in settled {
+        let (Some(changes), Some(pack), Some(index)) = (
+            pending.remove(&id),
+            library.pack(&id).cloned(),
+            indexes.get(&id).cloned(),
+        ) else {
+            continue;
+        };
+
+        let settings = library.index_settings(&pack);
+        let token = start_update(&mut commands, pack, index, settings, changes);
+        updating.insert(id, token);
+    }
+}
+
+/// Applies the `changes` to the files of `pack` to its `index` in the background, and returns
+/// the token cancelling the update.
+///
+/// Changes to the pack's manifest can give any asset another category or tags, so the manifest is
+/// read again and the whole pack indexed again.
+fn start_update(
+    commands: &mut Commands,
+    mut pack: AssetPack,
+    index: AssetPackIndex,
+    settings: IndexSettings,
+    changes: PendingChanges,
+) -> CancellationToken {
+    commands.spawn_async(move |context| async move {
+        let manifest_changed = changes
+            .touched
+            .contains_key(&pack.root.join(PACK_MANIFEST_FILE));
+        let rescanned = changes.rescan || manifest_changed;
+        let (changes, result) = if rescanned {
+            let result = if manifest_changed {
+                read_manifest(&mut pack)
+            } else {
+                Ok(())
+            }
+            .and_then(|()| {
+                let mut reporter = ProgressReporter::new(&context);
+                index.rebuild(&pack, &settings, context.token(), |checked, total| {
+                    reporter.report(PackIndexProgress {
+                        pack: pack.id.clone(),
+                        checked,
+                        total,
+                    });
+                })
+            })
+            .map(|_| ());
+            (PackChanges::default(), result)
+        } else {
+            let changes = collect_changes(&pack.root, changes.touched);
+            if changes.is_empty() {
+                context.queue(move |world: &mut World| {
+                    world
+                        .resource_mut::<PackWatcher>()
+                        .updating
+                        .remove(&pack.id);
+                });
+                return;
+            }
+            let result = index.update(&pack, &changes).map(|_| ());
+            (changes, result)
+        };
+
+        context.queue(move |world: &mut World| {
+            world
+                .resource_mut::<PackWatcher>()
+                .updating
+                .remove(&pack.id);
+            if let Err(error) = result {
+                world.write_message(PackIndexFailed {
+                    pack: pack.id,
+                    error,
+                });
+                return;
+            }
+            if manifest_changed {
+                set_manifest(world, &pack.id, pack.manifest);
+            }
+
+            // Thumbnails of modified assets are reloaded, those of removed assets released.
+            if let Some(asset_server) = world.get_resource::<AssetServer>().cloned() {
+                for path in &changes.modified {
+                    asset_server.reload(pack.root.join(path));
+                }
+            }
+            if let Some(mut cache) = world.get_resource_mut::<TextureCache>() {
+                for path in &changes.removed {
+                    cache.forget(pack.root.join(path));
+                }
+            }
+
+            world.write_message(PackAssetsChanged {
+                pack: pack.id,
+                changes,
+                rescanned,
+            });
+        });
+    })
+}
+
+/// Turns the `touched` paths into the changes to the assets of the pack in `root`.
+///
+/// The paths are checked against the file system once they settled, so a file that was created
+/// and removed again in the meantime is simply removed.
+fn collect_changes(root: &Path, touched: HashMap<PathBuf, bool>) -> PackChanges {
+    let mut changes = PackChanges::default();
+    for (path, created) in touched {
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+
+        if path.is_dir() {
+            if !created {
+                continue;
+            }
+            // Files moved in along with a directory don't get events of their own. Entries that
+            // can't be read are skipped, they'll be picked up when the pack is indexed again.
+            for entry in WalkDir::new(&path).into_iter().filter_map(Result::ok) {
+                if asset_extension(entry.path()).is_some()
+                    && let Ok(relative) = entry.path().strip_prefix(root)
+                {
+                    changes.added.push(relative.to_path_buf());
+                }
+            }
+        } else if path.is_file() {
+            if asset_extension(&path).is_some() {
+                if created {
+                    changes.added.push(relative.to_path_buf());
+                } else {
+                    changes.modified.push(relative.to_path_buf());
+                }
+            }
+        } else if asset_extension(&path).is_some() || path.extension().is_none() {
+            changes.removed.push(relative.to_path_buf());
+        }
+    }
+
+    for paths in [
+        &mut changes.added,
+        &mut changes.modified,
+        &mut changes.removed,
+    ] {
+        paths.sort_unstable();
+        paths.dedup();
+    }
+    changes
+}
@@ -0,0 +1,179 @@
+//! Plays the frames of elements carrying an [`AnimatedTexture`].
+//!
+//! Spritesheets are displayed a cell at a time by narrowing the sprite to the cell, directories of
+//! frames by swapping the sprite's image. Animated elements manage their own sprite, so their
+//! textures aren't packed into the shared [`TextureAtlases`](dungeonrs_assets::TextureAtlases).
+//!
+//! While an export is captured every animated element displays its
+//! [`AnimatedTexture::export_frame`], as the exported image can't play.
+
+use crate::export::ExportCapture;
+use bevy::prelude::*;
+use dungeonrs_assets::{AtlasTexture, HandleCache};
+use dungeonrs_data::{AnimatedTexture, AnimationFrames, Element};
+use dungeonrs_macros::bevy_system;
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+/// The file extensions of the images read from a directory of frames.
+const FRAME_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+
+/// Registers the systems loading and playing the frames of
+/// [`AnimatedTexture`](dungeonrs_data::AnimatedTexture)s.
+///
+/// Requires the [`AssetsPlugin`](dungeonrs_assets::AssetsPlugin) for the frames' textures.
+pub struct AnimatedTexturesPlugin;
+
+impl Plugin for AnimatedTexturesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (load_frames, stop_animations))
+            .add_systems(PostUpdate, play_frames);
+    }
+}
+
+/// The images the frames of an animated element are taken from.
+#[derive(Component, Debug, Clone)]
+#[require(Sprite)]
+struct FrameImages(Vec<Handle<Image>>);
+
+/// Filters the animated elements whose animation or asset changed.
+type AnimationChanged = Or<(Changed<AnimatedTexture>, Changed<Element>)>;
+
+/// Loads the frames of the elements whose animation or asset changed.
+#[bevy_system]
+fn load_frames(
+    mut commands: Commands,
+    mut cache: ResMut<HandleCache>,
+    asset_server: Res<AssetServer>,
+    elements: Query<(Entity, &Element, &AnimatedTexture), AnimationChanged>,
+) {
+    for (entity, element, animation) in &elements {
+        let images = match animation.frames {
+            AnimationFrames::Sheet(_) => vec![cache.image(&asset_server, element.asset.clone())],
+            AnimationFrames::Folder => frame_paths(&element.asset)
+                .into_iter()
+                .map(|path| cache.image(&asset_server, path))
+                .collect(),
+        };
+        commands
+            .entity(entity)
+            .remove::<AtlasTexture>()
+            .insert(FrameImages(images));
+    }
+}
+
+/// Gives elements that are no longer animated their texture back.
+#[bevy_system]
+fn stop_animations(
+    mut commands: Commands,
+    mut cache: ResMut<HandleCache>,
+    asset_server: Res<AssetServer>,
+    mut stopped: RemovedComponents<AnimatedTexture>,
+    mut elements: Query<(&Element, &mut Sprite), With<FrameImages>>,
+) {
+    for entity in stopped.read() {
+        let Ok((element, mut sprite)) = elements.get_mut(entity) else {
+            continue;
+        };
+
+        sprite.rect = None;
+        let texture = cache.image(&asset_server, element.asset.clone());
+        commands
+            .entity(entity)
+            .remove::<FrameImages>()
+            .insert(AtlasTexture(texture));
+    }
+}
+
+/// Displays the current frame of every animated element, or its export frame while an export is
+/// captured.
+#[bevy_system]
+fn play_frames(
+    time: Res<Time>,
+    images: Res<Assets<Image>>,
+    capture: Option<Res<ExportCapture>>,
+    mut elements: Query<(&AnimatedTexture, &FrameImages, &mut Sprite)>,
+) {
+    let seconds = time.elapsed_secs();
+    for (animation, frames, mut sprite) in &mut elements {
+        let (image, rect) = match animation.frames {
+            AnimationFrames::Sheet(grid) => {
+                let Some(image) = frames.0.first() else {
+                    continue;
+                };
+                let grid = grid.max(UVec2::ONE);
+                let count = (grid.x * grid.y) as usize;
+                let frame = current_frame(animation, capture.is_some(), seconds, count);
+                (
+                    image,
+                    images.get(image).map(|loaded| cell(loaded, grid, frame)),
+                )
+            }
+            AnimationFrames::Folder => {
+                let count = frames.0.len();
+                let frame = current_frame(animation, capture.is_some(), seconds, count);
+                let Some(image) = frames.0.get(frame) else {
+                    continue;
+                };
+                (image, None)
+            }
+        };
+
+        // Sprites are only touched when their frame changes, so they aren't flagged as changed
+        // every frame.
+        if sprite.image != *image {
+            sprite.image = image.clone();
+        }
+        if sprite.rect != rect {
+            sprite.rect = rect;
+        }
+    }
+}
+
+/// The index of the frame `animation` displays out of `count` frames.
+fn current_frame(
+    animation: &AnimatedTexture,
+    exporting: bool,
+    seconds: f32,
+    count: usize,
+) -> usize {
+    if exporting {
+        animation.exported_frame(count)
+    } else {
+        animation.frame_at(seconds, count)
+    }
+}
+
+/// The area of the cell at `frame` in the spritesheet `image` of `grid` columns and rows.
+#[allow(
+    clippy::cast_possible_truncation,
+    reason = "the frame is less than the number of cells of the grid"
+)]
+fn cell(image: &Image, grid: UVec2, frame: usize) -> Rect {
+    let size = image.size() / grid;
+    let position = UVec2::new(frame as u32 % grid.x, frame as u32 / grid.x) * size;
+
+    Rect::from_corners(position.as_vec2(), (position + size).as_vec2())
+}
+
+/// The images in the directory at `path`, ordered by file name.
+///
+/// Frames that can't be listed are left out, the element then displays nothing.
+fn frame_paths(path: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = read_dir(path) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.extension().is_some_and(|extension| {
+                FRAME_EXTENSIONS
+                    .iter()
+                    .any(|supported| extension.eq_ignore_ascii_case(supported))
+            })
+        })
+        .collect();
+    paths.sort_unstable();
+    paths
+}
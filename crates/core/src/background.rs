@@ -0,0 +1,195 @@
+//! Draws the [`LevelBackground`] of each level below its layers.
+//!
+//! The background is a [`BackgroundSprite`] child of the level, covering the level's contents.
+//! While an export is captured it also covers the exported area, so exports have no void
+//! around the placed textures, whatever area they cover.
+
+use crate::export::ExportCapture;
+use crate::grid::GridOverlay;
+use crate::lighting::{LightMap, world_bounds};
+use crate::regions::RegionCover;
+use bevy::camera::primitives::Aabb;
+use bevy::image::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor};
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use dungeonrs_assets::HandleCache;
+use dungeonrs_data::{Level, LevelBackground, Region};
+use dungeonrs_macros::bevy_system;
+
+/// The depth of backgrounds within their level, below the first layer.
+const BACKGROUND_Z: f32 = -1.0;
+
+/// Registers the systems that draw the [`LevelBackground`] of each level.
+///
+/// Requires the [`AssetsPlugin`](dungeonrs_assets::AssetsPlugin) for the textures of the
+/// backgrounds.
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        // The backgrounds are sized before the transforms are propagated, so a background
+        // resized for an export is in place in the first captured frame.
+        app.add_systems(
+            PostUpdate,
+            update_backgrounds.before(TransformSystems::Propagate),
+        );
+    }
+}
+
+/// The background drawn below the layers of a level, as a sprite showing its
+/// [`LevelBackground`].
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq)]
+#[require(Sprite)]
+pub struct BackgroundSprite {
+    /// The level the background is drawn for, the sprite is one of its children.
+    pub level: Entity,
+}
+
+/// The contents of the levels, leaving out what is drawn below or over them.
+type LevelContents<'w, 's> = Query<
+    'w,
+    's,
+    (&'static GlobalTransform, &'static Aabb),
+    (
+        Without<BackgroundSprite>,
+        Without<GridOverlay>,
+        Without<LightMap>,
+        Without<RegionCover>,
+        Without<Region>,
+    ),
+>;
+
+/// Sizes the background of every level to its contents and the area being exported, and drops
+/// the backgrounds of levels that have none, are empty or were despawned.
+#[bevy_system]
+#[allow(
+    clippy::too_many_arguments,
+    reason = "the backgrounds depend on the levels, their contents, the export and the textures"
+)]
+fn update_backgrounds(
+    mut commands: Commands,
+    mut cache: ResMut<HandleCache>,
+    mut images: ResMut<Assets<Image>>,
+    asset_server: Res<AssetServer>,
+    capture: Option<Res<ExportCapture>>,
+    levels: Query<(Entity, &GlobalTransform, &LevelBackground), With<Level>>,
+    children: Query<&Children>,
+    contents: LevelContents,
+    mut sprites: Query<(Entity, &BackgroundSprite, &mut Sprite, &mut Transform)>,
+) {
+    let exported = capture.map(|capture| capture.map_area());
+    let mut existing: HashMap<Entity, Entity> = sprites
+        .iter()
+        .map(|(entity, background, ..)| (background.level, entity))
+        .collect();
+    for (level, level_transform, background) in &levels {
+        let current = existing.remove(&level);
+        let area = children
+            .iter_descendants(level)
+            .filter_map(|entity| contents.get(entity).ok())
+            .map(|(transform, aabb)| world_bounds(transform, aabb))
+            .chain(exported)
+            .reduce(|area, bounds| area.union(bounds))
+            .filter(|area| !area.is_empty());
+        let Some(area) = area else {
+            if let Some(current) = current {
+                commands.entity(current).despawn();
+            }
+            continue;
+        };
+
+        let sprite = background_sprite(background, area, &mut cache, &mut images, &asset_server);
+        let translation = level_transform
+            .affine()
+            .inverse()
+            .transform_point3(area.center().extend(0.0))
+            .truncate()
+            .extend(BACKGROUND_Z);
+
+        let Some((_, _, mut current, mut transform)) =
+            current.and_then(|current| sprites.get_mut(current).ok())
+        else {
+            commands.spawn((
+                sprite,
+                BackgroundSprite { level },
+                Transform::from_translation(translation),
+                ChildOf(level),
+            ));
+            continue;
+        };
+
+        // Backgrounds are only touched when they change, so they aren't flagged as changed every
+        // frame.
+        if current.image != sprite.image
+            || current.color != sprite.color
+            || current.custom_size != sprite.custom_size
+            || current.rect != sprite.rect
+        {
+            *current = sprite;
+        }
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+
+    for current in existing.into_values() {
+        commands.entity(current).despawn();
+    }
+}
+
+/// The sprite showing `background` across `area`.
+///
+/// Repeated textures are drawn once their image is loaded, as the number of repetitions depends
+/// on its size.
+fn background_sprite(
+    background: &LevelBackground,
+    area: Rect,
+    cache: &mut HandleCache,
+    images: &mut Assets<Image>,
+    asset_server: &AssetServer,
+) -> Sprite {
+    let mut sprite = Sprite {
+        custom_size: Some(area.size()),
+        ..default()
+    };
+    match background {
+        LevelBackground::Color(color) => sprite.color = *color,
+        LevelBackground::Image(asset) => sprite.image = cache.image(asset_server, asset.clone()),
+        LevelBackground::Texture(asset, tile_size) => {
+            sprite.image = cache.image(asset_server, asset.clone());
+            let Some(image) = images.get(&sprite.image) else {
+                sprite.color = Color::NONE;
+                return sprite;
+            };
+
+            let size = image.size_f32();
+            sprite.rect = Some(Rect::from_corners(
+                Vec2::ZERO,
+                area.size() * size.x / tile_size.max(f32::EPSILON),
+            ));
+            if !repeats(&image.sampler)
+                && let Some(image) = images.get_mut(&sprite.image)
+            {
+                image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+                    address_mode_u: ImageAddressMode::Repeat,
+                    address_mode_v: ImageAddressMode::Repeat,
+                    ..ImageSamplerDescriptor::linear()
+                });
+            }
+        }
+    }
+
+    sprite
+}
+
+/// Whether `sampler` repeats its image in both directions.
+fn repeats(sampler: &ImageSampler) -> bool {
+    matches!(
+        sampler,
+        ImageSampler::Descriptor(ImageSamplerDescriptor {
+            address_mode_u: ImageAddressMode::Repeat,
+            address_mode_v: ImageAddressMode::Repeat,
+            ..
+        })
+    )
+}
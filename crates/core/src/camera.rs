@@ -0,0 +1,283 @@
+//! Zooms the viewport with the mouse wheel and keyboard shortcuts.
+//!
+//! Zooming changes the scale of the cameras' orthographic [`Projection`], like the export cameras
+//! are scaled to their pixels per world unit, and keeps the point of the map under the cursor in
+//! place. The scale eases towards the requested zoom instead of jumping there.
+
+use crate::export::ViewportCamera;
+use crate::lighting::world_bounds;
+use crate::{Action, ActionTriggered, AssetDrag};
+use bevy::camera::primitives::Aabb;
+use bevy::input::mouse::{AccumulatedMouseScroll, MouseScrollUnit};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use dungeonrs_data::{Element, MapProjection};
+use dungeonrs_macros::bevy_system;
+
+/// How much of the viewport the project fills once fitted, leaving a margin around it.
+const FIT_MARGIN: f32 = 0.9;
+
+/// Zooms the viewport cameras with the mouse wheel and the [`Action::ZoomIn`] and
+/// [`Action::ZoomOut`] shortcuts, `+` and `-` by default, and fits the project with
+/// [`Action::FitProject`], `Home` by default.
+pub struct CameraControlsPlugin;
+
+impl Plugin for CameraControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraControls>()
+            .init_resource::<AccumulatedMouseScroll>()
+            .add_message::<ActionTriggered>()
+            .add_message::<ZoomCamera>()
+            .add_message::<FitProject>()
+            .add_systems(
+                Update,
+                (
+                    (zoom_shortcuts, fit_project, zoom_cameras),
+                    ease_camera_zoom,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Configures how the viewport zooms.
+///
+/// Zoom levels are in screen pixels per world unit, the inverse of the scale of the cameras'
+/// orthographic projection.
+#[derive(Resource, Debug, Copy, Clone, PartialEq)]
+pub struct CameraControls {
+    /// The furthest the viewport zooms out.
+    pub min_zoom: f32,
+    /// The furthest the viewport zooms in.
+    pub max_zoom: f32,
+    /// The factor the zoom changes by for each notch of the mouse wheel or press of `+` or `-`.
+    pub zoom_step: f32,
+    /// How quickly the zoom eases towards the requested zoom, the remaining distance shrinking
+    /// by this factor per second. The zoom changes at once when `None`.
+    pub smoothing: Option<f32>,
+}
+
+impl Default for CameraControls {
+    fn default() -> Self {
+        Self {
+            min_zoom: 0.02,
+            max_zoom: 8.0,
+            zoom_step: 1.2,
+            smoothing: Some(12.0),
+        }
+    }
+}
+
+impl CameraControls {
+    /// The scale of an orthographic projection showing the map at `zoom`, within the limits.
+    fn scale(&self, zoom: f32) -> f32 {
+        zoom.clamp(self.min_zoom, self.max_zoom.max(self.min_zoom))
+            .max(f32::EPSILON)
+            .recip()
+    }
+}
+
+/// Zooms the viewport cameras in or out.
+#[derive(Message, Debug, Copy, Clone, PartialEq)]
+pub struct ZoomCamera {
+    /// The factor the zoom is multiplied by, above `1.0` to zoom in.
+    pub factor: f32,
+    /// The position in the viewport that stays in place, in logical pixels, such as the cursor.
+    /// The center of the viewport when `None`.
+    pub anchor: Option<Vec2>,
+}
+
+/// Zooms and moves the viewport cameras to show every element of the project.
+#[derive(Message, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct FitProject;
+
+/// The zoom a viewport camera eases towards.
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct CameraZoom {
+    /// The scale the camera's projection eases towards.
+    pub target: f32,
+    /// The point of the map kept in place while the camera zooms, in world units, the center of
+    /// the viewport when `None`.
+    pub anchor: Option<Vec2>,
+}
+
+/// The viewport cameras, leaving out the cameras capturing exports.
+type ViewportCameras<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Camera,
+        &'static GlobalTransform,
+        &'static mut Transform,
+        &'static mut Projection,
+        Option<&'static mut CameraZoom>,
+    ),
+    ViewportCamera,
+>;
+
+/// Writes a [`ZoomCamera`] for the mouse wheel, anchored at the cursor, and for the zoom actions,
+/// and a [`FitProject`] for [`Action::FitProject`].
+///
+/// The mouse wheel rotates dragged assets instead of zooming while one is dragged.
+#[bevy_system]
+fn zoom_shortcuts(
+    mut actions: MessageReader<ActionTriggered>,
+    scroll: Res<AccumulatedMouseScroll>,
+    controls: Res<CameraControls>,
+    drag: Option<Res<AssetDrag>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut zooms: MessageWriter<ZoomCamera>,
+    mut fits: MessageWriter<FitProject>,
+) {
+    let notches = match scroll.unit {
+        MouseScrollUnit::Line => scroll.delta.y,
+        MouseScrollUnit::Pixel => scroll.delta.y / MouseScrollUnit::SCROLL_UNIT_CONVERSION_FACTOR,
+    };
+    let dragging = drag.is_some_and(|drag| drag.is_dragging());
+    if !dragging && notches.abs() > f32::EPSILON {
+        zooms.write(ZoomCamera {
+            factor: controls.zoom_step.powf(notches),
+            anchor: windows.iter().find_map(Window::cursor_position),
+        });
+    }
+
+    for triggered in actions.read() {
+        let factor = match triggered.action {
+            Action::ZoomIn => controls.zoom_step,
+            Action::ZoomOut => controls.zoom_step.recip(),
+            Action::FitProject => {
+                fits.write(FitProject);
+                continue;
+            }
+            _ => continue,
+        };
+        zooms.write(ZoomCamera {
+            factor,
+            anchor: None,
+        });
+    }
+}
+
+/// Sets the zoom the viewport cameras ease towards for each [`ZoomCamera`].
+#[bevy_system]
+fn zoom_cameras(
+    mut commands: Commands,
+    mut zooms: MessageReader<ZoomCamera>,
+    controls: Res<CameraControls>,
+    mut cameras: ViewportCameras,
+) {
+    for zoom in zooms.read() {
+        for (entity, camera, global, _, projection, current) in &mut cameras {
+            let Projection::Orthographic(projection) = &*projection else {
+                continue;
+            };
+            if !camera.is_active {
+                continue;
+            }
+
+            let scale = current
+                .as_ref()
+                .map_or(projection.scale, |zoom| zoom.target);
+            let target = controls.scale(scale.recip() * zoom.factor);
+            let anchor = zoom
+                .anchor
+                .and_then(|anchor| camera.viewport_to_world_2d(global, anchor).ok());
+            match current {
+                Some(mut current) => {
+                    current.target = target;
+                    current.anchor = anchor;
+                }
+                None => {
+                    commands
+                        .entity(entity)
+                        .insert(CameraZoom { target, anchor });
+                }
+            }
+        }
+    }
+}
+
+/// Centers the viewport cameras on the project and zooms them to show all of it for each
+/// [`FitProject`].
+#[bevy_system]
+fn fit_project(
+    mut commands: Commands,
+    mut fits: MessageReader<FitProject>,
+    controls: Res<CameraControls>,
+    map_projection: Res<MapProjection>,
+    elements: Query<(&GlobalTransform, &Aabb), With<Element>>,
+    mut cameras: ViewportCameras,
+) {
+    if fits.read().count() == 0 {
+        return;
+    }
+    let Some(bounds) = elements
+        .iter()
+        .map(|(transform, aabb)| world_bounds(transform, aabb))
+        .reduce(|bounds, element| bounds.union(element))
+        .filter(|bounds| !bounds.is_empty())
+    else {
+        return;
+    };
+
+    // The cameras show the map through the projection, so the projected bounds have to fit.
+    let view = map_projection.project_rect(bounds).size();
+    for (entity, camera, _, mut transform, _, current) in &mut cameras {
+        let Some(viewport) = camera.logical_viewport_size() else {
+            continue;
+        };
+        if !camera.is_active {
+            continue;
+        }
+
+        let zoom = (viewport * FIT_MARGIN / view).min_element();
+        let target = controls.scale(zoom);
+        transform.translation = bounds.center().extend(transform.translation.z);
+        match current {
+            Some(mut current) => {
+                current.target = target;
+                current.anchor = None;
+            }
+            None => {
+                commands.entity(entity).insert(CameraZoom {
+                    target,
+                    anchor: None,
+                });
+            }
+        }
+    }
+}
+
+/// Eases the scale of the viewport cameras towards their [`CameraZoom`], keeping its anchor in
+/// place.
+#[bevy_system]
+fn ease_camera_zoom(
+    time: Res<Time>,
+    controls: Res<CameraControls>,
+    mut cameras: Query<(&mut Transform, &mut Projection, &CameraZoom), With<Camera2d>>,
+) {
+    for (mut transform, mut projection, zoom) in &mut cameras {
+        let Projection::Orthographic(projection) = &mut *projection else {
+            continue;
+        };
+        if (projection.scale - zoom.target).abs() <= f32::EPSILON * zoom.target {
+            continue;
+        }
+
+        let scale = match controls.smoothing {
+            Some(smoothing) => projection
+                .scale
+                .lerp(zoom.target, 1.0 - (-smoothing * time.delta_secs()).exp()),
+            None => zoom.target,
+        };
+        // The map is drawn scaled around the camera's position, so moving the camera towards the
+        // anchor by the same ratio keeps the anchor under the same point of the viewport.
+        if let Some(anchor) = zoom.anchor {
+            let ratio = scale / projection.scale;
+            let translation = anchor + (transform.translation.truncate() - anchor) * ratio;
+            transform.translation = translation.extend(transform.translation.z);
+        }
+        projection.scale = scale;
+    }
+}
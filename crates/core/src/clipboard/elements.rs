@@ -0,0 +1,305 @@
+//! Copies the selected elements to the [`Clipboard`] and pastes them onto a layer.
+//!
+//! Copied elements are serialized like they're saved, so they can be pasted into any layer of any
+//! level, or into another running editor through the JSON of the [`SelectionCopied`] put on the
+//! system clipboard.
+
+use crate::clipboard::{PasteError, PasteFailed};
+use crate::{Edit, EditGroup, ElementData, History, PlaceElement, RemoveElement, Selection};
+use bevy::asset::uuid::Uuid;
+use bevy::platform::collections::HashSet;
+use bevy::prelude::*;
+use dungeonrs_data::{HierarchySnapshot, Layer, PersistentId};
+use dungeonrs_macros::bevy_system;
+use dungeonrs_serialization::{Error, Format, deserialize, serialize};
+use serde::{Deserialize, Serialize};
+
+/// The elements copied by the last [`CopySelection`].
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct Clipboard {
+    /// The copied elements, `None` until something is copied.
+    contents: Option<CopiedElements>,
+    /// The number of times the contents were pasted without a position, each paste landing a
+    /// little further from the copied elements.
+    pastes: u32,
+}
+
+impl Clipboard {
+    /// The copied elements, `None` until something is copied.
+    #[must_use]
+    pub fn contents(&self) -> Option<&CopiedElements> {
+        self.contents.as_ref()
+    }
+
+    /// Whether nothing was copied yet.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_none()
+    }
+}
+
+/// How copied elements are pasted.
+#[derive(Resource, Debug, Copy, Clone, PartialEq)]
+pub struct ClipboardSettings {
+    /// How far each paste without a position lands from the previous one, in world units, so
+    /// pasting repeatedly doesn't stack the copies on top of each other.
+    pub paste_offset: Vec2,
+}
+
+impl Default for ClipboardSettings {
+    fn default() -> Self {
+        Self {
+            paste_offset: Vec2::new(50.0, -50.0),
+        }
+    }
+}
+
+/// Elements copied from a layer, in the form they're put on the clipboard.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename = "dungeonrs-elements")]
+pub struct CopiedElements {
+    /// The center of the copied elements, in world units.
+    #[serde(with = "dungeonrs_serialization::compact::vec2")]
+    pub origin: Vec2,
+    /// The copied elements in drawing order, positioned relative to the `origin`.
+    pub elements: Vec<ElementData>,
+}
+
+impl CopiedElements {
+    /// Serializes the elements as the JSON put on the system clipboard.
+    ///
+    /// # Errors
+    /// Returns an error if the elements fail to serialize.
+    pub fn to_json(&self) -> Result<String, Error> {
+        // JSON is always valid UTF-8.
+        Ok(String::from_utf8_lossy(&serialize(self, Format::Json)?).into_owned())
+    }
+
+    /// Reads elements from the JSON of the system clipboard, such as copied by another editor.
+    ///
+    /// # Errors
+    /// Returns an error if `json` isn't a copy of elements.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        deserialize(json.as_bytes(), Format::Json)
+    }
+}
+
+/// Copies the selected elements to the [`Clipboard`], optionally removing them as when cutting.
+#[derive(Message, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CopySelection {
+    /// Whether the elements are removed once copied.
+    pub cut: bool,
+}
+
+/// Written once the selected elements were copied.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct SelectionCopied {
+    /// The number of copied elements.
+    pub count: usize,
+    /// The copied elements as JSON, for the user interface to put on the system clipboard.
+    pub json: String,
+}
+
+/// Requests pasting copied elements onto a layer.
+#[derive(Message, Debug, Clone, PartialEq)]
+pub struct PasteElements {
+    /// The layer the elements are pasted onto.
+    pub layer: Entity,
+    /// Where the center of the elements is pasted, in world units. Without a position, the
+    /// elements are pasted where they were copied, moved by the
+    /// [`ClipboardSettings::paste_offset`] for each paste.
+    pub position: Option<Vec2>,
+    /// The text of the system clipboard to paste instead of the [`Clipboard`], such as copied by
+    /// another editor.
+    pub json: Option<String>,
+}
+
+impl PasteElements {
+    /// Pastes the [`Clipboard`] onto `layer`.
+    #[must_use]
+    pub fn new(layer: Entity) -> Self {
+        Self {
+            layer,
+            position: None,
+            json: None,
+        }
+    }
+
+    /// Pastes the elements centered on `position` instead.
+    #[must_use]
+    pub fn at(mut self, position: Vec2) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Pastes the elements copied as `json` instead of the [`Clipboard`].
+    #[must_use]
+    pub fn with_json(mut self, json: impl Into<String>) -> Self {
+        self.json = Some(json.into());
+        self
+    }
+}
+
+/// Written once copied elements were pasted.
+///
+/// The paste is recorded in the [`History`] as a single edit.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct ElementsPasted {
+    /// The layer the elements were pasted onto.
+    pub layer: Entity,
+    /// The pasted elements, in drawing order.
+    pub elements: Vec<Entity>,
+}
+
+/// Copies the selected elements of each [`CopySelection`] request to the [`Clipboard`].
+#[bevy_system]
+pub(crate) fn copy_selection(
+    mut commands: Commands,
+    mut requests: MessageReader<CopySelection>,
+    selection: Res<Selection>,
+    snapshot: Res<HierarchySnapshot>,
+) {
+    for request in requests.read() {
+        // The elements are copied in drawing order, so pasting them keeps them stacked the same.
+        let selected: Vec<_> = snapshot
+            .elements()
+            .filter(|entity| selection.contains(*entity))
+            .collect();
+        if selected.is_empty() {
+            continue;
+        }
+
+        let cut = request.cut;
+        commands.queue(move |world: &mut World| {
+            let mut elements: Vec<_> = selected
+                .iter()
+                .filter_map(|element| ElementData::capture(world, *element))
+                .collect();
+            #[allow(
+                clippy::cast_precision_loss,
+                reason = "selections are far smaller than the precision of f32"
+            )]
+            let origin = elements
+                .iter()
+                .map(|element| element.transform.translation.truncate())
+                .sum::<Vec2>()
+                / elements.len().max(1) as f32;
+            for element in &mut elements {
+                element.transform.translation -= origin.extend(0.0);
+            }
+
+            let ids: Vec<_> = elements.iter().map(|element| element.id).collect();
+            let copied = CopiedElements { origin, elements };
+            // Elements always serialize to JSON, the copy only stays internal if they don't.
+            let json = copied.to_json().unwrap_or_default();
+            let mut clipboard = world.resource_mut::<Clipboard>();
+            clipboard.contents = Some(copied);
+            clipboard.pastes = 0;
+            world.write_message(SelectionCopied {
+                count: ids.len(),
+                json,
+            });
+
+            if cut {
+                let edits = ids
+                    .into_iter()
+                    .map(|id| Box::new(RemoveElement::new(PersistentId(id))) as Box<dyn Edit>)
+                    .collect();
+                History::record(world, EditGroup::new("Cut", edits));
+            }
+        });
+    }
+}
+
+/// Pastes the elements of each [`PasteElements`] request onto its layer.
+#[bevy_system]
+pub(crate) fn paste_elements(
+    mut commands: Commands,
+    mut requests: MessageReader<PasteElements>,
+    mut failed: MessageWriter<PasteFailed>,
+    mut clipboard: ResMut<Clipboard>,
+    settings: Res<ClipboardSettings>,
+    layers: Query<(&Layer, &PersistentId)>,
+) {
+    for request in requests.read() {
+        let layer = request.layer;
+        let layer_id = match layers.get(layer) {
+            Ok((Layer { locked: true, .. }, _)) => {
+                failed.write(PasteFailed {
+                    layer,
+                    error: PasteError::Locked,
+                });
+                continue;
+            }
+            Ok((_, id)) => *id,
+            Err(_) => {
+                failed.write(PasteFailed {
+                    layer,
+                    error: PasteError::NotALayer(layer),
+                });
+                continue;
+            }
+        };
+
+        let copied = match &request.json {
+            Some(json) => CopiedElements::from_json(json).map_err(PasteError::from),
+            None => clipboard.contents.clone().ok_or(PasteError::Empty),
+        };
+        let copied = match copied {
+            Ok(copied) => copied,
+            Err(error) => {
+                failed.write(PasteFailed { layer, error });
+                continue;
+            }
+        };
+
+        #[allow(
+            clippy::cast_precision_loss,
+            reason = "the offset only needs to grow with each paste"
+        )]
+        let center = request.position.unwrap_or_else(|| {
+            clipboard.pastes += 1;
+            copied.origin + settings.paste_offset * clipboard.pastes as f32
+        });
+        // Every paste gets new ids, so the same elements can be pasted again.
+        let elements: Vec<_> = copied
+            .elements
+            .into_iter()
+            .map(|mut element| {
+                element.id = Uuid::new_v4();
+                element.transform.translation += center.extend(0.0);
+                element
+            })
+            .collect();
+        let ids: HashSet<_> = elements
+            .iter()
+            .map(|element| PersistentId(element.id))
+            .collect();
+        let edits = elements
+            .into_iter()
+            .map(|element| Box::new(PlaceElement::new(layer_id, element)) as Box<dyn Edit>)
+            .collect();
+
+        commands.queue(move |world: &mut World| {
+            if !History::record(world, EditGroup::new("Paste", edits)) {
+                world.write_message(PasteFailed {
+                    layer,
+                    error: PasteError::NotALayer(layer),
+                });
+                return;
+            }
+
+            let elements = world
+                .get::<Children>(layer)
+                .into_iter()
+                .flat_map(RelationshipTarget::iter)
+                .filter(|child| {
+                    world
+                        .get::<PersistentId>(*child)
+                        .is_some_and(|id| ids.contains(id))
+                })
+                .collect();
+            world.write_message(ElementsPasted { layer, elements });
+        });
+    }
+}
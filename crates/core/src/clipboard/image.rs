@@ -0,0 +1,173 @@
+//! Pastes images from the clipboard into a layer.
+//!
+//! Reading the system clipboard is left to the user interface, which hands the image over as a
+//! [`PasteImage`] request. The image is written into the project's assets directory, named after
+//! a hash of its pixels so pasting the same image twice reuses the file, and placed on the layer
+//! through a [`PlaceElement`] edit once written, so it can be undone.
+
+use crate::clipboard::{PasteError, PasteFailed};
+use crate::{ElementData, History, PlaceElement};
+use bevy::asset::uuid::Uuid;
+use bevy::prelude::*;
+use dungeonrs_data::{Layer, PersistentId};
+use dungeonrs_utils::{AsyncCommandsExt, report_progress};
+use image::{ImageError, ImageFormat, RgbaImage};
+use std::fs::{create_dir_all, remove_file, rename};
+use std::path::{Path, PathBuf};
+use xxhash_rust::xxh3::Xxh3;
+
+/// Requests pasting an image as a new element.
+#[derive(Message, Debug, Clone)]
+pub struct PasteImage {
+    /// The pasted image.
+    pub image: RgbaImage,
+    /// The layer the element is placed on.
+    pub layer: Entity,
+    /// The position of the element's center, in world units.
+    pub position: Vec2,
+    /// The directory the image is written to, usually the assets directory of the project.
+    pub directory: PathBuf,
+}
+
+/// Written once a pasted image was placed.
+///
+/// Undoing the paste removes the element, the image file is left in place since other elements
+/// may show it.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct ImagePasted {
+    /// The layer the element was placed on.
+    pub layer: Entity,
+    /// The element showing the image.
+    pub element: Entity,
+    /// The file the image was written to.
+    pub path: PathBuf,
+}
+
+/// Writes the image of each [`PasteImage`] request in the background and places it once written.
+pub(crate) fn paste_images(
+    mut commands: Commands,
+    mut requests: MessageReader<PasteImage>,
+    mut failed: MessageWriter<PasteFailed>,
+    layers: Query<&Layer>,
+) {
+    for request in requests.read() {
+        let layer = request.layer;
+        match layers.get(layer) {
+            Ok(Layer { locked: true, .. }) => {
+                failed.write(PasteFailed {
+                    layer,
+                    error: PasteError::Locked,
+                });
+                continue;
+            }
+            Ok(_) => {}
+            Err(_) => {
+                failed.write(PasteFailed {
+                    layer,
+                    error: PasteError::NotALayer(layer),
+                });
+                continue;
+            }
+        }
+
+        let request = request.clone();
+        commands.spawn_async(move |context| async move {
+            let path = request
+                .directory
+                .join(format!("pasted-{:016x}.png", hash_image(&request.image)));
+            // Images are only ever renamed into place once fully written, so an existing file
+            // is a complete copy of the same image.
+            let result = if path.exists() {
+                Ok(())
+            } else {
+                write_image(&request.image, &path)
+            };
+            drop(request.image);
+
+            if let Err(error) = result {
+                report_progress(
+                    &context,
+                    PasteFailed {
+                        layer,
+                        error: error.into(),
+                    },
+                );
+                return;
+            }
+
+            let position = request.position.extend(0.0);
+            context.queue(move |world: &mut World| {
+                // The layer may have been removed while the image was written.
+                let layer_id = world
+                    .get::<PersistentId>(layer)
+                    .copied()
+                    .filter(|_| world.get::<Layer>(layer).is_some());
+                let Some(layer_id) = layer_id else {
+                    world.write_message(PasteFailed {
+                        layer,
+                        error: PasteError::NotALayer(layer),
+                    });
+                    return;
+                };
+
+                let element = ElementData {
+                    id: Uuid::new_v4(),
+                    asset: path.clone(),
+                    transform: Transform::from_translation(position),
+                    animation: None,
+                };
+                let element_id = PersistentId(element.id);
+                if !History::record(world, PlaceElement::new(layer_id, element)) {
+                    return;
+                }
+
+                let placed = world
+                    .get::<Children>(layer)
+                    .into_iter()
+                    .flat_map(RelationshipTarget::iter)
+                    .find(|child| world.get::<PersistentId>(*child) == Some(&element_id));
+                if let Some(element) = placed {
+                    world.write_message(ImagePasted {
+                        layer,
+                        element,
+                        path,
+                    });
+                }
+            });
+        });
+    }
+}
+
+/// Writes `image` as a PNG to `path`.
+///
+/// The image is written next to `path` first and renamed into place, so a crash while writing
+/// never leaves a partial image that later pastes would reuse. The partial file is removed when
+/// writing fails.
+///
+/// # Errors
+/// Returns an error if the image can't be encoded or written.
+fn write_image(image: &RgbaImage, path: &Path) -> Result<(), ImageError> {
+    if let Some(directory) = path.parent() {
+        create_dir_all(directory)?;
+    }
+
+    let partial = path.with_extension("partial");
+    let result = image
+        .save_with_format(&partial, ImageFormat::Png)
+        .and_then(|()| Ok(rename(&partial, path)?));
+    if result.is_err() {
+        let _ = remove_file(&partial);
+    }
+
+    result
+}
+
+/// Hashes the dimensions and pixels of `image`.
+fn hash_image(image: &RgbaImage) -> u64 {
+    let mut hasher = Xxh3::new();
+    hasher.update(&image.width().to_le_bytes());
+    hasher.update(&image.height().to_le_bytes());
+    hasher.update(image.as_raw());
+
+    hasher.digest()
+}
@@ -0,0 +1,104 @@
+//! Copies and pastes the selected elements, and pastes images from the system clipboard.
+//!
+//! Reading and writing the system clipboard is left to the user interface, which hands pasted
+//! images and text over as [`PasteImage`] and [`PasteElements`] requests, and puts the text of
+//! each [`SelectionCopied`] on the system clipboard.
+
+mod elements;
+mod image;
+
+pub use elements::{
+    Clipboard, ClipboardSettings, CopiedElements, CopySelection, ElementsPasted, PasteElements,
+    SelectionCopied,
+};
+pub use image::{ImagePasted, PasteImage};
+
+use crate::{Action, ActionTriggered};
+use bevy::prelude::*;
+use dungeonrs_macros::bevy_system;
+use thiserror::Error;
+
+/// Registers the messages and systems that copy and paste elements and paste images.
+///
+/// Requires the [`UtilsPlugin`](dungeonrs_utils::UtilsPlugin), which runs the background task
+/// writing pasted images, the [`SelectionPlugin`](crate::SelectionPlugin) for the elements to
+/// copy, and the [`HistoryPlugin`](crate::HistoryPlugin) to undo the pastes.
+pub struct ClipboardPlugin;
+
+impl Plugin for ClipboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Clipboard>()
+            .init_resource::<ClipboardSettings>()
+            .add_message::<PasteImage>()
+            .add_message::<ImagePasted>()
+            .add_message::<CopySelection>()
+            .add_message::<SelectionCopied>()
+            .add_message::<PasteElements>()
+            .add_message::<ElementsPasted>()
+            .add_message::<PasteFailed>()
+            .add_message::<ActionTriggered>()
+            .add_systems(
+                Update,
+                (
+                    image::paste_images,
+                    (
+                        clipboard_shortcuts,
+                        elements::copy_selection,
+                        elements::paste_elements,
+                    )
+                        .chain(),
+                ),
+            );
+    }
+}
+
+/// Errors that can occur while pasting an image or elements.
+#[derive(Error, Debug)]
+pub enum PasteError {
+    /// The layer doesn't exist (anymore).
+    #[error("{0} is not a layer")]
+    NotALayer(Entity),
+    /// The layer is locked.
+    #[error("the layer is locked")]
+    Locked,
+    /// The image couldn't be encoded or written.
+    #[error("failed to write the pasted image: {0}")]
+    Image(#[from] ::image::ImageError),
+    /// Nothing was copied yet.
+    #[error("the clipboard is empty")]
+    Empty,
+    /// The pasted text isn't a copy of elements.
+    #[error("the clipboard doesn't contain elements: {0}")]
+    Contents(#[from] dungeonrs_serialization::Error),
+}
+
+/// Written when an image or elements couldn't be pasted.
+#[derive(Message, Debug)]
+pub struct PasteFailed {
+    /// The layer the image or elements were pasted into.
+    pub layer: Entity,
+    /// The reason the paste failed.
+    pub error: PasteError,
+}
+
+/// Writes a [`CopySelection`] for the triggered [`Action::Copy`] and [`Action::Cut`].
+///
+/// Pasting needs the layer to paste onto and the contents of the system clipboard, so
+/// [`Action::Paste`] is left to the user interface.
+#[bevy_system]
+fn clipboard_shortcuts(
+    mut actions: MessageReader<ActionTriggered>,
+    mut copies: MessageWriter<CopySelection>,
+) {
+    for triggered in actions.read() {
+        match triggered.action {
+            Action::Copy => {
+                copies.write(CopySelection { cut: false });
+            }
+            Action::Cut => {
+                copies.write(CopySelection { cut: true });
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,405 @@
+//! Contains the [`Configuration`], the preferences of the user kept across sessions.
+//!
+//! The settings dialog edits the configuration through its setters, each change is saved and
+//! reported as a [`ConfigurationChanged`] so the plugins depending on a setting apply it live.
+
+use crate::{Action, DockLayout, ExportPreset, History, KeyBinding, Keybindings};
+use bevy::prelude::*;
+use dungeonrs_macros::bevy_system;
+use dungeonrs_serialization::{Error, Format, deserialize, serialize};
+use dungeonrs_utils::Directory;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read, write};
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The time between two autosaves when the configuration doesn't set it, in seconds.
+const DEFAULT_AUTOSAVE_INTERVAL: u64 = 5 * 60;
+
+/// Reads the [`Configuration`] when the app starts, and reports and saves it whenever it changes.
+pub struct ConfigurationPlugin;
+
+impl Plugin for ConfigurationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Configuration>()
+            .add_message::<ConfigurationChanged>()
+            .add_systems(Startup, load_configuration)
+            .add_systems(
+                Last,
+                (report_configuration_changes, save_configuration).chain(),
+            );
+    }
+}
+
+/// A group of settings of the [`Configuration`], changed together.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ConfigurationSetting {
+    /// The language of the user interface.
+    Language,
+    /// The theme of the user interface.
+    Theme,
+    /// Whether and how often the open project is autosaved.
+    Autosave,
+    /// The number of edits that can be undone.
+    History,
+    /// The export presets.
+    ExportPresets,
+    /// The keyboard shortcuts.
+    Keybindings,
+    /// The arrangement of the panels.
+    Layout,
+}
+
+impl ConfigurationSetting {
+    /// Every setting, in the order they're shown in the settings dialog.
+    pub const ALL: [Self; 7] = [
+        Self::Language,
+        Self::Theme,
+        Self::Autosave,
+        Self::History,
+        Self::ExportPresets,
+        Self::Keybindings,
+        Self::Layout,
+    ];
+}
+
+/// Written once a [`ConfigurationSetting`] of the [`Configuration`] changed, including when it's read at
+/// startup.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ConfigurationChanged {
+    /// The changed setting.
+    pub setting: ConfigurationSetting,
+}
+
+/// The contents of the file the configuration is saved to.
+#[derive(Debug, Serialize, Deserialize)]
+struct ConfigurationFile {
+    /// The language of the user interface, such as `en-US`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    /// The name of the theme of the user interface.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    theme: Option<String>,
+    /// The time between two autosaves in seconds, `0` to disable autosaving.
+    #[serde(default = "default_autosave_interval")]
+    autosave_interval: u64,
+    /// The number of edits that can be undone.
+    #[serde(default = "default_history_depth")]
+    history_depth: usize,
+    /// The saved export presets.
+    #[serde(default)]
+    export_presets: Vec<ExportPreset>,
+    /// The shortcuts of each action.
+    #[serde(default)]
+    keybindings: BTreeMap<Action, Vec<KeyBinding>>,
+    /// The arrangement of the panels.
+    #[serde(default)]
+    layout: DockLayout,
+}
+
+/// The preferences of the user, such as their language, export presets and keyboard shortcuts.
+///
+/// They're read from [`Configuration::file`] when the app starts and saved to it whenever they
+/// change. Every change is reported as a [`ConfigurationChanged`].
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Configuration {
+    /// The file the configuration is saved to.
+    pub file: PathBuf,
+    /// The language of the user interface, the system's language when `None`.
+    language: Option<String>,
+    /// The name of the theme of the user interface, the default theme when `None`.
+    theme: Option<String>,
+    /// The time between two autosaves, `None` when autosaving is disabled.
+    autosave_interval: Option<Duration>,
+    /// The number of edits that can be undone.
+    history_depth: usize,
+    /// The export presets, in the order they were saved.
+    export_presets: Vec<ExportPreset>,
+    /// The keyboard shortcuts of each action.
+    keybindings: Keybindings,
+    /// The arrangement of the panels.
+    layout: DockLayout,
+    /// Whether the configuration changed since it was last read or saved.
+    unsaved: bool,
+    /// The settings changed since they were last reported.
+    changed: Vec<ConfigurationSetting>,
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Self::new(Directory::Config.join("config.toml"))
+    }
+}
+
+impl Configuration {
+    /// Saves the configuration to `file` instead of the default location.
+    ///
+    /// Insert it before adding the [`ConfigurationPlugin`].
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: file.into(),
+            language: None,
+            theme: None,
+            autosave_interval: Some(Duration::from_secs(DEFAULT_AUTOSAVE_INTERVAL)),
+            history_depth: History::DEFAULT_DEPTH,
+            export_presets: Vec::new(),
+            keybindings: Keybindings::default(),
+            layout: DockLayout::default(),
+            unsaved: false,
+            changed: Vec::new(),
+        }
+    }
+
+    /// The language of the user interface, such as `en-US`, or `None` for the system's language.
+    #[must_use]
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Sets the language of the user interface, `None` for the system's language.
+    pub fn set_language(&mut self, language: Option<String>) {
+        if self.language != language {
+            self.language = language;
+            self.mark_changed(ConfigurationSetting::Language);
+        }
+    }
+
+    /// The name of the theme of the user interface, or `None` for the default theme.
+    #[must_use]
+    pub fn theme(&self) -> Option<&str> {
+        self.theme.as_deref()
+    }
+
+    /// Sets the theme of the user interface by name, `None` for the default theme.
+    pub fn set_theme(&mut self, theme: Option<String>) {
+        if self.theme != theme {
+            self.theme = theme;
+            self.mark_changed(ConfigurationSetting::Theme);
+        }
+    }
+
+    /// The time between two autosaves, or `None` when autosaving is disabled.
+    #[must_use]
+    pub fn autosave_interval(&self) -> Option<Duration> {
+        self.autosave_interval
+    }
+
+    /// Sets the time between two autosaves, `None` to disable autosaving.
+    ///
+    /// Intervals are saved in whole seconds, a zero interval disables autosaving.
+    pub fn set_autosave_interval(&mut self, interval: Option<Duration>) {
+        let interval = interval
+            .map(|interval| Duration::from_secs(interval.as_secs()))
+            .filter(|interval| !interval.is_zero());
+        if self.autosave_interval != interval {
+            self.autosave_interval = interval;
+            self.mark_changed(ConfigurationSetting::Autosave);
+        }
+    }
+
+    /// The number of edits that can be undone.
+    #[must_use]
+    pub fn history_depth(&self) -> usize {
+        self.history_depth
+    }
+
+    /// Sets the number of edits that can be undone, `0` to keep none.
+    pub fn set_history_depth(&mut self, depth: usize) {
+        if self.history_depth != depth {
+            self.history_depth = depth;
+            self.mark_changed(ConfigurationSetting::History);
+        }
+    }
+
+    /// Iterates over the export presets, in the order they were saved.
+    pub fn export_presets(&self) -> impl Iterator<Item = &ExportPreset> {
+        self.export_presets.iter()
+    }
+
+    /// Returns the export preset named `name`.
+    #[must_use]
+    pub fn export_preset(&self, name: &str) -> Option<&ExportPreset> {
+        self.export_presets
+            .iter()
+            .find(|preset| preset.name == name)
+    }
+
+    /// Saves `preset`, returning the preset it replaced if one had the same name.
+    ///
+    /// A replaced preset keeps its position among the presets.
+    pub fn save_export_preset(&mut self, preset: ExportPreset) -> Option<ExportPreset> {
+        self.mark_changed(ConfigurationSetting::ExportPresets);
+        if let Some(existing) = self
+            .export_presets
+            .iter_mut()
+            .find(|existing| existing.name == preset.name)
+        {
+            return Some(std::mem::replace(existing, preset));
+        }
+
+        self.export_presets.push(preset);
+        None
+    }
+
+    /// Removes the export preset named `name`, returning it if it existed.
+    pub fn remove_export_preset(&mut self, name: &str) -> Option<ExportPreset> {
+        let index = self
+            .export_presets
+            .iter()
+            .position(|preset| preset.name == name)?;
+        self.mark_changed(ConfigurationSetting::ExportPresets);
+
+        Some(self.export_presets.remove(index))
+    }
+
+    /// The keyboard shortcuts of each action.
+    #[must_use]
+    pub fn keybindings(&self) -> &Keybindings {
+        &self.keybindings
+    }
+
+    /// Replaces the keyboard shortcuts.
+    pub fn set_keybindings(&mut self, keybindings: Keybindings) {
+        if self.keybindings != keybindings {
+            self.keybindings = keybindings;
+            self.mark_changed(ConfigurationSetting::Keybindings);
+        }
+    }
+
+    /// The arrangement of the panels.
+    #[must_use]
+    pub fn layout(&self) -> &DockLayout {
+        &self.layout
+    }
+
+    /// Replaces the arrangement of the panels.
+    pub fn set_layout(&mut self, layout: DockLayout) {
+        if self.layout != layout {
+            self.layout = layout;
+            self.mark_changed(ConfigurationSetting::Layout);
+        }
+    }
+
+    /// Returns whether the configuration changed since it was last read or saved.
+    #[must_use]
+    pub fn is_unsaved(&self) -> bool {
+        self.unsaved
+    }
+
+    /// Reads the configuration from [`Configuration::file`], replacing the current one.
+    ///
+    /// Every setting is reported as changed, since any of them may differ from the replaced
+    /// configuration.
+    ///
+    /// # Errors
+    /// Returns an error if the file exists but can't be read or isn't valid.
+    pub fn reload(&mut self) -> Result<(), Error> {
+        *self = Self {
+            file: std::mem::take(&mut self.file),
+            changed: ConfigurationSetting::ALL.to_vec(),
+            ..Self::new(PathBuf::new())
+        };
+        let bytes = match read(&self.file) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error.into()),
+        };
+
+        let file: ConfigurationFile = deserialize(&bytes, Format::Toml)?;
+        self.language = file.language;
+        self.theme = file.theme;
+        self.autosave_interval = Some(Duration::from_secs(file.autosave_interval))
+            .filter(|interval| !interval.is_zero());
+        self.history_depth = file.history_depth;
+        self.export_presets = file.export_presets;
+        self.keybindings = Keybindings::from_saved(file.keybindings);
+        self.layout = file.layout;
+        Ok(())
+    }
+
+    /// Writes the configuration to [`Configuration::file`].
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save(&mut self) -> Result<(), Error> {
+        let bytes = serialize(
+            &ConfigurationFile {
+                language: self.language.clone(),
+                theme: self.theme.clone(),
+                autosave_interval: self
+                    .autosave_interval
+                    .map_or(0, |interval| interval.as_secs()),
+                history_depth: self.history_depth,
+                export_presets: self.export_presets.clone(),
+                keybindings: self.keybindings.to_saved(),
+                layout: self.layout.clone(),
+            },
+            Format::Toml,
+        )?;
+        if let Some(parent) = self.file.parent() {
+            create_dir_all(parent)?;
+        }
+
+        write(&self.file, bytes)?;
+        self.unsaved = false;
+        Ok(())
+    }
+
+    /// Flags `setting` to be saved and reported as changed.
+    fn mark_changed(&mut self, setting: ConfigurationSetting) {
+        self.unsaved = true;
+        if !self.changed.contains(&setting) {
+            self.changed.push(setting);
+        }
+    }
+}
+
+/// The time between two autosaves of configurations that don't set it, in seconds.
+fn default_autosave_interval() -> u64 {
+    DEFAULT_AUTOSAVE_INTERVAL
+}
+
+/// The number of edits that can be undone in configurations that don't set it.
+fn default_history_depth() -> usize {
+    History::DEFAULT_DEPTH
+}
+
+/// Reads the configuration saved by previous sessions.
+#[bevy_system]
+fn load_configuration(mut configuration: ResMut<Configuration>) {
+    // An unreadable configuration is replaced by the defaults, reload clears it before reading.
+    let _ = configuration.reload();
+}
+
+/// Writes a [`ConfigurationChanged`] for each setting changed since the last frame.
+#[bevy_system]
+fn report_configuration_changes(
+    mut configuration: ResMut<Configuration>,
+    mut changes: MessageWriter<ConfigurationChanged>,
+) {
+    if configuration.changed.is_empty() {
+        return;
+    }
+
+    // Reporting the changes doesn't change the configuration itself.
+    let settings = std::mem::take(&mut configuration.bypass_change_detection().changed);
+    changes.write_batch(
+        settings
+            .into_iter()
+            .map(|setting| ConfigurationChanged { setting }),
+    );
+}
+
+/// Saves the configuration once it changed.
+#[bevy_system]
+fn save_configuration(mut configuration: ResMut<Configuration>) {
+    if !configuration.is_unsaved() {
+        return;
+    }
+
+    // A configuration that fails to save stays unsaved, so saving it is attempted again in the
+    // next frame.
+    let _ = configuration.bypass_change_detection().save();
+}
@@ -0,0 +1,281 @@
+//! Collects diagnostics about the editor for the debug overlay, only built with the `dev`
+//! feature.
+//!
+//! Pressing F1 opens the overlay. Each [`DebugSection`] can be toggled separately from its menu,
+//! statistics are only collected for the sections that are shown.
+
+use crate::{Action, ActionTriggered};
+use bevy::platform::collections::HashSet;
+use bevy::prelude::*;
+use dungeonrs_assets::{HandleCache, TextureCache, TextureMemory};
+use dungeonrs_data::HierarchySnapshot;
+use dungeonrs_macros::bevy_system;
+use dungeonrs_utils::AsyncTask;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// The number of frames shown in the frame time graph.
+const FRAME_HISTORY: usize = 240;
+
+/// The characters the frame time graph is drawn with, from shortest to longest frame.
+const GRAPH_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Opens the debug overlay with F1, or its rebound shortcut, and collects the statistics it shows.
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlay>()
+            .init_resource::<DebugStats>()
+            .init_resource::<HierarchySnapshot>()
+            .add_message::<ActionTriggered>()
+            .add_systems(Update, toggle_debug_overlay)
+            .add_systems(
+                Last,
+                collect_debug_stats.run_if(|overlay: Res<DebugOverlay>| overlay.open),
+            );
+    }
+}
+
+/// The sections of the debug overlay.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DebugSection {
+    /// The frame rate and a graph of recent frame times.
+    FrameTime,
+    /// The number of entities per level of the hierarchy.
+    Entities,
+    /// The number of visible sprites and the textures they're drawn with.
+    Rendering,
+    /// The memory used by cached textures and the number of cached handles.
+    Assets,
+    /// The number of running background tasks.
+    Tasks,
+}
+
+impl DebugSection {
+    /// Every section, in the order they're shown.
+    pub const ALL: [DebugSection; 5] = [
+        DebugSection::FrameTime,
+        DebugSection::Entities,
+        DebugSection::Rendering,
+        DebugSection::Assets,
+        DebugSection::Tasks,
+    ];
+}
+
+/// Whether the debug overlay is open and which of its sections are shown.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct DebugOverlay {
+    /// Whether the overlay is open, toggled with F1.
+    pub open: bool,
+    /// The sections that are shown.
+    sections: HashSet<DebugSection>,
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self {
+            open: false,
+            sections: DebugSection::ALL.into_iter().collect(),
+        }
+    }
+}
+
+impl DebugOverlay {
+    /// Returns whether `section` is shown.
+    #[must_use]
+    pub fn shows(&self, section: DebugSection) -> bool {
+        self.sections.contains(&section)
+    }
+
+    /// Shows `section` if it's hidden and hides it otherwise.
+    pub fn toggle(&mut self, section: DebugSection) {
+        if !self.sections.remove(&section) {
+            self.sections.insert(section);
+        }
+    }
+
+    /// The lines of text the overlay shows for `stats`, only including the shown sections.
+    #[must_use]
+    pub fn lines(&self, stats: &DebugStats) -> Vec<String> {
+        let mut lines = Vec::new();
+        for section in DebugSection::ALL.into_iter().filter(|s| self.shows(*s)) {
+            match section {
+                DebugSection::FrameTime => {
+                    lines.push(format!(
+                        "{:.0} FPS ({:.2} ms)",
+                        stats.fps(),
+                        stats.average_frame_time().as_secs_f64() * 1000.0
+                    ));
+                    lines.push(stats.frame_time_graph());
+                }
+                DebugSection::Entities => lines.push(format!(
+                    "{} entities: {} projects, {} levels, {} layers, {} elements, {} labels",
+                    stats.entities,
+                    stats.projects,
+                    stats.levels,
+                    stats.layers,
+                    stats.elements,
+                    stats.labels
+                )),
+                DebugSection::Rendering => lines.push(format!(
+                    "{} visible sprites, {} textures",
+                    stats.visible_sprites, stats.textures
+                )),
+                DebugSection::Assets => lines.push(format!(
+                    "texture cache: {} MiB CPU, {} MiB GPU, {} cached handles",
+                    stats.texture_memory.cpu >> 20,
+                    stats.texture_memory.gpu >> 20,
+                    stats.cached_handles
+                )),
+                DebugSection::Tasks => lines.push(format!("{} background tasks", stats.tasks)),
+            }
+        }
+
+        lines
+    }
+}
+
+/// The statistics shown in the debug overlay, collected while it's open.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct DebugStats {
+    /// The duration of the most recent frames, oldest first.
+    pub frame_times: VecDeque<Duration>,
+    /// The number of entities in the world.
+    pub entities: usize,
+    /// The number of projects.
+    pub projects: usize,
+    /// The number of levels in every project.
+    pub levels: usize,
+    /// The number of layers in every project.
+    pub layers: usize,
+    /// The number of elements in every project.
+    pub elements: usize,
+    /// The number of labels in every project.
+    pub labels: usize,
+    /// The number of sprites visible in a view.
+    pub visible_sprites: usize,
+    /// The number of distinct textures the visible sprites are drawn with, which bounds the
+    /// number of sprite batches (and so draw calls).
+    pub textures: usize,
+    /// The memory used by the textures in the [`TextureCache`].
+    pub texture_memory: TextureMemory,
+    /// The number of handles in the [`HandleCache`].
+    pub cached_handles: usize,
+    /// The number of running background tasks.
+    pub tasks: usize,
+}
+
+impl DebugStats {
+    /// The average duration of the recent frames.
+    #[must_use]
+    pub fn average_frame_time(&self) -> Duration {
+        let total: Duration = self.frame_times.iter().sum();
+        u32::try_from(self.frame_times.len())
+            .ok()
+            .filter(|count| *count > 0)
+            .map_or(Duration::ZERO, |count| total / count)
+    }
+
+    /// The average number of frames per second over the recent frames.
+    #[must_use]
+    pub fn fps(&self) -> f64 {
+        let average = self.average_frame_time().as_secs_f64();
+        if average > 0.0 { 1.0 / average } else { 0.0 }
+    }
+
+    /// Draws the recent frame times as a bar graph, scaled to the longest frame.
+    #[must_use]
+    pub fn frame_time_graph(&self) -> String {
+        let longest = self
+            .frame_times
+            .iter()
+            .max()
+            .copied()
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        self.frame_times
+            .iter()
+            .map(|frame| {
+                if longest <= 0.0 {
+                    return GRAPH_BARS[0];
+                }
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    clippy::cast_precision_loss,
+                    clippy::cast_sign_loss,
+                    reason = "the ratio is between 0 and 1, so the index is within the bars"
+                )]
+                let bar = (frame.as_secs_f64() / longest * (GRAPH_BARS.len() - 1) as f64).round()
+                    as usize;
+                GRAPH_BARS[bar.min(GRAPH_BARS.len() - 1)]
+            })
+            .collect()
+    }
+}
+
+/// Opens or closes the debug overlay when [`Action::ToggleDebugOverlay`] is triggered.
+#[bevy_system]
+fn toggle_debug_overlay(
+    mut actions: MessageReader<ActionTriggered>,
+    mut overlay: ResMut<DebugOverlay>,
+) {
+    for triggered in actions.read() {
+        if triggered.action == Action::ToggleDebugOverlay {
+            overlay.open = !overlay.open;
+        }
+    }
+}
+
+/// Collects the statistics of the shown sections.
+#[bevy_system]
+#[allow(
+    clippy::too_many_arguments,
+    reason = "every section reads its own part of the world"
+)]
+fn collect_debug_stats(
+    mut stats: ResMut<DebugStats>,
+    overlay: Res<DebugOverlay>,
+    time: Res<Time>,
+    snapshot: Res<HierarchySnapshot>,
+    texture_cache: Option<Res<TextureCache>>,
+    handle_cache: Option<Res<HandleCache>>,
+    entities: Query<()>,
+    sprites: Query<(&Sprite, &ViewVisibility)>,
+    tasks: Query<(), With<AsyncTask>>,
+) {
+    if overlay.shows(DebugSection::FrameTime) {
+        if stats.frame_times.len() == FRAME_HISTORY {
+            stats.frame_times.pop_front();
+        }
+        stats.frame_times.push_back(time.delta());
+    }
+
+    if overlay.shows(DebugSection::Entities) {
+        stats.entities = entities.iter().len();
+        stats.projects = snapshot.projects.len();
+        stats.levels = snapshot.levels().count();
+        stats.layers = snapshot.layers().count();
+        stats.elements = snapshot.elements().count();
+        stats.labels = snapshot.layers().map(|layer| layer.labels.len()).sum();
+    }
+
+    if overlay.shows(DebugSection::Rendering) {
+        let visible = sprites.iter().filter(|(_, visibility)| visibility.get());
+        stats.visible_sprites = visible.clone().count();
+        stats.textures = visible
+            .map(|(sprite, _)| sprite.image.id())
+            .collect::<HashSet<_>>()
+            .len();
+    }
+
+    if overlay.shows(DebugSection::Assets) {
+        stats.texture_memory = texture_cache.map(|cache| cache.usage()).unwrap_or_default();
+        stats.cached_handles = handle_cache.map_or(0, |cache| cache.len());
+    }
+
+    if overlay.shows(DebugSection::Tasks) {
+        stats.tasks = tasks.iter().len();
+    }
+}
@@ -0,0 +1,325 @@
+//! Arranges the panels of the editor in docks around the viewport.
+//!
+//! The [`DockLayout`] tells the user interface which [`Panel`]s each [`DockArea`] shows as tabs and
+//! how large the docks are. Panels are moved, closed and docks resized through requests, and the
+//! layout is kept in the [`Configuration`] so it's restored in the next session.
+
+use crate::Configuration;
+use bevy::prelude::*;
+use dungeonrs_macros::bevy_system;
+use serde::{Deserialize, Serialize};
+
+/// The smallest size of a dock, in logical pixels, so a dock can't be resized out of reach.
+const MIN_DOCK_SIZE: f32 = 120.0;
+
+/// Keeps the [`DockLayout`] and applies the requests rearranging it.
+///
+/// Add the [`ConfigurationPlugin`](crate::ConfigurationPlugin) to keep the layout across sessions.
+pub struct DockingPlugin;
+
+impl Plugin for DockingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DockLayout>()
+            .add_message::<MovePanel>()
+            .add_message::<ClosePanel>()
+            .add_message::<OpenPanel>()
+            .add_message::<ResizeDock>()
+            .add_message::<ResetLayout>()
+            .add_systems(
+                Update,
+                (
+                    load_layout.run_if(resource_exists_and_changed::<Configuration>),
+                    arrange_panels,
+                    store_layout.run_if(resource_changed::<DockLayout>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// A panel of the editor, shown as a tab of a dock.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Panel {
+    /// Browses and searches the asset packs.
+    AssetBrowser,
+    /// Lists the levels and layers of the project.
+    Layers,
+    /// Edits the selected elements.
+    Inspector,
+    /// Shows the log and the reported errors.
+    Console,
+    /// Shows the [`Minimap`](crate::Minimap) of the level.
+    Minimap,
+}
+
+impl Panel {
+    /// Every panel, in the order they're listed in the menus.
+    pub const ALL: [Self; 5] = [
+        Self::AssetBrowser,
+        Self::Layers,
+        Self::Inspector,
+        Self::Console,
+        Self::Minimap,
+    ];
+
+    /// The dock the panel opens in when it isn't docked anywhere.
+    #[must_use]
+    pub fn default_area(self) -> DockArea {
+        match self {
+            Self::AssetBrowser => DockArea::Left,
+            Self::Layers | Self::Inspector | Self::Minimap => DockArea::Right,
+            Self::Console => DockArea::Bottom,
+        }
+    }
+}
+
+/// Where a dock is attached around the viewport.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DockArea {
+    /// Along the left side of the viewport.
+    Left,
+    /// Along the right side of the viewport.
+    Right,
+    /// Along the bottom of the viewport.
+    Bottom,
+}
+
+impl DockArea {
+    /// Every dock area.
+    pub const ALL: [Self; 3] = [Self::Left, Self::Right, Self::Bottom];
+}
+
+/// The panels shown as tabs of a dock, and its size.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dock {
+    /// The panels docked here, in the order of their tabs. The dock is hidden when empty.
+    #[serde(default)]
+    pub panels: Vec<Panel>,
+    /// The panel whose tab is shown, the first one when `None` or no longer docked here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<Panel>,
+    /// The width of a side dock or the height of the bottom dock, in logical pixels.
+    pub size: f32,
+}
+
+impl Dock {
+    /// An empty dock of `size`.
+    fn new(size: f32) -> Self {
+        Self {
+            panels: Vec::new(),
+            active: None,
+            size,
+        }
+    }
+
+    /// The panel whose tab is shown, `None` when the dock is empty.
+    #[must_use]
+    pub fn active(&self) -> Option<Panel> {
+        self.active
+            .filter(|active| self.panels.contains(active))
+            .or_else(|| self.panels.first().copied())
+    }
+}
+
+/// The arrangement of the panels in docks around the viewport.
+///
+/// Each panel is docked at most once, panels that aren't docked anywhere are closed.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DockLayout {
+    /// The dock along the left side of the viewport.
+    left: Dock,
+    /// The dock along the right side of the viewport.
+    right: Dock,
+    /// The dock along the bottom of the viewport.
+    bottom: Dock,
+}
+
+impl Default for DockLayout {
+    /// The asset browser on the left, the layers, inspector and minimap on the right and the
+    /// console at the bottom.
+    fn default() -> Self {
+        let mut layout = Self {
+            left: Dock::new(280.0),
+            right: Dock::new(320.0),
+            bottom: Dock::new(180.0),
+        };
+        for panel in Panel::ALL {
+            layout.open(panel);
+        }
+
+        layout
+    }
+}
+
+impl DockLayout {
+    /// The dock at `area`.
+    #[must_use]
+    pub fn dock(&self, area: DockArea) -> &Dock {
+        match area {
+            DockArea::Left => &self.left,
+            DockArea::Right => &self.right,
+            DockArea::Bottom => &self.bottom,
+        }
+    }
+
+    /// The dock at `area`, mutably.
+    fn dock_mut(&mut self, area: DockArea) -> &mut Dock {
+        match area {
+            DockArea::Left => &mut self.left,
+            DockArea::Right => &mut self.right,
+            DockArea::Bottom => &mut self.bottom,
+        }
+    }
+
+    /// The dock `panel` is shown in, `None` when it's closed.
+    #[must_use]
+    pub fn area_of(&self, panel: Panel) -> Option<DockArea> {
+        DockArea::ALL
+            .into_iter()
+            .find(|area| self.dock(*area).panels.contains(&panel))
+    }
+
+    /// Whether `panel` is docked anywhere.
+    #[must_use]
+    pub fn is_open(&self, panel: Panel) -> bool {
+        self.area_of(panel).is_some()
+    }
+
+    /// Moves `panel` to the tab at `index` of the dock at `area`, after its last tab when `index`
+    /// is past it, and shows it.
+    pub fn move_panel(&mut self, panel: Panel, area: DockArea, index: usize) {
+        self.close(panel);
+        let dock = self.dock_mut(area);
+        dock.panels.insert(index.min(dock.panels.len()), panel);
+        dock.active = Some(panel);
+    }
+
+    /// Shows `panel`, opening it in its [`Panel::default_area`] when it's closed.
+    pub fn open(&mut self, panel: Panel) {
+        match self.area_of(panel) {
+            Some(area) => self.dock_mut(area).active = Some(panel),
+            None => self.move_panel(panel, panel.default_area(), usize::MAX),
+        }
+    }
+
+    /// Closes `panel`, returning whether it was open.
+    pub fn close(&mut self, panel: Panel) -> bool {
+        let Some(area) = self.area_of(panel) else {
+            return false;
+        };
+
+        let dock = self.dock_mut(area);
+        dock.panels.retain(|docked| *docked != panel);
+        if dock.active == Some(panel) {
+            dock.active = None;
+        }
+        true
+    }
+
+    /// Resizes the dock at `area` to `size` logical pixels, no smaller than the smallest dock.
+    pub fn resize(&mut self, area: DockArea, size: f32) {
+        self.dock_mut(area).size = size.max(MIN_DOCK_SIZE);
+    }
+
+    /// Drops the panels docked more than once and the sizes out of range, such as in a layout
+    /// edited by hand.
+    fn sanitized(mut self) -> Self {
+        let mut seen = Vec::new();
+        for area in DockArea::ALL {
+            let dock = self.dock_mut(area);
+            dock.panels.retain(|panel| {
+                let first = !seen.contains(panel);
+                seen.push(*panel);
+                first
+            });
+            if !dock.size.is_finite() || dock.size < MIN_DOCK_SIZE {
+                dock.size = MIN_DOCK_SIZE;
+            }
+        }
+
+        self
+    }
+}
+
+/// Moves a panel to a dock, such as when the user drags its tab there.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MovePanel {
+    /// The moved panel, opened if it was closed.
+    pub panel: Panel,
+    /// The dock the panel is moved to.
+    pub area: DockArea,
+    /// The position of its tab among the tabs of the dock, after the last tab when past it.
+    pub index: usize,
+}
+
+/// Closes a panel, such as when the user clicks the close button of its tab.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClosePanel {
+    /// The closed panel.
+    pub panel: Panel,
+}
+
+/// Shows a panel, opening it in its default dock when it's closed, such as from the panels menu.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OpenPanel {
+    /// The shown panel.
+    pub panel: Panel,
+}
+
+/// Resizes a dock, such as when the user drags its edge.
+#[derive(Message, Debug, Copy, Clone, PartialEq)]
+pub struct ResizeDock {
+    /// The resized dock.
+    pub area: DockArea,
+    /// The width of a side dock or the height of the bottom dock, in logical pixels.
+    pub size: f32,
+}
+
+/// Restores the default [`DockLayout`].
+#[derive(Message, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ResetLayout;
+
+/// Takes over the layout of the configuration read at startup.
+#[bevy_system]
+fn load_layout(configuration: Res<Configuration>, mut layout: ResMut<DockLayout>) {
+    layout.set_if_neq(configuration.layout().clone().sanitized());
+}
+
+/// Applies the requests rearranging the panels.
+#[bevy_system]
+fn arrange_panels(
+    mut layout: ResMut<DockLayout>,
+    mut moves: MessageReader<MovePanel>,
+    mut closes: MessageReader<ClosePanel>,
+    mut opens: MessageReader<OpenPanel>,
+    mut resizes: MessageReader<ResizeDock>,
+    mut resets: MessageReader<ResetLayout>,
+) {
+    if resets.read().count() > 0 {
+        layout.set_if_neq(DockLayout::default());
+    }
+    for request in moves.read() {
+        layout.move_panel(request.panel, request.area, request.index);
+    }
+    for request in closes.read() {
+        layout.close(request.panel);
+    }
+    for request in opens.read() {
+        layout.open(request.panel);
+    }
+    for request in resizes.read() {
+        layout.resize(request.area, request.size);
+    }
+}
+
+/// Stores the rearranged layout in the configuration, which saves it.
+#[bevy_system]
+fn store_layout(configuration: Option<ResMut<Configuration>>, layout: Res<DockLayout>) {
+    if let Some(mut configuration) = configuration
+        && configuration.layout() != &*layout
+    {
+        configuration.set_layout(layout.clone());
+    }
+}
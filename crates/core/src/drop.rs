@@ -0,0 +1,144 @@
+//! Routes files dropped onto the editor window to the request they stand for.
+//!
+//! Saved projects are opened, images are placed on the layer under the cursor (or imported as a
+//! reference layer when there's none) and archives or directories are offered to be installed as
+//! asset packs.
+
+use crate::{ImportReferenceImage, OpenProject, SaveFile};
+use bevy::prelude::*;
+use bevy::window::FileDragAndDrop;
+use dungeonrs_data::{Element, Layer};
+use dungeonrs_macros::bevy_system;
+use std::path::{Path, PathBuf};
+
+/// The file extensions of images that can be dropped.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+
+/// Registers the systems that handle dropped files.
+///
+/// Dropped projects and images are handled by the [`PersistencePlugin`](crate::PersistencePlugin)
+/// and the [`LayersPlugin`](crate::LayersPlugin).
+pub struct DropPlugin;
+
+impl Plugin for DropPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DropTarget>()
+            .add_message::<FileDragAndDrop>()
+            .add_message::<OpenProject>()
+            .add_message::<ImportReferenceImage>()
+            .add_message::<InstallPackRequested>()
+            .add_systems(Update, route_dropped_files);
+    }
+}
+
+/// Where dropped files end up, kept up to date by the user interface.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct DropTarget {
+    /// The level dropped images are imported into as reference layers.
+    pub level: Option<Entity>,
+    /// The layer dropped images are placed on.
+    pub layer: Option<Entity>,
+    /// The world position under the cursor.
+    pub position: Vec2,
+    /// The size of a grid cell in dropped reference images, in pixels.
+    pub image_cell_size: f32,
+    /// The size of a grid cell in the level, in world units.
+    pub cell_size: f32,
+}
+
+impl Default for DropTarget {
+    fn default() -> Self {
+        Self {
+            level: None,
+            layer: None,
+            position: Vec2::ZERO,
+            image_cell_size: 1.0,
+            cell_size: 1.0,
+        }
+    }
+}
+
+/// Written when an archive or directory was dropped, so the user can be asked whether to install
+/// it as an asset pack.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct InstallPackRequested {
+    /// The dropped archive or directory.
+    pub path: PathBuf,
+}
+
+/// What a dropped file is treated as.
+enum DroppedFile {
+    /// A saved project.
+    Project,
+    /// An image.
+    Image,
+    /// An archive or directory of assets.
+    Pack,
+}
+
+impl DroppedFile {
+    /// Determines what the file at `path` is treated as, `None` if it isn't supported.
+    fn classify(path: &Path) -> Option<Self> {
+        if path.is_dir() {
+            return Some(Self::Pack);
+        }
+
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        if extension == SaveFile::EXTENSION {
+            Some(Self::Project)
+        } else if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            Some(Self::Image)
+        } else if extension == "zip" {
+            Some(Self::Pack)
+        } else {
+            None
+        }
+    }
+}
+
+/// Writes the request matching each dropped file, unsupported files are ignored.
+#[bevy_system]
+fn route_dropped_files(
+    mut commands: Commands,
+    mut drops: MessageReader<FileDragAndDrop>,
+    mut projects: MessageWriter<OpenProject>,
+    mut references: MessageWriter<ImportReferenceImage>,
+    mut packs: MessageWriter<InstallPackRequested>,
+    target: Res<DropTarget>,
+    layers: Query<&Layer>,
+) {
+    for drop in drops.read() {
+        let FileDragAndDrop::DroppedFile { path_buf: path, .. } = drop else {
+            continue;
+        };
+
+        match DroppedFile::classify(path) {
+            Some(DroppedFile::Project) => {
+                projects.write(OpenProject::new(path.clone()));
+            }
+            Some(DroppedFile::Image) => {
+                let layer = target
+                    .layer
+                    .filter(|layer| layers.get(*layer).is_ok_and(|layer| !layer.locked));
+                if let Some(layer) = layer {
+                    commands.spawn((
+                        Element::new(path.clone()),
+                        Transform::from_translation(target.position.extend(0.0)),
+                        ChildOf(layer),
+                    ));
+                } else if let Some(level) = target.level {
+                    references.write(ImportReferenceImage::new(
+                        path.clone(),
+                        level,
+                        target.image_cell_size,
+                        target.cell_size,
+                    ));
+                }
+            }
+            Some(DroppedFile::Pack) => {
+                packs.write(InstallPackRequested { path: path.clone() });
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Duplicates the selected elements along a line or a grid, such as a row of pillars or torches.
+//!
+//! Each [`DuplicateSelection`] places the copies next to their original, on the same layer, and
+//! records them in the [`History`] as a single edit.
+
+use crate::{Edit, EditGroup, ElementData, History, PlaceElement, Selection};
+use bevy::asset::uuid::Uuid;
+use bevy::platform::collections::HashSet;
+use bevy::prelude::*;
+use dungeonrs_data::{HierarchySnapshot, PersistentId};
+use dungeonrs_macros::bevy_system;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Registers the messages and systems that duplicate elements.
+///
+/// Requires the [`SelectionPlugin`](crate::SelectionPlugin) for the elements to duplicate, and
+/// the [`HistoryPlugin`](crate::HistoryPlugin) to undo the duplicates.
+pub struct DuplicatePlugin;
+
+impl Plugin for DuplicatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<DuplicateSelection>()
+            .add_message::<SelectionDuplicated>()
+            .add_systems(Update, duplicate_selection);
+    }
+}
+
+/// Where the copies of an element are placed, relative to the element.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DuplicatePattern {
+    /// A row of copies, each `spacing` further from the previous one.
+    Line {
+        /// The number of copies, not counting the original.
+        count: u32,
+        /// The offset between two neighbouring copies, in world units.
+        spacing: Vec2,
+    },
+    /// A grid of copies, the original taking the first cell.
+    Grid {
+        /// The number of columns of the grid.
+        columns: u32,
+        /// The number of rows of the grid.
+        rows: u32,
+        /// The offset between two neighbouring columns and rows, in world units.
+        spacing: Vec2,
+    },
+}
+
+impl DuplicatePattern {
+    /// The offsets of the copies from their original, in world units.
+    #[must_use]
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "patterns are far smaller than the precision of f32"
+    )]
+    pub fn offsets(&self) -> Vec<Vec2> {
+        match *self {
+            Self::Line { count, spacing } => {
+                (1..=count).map(|index| spacing * index as f32).collect()
+            }
+            Self::Grid {
+                columns,
+                rows,
+                spacing,
+            } => (0..rows)
+                .flat_map(|row| (0..columns).map(move |column| (column, row)))
+                .skip(1)
+                .map(|(column, row)| spacing * Vec2::new(column as f32, row as f32))
+                .collect(),
+        }
+    }
+}
+
+/// Requests duplicating the selected elements.
+#[derive(Message, Debug, Copy, Clone, PartialEq)]
+pub struct DuplicateSelection {
+    /// Where the copies are placed.
+    pub pattern: DuplicatePattern,
+    /// The most each copy is rotated away from its original, in radians, so rows of props don't
+    /// look too regular.
+    pub rotation_jitter: f32,
+    /// Picks the rotation of each copy, the same seed rotating the copies the same way.
+    pub seed: u64,
+}
+
+impl DuplicateSelection {
+    /// Duplicates the selected elements in `pattern`, without rotating the copies.
+    #[must_use]
+    pub fn new(pattern: DuplicatePattern) -> Self {
+        Self {
+            pattern,
+            rotation_jitter: 0.0,
+            seed: 0,
+        }
+    }
+
+    /// Rotates each copy by up to `rotation_jitter` radians either way.
+    #[must_use]
+    pub fn with_rotation_jitter(mut self, rotation_jitter: f32) -> Self {
+        self.rotation_jitter = rotation_jitter;
+        self
+    }
+
+    /// Picks the rotations of the copies with `seed`.
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The rotation of the `copy`th copy of the `element`th selected element, in radians.
+    fn jitter(&self, element: usize, copy: usize) -> f32 {
+        if self.rotation_jitter == 0.0 {
+            return 0.0;
+        }
+
+        let mut bytes = self.seed.to_le_bytes().to_vec();
+        bytes.extend(element.to_le_bytes());
+        bytes.extend(copy.to_le_bytes());
+        #[allow(
+            clippy::cast_precision_loss,
+            reason = "the rotation only needs to look random"
+        )]
+        let unit = (xxh3_64(&bytes) >> 40) as f32 / (1u64 << 24) as f32;
+
+        (unit * 2.0 - 1.0) * self.rotation_jitter
+    }
+}
+
+/// Written once the selected elements were duplicated.
+///
+/// The duplicates are recorded in the [`History`] as a single edit.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct SelectionDuplicated {
+    /// The copies, in drawing order.
+    pub copies: Vec<Entity>,
+}
+
+/// Places the copies of the selected elements of each [`DuplicateSelection`] request.
+#[bevy_system]
+fn duplicate_selection(
+    mut commands: Commands,
+    mut requests: MessageReader<DuplicateSelection>,
+    selection: Res<Selection>,
+    snapshot: Res<HierarchySnapshot>,
+) {
+    for request in requests.read() {
+        // The copies are placed in drawing order, above every element of their layer.
+        let selected: Vec<_> = snapshot
+            .elements()
+            .filter(|entity| selection.contains(*entity))
+            .collect();
+        let offsets = request.pattern.offsets();
+        if selected.is_empty() || offsets.is_empty() {
+            continue;
+        }
+
+        let request = *request;
+        commands.queue(move |world: &mut World| {
+            let mut edits: Vec<Box<dyn Edit>> = Vec::new();
+            let mut ids = HashSet::new();
+            let mut layers = Vec::new();
+            for (index, element) in selected.into_iter().enumerate() {
+                let Some(parent) = world.get::<ChildOf>(element).map(ChildOf::parent) else {
+                    continue;
+                };
+                let (Some(data), Some(layer)) = (
+                    ElementData::capture(world, element),
+                    world.get::<PersistentId>(parent).copied(),
+                ) else {
+                    continue;
+                };
+                if !layers.contains(&parent) {
+                    layers.push(parent);
+                }
+
+                for (copy, offset) in offsets.iter().enumerate() {
+                    let mut data = data.clone();
+                    data.id = Uuid::new_v4();
+                    data.transform.translation += offset.extend(0.0);
+                    data.transform.rotate_z(request.jitter(index, copy));
+                    ids.insert(PersistentId(data.id));
+                    edits.push(Box::new(PlaceElement::new(layer, data)));
+                }
+            }
+
+            if !History::record(world, EditGroup::new("Duplicate", edits)) {
+                return;
+            }
+
+            let copies = layers
+                .into_iter()
+                .filter_map(|layer| world.get::<Children>(layer))
+                .flat_map(RelationshipTarget::iter)
+                .filter(|child| {
+                    world
+                        .get::<PersistentId>(*child)
+                        .is_some_and(|id| ids.contains(id))
+                })
+                .collect();
+            world.write_message(SelectionDuplicated { copies });
+        });
+    }
+}
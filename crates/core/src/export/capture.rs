@@ -0,0 +1,744 @@
+//! Captures the frames of an export from the GPU.
+//!
+//! Two cameras render to their own target texture, so while the readback of one frame is in
+//! flight the other camera already renders the next one. Each camera only moves on to a new
+//! frame once its previous readback completed.
+//!
+//! When the export is split into subsets of layers, each subset is captured in turn by the same
+//! cameras, with the other layers hidden, and with the hidden regions covered when the subset is
+//! meant for the players.
+
+use crate::export::scene::{SceneContents, SceneLevels, gather_scene};
+use crate::export::{
+    CancelExport, CapturedFrame, ExportAudience, ExportCapabilities, ExportError, ExportFailed,
+    ExportLayers, ExportRegistry, ExportRequest, ExportSettings, Exporter, process,
+};
+use bevy::camera::RenderTarget;
+use bevy::prelude::*;
+use bevy::render::gpu_readback::{Readback, ReadbackComplete};
+use bevy::render::render_resource::{TextureFormat, TextureUsages};
+use bevy::render::renderer::RenderDevice;
+use dungeonrs_data::{Layer, MapProjection};
+use dungeonrs_macros::bevy_system;
+use dungeonrs_utils::{CancellationToken, Cancelled, PathError};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// The number of bytes of a single RGBA8 pixel.
+const PIXEL_SIZE: u32 = 4;
+
+/// What a capture slot is currently doing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SlotState {
+    /// Waiting for a frame to render.
+    Idle,
+    /// Rendering the frame at the given position of the output image.
+    Rendering(UVec2),
+    /// Waiting for the readback of the frame at the given position.
+    Reading(UVec2),
+}
+
+/// Marks the cameras capturing the frames of an export, so they can be told apart from the
+/// viewport cameras.
+#[derive(Component, Debug, Default)]
+pub(crate) struct ExportCamera;
+
+/// Matches the cameras showing the map in the editor, leaving out the [`ExportCamera`]s.
+pub(crate) type ViewportCamera = (With<Camera2d>, Without<ExportCamera>);
+
+/// A camera and the texture it renders the export frames to.
+struct CaptureSlot {
+    /// The camera rendering the frames.
+    camera: Entity,
+    /// The texture the camera renders to.
+    image: Handle<Image>,
+    /// What the slot is currently doing.
+    state: SlotState,
+}
+
+/// Tracks the export being captured.
+#[derive(Resource)]
+pub(crate) struct ExportCapture {
+    /// The file the export is written to.
+    path: PathBuf,
+    /// The exporter writing the export.
+    exporter: Arc<dyn Exporter>,
+    /// The settings of the exporter.
+    settings: ExportSettings,
+    /// The projection the map is exported through.
+    projection: MapProjection,
+    /// The area of the screen being exported, showing the requested area of the map through the
+    /// projection.
+    view: Rect,
+    /// The number of pixels per world unit.
+    pixels_per_unit: f32,
+    /// The size of the output image.
+    size: UVec2,
+    /// The size of a single frame.
+    frame_size: UVec2,
+    /// The positions of the frames that haven't been rendered yet.
+    pending: VecDeque<UVec2>,
+    /// The slots rendering frames, used in turns.
+    slots: [CaptureSlot; 2],
+    /// The frames captured so far.
+    frames: Vec<CapturedFrame>,
+    /// Who the subset of layers currently captured is exported for.
+    audience: ExportAudience,
+    /// The subsets of layers that are captured after the current one.
+    passes: VecDeque<ExportLayers>,
+    /// The visibility of every layer before the export, restored once it's captured.
+    ///
+    /// Empty when the export isn't split into subsets of layers.
+    visibility: Vec<(Entity, Visibility)>,
+    /// Whether the grid is captured along with the layers.
+    gridlines: bool,
+    /// Whether the export renders the map for the editor itself, see
+    /// [`ExportRequest::background`].
+    background: bool,
+    /// Cancels the processing of the subsets of layers handed off so far, see [`CancelExport`].
+    token: CancellationToken,
+}
+
+impl ExportCapture {
+    /// Who the map currently captured is exported for.
+    pub(crate) fn audience(&self) -> ExportAudience {
+        self.audience
+    }
+
+    /// Whether the grid is burned into the map currently captured.
+    pub(crate) fn gridlines(&self) -> bool {
+        self.gridlines
+    }
+
+    /// The area of the map shown by the export, which is larger than the requested area when
+    /// the map is exported through an isometric projection.
+    pub(crate) fn map_area(&self) -> Rect {
+        self.projection.unproject_rect(self.view)
+    }
+}
+
+/// The positions of the frames covering an output image of `size`.
+fn frame_positions(size: UVec2, frame_size: UVec2) -> VecDeque<UVec2> {
+    let mut positions = VecDeque::new();
+    for y in (0..size.y).step_by(frame_size.y as usize) {
+        for x in (0..size.x).step_by(frame_size.x as usize) {
+            positions.push_back(UVec2::new(x, y));
+        }
+    }
+
+    positions
+}
+
+/// Shows the layers of `pass` and hides all others.
+///
+/// Passes without layers show the layers that were visible before the export, as listed in
+/// `before`.
+fn show_pass(
+    pass: &ExportLayers,
+    before: &[(Entity, Visibility)],
+    layers: &mut Query<(Entity, &mut Visibility), With<Layer>>,
+) {
+    for (layer, mut visibility) in layers {
+        *visibility = if pass.layers.is_empty() {
+            before
+                .iter()
+                .find(|(entity, _)| *entity == layer)
+                .map_or(Visibility::Inherited, |(_, visibility)| *visibility)
+        } else if pass.layers.contains(&layer) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// The position on screen of the centre of the frame at `position` in the output image, which
+/// shows `area` of the screen.
+///
+/// The output image's rows run top to bottom, while the world's Y axis points up.
+fn frame_center(area: Rect, pixels_per_unit: f32, frame_size: UVec2, position: UVec2) -> Vec2 {
+    let center = position.as_vec2() + frame_size.as_vec2() / 2.0;
+
+    Vec2::new(
+        area.min.x + center.x / pixels_per_unit,
+        area.max.y - center.y / pixels_per_unit,
+    )
+}
+
+/// Spawns a camera rendering to a texture of `size`, scaled to `pixels_per_unit`.
+fn spawn_slot(
+    commands: &mut Commands,
+    images: &mut Assets<Image>,
+    size: UVec2,
+    pixels_per_unit: f32,
+) -> CaptureSlot {
+    let mut image = Image::new_target_texture(size.x, size.y, TextureFormat::Rgba8UnormSrgb, None);
+    image.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+    let image = images.add(image);
+
+    let camera = commands
+        .spawn((
+            ExportCamera,
+            Camera2d,
+            Camera {
+                is_active: false,
+                order: -1,
+                ..default()
+            },
+            RenderTarget::from(image.clone()),
+            Projection::Orthographic(OrthographicProjection {
+                scale: pixels_per_unit.recip(),
+                ..OrthographicProjection::default_2d()
+            }),
+        ))
+        .id();
+
+    CaptureSlot {
+        camera,
+        image,
+        state: SlotState::Idle,
+    }
+}
+
+/// The exports waiting for the running one to be captured.
+#[derive(Resource, Default)]
+pub(crate) struct ExportQueue {
+    /// The exports requested by the user, in the order they were requested.
+    requests: VecDeque<ExportRequest>,
+    /// The latest background export of each exporter, captured once no other export is queued.
+    background: VecDeque<ExportRequest>,
+}
+
+impl ExportQueue {
+    /// Queues `request`, replacing the queued background export of the same exporter.
+    fn push(&mut self, request: ExportRequest) {
+        if request.background {
+            self.background
+                .retain(|queued| queued.exporter != request.exporter);
+            self.background.push_back(request);
+        } else {
+            self.requests.push_back(request);
+        }
+    }
+
+    /// Takes the export to capture next.
+    fn pop(&mut self) -> Option<ExportRequest> {
+        self.requests
+            .pop_front()
+            .or_else(|| self.background.pop_front())
+    }
+}
+
+/// Queues the requested exports and starts capturing the frames of the next one, unless an
+/// export is already running.
+///
+/// The paths of the export are validated and given the extension of the exporter when missing,
+/// see [`ExportCapabilities::output_path`](crate::ExportCapabilities::output_path). Requests that
+/// can't be exported are reported through [`ExportFailed`] when their turn comes.
+#[bevy_system]
+#[allow(
+    clippy::too_many_arguments,
+    reason = "the export depends on the request, the exporters, the projection and the layers"
+)]
+pub(crate) fn start_export(
+    mut commands: Commands,
+    mut requests: MessageReader<ExportRequest>,
+    mut queue: ResMut<ExportQueue>,
+    mut failed: MessageWriter<ExportFailed>,
+    mut images: ResMut<Assets<Image>>,
+    registry: Res<ExportRegistry>,
+    projection: Res<MapProjection>,
+    capture: Option<Res<ExportCapture>>,
+    mut layers: Query<(Entity, &mut Visibility), With<Layer>>,
+) {
+    for request in requests.read() {
+        queue.push(request.clone());
+    }
+    if capture.is_some() {
+        return;
+    }
+
+    let (request, exporter) = loop {
+        let Some(mut request) = queue.pop() else {
+            return;
+        };
+        let exporter = registry.get(&request.exporter);
+        let error = match exporter {
+            _ if request.pixels_per_unit <= 0.0 || request.pixels_per_unit.is_nan() => {
+                ExportError::InvalidResolution(request.pixels_per_unit)
+            }
+            Some(exporter) => match validate_paths(&mut request, &exporter.capabilities()) {
+                Ok(()) => break (request, exporter),
+                Err(error) => error.into(),
+            },
+            None => ExportError::UnknownExporter(request.exporter.clone()),
+        };
+        failed.write(ExportFailed {
+            path: request.path,
+            error,
+        });
+    };
+    let mut settings = request.settings.clone();
+    settings.fill_defaults(&exporter.settings());
+
+    let view = projection.project_rect(request.area);
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "the size is positive and rounded up"
+    )]
+    let size = (view.size() * request.pixels_per_unit).ceil().as_uvec2();
+    let frame_size = request.frame_size.max(UVec2::ONE);
+
+    let mut path = request.path.clone();
+    let mut passes: VecDeque<_> = request.layers.iter().cloned().collect();
+    let mut audience = ExportAudience::Gm;
+    let mut visibility = Vec::new();
+    if let Some(pass) = passes.pop_front() {
+        visibility = layers
+            .iter()
+            .map(|(layer, visibility)| (layer, *visibility))
+            .collect();
+        show_pass(&pass, &visibility, &mut layers);
+        path = pass.path;
+        audience = pass.audience;
+    }
+
+    let slots = [
+        spawn_slot(
+            &mut commands,
+            &mut images,
+            frame_size,
+            request.pixels_per_unit,
+        ),
+        spawn_slot(
+            &mut commands,
+            &mut images,
+            frame_size,
+            request.pixels_per_unit,
+        ),
+    ];
+    commands.insert_resource(ExportCapture {
+        path,
+        exporter,
+        settings,
+        projection: *projection,
+        view,
+        pixels_per_unit: request.pixels_per_unit,
+        size,
+        frame_size,
+        pending: frame_positions(size, frame_size),
+        slots,
+        frames: Vec::new(),
+        audience,
+        passes,
+        visibility,
+        gridlines: request.gridlines,
+        background: request.background,
+        token: CancellationToken::default(),
+    });
+}
+
+/// Validates the paths `request` writes to, as written by an exporter with `capabilities`.
+///
+/// # Errors
+/// Returns an error if one of the paths isn't valid.
+fn validate_paths(
+    request: &mut ExportRequest,
+    capabilities: &ExportCapabilities,
+) -> Result<(), PathError> {
+    request.path = capabilities.output_path(&request.path)?;
+    for pass in &mut request.layers {
+        pass.path = capabilities.output_path(&pass.path)?;
+    }
+
+    Ok(())
+}
+
+/// Moves idle cameras to the next frame, stops rendering frames whose readback was requested and
+/// hands the frames off for processing once all of them were captured.
+///
+/// When there's another subset of layers to export, its capture starts once the previous one is
+/// handed off. The visibility of the layers is restored after the last one.
+pub(crate) fn advance_export(
+    mut commands: Commands,
+    capture: Option<ResMut<ExportCapture>>,
+    mut cameras: Query<(&mut Camera, &mut Transform)>,
+    mut layers: Query<(Entity, &mut Visibility), With<Layer>>,
+    contents: SceneContents,
+    levels: SceneLevels,
+) {
+    let Some(mut capture) = capture else {
+        return;
+    };
+
+    let capture = &mut *capture;
+    for slot in &mut capture.slots {
+        let Ok((mut camera, mut transform)) = cameras.get_mut(slot.camera) else {
+            continue;
+        };
+
+        match slot.state {
+            SlotState::Rendering(position) => {
+                // The frame was rendered and copied for readback during the previous update.
+                camera.is_active = false;
+                commands.entity(slot.camera).remove::<Readback>();
+                slot.state = SlotState::Reading(position);
+            }
+            SlotState::Idle => {
+                let Some(position) = capture.pending.pop_front() else {
+                    continue;
+                };
+
+                let center = frame_center(
+                    capture.view,
+                    capture.pixels_per_unit,
+                    capture.frame_size,
+                    position,
+                );
+                // The camera is turned by the projection, so it's placed where the map shows
+                // the center of the frame.
+                transform.translation = capture
+                    .projection
+                    .unproject(center)
+                    .extend(transform.translation.z);
+                camera.is_active = true;
+                commands
+                    .entity(slot.camera)
+                    .insert(Readback::texture(slot.image.clone()));
+                slot.state = SlotState::Rendering(position);
+            }
+            SlotState::Reading(_) => {}
+        }
+    }
+
+    let finished = capture.pending.is_empty()
+        && capture
+            .slots
+            .iter()
+            .all(|slot| slot.state == SlotState::Idle);
+    if !finished {
+        return;
+    }
+
+    let next = capture.passes.pop_front();
+    let path = match &next {
+        Some(pass) => std::mem::replace(&mut capture.path, pass.path.clone()),
+        None => capture.path.clone(),
+    };
+    commands.queue(process(
+        std::mem::take(&mut capture.frames),
+        capture.size,
+        capture.pixels_per_unit,
+        path,
+        // The layers of the pass were shown when its capture started, so their visibility has
+        // been propagated by now.
+        gather_scene(
+            &contents,
+            &levels,
+            capture.projection,
+            capture.view,
+            capture.pixels_per_unit,
+        ),
+        capture.exporter.clone(),
+        capture.settings.clone(),
+        capture.background,
+        capture.token.clone(),
+    ));
+
+    if let Some(pass) = next {
+        show_pass(&pass, &capture.visibility, &mut layers);
+        capture.audience = pass.audience;
+        capture.pending = frame_positions(capture.size, capture.frame_size);
+        return;
+    }
+
+    finish_capture(&mut commands, capture, &mut layers);
+}
+
+/// Cancels the running export when requested, reporting it through [`ExportFailed`].
+///
+/// The subsets of layers whose frames were already handed off stop processing at their next
+/// cancellation check, and exports that were fully captured finish writing.
+#[bevy_system]
+pub(crate) fn cancel_export(
+    mut commands: Commands,
+    mut cancels: MessageReader<CancelExport>,
+    mut failed: MessageWriter<ExportFailed>,
+    capture: Option<ResMut<ExportCapture>>,
+    mut layers: Query<(Entity, &mut Visibility), With<Layer>>,
+) {
+    if cancels.read().count() == 0 {
+        return;
+    }
+    let Some(mut capture) = capture else {
+        return;
+    };
+
+    capture.token.cancel();
+    failed.write(ExportFailed {
+        path: capture.path.clone(),
+        error: ExportError::Cancelled(Cancelled),
+    });
+    finish_capture(&mut commands, &mut capture, &mut layers);
+}
+
+/// Restores the visibility of the layers, despawns the cameras and removes the [`ExportCapture`]
+/// so the next export can start.
+fn finish_capture(
+    commands: &mut Commands,
+    capture: &mut ExportCapture,
+    layers: &mut Query<(Entity, &mut Visibility), With<Layer>>,
+) {
+    for (layer, visibility) in capture.visibility.drain(..) {
+        if let Ok((_, mut current)) = layers.get_mut(layer) {
+            *current = visibility;
+        }
+    }
+    for slot in &capture.slots {
+        commands.entity(slot.camera).despawn();
+    }
+    commands.remove_resource::<ExportCapture>();
+}
+
+/// Stores the frame read back for one of the capture cameras and frees up its slot.
+#[bevy_system]
+pub(crate) fn receive_readback(
+    readback: On<ReadbackComplete>,
+    capture: Option<ResMut<ExportCapture>>,
+) {
+    let Some(mut capture) = capture else {
+        return;
+    };
+
+    let frame_size = capture.frame_size;
+    let Some(slot) = capture
+        .slots
+        .iter_mut()
+        .find(|slot| slot.camera == readback.entity)
+    else {
+        return;
+    };
+    let (SlotState::Rendering(position) | SlotState::Reading(position)) = slot.state else {
+        return;
+    };
+    slot.state = SlotState::Idle;
+
+    let data = unpad_rows(&readback.data, frame_size);
+    capture.frames.push(CapturedFrame {
+        position,
+        size: frame_size,
+        data,
+    });
+}
+
+/// Removes the padding the GPU adds to every row of a texture copied to a buffer.
+fn unpad_rows(data: &[u8], size: UVec2) -> Vec<u8> {
+    let row_length = (size.x * PIXEL_SIZE) as usize;
+    let padded_row_length = RenderDevice::align_copy_bytes_per_row(row_length);
+    if padded_row_length == row_length {
+        return data.to_vec();
+    }
+
+    data.chunks(padded_row_length)
+        .take(size.y as usize)
+        .flat_map(|row| &row[..row_length.min(row.len())])
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    //! Queues the requested exports and reports the ones that can't be exported, and lays out and
+    //! reads back the frames they capture.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+    use crate::ImageExporter;
+    use bevy::ecs::message::MessageRegistry;
+    use bevy::ecs::system::SystemId;
+
+    /// Creates a world able to start exports, and returns it along with [`start_export`]
+    /// registered as a system, so it keeps track of the requests it read.
+    fn setup() -> (World, SystemId) {
+        let mut world = World::new();
+        MessageRegistry::register_message::<ExportRequest>(&mut world);
+        MessageRegistry::register_message::<ExportFailed>(&mut world);
+        MessageRegistry::register_message::<CancelExport>(&mut world);
+        world.init_resource::<ExportQueue>();
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<MapProjection>();
+        world.init_resource::<ExportRegistry>();
+        world
+            .resource_mut::<ExportRegistry>()
+            .register(ImageExporter);
+        let system = world.register_system(start_export);
+
+        (world, system)
+    }
+
+    /// Requests exporting to `path` at `pixels_per_unit`.
+    fn request(world: &mut World, path: &str, pixels_per_unit: f32) {
+        world.write_message(ExportRequest::new(
+            path,
+            Rect::new(0.0, 0.0, 64.0, 64.0),
+            pixels_per_unit,
+        ));
+    }
+
+    /// Runs [`start_export`] once and returns the paths of the failed exports.
+    fn start(world: &mut World, system: SystemId) -> Vec<PathBuf> {
+        world.run_system(system).expect("the system runs");
+        world
+            .resource_mut::<Messages<ExportFailed>>()
+            .drain()
+            .map(|failed| failed.path)
+            .collect()
+    }
+
+    /// The path of the running export.
+    fn running(world: &World) -> Option<PathBuf> {
+        world
+            .get_resource::<ExportCapture>()
+            .map(|capture| capture.path.clone())
+    }
+
+    /// Every request made in a frame or during an export is captured in turn, and invalid ones
+    /// are reported instead of dropped.
+    #[test]
+    fn queues_requests() {
+        let (mut world, system) = setup();
+        request(&mut world, "invalid.png", 0.0);
+        request(&mut world, "first.png", 1.0);
+        request(&mut world, "second.png", 1.0);
+        assert_eq!(start(&mut world, system), [PathBuf::from("invalid.png")]);
+        assert_eq!(running(&world), Some("first.png".into()));
+
+        request(&mut world, "third.png", 1.0);
+        assert!(start(&mut world, system).is_empty());
+        assert_eq!(running(&world), Some("first.png".into()));
+
+        world.remove_resource::<ExportCapture>();
+        start(&mut world, system);
+        assert_eq!(running(&world), Some("second.png".into()));
+
+        world.remove_resource::<ExportCapture>();
+        start(&mut world, system);
+        assert_eq!(running(&world), Some("third.png".into()));
+    }
+
+    /// Background exports wait for the exports requested by the user, and only the latest one of
+    /// each exporter is kept.
+    #[test]
+    fn queues_background_behind_requests() {
+        let (mut world, system) = setup();
+        for path in ["outdated.png", "preview.png"] {
+            world.write_message(
+                ExportRequest::new(path, Rect::new(0.0, 0.0, 64.0, 64.0), 1.0).in_background(),
+            );
+        }
+        request(&mut world, "map.png", 1.0);
+        start(&mut world, system);
+        assert_eq!(running(&world), Some("map.png".into()));
+
+        world.remove_resource::<ExportCapture>();
+        start(&mut world, system);
+        assert_eq!(running(&world), Some("preview.png".into()));
+
+        world.remove_resource::<ExportCapture>();
+        start(&mut world, system);
+        assert!(running(&world).is_none());
+    }
+
+    /// Paths without the extension of the exporter are given it, invalid paths are reported.
+    #[test]
+    fn validates_paths() {
+        let (mut world, system) = setup();
+        request(&mut world, "..", 1.0);
+        request(&mut world, "map", 1.0);
+        assert_eq!(start(&mut world, system), [PathBuf::from("..")]);
+        assert_eq!(running(&world), Some("map.png".into()));
+    }
+
+    /// Cancelling stops the running export, cancels its processing and starts the next one.
+    #[test]
+    fn cancels_the_running_export() {
+        let (mut world, system) = setup();
+        let cancel = world.register_system(cancel_export);
+        request(&mut world, "first.png", 1.0);
+        request(&mut world, "second.png", 1.0);
+        start(&mut world, system);
+        let token = world.resource::<ExportCapture>().token.clone();
+
+        world.write_message(CancelExport);
+        world.run_system(cancel).expect("the system runs");
+        assert!(token.is_cancelled());
+        assert!(running(&world).is_none());
+        assert_eq!(start(&mut world, system), [PathBuf::from("first.png")]);
+        assert_eq!(running(&world), Some("second.png".into()));
+    }
+
+    /// Requests for an exporter that isn't registered are reported.
+    #[test]
+    fn reports_unknown_exporters() {
+        let (mut world, system) = setup();
+        world.write_message(
+            ExportRequest::new("map.dd2vtt", Rect::new(0.0, 0.0, 64.0, 64.0), 1.0)
+                .with_exporter("missing", ExportSettings::default()),
+        );
+
+        assert_eq!(start(&mut world, system), [PathBuf::from("map.dd2vtt")]);
+        assert!(running(&world).is_none());
+    }
+
+    /// Frames start at every multiple of the frame size within the image, the last row and
+    /// column reaching past it when the size isn't a multiple of the frame size.
+    #[test]
+    fn lays_out_frames() {
+        assert_eq!(
+            Vec::from(frame_positions(UVec2::new(100, 130), UVec2::splat(64))),
+            [
+                UVec2::new(0, 0),
+                UVec2::new(64, 0),
+                UVec2::new(0, 64),
+                UVec2::new(64, 64),
+                UVec2::new(0, 128),
+                UVec2::new(64, 128),
+            ]
+        );
+        assert_eq!(
+            Vec::from(frame_positions(UVec2::ONE, UVec2::splat(64))),
+            [UVec2::ZERO]
+        );
+        assert_eq!(
+            Vec::from(frame_positions(UVec2::new(128, 64), UVec2::splat(64))),
+            [UVec2::new(0, 0), UVec2::new(64, 0)]
+        );
+    }
+
+    /// Rows are padded to 256 bytes by the GPU, the padding is dropped unless rows are already
+    /// aligned.
+    #[test]
+    fn removes_row_padding() {
+        // 10 pixels take 40 bytes, padded to 256.
+        let size = UVec2::new(10, 3);
+        let padded: Vec<u8> = (0..3_u8)
+            .flat_map(|row| {
+                let mut bytes = vec![row + 1; 40];
+                bytes.resize(256, 0);
+                bytes
+            })
+            .collect();
+        let data = unpad_rows(&padded, size);
+        assert_eq!(data.len(), 120);
+        assert!(data[..40].iter().all(|&byte| byte == 1));
+        assert!(data[80..].iter().all(|&byte| byte == 3));
+
+        let pixel = unpad_rows(&[7; 256], UVec2::ONE);
+        assert_eq!(pixel, [7; 4]);
+
+        let aligned: Vec<u8> = (0..=255).cycle().take(512).collect();
+        assert_eq!(unpad_rows(&aligned, UVec2::new(64, 2)), aligned);
+    }
+}
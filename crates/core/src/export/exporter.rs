@@ -0,0 +1,338 @@
+//! The [`Exporter`] trait implemented by every export target, and the registry they're looked up
+//! in.
+
+use crate::export::{
+    EncodeSettings, ExportError, ExportFormat, ExportScene, PngCompression, encode_image,
+};
+use bevy::prelude::Resource;
+use dungeonrs_utils::{PathError, validate_file_name, validate_output_path};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// What an exporter writes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExportOutput {
+    /// A single file.
+    File,
+    /// A directory of files.
+    Directory,
+}
+
+/// Describes an exporter to the user interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportCapabilities {
+    /// Whether the exporter writes a file or a directory.
+    pub output: ExportOutput,
+    /// The file extensions the exporter can write, the first is suggested to the user.
+    ///
+    /// Empty for exporters writing a directory.
+    pub extensions: &'static [&'static str],
+}
+
+impl ExportCapabilities {
+    /// Validates the user-supplied `path` to write the export to, appending the first of the
+    /// [`ExportCapabilities::extensions`] when the file has none of them.
+    ///
+    /// # Errors
+    /// Returns an error if the file name of `path` isn't valid on this platform.
+    pub fn output_path(&self, path: &Path) -> Result<PathBuf, PathError> {
+        let has_extension = path
+            .extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|current| {
+                self.extensions
+                    .iter()
+                    .any(|extension| extension.eq_ignore_ascii_case(current))
+            });
+
+        match self.extensions.first() {
+            Some(extension) if self.output == ExportOutput::File && !has_extension => {
+                validate_output_path(path, extension)
+            }
+            _ => {
+                let file_name = path.file_name().ok_or(PathError::Empty)?;
+                validate_file_name(&file_name.to_string_lossy())?;
+                Ok(path.to_path_buf())
+            }
+        }
+    }
+}
+
+/// The type and default value of an [`ExportSetting`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportSettingKind {
+    /// A checkbox.
+    Bool(bool),
+    /// A number between a minimum and a maximum.
+    Number {
+        /// The default value.
+        default: f64,
+        /// The smallest accepted value.
+        min: f64,
+        /// The largest accepted value.
+        max: f64,
+    },
+    /// Free text.
+    Text(String),
+    /// One of a fixed set of values.
+    Choice {
+        /// The accepted values.
+        options: Vec<String>,
+        /// The index of the default value in `options`.
+        default: usize,
+    },
+}
+
+impl ExportSettingKind {
+    /// The default value of the setting.
+    #[must_use]
+    pub fn default_value(&self) -> ExportSettingValue {
+        match self {
+            Self::Bool(default) => ExportSettingValue::Bool(*default),
+            Self::Number { default, .. } => ExportSettingValue::Number(*default),
+            Self::Text(default) => ExportSettingValue::Text(default.clone()),
+            Self::Choice { options, default } => {
+                ExportSettingValue::Text(options.get(*default).cloned().unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// A setting an exporter accepts, from which the user interface builds its settings form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportSetting {
+    /// Identifies the setting in the [`ExportSettings`].
+    pub key: &'static str,
+    /// The label shown to the user.
+    pub label: String,
+    /// The type and default value of the setting.
+    pub kind: ExportSettingKind,
+}
+
+/// The value of an [`ExportSetting`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ExportSettingValue {
+    /// The value of a [`ExportSettingKind::Bool`] setting.
+    Bool(bool),
+    /// The value of a [`ExportSettingKind::Number`] setting.
+    Number(f64),
+    /// The value of a [`ExportSettingKind::Text`] or [`ExportSettingKind::Choice`] setting.
+    Text(String),
+}
+
+/// The values of the settings of an export, by [`ExportSetting::key`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ExportSettings(BTreeMap<String, ExportSettingValue>);
+
+impl ExportSettings {
+    /// Sets the value of the setting `key`.
+    #[must_use]
+    pub fn with(mut self, key: impl Into<String>, value: ExportSettingValue) -> Self {
+        self.0.insert(key.into(), value);
+        self
+    }
+
+    /// The value of the setting `key`.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&ExportSettingValue> {
+        self.0.get(key)
+    }
+
+    /// The value of the boolean setting `key`.
+    #[must_use]
+    pub fn bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            ExportSettingValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of the numeric setting `key`.
+    #[must_use]
+    pub fn number(&self, key: &str) -> Option<f64> {
+        match self.get(key)? {
+            ExportSettingValue::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of the text or choice setting `key`.
+    #[must_use]
+    pub fn text(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            ExportSettingValue::Text(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Fills in the default value of every setting in `schema` that wasn't set.
+    pub(crate) fn fill_defaults(&mut self, schema: &[ExportSetting]) {
+        for setting in schema {
+            self.0
+                .entry(setting.key.to_owned())
+                .or_insert_with(|| setting.kind.default_value());
+        }
+    }
+}
+
+/// What an exporter receives once the export area has been rendered.
+#[derive(Debug)]
+pub struct ExportInput {
+    /// The rendered area.
+    pub image: RgbaImage,
+    /// The number of pixels per world unit in the image.
+    pub pixels_per_unit: f32,
+    /// The file or directory to write to.
+    pub path: PathBuf,
+    /// The walls, portals and lights shown in the image.
+    pub scene: ExportScene,
+}
+
+/// An export target, such as an image file or a virtual tabletop package.
+///
+/// Exporters run on a background thread once the requested area has been rendered.
+pub trait Exporter: Send + Sync + 'static {
+    /// Uniquely identifies the exporter, used by [`ExportRequest::exporter`].
+    ///
+    /// [`ExportRequest::exporter`]: crate::ExportRequest::exporter
+    fn id(&self) -> &'static str;
+
+    /// The name shown to the user.
+    fn name(&self) -> String;
+
+    /// What the exporter writes.
+    fn capabilities(&self) -> ExportCapabilities;
+
+    /// The settings the exporter accepts.
+    fn settings(&self) -> Vec<ExportSetting> {
+        Vec::new()
+    }
+
+    /// Writes `input`, configured by `settings`.
+    ///
+    /// Every setting returned by [`Exporter::settings`] is present in `settings`.
+    ///
+    /// # Errors
+    /// Returns an error if the export couldn't be written.
+    fn run(&self, input: ExportInput, settings: &ExportSettings) -> Result<(), ExportError>;
+}
+
+/// The exporters available to [`ExportRequest`](crate::ExportRequest)s, by [`Exporter::id`].
+#[derive(Resource, Default, Clone)]
+pub struct ExportRegistry {
+    /// The registered exporters.
+    exporters: HashMap<&'static str, Arc<dyn Exporter>>,
+}
+
+impl ExportRegistry {
+    /// Registers `exporter`, replacing any exporter registered with the same id.
+    pub fn register(&mut self, exporter: impl Exporter) -> &mut Self {
+        self.exporters.insert(exporter.id(), Arc::new(exporter));
+        self
+    }
+
+    /// The exporter registered as `id`.
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<Arc<dyn Exporter>> {
+        self.exporters.get(id).cloned()
+    }
+
+    /// Iterates over the registered exporters, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Exporter> {
+        self.exporters.values().map(AsRef::as_ref)
+    }
+}
+
+/// Writes the export as a single image, its [`ExportFormat`] determined by the file extension.
+///
+/// The `quality` setting applies to JPEG images and the `compression` setting to PNG images.
+pub struct ImageExporter;
+
+impl ImageExporter {
+    /// The id the exporter is registered as.
+    pub const ID: &'static str = "image";
+}
+
+impl Exporter for ImageExporter {
+    fn id(&self) -> &'static str {
+        Self::ID
+    }
+
+    fn name(&self) -> String {
+        "Image".into()
+    }
+
+    fn capabilities(&self) -> ExportCapabilities {
+        ExportCapabilities {
+            output: ExportOutput::File,
+            extensions: &["png", "jpg", "jpeg", "webp"],
+        }
+    }
+
+    fn settings(&self) -> Vec<ExportSetting> {
+        let defaults = EncodeSettings::default();
+        vec![
+            ExportSetting {
+                key: "quality",
+                label: "JPEG quality".into(),
+                kind: ExportSettingKind::Number {
+                    default: f64::from(defaults.quality),
+                    min: 1.0,
+                    max: 100.0,
+                },
+            },
+            ExportSetting {
+                key: "compression",
+                label: "PNG compression".into(),
+                kind: ExportSettingKind::Choice {
+                    options: PngCompression::ALL
+                        .iter()
+                        .map(|compression| compression.name().to_owned())
+                        .collect(),
+                    default: PngCompression::ALL
+                        .iter()
+                        .position(|compression| *compression == defaults.compression)
+                        .unwrap_or_default(),
+                },
+            },
+        ]
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "the value is clamped to the setting's range"
+    )]
+    fn run(&self, input: ExportInput, settings: &ExportSettings) -> Result<(), ExportError> {
+        let format = ExportFormat::from_path(&input.path).ok_or_else(|| {
+            ExportError::UnsupportedFormat(
+                input
+                    .path
+                    .extension()
+                    .map_or_else(String::new, |extension| {
+                        extension.to_string_lossy().into_owned()
+                    }),
+            )
+        })?;
+        let defaults = EncodeSettings::default();
+        let settings = EncodeSettings {
+            quality: settings
+                .number("quality")
+                .map_or(defaults.quality, |quality| {
+                    quality.round().clamp(1.0, 100.0) as u8
+                }),
+            compression: settings
+                .text("compression")
+                .and_then(PngCompression::from_name)
+                .unwrap_or(defaults.compression),
+        };
+
+        encode_image(input.image, format, settings, &input.path)
+    }
+}
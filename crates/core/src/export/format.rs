@@ -0,0 +1,156 @@
+//! Encodes exported images as PNG, JPEG or WebP.
+
+use crate::export::ExportError;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ExtendedColorType, ImageEncoder, ImageError, RgbaImage};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// The file formats exported images can be written in.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum ExportFormat {
+    /// Lossless and keeps transparency, but large for big maps.
+    #[default]
+    Png,
+    /// Lossy and drops transparency, by far the smallest for big maps.
+    Jpeg,
+    /// Lossless and keeps transparency, usually smaller than PNG.
+    WebP,
+}
+
+impl ExportFormat {
+    /// Every format, in the order they're offered to the user.
+    pub const ALL: [Self; 3] = [Self::Png, Self::Jpeg, Self::WebP];
+
+    /// The format matching the extension of `path`, ignoring its case.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "webp" => Some(Self::WebP),
+            _ => None,
+        }
+    }
+
+    /// The file extensions of the format, the first is the usual one.
+    #[must_use]
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Self::Png => &["png"],
+            Self::Jpeg => &["jpg", "jpeg"],
+            Self::WebP => &["webp"],
+        }
+    }
+}
+
+/// How hard PNG exports are compressed, trading export time for file size.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum PngCompression {
+    /// Compresses quickly, producing the largest files.
+    Fast,
+    /// Balances time and size.
+    #[default]
+    Balanced,
+    /// Compresses as much as possible, which is slow for big maps.
+    Smallest,
+}
+
+impl PngCompression {
+    /// Every compression level, in the order they're offered to the user.
+    pub const ALL: [Self; 3] = [Self::Fast, Self::Balanced, Self::Smallest];
+
+    /// The name shown to the user.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Fast => "Fast",
+            Self::Balanced => "Balanced",
+            Self::Smallest => "Smallest",
+        }
+    }
+
+    /// The compression level named `name`, as returned by [`PngCompression::name`].
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|compression| compression.name() == name)
+    }
+}
+
+/// The encoding settings of an exported image, each only applying to some formats.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EncodeSettings {
+    /// The quality of JPEG images, from 1 (smallest) to 100 (best).
+    pub quality: u8,
+    /// How hard PNG images are compressed.
+    pub compression: PngCompression,
+}
+
+impl Default for EncodeSettings {
+    fn default() -> Self {
+        Self {
+            quality: 90,
+            compression: PngCompression::default(),
+        }
+    }
+}
+
+/// Writes `image` to `path` as `format`.
+///
+/// JPEG has no transparency, so transparent parts of `image` are written with their colour as if
+/// they were opaque.
+///
+/// # Errors
+/// Returns an error if the file can't be created or the image can't be encoded, for example
+/// because it's larger than JPEG and WebP allow.
+pub fn encode_image(
+    image: RgbaImage,
+    format: ExportFormat,
+    settings: EncodeSettings,
+    path: &Path,
+) -> Result<(), ExportError> {
+    let mut writer = BufWriter::new(File::create(path).map_err(ImageError::IoError)?);
+    match format {
+        ExportFormat::Png => {
+            let compression = match settings.compression {
+                PngCompression::Fast => CompressionType::Fast,
+                PngCompression::Balanced => CompressionType::Default,
+                PngCompression::Smallest => CompressionType::Best,
+            };
+            PngEncoder::new_with_quality(&mut writer, compression, FilterType::Adaptive)
+                .write_image(
+                    image.as_raw(),
+                    image.width(),
+                    image.height(),
+                    ExtendedColorType::Rgba8,
+                )?;
+        }
+        ExportFormat::Jpeg => {
+            let image = DynamicImage::ImageRgba8(image).into_rgb8();
+            JpegEncoder::new_with_quality(&mut writer, settings.quality.clamp(1, 100))
+                .write_image(
+                    image.as_raw(),
+                    image.width(),
+                    image.height(),
+                    ExtendedColorType::Rgb8,
+                )?;
+        }
+        ExportFormat::WebP => {
+            WebPEncoder::new_lossless(&mut writer).write_image(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                ExtendedColorType::Rgba8,
+            )?;
+        }
+    }
+
+    std::io::Write::flush(&mut writer).map_err(ImageError::IoError)?;
+    Ok(())
+}
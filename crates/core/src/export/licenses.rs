@@ -0,0 +1,44 @@
+//! Warns about exports combining assets whose licenses conflict.
+//!
+//! The check only reports the conflicts, the export itself goes ahead: the user knows best
+//! whether the map will be shared at all.
+
+use crate::export::{ExportLicenseConflicts, ExportRequest};
+use bevy::prelude::*;
+use dungeonrs_assets::AssetLibrary;
+use dungeonrs_data::{Element, LevelBackground};
+use dungeonrs_macros::bevy_system;
+
+/// Checks the licenses of the elements in the area of each requested export, along with the
+/// backgrounds of the levels. Background exports are never shared, so they aren't checked.
+///
+/// Elements are checked whether or not their layer is exported, as a warning too many is better
+/// than a missed one.
+#[bevy_system]
+pub(crate) fn check_export_licenses(
+    mut requests: MessageReader<ExportRequest>,
+    mut conflicts: MessageWriter<ExportLicenseConflicts>,
+    library: Option<Res<AssetLibrary>>,
+    elements: Query<(&Element, &GlobalTransform)>,
+    backgrounds: Query<&LevelBackground>,
+) {
+    let Some(library) = library else {
+        requests.clear();
+        return;
+    };
+
+    for request in requests.read().filter(|request| !request.background) {
+        let assets = elements
+            .iter()
+            .filter(|(_, transform)| request.area.contains(transform.translation().truncate()))
+            .map(|(element, _)| element.asset.as_path())
+            .chain(backgrounds.iter().filter_map(LevelBackground::asset));
+        let found = library.license_conflicts(assets);
+        if !found.is_empty() {
+            conflicts.write(ExportLicenseConflicts {
+                path: request.path.clone(),
+                conflicts: found,
+            });
+        }
+    }
+}
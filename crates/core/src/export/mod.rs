@@ -0,0 +1,381 @@
+//! Exports levels as a single image.
+//!
+//! Levels are usually far larger than what fits on screen, so they're captured as a grid of
+//! frames. Once all frames are captured they're handed to [`process_export`], which stitches them
+//! together and passes the result to the requested [`Exporter`] without blocking the main thread.
+//!
+//! The walls, portals, lights and level links visible in each exported image are passed to the
+//! exporter as an [`ExportScene`], for formats describing them next to the image.
+//!
+//! Animated elements are captured at their
+//! [`AnimatedTexture::export_frame`](dungeonrs_data::AnimatedTexture::export_frame).
+//!
+//! The [`LightMap`](crate::LightMap) of a darkened level is drawn over its layers, so it's
+//! captured along with them.
+//!
+//! Hidden [`Region`](dungeonrs_data::Region)s are covered in the exports for the
+//! [`ExportAudience::Players`], so the GM and the player version of a map come out of a single
+//! request.
+//!
+//! Exports combining assets of packs whose licenses conflict are reported through
+//! [`ExportLicenseConflicts`], once the [`AssetsPlugin`](dungeonrs_assets::AssetsPlugin) knows
+//! the packs.
+
+mod capture;
+mod exporter;
+mod format;
+mod licenses;
+mod presets;
+mod processing;
+mod scene;
+
+pub use exporter::{
+    ExportCapabilities, ExportInput, ExportOutput, ExportRegistry, ExportSetting,
+    ExportSettingKind, ExportSettingValue, ExportSettings, Exporter, ImageExporter,
+};
+pub use format::{EncodeSettings, ExportFormat, PngCompression, encode_image};
+pub use presets::{ExportPreset, ExportRegion};
+pub use processing::{CapturedFrame, process_image_data};
+pub use scene::{ExportLight, ExportLink, ExportPortal, ExportScene};
+
+pub(crate) use capture::{ExportCapture, ExportQueue, ViewportCamera};
+
+use bevy::prelude::{App, Entity, IntoScheduleConfigs, Message, Plugin, Rect, UVec2, Update};
+use dungeonrs_assets::LicenseConflict;
+use dungeonrs_utils::{
+    AsyncCommand, CancellationToken, Cancelled, PathError, info_span, report_progress,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Registers the messages and systems that capture and process exports, and the
+/// [`ImageExporter`].
+///
+/// Other crates add export targets by registering them with the [`ExportRegistry`].
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ExportRegistry>();
+        app.world_mut()
+            .resource_mut::<ExportRegistry>()
+            .register(ImageExporter);
+
+        app.init_resource::<ExportQueue>()
+            .add_message::<ExportRequest>()
+            .add_message::<CancelExport>()
+            .add_message::<ExportCompleted>()
+            .add_message::<ExportFailed>()
+            .add_message::<ExportLicenseConflicts>()
+            .add_observer(capture::receive_readback)
+            .add_systems(
+                Update,
+                (
+                    licenses::check_export_licenses,
+                    (
+                        capture::cancel_export,
+                        capture::start_export,
+                        capture::advance_export,
+                    )
+                        .chain(),
+                ),
+            );
+    }
+}
+
+/// Requests exporting an area of the world.
+///
+/// Only one export runs at a time, requests made while an export is running are queued and
+/// captured in the order they were made. [Background](ExportRequest::in_background) requests wait
+/// until no other export is queued.
+#[derive(Message, Debug, Clone)]
+pub struct ExportRequest {
+    /// The file or directory to write the export to.
+    pub path: PathBuf,
+    /// The world-space area to export.
+    pub area: Rect,
+    /// The number of pixels per world unit in the exported image.
+    pub pixels_per_unit: f32,
+    /// The size of the frames the area is captured in, limited by the maximum texture size.
+    pub frame_size: UVec2,
+    /// The [`Exporter::id`] of the exporter that writes the export.
+    pub exporter: String,
+    /// The settings passed to the exporter, those that aren't set use their default value.
+    pub settings: ExportSettings,
+    /// Subsets of the layers each exported separately, such as a GM and a player version of the
+    /// map, each for its own [`ExportAudience`].
+    ///
+    /// When empty, every visible layer is exported to [`ExportRequest::path`].
+    pub layers: Vec<ExportLayers>,
+    /// Whether the grid drawn by the [`GridOverlayPlugin`](crate::GridOverlayPlugin) is burned
+    /// into the exported image.
+    pub gridlines: bool,
+    /// Whether the export renders the map for the editor itself, such as the live preview.
+    ///
+    /// Background exports are captured once no other export is queued, only the latest one of
+    /// each exporter is kept, and they aren't reported through [`ExportCompleted`] or
+    /// [`ExportLicenseConflicts`].
+    pub background: bool,
+}
+
+impl ExportRequest {
+    /// The frame size used by [`ExportRequest::new`].
+    pub const DEFAULT_FRAME_SIZE: UVec2 = UVec2::splat(2048);
+
+    /// Requests exporting `area` as an image to `path` at `pixels_per_unit`.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>, area: Rect, pixels_per_unit: f32) -> Self {
+        Self {
+            path: path.into(),
+            area,
+            pixels_per_unit,
+            frame_size: Self::DEFAULT_FRAME_SIZE,
+            exporter: ImageExporter::ID.into(),
+            settings: ExportSettings::default(),
+            layers: Vec::new(),
+            gridlines: false,
+            background: false,
+        }
+    }
+
+    /// Renders the export for the editor itself, behind the exports requested by the user, see
+    /// [`ExportRequest::background`].
+    #[must_use]
+    pub fn in_background(mut self) -> Self {
+        self.background = true;
+        self
+    }
+
+    /// Sets whether the grid is burned into the exported image.
+    #[must_use]
+    pub fn with_gridlines(mut self, gridlines: bool) -> Self {
+        self.gridlines = gridlines;
+        self
+    }
+
+    /// Writes the export with the exporter registered as `exporter`, configured by `settings`.
+    #[must_use]
+    pub fn with_exporter(mut self, exporter: impl Into<String>, settings: ExportSettings) -> Self {
+        self.exporter = exporter.into();
+        self.settings = settings;
+        self
+    }
+
+    /// Also exports only `layers` to `path`, in the same pass as the other subsets of layers.
+    ///
+    /// Once a subset was added, [`ExportRequest::path`] is no longer written.
+    #[must_use]
+    pub fn with_layers(
+        mut self,
+        path: impl Into<PathBuf>,
+        layers: impl IntoIterator<Item = Entity>,
+    ) -> Self {
+        self.layers.push(ExportLayers {
+            path: path.into(),
+            layers: layers.into_iter().collect(),
+            audience: ExportAudience::Gm,
+        });
+        self
+    }
+
+    /// Also exports only `layers` to `path` for the players, with the hidden
+    /// [`Region`](dungeonrs_data::Region)s covered.
+    ///
+    /// Once a subset was added, [`ExportRequest::path`] is no longer written.
+    #[must_use]
+    pub fn with_player_layers(
+        mut self,
+        path: impl Into<PathBuf>,
+        layers: impl IntoIterator<Item = Entity>,
+    ) -> Self {
+        self.layers.push(ExportLayers {
+            path: path.into(),
+            layers: layers.into_iter().collect(),
+            audience: ExportAudience::Players,
+        });
+        self
+    }
+
+    /// Exports every visible layer twice in the same pass: to `gm` with everything shown, and to
+    /// `players` with the hidden [`Region`](dungeonrs_data::Region)s covered.
+    ///
+    /// [`ExportRequest::path`] is no longer written.
+    #[must_use]
+    pub fn with_gm_and_player_maps(
+        mut self,
+        gm: impl Into<PathBuf>,
+        players: impl Into<PathBuf>,
+    ) -> Self {
+        for (path, audience) in [
+            (gm.into(), ExportAudience::Gm),
+            (players.into(), ExportAudience::Players),
+        ] {
+            self.layers.push(ExportLayers {
+                path,
+                layers: Vec::new(),
+                audience,
+            });
+        }
+        self
+    }
+}
+
+/// Who an exported map is meant for.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ExportAudience {
+    /// The GM, who sees everything.
+    #[default]
+    Gm,
+    /// The players, who don't see the hidden [`Region`](dungeonrs_data::Region)s.
+    Players,
+}
+
+/// A subset of the layers exported to its own file by an [`ExportRequest`].
+///
+/// The other layers are hidden while the subset is captured, and the visibility of every layer
+/// is restored once the export was captured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportLayers {
+    /// The file or directory to write the subset to.
+    pub path: PathBuf,
+    /// The layers to export, they're exported even if they're hidden.
+    ///
+    /// When empty, every visible layer is exported.
+    pub layers: Vec<Entity>,
+    /// Who the subset is exported for.
+    pub audience: ExportAudience,
+}
+
+/// Cancels the running export, which is reported through [`ExportFailed`] with
+/// [`ExportError::Cancelled`]. Queued exports start once it stopped.
+#[derive(Message, Debug, Clone, Copy, Default)]
+pub struct CancelExport;
+
+/// Errors that can occur while processing an export.
+#[derive(Error, Debug)]
+pub enum ExportError {
+    /// The requested number of pixels per world unit isn't positive.
+    #[error("can't export at {0} pixels per unit")]
+    InvalidResolution(f32),
+    /// The path to write the export to isn't valid.
+    #[error(transparent)]
+    Path(#[from] PathError),
+    /// No exporter is registered with the requested id.
+    #[error("no exporter named '{0}' is registered")]
+    UnknownExporter(String),
+    /// The image can't be written in the format matching the file extension.
+    #[error("can't export images as '{0}', use png, jpg or webp")]
+    UnsupportedFormat(String),
+    /// The stitched image couldn't be encoded or written.
+    #[error("failed to write the exported image: {0}")]
+    Image(#[from] image::ImageError),
+    /// The exporter failed to write the export.
+    #[error("failed to write the export: {0}")]
+    Exporter(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// The export was cancelled through [`CancelExport`].
+    #[error(transparent)]
+    Cancelled(#[from] Cancelled),
+}
+
+/// Written when an export was written to disk.
+#[derive(Message, Debug, Clone)]
+pub struct ExportCompleted {
+    /// The file the export was written to.
+    pub path: PathBuf,
+}
+
+/// Written when an export couldn't be processed.
+#[derive(Message, Debug)]
+pub struct ExportFailed {
+    /// The file the export would have been written to.
+    pub path: PathBuf,
+    /// The reason the export failed.
+    pub error: ExportError,
+}
+
+/// Written when an export is requested for an area combining assets of packs whose licenses
+/// conflict, so the user can be warned. The export still goes ahead.
+#[derive(Message, Debug, Clone)]
+pub struct ExportLicenseConflicts {
+    /// The file or directory the export is written to.
+    pub path: PathBuf,
+    /// The conflicting licenses, a pair of packs at a time.
+    pub conflicts: Vec<LicenseConflict>,
+}
+
+/// Returns a command that stitches `frames` into an image of `size` and passes it to `exporter`
+/// along with its `scene`, which writes it to `path`.
+///
+/// Completion is reported through [`ExportCompleted`] or [`ExportFailed`], so both messages need
+/// to be registered with the app. Cancelling the command's [token](AsyncCommand::token) stops
+/// processing before the exporter runs.
+#[must_use]
+pub fn process_export(
+    frames: Vec<CapturedFrame>,
+    size: UVec2,
+    pixels_per_unit: f32,
+    path: PathBuf,
+    scene: ExportScene,
+    exporter: Arc<dyn Exporter>,
+    settings: ExportSettings,
+) -> AsyncCommand {
+    process(
+        frames,
+        size,
+        pixels_per_unit,
+        path,
+        scene,
+        exporter,
+        settings,
+        false,
+        CancellationToken::default(),
+    )
+}
+
+/// Returns a command like [`process_export`] cancelled by `token`, which doesn't report the
+/// completion of `background` exports.
+#[allow(
+    clippy::too_many_arguments,
+    reason = "the export is processed from the captured frames, the scene and the exporter"
+)]
+pub(crate) fn process(
+    frames: Vec<CapturedFrame>,
+    size: UVec2,
+    pixels_per_unit: f32,
+    path: PathBuf,
+    scene: ExportScene,
+    exporter: Arc<dyn Exporter>,
+    settings: ExportSettings,
+    background: bool,
+    token: CancellationToken,
+) -> AsyncCommand {
+    AsyncCommand::with_token(token, move |context| async move {
+        let span = info_span!(
+            "export",
+            exporter = exporter.id(),
+            path = %path.display(),
+            length = frames,
+        );
+        let result = span.in_scope(|| {
+            let image = process_image_data(&frames, size);
+            drop(frames);
+            // Cancelled exports were reported when they were cancelled.
+            context.check_cancelled()?;
+
+            let input = ExportInput {
+                image,
+                pixels_per_unit,
+                path: path.clone(),
+                scene,
+            };
+            exporter.run(input, &settings)
+        });
+        match result {
+            Err(ExportError::Cancelled(_)) => {}
+            Ok(()) if background => {}
+            Ok(()) => report_progress(&context, ExportCompleted { path }),
+            Err(error) => report_progress(&context, ExportFailed { path, error }),
+        }
+    })
+}
@@ -0,0 +1,140 @@
+//! Named export configurations the user saves to repeat an export workflow, such as a map for a
+//! virtual tabletop at 140 pixels per cell or a print at 300 DPI.
+//!
+//! Presets are kept in the [`Configuration`](crate::Configuration), so they're available in every
+//! project.
+
+use crate::export::{ExportRequest, ExportSettings, ImageExporter};
+use bevy::prelude::{Entity, Rect};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The area of the level an [`ExportPreset`] exports.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportRegion {
+    /// Everything placed on the level.
+    #[default]
+    Level,
+    /// The area shown in the viewport.
+    Viewport,
+    /// The area around the selected elements.
+    Selection,
+}
+
+/// A named set of export settings, applied to an export instead of setting them one by one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportPreset {
+    /// The name of the preset, which identifies it in the [`Configuration`](crate::Configuration).
+    pub name: String,
+    /// The [`Exporter::id`](crate::Exporter::id) of the exporter writing the export.
+    pub exporter: String,
+    /// The extension given to the exported file, choosing the format of exported images, or
+    /// empty to keep the extension of the chosen path.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub extension: String,
+    /// The settings passed to the exporter.
+    #[serde(default)]
+    pub settings: ExportSettings,
+    /// The number of pixels per grid cell in the exported image, the DPI when a cell is printed an
+    /// inch wide.
+    pub pixels_per_cell: f32,
+    /// The area of the level exported.
+    #[serde(default)]
+    pub region: ExportRegion,
+    /// The names of the layers exported, every visible layer when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<String>,
+    /// Whether the grid lines are drawn over the exported map.
+    #[serde(default)]
+    pub gridlines: bool,
+}
+
+impl ExportPreset {
+    /// Creates a preset named `name` exporting every visible layer of the level as a PNG image
+    /// at `pixels_per_cell`, without grid lines.
+    pub fn new(name: impl Into<String>, pixels_per_cell: f32) -> Self {
+        Self {
+            name: name.into(),
+            exporter: ImageExporter::ID.into(),
+            extension: "png".into(),
+            settings: ExportSettings::default(),
+            pixels_per_cell,
+            region: ExportRegion::Level,
+            layers: Vec::new(),
+            gridlines: false,
+        }
+    }
+
+    /// Writes the export with the exporter registered as `exporter`, configured by `settings`,
+    /// to a file with `extension`.
+    #[must_use]
+    pub fn with_exporter(
+        mut self,
+        exporter: impl Into<String>,
+        extension: impl Into<String>,
+        settings: ExportSettings,
+    ) -> Self {
+        self.exporter = exporter.into();
+        self.extension = extension.into();
+        self.settings = settings;
+        self
+    }
+
+    /// Exports `region` instead of the whole level.
+    #[must_use]
+    pub fn with_region(mut self, region: ExportRegion) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Exports only the layers named `layers`.
+    #[must_use]
+    pub fn with_layers(mut self, layers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.layers = layers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets whether the grid lines are drawn over the exported map.
+    #[must_use]
+    pub fn with_gridlines(mut self, gridlines: bool) -> Self {
+        self.gridlines = gridlines;
+        self
+    }
+
+    /// Applies the preset to an export of `area` to `path`, on a grid of `cell_size` world units.
+    ///
+    /// `area` is the [`ExportPreset::region`] resolved by the caller, and `layers` the layers of
+    /// the exported level along with their names, of which those named by the preset are
+    /// exported.
+    #[must_use]
+    pub fn request<'a>(
+        &self,
+        path: impl Into<PathBuf>,
+        area: Rect,
+        cell_size: f32,
+        layers: impl IntoIterator<Item = (Entity, &'a str)>,
+    ) -> ExportRequest {
+        let mut path = path.into();
+        if !self.extension.is_empty() {
+            path.set_extension(&self.extension);
+        }
+
+        let request = ExportRequest::new(
+            path.clone(),
+            area,
+            self.pixels_per_cell / cell_size.max(f32::EPSILON),
+        )
+        .with_exporter(self.exporter.clone(), self.settings.clone())
+        .with_gridlines(self.gridlines);
+        if self.layers.is_empty() {
+            return request;
+        }
+
+        let layers = layers
+            .into_iter()
+            .filter(|(_, name)| self.layers.iter().any(|layer| layer == name))
+            .map(|(layer, _)| layer);
+        request.with_layers(path, layers)
+    }
+}
@@ -0,0 +1,167 @@
+//! Stitches the captured frames of an export into a single image.
+
+use bevy::math::{URect, UVec2};
+use image::RgbaImage;
+use rayon::prelude::*;
+
+/// The number of rows of the output image each worker stitches at a time.
+const BAND_HEIGHT: u32 = 64;
+
+/// The number of bytes of a single RGBA8 pixel.
+const PIXEL_SIZE: usize = 4;
+
+/// A single frame captured during an export.
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    /// The position of the frame's top-left corner in the output image, in pixels.
+    pub position: UVec2,
+    /// The size of the frame, in pixels.
+    pub size: UVec2,
+    /// The frame's pixels as tightly packed RGBA8 rows.
+    pub data: Vec<u8>,
+}
+
+impl CapturedFrame {
+    /// The area of the output image covered by this frame.
+    fn rect(&self) -> URect {
+        URect::from_corners(self.position, self.position + self.size)
+    }
+}
+
+/// Stitches `frames` into a single image of `size`.
+///
+/// The output is split in horizontal bands that are filled in parallel, each band copying the
+/// rows of the frames overlapping it. Parts of frames falling outside of the output are cropped,
+/// and where frames overlap the one that comes last in `frames` wins.
+#[must_use]
+pub fn process_image_data(frames: &[CapturedFrame], size: UVec2) -> RgbaImage {
+    let row_length = size.x as usize * PIXEL_SIZE;
+    let mut buffer = vec![0; row_length * size.y as usize];
+    let output = URect::from_corners(UVec2::ZERO, size);
+
+    if row_length > 0 {
+        buffer
+            .par_chunks_mut(row_length * BAND_HEIGHT as usize)
+            .enumerate()
+            .for_each(|(band, pixels)| {
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    reason = "bands never exceed the height"
+                )]
+                let top = band as u32 * BAND_HEIGHT;
+                let band = URect::new(0, top, size.x, top + BAND_HEIGHT).intersect(output);
+
+                for frame in frames {
+                    stitch_frame(frame, band, pixels, row_length);
+                }
+            });
+    }
+
+    RgbaImage::from_raw(size.x, size.y, buffer).unwrap_or_else(|| RgbaImage::new(size.x, size.y))
+}
+
+/// Copies the rows of `frame` overlapping `band` into `pixels`, which holds the band's rows.
+fn stitch_frame(frame: &CapturedFrame, band: URect, pixels: &mut [u8], row_length: usize) {
+    let overlap = frame.rect().intersect(band);
+    if overlap.is_empty() {
+        return;
+    }
+
+    let frame_row_length = frame.size.x as usize * PIXEL_SIZE;
+    let width = overlap.width() as usize * PIXEL_SIZE;
+    for y in overlap.min.y..overlap.max.y {
+        let source = (y - frame.position.y) as usize * frame_row_length
+            + (overlap.min.x - frame.position.x) as usize * PIXEL_SIZE;
+        let target = (y - band.min.y) as usize * row_length + overlap.min.x as usize * PIXEL_SIZE;
+
+        if let Some(row) = frame.data.get(source..source + width) {
+            pixels[target..target + width].copy_from_slice(row);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Stitches frames whose pixels tell which frame and pixel they come from.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+    use image::Rgba;
+
+    /// A frame `index` of `size` at `position`, whose pixels hold the index and their position
+    /// within the frame.
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "the test frames are smaller than 256 pixels"
+    )]
+    fn frame(index: u8, position: UVec2, size: UVec2) -> CapturedFrame {
+        let data = (0..size.y)
+            .flat_map(|y| (0..size.x).flat_map(move |x| [index, x as u8, y as u8, 255]))
+            .collect();
+
+        CapturedFrame {
+            position,
+            size,
+            data,
+        }
+    }
+
+    /// The pixel `(x, y)` of frame `index`.
+    fn pixel(index: u8, x: u8, y: u8) -> Rgba<u8> {
+        Rgba([index, x, y, 255])
+    }
+
+    /// Frames tiling an image whose size isn't a multiple of the frame or band size land at
+    /// their position, across band boundaries, with the last row and column of frames cropped.
+    #[test]
+    fn places_frames_across_bands() {
+        let size = UVec2::new(100, 150);
+        let frames: Vec<_> = [(0, 0), (64, 0), (0, 64), (64, 64), (0, 128), (64, 128)]
+            .into_iter()
+            .zip(0..)
+            .map(|((x, y), index)| frame(index, UVec2::new(x, y), UVec2::splat(64)))
+            .collect();
+        let image = process_image_data(&frames, size);
+
+        assert_eq!(image.dimensions(), (100, 150));
+        assert_eq!(*image.get_pixel(0, 0), pixel(0, 0, 0));
+        assert_eq!(*image.get_pixel(63, 63), pixel(0, 63, 63));
+        assert_eq!(*image.get_pixel(64, 63), pixel(1, 0, 63));
+        assert_eq!(*image.get_pixel(63, 64), pixel(2, 63, 0));
+        assert_eq!(*image.get_pixel(99, 127), pixel(3, 35, 63));
+        assert_eq!(*image.get_pixel(64, 128), pixel(5, 0, 0));
+        assert_eq!(*image.get_pixel(99, 149), pixel(5, 35, 21));
+    }
+
+    /// Where frames overlap, the one that comes last wins, and the rest of each frame is kept.
+    #[test]
+    fn crops_overlapping_frames() {
+        let frames = [
+            frame(0, UVec2::ZERO, UVec2::splat(80)),
+            frame(1, UVec2::new(40, 60), UVec2::splat(80)),
+        ];
+        let image = process_image_data(&frames, UVec2::splat(100));
+
+        assert_eq!(*image.get_pixel(39, 79), pixel(0, 39, 79));
+        assert_eq!(*image.get_pixel(79, 59), pixel(0, 79, 59));
+        assert_eq!(*image.get_pixel(40, 60), pixel(1, 0, 0));
+        assert_eq!(*image.get_pixel(79, 79), pixel(1, 39, 19));
+        assert_eq!(*image.get_pixel(99, 99), pixel(1, 59, 39));
+        assert_eq!(
+            *image.get_pixel(99, 0),
+            Rgba([0; 4]),
+            "no frame covers the corner"
+        );
+    }
+
+    /// An empty image stitches without frames being read.
+    #[test]
+    fn stitches_empty_images() {
+        let frames = [frame(0, UVec2::ZERO, UVec2::splat(8))];
+
+        assert_eq!(
+            process_image_data(&frames, UVec2::new(0, 10)).dimensions(),
+            (0, 10)
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Gathers the walls, portals, lights and level links of an export, for exporters writing them
+//! along with the image such as virtual tabletop formats.
+
+use bevy::prelude::*;
+use dungeonrs_data::{
+    Level, LevelLink, LightSource, LinkKind, MapProjection, PersistentId, Portal, Wall, WallPath,
+};
+
+/// The walls, portals, lights and level links shown in an exported image.
+///
+/// Positions and distances are in pixels of the image, from its top left corner with the Y axis
+/// pointing down.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportScene {
+    /// The walls blocking line of sight, each a line through its points. Closed
+    /// [`WallPath`]s end where they started.
+    pub walls: Vec<Vec<Vec2>>,
+    /// The doors and windows.
+    pub portals: Vec<ExportPortal>,
+    /// The light sources.
+    pub lights: Vec<ExportLight>,
+    /// The stairs, ladders and teleporters leading to other levels.
+    pub links: Vec<ExportLink>,
+}
+
+/// A [`Portal`] in an [`ExportScene`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ExportPortal {
+    /// One end of the portal.
+    pub start: Vec2,
+    /// The other end of the portal.
+    pub end: Vec2,
+    /// Whether the portal blocks line of sight.
+    pub closed: bool,
+}
+
+/// A [`LightSource`] in an [`ExportScene`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ExportLight {
+    /// The center of the light.
+    pub position: Vec2,
+    /// The distance the light reaches.
+    pub radius: f32,
+    /// The colour of the light.
+    pub color: Color,
+    /// The brightness of the light.
+    pub intensity: f32,
+}
+
+/// A [`LevelLink`] in an [`ExportScene`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportLink {
+    /// Where the link starts.
+    pub position: Vec2,
+    /// How the link takes tokens to the other level.
+    pub kind: LinkKind,
+    /// The name of the level the link leads to.
+    pub level: String,
+    /// Where the link arrives, in pixels of an image of the other level covering the same area.
+    pub destination: Vec2,
+}
+
+/// The walls, portals, lights and level links of the world.
+pub(crate) type SceneContents<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static GlobalTransform,
+        Option<&'static InheritedVisibility>,
+        Option<&'static Wall>,
+        Option<&'static WallPath>,
+        Option<&'static Portal>,
+        Option<&'static LightSource>,
+        Option<&'static LevelLink>,
+    ),
+    Or<(
+        With<Wall>,
+        With<WallPath>,
+        With<Portal>,
+        With<LightSource>,
+        With<LevelLink>,
+    )>,
+>;
+
+/// The levels of the world, to name the levels links lead to.
+pub(crate) type SceneLevels<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Level,
+        &'static PersistentId,
+        &'static GlobalTransform,
+    ),
+>;
+
+/// The visible walls, portals, lights and level links of `contents`, in pixels of an image of
+/// the screen area `view` at `pixels_per_unit`, showing the map through `projection`.
+///
+/// Everything visible is included, even outside of `area`, so walls crossing the edge of the
+/// image aren't cut short.
+pub(crate) fn gather_scene(
+    contents: &SceneContents,
+    levels: &SceneLevels,
+    projection: MapProjection,
+    view: Rect,
+    pixels_per_unit: f32,
+) -> ExportScene {
+    let pixel = |transform: &GlobalTransform, point: Vec2| {
+        let point = projection.project(transform.transform_point(point.extend(0.0)).truncate());
+        Vec2::new(point.x - view.min.x, view.max.y - point.y) * pixels_per_unit
+    };
+
+    let mut scene = ExportScene::default();
+    for (transform, visibility, wall, path, portal, light, link) in contents {
+        if visibility.is_some_and(|visibility| !visibility.get()) {
+            continue;
+        }
+
+        if let Some(wall) = wall {
+            scene.walls.push(
+                wall.points
+                    .iter()
+                    .map(|point| pixel(transform, *point))
+                    .collect(),
+            );
+        }
+        if let Some(path) = path {
+            let mut points: Vec<_> = path
+                .points
+                .iter()
+                .map(|point| pixel(transform, *point))
+                .collect();
+            if path.closed
+                && let Some(first) = points.first().copied()
+            {
+                points.push(first);
+            }
+            scene.walls.push(points);
+        }
+        if let Some(portal) = portal {
+            scene.portals.push(ExportPortal {
+                start: pixel(transform, portal.start),
+                end: pixel(transform, portal.end),
+                closed: portal.closed,
+            });
+        }
+        if let Some(light) = light {
+            scene.lights.push(ExportLight {
+                position: pixel(transform, Vec2::ZERO),
+                radius: light.radius * pixels_per_unit,
+                color: light.color,
+                intensity: light.intensity,
+            });
+        }
+        // Links to levels that were removed lead nowhere, so they're left out.
+        if let Some(link) = link
+            && let Some((level, _, target)) = levels.iter().find(|(_, id, _)| **id == link.level)
+        {
+            scene.links.push(ExportLink {
+                position: pixel(transform, Vec2::ZERO),
+                kind: link.kind,
+                level: level.name.clone(),
+                destination: pixel(target, link.destination),
+            });
+        }
+    }
+    scene.walls.retain(|points| points.len() > 1);
+
+    scene
+}
@@ -0,0 +1,284 @@
+//! Moves, rotates and scales the selected elements by dragging a transform gizmo.
+//!
+//! The user interface draws the gizmo at [`TransformGizmo::pivot`] and hands the drags over as
+//! [`DragGizmo`] requests, in world units. The elements follow the drag as it happens, and the
+//! whole drag is recorded in the [`History`](crate::History) as a single edit once it ends.
+
+use crate::{Edit, EditGroup, HistoryCommandsExt, Selection, SetTransform};
+use bevy::math::Affine3A;
+use bevy::prelude::*;
+use dungeonrs_data::{Grid, PersistentId};
+use dungeonrs_macros::bevy_system;
+
+/// Registers the messages and systems of the transform gizmo.
+///
+/// Requires the [`SelectionPlugin`](crate::SelectionPlugin) for the elements to transform, and
+/// the [`HistoryPlugin`](crate::HistoryPlugin) to undo the drags.
+pub struct TransformGizmoPlugin;
+
+impl Plugin for TransformGizmoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TransformGizmo>()
+            .add_message::<DragGizmo>()
+            .add_systems(Update, (drag_gizmo, update_pivot).chain());
+    }
+}
+
+/// What dragging the gizmo does to the selected elements.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum GizmoMode {
+    /// Moves the elements along with the cursor.
+    #[default]
+    Move,
+    /// Rotates the elements around the pivot.
+    Rotate,
+    /// Scales the elements away from or towards the pivot.
+    Scale,
+}
+
+/// The state of the transform gizmo.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct TransformGizmo {
+    /// What dragging the gizmo does.
+    pub mode: GizmoMode,
+    /// Whether the dragged elements snap to the [`Grid`].
+    pub snap: bool,
+    /// The center of the selected elements, in world units, where the gizmo is drawn. `None`
+    /// when nothing is selected.
+    pivot: Option<Vec2>,
+    /// The drag in progress.
+    drag: Option<Drag>,
+}
+
+impl TransformGizmo {
+    /// The center of the selected elements, in world units, where the gizmo is drawn.
+    ///
+    /// Stays where the drag started while rotating or scaling, so the gizmo doesn't move under
+    /// the cursor.
+    #[must_use]
+    pub fn pivot(&self) -> Option<Vec2> {
+        match &self.drag {
+            Some(drag) if drag.mode != GizmoMode::Move => Some(drag.pivot),
+            _ => self.pivot,
+        }
+    }
+
+    /// Whether the gizmo is being dragged.
+    #[must_use]
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+}
+
+/// A step of a drag, such as of the gizmo or the terrain brush.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DragPhase {
+    /// The gizmo was grabbed.
+    Start,
+    /// The cursor moved while the gizmo is grabbed.
+    Move,
+    /// The gizmo was released, recording the drag in the history.
+    End,
+    /// The drag was cancelled, putting the elements back where they were.
+    Cancel,
+}
+
+/// Drags the transform gizmo.
+#[derive(Message, Debug, Copy, Clone, PartialEq)]
+pub struct DragGizmo {
+    /// The step of the drag.
+    pub phase: DragPhase,
+    /// The position of the cursor, in world units.
+    pub position: Vec2,
+}
+
+/// A drag of the gizmo in progress.
+#[derive(Debug, Clone, PartialEq)]
+struct Drag {
+    /// What the drag does.
+    mode: GizmoMode,
+    /// Where the drag started, in world units.
+    start: Vec2,
+    /// The point elements are rotated around and scaled away from, in world units.
+    pivot: Vec2,
+    /// The dragged elements, as they were before the drag.
+    elements: Vec<DraggedElement>,
+}
+
+/// An element dragged by the gizmo, as it was before the drag.
+#[derive(Debug, Clone, PartialEq)]
+struct DraggedElement {
+    /// The element entity.
+    entity: Entity,
+    /// The transform of the element.
+    transform: Transform,
+    /// The position of the element, in world units.
+    position: Vec2,
+    /// Converts world units to the space of the element's parent.
+    to_parent: Affine3A,
+}
+
+/// Applies the [`DragGizmo`] requests to the selected elements.
+#[bevy_system]
+fn drag_gizmo(
+    mut commands: Commands,
+    mut gizmo: ResMut<TransformGizmo>,
+    mut drags: MessageReader<DragGizmo>,
+    selection: Res<Selection>,
+    grid: Option<Res<Grid>>,
+    mut transforms: Query<(&mut Transform, &PersistentId)>,
+    globals: Query<&GlobalTransform>,
+) {
+    let grid = grid.filter(|_| gizmo.snap).map(|grid| *grid);
+    for drag in drags.read() {
+        match drag.phase {
+            DragPhase::Start => {
+                restore(&mut gizmo, &mut transforms);
+                let elements: Vec<_> = selection
+                    .iter()
+                    .filter_map(|entity| {
+                        let transform = *transforms.get(entity).ok()?.0;
+                        let global = globals.get(entity).ok()?;
+                        Some(DraggedElement {
+                            entity,
+                            transform,
+                            position: global.translation().truncate(),
+                            to_parent: (global.affine() * transform.compute_affine().inverse())
+                                .inverse(),
+                        })
+                    })
+                    .collect();
+                gizmo.drag = gizmo
+                    .pivot
+                    .filter(|_| !elements.is_empty())
+                    .map(|pivot| Drag {
+                        mode: gizmo.mode,
+                        start: drag.position,
+                        pivot,
+                        elements,
+                    });
+            }
+            DragPhase::Move => {
+                let Some(active) = &gizmo.drag else {
+                    continue;
+                };
+                for (entity, transform) in dragged(active, drag.position, grid.as_ref()) {
+                    if let Ok((mut current, ..)) = transforms.get_mut(entity) {
+                        *current = transform;
+                    }
+                }
+            }
+            DragPhase::End => {
+                let Some(active) = gizmo.drag.clone() else {
+                    continue;
+                };
+                let moved = dragged(&active, drag.position, grid.as_ref());
+                restore(&mut gizmo, &mut transforms);
+
+                let edits: Vec<Box<dyn Edit>> = moved
+                    .into_iter()
+                    .filter_map(|(entity, transform)| {
+                        let (_, id) = transforms.get(entity).ok()?;
+                        Some(Box::new(SetTransform::new(*id, transform)) as Box<dyn Edit>)
+                    })
+                    .collect();
+                let label = match active.mode {
+                    GizmoMode::Move => "Move",
+                    GizmoMode::Rotate => "Rotate",
+                    GizmoMode::Scale => "Scale",
+                };
+                commands.edit(EditGroup::new(label, edits));
+            }
+            DragPhase::Cancel => restore(&mut gizmo, &mut transforms),
+        }
+    }
+}
+
+/// Ends the drag in progress, putting the dragged elements back where they were before it.
+fn restore(gizmo: &mut TransformGizmo, transforms: &mut Query<(&mut Transform, &PersistentId)>) {
+    for element in gizmo.drag.take().into_iter().flat_map(|drag| drag.elements) {
+        if let Ok((mut transform, _)) = transforms.get_mut(element.entity) {
+            *transform = element.transform;
+        }
+    }
+}
+
+/// The transforms of the elements of `drag` with the cursor at `position`.
+///
+/// The drag is computed in world units and applied to each element in the space of its parent,
+/// so elements on scaled layers follow the cursor too.
+fn dragged(drag: &Drag, position: Vec2, grid: Option<&Grid>) -> Vec<(Entity, Transform)> {
+    let from_pivot = drag.start - drag.pivot;
+    let to_pivot = position - drag.pivot;
+    let mut delta = position - drag.start;
+    let mut angle = from_pivot.angle_to(to_pivot);
+    let factor = if from_pivot.length() > f32::EPSILON {
+        to_pivot.length() / from_pivot.length()
+    } else {
+        1.0
+    };
+
+    if let Some(grid) = grid {
+        // The pivot is snapped rather than every element, so the elements keep their layout.
+        delta = grid.snap_position(drag.pivot + delta) - drag.pivot;
+        angle = grid
+            .snap_rotation(Quat::from_rotation_z(angle))
+            .to_euler(EulerRot::ZYX)
+            .0;
+    }
+
+    drag.elements
+        .iter()
+        .map(|element| {
+            let original = element.transform;
+            let mut transform = original;
+            let target = match drag.mode {
+                GizmoMode::Move => element.position + delta,
+                GizmoMode::Rotate => {
+                    transform.rotation = Quat::from_rotation_z(angle) * original.rotation;
+                    drag.pivot + Vec2::from_angle(angle).rotate(element.position - drag.pivot)
+                }
+                GizmoMode::Scale => {
+                    transform.scale = (original.scale.truncate() * factor).extend(original.scale.z);
+                    drag.pivot + (element.position - drag.pivot) * factor
+                }
+            };
+
+            // The element moves by as much as its world position does, in the space of its parent.
+            let to_parent = |point: Vec2| {
+                element
+                    .to_parent
+                    .transform_point3(point.extend(0.0))
+                    .truncate()
+            };
+            let offset = to_parent(target) - to_parent(element.position);
+            transform.translation += offset.extend(0.0);
+
+            (element.entity, transform)
+        })
+        .collect()
+}
+
+/// Keeps [`TransformGizmo::pivot`] at the center of the selected elements.
+#[bevy_system]
+fn update_pivot(
+    mut gizmo: ResMut<TransformGizmo>,
+    selection: Res<Selection>,
+    globals: Query<&GlobalTransform>,
+) {
+    let positions: Vec<_> = selection
+        .iter()
+        .filter_map(|entity| globals.get(entity).ok())
+        .map(|global| global.translation().truncate())
+        .collect();
+
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "selections are far smaller than f32 can count exactly"
+    )]
+    let pivot =
+        (!positions.is_empty()).then(|| positions.iter().sum::<Vec2>() / positions.len() as f32);
+    if gizmo.pivot != pivot {
+        gizmo.pivot = pivot;
+    }
+}
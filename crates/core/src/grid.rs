@@ -0,0 +1,298 @@
+//! Draws the [`Grid`] over the levels.
+//!
+//! Each level with contents gets a [`GridOverlay`]: a sprite covering the level's contents,
+//! repeating the texture of the few cells tiling the grid, square or hexagonal, drawn by
+//! [`grid_tile_image`]. It's drawn above the lighting and below the region covers, so the viewport
+//! and the export cameras show the grid alike. Exports only keep it when [`ExportRequest::gridlines`](crate::ExportRequest)
+//! is set.
+
+use crate::export::ExportCapture;
+use crate::lighting::{LightMap, world_bounds};
+use crate::regions::RegionCover;
+use crate::{Action, ActionTriggered};
+use bevy::asset::RenderAssetUsages;
+use bevy::camera::primitives::Aabb;
+use bevy::image::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor};
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use dungeonrs_data::{Grid, Level, Region};
+use dungeonrs_macros::bevy_system;
+
+/// The depth of grid overlays within their level, above the light maps and below the region
+/// covers.
+const GRID_OVERLAY_Z: f32 = 925.0;
+
+/// Registers the [`GridOverlaySettings`] and the systems that draw the [`Grid`] over the levels.
+pub struct GridOverlayPlugin;
+
+impl Plugin for GridOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GridOverlaySettings>()
+            .add_message::<ActionTriggered>()
+            .add_systems(Update, toggle_grid)
+            .add_systems(
+                PostUpdate,
+                (update_grid_overlays, show_grid_overlays)
+                    .chain()
+                    .after(TransformSystems::Propagate),
+            );
+    }
+}
+
+/// Configures how the [`Grid`] is drawn.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct GridOverlaySettings {
+    /// Whether the grid is shown in the viewport.
+    pub visible: bool,
+    /// The colour of the grid lines.
+    pub color: Color,
+    /// The opacity of the grid lines, from 0 to 1.
+    pub opacity: f32,
+    /// The width of the grid lines, in world units.
+    pub line_width: f32,
+    /// The number of texels across a cell in the texture of the grid.
+    pub resolution: u32,
+}
+
+impl Default for GridOverlaySettings {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            color: Color::BLACK,
+            opacity: 0.5,
+            line_width: 2.0,
+            resolution: 128,
+        }
+    }
+}
+
+/// The grid drawn over a level, as a sprite repeating the texture of a tile of the grid.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq)]
+#[require(Sprite)]
+pub struct GridOverlay {
+    /// The level the grid is drawn over, the overlay is one of its children.
+    pub level: Entity,
+}
+
+/// The texture of the smallest rectangle tiling `grid`, its [`Grid::tile_size`], with
+/// `resolution` texels across a cell, opaque white along lines of `line_width` world units and
+/// transparent elsewhere.
+///
+/// The lines are drawn on both sides of each cell edge, so the lines of neighbouring tiles join
+/// into lines of the full width. The texture repeats, so a sprite showing more than its size
+/// draws several tiles.
+#[must_use]
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "the resolution of the tile is far below 2^23"
+)]
+pub fn grid_tile_image(grid: &Grid, line_width: f32, resolution: u32) -> Image {
+    let texels = tile_texels(grid, resolution);
+    let texel = grid.tile_size() / texels.as_vec2();
+    let half_width = line_width.max(0.0) / 2.0;
+
+    let mut data = Vec::with_capacity((texels.x * texels.y * 4) as usize);
+    for y in 0..texels.y {
+        for x in 0..texels.x {
+            // Rows go from the top of the tile to its bottom.
+            let position = grid.offset + Vec2::new(x as f32 + 0.5, -(y as f32 + 0.5)) * texel;
+            let alpha = if grid.edge_distance(position) < half_width {
+                255
+            } else {
+                0
+            };
+            data.extend_from_slice(&[255, 255, 255, alpha]);
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width: texels.x,
+            height: texels.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::linear()
+    });
+
+    image
+}
+
+/// The number of texels of the texture of a [`Grid::tile_size`] with `resolution` texels across a
+/// cell.
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "the resolution of the tile is far below 2^23"
+)]
+fn tile_texels(grid: &Grid, resolution: u32) -> UVec2 {
+    let texels_per_unit = resolution.max(1) as f32 / grid.cell_size.max(f32::EPSILON);
+
+    (grid.tile_size() * texels_per_unit)
+        .round()
+        .as_uvec2()
+        .max(UVec2::ONE)
+}
+
+/// The contents of the levels, leaving out what is drawn over them.
+type LevelContents<'w, 's> = Query<
+    'w,
+    's,
+    (&'static GlobalTransform, &'static Aabb),
+    (
+        Without<GridOverlay>,
+        Without<LightMap>,
+        Without<RegionCover>,
+        Without<Region>,
+    ),
+>;
+
+/// Sizes the grid overlay of every level to its contents, rebuilding the tile texture when the
+/// grid or the settings changed, and drops the overlays of levels that are empty or were
+/// despawned.
+#[bevy_system]
+#[allow(
+    clippy::too_many_arguments,
+    reason = "the overlays depend on the grid, the levels and their contents"
+)]
+fn update_grid_overlays(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut tile: Local<Option<Handle<Image>>>,
+    grid: Res<Grid>,
+    settings: Res<GridOverlaySettings>,
+    levels: Query<(Entity, &GlobalTransform), With<Level>>,
+    children: Query<&Children>,
+    contents: LevelContents,
+    mut overlays: Query<(Entity, &GridOverlay, &mut Sprite, &mut Transform)>,
+) {
+    if tile.is_none() || grid.is_changed() || settings.is_changed() {
+        let image = grid_tile_image(&grid, settings.line_width, settings.resolution);
+        // The overlays share the tile, replacing it keeps the handle.
+        match tile.as_ref().and_then(|tile| images.get_mut(tile)) {
+            Some(existing) => *existing = image,
+            None => *tile = Some(images.add(image)),
+        }
+    }
+    let Some(image) = tile.clone() else {
+        return;
+    };
+
+    let mut existing: HashMap<Entity, Entity> = overlays
+        .iter()
+        .map(|(entity, overlay, ..)| (overlay.level, entity))
+        .collect();
+    for (level, level_transform) in &levels {
+        let overlay = existing.remove(&level);
+        let area = children
+            .iter_descendants(level)
+            .filter_map(|entity| contents.get(entity).ok())
+            .map(|(transform, aabb)| world_bounds(transform, aabb))
+            .reduce(|area, bounds| area.union(bounds))
+            .filter(|area| !area.is_empty());
+        let Some(area) = area else {
+            if let Some(overlay) = overlay {
+                commands.entity(overlay).despawn();
+            }
+            continue;
+        };
+
+        // The overlay covers whole tiles, so its texture lines up with the grid.
+        let tile_size = grid.tile_size();
+        let min = ((area.min - grid.offset) / tile_size).floor();
+        let max = ((area.max - grid.offset) / tile_size).ceil().max(min + 1.0);
+        let area = Rect::from_corners(min * tile_size + grid.offset, max * tile_size + grid.offset);
+        let sprite = Sprite {
+            image: image.clone(),
+            custom_size: Some(area.size()),
+            rect: Some(Rect::from_corners(
+                Vec2::ZERO,
+                (max - min) * tile_texels(&grid, settings.resolution).as_vec2(),
+            )),
+            ..default()
+        };
+        let translation = level_transform
+            .affine()
+            .inverse()
+            .transform_point3(area.center().extend(0.0))
+            .truncate()
+            .extend(GRID_OVERLAY_Z);
+
+        let Some((_, _, mut current, mut transform)) =
+            overlay.and_then(|overlay| overlays.get_mut(overlay).ok())
+        else {
+            commands.spawn((
+                sprite,
+                GridOverlay { level },
+                Transform::from_translation(translation),
+                Visibility::Hidden,
+                ChildOf(level),
+            ));
+            continue;
+        };
+
+        // Overlays are only touched when they change, so they aren't flagged as changed every
+        // frame.
+        if current.image != sprite.image
+            || current.custom_size != sprite.custom_size
+            || current.rect != sprite.rect
+        {
+            current.image = sprite.image;
+            current.custom_size = sprite.custom_size;
+            current.rect = sprite.rect;
+        }
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+
+    for overlay in existing.into_values() {
+        commands.entity(overlay).despawn();
+    }
+}
+
+/// Shows or hides the grid when [`Action::ToggleGrid`] is triggered.
+#[bevy_system]
+fn toggle_grid(
+    mut actions: MessageReader<ActionTriggered>,
+    mut settings: ResMut<GridOverlaySettings>,
+) {
+    for triggered in actions.read() {
+        if triggered.action == Action::ToggleGrid {
+            settings.visible = !settings.visible;
+        }
+    }
+}
+
+/// Shows the grid overlays in the viewport as set by the [`GridOverlaySettings`], and in exports
+/// that burn the grid into the image.
+#[bevy_system]
+fn show_grid_overlays(
+    settings: Res<GridOverlaySettings>,
+    capture: Option<Res<ExportCapture>>,
+    mut overlays: Query<(&mut Sprite, &mut Visibility), With<GridOverlay>>,
+) {
+    let shown = capture.map_or(settings.visible, |capture| capture.gridlines());
+    let color = settings
+        .color
+        .with_alpha(settings.color.alpha() * settings.opacity.clamp(0.0, 1.0));
+    let expected = if shown {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    for (mut sprite, mut visibility) in &mut overlays {
+        if sprite.color != color {
+            sprite.color = color;
+        }
+        visibility.set_if_neq(expected);
+    }
+}
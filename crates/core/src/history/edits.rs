@@ -0,0 +1,1175 @@
+//! The [`Edit`]s changing the project hierarchy.
+
+use crate::persistence::capture_layer;
+use crate::{ElementData, GroupData, LayerData, LevelData, TerrainData, WallPathData};
+use bevy::math::Affine3A;
+use bevy::prelude::*;
+use dungeonrs_data::{Layer, Level, PersistentId, Project, Terrain, WallPath};
+
+use super::Edit;
+
+/// Moves, rotates or scales a node, such as an element or a layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetTransform {
+    /// The node to transform.
+    pub node: PersistentId,
+    /// The new transform of the node.
+    pub transform: Transform,
+    /// The transform of the node before the edit, captured when it's applied.
+    previous: Option<Transform>,
+}
+
+impl SetTransform {
+    /// Changes the transform of `node` to `transform`.
+    #[must_use]
+    pub fn new(node: PersistentId, transform: Transform) -> Self {
+        Self {
+            node,
+            transform,
+            previous: None,
+        }
+    }
+}
+
+impl Edit for SetTransform {
+    fn label(&self) -> String {
+        "Move".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(mut transform) =
+            find(world, self.node).and_then(|entity| world.get_mut::<Transform>(entity))
+        else {
+            return false;
+        };
+
+        self.previous = Some(std::mem::replace(&mut *transform, self.transform));
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(previous) = self.previous
+            && let Some(mut transform) =
+                find(world, self.node).and_then(|entity| world.get_mut::<Transform>(entity))
+        {
+            *transform = previous;
+        }
+    }
+}
+
+/// Renames a project, level or layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    /// The node to rename.
+    pub node: PersistentId,
+    /// The new name of the node.
+    pub name: String,
+    /// The name of the node before the edit, captured when it's applied.
+    previous: Option<String>,
+}
+
+impl Rename {
+    /// Renames `node` to `name`.
+    #[must_use]
+    pub fn new(node: PersistentId, name: impl Into<String>) -> Self {
+        Self {
+            node,
+            name: name.into(),
+            previous: None,
+        }
+    }
+}
+
+impl Edit for Rename {
+    fn label(&self) -> String {
+        format!("Rename to \"{}\"", self.name)
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(entity) = find(world, self.node) else {
+            return false;
+        };
+
+        self.previous = set_name(world, entity, self.name.clone());
+        self.previous.is_some()
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(previous) = self.previous.clone()
+            && let Some(entity) = find(world, self.node)
+        {
+            set_name(world, entity, previous);
+        }
+    }
+}
+
+/// Places an element on a layer, such as a texture dragged from the asset browser.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaceElement {
+    /// The layer the element is placed on.
+    pub layer: PersistentId,
+    /// The placed element.
+    pub element: ElementData,
+}
+
+impl PlaceElement {
+    /// Places `element` on `layer`.
+    #[must_use]
+    pub fn new(layer: PersistentId, element: ElementData) -> Self {
+        Self { layer, element }
+    }
+}
+
+impl Edit for PlaceElement {
+    fn label(&self) -> String {
+        "Place element".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(layer) =
+            find(world, self.layer).filter(|layer| world.get::<Layer>(*layer).is_some())
+        else {
+            return false;
+        };
+
+        self.element.restore(&mut world.commands(), layer);
+        world.flush();
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(element) = find(world, PersistentId(self.element.id)) {
+            world.despawn(element);
+        }
+    }
+}
+
+/// Removes an element from its layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoveElement {
+    /// The element to remove.
+    pub element: PersistentId,
+    /// The layer, position within it and contents of the element, captured when it's removed.
+    removed: Option<(PersistentId, usize, ElementData)>,
+}
+
+impl RemoveElement {
+    /// Removes `element`.
+    #[must_use]
+    pub fn new(element: PersistentId) -> Self {
+        Self {
+            element,
+            removed: None,
+        }
+    }
+}
+
+impl Edit for RemoveElement {
+    fn label(&self) -> String {
+        "Remove element".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(element) = find(world, self.element) else {
+            return false;
+        };
+        let (Some(data), Some((layer, index))) = (
+            ElementData::capture(world, element),
+            persistent_position(world, element),
+        ) else {
+            return false;
+        };
+
+        world.despawn(element);
+        self.removed = Some((layer, index, data));
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        let Some((layer, index, data)) = &self.removed else {
+            return;
+        };
+        let Some(layer) = find(world, *layer) else {
+            return;
+        };
+
+        let element = data.restore(&mut world.commands(), layer);
+        world.flush();
+        world.entity_mut(layer).insert_child(*index, element);
+    }
+}
+
+/// Places a group of elements on a layer, such as an instance of a prefab.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaceGroup {
+    /// The layer the group is placed on.
+    pub layer: PersistentId,
+    /// The placed group and its elements.
+    pub group: GroupData,
+}
+
+impl PlaceGroup {
+    /// Places `group` on `layer`.
+    #[must_use]
+    pub fn new(layer: PersistentId, group: GroupData) -> Self {
+        Self { layer, group }
+    }
+}
+
+impl Edit for PlaceGroup {
+    fn label(&self) -> String {
+        format!("Place {}", self.group.name)
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(layer) =
+            find(world, self.layer).filter(|layer| world.get::<Layer>(*layer).is_some())
+        else {
+            return false;
+        };
+
+        self.group.restore(&mut world.commands(), layer);
+        world.flush();
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(group) = find(world, PersistentId(self.group.id)) {
+            world.despawn(group);
+        }
+    }
+}
+
+/// Dissolves a group, putting its elements on the group's layer where the group was, so they can
+/// be moved one by one again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ungroup {
+    /// The group to dissolve.
+    pub group: PersistentId,
+    /// The layer, position within it and contents of the group, captured when it's dissolved.
+    removed: Option<(PersistentId, usize, GroupData)>,
+}
+
+impl Ungroup {
+    /// Dissolves `group`.
+    #[must_use]
+    pub fn new(group: PersistentId) -> Self {
+        Self {
+            group,
+            removed: None,
+        }
+    }
+}
+
+impl Edit for Ungroup {
+    fn label(&self) -> String {
+        "Ungroup".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(group) = find(world, self.group) else {
+            return false;
+        };
+        let (Some(data), Some((layer, index))) =
+            (GroupData::capture(world, group), position(world, group))
+        else {
+            return false;
+        };
+        let Some(layer_id) = world.get::<PersistentId>(layer).copied() else {
+            return false;
+        };
+
+        world.despawn(group);
+        let mut commands = world.commands();
+        let elements: Vec<_> = data
+            .elements
+            .iter()
+            .map(|element| {
+                // The elements keep their place on the map, relative to the layer now.
+                let mut element = element.clone();
+                element.transform = data.transform.mul_transform(element.transform);
+                element.restore(&mut commands, layer)
+            })
+            .collect();
+        world.flush();
+        world.entity_mut(layer).insert_children(index, &elements);
+        self.removed = Some((layer_id, index, data));
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        let Some((layer, index, data)) = &self.removed else {
+            return;
+        };
+        for element in &data.elements {
+            if let Some(element) = find(world, PersistentId(element.id)) {
+                world.despawn(element);
+            }
+        }
+        let Some(layer) = find(world, *layer) else {
+            return;
+        };
+
+        let group = data.restore(&mut world.commands(), layer);
+        world.flush();
+        world.entity_mut(layer).insert_child(*index, group);
+    }
+}
+
+/// Draws a wall path on a layer, such as one drawn with the wall tool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaceWallPath {
+    /// The layer the wall path is drawn on.
+    pub layer: PersistentId,
+    /// The drawn wall path.
+    pub path: WallPathData,
+}
+
+impl PlaceWallPath {
+    /// Draws `path` on `layer`.
+    #[must_use]
+    pub fn new(layer: PersistentId, path: WallPathData) -> Self {
+        Self { layer, path }
+    }
+}
+
+impl Edit for PlaceWallPath {
+    fn label(&self) -> String {
+        "Draw wall".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(layer) =
+            find(world, self.layer).filter(|layer| world.get::<Layer>(*layer).is_some())
+        else {
+            return false;
+        };
+
+        self.path.restore(&mut world.commands(), layer);
+        world.flush();
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(path) = find(world, PersistentId(self.path.id)) {
+            world.despawn(path);
+        }
+    }
+}
+
+/// Moves the control points of a wall path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetWallPoints {
+    /// The wall path to change.
+    pub path: PersistentId,
+    /// The new control points, relative to the wall path's transform.
+    pub points: Vec<Vec2>,
+    /// The control points before the edit, captured when it's applied.
+    previous: Option<Vec<Vec2>>,
+}
+
+impl SetWallPoints {
+    /// Changes the control points of `path` to `points`.
+    #[must_use]
+    pub fn new(path: PersistentId, points: impl Into<Vec<Vec2>>) -> Self {
+        Self {
+            path,
+            points: points.into(),
+            previous: None,
+        }
+    }
+}
+
+impl Edit for SetWallPoints {
+    fn label(&self) -> String {
+        "Edit wall".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(mut path) =
+            find(world, self.path).and_then(|entity| world.get_mut::<WallPath>(entity))
+        else {
+            return false;
+        };
+
+        self.previous = Some(std::mem::replace(&mut path.points, self.points.clone()));
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(previous) = self.previous.clone()
+            && let Some(mut path) =
+                find(world, self.path).and_then(|entity| world.get_mut::<WallPath>(entity))
+        {
+            path.points = previous;
+        }
+    }
+}
+
+/// Adds a terrain to a layer, making its ground paintable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddTerrain {
+    /// The layer the terrain is added to.
+    pub layer: PersistentId,
+    /// The added terrain.
+    pub terrain: TerrainData,
+}
+
+impl AddTerrain {
+    /// Adds `terrain` to `layer`.
+    #[must_use]
+    pub fn new(layer: PersistentId, terrain: TerrainData) -> Self {
+        Self { layer, terrain }
+    }
+}
+
+impl Edit for AddTerrain {
+    fn label(&self) -> String {
+        "Add terrain".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(layer) =
+            find(world, self.layer).filter(|layer| world.get::<Layer>(*layer).is_some())
+        else {
+            return false;
+        };
+
+        self.terrain.restore(&mut world.commands(), layer);
+        world.flush();
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(terrain) = find(world, PersistentId(self.terrain.id)) {
+            world.despawn(terrain);
+        }
+    }
+}
+
+/// Paints the splat map of a terrain, such as a stroke of the terrain brush.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetTerrainWeights {
+    /// The terrain to paint.
+    pub terrain: PersistentId,
+    /// The new splat map, in the layout of [`Terrain::weights`].
+    pub weights: Vec<[u8; 4]>,
+    /// The splat map before the edit, captured when it's applied.
+    previous: Option<Vec<[u8; 4]>>,
+}
+
+impl SetTerrainWeights {
+    /// Changes the splat map of `terrain` to `weights`.
+    #[must_use]
+    pub fn new(terrain: PersistentId, weights: impl Into<Vec<[u8; 4]>>) -> Self {
+        Self {
+            terrain,
+            weights: weights.into(),
+            previous: None,
+        }
+    }
+}
+
+impl Edit for SetTerrainWeights {
+    fn label(&self) -> String {
+        "Paint terrain".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(mut terrain) = find(world, self.terrain)
+            .and_then(|entity| world.get_mut::<Terrain>(entity))
+            .filter(|terrain| terrain.weights.len() == self.weights.len())
+        else {
+            return false;
+        };
+
+        self.previous = Some(std::mem::replace(
+            &mut terrain.weights,
+            self.weights.clone(),
+        ));
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(previous) = self.previous.clone()
+            && let Some(mut terrain) =
+                find(world, self.terrain).and_then(|entity| world.get_mut::<Terrain>(entity))
+        {
+            terrain.weights = previous;
+        }
+    }
+}
+
+/// Adds a layer to a level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddLayer {
+    /// The level the layer is added to.
+    pub level: PersistentId,
+    /// The position of the layer among the level's layers, at the top when out of bounds.
+    pub index: usize,
+    /// The added layer and its contents.
+    pub layer: LayerData,
+}
+
+impl AddLayer {
+    /// Adds `layer` on top of the layers of `level`.
+    #[must_use]
+    pub fn new(level: PersistentId, layer: LayerData) -> Self {
+        Self {
+            level,
+            index: usize::MAX,
+            layer,
+        }
+    }
+
+    /// Adds the layer at `index` among the level's layers instead, `0` being the bottom.
+    #[must_use]
+    pub fn at(mut self, index: usize) -> Self {
+        self.index = index;
+        self
+    }
+}
+
+impl Edit for AddLayer {
+    fn label(&self) -> String {
+        format!("Add layer \"{}\"", self.layer.name)
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(level) =
+            find(world, self.level).filter(|level| world.get::<Level>(*level).is_some())
+        else {
+            return false;
+        };
+
+        let layer = self.layer.restore(&mut world.commands(), level);
+        world.flush();
+        let count = world
+            .get::<Children>(level)
+            .map_or(0, RelationshipTarget::len);
+        world
+            .entity_mut(level)
+            .insert_child(self.index.min(count.saturating_sub(1)), layer);
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(layer) = find(world, PersistentId(self.layer.id)) {
+            world.despawn(layer);
+        }
+    }
+}
+
+/// Removes a layer and its contents from its level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoveLayer {
+    /// The layer to remove.
+    pub layer: PersistentId,
+    /// The level, position within it and contents of the layer, captured when it's removed.
+    removed: Option<(PersistentId, usize, LayerData)>,
+}
+
+impl RemoveLayer {
+    /// Removes `layer` and its contents.
+    #[must_use]
+    pub fn new(layer: PersistentId) -> Self {
+        Self {
+            layer,
+            removed: None,
+        }
+    }
+}
+
+impl Edit for RemoveLayer {
+    fn label(&self) -> String {
+        "Remove layer".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(layer) = find(world, self.layer) else {
+            return false;
+        };
+        let (Some(data), Some((level, index))) = (
+            capture_layer(world, layer),
+            persistent_position(world, layer),
+        ) else {
+            return false;
+        };
+
+        world.despawn(layer);
+        self.removed = Some((level, index, data));
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        let Some((level, index, data)) = &self.removed else {
+            return;
+        };
+        let Some(level) = find(world, *level) else {
+            return;
+        };
+
+        let layer = data.restore(&mut world.commands(), level);
+        world.flush();
+        world.entity_mut(level).insert_child(*index, layer);
+    }
+}
+
+/// Moves a layer up or down among the layers of its level, changing the order they're drawn in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveLayer {
+    /// The layer to move.
+    pub layer: PersistentId,
+    /// The new position of the layer, `0` being the bottom.
+    pub index: usize,
+    /// The position of the layer before the edit, captured when it's applied.
+    previous: Option<usize>,
+}
+
+impl MoveLayer {
+    /// Moves `layer` to `index` among the layers of its level.
+    #[must_use]
+    pub fn new(layer: PersistentId, index: usize) -> Self {
+        Self {
+            layer,
+            index,
+            previous: None,
+        }
+    }
+}
+
+impl Edit for MoveLayer {
+    fn label(&self) -> String {
+        "Move layer".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(layer) = find(world, self.layer) else {
+            return false;
+        };
+        let Some((level, index)) = position(world, layer) else {
+            return false;
+        };
+
+        world.entity_mut(level).insert_child(self.index, layer);
+        self.previous = Some(index);
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(previous) = self.previous
+            && let Some(layer) = find(world, self.layer)
+            && let Some((level, _)) = position(world, layer)
+        {
+            world.entity_mut(level).insert_child(previous, layer);
+        }
+    }
+}
+
+/// Adds a level and its contents to a project, such as a new floor of a dungeon.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddLevel {
+    /// The project the level is added to.
+    pub project: PersistentId,
+    /// The position of the level among the project's levels, at the end when out of bounds.
+    pub index: usize,
+    /// The added level and its contents.
+    pub level: LevelData,
+}
+
+impl AddLevel {
+    /// Adds `level` after the levels of `project`.
+    #[must_use]
+    pub fn new(project: PersistentId, level: LevelData) -> Self {
+        Self {
+            project,
+            index: usize::MAX,
+            level,
+        }
+    }
+
+    /// Adds the level at `index` among the project's levels instead, `0` being the first.
+    #[must_use]
+    pub fn at(mut self, index: usize) -> Self {
+        self.index = index;
+        self
+    }
+}
+
+impl Edit for AddLevel {
+    fn label(&self) -> String {
+        format!("Add level \"{}\"", self.level.name)
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(project) =
+            find(world, self.project).filter(|project| world.get::<Project>(*project).is_some())
+        else {
+            return false;
+        };
+
+        let level = self.level.restore(&mut world.commands(), project);
+        world.flush();
+        let count = world
+            .get::<Children>(project)
+            .map_or(0, RelationshipTarget::len);
+        world
+            .entity_mut(project)
+            .insert_child(self.index.min(count.saturating_sub(1)), level);
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(level) = find(world, PersistentId(self.level.id)) {
+            world.despawn(level);
+        }
+    }
+}
+
+/// Removes a level and its contents from its project.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoveLevel {
+    /// The level to remove.
+    pub level: PersistentId,
+    /// The project, position within it and contents of the level, captured when it's removed.
+    removed: Option<(PersistentId, usize, LevelData)>,
+}
+
+impl RemoveLevel {
+    /// Removes `level` and its contents.
+    #[must_use]
+    pub fn new(level: PersistentId) -> Self {
+        Self {
+            level,
+            removed: None,
+        }
+    }
+}
+
+impl Edit for RemoveLevel {
+    fn label(&self) -> String {
+        "Remove level".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(level) = find(world, self.level) else {
+            return false;
+        };
+        let (Some(data), Some((project, index))) = (
+            LevelData::capture(world, level),
+            persistent_position(world, level),
+        ) else {
+            return false;
+        };
+
+        world.despawn(level);
+        self.removed = Some((project, index, data));
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        let Some((project, index, data)) = &self.removed else {
+            return;
+        };
+        let Some(project) = find(world, *project) else {
+            return;
+        };
+
+        let level = data.restore(&mut world.commands(), project);
+        world.flush();
+        world.entity_mut(project).insert_child(*index, level);
+    }
+}
+
+/// Moves a level up or down among the levels of its project, such as to order the floors of a
+/// dungeon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveLevel {
+    /// The level to move.
+    pub level: PersistentId,
+    /// The new position of the level, `0` being the first.
+    pub index: usize,
+    /// The position of the level before the edit, captured when it's applied.
+    previous: Option<usize>,
+}
+
+impl MoveLevel {
+    /// Moves `level` to `index` among the levels of its project.
+    #[must_use]
+    pub fn new(level: PersistentId, index: usize) -> Self {
+        Self {
+            level,
+            index,
+            previous: None,
+        }
+    }
+}
+
+impl Edit for MoveLevel {
+    fn label(&self) -> String {
+        "Move level".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(level) = find(world, self.level) else {
+            return false;
+        };
+        let Some((project, index)) = position(world, level) else {
+            return false;
+        };
+
+        world.entity_mut(project).insert_child(self.index, level);
+        self.previous = Some(index);
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(previous) = self.previous
+            && let Some(level) = find(world, self.level)
+            && let Some((project, _)) = position(world, level)
+        {
+            world.entity_mut(project).insert_child(previous, level);
+        }
+    }
+}
+
+/// Locks or unlocks a layer, protecting its contents from being selected and edited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetLayerLocked {
+    /// The layer to lock or unlock.
+    pub layer: PersistentId,
+    /// Whether the layer is locked.
+    pub locked: bool,
+    /// Whether the layer was locked before the edit, captured when it's applied.
+    previous: Option<bool>,
+}
+
+impl SetLayerLocked {
+    /// Locks `layer` if `locked`, unlocks it otherwise.
+    #[must_use]
+    pub fn new(layer: PersistentId, locked: bool) -> Self {
+        Self {
+            layer,
+            locked,
+            previous: None,
+        }
+    }
+}
+
+impl Edit for SetLayerLocked {
+    fn label(&self) -> String {
+        if self.locked {
+            "Lock layer"
+        } else {
+            "Unlock layer"
+        }
+        .to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(mut layer) =
+            find(world, self.layer).and_then(|layer| world.get_mut::<Layer>(layer))
+        else {
+            return false;
+        };
+
+        self.previous = Some(std::mem::replace(&mut layer.locked, self.locked));
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(previous) = self.previous
+            && let Some(mut layer) =
+                find(world, self.layer).and_then(|layer| world.get_mut::<Layer>(layer))
+        {
+            layer.locked = previous;
+        }
+    }
+}
+
+/// Hides or shows a layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetLayerHidden {
+    /// The layer to hide or show.
+    pub layer: PersistentId,
+    /// Whether the layer is hidden.
+    pub hidden: bool,
+    /// Whether the layer was hidden before the edit, captured when it's applied.
+    previous: Option<bool>,
+}
+
+impl SetLayerHidden {
+    /// Hides `layer` if `hidden`, shows it otherwise.
+    #[must_use]
+    pub fn new(layer: PersistentId, hidden: bool) -> Self {
+        Self {
+            layer,
+            hidden,
+            previous: None,
+        }
+    }
+}
+
+impl Edit for SetLayerHidden {
+    fn label(&self) -> String {
+        if self.hidden {
+            "Hide layer"
+        } else {
+            "Show layer"
+        }
+        .to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(mut layer) =
+            find(world, self.layer).and_then(|layer| world.get_mut::<Layer>(layer))
+        else {
+            return false;
+        };
+
+        self.previous = Some(std::mem::replace(&mut layer.hidden, self.hidden));
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(previous) = self.previous
+            && let Some(mut layer) =
+                find(world, self.layer).and_then(|layer| world.get_mut::<Layer>(layer))
+        {
+            layer.hidden = previous;
+        }
+    }
+}
+
+/// Changes the opacity the contents of a layer are drawn with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetLayerOpacity {
+    /// The layer to change.
+    pub layer: PersistentId,
+    /// The opacity of the layer, between `0.0` and `1.0`.
+    pub opacity: f32,
+    /// The opacity of the layer before the edit, captured when it's applied.
+    previous: Option<f32>,
+}
+
+impl SetLayerOpacity {
+    /// Draws the contents of `layer` with `opacity`, clamped between `0.0` and `1.0`.
+    #[must_use]
+    pub fn new(layer: PersistentId, opacity: f32) -> Self {
+        Self {
+            layer,
+            opacity: opacity.clamp(0.0, 1.0),
+            previous: None,
+        }
+    }
+}
+
+impl Edit for SetLayerOpacity {
+    fn label(&self) -> String {
+        "Change layer opacity".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let Some(mut layer) =
+            find(world, self.layer).and_then(|layer| world.get_mut::<Layer>(layer))
+        else {
+            return false;
+        };
+
+        self.previous = Some(std::mem::replace(&mut layer.opacity, self.opacity));
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(previous) = self.previous
+            && let Some(mut layer) =
+                find(world, self.layer).and_then(|layer| world.get_mut::<Layer>(layer))
+        {
+            layer.opacity = previous;
+        }
+    }
+}
+
+/// Moves the contents of a layer on top of the contents of another layer, and removes the emptied
+/// layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeLayer {
+    /// The layer whose contents are moved, removed by the edit.
+    pub layer: PersistentId,
+    /// The layer receiving the contents.
+    pub into: PersistentId,
+    /// The level, position within it and contents of both layers, captured when they're merged.
+    merged: Vec<(PersistentId, usize, LayerData)>,
+}
+
+impl MergeLayer {
+    /// Merges `layer` into `into`.
+    #[must_use]
+    pub fn new(layer: PersistentId, into: PersistentId) -> Self {
+        Self {
+            layer,
+            into,
+            merged: Vec::new(),
+        }
+    }
+}
+
+impl Edit for MergeLayer {
+    fn label(&self) -> String {
+        "Merge layers".to_owned()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let (Some(layer), Some(into)) = (find(world, self.layer), find(world, self.into)) else {
+            return false;
+        };
+        if layer == into {
+            return false;
+        }
+        let (Some(layer_data), Some(into_data), Some(layer_position), Some(into_position)) = (
+            capture_layer(world, layer),
+            capture_layer(world, into),
+            persistent_position(world, layer),
+            persistent_position(world, into),
+        ) else {
+            return false;
+        };
+
+        // The contents keep their place on the map, whatever the transforms of both layers. The
+        // depth of the layers only orders them, so it's left out.
+        let flat = |entity| {
+            let transform = world.get::<Transform>(entity).copied().unwrap_or_default();
+            Transform::from_translation(transform.translation.truncate().extend(0.0))
+                .with_rotation(transform.rotation)
+                .with_scale(transform.scale)
+                .compute_affine()
+        };
+        let to_into = flat(into).inverse() * flat(layer);
+        let children: Vec<_> = world
+            .get::<Children>(layer)
+            .map(|children| children.to_vec())
+            .unwrap_or_default();
+        if to_into != Affine3A::IDENTITY {
+            for child in &children {
+                if let Some(mut transform) = world.get_mut::<Transform>(*child) {
+                    *transform =
+                        Transform::from_matrix(Mat4::from(to_into * transform.compute_affine()));
+                }
+            }
+        }
+
+        world.entity_mut(into).add_children(&children);
+        world.despawn(layer);
+        self.merged = vec![
+            (into_position.0, into_position.1, into_data),
+            (layer_position.0, layer_position.1, layer_data),
+        ];
+        // Restoring the lower layer first puts both back at their position.
+        self.merged.sort_by_key(|(_, index, _)| *index);
+        true
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        if let Some(into) = find(world, self.into) {
+            world.despawn(into);
+        }
+        for (level, index, data) in &self.merged {
+            let Some(level) = find(world, *level) else {
+                continue;
+            };
+
+            let layer = data.restore(&mut world.commands(), level);
+            world.flush();
+            world.entity_mut(level).insert_child(*index, layer);
+        }
+    }
+}
+
+/// Applies several edits as one, so they're undone and redone together, such as moving every
+/// selected element.
+pub struct EditGroup {
+    /// The description shown to the user.
+    pub label: String,
+    /// The edits, applied in order and reverted in reverse order.
+    pub edits: Vec<Box<dyn Edit>>,
+    /// Whether each edit applied, captured when the group is applied.
+    applied: Vec<bool>,
+}
+
+impl EditGroup {
+    /// Groups `edits` under `label`.
+    #[must_use]
+    pub fn new(label: impl Into<String>, edits: Vec<Box<dyn Edit>>) -> Self {
+        Self {
+            label: label.into(),
+            edits,
+            applied: Vec::new(),
+        }
+    }
+}
+
+impl Edit for EditGroup {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        self.applied = self
+            .edits
+            .iter_mut()
+            .map(|edit| edit.apply(world))
+            .collect();
+        self.applied.contains(&true)
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        for (edit, applied) in self.edits.iter_mut().zip(&self.applied).rev() {
+            if *applied {
+                edit.revert(world);
+            }
+        }
+    }
+}
+
+/// Finds the node identified by `id`.
+fn find(world: &mut World, id: PersistentId) -> Option<Entity> {
+    world
+        .query::<(Entity, &PersistentId)>()
+        .iter(world)
+        .find_map(|(entity, candidate)| (*candidate == id).then_some(entity))
+}
+
+/// Returns the parent of `entity` and the position of `entity` among its children.
+fn position(world: &World, entity: Entity) -> Option<(Entity, usize)> {
+    let parent = world.get::<ChildOf>(entity)?.parent();
+    let index = world
+        .get::<Children>(parent)?
+        .iter()
+        .position(|child| child == entity)?;
+
+    Some((parent, index))
+}
+
+/// Returns the [`PersistentId`] of the parent of `entity` and the position of `entity` among its
+/// children, so it can be put back after it's despawned.
+fn persistent_position(world: &World, entity: Entity) -> Option<(PersistentId, usize)> {
+    let (parent, index) = position(world, entity)?;
+
+    Some((*world.get::<PersistentId>(parent)?, index))
+}
+
+/// Sets the name of the project, level or layer `entity` to `name` and returns its previous name.
+///
+/// Returns `None` if `entity` isn't a project, level or layer.
+fn set_name(world: &mut World, entity: Entity, name: String) -> Option<String> {
+    let mut entity = world.get_entity_mut(entity).ok()?;
+    if let Some(mut project) = entity.get_mut::<Project>() {
+        return Some(std::mem::replace(&mut project.name, name));
+    }
+    if let Some(mut level) = entity.get_mut::<Level>() {
+        return Some(std::mem::replace(&mut level.name, name));
+    }
+    let mut layer = entity.get_mut::<Layer>()?;
+
+    Some(std::mem::replace(&mut layer.name, name))
+}
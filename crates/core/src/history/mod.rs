@@ -0,0 +1,264 @@
+//! Records the changes made to the project hierarchy so they can be undone and redone.
+//!
+//! Changes are made through [`Edit`]s applied with [`HistoryCommandsExt::edit`], which records
+//! them in the [`History`]. Edits refer to nodes by their [`PersistentId`](dungeonrs_data::PersistentId)
+//! rather than their entity, since undoing a removal spawns the node again as a new entity.
+
+mod edits;
+#[cfg(test)]
+mod tests;
+
+pub use edits::{
+    AddLayer, AddLevel, AddTerrain, EditGroup, MergeLayer, MoveLayer, MoveLevel, PlaceElement,
+    PlaceGroup, PlaceWallPath, RemoveElement, RemoveLayer, RemoveLevel, Rename, SetLayerHidden,
+    SetLayerLocked, SetLayerOpacity, SetTerrainWeights, SetTransform, SetWallPoints, Ungroup,
+};
+
+use crate::{Action, ActionTriggered, Configuration, ConfigurationChanged, ConfigurationSetting};
+use bevy::prelude::*;
+use dungeonrs_macros::bevy_system;
+use std::collections::VecDeque;
+
+/// Records edits and undoes or redoes them on request, or with the [`Action::Undo`] and
+/// [`Action::Redo`] shortcuts.
+///
+/// Add the [`ConfigurationPlugin`](crate::ConfigurationPlugin) to keep as many edits as the
+/// [`Configuration`] sets.
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<History>()
+            .add_message::<ActionTriggered>()
+            .add_message::<UndoRedo>()
+            .add_message::<ConfigurationChanged>()
+            .add_systems(
+                Update,
+                (apply_history_depth, history_shortcuts, undo_redo).chain(),
+            );
+    }
+}
+
+/// A reversible change to the project hierarchy.
+///
+/// Edits capture whatever they need to revert themselves when they're applied, so an edit only
+/// has to describe the change, such as the new name of a level.
+pub trait Edit: Send + Sync + 'static {
+    /// A short description shown to the user, such as "Remove layer".
+    fn label(&self) -> String;
+
+    /// Applies the edit to `world`.
+    ///
+    /// Returns `false` when the edit doesn't apply, for example because the node it changes no
+    /// longer exists, in which case it's not recorded.
+    fn apply(&mut self, world: &mut World) -> bool;
+
+    /// Reverts the edit, restoring `world` to how it was before it was applied.
+    fn revert(&mut self, world: &mut World);
+}
+
+/// Undoes the most recent edit or redoes the most recently undone edit.
+///
+/// Requests are carried out in the order they're written, so undoing twice then redoing once
+/// leaves a single edit undone.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UndoRedo {
+    /// Undoes the most recent edit.
+    Undo,
+    /// Redoes the most recently undone edit.
+    Redo,
+}
+
+/// The applied edits that can be undone and the undone edits that can be redone.
+#[derive(Resource)]
+pub struct History {
+    /// The edits that can be undone, oldest first.
+    undo: VecDeque<Box<dyn Edit>>,
+    /// The edits that can be redone, most recently undone last.
+    redo: Vec<Box<dyn Edit>>,
+    /// The most edits kept, the oldest are dropped once there are more.
+    depth: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DEPTH)
+    }
+}
+
+impl History {
+    /// The number of edits kept by default.
+    pub const DEFAULT_DEPTH: usize = 100;
+
+    /// Creates an empty history keeping up to `depth` edits.
+    #[must_use]
+    pub fn new(depth: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth,
+        }
+    }
+
+    /// The most edits kept.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Changes the most edits kept, dropping the oldest edits if there are more.
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        self.trim();
+    }
+
+    /// Returns whether there's an edit to undo.
+    #[must_use]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Returns whether there's an edit to redo.
+    #[must_use]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// The label of the edit [`UndoRedo::Undo`] reverts.
+    #[must_use]
+    pub fn undo_label(&self) -> Option<String> {
+        self.undo.back().map(|edit| edit.label())
+    }
+
+    /// The label of the edit [`UndoRedo::Redo`] applies again.
+    #[must_use]
+    pub fn redo_label(&self) -> Option<String> {
+        self.redo.last().map(|edit| edit.label())
+    }
+
+    /// Forgets every edit, for example when another project is opened.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Applies `edit` to `world` and records it, forgetting the undone edits.
+    ///
+    /// Returns whether the edit applied.
+    pub fn record(world: &mut World, mut edit: impl Edit) -> bool {
+        if !edit.apply(world) {
+            return false;
+        }
+
+        if let Some(mut history) = world.get_resource_mut::<History>() {
+            history.redo.clear();
+            history.undo.push_back(Box::new(edit));
+            history.trim();
+        }
+        true
+    }
+
+    /// Reverts the most recent edit in `world`.
+    ///
+    /// Returns `false` if there was nothing to undo.
+    pub fn undo(world: &mut World) -> bool {
+        world
+            .get_resource_mut::<History>()
+            .and_then(|mut history| history.undo.pop_back())
+            .is_some_and(|mut edit| {
+                edit.revert(world);
+                if let Some(mut history) = world.get_resource_mut::<History>() {
+                    history.redo.push(edit);
+                }
+                true
+            })
+    }
+
+    /// Applies the most recently undone edit to `world` again.
+    ///
+    /// Returns `false` if there was nothing to redo. An edit that no longer applies is dropped.
+    pub fn redo(world: &mut World) -> bool {
+        world
+            .get_resource_mut::<History>()
+            .and_then(|mut history| history.redo.pop())
+            .is_some_and(|mut edit| {
+                if edit.apply(world)
+                    && let Some(mut history) = world.get_resource_mut::<History>()
+                {
+                    history.undo.push_back(edit);
+                    history.trim();
+                }
+                true
+            })
+    }
+
+    /// Drops the oldest edits until there are at most `depth` left.
+    fn trim(&mut self) {
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+}
+
+/// Applies [`Edit`]s through [`Commands`], recording them in the [`History`].
+pub trait HistoryCommandsExt {
+    /// Applies `edit` and records it so it can be undone.
+    fn edit(&mut self, edit: impl Edit);
+}
+
+impl HistoryCommandsExt for Commands<'_, '_> {
+    fn edit(&mut self, edit: impl Edit) {
+        self.queue(move |world: &mut World| {
+            History::record(world, edit);
+        });
+    }
+}
+
+/// Keeps as many edits as the [`Configuration`] sets once it changed.
+#[bevy_system]
+fn apply_history_depth(
+    mut changes: MessageReader<ConfigurationChanged>,
+    configuration: Option<Res<Configuration>>,
+    mut history: ResMut<History>,
+) {
+    let resized = changes
+        .read()
+        .any(|change| change.setting == ConfigurationSetting::History);
+    if let Some(configuration) = configuration.filter(|_| resized) {
+        history.set_depth(configuration.history_depth());
+    }
+}
+
+/// Writes an [`UndoRedo`] for the triggered [`Action::Undo`] and [`Action::Redo`].
+#[bevy_system]
+fn history_shortcuts(
+    mut actions: MessageReader<ActionTriggered>,
+    mut requests: MessageWriter<UndoRedo>,
+) {
+    for triggered in actions.read() {
+        match triggered.action {
+            Action::Undo => {
+                requests.write(UndoRedo::Undo);
+            }
+            Action::Redo => {
+                requests.write(UndoRedo::Redo);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Undoes and redoes the requested edits, in the order they were requested.
+#[bevy_system]
+fn undo_redo(mut commands: Commands, mut requests: MessageReader<UndoRedo>) {
+    for request in requests.read() {
+        match request {
+            UndoRedo::Undo => commands.queue(|world: &mut World| {
+                History::undo(world);
+            }),
+            UndoRedo::Redo => commands.queue(|world: &mut World| {
+                History::redo(world);
+            }),
+        }
+    }
+}
@@ -0,0 +1,566 @@
+//! Applies, undoes and redoes every [`Edit`] on a world holding a project, checking that undoing
+//! restores the project exactly and redoing makes the same change again.
+#![allow(clippy::missing_panics_doc)]
+
+use super::*;
+use crate::{ElementData, GroupData, LayerData, LevelData, SaveFile, TerrainData, WallPathData};
+use bevy::asset::uuid::Uuid;
+use dungeonrs_data::PersistentId;
+
+/// Spawns a project with two levels, the first holding three layers of three elements each, and
+/// returns the project.
+fn spawn_project(world: &mut World) -> Entity {
+    let mut level = LevelData::new("Ground floor");
+    level.layers = (1..=3)
+        .map(|index| {
+            let mut layer = LayerData::new(format!("Layer {index}"));
+            layer.elements = (0..3).map(element).collect();
+            layer
+        })
+        .collect();
+    let save = SaveFile {
+        id: Uuid::new_v4(),
+        name: "Dungeon".into(),
+        levels: vec![level, LevelData::new("Cellar")],
+    };
+
+    let project = save.restore(&mut world.commands());
+    world.flush();
+    project
+}
+
+/// Creates a world holding a project and an empty [`History`], and returns the world and the
+/// project.
+fn setup() -> (World, Entity) {
+    let mut world = World::new();
+    world.init_resource::<History>();
+    let project = spawn_project(&mut world);
+
+    (world, project)
+}
+
+/// An element placed `index` tiles to the right.
+fn element(index: u8) -> ElementData {
+    ElementData {
+        id: Uuid::new_v4(),
+        asset: "props/barrel.png".into(),
+        transform: Transform::from_xyz(f32::from(index) * 64.0, 0.0, 0.0),
+        animation: None,
+    }
+}
+
+/// Captures the project.
+fn capture(world: &World, project: Entity) -> SaveFile {
+    SaveFile::capture(world, project).expect("the project exists")
+}
+
+/// Records `edit`, then checks that undoing it restores the project and redoing it makes the
+/// same change again. Returns the project once the edit is redone.
+fn round_trip(world: &mut World, project: Entity, edit: impl Edit) -> SaveFile {
+    let before = capture(world, project);
+    assert!(History::record(world, edit), "the edit applies");
+    let after = capture(world, project);
+    assert_ne!(before, after, "the edit changes the project");
+
+    assert!(History::undo(world));
+    assert_eq!(
+        capture(world, project),
+        before,
+        "undoing restores the project"
+    );
+    assert!(History::redo(world));
+    assert_eq!(
+        capture(world, project),
+        after,
+        "redoing makes the change again"
+    );
+    assert!(world.resource::<History>().can_undo());
+    assert!(!world.resource::<History>().can_redo());
+
+    after
+}
+
+/// The [`PersistentId`] of a node of the project.
+fn id(uuid: Uuid) -> PersistentId {
+    PersistentId(uuid)
+}
+
+/// Moves an element and moves it back.
+#[test]
+fn set_transform() {
+    let (mut world, project) = setup();
+    let element = capture(&world, project).levels[0].layers[0].elements[1].id;
+
+    let after = round_trip(
+        &mut world,
+        project,
+        SetTransform::new(id(element), Transform::from_xyz(5.0, 7.0, 0.0)),
+    );
+    assert_eq!(
+        after.levels[0].layers[0].elements[1].transform,
+        Transform::from_xyz(5.0, 7.0, 0.0)
+    );
+}
+
+/// Renames a level and gives it its name back.
+#[test]
+fn rename() {
+    let (mut world, project) = setup();
+    let level = capture(&world, project).levels[1].id;
+
+    let after = round_trip(&mut world, project, Rename::new(id(level), "Crypt"));
+    assert_eq!(after.levels[1].name, "Crypt");
+}
+
+/// Places an element on top of a layer and takes it away.
+#[test]
+fn place_element() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[2].id;
+    let placed = element(9);
+
+    let after = round_trip(
+        &mut world,
+        project,
+        PlaceElement::new(id(layer), placed.clone()),
+    );
+    assert_eq!(after.levels[0].layers[2].elements.last(), Some(&placed));
+}
+
+/// Removes the middle element of a layer and puts it back at its position.
+#[test]
+fn remove_element() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[1].clone();
+
+    let after = round_trip(
+        &mut world,
+        project,
+        RemoveElement::new(id(layer.elements[1].id)),
+    );
+    assert_eq!(
+        after.levels[0].layers[1].elements,
+        [layer.elements[0].clone(), layer.elements[2].clone()]
+    );
+}
+
+/// Places a group on a layer and takes it away.
+#[test]
+fn place_group() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[0].id;
+    let group = GroupData {
+        id: Uuid::new_v4(),
+        name: "Table".into(),
+        transform: Transform::from_xyz(128.0, 0.0, 0.0),
+        elements: vec![element(0), element(1)],
+    };
+
+    let after = round_trip(
+        &mut world,
+        project,
+        PlaceGroup::new(id(layer), group.clone()),
+    );
+    assert_eq!(after.levels[0].layers[0].groups, [group]);
+}
+
+/// Dissolves a group into its layer and puts the group back together.
+#[test]
+fn ungroup() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[0].id;
+    let group = GroupData {
+        id: Uuid::new_v4(),
+        name: "Table".into(),
+        transform: Transform::from_xyz(128.0, 0.0, 0.0),
+        elements: vec![element(0), element(1)],
+    };
+    assert!(History::record(
+        &mut world,
+        PlaceGroup::new(id(layer), group.clone())
+    ));
+
+    let after = round_trip(&mut world, project, Ungroup::new(id(group.id)));
+    let layer = &after.levels[0].layers[0];
+    assert!(layer.groups.is_empty());
+    assert_eq!(layer.elements.len(), 5);
+    assert_eq!(
+        layer.elements[3].transform,
+        Transform::from_xyz(128.0, 0.0, 0.0)
+    );
+}
+
+/// Draws a wall path on a layer, moves its points and takes it away.
+#[test]
+fn wall_paths() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[0].id;
+    let path = WallPathData {
+        id: Uuid::new_v4(),
+        points: vec![[0.0, 0.0], [64.0, 0.0]],
+        closed: false,
+        width: 8.0,
+        texture: "walls/brick.png".into(),
+        transform: Transform::IDENTITY,
+    };
+
+    round_trip(
+        &mut world,
+        project,
+        PlaceWallPath::new(id(layer), path.clone()),
+    );
+    let after = round_trip(
+        &mut world,
+        project,
+        SetWallPoints::new(id(path.id), [Vec2::ZERO, Vec2::new(0.0, 64.0)]),
+    );
+    assert_eq!(
+        after.levels[0].layers[0].paths[0].points,
+        [[0.0, 0.0], [0.0, 64.0]]
+    );
+}
+
+/// Adds a terrain to a layer, paints it and takes it away.
+#[test]
+fn terrains() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[0].id;
+    let terrain = TerrainData {
+        id: Uuid::new_v4(),
+        size: [128.0, 128.0],
+        resolution: [2, 2],
+        textures: vec!["ground/grass.png".into(), "ground/dirt.png".into()],
+        weights: vec![[255, 0, 0, 0]; 4],
+        transform: Transform::IDENTITY,
+    };
+
+    round_trip(
+        &mut world,
+        project,
+        AddTerrain::new(id(layer), terrain.clone()),
+    );
+    let after = round_trip(
+        &mut world,
+        project,
+        SetTerrainWeights::new(id(terrain.id), vec![[0, 255, 0, 0]; 4]),
+    );
+    assert_eq!(
+        after.levels[0].layers[0].terrains[0].weights,
+        [[0, 255, 0, 0]; 4]
+    );
+}
+
+/// A splat map of another size doesn't apply, so the terrain isn't corrupted.
+#[test]
+fn terrain_weights_of_another_size() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[0].id;
+    let terrain = TerrainData {
+        id: Uuid::new_v4(),
+        size: [128.0, 128.0],
+        resolution: [2, 2],
+        textures: vec!["ground/grass.png".into()],
+        weights: vec![[255, 0, 0, 0]; 4],
+        transform: Transform::IDENTITY,
+    };
+    assert!(History::record(
+        &mut world,
+        AddTerrain::new(id(layer), terrain.clone())
+    ));
+
+    assert!(!History::record(
+        &mut world,
+        SetTerrainWeights::new(id(terrain.id), vec![[0, 255, 0, 0]; 3])
+    ));
+}
+
+/// Adds a layer between two layers and takes it away.
+#[test]
+fn add_layer() {
+    let (mut world, project) = setup();
+    let level = capture(&world, project).levels[0].id;
+    let layer = LayerData::new("Furniture");
+
+    let after = round_trip(
+        &mut world,
+        project,
+        AddLayer::new(id(level), layer.clone()).at(1),
+    );
+    assert_eq!(after.levels[0].layers[1], layer);
+    assert_eq!(after.levels[0].layers.len(), 4);
+}
+
+/// Adds a layer on top of the layers of a level when its position is out of bounds.
+#[test]
+fn add_layer_on_top() {
+    let (mut world, project) = setup();
+    let level = capture(&world, project).levels[0].id;
+    let layer = LayerData::new("Roof");
+
+    let after = round_trip(&mut world, project, AddLayer::new(id(level), layer.clone()));
+    assert_eq!(after.levels[0].layers.last(), Some(&layer));
+}
+
+/// Removes the middle layer and puts it back with its contents at its position.
+#[test]
+fn remove_layer() {
+    let (mut world, project) = setup();
+    let layers = capture(&world, project).levels[0].layers.clone();
+
+    let after = round_trip(&mut world, project, RemoveLayer::new(id(layers[1].id)));
+    assert_eq!(
+        after.levels[0].layers,
+        [layers[0].clone(), layers[2].clone()]
+    );
+}
+
+/// Moves the bottom layer to the top and back.
+#[test]
+fn move_layer() {
+    let (mut world, project) = setup();
+    let layers = capture(&world, project).levels[0].layers.clone();
+
+    let after = round_trip(&mut world, project, MoveLayer::new(id(layers[0].id), 2));
+    assert_eq!(
+        after.levels[0].layers,
+        [layers[1].clone(), layers[2].clone(), layers[0].clone()]
+    );
+}
+
+/// Adds a level before the other levels and takes it away.
+#[test]
+fn add_level() {
+    let (mut world, project) = setup();
+    let id_of_project = id(capture(&world, project).id);
+    let level = LevelData::new("Attic");
+
+    let after = round_trip(
+        &mut world,
+        project,
+        AddLevel::new(id_of_project, level.clone()).at(0),
+    );
+    assert_eq!(after.levels[0], level);
+}
+
+/// Removes the first level and puts it back with its contents at its position.
+#[test]
+fn remove_level() {
+    let (mut world, project) = setup();
+    let levels = capture(&world, project).levels;
+
+    let after = round_trip(&mut world, project, RemoveLevel::new(id(levels[0].id)));
+    assert_eq!(after.levels, [levels[1].clone()]);
+}
+
+/// Moves the first level after the second and back.
+#[test]
+fn move_level() {
+    let (mut world, project) = setup();
+    let levels = capture(&world, project).levels;
+
+    let after = round_trip(&mut world, project, MoveLevel::new(id(levels[0].id), 1));
+    assert_eq!(after.levels, [levels[1].clone(), levels[0].clone()]);
+}
+
+/// Locks, hides and dims a layer, and restores it each time.
+#[test]
+fn layer_state() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[0].id;
+
+    let after = round_trip(&mut world, project, SetLayerLocked::new(id(layer), true));
+    assert!(after.levels[0].layers[0].locked);
+    let after = round_trip(&mut world, project, SetLayerHidden::new(id(layer), true));
+    assert!(after.levels[0].layers[0].hidden);
+    let after = round_trip(&mut world, project, SetLayerOpacity::new(id(layer), 0.25));
+    assert!((after.levels[0].layers[0].opacity - 0.25).abs() < f32::EPSILON);
+}
+
+/// Merges the bottom layer into the top layer and splits them again at their positions.
+#[test]
+fn merge_layer() {
+    let (mut world, project) = setup();
+    let layers = capture(&world, project).levels[0].layers.clone();
+
+    let after = round_trip(
+        &mut world,
+        project,
+        MergeLayer::new(id(layers[0].id), id(layers[2].id)),
+    );
+    let merged = &after.levels[0].layers;
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged[1].id, layers[2].id);
+    assert_eq!(
+        merged[1].elements,
+        [layers[2].elements.clone(), layers[0].elements.clone()].concat()
+    );
+}
+
+/// A layer can't be merged into itself.
+#[test]
+fn merge_layer_into_itself() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[0].id;
+
+    assert!(!History::record(
+        &mut world,
+        MergeLayer::new(id(layer), id(layer))
+    ));
+    assert!(!world.resource::<History>().can_undo());
+}
+
+/// Applies the edits of a group that apply, and reverts only those.
+#[test]
+fn edit_group() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[0].id;
+    let group = EditGroup::new(
+        "Prepare layer",
+        vec![
+            Box::new(Rename::new(id(layer), "Floor")),
+            Box::new(Rename::new(id(Uuid::new_v4()), "Nowhere")),
+            Box::new(SetLayerLocked::new(id(layer), true)),
+        ],
+    );
+
+    let after = round_trip(&mut world, project, group);
+    assert_eq!(after.levels[0].layers[0].name, "Floor");
+    assert!(after.levels[0].layers[0].locked);
+    assert_eq!(
+        world.resource::<History>().undo_label().as_deref(),
+        Some("Prepare layer")
+    );
+}
+
+/// Edits of nodes that don't exist aren't recorded.
+#[test]
+fn edits_that_dont_apply() {
+    let (mut world, _) = setup();
+
+    assert!(!History::record(
+        &mut world,
+        Rename::new(id(Uuid::new_v4()), "Nowhere")
+    ));
+    assert!(!world.resource::<History>().can_undo());
+}
+
+/// Recording an edit forgets the undone edits.
+#[test]
+fn record_forgets_redo() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[0].id;
+
+    History::record(&mut world, Rename::new(id(layer), "Floor"));
+    History::undo(&mut world);
+    assert!(world.resource::<History>().can_redo());
+    History::record(&mut world, Rename::new(id(layer), "Walls"));
+
+    assert!(!world.resource::<History>().can_redo());
+    assert!(!History::redo(&mut world));
+}
+
+/// Redoing an edit whose node was removed meanwhile drops it instead of recording it again.
+#[test]
+fn redo_drops_edits_that_no_longer_apply() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[0].id;
+    History::record(&mut world, Rename::new(id(layer), "Floor"));
+    History::undo(&mut world);
+
+    let entity = world
+        .query::<(Entity, &PersistentId)>()
+        .iter(&world)
+        .find_map(|(entity, candidate)| (*candidate == id(layer)).then_some(entity))
+        .expect("the layer exists");
+    world.despawn(entity);
+
+    assert!(History::redo(&mut world));
+    let history = world.resource::<History>();
+    assert!(!history.can_undo());
+    assert!(!history.can_redo());
+}
+
+/// Only the most recent edits are kept, up to the depth of the history.
+#[test]
+fn depth() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[0].id;
+    world.insert_resource(History::new(3));
+
+    for name in ["A", "B", "C", "D", "E"] {
+        History::record(&mut world, Rename::new(id(layer), name));
+    }
+    assert_eq!(world.resource::<History>().undo.len(), 3);
+
+    world.resource_mut::<History>().set_depth(2);
+    assert_eq!(world.resource::<History>().depth(), 2);
+    assert!(History::undo(&mut world));
+    assert!(History::undo(&mut world));
+    assert!(!History::undo(&mut world));
+    assert_eq!(capture(&world, project).levels[0].layers[0].name, "C");
+
+    // Redone edits count towards the depth too.
+    world.resource_mut::<History>().set_depth(1);
+    assert!(History::redo(&mut world));
+    assert!(History::redo(&mut world));
+    assert_eq!(world.resource::<History>().undo.len(), 1);
+    assert_eq!(
+        world.resource::<History>().undo_label().as_deref(),
+        Some("Rename to \"E\"")
+    );
+}
+
+/// A depth of zero keeps no edits, but still applies them.
+#[test]
+fn depth_of_zero() {
+    let (mut world, project) = setup();
+    let layer = capture(&world, project).levels[0].layers[0].id;
+    world.resource_mut::<History>().set_depth(0);
+
+    assert!(History::record(&mut world, Rename::new(id(layer), "Floor")));
+    assert!(!world.resource::<History>().can_undo());
+    assert_eq!(capture(&world, project).levels[0].layers[0].name, "Floor");
+}
+
+/// Undo and redo requests are carried out in the order they're written.
+#[test]
+fn requests_in_order() {
+    let mut app = App::new();
+    app.add_plugins(HistoryPlugin);
+    let project = spawn_project(app.world_mut());
+    let layer = capture(app.world(), project).levels[0].layers[0].id;
+    History::record(app.world_mut(), Rename::new(id(layer), "Floor"));
+
+    // Redoing first does nothing, so the rename ends up undone.
+    app.world_mut().write_message(UndoRedo::Redo);
+    app.world_mut().write_message(UndoRedo::Undo);
+    app.update();
+
+    assert_eq!(
+        capture(app.world(), project).levels[0].layers[0].name,
+        "Layer 1"
+    );
+    assert!(app.world().resource::<History>().can_redo());
+}
+
+/// The depth set in the configuration is applied once it's reported as changed.
+#[test]
+fn configured_depth() {
+    let mut app = App::new();
+    app.add_plugins(HistoryPlugin);
+    let mut configuration = Configuration::new("config.toml");
+    configuration.set_history_depth(5);
+    app.insert_resource(configuration);
+
+    app.update();
+    assert_eq!(
+        app.world().resource::<History>().depth(),
+        History::DEFAULT_DEPTH
+    );
+
+    app.world_mut().write_message(ConfigurationChanged {
+        setting: ConfigurationSetting::History,
+    });
+    app.update();
+    assert_eq!(app.world().resource::<History>().depth(), 5);
+}
@@ -0,0 +1,586 @@
+//! Maps keyboard shortcuts to the [`Action`]s of the editor.
+//!
+//! The [`Keybindings`] are kept in the [`Configuration`], so shortcuts rebound by the user are
+//! restored in the next session. Every pressed shortcut is reported as an [`ActionTriggered`],
+//! which the plugins handling the action react to.
+
+use crate::Configuration;
+use bevy::prelude::*;
+use bevy::reflect::{DynamicEnum, DynamicVariant, Enum, FromReflect, Typed, VariantInfo};
+use dungeonrs_macros::bevy_system;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Triggers the [`Action`]s bound to the pressed shortcuts and records new shortcuts.
+///
+/// Add the [`ConfigurationPlugin`](crate::ConfigurationPlugin) to keep rebound shortcuts across
+/// sessions.
+pub struct KeybindingsPlugin;
+
+impl Plugin for KeybindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Keybindings>()
+            .init_resource::<KeybindingRecorder>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .add_message::<ActionTriggered>()
+            .add_message::<RecordKeybinding>()
+            .add_message::<KeybindingRecorded>()
+            .add_systems(
+                Update,
+                (
+                    load_keybindings.run_if(resource_exists_and_changed::<Configuration>),
+                    store_keybindings.run_if(resource_changed::<Keybindings>),
+                    trigger_actions,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Something the user does with a keyboard shortcut.
+///
+/// The actions that need more than a shortcut, such as the file a project is saved to or the
+/// layer copied elements are pasted onto, are left to the user interface.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Saves the open project, handled by the user interface.
+    Save,
+    /// Undoes the last edit.
+    Undo,
+    /// Redoes the last undone edit.
+    Redo,
+    /// Copies the selected elements.
+    Copy,
+    /// Cuts the selected elements.
+    Cut,
+    /// Pastes the copied elements, handled by the user interface.
+    Paste,
+    /// Shows or hides the grid.
+    ToggleGrid,
+    /// Opens or closes the debug overlay.
+    ToggleDebugOverlay,
+    /// Zooms the viewport in.
+    ZoomIn,
+    /// Zooms the viewport out.
+    ZoomOut,
+    /// Fits the whole project in the viewport.
+    FitProject,
+}
+
+impl Action {
+    /// Every action, in the order they're listed in the settings.
+    pub const ALL: [Self; 11] = [
+        Self::Save,
+        Self::Undo,
+        Self::Redo,
+        Self::Copy,
+        Self::Cut,
+        Self::Paste,
+        Self::ToggleGrid,
+        Self::ToggleDebugOverlay,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::FitProject,
+    ];
+
+    /// The name of the action shown in the settings.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Save => "Save",
+            Self::Undo => "Undo",
+            Self::Redo => "Redo",
+            Self::Copy => "Copy",
+            Self::Cut => "Cut",
+            Self::Paste => "Paste",
+            Self::ToggleGrid => "Toggle grid",
+            Self::ToggleDebugOverlay => "Toggle debug overlay",
+            Self::ZoomIn => "Zoom in",
+            Self::ZoomOut => "Zoom out",
+            Self::FitProject => "Fit project",
+        }
+    }
+}
+
+/// A key pressed along with a set of modifiers, such as `Ctrl+Shift+KeyZ`.
+///
+/// The Command key is accepted in place of Ctrl. Bindings are written as the modifiers followed by
+/// the name of the [`KeyCode`], joined by `+`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyBinding {
+    /// The key that triggers the binding when pressed.
+    pub key: KeyCode,
+    /// Whether Ctrl or Command is held.
+    pub ctrl: bool,
+    /// Whether Shift is held.
+    pub shift: bool,
+    /// Whether Alt is held.
+    pub alt: bool,
+}
+
+impl KeyBinding {
+    /// The Ctrl keys, along with the Command keys accepted in their place.
+    const CTRL: [KeyCode; 4] = [
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::SuperLeft,
+        KeyCode::SuperRight,
+    ];
+    /// The Shift keys.
+    const SHIFT: [KeyCode; 2] = [KeyCode::ShiftLeft, KeyCode::ShiftRight];
+    /// The Alt keys.
+    const ALT: [KeyCode; 2] = [KeyCode::AltLeft, KeyCode::AltRight];
+
+    /// Binds `key` without modifiers.
+    #[must_use]
+    pub fn new(key: KeyCode) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    /// Binds `key` with Ctrl held.
+    #[must_use]
+    pub fn ctrl(key: KeyCode) -> Self {
+        Self::new(key).with_ctrl()
+    }
+
+    /// Also requires Ctrl or Command to be held.
+    #[must_use]
+    pub fn with_ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    /// Also requires Shift to be held.
+    #[must_use]
+    pub fn with_shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    /// Also requires Alt to be held.
+    #[must_use]
+    pub fn with_alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    /// The binding of `key` with the modifiers currently held in `keys`.
+    #[must_use]
+    pub fn held(key: KeyCode, keys: &ButtonInput<KeyCode>) -> Self {
+        Self {
+            key,
+            ctrl: keys.any_pressed(Self::CTRL),
+            shift: keys.any_pressed(Self::SHIFT),
+            alt: keys.any_pressed(Self::ALT),
+        }
+    }
+
+    /// Whether the key was just pressed with exactly the binding's modifiers held.
+    #[must_use]
+    pub fn just_pressed(&self, keys: &ButtonInput<KeyCode>) -> bool {
+        keys.just_pressed(self.key) && Self::held(self.key, keys) == *self
+    }
+
+    /// Whether `key` is a modifier, which can't be bound on its own.
+    fn is_modifier(key: KeyCode) -> bool {
+        Self::CTRL
+            .into_iter()
+            .chain(Self::SHIFT)
+            .chain(Self::ALT)
+            .any(|modifier| modifier == key)
+    }
+}
+
+impl Display for KeyBinding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (held, modifier) in [
+            (self.ctrl, "Ctrl"),
+            (self.shift, "Shift"),
+            (self.alt, "Alt"),
+        ] {
+            if held {
+                write!(f, "{modifier}+")?;
+            }
+        }
+
+        write!(f, "{}", self.key.variant_name())
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = KeybindingError;
+
+    fn from_str(binding: &str) -> Result<Self, Self::Err> {
+        let invalid = || KeybindingError::Invalid(binding.to_owned());
+        let mut parts: Vec<&str> = binding.split('+').map(str::trim).collect();
+        let key = parts.pop().ok_or_else(invalid)?;
+        // Keys are matched by the name of their variant, keys carrying data can't be bound. The
+        // variant is looked up first, as reflecting one that doesn't exist panics.
+        KeyCode::type_info()
+            .as_enum()
+            .ok()
+            .and_then(|info| info.variant(key))
+            .filter(|variant| matches!(variant, VariantInfo::Unit(_)))
+            .ok_or_else(invalid)?;
+        let key = KeyCode::from_reflect(&DynamicEnum::new(key, DynamicVariant::Unit))
+            .ok_or_else(invalid)?;
+
+        let mut parsed = Self::new(key);
+        for modifier in parts {
+            match modifier {
+                "Ctrl" => parsed.ctrl = true,
+                "Shift" => parsed.shift = true,
+                "Alt" => parsed.alt = true,
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+impl TryFrom<String> for KeyBinding {
+    type Error = KeybindingError;
+
+    fn try_from(binding: String) -> Result<Self, Self::Error> {
+        binding.parse()
+    }
+}
+
+impl From<KeyBinding> for String {
+    fn from(binding: KeyBinding) -> Self {
+        binding.to_string()
+    }
+}
+
+/// Errors that can occur while reading a [`KeyBinding`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum KeybindingError {
+    /// The binding isn't modifiers followed by the name of a key.
+    #[error("'{0}' is not a valid key binding")]
+    Invalid(String),
+}
+
+/// A shortcut bound to more than one [`Action`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeybindingConflict {
+    /// The shortcut bound more than once.
+    pub binding: KeyBinding,
+    /// The actions it's bound to.
+    pub actions: Vec<Action>,
+}
+
+/// The shortcuts bound to each [`Action`].
+///
+/// The user interface rebinds actions with [`Keybindings::bind`] or a [`RecordKeybinding`], and
+/// is told about shortcuts bound to several actions by [`Keybindings::conflicts`].
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct Keybindings {
+    /// The shortcuts of each action, actions without shortcuts are unbound.
+    bindings: BTreeMap<Action, Vec<KeyBinding>>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        let bindings = [
+            (Action::Save, vec![KeyBinding::ctrl(KeyCode::KeyS)]),
+            (Action::Undo, vec![KeyBinding::ctrl(KeyCode::KeyZ)]),
+            (
+                Action::Redo,
+                vec![
+                    KeyBinding::ctrl(KeyCode::KeyY),
+                    KeyBinding::ctrl(KeyCode::KeyZ).with_shift(),
+                ],
+            ),
+            (Action::Copy, vec![KeyBinding::ctrl(KeyCode::KeyC)]),
+            (Action::Cut, vec![KeyBinding::ctrl(KeyCode::KeyX)]),
+            (Action::Paste, vec![KeyBinding::ctrl(KeyCode::KeyV)]),
+            (Action::ToggleGrid, vec![KeyBinding::ctrl(KeyCode::KeyG)]),
+            (
+                Action::ToggleDebugOverlay,
+                vec![KeyBinding::new(KeyCode::F1)],
+            ),
+            (
+                Action::ZoomIn,
+                vec![
+                    KeyBinding::new(KeyCode::Equal),
+                    KeyBinding::new(KeyCode::NumpadAdd),
+                ],
+            ),
+            (
+                Action::ZoomOut,
+                vec![
+                    KeyBinding::new(KeyCode::Minus),
+                    KeyBinding::new(KeyCode::NumpadSubtract),
+                ],
+            ),
+            (Action::FitProject, vec![KeyBinding::new(KeyCode::Home)]),
+        ];
+
+        Self {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+}
+
+impl Keybindings {
+    /// The shortcuts bound to `action`.
+    #[must_use]
+    pub fn bindings(&self, action: Action) -> &[KeyBinding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds `action` to `binding` only, replacing its other shortcuts.
+    ///
+    /// Returns the other actions `binding` is also bound to, the binding is made either way.
+    pub fn bind(&mut self, action: Action, binding: KeyBinding) -> Vec<Action> {
+        self.bindings.insert(action, vec![binding]);
+
+        self.actions(binding)
+            .filter(|other| *other != action)
+            .collect()
+    }
+
+    /// Removes every shortcut of `action`.
+    pub fn unbind(&mut self, action: Action) {
+        self.bindings.insert(action, Vec::new());
+    }
+
+    /// Binds `action` to its default shortcuts again.
+    pub fn reset(&mut self, action: Action) {
+        let defaults = Self::default().bindings(action).to_vec();
+        self.bindings.insert(action, defaults);
+    }
+
+    /// The actions `binding` is bound to.
+    pub fn actions(&self, binding: KeyBinding) -> impl Iterator<Item = Action> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    /// The shortcuts bound to more than one action.
+    #[must_use]
+    pub fn conflicts(&self) -> Vec<KeybindingConflict> {
+        let mut conflicts: Vec<KeybindingConflict> = Vec::new();
+        for binding in self.bindings.values().flatten() {
+            if conflicts
+                .iter()
+                .any(|conflict| conflict.binding == *binding)
+            {
+                continue;
+            }
+
+            let actions: Vec<Action> = self.actions(*binding).collect();
+            if actions.len() > 1 {
+                conflicts.push(KeybindingConflict {
+                    binding: *binding,
+                    actions,
+                });
+            }
+        }
+
+        conflicts
+    }
+
+    /// The keybindings saved in the configuration file, over the defaults so actions added since
+    /// the file was saved keep their default shortcuts.
+    pub(crate) fn from_saved(saved: BTreeMap<Action, Vec<KeyBinding>>) -> Self {
+        let mut keybindings = Self::default();
+        keybindings.bindings.extend(saved);
+        keybindings
+    }
+
+    /// The keybindings as saved in the configuration file.
+    pub(crate) fn to_saved(&self) -> BTreeMap<Action, Vec<KeyBinding>> {
+        self.bindings.clone()
+    }
+}
+
+/// Written when the shortcut of an [`Action`] was pressed.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ActionTriggered {
+    /// The triggered action.
+    pub action: Action,
+}
+
+/// Binds the next shortcut pressed to an action, such as when the user picks an action to rebind
+/// in the settings. Pressing Escape cancels the recording.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RecordKeybinding {
+    /// The action the shortcut is bound to.
+    pub action: Action,
+}
+
+/// Written once a [`RecordKeybinding`] bound a shortcut.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct KeybindingRecorded {
+    /// The rebound action.
+    pub action: Action,
+    /// The shortcut it's now bound to.
+    pub binding: KeyBinding,
+    /// The other actions the shortcut is also bound to.
+    pub conflicts: Vec<Action>,
+}
+
+/// The action waiting for the next shortcut to be pressed, see [`RecordKeybinding`].
+#[derive(Resource, Debug, Default)]
+struct KeybindingRecorder(Option<Action>);
+
+/// Takes over the keybindings of the configuration read at startup.
+#[bevy_system]
+fn load_keybindings(configuration: Res<Configuration>, mut keybindings: ResMut<Keybindings>) {
+    keybindings.set_if_neq(configuration.keybindings().clone());
+}
+
+/// Stores rebound shortcuts in the configuration, which saves them.
+#[bevy_system]
+fn store_keybindings(configuration: Option<ResMut<Configuration>>, keybindings: Res<Keybindings>) {
+    if let Some(mut configuration) = configuration
+        && configuration.keybindings() != &*keybindings
+    {
+        configuration.set_keybindings(keybindings.clone());
+    }
+}
+
+/// Records the shortcut of an action being rebound, or triggers the actions bound to the pressed
+/// shortcuts.
+#[bevy_system]
+fn trigger_actions(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut keybindings: ResMut<Keybindings>,
+    mut recorder: ResMut<KeybindingRecorder>,
+    mut requests: MessageReader<RecordKeybinding>,
+    mut recordings: MessageWriter<KeybindingRecorded>,
+    mut triggered: MessageWriter<ActionTriggered>,
+) {
+    if let Some(request) = requests.read().last() {
+        recorder.0 = Some(request.action);
+        // The key that started the recording doesn't end it.
+        return;
+    }
+
+    if let Some(action) = recorder.0 {
+        let Some(key) = keys
+            .get_just_pressed()
+            .copied()
+            .find(|key| !KeyBinding::is_modifier(*key))
+        else {
+            return;
+        };
+
+        recorder.0 = None;
+        if key == KeyCode::Escape {
+            return;
+        }
+        let binding = KeyBinding::held(key, &keys);
+        let conflicts = keybindings.bind(action, binding);
+        recordings.write(KeybindingRecorded {
+            action,
+            binding,
+            conflicts,
+        });
+        return;
+    }
+
+    for (action, bindings) in &keybindings.bindings {
+        if bindings.iter().any(|binding| binding.just_pressed(&keys)) {
+            triggered.write(ActionTriggered { action: *action });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Tells shortcuts sharing a key apart by their modifiers.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+
+    /// Presses `keys` in an app with the default keybindings and returns the triggered actions.
+    fn press(keys: &[KeyCode]) -> Vec<Action> {
+        let mut app = App::new();
+        app.add_plugins(KeybindingsPlugin);
+        let mut input = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        for key in keys {
+            input.press(*key);
+        }
+        app.update();
+
+        app.world_mut()
+            .resource_mut::<Messages<ActionTriggered>>()
+            .drain()
+            .map(|triggered| triggered.action)
+            .collect()
+    }
+
+    /// A shortcut is written as its modifiers followed by its key, and reads back the same.
+    #[test]
+    fn parses_chords() {
+        let redo = KeyBinding::ctrl(KeyCode::KeyZ).with_shift();
+        assert_eq!(redo.to_string(), "Ctrl+Shift+KeyZ");
+        assert_eq!("Ctrl + Shift + KeyZ".parse(), Ok(redo));
+        assert_eq!(
+            "Alt+F4".parse(),
+            Ok(KeyBinding::new(KeyCode::F4).with_alt())
+        );
+
+        for invalid in ["Hyper+KeyZ", "Ctrl+", "Ctrl+Unknown"] {
+            assert_eq!(
+                invalid.parse::<KeyBinding>(),
+                Err(KeybindingError::Invalid(invalid.into()))
+            );
+        }
+    }
+
+    /// Shortcuts on the same key with different modifiers don't conflict, the same chord bound to
+    /// two actions does.
+    #[test]
+    fn chord_conflicts() {
+        let mut keybindings = Keybindings::default();
+        assert_eq!(keybindings.conflicts(), []);
+
+        let chord = KeyBinding::ctrl(KeyCode::KeyZ).with_shift();
+        assert_eq!(keybindings.bind(Action::Undo, chord), [Action::Redo]);
+        assert_eq!(
+            keybindings.conflicts(),
+            [KeybindingConflict {
+                binding: chord,
+                actions: vec![Action::Undo, Action::Redo],
+            }]
+        );
+
+        assert_eq!(
+            keybindings.bind(Action::Undo, KeyBinding::new(KeyCode::KeyZ)),
+            []
+        );
+        assert_eq!(keybindings.conflicts(), []);
+    }
+
+    /// A shortcut triggers only when exactly its modifiers are held, so holding Shift as well
+    /// redoes instead of undoing.
+    #[test]
+    fn triggers_exact_modifiers() {
+        assert_eq!(
+            press(&[KeyCode::ControlLeft, KeyCode::KeyZ]),
+            [Action::Undo]
+        );
+        assert_eq!(
+            press(&[KeyCode::SuperRight, KeyCode::ShiftLeft, KeyCode::KeyZ]),
+            [Action::Redo]
+        );
+        assert_eq!(press(&[KeyCode::AltLeft, KeyCode::KeyZ]), []);
+        assert_eq!(press(&[KeyCode::ControlLeft]), []);
+    }
+}
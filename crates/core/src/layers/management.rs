@@ -0,0 +1,286 @@
+//! Reorders, renames, locks, hides, fades and merges layers on request, such as from the layers
+//! panel.
+//!
+//! Every request is applied as an [`Edit`](crate::Edit) recorded in the [`History`](crate::History),
+//! so it can be undone.
+
+use crate::{
+    History, HistoryCommandsExt, MergeLayer, MoveLayer, Rename, SetLayerHidden, SetLayerLocked,
+    SetLayerOpacity,
+};
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use dungeonrs_data::{Layer, Level, PersistentId};
+use dungeonrs_macros::bevy_system;
+use thiserror::Error;
+
+/// The depth between two neighbouring layers of a level, well below the light maps drawn over
+/// the level's layers.
+pub(crate) const LAYER_DEPTH: f32 = 0.1;
+
+/// Moves a layer to another position among the layers of its level.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReorderLayer {
+    /// The layer to move.
+    pub layer: Entity,
+    /// The new position of the layer among the layers of its level, `0` being the bottom and
+    /// the top when out of bounds.
+    pub index: usize,
+}
+
+/// Locks or unlocks a layer, protecting its contents from being selected and edited.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LockLayer {
+    /// The layer to lock or unlock.
+    pub layer: Entity,
+    /// Whether the layer is locked.
+    pub locked: bool,
+}
+
+/// Hides or shows a layer in the viewport and the exports.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HideLayer {
+    /// The layer to hide or show.
+    pub layer: Entity,
+    /// Whether the layer is hidden.
+    pub hidden: bool,
+}
+
+/// Renames a layer, such as when its name is edited in the layers panel.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct RenameLayer {
+    /// The layer to rename.
+    pub layer: Entity,
+    /// The new name of the layer.
+    pub name: String,
+}
+
+/// Changes the opacity the contents of a layer are drawn with, such as from the opacity slider of
+/// the layers panel.
+#[derive(Message, Debug, Copy, Clone, PartialEq)]
+pub struct FadeLayer {
+    /// The layer to change.
+    pub layer: Entity,
+    /// The opacity of the layer, between `0.0` and `1.0`.
+    pub opacity: f32,
+    /// Whether the opacity is only previewed while the slider is dragged. Previews aren't
+    /// recorded, the next request that isn't a preview records the change from the opacity the
+    /// layer had before them.
+    pub preview: bool,
+}
+
+/// Moves the contents of a layer on top of the contents of another layer, removing the emptied
+/// layer.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MergeLayers {
+    /// The layer whose contents are moved, removed once merged.
+    pub layer: Entity,
+    /// The layer receiving the contents.
+    pub into: Entity,
+}
+
+/// Written once two layers were merged.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LayersMerged {
+    /// The layer that received the contents.
+    pub layer: Entity,
+}
+
+/// Errors that can occur while merging layers.
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    /// One of the layers doesn't exist (anymore).
+    #[error("{0} is not a layer")]
+    NotALayer(Entity),
+    /// One of the layers is locked.
+    #[error("{0} is locked")]
+    Locked(Entity),
+    /// A layer can't be merged into itself.
+    #[error("a layer can't be merged into itself")]
+    SameLayer,
+}
+
+/// Written when two layers couldn't be merged.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MergeLayersFailed {
+    /// The layer whose contents were moved.
+    pub layer: Entity,
+    /// The layer that was to receive the contents.
+    pub into: Entity,
+    /// The reason the layers couldn't be merged.
+    pub error: MergeError,
+}
+
+/// Moves the layer of each [`ReorderLayer`] request.
+#[bevy_system]
+pub(crate) fn reorder_layers(
+    mut commands: Commands,
+    mut requests: MessageReader<ReorderLayer>,
+    layers: Query<(&PersistentId, &ChildOf), With<Layer>>,
+    children: Query<&Children, With<Level>>,
+) {
+    for request in requests.read() {
+        let Ok((id, parent)) = layers.get(request.layer) else {
+            continue;
+        };
+
+        // Levels hold their regions along with their layers, so the position among the layers is
+        // turned into a position among the children.
+        let positions: Vec<_> = children
+            .get(parent.parent())
+            .into_iter()
+            .flat_map(|children| children.iter().enumerate())
+            .filter(|(_, child)| layers.contains(*child))
+            .map(|(position, _)| position)
+            .collect();
+        if let Some(position) = positions.get(request.index).or(positions.last()) {
+            commands.edit(MoveLayer::new(*id, *position));
+        }
+    }
+}
+
+/// Locks or unlocks the layer of each [`LockLayer`] request.
+#[bevy_system]
+pub(crate) fn lock_layers(
+    mut commands: Commands,
+    mut requests: MessageReader<LockLayer>,
+    layers: Query<(&Layer, &PersistentId)>,
+) {
+    for request in requests.read() {
+        if let Ok((layer, id)) = layers.get(request.layer)
+            && layer.locked != request.locked
+        {
+            commands.edit(SetLayerLocked::new(*id, request.locked));
+        }
+    }
+}
+
+/// Hides or shows the layer of each [`HideLayer`] request.
+#[bevy_system]
+pub(crate) fn hide_layers(
+    mut commands: Commands,
+    mut requests: MessageReader<HideLayer>,
+    layers: Query<(&Layer, &PersistentId)>,
+) {
+    for request in requests.read() {
+        if let Ok((layer, id)) = layers.get(request.layer)
+            && layer.hidden != request.hidden
+        {
+            commands.edit(SetLayerHidden::new(*id, request.hidden));
+        }
+    }
+}
+
+/// Renames the layer of each [`RenameLayer`] request.
+#[bevy_system]
+pub(crate) fn rename_layers(
+    mut commands: Commands,
+    mut requests: MessageReader<RenameLayer>,
+    layers: Query<(&Layer, &PersistentId)>,
+) {
+    for request in requests.read() {
+        if let Ok((layer, id)) = layers.get(request.layer)
+            && layer.name != request.name
+        {
+            commands.edit(Rename::new(*id, request.name.clone()));
+        }
+    }
+}
+
+/// Changes the opacity of the layer of each [`FadeLayer`] request, recording it once it's no
+/// longer previewed.
+#[bevy_system]
+pub(crate) fn fade_layers(
+    mut commands: Commands,
+    mut requests: MessageReader<FadeLayer>,
+    mut layers: Query<(&mut Layer, &PersistentId)>,
+    mut previewed: Local<HashMap<Entity, f32>>,
+) {
+    for request in requests.read() {
+        let Ok((mut layer, id)) = layers.get_mut(request.layer) else {
+            previewed.remove(&request.layer);
+            continue;
+        };
+        let opacity = request.opacity.clamp(0.0, 1.0);
+        if request.preview {
+            let original = layer.opacity;
+            previewed.entry(request.layer).or_insert(original);
+            layer.opacity = opacity;
+            continue;
+        }
+
+        // The edit captures the opacity from before the previews, so undoing it restores that.
+        if let Some(original) = previewed.remove(&request.layer) {
+            layer.bypass_change_detection().opacity = original;
+        }
+        if (layer.opacity - opacity).abs() > f32::EPSILON {
+            commands.edit(SetLayerOpacity::new(*id, opacity));
+        } else {
+            // The previews are dropped without an edit, the sprites are drawn as before them.
+            layer.set_changed();
+        }
+    }
+}
+
+/// Merges the layers of each [`MergeLayers`] request.
+#[bevy_system]
+pub(crate) fn merge_layers(
+    mut commands: Commands,
+    mut requests: MessageReader<MergeLayers>,
+    mut failed: MessageWriter<MergeLayersFailed>,
+    layers: Query<(&Layer, &PersistentId)>,
+) {
+    for &MergeLayers { layer, into } in requests.read() {
+        let found = |entity: Entity| match layers.get(entity) {
+            Ok((Layer { locked: true, .. }, _)) => Err(MergeError::Locked(entity)),
+            Ok((_, id)) => Ok(*id),
+            Err(_) => Err(MergeError::NotALayer(entity)),
+        };
+        let ids = if layer == into {
+            Err(MergeError::SameLayer)
+        } else {
+            found(layer).and_then(|layer| Ok((layer, found(into)?)))
+        };
+        let (layer_id, into_id) = match ids {
+            Ok(ids) => ids,
+            Err(error) => {
+                failed.write(MergeLayersFailed { layer, into, error });
+                continue;
+            }
+        };
+
+        commands.queue(move |world: &mut World| {
+            if History::record(world, MergeLayer::new(layer_id, into_id)) {
+                world.write_message(LayersMerged { layer: into });
+            }
+        });
+    }
+}
+
+/// Stacks the layers of each level whose layers changed in the order they're listed, so they're
+/// drawn in that order.
+pub(crate) fn order_layers(
+    levels: Query<&Children, (With<Level>, Changed<Children>)>,
+    mut layers: Query<&mut Transform, With<Layer>>,
+) {
+    for children in &levels {
+        let mut depth = 0.0;
+        let mut transforms = layers.iter_many_mut(children);
+        while let Some(mut transform) = transforms.fetch_next() {
+            transform.translation.z = depth;
+            depth += LAYER_DEPTH;
+        }
+    }
+}
+
+/// Hides the layers that were hidden and shows the ones that were shown.
+pub(crate) fn apply_layer_visibility(mut layers: Query<(&Layer, &mut Visibility), Changed<Layer>>) {
+    for (layer, mut visibility) in &mut layers {
+        let expected = if layer.hidden {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        visibility.set_if_neq(expected);
+    }
+}
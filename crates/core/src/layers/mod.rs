@@ -0,0 +1,65 @@
+//! Layer-wide behaviour, managing layers and creating layers from outside sources.
+
+mod management;
+mod opacity;
+mod panel;
+mod reference;
+
+pub use management::{
+    FadeLayer, HideLayer, LayersMerged, LockLayer, MergeError, MergeLayers, MergeLayersFailed,
+    RenameLayer, ReorderLayer,
+};
+pub use panel::{LayerRow, LayersPanel, LevelRows};
+pub use reference::{ImportReferenceImage, ReferenceImageImported};
+
+pub(crate) use management::LAYER_DEPTH;
+
+use bevy::prelude::{App, IntoScheduleConfigs, Plugin, PostUpdate, TransformSystems, Update};
+use dungeonrs_data::HierarchySnapshot;
+
+/// Registers the messages and systems that manage layers.
+///
+/// Keeps the [`LayersPanel`] listing the layers of each level. Reordering, renaming, locking,
+/// hiding, fading and merging layers requires the
+/// [`HistoryPlugin`](crate::HistoryPlugin) to undo them. Imported reference images are shown
+/// through the [`PersistencePlugin`](crate::PersistencePlugin), which gives elements their
+/// texture.
+pub struct LayersPlugin;
+
+impl Plugin for LayersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LayersPanel>()
+            .init_resource::<HierarchySnapshot>()
+            .add_message::<ImportReferenceImage>()
+            .add_message::<ReferenceImageImported>()
+            .add_message::<ReorderLayer>()
+            .add_message::<LockLayer>()
+            .add_message::<HideLayer>()
+            .add_message::<RenameLayer>()
+            .add_message::<FadeLayer>()
+            .add_message::<MergeLayers>()
+            .add_message::<LayersMerged>()
+            .add_message::<MergeLayersFailed>()
+            .add_systems(
+                Update,
+                (
+                    reference::import_reference_images,
+                    management::reorder_layers,
+                    management::lock_layers,
+                    management::hide_layers,
+                    management::rename_layers,
+                    management::fade_layers,
+                    management::merge_layers,
+                    panel::update_layers_panel,
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    (opacity::apply_layer_opacity, opacity::apply_element_opacity).chain(),
+                    management::apply_layer_visibility,
+                    management::order_layers.before(TransformSystems::Propagate),
+                ),
+            );
+    }
+}
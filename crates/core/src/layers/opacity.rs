@@ -0,0 +1,42 @@
+//! Applies the opacity of layers to the elements on them.
+
+use bevy::prelude::*;
+use dungeonrs_data::{Element, Group, Layer};
+
+/// Matches elements whose sprite was just added.
+type NewElementSprite = (With<Element>, Added<Sprite>);
+
+/// Updates the sprites of every element on a layer whose opacity may have changed, including the
+/// elements of the layer's groups.
+pub(crate) fn apply_layer_opacity(
+    layers: Query<(&Layer, &Children), Changed<Layer>>,
+    groups: Query<&Children, With<Group>>,
+    mut sprites: Query<&mut Sprite, With<Element>>,
+) {
+    for (layer, children) in &layers {
+        let grouped = groups
+            .iter_many(children)
+            .flat_map(RelationshipTarget::iter);
+        let mut sprites = sprites.iter_many_mut(children.iter().chain(grouped));
+        while let Some(mut sprite) = sprites.fetch_next() {
+            sprite.color.set_alpha(layer.opacity);
+        }
+    }
+}
+
+/// Gives elements that just received a sprite the opacity of their layer.
+pub(crate) fn apply_element_opacity(
+    layers: Query<&Layer>,
+    groups: Query<&ChildOf, With<Group>>,
+    mut sprites: Query<(&ChildOf, &mut Sprite), NewElementSprite>,
+) {
+    for (parent, mut sprite) in &mut sprites {
+        // Grouped elements are on the layer of their group.
+        let layer = groups
+            .get(parent.parent())
+            .map_or(parent.parent(), ChildOf::parent);
+        if let Ok(layer) = layers.get(layer) {
+            sprite.color.set_alpha(layer.opacity);
+        }
+    }
+}
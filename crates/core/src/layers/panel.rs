@@ -0,0 +1,117 @@
+//! Keeps the rows of the layers panel, listing the layers of each level top-most first.
+
+use crate::ReorderLayer;
+use bevy::prelude::*;
+use dungeonrs_data::{HierarchySnapshot, Layer};
+use dungeonrs_macros::bevy_system;
+
+/// The layers of every level as the layers panel lists them, rebuilt whenever a layer changes.
+///
+/// The panel lists the top-most layer first, the reverse of the drawing order, so dragging a row
+/// to another position is turned into a [`ReorderLayer`] through
+/// [`LayersPanel::reorder`].
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct LayersPanel {
+    /// The levels of every project, in order.
+    levels: Vec<LevelRows>,
+}
+
+/// The rows of the layers of a level in the [`LayersPanel`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelRows {
+    /// The level entity.
+    pub level: Entity,
+    /// The name of the level.
+    pub name: String,
+    /// The layers of the level, the top-most first.
+    pub layers: Vec<LayerRow>,
+}
+
+/// A layer in the [`LayersPanel`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerRow {
+    /// The layer entity.
+    pub layer: Entity,
+    /// The name of the layer.
+    pub name: String,
+    /// Whether the contents of the layer are protected from being selected and edited.
+    pub locked: bool,
+    /// Whether the layer is hidden from the viewport and the exports.
+    pub hidden: bool,
+    /// The opacity the contents of the layer are drawn with, between `0.0` and `1.0`.
+    pub opacity: f32,
+}
+
+impl LayersPanel {
+    /// Iterates over the levels and their layers, in order.
+    pub fn levels(&self) -> impl Iterator<Item = &LevelRows> {
+        self.levels.iter()
+    }
+
+    /// Returns the rows of `level`.
+    #[must_use]
+    pub fn level(&self, level: Entity) -> Option<&LevelRows> {
+        self.levels.iter().find(|rows| rows.level == level)
+    }
+
+    /// Returns the row of `layer`.
+    #[must_use]
+    pub fn layer(&self, layer: Entity) -> Option<&LayerRow> {
+        self.levels
+            .iter()
+            .flat_map(|rows| &rows.layers)
+            .find(|row| row.layer == layer)
+    }
+
+    /// Returns the request moving `layer` to `row` of its level, `0` being the top-most row and
+    /// the bottom row when out of bounds, or `None` when `layer` isn't listed.
+    #[must_use]
+    pub fn reorder(&self, layer: Entity, row: usize) -> Option<ReorderLayer> {
+        let rows = self
+            .levels
+            .iter()
+            .find(|rows| rows.layers.iter().any(|listed| listed.layer == layer))?;
+        let last = rows.layers.len() - 1;
+        Some(ReorderLayer {
+            layer,
+            index: last - row.min(last),
+        })
+    }
+}
+
+/// Rebuilds the [`LayersPanel`] once the hierarchy or a layer changed.
+#[bevy_system]
+pub(crate) fn update_layers_panel(
+    mut panel: ResMut<LayersPanel>,
+    snapshot: Res<HierarchySnapshot>,
+    layers: Query<&Layer>,
+    changed: Query<(), Changed<Layer>>,
+) {
+    if !snapshot.is_changed() && changed.is_empty() {
+        return;
+    }
+
+    let levels = snapshot
+        .levels()
+        .map(|level| LevelRows {
+            level: level.entity,
+            name: level.name.clone(),
+            layers: level
+                .layers
+                .iter()
+                .rev()
+                .filter_map(|node| {
+                    let layer = layers.get(node.entity).ok()?;
+                    Some(LayerRow {
+                        layer: node.entity,
+                        name: layer.name.clone(),
+                        locked: layer.locked,
+                        hidden: layer.hidden,
+                        opacity: layer.opacity,
+                    })
+                })
+                .collect(),
+        })
+        .collect();
+    panel.set_if_neq(LayersPanel { levels });
+}
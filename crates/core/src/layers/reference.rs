@@ -0,0 +1,104 @@
+//! Imports images as reference layers to trace over.
+
+use bevy::prelude::*;
+use dungeonrs_data::{Element, Layer, Level};
+use std::path::PathBuf;
+
+/// Requests importing the image at `path` as a new layer at the bottom of a level.
+///
+/// The layer is locked so the image can't be moved by accident while tracing over it, and dimmed
+/// so the elements drawn on top of it stand out.
+#[derive(Message, Debug, Clone, PartialEq)]
+pub struct ImportReferenceImage {
+    /// The image to import.
+    pub path: PathBuf,
+    /// The level the layer is added to.
+    pub level: Entity,
+    /// The size of a grid cell in the image, in pixels.
+    pub image_cell_size: f32,
+    /// The size of a grid cell in the level, in world units.
+    pub cell_size: f32,
+    /// The opacity of the layer.
+    pub opacity: f32,
+}
+
+impl ImportReferenceImage {
+    /// The opacity used by [`ImportReferenceImage::new`].
+    pub const DEFAULT_OPACITY: f32 = 0.5;
+
+    /// Requests importing the image at `path` into `level`, scaled so that `image_cell_size`
+    /// pixels of the image cover `cell_size` world units.
+    #[must_use]
+    pub fn new(
+        path: impl Into<PathBuf>,
+        level: Entity,
+        image_cell_size: f32,
+        cell_size: f32,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            level,
+            image_cell_size,
+            cell_size,
+            opacity: Self::DEFAULT_OPACITY,
+        }
+    }
+
+    /// The scale the image is drawn at.
+    fn scale(&self) -> f32 {
+        if self.image_cell_size > 0.0 {
+            self.cell_size / self.image_cell_size
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Written once a reference image has been imported.
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReferenceImageImported {
+    /// The level the image was imported into.
+    pub level: Entity,
+    /// The layer created for the image.
+    pub layer: Entity,
+}
+
+/// Spawns a locked layer containing the image of each [`ImportReferenceImage`] request.
+///
+/// Requests for entities that aren't levels are ignored.
+pub(crate) fn import_reference_images(
+    mut commands: Commands,
+    mut requests: MessageReader<ImportReferenceImage>,
+    mut imported: MessageWriter<ReferenceImageImported>,
+    levels: Query<(), With<Level>>,
+) {
+    for request in requests.read() {
+        if !levels.contains(request.level) {
+            continue;
+        }
+
+        let name = request.path.file_stem().map_or_else(
+            || "Reference".to_owned(),
+            |stem| stem.to_string_lossy().into_owned(),
+        );
+        let layer = commands
+            .spawn(
+                Layer::new(name)
+                    .with_locked(true)
+                    .with_opacity(request.opacity),
+            )
+            .id();
+        commands.spawn((
+            Element::new(request.path.clone()),
+            Transform::from_scale(Vec3::new(request.scale(), request.scale(), 1.0)),
+            ChildOf(layer),
+        ));
+        // Layers are drawn in the order of their level's children, the reference goes below all.
+        commands.entity(request.level).insert_children(0, &[layer]);
+
+        imported.write(ReferenceImageImported {
+            level: request.level,
+            layer,
+        });
+    }
+}
@@ -0,0 +1,222 @@
+//! Creates, duplicates, deletes, renames and reorders the levels of a project on request, such as
+//! the floors of a multi-level dungeon managed from the levels panel.
+//!
+//! Every request is applied as an [`Edit`](crate::Edit) recorded in the [`History`], so it can be
+//! undone.
+
+use crate::{AddLevel, History, HistoryCommandsExt, LevelData, MoveLevel, RemoveLevel, Rename};
+use bevy::prelude::*;
+use dungeonrs_data::{Level, PersistentId, Project};
+use dungeonrs_macros::bevy_system;
+
+/// Registers the messages and systems that manage levels.
+///
+/// Requires the [`HistoryPlugin`](crate::HistoryPlugin) to undo the changes.
+pub struct LevelsPlugin;
+
+impl Plugin for LevelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<CreateLevel>()
+            .add_message::<DuplicateLevel>()
+            .add_message::<DeleteLevel>()
+            .add_message::<RenameLevel>()
+            .add_message::<ReorderLevel>()
+            .add_message::<LevelCreated>()
+            .add_systems(
+                Update,
+                (
+                    create_levels,
+                    duplicate_levels,
+                    delete_levels,
+                    rename_levels,
+                    reorder_levels,
+                ),
+            );
+    }
+}
+
+/// Adds a level with a single empty layer after the levels of a project.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct CreateLevel {
+    /// The project the level is added to.
+    pub project: Entity,
+    /// The name of the level.
+    pub name: String,
+}
+
+impl CreateLevel {
+    /// Adds a level named `name` to `project`.
+    pub fn new(project: Entity, name: impl Into<String>) -> Self {
+        Self {
+            project,
+            name: name.into(),
+        }
+    }
+}
+
+/// Copies a level with all its layers and their contents, right after the level.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DuplicateLevel {
+    /// The level to copy.
+    pub level: Entity,
+}
+
+/// Removes a level and its contents.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DeleteLevel {
+    /// The level to remove.
+    pub level: Entity,
+}
+
+/// Renames a level.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct RenameLevel {
+    /// The level to rename.
+    pub level: Entity,
+    /// The new name of the level.
+    pub name: String,
+}
+
+/// Moves a level to another position among the levels of its project.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReorderLevel {
+    /// The level to move.
+    pub level: Entity,
+    /// The new position of the level, `0` being the first and the last when out of bounds.
+    pub index: usize,
+}
+
+/// Written once a level was created or duplicated.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LevelCreated {
+    /// The project the level was added to.
+    pub project: Entity,
+    /// The new level.
+    pub level: Entity,
+}
+
+/// Adds the level of each [`CreateLevel`] request.
+#[bevy_system]
+fn create_levels(
+    mut commands: Commands,
+    mut requests: MessageReader<CreateLevel>,
+    projects: Query<&PersistentId, With<Project>>,
+) {
+    for request in requests.read() {
+        let Ok(project_id) = projects.get(request.project) else {
+            continue;
+        };
+
+        let project = request.project;
+        let level = LevelData::new(request.name.clone());
+        let project_id = *project_id;
+        commands.queue(move |world: &mut World| {
+            add_level(world, project, AddLevel::new(project_id, level));
+        });
+    }
+}
+
+/// Copies the level of each [`DuplicateLevel`] request.
+#[bevy_system]
+fn duplicate_levels(
+    mut commands: Commands,
+    mut requests: MessageReader<DuplicateLevel>,
+    levels: Query<&ChildOf, With<Level>>,
+    projects: Query<(&PersistentId, &Children), With<Project>>,
+) {
+    for request in requests.read() {
+        let Some((project, project_id, index)) =
+            levels.get(request.level).ok().and_then(|parent| {
+                let (id, children) = projects.get(parent.parent()).ok()?;
+                let index = children.iter().position(|child| child == request.level)?;
+                Some((parent.parent(), *id, index))
+            })
+        else {
+            continue;
+        };
+
+        let level = request.level;
+        commands.queue(move |world: &mut World| {
+            let Some(mut copy) = LevelData::capture(world, level) else {
+                return;
+            };
+            copy.renew_ids();
+            copy.name = format!("{} (copy)", copy.name);
+            add_level(
+                world,
+                project,
+                AddLevel::new(project_id, copy).at(index + 1),
+            );
+        });
+    }
+}
+
+/// Removes the level of each [`DeleteLevel`] request.
+#[bevy_system]
+fn delete_levels(
+    mut commands: Commands,
+    mut requests: MessageReader<DeleteLevel>,
+    levels: Query<&PersistentId, With<Level>>,
+) {
+    for request in requests.read() {
+        if let Ok(id) = levels.get(request.level) {
+            commands.edit(RemoveLevel::new(*id));
+        }
+    }
+}
+
+/// Renames the level of each [`RenameLevel`] request.
+#[bevy_system]
+fn rename_levels(
+    mut commands: Commands,
+    mut requests: MessageReader<RenameLevel>,
+    levels: Query<(&Level, &PersistentId)>,
+) {
+    for request in requests.read() {
+        if let Ok((level, id)) = levels.get(request.level)
+            && level.name != request.name
+        {
+            commands.edit(Rename::new(*id, request.name.clone()));
+        }
+    }
+}
+
+/// Moves the level of each [`ReorderLevel`] request.
+#[bevy_system]
+fn reorder_levels(
+    mut commands: Commands,
+    mut requests: MessageReader<ReorderLevel>,
+    levels: Query<(&PersistentId, &ChildOf), With<Level>>,
+    projects: Query<&Children, With<Project>>,
+) {
+    for request in requests.read() {
+        let Ok((id, parent)) = levels.get(request.level) else {
+            continue;
+        };
+
+        let count = projects
+            .get(parent.parent())
+            .map_or(0, RelationshipTarget::len);
+        commands.edit(MoveLevel::new(
+            *id,
+            request.index.min(count.saturating_sub(1)),
+        ));
+    }
+}
+
+/// Records `edit` and writes a [`LevelCreated`] for the level it added to `project`.
+fn add_level(world: &mut World, project: Entity, edit: AddLevel) {
+    let id = PersistentId(edit.level.id);
+    if !History::record(world, edit) {
+        return;
+    }
+
+    let level = world
+        .get::<Children>(project)
+        .into_iter()
+        .flat_map(RelationshipTarget::iter)
+        .find(|child| world.get::<PersistentId>(*child) == Some(&id));
+    if let Some(level) = level {
+        world.write_message(LevelCreated { project, level });
+    }
+}
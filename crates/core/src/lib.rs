@@ -0,0 +1,122 @@
+#![doc = include_str!("../README.md")]
+
+mod animation;
+mod background;
+mod camera;
+mod clipboard;
+mod configuration;
+#[cfg(feature = "dev")]
+mod debug;
+mod docking;
+mod drop;
+mod duplicate;
+mod export;
+mod gizmo;
+mod grid;
+mod history;
+mod keybindings;
+mod layers;
+mod levels;
+mod lighting;
+mod measurement;
+mod minimap;
+mod persistence;
+mod placement;
+mod prefabs;
+mod preview;
+mod projection;
+mod regions;
+mod selection;
+mod shapes;
+mod terrain;
+mod theme;
+mod updates;
+mod walls;
+
+pub use animation::AnimatedTexturesPlugin;
+pub use background::{BackgroundPlugin, BackgroundSprite};
+pub use camera::{CameraControls, CameraControlsPlugin, CameraZoom, FitProject, ZoomCamera};
+pub use clipboard::{
+    Clipboard, ClipboardPlugin, ClipboardSettings, CopiedElements, CopySelection, ElementsPasted,
+    ImagePasted, PasteElements, PasteError, PasteFailed, PasteImage, SelectionCopied,
+};
+pub use configuration::{
+    Configuration, ConfigurationChanged, ConfigurationPlugin, ConfigurationSetting,
+};
+#[cfg(feature = "dev")]
+pub use debug::{DebugOverlay, DebugPlugin, DebugSection, DebugStats};
+pub use docking::{
+    ClosePanel, Dock, DockArea, DockLayout, DockingPlugin, MovePanel, OpenPanel, Panel,
+    ResetLayout, ResizeDock,
+};
+pub use drop::{DropPlugin, DropTarget, InstallPackRequested};
+pub use duplicate::{DuplicatePattern, DuplicatePlugin, DuplicateSelection, SelectionDuplicated};
+pub use export::{
+    CancelExport, CapturedFrame, EncodeSettings, ExportAudience, ExportCapabilities,
+    ExportCompleted, ExportError, ExportFailed, ExportFormat, ExportInput, ExportLayers,
+    ExportLicenseConflicts, ExportLight, ExportLink, ExportOutput, ExportPlugin, ExportPortal,
+    ExportPreset, ExportRegion, ExportRegistry, ExportRequest, ExportScene, ExportSetting,
+    ExportSettingKind, ExportSettingValue, ExportSettings, Exporter, ImageExporter, PngCompression,
+    encode_image, process_export, process_image_data,
+};
+pub use gizmo::{DragGizmo, DragPhase, GizmoMode, TransformGizmo, TransformGizmoPlugin};
+pub use grid::{GridOverlay, GridOverlayPlugin, GridOverlaySettings, grid_tile_image};
+pub use history::{
+    AddLayer, AddLevel, AddTerrain, Edit, EditGroup, History, HistoryCommandsExt, HistoryPlugin,
+    MergeLayer, MoveLayer, MoveLevel, PlaceElement, PlaceGroup, PlaceWallPath, RemoveElement,
+    RemoveLayer, RemoveLevel, Rename, SetLayerHidden, SetLayerLocked, SetLayerOpacity,
+    SetTerrainWeights, SetTransform, SetWallPoints, UndoRedo, Ungroup,
+};
+pub use keybindings::{
+    Action, ActionTriggered, KeyBinding, KeybindingConflict, KeybindingError, KeybindingRecorded,
+    Keybindings, KeybindingsPlugin, RecordKeybinding,
+};
+pub use layers::{
+    FadeLayer, HideLayer, ImportReferenceImage, LayerRow, LayersMerged, LayersPanel, LayersPlugin,
+    LevelRows, LockLayer, MergeError, MergeLayers, MergeLayersFailed, ReferenceImageImported,
+    RenameLayer, ReorderLayer,
+};
+pub use levels::{
+    CreateLevel, DeleteLevel, DuplicateLevel, LevelCreated, LevelsPlugin, RenameLevel, ReorderLevel,
+};
+pub use lighting::{LightMap, LightingPlugin, LightingSettings, LitArea, light_map_image};
+pub use measurement::{
+    AddRulerPoint, CancelRuler, DragRuler, FinishRuler, Measured, Measurement, MeasurementKind,
+    MeasurementPlugin, Ruler,
+};
+pub use minimap::{JumpToMinimap, Minimap, MinimapPlugin, MinimapSettings};
+pub use persistence::{
+    AnimationData, AutosaveFailed, AutosaveSettings, BackgroundData, CreateProject, ElementData,
+    FillData, GroupData, LabelData, LayerData, LevelData, LightData, LightingData, LinkData,
+    LinkKindData, LoadBudget, LoadProgress, OpenProject, OutlineData, PersistencePlugin,
+    PortalData, ProjectCreateFailed, ProjectCreated, ProjectLoaded, ProjectLoading,
+    ProjectOpenFailed, ProjectSaveFailed, ProjectSaved, ProjectSaving, ProjectTemplate,
+    RecentProject, RecentProjects, RegionData, SaveCache, SaveError, SaveFile, SaveProgress,
+    SaveProject, ShapeData, ShapeKindData, StrokeData, TerrainData, UnsavedWorkFound, WallData,
+    WallPathData, autosave_snapshots,
+};
+pub use placement::{
+    AssetDrag, AssetPlaced, DragAsset, PlacementError, PlacementFailed, PlacementGhost,
+    PlacementPlugin, PlacementSettings,
+};
+pub use prefabs::{
+    PlacePrefab, PrefabError, PrefabFailed, PrefabPlaced, PrefabSaved, PrefabsPlugin, SavePrefab,
+    UngroupSelection,
+};
+pub use preview::{PreviewPlugin, PreviewServer, PreviewServerFailed, PreviewSettings};
+pub use projection::ProjectionPlugin;
+pub use regions::{RegionCover, RegionSettings, RegionsPlugin, region_mask};
+pub use selection::{
+    ClearSelection, SelectArea, SelectAt, Selected, Selection, SelectionChanged, SelectionPlugin,
+};
+pub use shapes::{ShapesPlugin, shape_mesh};
+pub use terrain::{
+    PaintTerrain, TerrainBrush, TerrainPlugin, TerrainSplat, TerrainTextures, splat_image,
+};
+pub use theme::{Palette, Theme, ThemeLoadFailed, ThemePlugin, Themes};
+pub use updates::{
+    UpdateAvailable, UpdateCheckFailed, UpdateError, UpdatePlugin, UpdateSettings, check_for_update,
+};
+pub use walls::{
+    AddWallPoint, CancelWallPath, FinishWallPath, WallTexture, WallTool, WallsPlugin, wall_mesh,
+};
@@ -0,0 +1,394 @@
+//! Composites the lighting of levels over their layers.
+//!
+//! Each level with a [`LevelLighting`] gets a [`LightMap`]: a texture covering the level's
+//! contents, darkened by the level's darkness except where its [`LightSource`]s reach. It's drawn
+//! as a sprite above the layers, so the viewport and the export cameras show the lit map alike.
+//!
+//! The light map is computed on the CPU at the resolution set by the [`LightingSettings`], and
+//! only recomputed when the lights, the level's contents or its lighting change, or while lights
+//! flicker. Flickering lights hold steady while an export is captured, so the frames of the
+//! export match.
+
+use crate::export::ExportCapture;
+use crate::grid::GridOverlay;
+use crate::regions::RegionCover;
+use bevy::asset::RenderAssetUsages;
+use bevy::camera::primitives::Aabb;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use dungeonrs_data::{LevelLighting, LightSource, Region};
+use dungeonrs_macros::bevy_system;
+
+/// The depth of light maps within their level, above every layer.
+const LIGHT_MAP_Z: f32 = 900.0;
+
+/// Registers the [`LightingSettings`] and the systems that composite the lighting of
+/// [`LevelLighting`]s.
+pub struct LightingPlugin;
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LightingSettings>().add_systems(
+            PostUpdate,
+            update_light_maps.after(TransformSystems::Propagate),
+        );
+    }
+}
+
+/// Controls the resolution of the light maps.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct LightingSettings {
+    /// The size of a texel of the light maps, in world units.
+    pub texel_size: f32,
+    /// The maximum width and height of a light map, in texels. Levels too large for it get
+    /// larger texels.
+    pub max_resolution: u32,
+}
+
+impl Default for LightingSettings {
+    fn default() -> Self {
+        Self {
+            texel_size: 10.0,
+            max_resolution: 2048,
+        }
+    }
+}
+
+/// A light as composited into a light map.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LitArea {
+    /// The center of the light, in world units.
+    pub center: Vec2,
+    /// The distance the light reaches, in world units.
+    pub radius: f32,
+    /// The colour of the light.
+    pub color: Color,
+    /// The brightness of the light at this moment.
+    pub intensity: f32,
+}
+
+/// What a light map was last computed from.
+#[derive(Debug, Clone, PartialEq)]
+struct LightMapInputs {
+    /// The lighting of the level.
+    lighting: LevelLighting,
+    /// The area covered by the light map, in world units.
+    area: Rect,
+    /// The number of texels of the light map.
+    resolution: UVec2,
+    /// The lights of the level.
+    lights: Vec<LitArea>,
+}
+
+/// The lighting of a level, drawn over its layers as a sprite.
+#[derive(Component, Debug, Clone)]
+#[require(Sprite)]
+pub struct LightMap {
+    /// The level whose lighting this is, the light map is one of its children.
+    pub level: Entity,
+    /// What the light map was last computed from.
+    inputs: Option<LightMapInputs>,
+}
+
+/// The light map of `lighting` covering `area` with `resolution` texels, lit by `lights`.
+///
+/// Each texel is the ambient colour with the level's darkness as opacity, tinted towards the
+/// colour of the lights and made transparent where they reach.
+#[must_use]
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss,
+    reason = "texel indexes are far below 2^23 and channels are clamped to a byte"
+)]
+pub fn light_map_image(
+    lighting: &LevelLighting,
+    area: Rect,
+    resolution: UVec2,
+    lights: &[LitArea],
+) -> Image {
+    let resolution = resolution.max(UVec2::ONE);
+    let texel = area.size() / resolution.as_vec2();
+
+    // The amount of light reaching each texel, along with the colour of the lights weighted by
+    // their contribution.
+    let mut light = vec![Vec4::ZERO; resolution.element_product() as usize];
+    for lit in lights {
+        if lit.radius <= 0.0 || lit.intensity <= 0.0 {
+            continue;
+        }
+
+        let color = lit.color.to_srgba();
+        let tint = Vec3::new(color.red, color.green, color.blue);
+        let (min, max) = texel_range(area, texel, resolution, lit);
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let center = Vec2::new(
+                    area.min.x + (x as f32 + 0.5) * texel.x,
+                    area.max.y - (y as f32 + 0.5) * texel.y,
+                );
+                let falloff = 1.0 - center.distance(lit.center) / lit.radius;
+                if falloff <= 0.0 {
+                    continue;
+                }
+
+                let amount = falloff * falloff * lit.intensity;
+                light[(y * resolution.x + x) as usize] += (tint * amount).extend(amount);
+            }
+        }
+    }
+
+    let ambient = lighting.ambient.to_srgba();
+    let ambient = Vec3::new(ambient.red, ambient.green, ambient.blue);
+    let darkness = lighting.darkness.clamp(0.0, 1.0);
+    let data = light
+        .into_iter()
+        .flat_map(|light| {
+            let lit = light.w.min(1.0);
+            let color = if light.w > 0.0 {
+                ambient.lerp(light.truncate() / light.w, lit)
+            } else {
+                ambient
+            };
+            color
+                .extend(darkness * (1.0 - lit))
+                .to_array()
+                .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+        })
+        .collect();
+
+    Image::new(
+        Extent3d {
+            width: resolution.x,
+            height: resolution.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+/// The texels of a light map covering `area` with texels of `texel` size that `lit` can reach,
+/// from the first to past the last one.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "the texels are clamped to the light map"
+)]
+fn texel_range(area: Rect, texel: Vec2, resolution: UVec2, lit: &LitArea) -> (UVec2, UVec2) {
+    // Rows go from the top of the area to its bottom.
+    let min = Vec2::new(
+        (lit.center.x - lit.radius - area.min.x) / texel.x,
+        (area.max.y - lit.center.y - lit.radius) / texel.y,
+    );
+    let max = Vec2::new(
+        (lit.center.x + lit.radius - area.min.x) / texel.x,
+        (area.max.y - lit.center.y + lit.radius) / texel.y,
+    );
+    let clamp = |texels: Vec2| texels.clamp(Vec2::ZERO, resolution.as_vec2()).as_uvec2();
+
+    (clamp(min.floor()), clamp(max.ceil()))
+}
+
+/// The brightness of `light` after `seconds`, varying with its flicker. `seed` keeps lights from
+/// flickering in unison.
+fn flickered_intensity(light: &LightSource, seconds: f32, seed: f32) -> f32 {
+    // Overlapping waves of unrelated frequencies read as the irregular flicker of a flame.
+    let wave = (seconds * 7.3 + seed).sin() * 0.5 + (seconds * 13.1 + seed * 2.7).sin() * 0.5;
+    let dimming = light.flicker.clamp(0.0, 1.0) * (wave * 0.5 + 0.5);
+
+    light.intensity * (1.0 - dimming)
+}
+
+/// The lights and contents of the levels, leaving out what is drawn over them.
+type LevelContents<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static GlobalTransform,
+        Option<&'static Aabb>,
+        Option<&'static LightSource>,
+        Option<&'static InheritedVisibility>,
+    ),
+    (
+        Without<LightMap>,
+        Without<RegionCover>,
+        Without<Region>,
+        Without<GridOverlay>,
+    ),
+>;
+
+/// Computes the light map of every level with a [`LevelLighting`] whose lights or contents
+/// changed, and drops the light maps of levels that are no longer darkened.
+#[bevy_system]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::cast_precision_loss,
+    reason = "the light maps depend on the levels, their contents and the export, and their
+              resolution is far below 2^23"
+)]
+fn update_light_maps(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    settings: Res<LightingSettings>,
+    time: Res<Time>,
+    capture: Option<Res<ExportCapture>>,
+    levels: Query<(Entity, &LevelLighting, &GlobalTransform)>,
+    children: Query<&Children>,
+    contents: LevelContents,
+    mut light_maps: Query<(Entity, &mut LightMap, &mut Sprite, &mut Transform)>,
+) {
+    let mut existing: HashMap<Entity, Entity> = light_maps
+        .iter()
+        .map(|(entity, light_map, ..)| (light_map.level, entity))
+        .collect();
+
+    for (level, lighting, level_transform) in &levels {
+        let light_map = existing.remove(&level);
+        if lighting.darkness <= 0.0 {
+            if let Some(light_map) = light_map {
+                commands.entity(light_map).despawn();
+            }
+            continue;
+        }
+
+        let (area, lights) = gather(
+            level,
+            &children,
+            &contents,
+            time.elapsed_secs(),
+            capture.is_some(),
+        );
+        let Some(area) = area else {
+            continue;
+        };
+        let texel_size = settings
+            .texel_size
+            .max(area.width().max(area.height()) / settings.max_resolution.max(1) as f32);
+        let inputs = LightMapInputs {
+            lighting: lighting.clone(),
+            area,
+            resolution: (area.size() / texel_size.max(f32::EPSILON))
+                .ceil()
+                .as_uvec2(),
+            lights,
+        };
+
+        let translation = local_center(level_transform, area);
+        let Some((_, mut light_map, mut sprite, mut transform)) =
+            light_map.and_then(|light_map| light_maps.get_mut(light_map).ok())
+        else {
+            let image = light_map_image(lighting, area, inputs.resolution, &inputs.lights);
+            commands.spawn((
+                Sprite {
+                    image: images.add(image),
+                    custom_size: Some(area.size()),
+                    ..default()
+                },
+                LightMap {
+                    level,
+                    inputs: Some(inputs),
+                },
+                Transform::from_translation(translation),
+                ChildOf(level),
+            ));
+            continue;
+        };
+        if light_map.inputs.as_ref() == Some(&inputs) {
+            continue;
+        }
+
+        // Light maps are spawned with their own image, replacing it keeps the handle.
+        let image = light_map_image(lighting, area, inputs.resolution, &inputs.lights);
+        if let Some(existing) = images.get_mut(&sprite.image) {
+            *existing = image;
+        }
+        sprite.custom_size = Some(area.size());
+        transform.translation = translation;
+        light_map.inputs = Some(inputs);
+    }
+
+    // Levels that lost their lighting or were despawned take their light map with them.
+    for light_map in existing.into_values() {
+        commands.entity(light_map).despawn();
+    }
+}
+
+/// The area covered by the contents of `level` and the areas lit by its visible lights, after
+/// `seconds` or held steady while `exporting`.
+///
+/// Returns no area if the level has no contents.
+fn gather(
+    level: Entity,
+    children: &Query<&Children>,
+    contents: &LevelContents,
+    seconds: f32,
+    exporting: bool,
+) -> (Option<Rect>, Vec<LitArea>) {
+    let mut area: Option<Rect> = None;
+    let mut lights = Vec::new();
+    for entity in children.iter_descendants(level) {
+        let Ok((transform, aabb, light, visibility)) = contents.get(entity) else {
+            continue;
+        };
+
+        let center = transform.translation().truncate();
+        let bounds = match (light, aabb) {
+            (Some(light), _) => Rect::from_center_half_size(center, Vec2::splat(light.radius)),
+            (None, Some(aabb)) => world_bounds(transform, aabb),
+            (None, None) => Rect::from_center_size(center, Vec2::ZERO),
+        };
+        area = Some(area.map_or(bounds, |area| area.union(bounds)));
+
+        if let Some(light) = light
+            && visibility.is_none_or(|visibility| visibility.get())
+        {
+            #[allow(
+                clippy::cast_precision_loss,
+                reason = "the seed only needs to differ between lights"
+            )]
+            let seed = entity.to_bits() as f32;
+            lights.push(LitArea {
+                center,
+                radius: light.radius,
+                color: light.color,
+                intensity: if exporting {
+                    light.intensity
+                } else {
+                    flickered_intensity(light, seconds, seed)
+                },
+            });
+        }
+    }
+
+    (area.filter(|area| !area.is_empty()), lights)
+}
+
+/// The world-space bounds of `aabb` positioned by `transform`.
+pub(crate) fn world_bounds(transform: &GlobalTransform, aabb: &Aabb) -> Rect {
+    let center = Vec3::from(aabb.center);
+    let half = Vec3::from(aabb.half_extents);
+    [
+        Vec3::new(-half.x, -half.y, 0.0),
+        Vec3::new(half.x, -half.y, 0.0),
+        Vec3::new(-half.x, half.y, 0.0),
+        Vec3::new(half.x, half.y, 0.0),
+    ]
+    .into_iter()
+    .map(|corner| transform.transform_point(center + corner).truncate())
+    .fold(Rect::EMPTY, |bounds, corner| bounds.union_point(corner))
+}
+
+/// The position of the center of `area` within the level positioned by `level`, above its
+/// layers.
+fn local_center(level: &GlobalTransform, area: Rect) -> Vec3 {
+    let center = level
+        .affine()
+        .inverse()
+        .transform_point3(area.center().extend(0.0));
+
+    center.truncate().extend(LIGHT_MAP_Z)
+}
@@ -0,0 +1,356 @@
+//! Measures distances and areas on the map in real-world units.
+//!
+//! The user interface hands the ruler over as requests in world units: dragging measures the
+//! distance between two points, clicking corners measures the area of the polygon through them.
+//! The [`Ruler`] holds the measurement in progress for the user interface to show, and a
+//! [`Measured`] message reports every finished measurement.
+
+use crate::DragPhase;
+use bevy::prelude::*;
+use dungeonrs_data::{DistanceUnit, Grid, MapScale};
+use dungeonrs_macros::bevy_system;
+use std::fmt::{Display, Formatter};
+
+/// Registers the ruler and its messages.
+pub struct MeasurementPlugin;
+
+impl Plugin for MeasurementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Ruler>()
+            .add_message::<DragRuler>()
+            .add_message::<AddRulerPoint>()
+            .add_message::<FinishRuler>()
+            .add_message::<CancelRuler>()
+            .add_message::<Measured>()
+            .add_systems(Update, measure);
+    }
+}
+
+/// The state of the ruler, configured by the user interface.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Ruler {
+    /// The unit measurements are given in.
+    pub unit: DistanceUnit,
+    /// Whether the measured points snap to the [`Grid`].
+    pub snap: bool,
+    /// The points measured so far, in world units.
+    points: Vec<Vec2>,
+    /// What the points measure, `None` while nothing is measured.
+    kind: Option<MeasurementKind>,
+    /// Whether the measurement was finished, the next request starts a new one.
+    finished: bool,
+}
+
+impl Default for Ruler {
+    fn default() -> Self {
+        Self {
+            unit: DistanceUnit::Feet,
+            snap: false,
+            points: Vec::new(),
+            kind: None,
+            finished: false,
+        }
+    }
+}
+
+impl Ruler {
+    /// The points measured so far, in world units, so the user interface can draw the ruler:
+    /// the ends of a distance or the corners of an area.
+    #[must_use]
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+
+    /// The current measurement in the ruler's unit, `None` until there's something to measure.
+    ///
+    /// Finished measurements stay on the ruler until the next one starts or the ruler is
+    /// cancelled.
+    #[must_use]
+    pub fn measurement(&self, grid: &Grid, scale: &MapScale) -> Option<Measurement> {
+        let kind = self.kind?;
+        let world = match kind {
+            MeasurementKind::Distance if self.points.len() >= 2 => self
+                .points
+                .windows(2)
+                .map(|pair| pair[0].distance(pair[1]))
+                .sum(),
+            MeasurementKind::Area if self.points.len() >= 3 => polygon_area(&self.points),
+            _ => return None,
+        };
+        let value = match kind {
+            MeasurementKind::Distance => scale.distance(grid, world, self.unit),
+            MeasurementKind::Area => scale.area(grid, world, self.unit),
+        };
+
+        Some(Measurement {
+            kind,
+            world,
+            value,
+            unit: self.unit,
+        })
+    }
+
+    /// Drops the points, starting a new measurement of `kind`.
+    fn start(&mut self, kind: MeasurementKind) {
+        self.points.clear();
+        self.kind = Some(kind);
+        self.finished = false;
+    }
+
+    /// Drops the points and what they measure.
+    fn clear(&mut self) {
+        self.points.clear();
+        self.kind = None;
+        self.finished = false;
+    }
+}
+
+/// What a [`Measurement`] measures.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MeasurementKind {
+    /// The length of the line through the points.
+    Distance,
+    /// The area of the polygon through the points.
+    Area,
+}
+
+/// A distance or area measured by the [`Ruler`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Measurement {
+    /// Whether a distance or an area was measured.
+    pub kind: MeasurementKind,
+    /// The measurement in world units, or square world units for an area.
+    pub world: f32,
+    /// The measurement in `unit`, or square `unit` for an area.
+    pub value: f32,
+    /// The unit of `value`.
+    pub unit: DistanceUnit,
+}
+
+impl Display for Measurement {
+    /// Writes the value with its unit, such as `30.0 ft` or `150.0 ft²`. Areas in squares are
+    /// written as a number of squares, such as `6.0 sq`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let precision = f.precision().unwrap_or(1);
+        write!(f, "{:.*} {}", precision, self.value, self.unit.symbol())?;
+        if self.kind == MeasurementKind::Area && self.unit != DistanceUnit::Squares {
+            write!(f, "²")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Measures the distance between where the cursor was pressed and where it is, such as while the
+/// user drags the ruler.
+#[derive(Message, Debug, Copy, Clone, PartialEq)]
+pub struct DragRuler {
+    /// The step of the drag, ending it reports the distance as [`Measured`].
+    pub phase: DragPhase,
+    /// The position of the cursor, in world units.
+    pub position: Vec2,
+}
+
+/// Adds a corner to the area being measured, such as where the user clicked.
+#[derive(Message, Debug, Copy, Clone, PartialEq)]
+pub struct AddRulerPoint {
+    /// The position of the corner, in world units.
+    pub position: Vec2,
+}
+
+/// Finishes the area being measured, reporting it as [`Measured`].
+#[derive(Message, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct FinishRuler;
+
+/// Clears the ruler, discarding the measurement in progress.
+#[derive(Message, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CancelRuler;
+
+/// Reports a finished measurement of the [`Ruler`].
+#[derive(Message, Debug, Clone, PartialEq)]
+pub struct Measured {
+    /// The finished measurement.
+    pub measurement: Measurement,
+    /// The measured points, in world units.
+    pub points: Vec<Vec2>,
+}
+
+/// Applies the requests of the ruler and reports finished measurements.
+#[bevy_system]
+#[allow(
+    clippy::too_many_arguments,
+    reason = "the ruler is driven by its requests and converts through the grid and the scale"
+)]
+fn measure(
+    mut ruler: ResMut<Ruler>,
+    mut drags: MessageReader<DragRuler>,
+    mut points: MessageReader<AddRulerPoint>,
+    mut finishes: MessageReader<FinishRuler>,
+    mut cancels: MessageReader<CancelRuler>,
+    mut measured: MessageWriter<Measured>,
+    grid: Option<Res<Grid>>,
+    scale: Option<Res<MapScale>>,
+) {
+    let grid = grid.as_deref().copied().unwrap_or_default();
+    let scale = scale.as_deref().copied().unwrap_or_default();
+    let snap = |ruler: &Ruler, position: Vec2| {
+        if ruler.snap {
+            grid.snap_position(position)
+        } else {
+            position
+        }
+    };
+    let mut finish = |ruler: &mut Ruler| {
+        ruler.finished = true;
+        if let Some(measurement) = ruler.measurement(&grid, &scale) {
+            measured.write(Measured {
+                measurement,
+                points: ruler.points.clone(),
+            });
+        }
+    };
+
+    for drag in drags.read() {
+        let position = snap(&ruler, drag.position);
+        match drag.phase {
+            DragPhase::Start => {
+                ruler.start(MeasurementKind::Distance);
+                ruler.points.extend([position, position]);
+            }
+            DragPhase::Move | DragPhase::End
+                if ruler.kind == Some(MeasurementKind::Distance) && !ruler.finished =>
+            {
+                if let Some(end) = ruler.points.last_mut() {
+                    *end = position;
+                }
+                if drag.phase == DragPhase::End {
+                    finish(&mut ruler);
+                }
+            }
+            DragPhase::Cancel => ruler.clear(),
+            DragPhase::Move | DragPhase::End => {}
+        }
+    }
+
+    for point in points.read() {
+        if ruler.kind != Some(MeasurementKind::Area) || ruler.finished {
+            ruler.start(MeasurementKind::Area);
+        }
+        let position = snap(&ruler, point.position);
+        if ruler.points.last() != Some(&position) {
+            ruler.points.push(position);
+        }
+    }
+
+    if finishes.read().count() > 0 && ruler.kind == Some(MeasurementKind::Area) && !ruler.finished {
+        finish(&mut ruler);
+    }
+
+    if cancels.read().count() > 0 {
+        ruler.clear();
+    }
+}
+
+/// The area of the polygon through `corners`, whichever way they go around.
+fn polygon_area(corners: &[Vec2]) -> f32 {
+    corners
+        .iter()
+        .zip(corners.iter().cycle().skip(1))
+        .map(|(from, to)| from.perp_dot(*to))
+        .sum::<f32>()
+        .abs()
+        / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    //! Measures distances and areas on square and hexagonal grids.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+    use dungeonrs_data::{GridShape, SnapTargets};
+
+    /// An app measuring on `grid`, where a cell is 5 feet across.
+    fn app(grid: Grid) -> App {
+        let mut app = App::new();
+        app.add_plugins(MeasurementPlugin)
+            .insert_resource(grid)
+            .insert_resource(MapScale::default());
+
+        app
+    }
+
+    /// Drags the ruler from `from` to `to` and returns the measured distance.
+    fn drag(app: &mut App, from: Vec2, to: Vec2) -> Measurement {
+        for (phase, position) in [(DragPhase::Start, from), (DragPhase::End, to)] {
+            app.world_mut().write_message(DragRuler { phase, position });
+            app.update();
+        }
+
+        let grid = *app.world().resource::<Grid>();
+        app.world()
+            .resource::<Ruler>()
+            .measurement(&grid, &MapScale::default())
+            .expect("the drag measured a distance")
+    }
+
+    /// Diagonals are measured along a straight line rather than counted in cells, and convert to
+    /// other units through the scale.
+    #[test]
+    fn measures_diagonals() {
+        let mut app = app(Grid::new(100.0));
+
+        let measurement = drag(&mut app, Vec2::ZERO, Vec2::new(300.0, 400.0));
+        assert!((measurement.world - 500.0).abs() < 1e-3);
+        assert!((measurement.value - 25.0).abs() < 1e-3);
+        assert_eq!(measurement.to_string(), "25.0 ft");
+
+        let scale = MapScale::default();
+        let metres = scale.distance(&Grid::new(100.0), measurement.world, DistanceUnit::Metres);
+        assert!((metres - 7.62).abs() < 1e-3);
+    }
+
+    /// On a hexagonal grid a cell is the distance between neighbouring centers, which the
+    /// snapped ends of the ruler land on.
+    #[test]
+    fn measures_hex_distances() {
+        let mut app = app(Grid {
+            targets: SnapTargets {
+                corners: false,
+                centers: true,
+                edges: false,
+            },
+            ..Grid::new(100.0).with_shape(GridShape::PointyHex)
+        });
+        app.world_mut().resource_mut::<Ruler>().snap = true;
+
+        // The neighbour to the right, then the one after the next up and to the right.
+        let neighbour = drag(&mut app, Vec2::new(5.0, -5.0), Vec2::new(95.0, 8.0));
+        assert!((neighbour.value - 5.0).abs() < 1e-3);
+        let diagonal = drag(&mut app, Vec2::ZERO, Vec2::new(148.0, 90.0));
+        assert!((diagonal.world - 100.0 * 3.0_f32.sqrt()).abs() < 1e-2);
+    }
+
+    /// The area of the polygon through the corners is measured whichever way they go around, in
+    /// squares as a number of cells.
+    #[test]
+    fn measures_areas() {
+        let mut app = app(Grid::new(100.0));
+        app.world_mut().resource_mut::<Ruler>().unit = DistanceUnit::Squares;
+        for corner in [[0.0, 0.0], [0.0, 200.0], [300.0, 200.0], [300.0, 0.0]] {
+            app.world_mut().write_message(AddRulerPoint {
+                position: Vec2::from(corner),
+            });
+        }
+        app.world_mut().write_message(FinishRuler);
+        app.update();
+
+        let measurement = app
+            .world()
+            .resource::<Ruler>()
+            .measurement(&Grid::new(100.0), &MapScale::default())
+            .unwrap();
+        assert_eq!(measurement.kind, MeasurementKind::Area);
+        assert_eq!(measurement.to_string(), "6.0 sq");
+    }
+}
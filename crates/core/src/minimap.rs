@@ -0,0 +1,301 @@
+//! Renders an overview of a whole level, so the user can find their way around very large maps.
+//!
+//! Like the live preview, the level is exported through the regular export pipeline at a low
+//! resolution, at most once per [`MinimapSettings::interval`] and only when the map changed. The
+//! result is uploaded as the [`Minimap`] image for the user interface to show, and clicking it
+//! moves the viewport there with a [`JumpToMinimap`].
+
+use crate::export::{ExportCapture, ViewportCamera};
+use crate::lighting::world_bounds;
+use crate::preview::map_changed;
+use crate::{
+    ExportCapabilities, ExportError, ExportInput, ExportOutput, ExportRegistry, ExportRequest,
+    ExportSettings, Exporter,
+};
+use bevy::asset::RenderAssetUsages;
+use bevy::camera::primitives::Aabb;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::time::Real;
+use dungeonrs_data::{Element, HierarchySnapshot, Layer, MapProjection};
+use dungeonrs_macros::bevy_system;
+use image::RgbaImage;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// The path minimap renders are exported to, they're never written to disk.
+const MINIMAP_PATH: &str = "minimap.png";
+
+/// Renders the [`Minimap`] of a level and moves the viewport to where it's clicked.
+///
+/// Requires the [`ExportPlugin`](crate::ExportPlugin) to render the minimap, the
+/// [`UtilsPlugin`](dungeonrs_utils::UtilsPlugin) to process it in the background and the
+/// [`DataPlugin`](dungeonrs_data::DataPlugin), whose [`HierarchySnapshot`] lists the levels.
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        let minimap = Minimap::default();
+        app.init_resource::<ExportRegistry>();
+        app.world_mut()
+            .resource_mut::<ExportRegistry>()
+            .register(MinimapExporter(minimap.rendered.clone()));
+
+        app.insert_resource(minimap)
+            .init_resource::<MinimapSettings>()
+            .init_resource::<HierarchySnapshot>()
+            .add_message::<JumpToMinimap>()
+            .add_systems(
+                Update,
+                (
+                    mark_minimap_outdated.run_if(map_changed),
+                    request_minimap,
+                    upload_minimap,
+                    jump_to_minimap,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Configures the [`Minimap`].
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct MinimapSettings {
+    /// The level shown by the minimap, the first level of the project when `None`.
+    pub level: Option<Entity>,
+    /// The size of the longest side of the minimap, in pixels.
+    pub max_size: u32,
+    /// The shortest time between two renders of the minimap, however often the map changes.
+    pub interval: Duration,
+}
+
+impl Default for MinimapSettings {
+    fn default() -> Self {
+        Self {
+            level: None,
+            max_size: 256,
+            interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// The overview of a level rendered for the user interface.
+#[derive(Resource, Default)]
+pub struct Minimap {
+    /// The rendered overview, `None` until it was rendered once.
+    image: Option<Handle<Image>>,
+    /// The area of the map shown by `image`.
+    area: Option<Rect>,
+    /// The projection `image` was rendered through.
+    projection: MapProjection,
+    /// The area of the map being rendered, shown once the render arrives.
+    requested: Option<Rect>,
+    /// When the last render was requested, in real time since startup.
+    requested_at: Option<Duration>,
+    /// Whether the map changed since the last render.
+    outdated: bool,
+    /// The render handed over by the [`MinimapExporter`], waiting to be uploaded.
+    rendered: Arc<Mutex<Option<RgbaImage>>>,
+}
+
+impl Minimap {
+    /// The rendered overview, `None` until it was rendered once.
+    #[must_use]
+    pub fn image(&self) -> Option<&Handle<Image>> {
+        self.image.as_ref()
+    }
+
+    /// The area of the map shown by the overview, in world units.
+    #[must_use]
+    pub fn area(&self) -> Option<Rect> {
+        self.area
+    }
+
+    /// The position on the map shown at `position` on the overview, from `(0, 0)` at its top
+    /// left corner to `(1, 1)` at its bottom right corner.
+    ///
+    /// Returns `None` until the overview was rendered once.
+    #[must_use]
+    pub fn map_position(&self, position: Vec2) -> Option<Vec2> {
+        // The overview shows the area through the projection, the point on screen is turned
+        // back into a point of the map.
+        let view = self.projection.project_rect(self.area?);
+        let screen = Vec2::new(
+            view.min.x + position.x * view.width(),
+            view.max.y - position.y * view.height(),
+        );
+
+        Some(self.projection.unproject(screen))
+    }
+}
+
+/// Centers the viewport on the point of the map shown at a position of the [`Minimap`], such as
+/// where the user clicked it.
+#[derive(Message, Debug, Copy, Clone, PartialEq)]
+pub struct JumpToMinimap {
+    /// The position on the overview, see [`Minimap::map_position`].
+    pub position: Vec2,
+}
+
+/// Hands the exported overview over to the [`Minimap`] instead of writing a file.
+struct MinimapExporter(Arc<Mutex<Option<RgbaImage>>>);
+
+impl MinimapExporter {
+    /// The id the exporter is registered as.
+    const ID: &'static str = "minimap";
+}
+
+impl Exporter for MinimapExporter {
+    fn id(&self) -> &'static str {
+        Self::ID
+    }
+
+    fn name(&self) -> String {
+        "Minimap".into()
+    }
+
+    fn capabilities(&self) -> ExportCapabilities {
+        ExportCapabilities {
+            output: ExportOutput::File,
+            extensions: &[],
+        }
+    }
+
+    fn run(&self, input: ExportInput, _settings: &ExportSettings) -> Result<(), ExportError> {
+        // A panic while the render was locked can't leave it inconsistent.
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = Some(input.image);
+
+        Ok(())
+    }
+}
+
+/// Flags the minimap to be rendered again.
+#[bevy_system]
+fn mark_minimap_outdated(mut minimap: ResMut<Minimap>) {
+    minimap.outdated = true;
+}
+
+/// Requests exporting the level shown by the minimap, once the interval since the last render
+/// passed.
+#[bevy_system]
+#[allow(
+    clippy::too_many_arguments,
+    reason = "the minimap depends on its settings, the running export and the level's contents"
+)]
+fn request_minimap(
+    mut minimap: ResMut<Minimap>,
+    mut requests: MessageWriter<ExportRequest>,
+    settings: Res<MinimapSettings>,
+    projection: Res<MapProjection>,
+    time: Res<Time<Real>>,
+    capture: Option<Res<ExportCapture>>,
+    snapshot: Res<HierarchySnapshot>,
+    layers: Query<&Layer>,
+    elements: Query<(&GlobalTransform, &Aabb), With<Element>>,
+) {
+    if settings.is_changed() || projection.is_changed() {
+        minimap.outdated = true;
+    }
+    let now = time.elapsed();
+    let due = minimap
+        .requested_at
+        .is_none_or(|requested_at| now.saturating_sub(requested_at) >= settings.interval);
+    // Rather than queueing behind a running export, the minimap waits for it to finish.
+    if !minimap.outdated || !due || capture.is_some() {
+        return;
+    }
+
+    let level = settings
+        .level
+        .and_then(|level| snapshot.level(level))
+        .or_else(|| snapshot.levels().next());
+    let Some(level) = level else {
+        return;
+    };
+    minimap.outdated = false;
+    minimap.requested_at = Some(now);
+
+    let visible: Vec<_> = level
+        .layers
+        .iter()
+        .filter(|node| layers.get(node.entity).is_ok_and(|layer| !layer.hidden))
+        .collect();
+    let area = visible
+        .iter()
+        .flat_map(|node| node.all_elements())
+        .filter_map(|entity| elements.get(entity).ok())
+        .map(|(transform, aabb)| world_bounds(transform, aabb))
+        .reduce(|area, bounds| area.union(bounds))
+        .filter(|area| !area.is_empty());
+    let Some(area) = area else {
+        return;
+    };
+
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "minimap sizes are far below the range where f32 loses precision"
+    )]
+    let pixels_per_unit = settings.max_size.max(1) as f32 / area.size().max_element();
+    minimap.requested = Some(area);
+    minimap.projection = *projection;
+    requests.write(
+        ExportRequest::new(MINIMAP_PATH, area, pixels_per_unit)
+            .with_exporter(MinimapExporter::ID, ExportSettings::default())
+            .with_layers(MINIMAP_PATH, visible.iter().map(|node| node.entity))
+            .in_background(),
+    );
+}
+
+/// Uploads the overview handed over by the [`MinimapExporter`].
+#[bevy_system]
+fn upload_minimap(mut minimap: ResMut<Minimap>, mut images: ResMut<Assets<Image>>) {
+    let rendered = minimap
+        .rendered
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    let Some(rendered) = rendered else {
+        return;
+    };
+
+    let image = Image::new(
+        Extent3d {
+            width: rendered.width(),
+            height: rendered.height(),
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        rendered.into_raw(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    match minimap
+        .image
+        .as_ref()
+        .and_then(|handle| images.get_mut(handle))
+    {
+        Some(current) => *current = image,
+        None => minimap.image = Some(images.add(image)),
+    }
+    minimap.area = minimap.requested.take().or(minimap.area);
+}
+
+/// Centers the viewport cameras on the points of the map clicked on the minimap.
+#[bevy_system]
+fn jump_to_minimap(
+    mut jumps: MessageReader<JumpToMinimap>,
+    minimap: Res<Minimap>,
+    mut cameras: Query<(&Camera, &mut Transform), ViewportCamera>,
+) {
+    let Some(target) = jumps
+        .read()
+        .last()
+        .and_then(|jump| minimap.map_position(jump.position))
+    else {
+        return;
+    };
+
+    for (_, mut transform) in cameras.iter_mut().filter(|(camera, _)| camera.is_active) {
+        transform.translation = target.extend(transform.translation.z);
+    }
+}
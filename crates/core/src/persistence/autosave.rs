@@ -0,0 +1,239 @@
+//! Periodically saves the open project as a snapshot, so unsaved work survives a crash.
+//!
+//! A marker file in the autosave directory is created at startup and removed when the app exits.
+//! When the marker still exists at the next launch, the previous session crashed and the most
+//! recent snapshot is offered through [`UnsavedWorkFound`].
+
+use crate::Configuration;
+use crate::persistence::{SaveCache, SaveFile};
+use bevy::prelude::*;
+use bevy::time::Real;
+use dungeonrs_data::Project;
+use dungeonrs_macros::bevy_system;
+use dungeonrs_serialization::{Error, Format};
+use dungeonrs_utils::{AsyncCommandsExt, Directory, report_progress};
+use std::fs::{File, create_dir_all, read_dir, remove_file, rename};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The prefix of the file names of snapshots.
+const SNAPSHOT_PREFIX: &str = "autosave-";
+
+/// The name of the file marking a running session.
+const SESSION_MARKER: &str = "session";
+
+/// How often [`autosave`] checks whether the next snapshot is due.
+///
+/// The system has exclusive access to the world, so it's kept from running every frame.
+pub(crate) const AUTOSAVE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the [`Configuration`] must stay unchanged before its autosave interval is applied,
+/// so typing a new interval doesn't apply every keystroke.
+pub(crate) const CONFIGURATION_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Configures the autosave.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct AutosaveSettings {
+    /// Whether the open project is saved periodically.
+    pub enabled: bool,
+    /// The time between two snapshots.
+    pub interval: Duration,
+    /// The number of snapshots kept, older snapshots are removed.
+    pub snapshots: usize,
+    /// The directory the snapshots are written to.
+    pub directory: PathBuf,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: Duration::from_mins(5),
+            snapshots: 3,
+            directory: Directory::Data.join("autosave"),
+        }
+    }
+}
+
+/// Written at startup when the previous session crashed, with the last snapshot it saved.
+///
+/// The work is restored by opening the snapshot with an [`OpenProject`](crate::OpenProject).
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct UnsavedWorkFound {
+    /// The most recent snapshot.
+    pub snapshot: PathBuf,
+    /// When the snapshot was saved.
+    pub saved_at: SystemTime,
+}
+
+/// Written when a snapshot couldn't be saved.
+#[derive(Message, Debug)]
+pub struct AutosaveFailed {
+    /// The reason the snapshot couldn't be saved.
+    pub error: Error,
+}
+
+/// Returns the snapshots in `directory`, most recent first.
+///
+/// # Errors
+/// Returns an error if the directory can't be read.
+pub fn autosave_snapshots(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut snapshots = Vec::new();
+    for entry in read_dir(directory)? {
+        let path = entry?.path();
+        let is_snapshot = path
+            .extension()
+            .is_some_and(|extension| extension == SaveFile::EXTENSION)
+            && path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(SNAPSHOT_PREFIX));
+        if is_snapshot {
+            snapshots.push(path);
+        }
+    }
+
+    // File names hold the zero-padded time the snapshot was saved at, so they sort by age.
+    snapshots.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(snapshots)
+}
+
+/// Offers the last snapshot if the previous session crashed, and marks this session as running.
+#[bevy_system]
+pub(crate) fn start_session(
+    settings: Res<AutosaveSettings>,
+    mut found: MessageWriter<UnsavedWorkFound>,
+) {
+    if !settings.enabled {
+        return;
+    }
+
+    let marker = settings.directory.join(SESSION_MARKER);
+    if marker.exists()
+        && let Some(snapshot) = autosave_snapshots(&settings.directory)
+            .ok()
+            .and_then(|snapshots| snapshots.into_iter().next())
+    {
+        let saved_at = snapshot
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or_else(|_| SystemTime::now());
+        found.write(UnsavedWorkFound { snapshot, saved_at });
+    }
+
+    // Without the marker a crash goes unnoticed, which doesn't prevent saving snapshots.
+    let _ = create_dir_all(&settings.directory).and_then(|()| File::create(&marker));
+}
+
+/// Removes the marker of the running session once the app exits.
+#[bevy_system]
+pub(crate) fn end_session(settings: Res<AutosaveSettings>, mut exits: MessageReader<AppExit>) {
+    if exits.read().count() > 0 {
+        // A leftover marker only means the last snapshot is offered again at the next launch.
+        let _ = remove_file(settings.directory.join(SESSION_MARKER));
+    }
+}
+
+/// Applies the autosave interval of the [`Configuration`], run once it stopped changing for
+/// [`CONFIGURATION_DEBOUNCE`].
+///
+/// The [`ConfigurationChanged`](crate::ConfigurationChanged) messages are gone by then, so the interval is applied whichever
+/// setting changed, which leaves the settings untouched when it's the same.
+#[bevy_system]
+pub(crate) fn apply_autosave_configuration(
+    configuration: Option<Res<Configuration>>,
+    mut settings: ResMut<AutosaveSettings>,
+) {
+    let Some(configuration) = configuration else {
+        return;
+    };
+
+    let mut applied = settings.clone();
+    match configuration.autosave_interval() {
+        Some(interval) => {
+            applied.enabled = true;
+            applied.interval = interval;
+        }
+        None => applied.enabled = false,
+    }
+    settings.set_if_neq(applied);
+}
+
+/// Saves a snapshot of the open project in the background every [`AutosaveSettings::interval`].
+///
+/// Run every [`AUTOSAVE_CHECK_INTERVAL`], so snapshots are saved up to that much later than due.
+pub(crate) fn autosave(world: &mut World, mut last_saved: Local<Option<Duration>>) {
+    let (Some(settings), Some(time)) = (
+        world.get_resource::<AutosaveSettings>().cloned(),
+        world.get_resource::<Time<Real>>(),
+    ) else {
+        return;
+    };
+    let now = time.elapsed();
+    let last = *last_saved.get_or_insert(now);
+    if !settings.enabled || now.saturating_sub(last) < settings.interval {
+        return;
+    }
+    *last_saved = Some(now);
+
+    let mut projects = world.query_filtered::<Entity, With<Project>>();
+    let Some(save) = projects
+        .iter(world)
+        .next()
+        .and_then(|project| SaveFile::capture(world, project))
+    else {
+        return;
+    };
+
+    world.commands().spawn_async(move |context| async move {
+        if let Err(error) = write_snapshot(&save, &settings) {
+            report_progress(&context, AutosaveFailed { error });
+        }
+    });
+    world.flush();
+}
+
+/// Writes `save` as a new snapshot and removes the snapshots exceeding
+/// [`AutosaveSettings::snapshots`].
+///
+/// # Errors
+/// Returns an error if the snapshot can't be serialized or written.
+fn write_snapshot(save: &SaveFile, settings: &AutosaveSettings) -> Result<(), Error> {
+    create_dir_all(&settings.directory)?;
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = settings.directory.join(format!(
+        "{SNAPSHOT_PREFIX}{millis:020}.{}",
+        SaveFile::EXTENSION
+    ));
+
+    // Written and synced next to the snapshot first, so a crash while saving never leaves a
+    // partial one.
+    let partial = path.with_extension("partial");
+    let written = File::create(&partial)
+        .map_err(Error::from)
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            save.write(&mut writer, Format::MessagePack, &mut SaveCache::default())?;
+            writer
+                .into_inner()
+                .map_err(|error| Error::Io(error.into_error()))?
+                .sync_all()?;
+            Ok(rename(&partial, &path)?)
+        });
+    if written.is_err() {
+        let _ = remove_file(&partial);
+    }
+    written?;
+
+    for stale in autosave_snapshots(&settings.directory)?
+        .into_iter()
+        .skip(settings.snapshots.max(1))
+    {
+        remove_file(stale)?;
+    }
+
+    Ok(())
+}
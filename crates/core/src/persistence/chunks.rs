@@ -0,0 +1,616 @@
+//! Writes a [`SaveFile`] as a stream of chunks, reusing the bytes of unchanged layers.
+//!
+//! Saving a huge map serializes (and compresses) every element even if only a few of them
+//! changed. The save is instead split into a header describing the hierarchy, followed by one
+//! chunk per layer holding its elements, labels and shapes. The [`SaveCache`] remembers the hash and bytes of each
+//! layer's chunk by its [`PersistentId`](dungeonrs_data::PersistentId), so layers that hash the
+//! same as last time are written without being serialized again.
+
+use crate::persistence::migrations;
+use crate::persistence::{
+    AnimationData, BackgroundData, ElementData, GroupData, LabelData, LayerData, LevelData,
+    LightData, LightingData, LinkData, PortalData, RegionData, SaveFile, ShapeData, ShapeKindData,
+    TerrainData, WallData, WallPathData,
+};
+use bevy::asset::uuid::Uuid;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use dungeonrs_serialization::{Error, Format, StreamReader, StreamWriter, Versioned, from_value};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{Read, Write};
+use xxhash_rust::xxh3::Xxh3;
+
+/// The serialized chunks of the layers written by the last save.
+#[derive(Resource, Default)]
+pub struct SaveCache {
+    /// The format the chunks are serialized in, chunks are only reused for the same format.
+    format: Option<Format>,
+    /// The last written chunk of each layer.
+    chunks: HashMap<Uuid, Chunk>,
+}
+
+/// The serialized contents of a single layer.
+struct Chunk {
+    /// The hash of the contents the chunk was serialized from.
+    hash: u64,
+    /// The serialized contents.
+    bytes: Vec<u8>,
+}
+
+impl SaveCache {
+    /// The number of layers with a cached chunk.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns whether no layers are cached.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Forgets all cached chunks, the next save serializes every layer.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+}
+
+/// The first record of a chunked save, describing the hierarchy without the layer contents.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    /// The [`Versioned::VERSION`] of the [`SaveFile`] the stream was written from.
+    version: u32,
+    /// The id of the project.
+    id: Uuid,
+    /// The name of the project.
+    name: String,
+    /// The levels of the project, in order.
+    levels: Vec<LevelHeader>,
+}
+
+/// Describes a level in the [`Header`].
+#[derive(Serialize, Deserialize)]
+struct LevelHeader {
+    /// The id of the level.
+    id: Uuid,
+    /// The name of the level.
+    name: String,
+    /// The darkness and ambient light of the level.
+    #[serde(default)]
+    lighting: Option<LightingData>,
+    /// What the level shows below its layers.
+    #[serde(default)]
+    background: Option<BackgroundData>,
+    /// The regions of the level that can be hidden from the players.
+    #[serde(default)]
+    regions: Vec<RegionData>,
+    /// The stairs, ladders and teleporters leading from the level to another level.
+    #[serde(default)]
+    links: Vec<LinkData>,
+    /// The layers of the level, their contents follow the header in the same order.
+    layers: Vec<LayerHeader>,
+}
+
+/// The contents of a layer, written as one chunk per layer after the [`Header`].
+#[derive(Serialize, Deserialize)]
+struct LayerContents<Elements, Groups, Labels, Walls, Portals, Lights, Paths, Terrains, Shapes> {
+    /// The elements on the layer.
+    elements: Elements,
+    /// The groups of elements on the layer, missing from saves written before groups existed.
+    #[serde(default)]
+    groups: Groups,
+    /// The labels on the layer.
+    labels: Labels,
+    /// The walls on the layer.
+    walls: Walls,
+    /// The portals on the layer.
+    portals: Portals,
+    /// The light sources on the layer.
+    lights: Lights,
+    /// The wall paths on the layer, missing from saves written before wall paths existed.
+    #[serde(default)]
+    paths: Paths,
+    /// The terrains on the layer, missing from saves written before terrains existed.
+    #[serde(default)]
+    terrains: Terrains,
+    /// The shapes on the layer, missing from saves written before shapes existed.
+    #[serde(default)]
+    shapes: Shapes,
+}
+
+/// The [`LayerContents`] as read back from a stream.
+type OwnedLayerContents = LayerContents<
+    Vec<ElementData>,
+    Vec<GroupData>,
+    Vec<LabelData>,
+    Vec<WallData>,
+    Vec<PortalData>,
+    Vec<LightData>,
+    Vec<WallPathData>,
+    Vec<TerrainData>,
+    Vec<ShapeData>,
+>;
+
+/// Describes a layer in the [`Header`].
+#[derive(Serialize, Deserialize)]
+struct LayerHeader {
+    /// The id of the layer.
+    id: Uuid,
+    /// The name of the layer.
+    name: String,
+    /// Whether the layer is locked.
+    locked: bool,
+    /// Whether the layer is hidden, missing from saves written before layers could be hidden.
+    #[serde(default)]
+    hidden: bool,
+    /// The opacity of the layer.
+    opacity: f32,
+}
+
+impl SaveFile {
+    /// Writes the save file as a stream of chunks in `format`, reusing the chunks in `cache` for
+    /// layers that didn't change since they were last written.
+    ///
+    /// Returns the number of layers whose chunk was reused.
+    ///
+    /// # Errors
+    /// Returns an error if a chunk fails to serialize or `writer` fails.
+    pub fn write<W: Write>(
+        &self,
+        writer: W,
+        format: Format,
+        cache: &mut SaveCache,
+    ) -> Result<usize, Error> {
+        self.write_reporting(writer, format, cache, |_, _| {})
+    }
+
+    /// Writes the save file like [`SaveFile::write`], calling `progress` with the number of
+    /// layers written so far and the total number of layers after each layer.
+    ///
+    /// # Errors
+    /// Returns an error if a chunk fails to serialize or `writer` fails.
+    pub(crate) fn write_reporting<W: Write>(
+        &self,
+        writer: W,
+        format: Format,
+        cache: &mut SaveCache,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<usize, Error> {
+        if cache.format != Some(format) {
+            cache.chunks.clear();
+            cache.format = Some(format);
+        }
+
+        let mut stream = StreamWriter::new(writer, format)?;
+        stream.write(&Header {
+            version: Self::VERSION,
+            id: self.id,
+            name: self.name.clone(),
+            levels: self
+                .levels
+                .iter()
+                .map(|level| LevelHeader {
+                    id: level.id,
+                    name: level.name.clone(),
+                    lighting: level.lighting.clone(),
+                    background: level.background.clone(),
+                    regions: level.regions.clone(),
+                    links: level.links.clone(),
+                    layers: level
+                        .layers
+                        .iter()
+                        .map(|layer| LayerHeader {
+                            id: layer.id,
+                            name: layer.name.clone(),
+                            locked: layer.locked,
+                            hidden: layer.hidden,
+                            opacity: layer.opacity,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        })?;
+
+        let mut chunks = HashMap::with_capacity(cache.chunks.len());
+        let mut reused = 0;
+        let total = self.levels.iter().map(|level| level.layers.len()).sum();
+        for (index, layer) in self
+            .levels
+            .iter()
+            .flat_map(|level| &level.layers)
+            .enumerate()
+        {
+            let hash = hash_layer(layer);
+            let chunk = match cache.chunks.remove(&layer.id) {
+                Some(chunk) if chunk.hash == hash => {
+                    reused += 1;
+                    chunk
+                }
+                _ => Chunk {
+                    hash,
+                    bytes: dungeonrs_serialization::serialize(
+                        &LayerContents {
+                            elements: &layer.elements,
+                            groups: &layer.groups,
+                            labels: &layer.labels,
+                            walls: &layer.walls,
+                            portals: &layer.portals,
+                            lights: &layer.lights,
+                            paths: &layer.paths,
+                            terrains: &layer.terrains,
+                            shapes: &layer.shapes,
+                        },
+                        format,
+                    )?,
+                },
+            };
+
+            stream.write_serialized(&chunk.bytes)?;
+            chunks.insert(layer.id, chunk);
+            progress(index + 1, total);
+        }
+        stream.finish()?;
+
+        // Layers that no longer exist are dropped from the cache.
+        cache.chunks = chunks;
+
+        Ok(reused)
+    }
+
+    /// Reads a save file previously written by [`SaveFile::write`].
+    ///
+    /// Saves written by an older version are upgraded to the current version.
+    ///
+    /// # Errors
+    /// Returns an error if the stream is invalid, was written by a newer version, can't be
+    /// migrated or ends before all layers were read.
+    pub fn read<R: Read>(reader: R) -> Result<Self, Error> {
+        let mut stream = StreamReader::new(reader)?;
+        let header = stream.read_value()?.ok_or(Error::Truncated)?;
+        let version = header
+            .get("version")
+            .and_then(Value::as_u64)
+            .and_then(|version| u32::try_from(version).ok())
+            .unwrap_or_default();
+        if version > Self::VERSION {
+            return Err(Error::UnsupportedVersion {
+                kind: Self::KIND,
+                found: version,
+                supported: Self::VERSION,
+            });
+        }
+        if version < Self::VERSION {
+            return migrations::read_outdated(stream, header, version);
+        }
+
+        // The header is small, reading it as a value first costs next to nothing.
+        let header: Header = from_value(header, stream.format())?;
+
+        let mut levels = Vec::with_capacity(header.levels.len());
+        for level in header.levels {
+            let mut layers = Vec::with_capacity(level.layers.len());
+            for layer in level.layers {
+                let contents: OwnedLayerContents = stream.read()?.ok_or(Error::Truncated)?;
+                layers.push(LayerData {
+                    id: layer.id,
+                    name: layer.name,
+                    locked: layer.locked,
+                    hidden: layer.hidden,
+                    opacity: layer.opacity,
+                    elements: contents.elements,
+                    groups: contents.groups,
+                    labels: contents.labels,
+                    walls: contents.walls,
+                    portals: contents.portals,
+                    lights: contents.lights,
+                    paths: contents.paths,
+                    terrains: contents.terrains,
+                    shapes: contents.shapes,
+                });
+            }
+            levels.push(LevelData {
+                id: level.id,
+                name: level.name,
+                lighting: level.lighting,
+                background: level.background,
+                regions: level.regions,
+                links: level.links,
+                layers,
+            });
+        }
+
+        Ok(Self {
+            id: header.id,
+            name: header.name,
+            levels,
+        })
+    }
+}
+
+/// Hashes the contents of `layer`, which is much cheaper than serializing them.
+fn hash_layer(layer: &LayerData) -> u64 {
+    let mut hasher = Xxh3::new();
+    for element in &layer.elements {
+        hash_element(&mut hasher, element);
+    }
+    for group in &layer.groups {
+        hasher.update(group.id.as_bytes());
+        hash_bytes(&mut hasher, group.name.as_bytes());
+        hash_transform(&mut hasher, &group.transform);
+        hasher.update(&group.elements.len().to_le_bytes());
+        for element in &group.elements {
+            hash_element(&mut hasher, element);
+        }
+    }
+    for label in &layer.labels {
+        hasher.update(label.id.as_bytes());
+        hash_bytes(&mut hasher, label.text.as_bytes());
+        hash_bytes(
+            &mut hasher,
+            label
+                .font
+                .as_deref()
+                .map_or(&[], |font| font.as_os_str().as_encoded_bytes()),
+        );
+        let outline = label.outline.map_or([0.0; 5], |outline| {
+            let [red, green, blue, alpha] = outline.color.to_srgba().to_f32_array();
+            [red, green, blue, alpha, outline.width]
+        });
+        for value in std::iter::once(label.size)
+            .chain(label.color.to_srgba().to_f32_array())
+            .chain(outline)
+        {
+            hasher.update(&value.to_le_bytes());
+        }
+        hash_transform(&mut hasher, &label.transform);
+    }
+    for wall in &layer.walls {
+        hasher.update(wall.id.as_bytes());
+        hasher.update(&wall.points.len().to_le_bytes());
+        for value in wall.points.iter().flatten() {
+            hasher.update(&value.to_le_bytes());
+        }
+        hash_transform(&mut hasher, &wall.transform);
+    }
+    for portal in &layer.portals {
+        hasher.update(portal.id.as_bytes());
+        for value in portal
+            .start
+            .to_array()
+            .into_iter()
+            .chain(portal.end.to_array())
+        {
+            hasher.update(&value.to_le_bytes());
+        }
+        hasher.update(&[u8::from(portal.closed)]);
+        hash_transform(&mut hasher, &portal.transform);
+    }
+    for light in &layer.lights {
+        hasher.update(light.id.as_bytes());
+        for value in [light.radius, light.intensity, light.flicker]
+            .into_iter()
+            .chain(light.color.to_srgba().to_f32_array())
+        {
+            hasher.update(&value.to_le_bytes());
+        }
+        hash_transform(&mut hasher, &light.transform);
+    }
+    for path in &layer.paths {
+        hasher.update(path.id.as_bytes());
+        hasher.update(&path.points.len().to_le_bytes());
+        for value in path.points.iter().flatten() {
+            hasher.update(&value.to_le_bytes());
+        }
+        hasher.update(&[u8::from(path.closed)]);
+        hasher.update(&path.width.to_le_bytes());
+        hash_bytes(&mut hasher, path.texture.as_os_str().as_encoded_bytes());
+        hash_transform(&mut hasher, &path.transform);
+    }
+    for terrain in &layer.terrains {
+        hasher.update(terrain.id.as_bytes());
+        for value in terrain.size {
+            hasher.update(&value.to_le_bytes());
+        }
+        for value in terrain.resolution {
+            hasher.update(&value.to_le_bytes());
+        }
+        for texture in &terrain.textures {
+            hash_bytes(&mut hasher, texture.as_os_str().as_encoded_bytes());
+        }
+        hash_bytes(&mut hasher, terrain.weights.as_flattened());
+        hash_transform(&mut hasher, &terrain.transform);
+    }
+    for shape in &layer.shapes {
+        hash_shape(&mut hasher, shape);
+    }
+
+    hasher.digest()
+}
+
+/// Feeds the id, asset, transform and animation of `element` into `hasher`.
+fn hash_element(hasher: &mut Xxh3, element: &ElementData) {
+    hasher.update(element.id.as_bytes());
+    hash_bytes(hasher, element.asset.as_os_str().as_encoded_bytes());
+    hash_transform(hasher, &element.transform);
+    hasher.update(&[u8::from(element.animation.is_some())]);
+    if let Some(animation) = &element.animation {
+        hash_animation(hasher, animation);
+    }
+}
+
+/// Feeds the spritesheet, frame rate and frames of `animation` into `hasher`.
+fn hash_animation(hasher: &mut Xxh3, animation: &AnimationData) {
+    let sheet = animation.sheet.unwrap_or_default();
+    let frozen = animation.frozen.unwrap_or_default();
+    hasher.update(&[
+        u8::from(animation.sheet.is_some()),
+        u8::from(animation.frozen.is_some()),
+    ]);
+    for value in sheet.into_iter().chain([frozen, animation.export_frame]) {
+        hasher.update(&value.to_le_bytes());
+    }
+    hasher.update(&animation.fps.to_le_bytes());
+}
+
+/// Feeds the id, outline, colours, opacity and transform of `shape` into `hasher`.
+fn hash_shape(hasher: &mut Xxh3, shape: &ShapeData) {
+    hasher.update(shape.id.as_bytes());
+    let (tag, points) = match &shape.kind {
+        ShapeKindData::Rectangle { size } => (0_u8, std::slice::from_ref(size)),
+        ShapeKindData::Ellipse { radius } => (1, std::slice::from_ref(radius)),
+        ShapeKindData::Polygon { points } => (2, points.as_slice()),
+        ShapeKindData::Line { points } => (3, points.as_slice()),
+    };
+    hasher.update(&[tag]);
+    hasher.update(&points.len().to_le_bytes());
+    for value in points.iter().flatten() {
+        hasher.update(&value.to_le_bytes());
+    }
+    hasher.update(&[
+        u8::from(shape.fill.is_some()),
+        u8::from(shape.stroke.is_some()),
+    ]);
+    let fill = shape
+        .fill
+        .map_or([0.0; 4], |fill| fill.color.to_srgba().to_f32_array());
+    let stroke = shape.stroke.map_or([0.0; 5], |stroke| {
+        let [red, green, blue, alpha] = stroke.color.to_srgba().to_f32_array();
+        [red, green, blue, alpha, stroke.width]
+    });
+    for value in fill
+        .into_iter()
+        .chain(stroke)
+        .chain(std::iter::once(shape.opacity))
+    {
+        hasher.update(&value.to_le_bytes());
+    }
+    hash_transform(hasher, &shape.transform);
+}
+
+/// Feeds `bytes` into `hasher`, prefixed with their length so adjacent values can't collide.
+fn hash_bytes(hasher: &mut Xxh3, bytes: &[u8]) {
+    hasher.update(&bytes.len().to_le_bytes());
+    hasher.update(bytes);
+}
+
+/// Feeds the components of `transform` into `hasher`.
+fn hash_transform(hasher: &mut Xxh3, transform: &Transform) {
+    for value in transform
+        .translation
+        .to_array()
+        .into_iter()
+        .chain(transform.rotation.to_array())
+        .chain(transform.scale.to_array())
+    {
+        hasher.update(&value.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Checks which layer chunks a save reuses after the project changes.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+
+    /// A project with one layer holding a single animated element.
+    fn save_file() -> SaveFile {
+        let mut layer = LayerData::new("Props");
+        layer.elements.push(ElementData {
+            id: Uuid::new_v4(),
+            asset: "props/torch.png".into(),
+            transform: Transform::IDENTITY,
+            animation: Some(AnimationData {
+                sheet: Some([4, 2]),
+                fps: 12.0,
+                frozen: None,
+                export_frame: 0,
+            }),
+        });
+        let mut level = LevelData::new("Ground floor");
+        level.layers = vec![layer];
+
+        SaveFile {
+            id: Uuid::new_v4(),
+            name: "Dungeon".into(),
+            levels: vec![level],
+        }
+    }
+
+    /// Writes `save` with `cache` and returns the number of reused chunks.
+    fn write(save: &SaveFile, cache: &mut SaveCache) -> usize {
+        save.write(Vec::new(), Format::MessagePack, cache)
+            .expect("the save is written")
+    }
+
+    /// Unchanged layers are reused, changing only an element's animation rewrites its layer.
+    #[test]
+    fn rewrites_changed_animations() {
+        let mut save = save_file();
+        let mut cache = SaveCache::default();
+        assert_eq!(write(&save, &mut cache), 0);
+        assert_eq!(write(&save, &mut cache), 1);
+
+        let edits: [fn(&mut AnimationData); 4] = [
+            |animation| animation.sheet = Some([2, 4]),
+            |animation| animation.fps = 24.0,
+            |animation| animation.frozen = Some(3),
+            |animation| animation.export_frame = 5,
+        ];
+        for edit in edits {
+            let animation = save.levels[0].layers[0].elements[0]
+                .animation
+                .as_mut()
+                .expect("the element is animated");
+            edit(animation);
+            assert_eq!(write(&save, &mut cache), 0);
+        }
+
+        save.levels[0].layers[0].elements[0].animation = None;
+        assert_eq!(write(&save, &mut cache), 0);
+    }
+
+    /// Changing only the flicker of a light rewrites its layer.
+    #[test]
+    fn rewrites_changed_flicker() {
+        let mut save = save_file();
+        save.levels[0].layers[0].lights.push(LightData {
+            id: Uuid::new_v4(),
+            radius: 256.0,
+            color: Color::WHITE,
+            intensity: 1.0,
+            flicker: 0.0,
+            transform: Transform::IDENTITY,
+        });
+        let mut cache = SaveCache::default();
+        write(&save, &mut cache);
+
+        save.levels[0].layers[0].lights[0].flicker = 0.25;
+        assert_eq!(write(&save, &mut cache), 0);
+        assert_eq!(write(&save, &mut cache), 1);
+    }
+
+    /// The written chunk holds the edited animation when read back.
+    #[test]
+    fn reads_back_edited_animation() {
+        let mut save = save_file();
+        let mut cache = SaveCache::default();
+        write(&save, &mut cache);
+
+        save.levels[0].layers[0].elements[0]
+            .animation
+            .as_mut()
+            .expect("the element is animated")
+            .fps = 6.0;
+        let mut bytes = Vec::new();
+        save.write(&mut bytes, Format::MessagePack, &mut cache)
+            .expect("the save is written");
+
+        assert_eq!(
+            SaveFile::read(bytes.as_slice()).expect("the save is read"),
+            save
+        );
+    }
+}
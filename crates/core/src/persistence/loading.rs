@@ -0,0 +1,272 @@
+//! Restores a [`SaveFile`] over multiple frames.
+//!
+//! Spawning a large project in a single frame stalls the editor for seconds. The hierarchy is
+//! instead flattened into a queue that is spawned a chunk at a time, within a per-frame budget.
+
+use crate::persistence::{
+    BackgroundData, ElementData, GroupData, LabelData, LightData, LightingData, LinkData,
+    PortalData, RegionData, SaveFile, ShapeData, TerrainData, WallData, WallPathData,
+};
+use bevy::asset::uuid::Uuid;
+use bevy::prelude::*;
+use dungeonrs_data::{Layer, Level, LevelBackground, LevelLighting, PersistentId, Project};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Limits how much of a project is spawned per frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LoadBudget {
+    /// Spawn at most this many entities per frame.
+    Entities(usize),
+    /// Spawn entities until this much time has passed in the frame.
+    Time(Duration),
+}
+
+impl Default for LoadBudget {
+    fn default() -> Self {
+        Self::Time(Duration::from_millis(8))
+    }
+}
+
+/// A single entity waiting to be spawned, in depth-first order of the hierarchy.
+enum SpawnOperation {
+    /// Spawns a level as child of the project, along with its lighting and background.
+    Level(Uuid, String, Option<LevelLighting>, Option<LevelBackground>),
+    /// Spawns a region as child of the most recently spawned level.
+    Region(RegionData),
+    /// Spawns a link to another level as child of the most recently spawned level.
+    Link(LinkData),
+    /// Spawns a layer as child of the most recently spawned level.
+    Layer(Uuid, Layer),
+    /// Spawns an element as child of the most recently spawned layer.
+    Element(ElementData),
+    /// Spawns a group and its elements as child of the most recently spawned layer.
+    Group(GroupData),
+    /// Spawns a label as child of the most recently spawned layer.
+    Label(LabelData),
+    /// Spawns a wall as child of the most recently spawned layer.
+    Wall(WallData),
+    /// Spawns a portal as child of the most recently spawned layer.
+    Portal(PortalData),
+    /// Spawns a light source as child of the most recently spawned layer.
+    Light(LightData),
+    /// Spawns a wall path as child of the most recently spawned layer.
+    WallPath(WallPathData),
+    /// Spawns a terrain as child of the most recently spawned layer.
+    Terrain(TerrainData),
+    /// Spawns a shape as child of the most recently spawned layer.
+    Shape(ShapeData),
+}
+
+/// Present while a project is being restored over multiple frames.
+///
+/// Use `resource_exists::<ProjectLoading>` as run condition for systems that should wait for
+/// the hierarchy to be complete.
+#[derive(Resource)]
+pub struct ProjectLoading {
+    /// The project entity being restored.
+    project: Entity,
+    /// Limits how much is spawned per frame.
+    budget: LoadBudget,
+    /// The entities that haven't been spawned yet.
+    queue: VecDeque<SpawnOperation>,
+    /// The most recently spawned level.
+    level: Option<Entity>,
+    /// The most recently spawned layer.
+    layer: Option<Entity>,
+    /// The number of entities spawned so far.
+    spawned: usize,
+    /// The number of entities to spawn in total.
+    total: usize,
+}
+
+impl ProjectLoading {
+    /// The project entity being restored.
+    #[must_use]
+    pub fn project(&self) -> Entity {
+        self.project
+    }
+
+    /// The number of entities spawned so far, and the total number to spawn.
+    #[must_use]
+    pub fn progress(&self) -> (usize, usize) {
+        (self.spawned, self.total)
+    }
+}
+
+/// Written every frame a chunk of the project is spawned.
+#[derive(Message, Debug, Copy, Clone)]
+pub struct LoadProgress {
+    /// The project entity being restored.
+    pub project: Entity,
+    /// The number of entities spawned so far.
+    pub spawned: usize,
+    /// The number of entities to spawn in total.
+    pub total: usize,
+}
+
+/// Written once the hierarchy of a restored project is complete.
+#[derive(Message, Debug, Copy, Clone)]
+pub struct ProjectLoaded {
+    /// The restored project entity.
+    pub project: Entity,
+}
+
+impl SaveFile {
+    /// Spawns the project entity and queues its hierarchy to be spawned over the next frames,
+    /// within `budget` per frame.
+    ///
+    /// Progress is reported through [`LoadProgress`] and [`ProjectLoaded`] is written once the
+    /// hierarchy is complete. Restoring another project while one is loading replaces the
+    /// remaining queue.
+    pub fn restore_chunked(self, commands: &mut Commands, budget: LoadBudget) -> Entity {
+        let total = self.entity_count();
+        let project = commands
+            .spawn((Project::new(self.name), PersistentId(self.id)))
+            .id();
+
+        let mut queue = VecDeque::with_capacity(total - 1);
+        for level in self.levels {
+            queue.push_back(SpawnOperation::Level(
+                level.id,
+                level.name,
+                level.lighting.as_ref().map(LightingData::lighting),
+                level.background.as_ref().map(BackgroundData::background),
+            ));
+            queue.extend(level.regions.into_iter().map(SpawnOperation::Region));
+            queue.extend(level.links.into_iter().map(SpawnOperation::Link));
+            for layer in level.layers {
+                queue.push_back(SpawnOperation::Layer(layer.id, layer.layer()));
+                queue.extend(layer.elements.into_iter().map(SpawnOperation::Element));
+                queue.extend(layer.groups.into_iter().map(SpawnOperation::Group));
+                queue.extend(layer.labels.into_iter().map(SpawnOperation::Label));
+                queue.extend(layer.walls.into_iter().map(SpawnOperation::Wall));
+                queue.extend(layer.portals.into_iter().map(SpawnOperation::Portal));
+                queue.extend(layer.lights.into_iter().map(SpawnOperation::Light));
+                queue.extend(layer.paths.into_iter().map(SpawnOperation::WallPath));
+                queue.extend(layer.terrains.into_iter().map(SpawnOperation::Terrain));
+                queue.extend(layer.shapes.into_iter().map(SpawnOperation::Shape));
+            }
+        }
+
+        commands.insert_resource(ProjectLoading {
+            project,
+            budget,
+            queue,
+            level: None,
+            layer: None,
+            spawned: 1,
+            total,
+        });
+
+        project
+    }
+}
+
+/// Spawns the next chunk of the project being restored.
+pub(crate) fn spawn_loading_chunk(
+    mut commands: Commands,
+    loading: Option<ResMut<ProjectLoading>>,
+    mut progress: MessageWriter<LoadProgress>,
+    mut loaded: MessageWriter<ProjectLoaded>,
+) {
+    let Some(mut loading) = loading else {
+        return;
+    };
+
+    let started = Instant::now();
+    let mut spawned = 0;
+    while let Some(operation) = loading.queue.pop_front() {
+        match operation {
+            SpawnOperation::Level(id, name, lighting, background) => {
+                let parent = loading.project;
+                let mut level =
+                    commands.spawn((Level::new(name), PersistentId(id), ChildOf(parent)));
+                if let Some(lighting) = lighting {
+                    level.insert(lighting);
+                }
+                if let Some(background) = background {
+                    level.insert(background);
+                }
+                loading.level = Some(level.id());
+            }
+            SpawnOperation::Region(region) => {
+                let parent = loading.level.unwrap_or(loading.project);
+                region.restore(&mut commands, parent);
+            }
+            SpawnOperation::Link(link) => {
+                let parent = loading.level.unwrap_or(loading.project);
+                link.restore(&mut commands, parent);
+            }
+            SpawnOperation::Layer(id, layer) => {
+                let parent = loading.level.unwrap_or(loading.project);
+                loading.layer = Some(
+                    commands
+                        .spawn((layer, PersistentId(id), ChildOf(parent)))
+                        .id(),
+                );
+            }
+            SpawnOperation::Element(element) => {
+                let parent = loading.layer.unwrap_or(loading.project);
+                element.restore(&mut commands, parent);
+            }
+            SpawnOperation::Group(group) => {
+                let parent = loading.layer.unwrap_or(loading.project);
+                group.restore(&mut commands, parent);
+                // Groups are small, their elements are spawned along with them.
+                spawned += group.elements.len();
+            }
+            SpawnOperation::Label(label) => {
+                let parent = loading.layer.unwrap_or(loading.project);
+                label.restore(&mut commands, parent);
+            }
+            SpawnOperation::Wall(wall) => {
+                let parent = loading.layer.unwrap_or(loading.project);
+                wall.restore(&mut commands, parent);
+            }
+            SpawnOperation::Portal(portal) => {
+                let parent = loading.layer.unwrap_or(loading.project);
+                portal.restore(&mut commands, parent);
+            }
+            SpawnOperation::Light(light) => {
+                let parent = loading.layer.unwrap_or(loading.project);
+                light.restore(&mut commands, parent);
+            }
+            SpawnOperation::WallPath(path) => {
+                let parent = loading.layer.unwrap_or(loading.project);
+                path.restore(&mut commands, parent);
+            }
+            SpawnOperation::Terrain(terrain) => {
+                let parent = loading.layer.unwrap_or(loading.project);
+                terrain.restore(&mut commands, parent);
+            }
+            SpawnOperation::Shape(shape) => {
+                let parent = loading.layer.unwrap_or(loading.project);
+                shape.restore(&mut commands, parent);
+            }
+        }
+        spawned += 1;
+
+        let exhausted = match loading.budget {
+            LoadBudget::Entities(limit) => spawned >= limit.max(1),
+            LoadBudget::Time(limit) => started.elapsed() >= limit,
+        };
+        if exhausted {
+            break;
+        }
+    }
+
+    loading.spawned += spawned;
+    progress.write(LoadProgress {
+        project: loading.project,
+        spawned: loading.spawned,
+        total: loading.total,
+    });
+
+    if loading.queue.is_empty() {
+        loaded.write(ProjectLoaded {
+            project: loading.project,
+        });
+        commands.remove_resource::<ProjectLoading>();
+    }
+}
@@ -0,0 +1,152 @@
+//! Upgrades projects saved by older versions of the editor to the current [`SaveFile`] version.
+//!
+//! Whenever the structure of a save file changes, bump [`SaveFile::VERSION`] and register a
+//! migration upgrading the previous version here. Migrations receive the whole project shaped
+//! like a serialized [`SaveFile`] (the chunks of its layers are merged back into the hierarchy),
+//! so they don't need to know how the save is split into chunks. The saves in `fixtures` are
+//! never rewritten, the tests check that every one of them still reads.
+//!
+//! [`SaveFile::VERSION`]: dungeonrs_serialization::Versioned::VERSION
+
+use crate::persistence::SaveFile;
+use dungeonrs_serialization::{Error, MigrationRegistry, StreamReader, Versioned};
+use serde_json::Value;
+use std::io::Read;
+use std::sync::LazyLock;
+
+/// The migrations of every [`SaveFile`] version that's no longer written, by the version they
+/// upgrade from.
+///
+/// No older versions exist yet.
+static MIGRATIONS: LazyLock<MigrationRegistry> = LazyLock::new(MigrationRegistry::default);
+
+/// Reads the rest of a save written by the older `version` of [`SaveFile`] from `stream`, its
+/// `header` already read, and upgrades it to the current version.
+///
+/// # Errors
+/// Returns an error if the stream ends before all layers were read, or the save can't be
+/// migrated.
+pub(crate) fn read_outdated<R: Read>(
+    mut stream: StreamReader<R>,
+    mut header: Value,
+    version: u32,
+) -> Result<SaveFile, Error> {
+    let format = stream.format();
+    if let Value::Object(header) = &mut header {
+        header.remove("version");
+    }
+
+    let layers = header
+        .get_mut("levels")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(|level| level.get_mut("layers").and_then(Value::as_array_mut))
+        .flatten();
+    for layer in layers {
+        let Value::Object(contents) = stream.read_value()?.ok_or(Error::Truncated)? else {
+            return Err(Error::Migration {
+                kind: SaveFile::KIND.to_owned(),
+                from: version,
+                reason: "the contents of a layer aren't a map".to_owned(),
+            });
+        };
+        if let Value::Object(layer) = layer {
+            layer.extend(contents);
+        }
+    }
+
+    MIGRATIONS.upgrade(version, header, format)
+}
+
+#[cfg(test)]
+mod tests {
+    //! Checks that saves written by older versions keep reading and newer ones are rejected.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+    use crate::persistence::{ElementData, LayerData, LevelData, WallData};
+    use bevy::asset::uuid::Uuid;
+    use bevy::prelude::Transform;
+    use dungeonrs_serialization::{Format, StreamWriter};
+    use serde_json::json;
+
+    /// A save written by version 1 of [`SaveFile`], holding the project built by [`version_1`].
+    const VERSION_1: &[u8] = include_bytes!("fixtures/save_v1.drs");
+
+    /// The project saved in the [`VERSION_1`] fixture.
+    fn version_1() -> SaveFile {
+        let mut layer = LayerData::new("Walls");
+        layer.id = Uuid::from_u128(3);
+        layer.elements.push(ElementData {
+            id: Uuid::from_u128(4),
+            asset: "props/torch.png".into(),
+            transform: Transform::from_xyz(64.0, 32.0, 0.0),
+            animation: None,
+        });
+        layer.walls.push(WallData {
+            id: Uuid::from_u128(5),
+            points: vec![[0.0, 0.0], [128.0, 0.0], [128.0, 64.0]],
+            transform: Transform::IDENTITY,
+        });
+        let mut level = LevelData::new("Ground floor");
+        level.id = Uuid::from_u128(2);
+        level.layers = vec![layer];
+
+        SaveFile {
+            id: Uuid::from_u128(1),
+            name: "Dungeon".into(),
+            levels: vec![level],
+        }
+    }
+
+    /// Writes a save holding only a header with the given `version`.
+    fn header_only(version: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut stream = StreamWriter::new(&mut bytes, Format::Json).expect("the stream starts");
+        stream
+            .write(&json!({ "version": version, "name": "Dungeon", "levels": [] }))
+            .expect("the header is written");
+        stream.finish().expect("the stream is finished");
+
+        bytes
+    }
+
+    /// Saves written by version 1 keep reading as the same project, through the registered
+    /// migrations once the version is bumped.
+    #[test]
+    fn reads_version_1() {
+        assert_eq!(
+            SaveFile::read(VERSION_1).expect("the save is read"),
+            version_1()
+        );
+    }
+
+    /// Versions older than the current one are upgraded through the registry, which fails for
+    /// versions it has no migration for.
+    #[test]
+    fn migrates_outdated_versions() {
+        let error = SaveFile::read(header_only(0).as_slice()).expect_err("nothing migrates v0");
+
+        assert!(
+            matches!(error, Error::MissingMigration { ref kind, from: 0 } if kind == SaveFile::KIND),
+            "unexpected error: {error}"
+        );
+    }
+
+    /// Saves written by a newer version of the editor are rejected.
+    #[test]
+    fn rejects_future_versions() {
+        let error = SaveFile::read(header_only(SaveFile::VERSION + 1).as_slice())
+            .expect_err("the version isn't supported");
+
+        assert!(
+            matches!(
+                error,
+                Error::UnsupportedVersion { found, supported, .. }
+                    if found == SaveFile::VERSION + 1 && supported == SaveFile::VERSION
+            ),
+            "unexpected error: {error}"
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Saving and restoring projects.
+
+mod autosave;
+mod chunks;
+mod loading;
+mod migrations;
+mod opening;
+mod recent;
+mod save_file;
+mod saving;
+mod templates;
+mod textures;
+
+pub use autosave::{AutosaveFailed, AutosaveSettings, UnsavedWorkFound, autosave_snapshots};
+pub use chunks::SaveCache;
+pub use loading::{LoadBudget, LoadProgress, ProjectLoaded, ProjectLoading};
+pub use opening::{OpenProject, ProjectOpenFailed};
+pub use recent::{RecentProject, RecentProjects};
+pub(crate) use save_file::capture_layer;
+pub use save_file::{
+    AnimationData, BackgroundData, ElementData, FillData, GroupData, LabelData, LayerData,
+    LevelData, LightData, LightingData, LinkData, LinkKindData, OutlineData, PortalData,
+    RegionData, SaveFile, ShapeData, ShapeKindData, StrokeData, TerrainData, WallData,
+    WallPathData,
+};
+pub use saving::{
+    ProjectSaveFailed, ProjectSaved, ProjectSaving, SaveError, SaveProgress, SaveProject,
+};
+pub use templates::{CreateProject, ProjectCreateFailed, ProjectCreated, ProjectTemplate};
+
+use crate::ConfigurationChanged;
+use autosave::{AUTOSAVE_CHECK_INTERVAL, CONFIGURATION_DEBOUNCE};
+use bevy::prelude::{App, IntoScheduleConfigs, Last, Plugin, Startup, Update};
+use dungeonrs_utils::{on_event_debounced, throttled};
+
+/// Registers the messages and systems that create, save and restore projects, remember the recent
+/// ones, and autosave the open project.
+///
+/// Requires the [`AssetsPlugin`](dungeonrs_assets::AssetsPlugin) for the textures of restored
+/// elements, and the [`UtilsPlugin`](dungeonrs_utils::UtilsPlugin) to read and write projects in
+/// the background.
+pub struct PersistencePlugin;
+
+impl Plugin for PersistencePlugin {
+    fn build(&self, app: &mut App) {
+        let configuration_settled =
+            on_event_debounced::<ConfigurationChanged>(CONFIGURATION_DEBOUNCE);
+        app.init_resource::<SaveCache>()
+            .init_resource::<AutosaveSettings>()
+            .init_resource::<RecentProjects>()
+            .add_message::<UnsavedWorkFound>()
+            .add_message::<AutosaveFailed>()
+            .add_message::<ConfigurationChanged>()
+            .add_message::<LoadProgress>()
+            .add_message::<ProjectLoaded>()
+            .add_message::<OpenProject>()
+            .add_message::<ProjectOpenFailed>()
+            .add_message::<CreateProject>()
+            .add_message::<ProjectCreated>()
+            .add_message::<ProjectCreateFailed>()
+            .add_message::<SaveProject>()
+            .add_message::<SaveProgress>()
+            .add_message::<ProjectSaved>()
+            .add_message::<ProjectSaveFailed>()
+            .add_systems(
+                Update,
+                (
+                    templates::create_projects,
+                    saving::save_projects,
+                    opening::open_projects,
+                    loading::spawn_loading_chunk,
+                    textures::attach_element_textures,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Startup,
+                (autosave::start_session, recent::load_recent_projects),
+            )
+            .add_systems(Update, recent::record_recent_projects)
+            .add_systems(
+                Update,
+                (
+                    autosave::apply_autosave_configuration.run_if(configuration_settled),
+                    autosave::autosave.run_if(throttled(AUTOSAVE_CHECK_INTERVAL)),
+                )
+                    .chain(),
+            )
+            .add_systems(Last, autosave::end_session);
+    }
+}
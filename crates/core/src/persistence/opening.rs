@@ -0,0 +1,66 @@
+//! Opens saved projects from disk.
+
+use crate::History;
+use crate::persistence::{LoadBudget, SaveFile};
+use bevy::prelude::*;
+use dungeonrs_serialization::Error;
+use dungeonrs_utils::{AsyncCommandsExt, report_progress};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+/// Requests opening the project saved at `path`.
+///
+/// The file is read in the background and then restored with [`SaveFile::restore_chunked`].
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct OpenProject {
+    /// The file the project was saved to.
+    pub path: PathBuf,
+    /// Limits how much of the project is spawned per frame.
+    pub budget: LoadBudget,
+}
+
+impl OpenProject {
+    /// Requests opening the project at `path` with the default [`LoadBudget`].
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            budget: LoadBudget::default(),
+        }
+    }
+}
+
+/// Written when a project couldn't be opened.
+#[derive(Message, Debug)]
+pub struct ProjectOpenFailed {
+    /// The file that couldn't be opened.
+    pub path: PathBuf,
+    /// The reason the project couldn't be opened.
+    pub error: Error,
+}
+
+/// Reads the project of each [`OpenProject`] request in the background and starts restoring it
+/// once read, forgetting the edits recorded in the [`History`].
+pub(crate) fn open_projects(mut commands: Commands, mut requests: MessageReader<OpenProject>) {
+    for request in requests.read() {
+        let OpenProject { path, budget } = request.clone();
+        commands.spawn_async(move |context| async move {
+            let result = File::open(&path)
+                .map_err(Error::from)
+                .and_then(|file| SaveFile::read(BufReader::new(file)));
+
+            match result {
+                Ok(save) => context.queue(move |world: &mut World| {
+                    // The edits of the previous project can't be undone in this one.
+                    if let Some(mut history) = world.get_resource_mut::<History>() {
+                        history.clear();
+                    }
+                    save.restore_chunked(&mut world.commands(), budget);
+                    world.flush();
+                }),
+                Err(error) => report_progress(&context, ProjectOpenFailed { path, error }),
+            }
+        });
+    }
+}
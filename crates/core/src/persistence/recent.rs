@@ -0,0 +1,189 @@
+//! Remembers the projects opened recently, for an "Open Recent" menu or a start screen.
+
+use crate::persistence::{OpenProject, ProjectSaved};
+use bevy::prelude::*;
+use dungeonrs_macros::bevy_system;
+use dungeonrs_serialization::{Error, Format, deserialize, serialize};
+use dungeonrs_utils::Directory;
+use serde::{Deserialize, Serialize};
+use std::fs::{create_dir_all, read, write};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A project opened recently.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentProject {
+    /// The file the project is saved to.
+    pub path: PathBuf,
+    /// When the project was last opened or saved.
+    pub opened_at: SystemTime,
+    /// An image previewing the project, if one was rendered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<PathBuf>,
+}
+
+/// The contents of the file the recent projects are saved to.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RecentFile {
+    /// The recent projects, most recent first.
+    #[serde(default)]
+    projects: Vec<RecentProject>,
+}
+
+/// The projects opened recently, most recent first.
+///
+/// Projects are recorded when they're opened through an [`OpenProject`] or saved, and the list is
+/// saved to [`RecentProjects::file`] each time it changes.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct RecentProjects {
+    /// The file the recent projects are saved to.
+    pub file: PathBuf,
+    /// The number of projects remembered, older ones are forgotten.
+    pub limit: usize,
+    /// The recent projects, most recent first.
+    projects: Vec<RecentProject>,
+}
+
+impl Default for RecentProjects {
+    fn default() -> Self {
+        Self::new(Directory::Config.join("recent.toml"))
+    }
+}
+
+impl RecentProjects {
+    /// Remembers the recent projects in `file` instead of the default location.
+    ///
+    /// Insert it before adding the [`PersistencePlugin`](crate::PersistencePlugin).
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: file.into(),
+            limit: 10,
+            projects: Vec::new(),
+        }
+    }
+
+    /// Iterates over the recent projects, most recent first.
+    pub fn iter(&self) -> impl Iterator<Item = &RecentProject> {
+        self.projects.iter()
+    }
+
+    /// Records that the project at `path` was just opened, moving it to the front.
+    ///
+    /// The thumbnail of the project is kept when it was already recorded.
+    pub fn push(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        let thumbnail = self
+            .projects
+            .iter()
+            .position(|project| project.path == path)
+            .and_then(|index| self.projects.remove(index).thumbnail);
+
+        self.projects.insert(
+            0,
+            RecentProject {
+                path,
+                opened_at: SystemTime::now(),
+                thumbnail,
+            },
+        );
+        self.projects.truncate(self.limit.max(1));
+    }
+
+    /// Sets the image previewing the project at `path`.
+    ///
+    /// Returns whether the project was recorded.
+    pub fn set_thumbnail(&mut self, path: &Path, thumbnail: impl Into<PathBuf>) -> bool {
+        let Some(project) = self
+            .projects
+            .iter_mut()
+            .find(|project| project.path == path)
+        else {
+            return false;
+        };
+
+        project.thumbnail = Some(thumbnail.into());
+        true
+    }
+
+    /// Forgets the projects whose file no longer exists, such as projects that were moved.
+    ///
+    /// Returns the number of projects forgotten.
+    pub fn prune_missing(&mut self) -> usize {
+        let count = self.projects.len();
+        self.projects.retain(|project| project.path.is_file());
+
+        count - self.projects.len()
+    }
+
+    /// Reads the recent projects from [`RecentProjects::file`], replacing the ones recorded so far.
+    ///
+    /// # Errors
+    /// Returns an error if the file exists but can't be read or isn't valid.
+    pub fn reload(&mut self) -> Result<(), Error> {
+        let bytes = match read(&self.file) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                self.projects.clear();
+                return Ok(());
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let file: RecentFile = deserialize(&bytes, Format::Toml)?;
+        self.projects = file.projects;
+        self.projects.truncate(self.limit.max(1));
+        Ok(())
+    }
+
+    /// Writes the recent projects to [`RecentProjects::file`].
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save(&self) -> Result<(), Error> {
+        let bytes = serialize(
+            &RecentFile {
+                projects: self.projects.clone(),
+            },
+            Format::Toml,
+        )?;
+        if let Some(parent) = self.file.parent() {
+            create_dir_all(parent)?;
+        }
+
+        write(&self.file, bytes)?;
+        Ok(())
+    }
+}
+
+/// Reads the recent projects saved by previous sessions.
+#[bevy_system]
+pub(crate) fn load_recent_projects(mut recent: ResMut<RecentProjects>) {
+    // Unreadable recent projects are forgotten, they're overwritten by the next project opened.
+    if recent.reload().is_err() {
+        recent.projects.clear();
+    }
+}
+
+/// Records the projects that are opened or saved.
+#[bevy_system]
+pub(crate) fn record_recent_projects(
+    mut recent: ResMut<RecentProjects>,
+    mut opened: MessageReader<OpenProject>,
+    mut saved: MessageReader<ProjectSaved>,
+) {
+    let paths: Vec<_> = opened
+        .read()
+        .map(|request| request.path.clone())
+        .chain(saved.read().map(|saved| saved.path.clone()))
+        .collect();
+    if paths.is_empty() {
+        return;
+    }
+
+    for path in paths {
+        recent.push(path);
+    }
+    // Failing to save only means the projects aren't offered again in the next session.
+    let _ = recent.save();
+}
@@ -0,0 +1,1303 @@
+//! Contains the [`SaveFile`], the serialized form of a project.
+
+use bevy::asset::uuid::Uuid;
+use bevy::prelude::*;
+use dungeonrs_data::{
+    AnimatedTexture, AnimationFrames, AnimationPlayback, Element, Group, Label, LabelOutline,
+    Layer, Level, LevelBackground, LevelLighting, LevelLink, LightSource, LinkKind, PersistentId,
+    Portal, Project, Region, Shape, ShapeKind, ShapeStroke, Terrain, Wall, WallPath,
+};
+use dungeonrs_serialization::Versioned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The serialized form of a [`Project`] and its hierarchy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveFile {
+    /// The [`PersistentId`] of the project.
+    pub id: Uuid,
+    /// The name of the project.
+    pub name: String,
+    /// The levels of the project, in order.
+    pub levels: Vec<LevelData>,
+}
+
+/// The serialized form of a [`Level`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelData {
+    /// The [`PersistentId`] of the level.
+    pub id: Uuid,
+    /// The name of the level.
+    pub name: String,
+    /// The darkness and ambient light of the level, `None` for a fully lit level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lighting: Option<LightingData>,
+    /// What the level shows below its layers, `None` for a transparent background.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<BackgroundData>,
+    /// The regions of the level that can be hidden from the players.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<RegionData>,
+    /// The stairs, ladders and teleporters leading from the level to another level.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<LinkData>,
+    /// The layers of the level, in drawing order.
+    pub layers: Vec<LayerData>,
+}
+
+/// The serialized form of a [`Region`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegionData {
+    /// The [`PersistentId`] of the region.
+    pub id: Uuid,
+    /// The corners of the region, relative to its transform.
+    pub points: Vec<[f32; 2]>,
+    /// Whether the region is hidden from the players.
+    pub hidden: bool,
+    /// The colour covering the region where it's hidden.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub cover: Color,
+    /// The position of the region within its level.
+    #[serde(with = "dungeonrs_serialization::compact::transform")]
+    pub transform: Transform,
+}
+
+/// The serialized form of a [`LevelLink`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkData {
+    /// The [`PersistentId`] of the link.
+    pub id: Uuid,
+    /// How the link takes tokens to the other level.
+    pub kind: LinkKindData,
+    /// The [`PersistentId`] of the level the link leads to.
+    pub level: Uuid,
+    /// Where the link arrives on the other level, relative to that level.
+    pub destination: [f32; 2],
+    /// The position of the link within its level.
+    #[serde(with = "dungeonrs_serialization::compact::transform")]
+    pub transform: Transform,
+}
+
+/// The serialized form of a [`LinkKind`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKindData {
+    /// A staircase.
+    Stairs,
+    /// A ladder.
+    Ladder,
+    /// A teleporter.
+    Teleporter,
+}
+
+/// The serialized form of a [`LevelLighting`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LightingData {
+    /// How dark the unlit parts of the level are, from 0 to 1.
+    pub darkness: f32,
+    /// The colour of the unlit parts of the level.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub ambient: Color,
+}
+
+/// The serialized form of a [`LevelBackground`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum BackgroundData {
+    /// A solid colour.
+    Color {
+        /// The colour of the background.
+        #[serde(with = "dungeonrs_serialization::compact::color")]
+        color: Color,
+    },
+    /// A repeated texture.
+    Texture {
+        /// The path of the repeated asset.
+        asset: PathBuf,
+        /// The width of a single repetition, in world units.
+        tile_size: f32,
+    },
+    /// A stretched image.
+    Image {
+        /// The path of the stretched asset.
+        asset: PathBuf,
+    },
+}
+
+/// The serialized form of a [`Layer`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerData {
+    /// The [`PersistentId`] of the layer.
+    pub id: Uuid,
+    /// The name of the layer.
+    pub name: String,
+    /// Whether the layer is locked.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+    /// Whether the layer is hidden.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+    /// The opacity of the layer.
+    #[serde(default = "opaque")]
+    pub opacity: f32,
+    /// The elements on the layer.
+    pub elements: Vec<ElementData>,
+    /// The groups of elements on the layer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupData>,
+    /// The labels on the layer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<LabelData>,
+    /// The walls on the layer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub walls: Vec<WallData>,
+    /// The portals on the layer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub portals: Vec<PortalData>,
+    /// The light sources on the layer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lights: Vec<LightData>,
+    /// The wall paths on the layer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<WallPathData>,
+    /// The terrains painted on the layer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub terrains: Vec<TerrainData>,
+    /// The shapes drawn on the layer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shapes: Vec<ShapeData>,
+}
+
+/// The serialized form of an [`Element`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ElementData {
+    /// The [`PersistentId`] of the element.
+    pub id: Uuid,
+    /// The path of the asset displayed by the element.
+    pub asset: PathBuf,
+    /// The position of the element within its layer.
+    #[serde(with = "dungeonrs_serialization::compact::transform")]
+    pub transform: Transform,
+    /// How the element's asset is animated, `None` for a still image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation: Option<AnimationData>,
+}
+
+/// The serialized form of a [`Group`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupData {
+    /// The [`PersistentId`] of the group.
+    pub id: Uuid,
+    /// The name of the group.
+    pub name: String,
+    /// The position of the group within its layer.
+    #[serde(with = "dungeonrs_serialization::compact::transform")]
+    pub transform: Transform,
+    /// The elements of the group, positioned relative to the group.
+    pub elements: Vec<ElementData>,
+}
+
+/// The serialized form of an [`AnimatedTexture`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationData {
+    /// The number of columns and rows of the spritesheet, `None` for a directory of frames.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sheet: Option<[u32; 2]>,
+    /// The number of frames displayed per second.
+    pub fps: f32,
+    /// The frame displayed instead of playing the frames.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frozen: Option<u32>,
+    /// The frame displayed when the map is exported.
+    #[serde(default)]
+    pub export_frame: u32,
+}
+
+/// The serialized form of a [`Label`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelData {
+    /// The [`PersistentId`] of the label.
+    pub id: Uuid,
+    /// The text of the label.
+    pub text: String,
+    /// The font size of the label.
+    pub size: f32,
+    /// The path of the font asset of the label, the default font when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font: Option<PathBuf>,
+    /// The colour of the text.
+    #[serde(default = "black", with = "dungeonrs_serialization::compact::color")]
+    pub color: Color,
+    /// The outline around the letters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outline: Option<OutlineData>,
+    /// The position of the label within its layer.
+    #[serde(with = "dungeonrs_serialization::compact::transform")]
+    pub transform: Transform,
+}
+
+/// The serialized form of a [`LabelOutline`].
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutlineData {
+    /// The colour of the outline.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub color: Color,
+    /// The width of the outline, in world units.
+    pub width: f32,
+}
+
+/// The serialized form of a [`Shape`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShapeData {
+    /// The [`PersistentId`] of the shape.
+    pub id: Uuid,
+    /// The outline of the shape, relative to its transform.
+    pub kind: ShapeKindData,
+    /// The inside of the shape, not filled when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fill: Option<FillData>,
+    /// The line along the outline, not outlined when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<StrokeData>,
+    /// The opacity of the whole shape.
+    #[serde(default = "opaque")]
+    pub opacity: f32,
+    /// The position of the shape within its layer.
+    #[serde(with = "dungeonrs_serialization::compact::transform")]
+    pub transform: Transform,
+}
+
+/// The serialized form of a [`ShapeKind`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ShapeKindData {
+    /// A rectangle centered on the shape's position.
+    Rectangle {
+        /// The width and height of the rectangle.
+        size: [f32; 2],
+    },
+    /// An ellipse centered on the shape's position.
+    Ellipse {
+        /// The horizontal and vertical radius of the ellipse.
+        radius: [f32; 2],
+    },
+    /// A closed polygon.
+    Polygon {
+        /// The corners of the polygon, in order.
+        points: Vec<[f32; 2]>,
+    },
+    /// An open line.
+    Line {
+        /// The points of the line, in order.
+        points: Vec<[f32; 2]>,
+    },
+}
+
+/// The serialized form of the fill of a [`Shape`].
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FillData {
+    /// The colour filling the shape.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub color: Color,
+}
+
+/// The serialized form of a [`ShapeStroke`].
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrokeData {
+    /// The colour of the line.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub color: Color,
+    /// The thickness of the line, in world units.
+    pub width: f32,
+}
+
+/// The serialized form of a [`Wall`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WallData {
+    /// The [`PersistentId`] of the wall.
+    pub id: Uuid,
+    /// The points of the wall, relative to its transform.
+    pub points: Vec<[f32; 2]>,
+    /// The position of the wall within its layer.
+    #[serde(with = "dungeonrs_serialization::compact::transform")]
+    pub transform: Transform,
+}
+
+/// The serialized form of a [`Portal`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortalData {
+    /// The [`PersistentId`] of the portal.
+    pub id: Uuid,
+    /// One end of the portal, relative to its transform.
+    #[serde(with = "dungeonrs_serialization::compact::vec2")]
+    pub start: Vec2,
+    /// The other end of the portal, relative to its transform.
+    #[serde(with = "dungeonrs_serialization::compact::vec2")]
+    pub end: Vec2,
+    /// Whether the portal blocks line of sight.
+    pub closed: bool,
+    /// The position of the portal within its layer.
+    #[serde(with = "dungeonrs_serialization::compact::transform")]
+    pub transform: Transform,
+}
+
+/// The serialized form of a [`LightSource`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LightData {
+    /// The [`PersistentId`] of the light source.
+    pub id: Uuid,
+    /// The distance the light reaches.
+    pub radius: f32,
+    /// The colour of the light.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub color: Color,
+    /// The brightness of the light.
+    pub intensity: f32,
+    /// How much the brightness of the light varies over time.
+    #[serde(default)]
+    pub flicker: f32,
+    /// The position of the light within its layer.
+    #[serde(with = "dungeonrs_serialization::compact::transform")]
+    pub transform: Transform,
+}
+
+/// The serialized form of a [`WallPath`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WallPathData {
+    /// The [`PersistentId`] of the wall path.
+    pub id: Uuid,
+    /// The control points of the path, relative to its transform.
+    pub points: Vec<[f32; 2]>,
+    /// Whether the last point connects back to the first.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub closed: bool,
+    /// The thickness of the wall.
+    pub width: f32,
+    /// The path of the wall texture.
+    pub texture: PathBuf,
+    /// The position of the wall path within its layer.
+    #[serde(with = "dungeonrs_serialization::compact::transform")]
+    pub transform: Transform,
+}
+
+/// The serialized form of a [`Terrain`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TerrainData {
+    /// The [`PersistentId`] of the terrain.
+    pub id: Uuid,
+    /// The area covered by the terrain.
+    pub size: [f32; 2],
+    /// The number of texels of the splat map.
+    pub resolution: [u32; 2],
+    /// The paths of the blended textures.
+    pub textures: Vec<PathBuf>,
+    /// The splat map, four weights per texel, base64 encoded.
+    #[serde(with = "splat_map")]
+    pub weights: Vec<[u8; 4]>,
+    /// The position of the terrain within its layer.
+    #[serde(with = "dungeonrs_serialization::compact::transform")]
+    pub transform: Transform,
+}
+
+impl LabelData {
+    /// Creates a black label showing `text` at font `size`, in the default font and without
+    /// outline.
+    pub fn new(text: impl Into<String>, size: f32, transform: Transform) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            text: text.into(),
+            size,
+            font: None,
+            color: Color::BLACK,
+            outline: None,
+            transform,
+        }
+    }
+
+    /// Captures the `label` entity.
+    ///
+    /// Returns `None` if `label` isn't a [`Label`].
+    #[must_use]
+    pub fn capture(world: &World, label: Entity) -> Option<Self> {
+        let data = world.get::<Label>(label)?;
+        Some(Self {
+            id: persistent_id(world, label),
+            text: data.text.clone(),
+            size: data.size,
+            font: data.font.clone(),
+            color: data.color,
+            outline: data.outline.map(|outline| OutlineData {
+                color: outline.color,
+                width: outline.width,
+            }),
+            transform: world.get::<Transform>(label).copied().unwrap_or_default(),
+        })
+    }
+
+    /// The [`Label`] component of this label.
+    #[must_use]
+    pub fn label(&self) -> Label {
+        Label {
+            text: self.text.clone(),
+            size: self.size,
+            font: self.font.clone(),
+            color: self.color,
+            outline: self.outline.map(|outline| LabelOutline {
+                color: outline.color,
+                width: outline.width,
+            }),
+        }
+    }
+
+    /// Spawns the label as the last child of `layer` and returns the label entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, layer: Entity) -> Entity {
+        commands
+            .spawn((
+                self.label(),
+                PersistentId(self.id),
+                self.transform,
+                ChildOf(layer),
+            ))
+            .id()
+    }
+}
+
+impl ShapeData {
+    /// Captures the `shape` entity.
+    ///
+    /// Returns `None` if `shape` isn't a [`Shape`].
+    #[must_use]
+    pub fn capture(world: &World, shape: Entity) -> Option<Self> {
+        let data = world.get::<Shape>(shape)?;
+        let points = |points: &[Vec2]| points.iter().map(Vec2::to_array).collect();
+        Some(Self {
+            id: persistent_id(world, shape),
+            kind: match &data.kind {
+                ShapeKind::Rectangle(size) => ShapeKindData::Rectangle {
+                    size: size.to_array(),
+                },
+                ShapeKind::Ellipse(radius) => ShapeKindData::Ellipse {
+                    radius: radius.to_array(),
+                },
+                ShapeKind::Polygon(corners) => ShapeKindData::Polygon {
+                    points: points(corners),
+                },
+                ShapeKind::Line(line) => ShapeKindData::Line {
+                    points: points(line),
+                },
+            },
+            fill: data.fill.map(|color| FillData { color }),
+            stroke: data.stroke.map(|stroke| StrokeData {
+                color: stroke.color,
+                width: stroke.width,
+            }),
+            opacity: data.opacity,
+            transform: world.get::<Transform>(shape).copied().unwrap_or_default(),
+        })
+    }
+
+    /// The [`Shape`] component of this shape.
+    #[must_use]
+    pub fn shape(&self) -> Shape {
+        let points = |points: &[[f32; 2]]| points.iter().copied().map(Vec2::from_array).collect();
+        let kind = match &self.kind {
+            ShapeKindData::Rectangle { size } => ShapeKind::Rectangle(Vec2::from_array(*size)),
+            ShapeKindData::Ellipse { radius } => ShapeKind::Ellipse(Vec2::from_array(*radius)),
+            ShapeKindData::Polygon { points: corners } => ShapeKind::Polygon(points(corners)),
+            ShapeKindData::Line { points: line } => ShapeKind::Line(points(line)),
+        };
+        Shape {
+            kind,
+            fill: self.fill.map(|fill| fill.color),
+            stroke: self.stroke.map(|stroke| ShapeStroke {
+                color: stroke.color,
+                width: stroke.width,
+            }),
+            opacity: self.opacity,
+        }
+    }
+
+    /// Spawns the shape as the last child of `layer` and returns the shape entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, layer: Entity) -> Entity {
+        commands
+            .spawn((
+                self.shape(),
+                PersistentId(self.id),
+                self.transform,
+                ChildOf(layer),
+            ))
+            .id()
+    }
+}
+
+impl WallData {
+    /// The [`Wall`] component of this wall.
+    #[must_use]
+    pub fn wall(&self) -> Wall {
+        Wall::new(
+            self.points
+                .iter()
+                .copied()
+                .map(Vec2::from_array)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Spawns the wall as the last child of `layer` and returns the wall entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, layer: Entity) -> Entity {
+        commands
+            .spawn((
+                self.wall(),
+                PersistentId(self.id),
+                self.transform,
+                ChildOf(layer),
+            ))
+            .id()
+    }
+}
+
+impl PortalData {
+    /// The [`Portal`] component of this portal.
+    #[must_use]
+    pub fn portal(&self) -> Portal {
+        Portal::new(self.start, self.end, self.closed)
+    }
+
+    /// Spawns the portal as the last child of `layer` and returns the portal entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, layer: Entity) -> Entity {
+        commands
+            .spawn((
+                self.portal(),
+                PersistentId(self.id),
+                self.transform,
+                ChildOf(layer),
+            ))
+            .id()
+    }
+}
+
+impl LightData {
+    /// The [`LightSource`] component of this light source.
+    #[must_use]
+    pub fn light(&self) -> LightSource {
+        LightSource {
+            radius: self.radius,
+            color: self.color,
+            intensity: self.intensity,
+            flicker: self.flicker,
+        }
+    }
+
+    /// Spawns the light source as the last child of `layer` and returns the light source entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, layer: Entity) -> Entity {
+        commands
+            .spawn((
+                self.light(),
+                PersistentId(self.id),
+                self.transform,
+                ChildOf(layer),
+            ))
+            .id()
+    }
+}
+
+impl WallPathData {
+    /// The [`WallPath`] component of this wall path.
+    #[must_use]
+    pub fn path(&self) -> WallPath {
+        WallPath::new(
+            self.points
+                .iter()
+                .copied()
+                .map(Vec2::from_array)
+                .collect::<Vec<_>>(),
+            self.width,
+            self.texture.clone(),
+        )
+        .with_closed(self.closed)
+    }
+
+    /// Spawns the wall path as the last child of `layer` and returns the wall path entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, layer: Entity) -> Entity {
+        commands
+            .spawn((
+                self.path(),
+                PersistentId(self.id),
+                self.transform,
+                ChildOf(layer),
+            ))
+            .id()
+    }
+}
+
+impl TerrainData {
+    /// The [`Terrain`] component of this terrain.
+    #[must_use]
+    pub fn terrain(&self) -> Terrain {
+        Terrain {
+            size: Vec2::from_array(self.size),
+            resolution: UVec2::from_array(self.resolution),
+            textures: self.textures.clone(),
+            weights: self.weights.clone(),
+        }
+    }
+
+    /// Spawns the terrain as the last child of `layer` and returns the terrain entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, layer: Entity) -> Entity {
+        commands
+            .spawn((
+                self.terrain(),
+                PersistentId(self.id),
+                self.transform,
+                ChildOf(layer),
+            ))
+            .id()
+    }
+}
+
+impl LevelData {
+    /// Creates a fully lit level named `name` with a single empty layer.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            lighting: None,
+            background: None,
+            regions: Vec::new(),
+            links: Vec::new(),
+            layers: vec![LayerData::new("Layer 1")],
+        }
+    }
+
+    /// Captures the `level` entity and its contents.
+    ///
+    /// Returns `None` if `level` isn't a [`Level`].
+    #[must_use]
+    pub fn capture(world: &World, level: Entity) -> Option<Self> {
+        Some(Self {
+            id: persistent_id(world, level),
+            name: world.get::<Level>(level)?.name.clone(),
+            lighting: world.get::<LevelLighting>(level).map(LightingData::capture),
+            background: world
+                .get::<LevelBackground>(level)
+                .map(BackgroundData::capture),
+            regions: children(world, level)
+                .filter_map(|region| RegionData::capture(world, region))
+                .collect(),
+            links: children(world, level)
+                .filter_map(|link| LinkData::capture(world, link))
+                .collect(),
+            layers: children(world, level)
+                .filter_map(|layer| capture_layer(world, layer))
+                .collect(),
+        })
+    }
+
+    /// Gives the level and every node within it a new id, so a copy of the level doesn't share
+    /// ids with the original.
+    ///
+    /// Links within the level lead to the copy, the others keep leading to the levels they led
+    /// to.
+    pub fn renew_ids(&mut self) {
+        let previous = std::mem::replace(&mut self.id, Uuid::new_v4());
+        for region in &mut self.regions {
+            region.id = Uuid::new_v4();
+        }
+        for link in &mut self.links {
+            link.id = Uuid::new_v4();
+            if link.level == previous {
+                link.level = self.id;
+            }
+        }
+        for layer in &mut self.layers {
+            layer.id = Uuid::new_v4();
+            let ids = layer
+                .elements
+                .iter_mut()
+                .map(|element| &mut element.id)
+                .chain(layer.groups.iter_mut().flat_map(|group| {
+                    std::iter::once(&mut group.id)
+                        .chain(group.elements.iter_mut().map(|element| &mut element.id))
+                }))
+                .chain(layer.labels.iter_mut().map(|label| &mut label.id))
+                .chain(layer.walls.iter_mut().map(|wall| &mut wall.id))
+                .chain(layer.portals.iter_mut().map(|portal| &mut portal.id))
+                .chain(layer.lights.iter_mut().map(|light| &mut light.id))
+                .chain(layer.paths.iter_mut().map(|path| &mut path.id))
+                .chain(layer.terrains.iter_mut().map(|terrain| &mut terrain.id))
+                .chain(layer.shapes.iter_mut().map(|shape| &mut shape.id));
+            for id in ids {
+                *id = Uuid::new_v4();
+            }
+        }
+    }
+
+    /// Spawns the level and its contents as the last child of `project` and returns the level
+    /// entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, project: Entity) -> Entity {
+        let level = commands
+            .spawn((
+                Level::new(self.name.clone()),
+                PersistentId(self.id),
+                ChildOf(project),
+            ))
+            .id();
+        if let Some(lighting) = &self.lighting {
+            commands.entity(level).insert(lighting.lighting());
+        }
+        if let Some(background) = &self.background {
+            commands.entity(level).insert(background.background());
+        }
+        for region in &self.regions {
+            region.restore(commands, level);
+        }
+        for link in &self.links {
+            link.restore(commands, level);
+        }
+        for layer in &self.layers {
+            layer.restore(commands, level);
+        }
+
+        level
+    }
+}
+
+impl LayerData {
+    /// Creates an empty, unlocked, visible and fully opaque layer named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            locked: false,
+            hidden: false,
+            opacity: 1.0,
+            elements: Vec::new(),
+            groups: Vec::new(),
+            labels: Vec::new(),
+            walls: Vec::new(),
+            portals: Vec::new(),
+            lights: Vec::new(),
+            paths: Vec::new(),
+            terrains: Vec::new(),
+            shapes: Vec::new(),
+        }
+    }
+
+    /// The number of entities on the layer.
+    #[must_use]
+    pub fn child_count(&self) -> usize {
+        self.elements.len()
+            + self
+                .groups
+                .iter()
+                .map(|group| 1 + group.elements.len())
+                .sum::<usize>()
+            + self.labels.len()
+            + self.walls.len()
+            + self.portals.len()
+            + self.lights.len()
+            + self.paths.len()
+            + self.terrains.len()
+            + self.shapes.len()
+    }
+
+    /// The [`Layer`] component of this layer.
+    #[must_use]
+    pub fn layer(&self) -> Layer {
+        Layer::new(self.name.clone())
+            .with_locked(self.locked)
+            .with_hidden(self.hidden)
+            .with_opacity(self.opacity)
+    }
+
+    /// Spawns the layer and its contents as the last child of `level` and returns the layer
+    /// entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, level: Entity) -> Entity {
+        let layer = commands
+            .spawn((self.layer(), PersistentId(self.id), ChildOf(level)))
+            .id();
+        for element in &self.elements {
+            element.restore(commands, layer);
+        }
+        for group in &self.groups {
+            group.restore(commands, layer);
+        }
+        for label in &self.labels {
+            label.restore(commands, layer);
+        }
+        for wall in &self.walls {
+            wall.restore(commands, layer);
+        }
+        for portal in &self.portals {
+            portal.restore(commands, layer);
+        }
+        for light in &self.lights {
+            light.restore(commands, layer);
+        }
+        for path in &self.paths {
+            path.restore(commands, layer);
+        }
+        for terrain in &self.terrains {
+            terrain.restore(commands, layer);
+        }
+        for shape in &self.shapes {
+            shape.restore(commands, layer);
+        }
+
+        layer
+    }
+}
+
+impl ElementData {
+    /// Captures the `element` entity.
+    ///
+    /// Returns `None` if `element` isn't an [`Element`].
+    #[must_use]
+    pub fn capture(world: &World, element: Entity) -> Option<Self> {
+        Some(Self {
+            id: persistent_id(world, element),
+            asset: world.get::<Element>(element)?.asset.clone(),
+            transform: world.get::<Transform>(element).copied().unwrap_or_default(),
+            animation: world
+                .get::<AnimatedTexture>(element)
+                .map(AnimationData::capture),
+        })
+    }
+
+    /// Spawns the element as the last child of `layer` and returns the element entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, layer: Entity) -> Entity {
+        let mut element = commands.spawn((
+            Element::new(self.asset.clone()),
+            PersistentId(self.id),
+            self.transform,
+            ChildOf(layer),
+        ));
+        if let Some(animation) = &self.animation {
+            element.insert(animation.animation());
+        }
+        element.id()
+    }
+}
+
+impl GroupData {
+    /// Captures the `group` entity and its elements.
+    ///
+    /// Returns `None` if `group` isn't a [`Group`].
+    #[must_use]
+    pub fn capture(world: &World, group: Entity) -> Option<Self> {
+        Some(Self {
+            id: persistent_id(world, group),
+            name: world.get::<Group>(group)?.name.clone(),
+            transform: world.get::<Transform>(group).copied().unwrap_or_default(),
+            elements: children(world, group)
+                .filter_map(|element| ElementData::capture(world, element))
+                .collect(),
+        })
+    }
+
+    /// Spawns the group and its elements as the last child of `layer` and returns the group
+    /// entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, layer: Entity) -> Entity {
+        let group = commands
+            .spawn((
+                Group::new(self.name.clone()),
+                PersistentId(self.id),
+                self.transform,
+                ChildOf(layer),
+            ))
+            .id();
+        for element in &self.elements {
+            element.restore(commands, group);
+        }
+
+        group
+    }
+}
+
+impl AnimationData {
+    /// Captures `animation`.
+    #[must_use]
+    pub fn capture(animation: &AnimatedTexture) -> Self {
+        Self {
+            sheet: match animation.frames {
+                AnimationFrames::Sheet(grid) => Some(grid.to_array()),
+                AnimationFrames::Folder => None,
+            },
+            fps: animation.fps,
+            frozen: match animation.playback {
+                AnimationPlayback::Frozen(frame) => Some(frame),
+                AnimationPlayback::Animated => None,
+            },
+            export_frame: animation.export_frame,
+        }
+    }
+
+    /// The [`AnimatedTexture`] component of this animation.
+    #[must_use]
+    pub fn animation(&self) -> AnimatedTexture {
+        let frames = self.sheet.map_or(AnimationFrames::Folder, |grid| {
+            AnimationFrames::Sheet(UVec2::from_array(grid))
+        });
+        let animation = AnimatedTexture::new(frames, self.fps).with_export_frame(self.export_frame);
+        match self.frozen {
+            Some(frame) => animation.with_frozen(frame),
+            None => animation,
+        }
+    }
+}
+
+impl RegionData {
+    /// Captures the region `region`.
+    ///
+    /// Returns `None` if `region` isn't a [`Region`].
+    #[must_use]
+    pub fn capture(world: &World, region: Entity) -> Option<Self> {
+        let data = world.get::<Region>(region)?;
+        Some(Self {
+            id: persistent_id(world, region),
+            points: data.points.iter().map(Vec2::to_array).collect(),
+            hidden: data.hidden,
+            cover: data.cover,
+            transform: world.get::<Transform>(region).copied().unwrap_or_default(),
+        })
+    }
+
+    /// The [`Region`] component of this region.
+    #[must_use]
+    pub fn region(&self) -> Region {
+        Region::new(
+            self.points
+                .iter()
+                .copied()
+                .map(Vec2::from_array)
+                .collect::<Vec<_>>(),
+        )
+        .with_hidden(self.hidden)
+        .with_cover(self.cover)
+    }
+
+    /// Spawns the region as the last child of `level` and returns the region entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, level: Entity) -> Entity {
+        commands
+            .spawn((
+                self.region(),
+                PersistentId(self.id),
+                self.transform,
+                ChildOf(level),
+            ))
+            .id()
+    }
+}
+
+impl LinkData {
+    /// Captures the link `link`.
+    ///
+    /// Returns `None` if `link` isn't a [`LevelLink`].
+    #[must_use]
+    pub fn capture(world: &World, link: Entity) -> Option<Self> {
+        let data = world.get::<LevelLink>(link)?;
+        Some(Self {
+            id: persistent_id(world, link),
+            kind: match data.kind {
+                LinkKind::Stairs => LinkKindData::Stairs,
+                LinkKind::Ladder => LinkKindData::Ladder,
+                LinkKind::Teleporter => LinkKindData::Teleporter,
+            },
+            level: data.level.0,
+            destination: data.destination.to_array(),
+            transform: world.get::<Transform>(link).copied().unwrap_or_default(),
+        })
+    }
+
+    /// The [`LevelLink`] component of this link.
+    #[must_use]
+    pub fn link(&self) -> LevelLink {
+        let kind = match self.kind {
+            LinkKindData::Stairs => LinkKind::Stairs,
+            LinkKindData::Ladder => LinkKind::Ladder,
+            LinkKindData::Teleporter => LinkKind::Teleporter,
+        };
+        LevelLink::new(
+            kind,
+            PersistentId(self.level),
+            Vec2::from_array(self.destination),
+        )
+    }
+
+    /// Spawns the link as the last child of `level` and returns the link entity.
+    pub(crate) fn restore(&self, commands: &mut Commands, level: Entity) -> Entity {
+        commands
+            .spawn((
+                self.link(),
+                PersistentId(self.id),
+                self.transform,
+                ChildOf(level),
+            ))
+            .id()
+    }
+}
+
+impl LightingData {
+    /// Captures `lighting`.
+    #[must_use]
+    pub fn capture(lighting: &LevelLighting) -> Self {
+        Self {
+            darkness: lighting.darkness,
+            ambient: lighting.ambient,
+        }
+    }
+
+    /// The [`LevelLighting`] component of this lighting.
+    #[must_use]
+    pub fn lighting(&self) -> LevelLighting {
+        LevelLighting::new(self.darkness).with_ambient(self.ambient)
+    }
+}
+
+impl BackgroundData {
+    /// Captures `background`.
+    #[must_use]
+    pub fn capture(background: &LevelBackground) -> Self {
+        match background {
+            LevelBackground::Color(color) => Self::Color { color: *color },
+            LevelBackground::Texture(asset, tile_size) => Self::Texture {
+                asset: asset.clone(),
+                tile_size: *tile_size,
+            },
+            LevelBackground::Image(asset) => Self::Image {
+                asset: asset.clone(),
+            },
+        }
+    }
+
+    /// The [`LevelBackground`] component of this background.
+    #[must_use]
+    pub fn background(&self) -> LevelBackground {
+        match self {
+            Self::Color { color } => LevelBackground::Color(*color),
+            Self::Texture { asset, tile_size } => {
+                LevelBackground::texture(asset.clone(), *tile_size)
+            }
+            Self::Image { asset } => LevelBackground::image(asset.clone()),
+        }
+    }
+}
+
+/// The colour of labels saved before labels could be coloured.
+///
+/// In sRGB like the colours read from files, so omitting it reads the same as writing it.
+fn black() -> Color {
+    Color::srgb(0.0, 0.0, 0.0)
+}
+
+/// The opacity of layers saved before layers could be dimmed.
+fn opaque() -> f32 {
+    1.0
+}
+
+impl Versioned for SaveFile {
+    const KIND: &'static str = "project";
+    const VERSION: u32 = 1;
+}
+
+impl SaveFile {
+    /// The file extension of saved projects.
+    pub const EXTENSION: &'static str = "drs";
+
+    /// Captures the hierarchy of the `project` entity.
+    ///
+    /// Returns `None` if `project` isn't a [`Project`]. Children that aren't of the expected
+    /// kind (such as helper entities) are skipped.
+    #[must_use]
+    pub fn capture(world: &World, project: Entity) -> Option<Self> {
+        let name = world.get::<Project>(project)?.name.clone();
+        let id = persistent_id(world, project);
+        let levels = children(world, project)
+            .filter_map(|level| LevelData::capture(world, level))
+            .collect();
+
+        Some(Self { id, name, levels })
+    }
+
+    /// The number of entities restoring this save file spawns, including the project itself.
+    #[must_use]
+    pub fn entity_count(&self) -> usize {
+        1 + self
+            .levels
+            .iter()
+            .map(|level| {
+                1 + level.regions.len()
+                    + level.links.len()
+                    + level
+                        .layers
+                        .iter()
+                        .map(|layer| 1 + layer.child_count())
+                        .sum::<usize>()
+            })
+            .sum::<usize>()
+    }
+
+    /// Spawns the whole project at once and returns the project entity.
+    ///
+    /// Large projects spawn tens of thousands of entities, use
+    /// [`SaveFile::restore_chunked`](crate::SaveFile::restore_chunked) to spread the work over
+    /// multiple frames instead.
+    pub fn restore(&self, commands: &mut Commands) -> Entity {
+        let project = commands
+            .spawn((Project::new(self.name.clone()), PersistentId(self.id)))
+            .id();
+        for level in &self.levels {
+            level.restore(commands, project);
+        }
+
+        project
+    }
+}
+
+/// Captures the contents of `layer`.
+///
+/// Returns `None` if `layer` isn't a [`Layer`].
+pub(crate) fn capture_layer(world: &World, layer: Entity) -> Option<LayerData> {
+    let transform = |entity| world.get::<Transform>(entity).copied().unwrap_or_default();
+
+    let data = world.get::<Layer>(layer)?;
+    Some(LayerData {
+        id: persistent_id(world, layer),
+        name: data.name.clone(),
+        locked: data.locked,
+        hidden: data.hidden,
+        opacity: data.opacity,
+        elements: children(world, layer)
+            .filter_map(|element| ElementData::capture(world, element))
+            .collect(),
+        groups: children(world, layer)
+            .filter_map(|group| GroupData::capture(world, group))
+            .collect(),
+        labels: children(world, layer)
+            .filter_map(|label| LabelData::capture(world, label))
+            .collect(),
+        walls: children(world, layer)
+            .filter_map(|wall| {
+                Some(WallData {
+                    id: persistent_id(world, wall),
+                    points: world
+                        .get::<Wall>(wall)?
+                        .points
+                        .iter()
+                        .map(Vec2::to_array)
+                        .collect(),
+                    transform: transform(wall),
+                })
+            })
+            .collect(),
+        portals: children(world, layer)
+            .filter_map(|portal| {
+                let data = world.get::<Portal>(portal)?;
+                Some(PortalData {
+                    id: persistent_id(world, portal),
+                    start: data.start,
+                    end: data.end,
+                    closed: data.closed,
+                    transform: transform(portal),
+                })
+            })
+            .collect(),
+        lights: children(world, layer)
+            .filter_map(|light| {
+                let data = world.get::<LightSource>(light)?;
+                Some(LightData {
+                    id: persistent_id(world, light),
+                    radius: data.radius,
+                    color: data.color,
+                    intensity: data.intensity,
+                    flicker: data.flicker,
+                    transform: transform(light),
+                })
+            })
+            .collect(),
+        paths: children(world, layer)
+            .filter_map(|path| {
+                let data = world.get::<WallPath>(path)?;
+                Some(WallPathData {
+                    id: persistent_id(world, path),
+                    points: data.points.iter().map(Vec2::to_array).collect(),
+                    closed: data.closed,
+                    width: data.width,
+                    texture: data.texture.clone(),
+                    transform: transform(path),
+                })
+            })
+            .collect(),
+        terrains: children(world, layer)
+            .filter_map(|terrain| {
+                let data = world.get::<Terrain>(terrain)?;
+                Some(TerrainData {
+                    id: persistent_id(world, terrain),
+                    size: data.size.to_array(),
+                    resolution: data.resolution.to_array(),
+                    textures: data.textures.clone(),
+                    weights: data.weights.clone(),
+                    transform: transform(terrain),
+                })
+            })
+            .collect(),
+        shapes: children(world, layer)
+            .filter_map(|shape| ShapeData::capture(world, shape))
+            .collect(),
+    })
+}
+
+/// Iterates over the children of `entity`, in order.
+fn children(world: &World, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+    world
+        .get::<Children>(entity)
+        .into_iter()
+        .flat_map(RelationshipTarget::iter)
+}
+
+/// Returns the [`PersistentId`] of `entity`, or a new one if it doesn't have any.
+fn persistent_id(world: &World, entity: Entity) -> Uuid {
+    world
+        .get::<PersistentId>(entity)
+        .map_or_else(Uuid::new_v4, |id| id.0)
+}
+
+/// Serializes the weights of a splat map as a base64 string, which is far more compact than a
+/// list of numbers in text formats.
+mod splat_map {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    /// Serializes `weights` as a base64 string.
+    ///
+    /// # Errors
+    /// Returns an error if the serializer fails.
+    pub(super) fn serialize<S: Serializer>(
+        weights: &[[u8; 4]],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(weights.as_flattened()))
+    }
+
+    /// Deserializes the weights from a base64 string.
+    ///
+    /// # Errors
+    /// Returns an error if the input isn't valid base64, or not a multiple of 4 bytes.
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<[u8; 4]>, D::Error> {
+        let bytes = STANDARD
+            .decode(String::deserialize(deserializer)?)
+            .map_err(D::Error::custom)?;
+        let (weights, remainder) = bytes.as_chunks::<4>();
+        if !remainder.is_empty() {
+            return Err(D::Error::custom(
+                "the splat map isn't a multiple of 4 bytes",
+            ));
+        }
+
+        Ok(weights.to_vec())
+    }
+}
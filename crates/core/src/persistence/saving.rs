@@ -0,0 +1,246 @@
+//! Saves projects to disk without blocking the main thread.
+//!
+//! Only capturing the hierarchy into a [`SaveFile`] happens on the main thread, serializing and
+//! writing it happen in the background. The [`SaveCache`] is moved into the background task while
+//! it runs and put back once the save completed.
+
+use crate::persistence::{SaveCache, SaveFile};
+use bevy::prelude::*;
+use dungeonrs_data::Project;
+use dungeonrs_serialization::{Error, Format};
+use dungeonrs_utils::{
+    AsyncCommandsExt, AsyncContext, PathError, Retry, info_span, is_transient, report_progress,
+    validate_output_path, with_extension,
+};
+use std::fs::{File, remove_file, rename};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Requests saving the open project to `path`.
+///
+/// A request made while a save is running starts once it completed, only the most recent one is
+/// kept.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct SaveProject {
+    /// The file to save the project to.
+    pub path: PathBuf,
+    /// The format the project is serialized in.
+    pub format: Format,
+}
+
+impl SaveProject {
+    /// Requests saving the open project to `path` in the default [`Format`].
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: Format::default(),
+        }
+    }
+}
+
+/// Written while a project is being saved, after each layer was written.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct SaveProgress {
+    /// The file the project is saved to.
+    pub path: PathBuf,
+    /// The number of layers written so far.
+    pub written: usize,
+    /// The total number of layers.
+    pub total: usize,
+}
+
+/// Written once a project was saved.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct ProjectSaved {
+    /// The file the project was saved to.
+    pub path: PathBuf,
+    /// The number of layers that didn't change since the last save, and weren't serialized again.
+    pub reused: usize,
+}
+
+/// Errors that can occur while saving a project.
+#[derive(Error, Debug)]
+pub enum SaveError {
+    /// There's no open project to save.
+    #[error("no project is open")]
+    NoProject,
+    /// The file to save the project to isn't valid.
+    #[error(transparent)]
+    Path(#[from] PathError),
+    /// The project couldn't be serialized or written.
+    #[error(transparent)]
+    Write(#[from] Error),
+}
+
+/// Written when a project couldn't be saved.
+#[derive(Message, Debug)]
+pub struct ProjectSaveFailed {
+    /// The file the project would have been saved to.
+    pub path: PathBuf,
+    /// The reason the project couldn't be saved.
+    pub error: SaveError,
+}
+
+/// Present while a project is being saved in the background.
+#[derive(Resource, Debug)]
+pub struct ProjectSaving {
+    /// The file the project is saved to.
+    path: PathBuf,
+    /// The save requested while this one runs, started once it completed.
+    queued: Option<SaveProject>,
+}
+
+impl ProjectSaving {
+    /// The file the project is saved to.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Starts saving the project for each [`SaveProject`] request.
+pub(crate) fn save_projects(mut commands: Commands, mut requests: MessageReader<SaveProject>) {
+    for request in requests.read() {
+        let request = request.clone();
+        commands.queue(move |world: &mut World| start_save(world, request));
+    }
+}
+
+/// Captures the project and writes it in the background, or queues `request` if a save is
+/// already running.
+///
+/// The file is given the [`SaveFile::EXTENSION`] when it has another one. Writes a
+/// [`ProjectSaveFailed`] when there's no project to save or the path isn't valid.
+fn start_save(world: &mut World, mut request: SaveProject) {
+    if let Some(mut saving) = world.get_resource_mut::<ProjectSaving>() {
+        saving.queued = Some(request);
+        return;
+    }
+
+    match validate_output_path(&request.path, SaveFile::EXTENSION) {
+        Ok(path) => request.path = path,
+        Err(error) => {
+            world.write_message(ProjectSaveFailed {
+                path: request.path,
+                error: error.into(),
+            });
+            return;
+        }
+    }
+
+    let mut projects = world.query_filtered::<Entity, With<Project>>();
+    let Some(save) = projects
+        .iter(world)
+        .next()
+        .and_then(|project| SaveFile::capture(world, project))
+    else {
+        world.write_message(ProjectSaveFailed {
+            path: request.path,
+            error: SaveError::NoProject,
+        });
+        return;
+    };
+
+    let mut cache = world.remove_resource::<SaveCache>().unwrap_or_default();
+    world.insert_resource(ProjectSaving {
+        path: request.path.clone(),
+        queued: None,
+    });
+    world.commands().spawn_async(move |context| async move {
+        let SaveProject { path, format } = request;
+        // Network drives and synced folders fail now and then, those failures are retried.
+        let result = Retry::default().run_if(
+            |_| write_save(&context, &save, &path, format, &mut cache),
+            |error| matches!(error, Error::Io(error) if is_transient(error)),
+        );
+        context.queue(move |world: &mut World| {
+            world.insert_resource(cache);
+            match result {
+                Ok(reused) => {
+                    world.write_message(ProjectSaved { path, reused });
+                }
+                Err(error) => {
+                    world.write_message(ProjectSaveFailed {
+                        path,
+                        error: error.into(),
+                    });
+                }
+            }
+
+            let queued = world
+                .remove_resource::<ProjectSaving>()
+                .and_then(|saving| saving.queued);
+            if let Some(queued) = queued {
+                world.write_message(queued);
+            }
+        });
+    });
+    world.flush();
+}
+
+/// Writes `save` to `path`, reporting the progress through `context`.
+///
+/// The save is written and synced to disk next to `path` first, so a crash while saving never
+/// leaves a partial file in place of the previous save. The partial file is removed when the save
+/// fails.
+///
+/// # Errors
+/// Returns an error if the save can't be serialized or written.
+fn write_save(
+    context: &AsyncContext,
+    save: &SaveFile,
+    path: &Path,
+    format: Format,
+    cache: &mut SaveCache,
+) -> Result<usize, Error> {
+    let span = info_span!("save_project", path = %path.display(), ?format, reused = _);
+    let _guard = span.enter();
+
+    let partial = with_extension(path, "partial");
+    let result = write_partial(context, save, path, &partial, format, cache)
+        .and_then(|reused| Ok(rename(&partial, path).map(|()| reused)?));
+    match &result {
+        Ok(reused) => {
+            span.record("reused", reused);
+        }
+        Err(_) => {
+            let _ = remove_file(&partial);
+        }
+    }
+
+    result
+}
+
+/// Writes `save` to `partial` and syncs it to disk, reporting the progress of saving to `path`
+/// through `context`.
+///
+/// # Errors
+/// Returns an error if the save can't be serialized, written or synced.
+fn write_partial(
+    context: &AsyncContext,
+    save: &SaveFile,
+    path: &Path,
+    partial: &Path,
+    format: Format,
+    cache: &mut SaveCache,
+) -> Result<usize, Error> {
+    let mut writer = BufWriter::new(File::create(partial)?);
+    let reused = save.write_reporting(&mut writer, format, cache, |written, total| {
+        report_progress(
+            context,
+            SaveProgress {
+                path: path.to_path_buf(),
+                written,
+                total,
+            },
+        );
+    })?;
+    writer
+        .into_inner()
+        .map_err(|error| Error::Io(error.into_error()))?
+        .sync_all()?;
+
+    Ok(reused)
+}
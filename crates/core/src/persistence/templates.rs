@@ -0,0 +1,207 @@
+//! Creates new projects from a template, instead of an empty world.
+
+use crate::History;
+use crate::persistence::{LayerData, LevelData, SaveFile};
+use bevy::asset::uuid::Uuid;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use dungeonrs_serialization::Error;
+use dungeonrs_utils::{AsyncCommandsExt, Directory, report_progress};
+use std::fs::{File, read_dir};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+/// The structure a new project starts with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum ProjectTemplate {
+    /// A single level with a single layer.
+    #[default]
+    Blank,
+    /// A dungeon level, with layers for the floor, the walls, the objects and the GM's notes.
+    GridDungeon,
+    /// A cave level, with layers for the ground, the rocks, the water and the details.
+    Cave,
+    /// A project saved as a template, whose levels and contents are copied.
+    Saved(PathBuf),
+}
+
+impl ProjectTemplate {
+    /// The built-in templates, in the order they're offered to the user.
+    pub const BUILT_IN: [Self; 3] = [Self::Blank, Self::GridDungeon, Self::Cave];
+
+    /// The directory projects are saved to to be used as templates.
+    #[must_use]
+    pub fn directory() -> PathBuf {
+        Directory::Data.join("templates")
+    }
+
+    /// Returns the templates saved in `directory`, ordered by file name.
+    ///
+    /// # Errors
+    /// Returns an error if the directory can't be read.
+    pub fn saved_in(directory: &Path) -> io::Result<Vec<Self>> {
+        let mut paths = Vec::new();
+        for entry in read_dir(directory)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == SaveFile::EXTENSION)
+            {
+                paths.push(path);
+            }
+        }
+
+        paths.sort_unstable();
+        Ok(paths.into_iter().map(Self::Saved).collect())
+    }
+
+    /// The name shown to the user, the file name of saved templates.
+    #[must_use]
+    pub fn name(&self) -> String {
+        match self {
+            Self::Blank => "Blank".into(),
+            Self::GridDungeon => "Grid dungeon".into(),
+            Self::Cave => "Cave".into(),
+            Self::Saved(path) => path
+                .file_stem()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The structure of a new project named `name` created from a built-in template.
+    ///
+    /// Returns `None` for saved templates, which have to be read first.
+    #[must_use]
+    pub fn built_in(&self, name: impl Into<String>) -> Option<SaveFile> {
+        let (level, layers): (&str, &[&str]) = match self {
+            Self::Blank => ("Level 1", &["Layer 1"]),
+            Self::GridDungeon => ("Dungeon", &["Floor", "Walls", "Objects", "GM notes"]),
+            Self::Cave => ("Cave", &["Ground", "Rocks", "Water", "Details"]),
+            Self::Saved(_) => return None,
+        };
+
+        Some(SaveFile {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            levels: vec![LevelData {
+                id: Uuid::new_v4(),
+                name: level.into(),
+                lighting: None,
+                background: None,
+                regions: Vec::new(),
+                links: Vec::new(),
+                layers: layers.iter().copied().map(LayerData::new).collect(),
+            }],
+        })
+    }
+}
+
+/// Requests creating a new project from a template.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct CreateProject {
+    /// The name of the new project.
+    pub name: String,
+    /// The structure the project starts with.
+    pub template: ProjectTemplate,
+}
+
+impl CreateProject {
+    /// Requests creating a project named `name` from `template`.
+    pub fn new(name: impl Into<String>, template: ProjectTemplate) -> Self {
+        Self {
+            name: name.into(),
+            template,
+        }
+    }
+}
+
+/// Written once a project was created from a template.
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProjectCreated {
+    /// The new project entity.
+    pub project: Entity,
+}
+
+/// Written when a project couldn't be created because its saved template couldn't be read.
+#[derive(Message, Debug)]
+pub struct ProjectCreateFailed {
+    /// The file of the template.
+    pub template: PathBuf,
+    /// The reason the template couldn't be read.
+    pub error: Error,
+}
+
+/// Creates a project for each [`CreateProject`] request, reading saved templates in the
+/// background, and forgets the edits recorded in the [`History`].
+pub(crate) fn create_projects(
+    mut commands: Commands,
+    mut requests: MessageReader<CreateProject>,
+    mut created: MessageWriter<ProjectCreated>,
+    mut history: Option<ResMut<History>>,
+) {
+    for CreateProject { name, template } in requests.read() {
+        if let Some(save) = template.built_in(name.clone()) {
+            if let Some(history) = history.as_deref_mut() {
+                history.clear();
+            }
+            let project = save.restore(&mut commands);
+            created.write(ProjectCreated { project });
+            continue;
+        }
+
+        let ProjectTemplate::Saved(path) = template.clone() else {
+            continue;
+        };
+        let name = name.clone();
+        commands.spawn_async(move |context| async move {
+            let result = File::open(&path)
+                .map_err(Error::from)
+                .and_then(|file| SaveFile::read(BufReader::new(file)));
+
+            match result {
+                Ok(save) => context.queue(move |world: &mut World| {
+                    if let Some(mut history) = world.get_resource_mut::<History>() {
+                        history.clear();
+                    }
+                    let project = instantiate(save, name).restore(&mut world.commands());
+                    world.flush();
+                    world.write_message(ProjectCreated { project });
+                }),
+                Err(error) => report_progress(
+                    &context,
+                    ProjectCreateFailed {
+                        template: path,
+                        error,
+                    },
+                ),
+            }
+        });
+    }
+}
+
+/// Turns the `template` project into a new project named `name`.
+///
+/// Every node gets a new id, so projects created from the same template don't share ids.
+fn instantiate(mut template: SaveFile, name: String) -> SaveFile {
+    template.id = Uuid::new_v4();
+    template.name = name;
+    let mut renewed = HashMap::new();
+    for level in &mut template.levels {
+        let previous = level.id;
+        level.renew_ids();
+        renewed.insert(previous, level.id);
+    }
+    // Links between the levels lead to the new ids of their levels.
+    for link in template
+        .levels
+        .iter_mut()
+        .flat_map(|level| level.links.iter_mut())
+    {
+        if let Some(level) = renewed.get(&link.level) {
+            link.level = *level;
+        }
+    }
+
+    template
+}
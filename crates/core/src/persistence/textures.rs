@@ -0,0 +1,27 @@
+//! Gives restored elements their texture.
+
+use bevy::prelude::*;
+use dungeonrs_assets::{AtlasTexture, HandleCache};
+use dungeonrs_data::{AnimatedTexture, Element};
+use dungeonrs_macros::bevy_system;
+
+/// Filters the newly spawned elements displaying a still texture.
+type StillElementAdded = (Added<Element>, Without<AnimatedTexture>);
+
+/// Points newly spawned elements at the shared texture of their asset.
+///
+/// Going through the [`HandleCache`] means every element using the same asset shares a single
+/// image handle, rather than each element loading and holding its own. Animated elements get
+/// their frames from the [`AnimatedTexturesPlugin`](crate::AnimatedTexturesPlugin) instead.
+#[bevy_system]
+pub(crate) fn attach_element_textures(
+    mut commands: Commands,
+    mut cache: ResMut<HandleCache>,
+    asset_server: Res<AssetServer>,
+    elements: Query<(Entity, &Element), StillElementAdded>,
+) {
+    for (entity, element) in &elements {
+        let texture = cache.image(&asset_server, element.asset.clone());
+        commands.entity(entity).insert(AtlasTexture(texture));
+    }
+}
@@ -0,0 +1,325 @@
+//! Places the assets dragged from the asset browser onto the map.
+//!
+//! The user interface hands the drag over as [`DragAsset`] requests in world units. While the
+//! drag goes on, a translucent ghost of the asset follows the cursor, snapped like the placed
+//! element will be, and the mouse wheel rotates it. Releasing the drag over the map places the
+//! element on the [`DropTarget`]'s layer through a [`PlaceElement`] edit, so it can be undone.
+
+use crate::{DragPhase, DropTarget, ElementData, History, PlaceElement};
+use bevy::asset::uuid::Uuid;
+use bevy::input::mouse::{AccumulatedMouseScroll, MouseScrollUnit};
+use bevy::prelude::*;
+use dungeonrs_assets::{AssetLibrary, HandleCache};
+use dungeonrs_data::{Grid, Layer, PersistentId};
+use dungeonrs_macros::bevy_system;
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The depth of the ghost, above everything drawn on the map.
+const GHOST_Z: f32 = 999.0;
+
+/// Registers the messages and systems that place dragged assets.
+///
+/// Requires the [`AssetsPlugin`](dungeonrs_assets::AssetsPlugin) for the textures of the ghosts
+/// and the [`HistoryPlugin`](crate::HistoryPlugin) to undo the placements.
+pub struct PlacementPlugin;
+
+impl Plugin for PlacementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlacementSettings>()
+            .init_resource::<AssetDrag>()
+            .init_resource::<DropTarget>()
+            .init_resource::<AccumulatedMouseScroll>()
+            .add_message::<DragAsset>()
+            .add_message::<AssetPlaced>()
+            .add_message::<PlacementFailed>()
+            .add_systems(
+                Update,
+                (rotate_dragged_asset, drag_assets, update_placement_ghost).chain(),
+            );
+    }
+}
+
+/// Configures how dragged assets are placed.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct PlacementSettings {
+    /// Whether the placed elements snap to the [`Grid`].
+    pub snap: bool,
+    /// The angle a notch of the mouse wheel rotates the dragged asset by, in radians.
+    pub rotation_step: f32,
+    /// The opacity of the ghost following the cursor, from 0 to 1.
+    pub ghost_opacity: f32,
+}
+
+impl Default for PlacementSettings {
+    fn default() -> Self {
+        Self {
+            snap: true,
+            rotation_step: PI / 12.0,
+            ghost_opacity: 0.5,
+        }
+    }
+}
+
+/// The asset being dragged onto the map, for the user interface to show where it would land.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct AssetDrag {
+    /// The dragged asset, `None` while nothing is dragged.
+    asset: Option<PathBuf>,
+    /// Where the asset would be placed, snapped, in world units.
+    position: Vec2,
+    /// The rotation of the asset, in radians.
+    rotation: f32,
+    /// The scale the asset is placed at, from the defaults of its pack.
+    scale: f32,
+}
+
+impl AssetDrag {
+    /// The dragged asset, `None` while nothing is dragged.
+    #[must_use]
+    pub fn asset(&self) -> Option<&Path> {
+        self.asset.as_deref()
+    }
+
+    /// Whether an asset is being dragged.
+    #[must_use]
+    pub fn is_dragging(&self) -> bool {
+        self.asset.is_some()
+    }
+
+    /// Where the asset would be placed, snapped to the grid when enabled, in world units.
+    #[must_use]
+    pub fn position(&self) -> Vec2 {
+        self.position
+    }
+
+    /// The rotation the asset would be placed with, in radians.
+    #[must_use]
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// The transform the asset would be placed with, in world units.
+    fn transform(&self) -> Transform {
+        Transform::from_translation(self.position.extend(0.0))
+            .with_rotation(Quat::from_rotation_z(self.rotation))
+            .with_scale(Vec3::splat(self.scale))
+    }
+}
+
+/// Drags an asset onto the map, such as from the asset browser.
+#[derive(Message, Debug, Clone, PartialEq)]
+pub struct DragAsset {
+    /// The step of the drag. Ending it places the asset, cancelling it drops the asset, such as
+    /// when it's released outside of the map.
+    pub phase: DragPhase,
+    /// The dragged asset.
+    pub asset: PathBuf,
+    /// The position of the cursor, in world units.
+    pub position: Vec2,
+}
+
+/// Written once a dragged asset was placed.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AssetPlaced {
+    /// The layer the asset was placed on.
+    pub layer: Entity,
+    /// The placed element.
+    pub element: Entity,
+}
+
+/// Errors that can occur while placing a dragged asset.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PlacementError {
+    /// No layer is targeted.
+    #[error("no layer is selected")]
+    NoLayer,
+    /// The layer doesn't exist (anymore).
+    #[error("{0} is not a layer")]
+    NotALayer(Entity),
+    /// The layer is locked.
+    #[error("the layer is locked")]
+    Locked,
+}
+
+/// Written when a dragged asset couldn't be placed.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct PlacementFailed {
+    /// The dragged asset.
+    pub asset: PathBuf,
+    /// The reason the asset couldn't be placed.
+    pub error: PlacementError,
+}
+
+/// The translucent preview of the dragged asset, following the cursor.
+#[derive(Component, Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[require(Sprite)]
+pub struct PlacementGhost;
+
+/// Rotates the dragged asset by a step for each notch of the mouse wheel.
+#[bevy_system]
+fn rotate_dragged_asset(
+    scroll: Res<AccumulatedMouseScroll>,
+    settings: Res<PlacementSettings>,
+    mut drag: ResMut<AssetDrag>,
+) {
+    let notches = match scroll.unit {
+        MouseScrollUnit::Line => scroll.delta.y,
+        MouseScrollUnit::Pixel => scroll.delta.y / MouseScrollUnit::SCROLL_UNIT_CONVERSION_FACTOR,
+    };
+    if drag.is_dragging() && notches.abs() > f32::EPSILON {
+        drag.rotation = (drag.rotation + notches * settings.rotation_step).rem_euclid(2.0 * PI);
+    }
+}
+
+/// Follows the drags of assets and places the dragged asset once released.
+#[bevy_system]
+#[allow(
+    clippy::too_many_arguments,
+    reason = "placing depends on the drag, the grid, the asset's pack and the targeted layer"
+)]
+fn drag_assets(
+    mut commands: Commands,
+    mut drags: MessageReader<DragAsset>,
+    mut failed: MessageWriter<PlacementFailed>,
+    mut drag: ResMut<AssetDrag>,
+    settings: Res<PlacementSettings>,
+    target: Res<DropTarget>,
+    grid: Option<Res<Grid>>,
+    library: Res<AssetLibrary>,
+    layers: Query<(&Layer, &PersistentId, &GlobalTransform)>,
+) {
+    let grid = grid.as_deref().copied().unwrap_or_default();
+    for request in drags.read() {
+        if request.phase == DragPhase::Start || drag.asset.as_ref() != Some(&request.asset) {
+            if matches!(request.phase, DragPhase::Cancel) {
+                continue;
+            }
+            let scale = library
+                .pack_of(&request.asset)
+                .and_then(|(pack, path)| pack.manifest.scale(path))
+                .unwrap_or(1.0);
+            *drag = AssetDrag {
+                asset: Some(request.asset.clone()),
+                scale,
+                ..AssetDrag::default()
+            };
+        }
+
+        drag.position = if settings.snap {
+            grid.snap_position(request.position)
+        } else {
+            request.position
+        };
+        match request.phase {
+            DragPhase::Start | DragPhase::Move => {}
+            DragPhase::Cancel => *drag = AssetDrag::default(),
+            DragPhase::End => {
+                let placed = std::mem::take(&mut *drag);
+                if let Err(error) = place(&mut commands, &placed, target.layer, &layers) {
+                    failed.write(PlacementFailed {
+                        asset: request.asset.clone(),
+                        error,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Records placing the asset of `drag` on `layer` in the [`History`].
+///
+/// # Errors
+/// Returns an error if `layer` isn't an unlocked layer.
+fn place(
+    commands: &mut Commands,
+    drag: &AssetDrag,
+    layer: Option<Entity>,
+    layers: &Query<(&Layer, &PersistentId, &GlobalTransform)>,
+) -> Result<(), PlacementError> {
+    let layer = layer.ok_or(PlacementError::NoLayer)?;
+    let (layer_id, layer_transform) = match layers.get(layer) {
+        Ok((Layer { locked: true, .. }, _, _)) => return Err(PlacementError::Locked),
+        Ok((_, id, transform)) => (*id, transform),
+        Err(_) => return Err(PlacementError::NotALayer(layer)),
+    };
+    let Some(asset) = drag.asset.clone() else {
+        return Ok(());
+    };
+
+    // Elements are positioned within their layer, which may not lie at the origin.
+    let transform = drag.transform();
+    let transform = Transform::from_matrix(
+        (layer_transform.affine().inverse() * transform.compute_affine()).into(),
+    );
+    let element = ElementData {
+        id: Uuid::new_v4(),
+        asset,
+        transform,
+        animation: None,
+    };
+    let element_id = PersistentId(element.id);
+
+    commands.queue(move |world: &mut World| {
+        if !History::record(world, PlaceElement::new(layer_id, element)) {
+            return;
+        }
+
+        let placed = world
+            .get::<Children>(layer)
+            .into_iter()
+            .flat_map(RelationshipTarget::iter)
+            .find(|child| world.get::<PersistentId>(*child) == Some(&element_id));
+        if let Some(element) = placed {
+            world.write_message(AssetPlaced { layer, element });
+        }
+    });
+    Ok(())
+}
+
+/// Shows the ghost of the dragged asset where it would be placed, and removes it once the drag
+/// ends.
+#[bevy_system]
+fn update_placement_ghost(
+    mut commands: Commands,
+    drag: Res<AssetDrag>,
+    settings: Res<PlacementSettings>,
+    mut cache: ResMut<HandleCache>,
+    asset_server: Res<AssetServer>,
+    mut ghosts: Query<(Entity, &mut Sprite, &mut Transform), With<PlacementGhost>>,
+    mut shown: Local<Option<PathBuf>>,
+) {
+    if !drag.is_changed() {
+        return;
+    }
+    let Some(asset) = drag.asset.clone() else {
+        for (ghost, ..) in &ghosts {
+            commands.entity(ghost).despawn();
+        }
+        *shown = None;
+        return;
+    };
+
+    let transform = drag.transform();
+    let transform = transform.with_translation(transform.translation.with_z(GHOST_Z));
+    let color = Color::WHITE.with_alpha(settings.ghost_opacity.clamp(0.0, 1.0));
+    if let Some((_, mut sprite, mut ghost_transform)) = ghosts.iter_mut().next() {
+        if shown.as_ref() != Some(&asset) {
+            sprite.image = cache.image(&asset_server, asset.clone());
+        }
+        sprite.color = color;
+        *ghost_transform = transform;
+    } else {
+        commands.spawn((
+            PlacementGhost,
+            Sprite {
+                image: cache.image(&asset_server, asset.clone()),
+                color,
+                ..default()
+            },
+            transform,
+        ));
+    }
+    *shown = Some(asset);
+}
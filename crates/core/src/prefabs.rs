@@ -0,0 +1,290 @@
+//! Saves the selected elements as a [`Prefab`] of the user library, places prefabs as groups and
+//! dissolves groups back into their elements.
+//!
+//! Prefabs are kept in the [`UserPrefabs`](dungeonrs_assets::UserPrefabs) of the
+//! [`AssetLibrary`], so they're available in every project.
+
+use crate::{Edit, EditGroup, ElementData, GroupData, History, PlaceGroup, Selection, Ungroup};
+use bevy::asset::uuid::Uuid;
+use bevy::prelude::*;
+use dungeonrs_assets::{AssetLibrary, Prefab, PrefabElement};
+use dungeonrs_data::{Group, HierarchySnapshot, Layer, PersistentId};
+use dungeonrs_macros::bevy_system;
+use thiserror::Error;
+
+/// Registers the messages and systems that save, place and dissolve prefabs.
+///
+/// Requires the [`AssetsPlugin`](dungeonrs_assets::AssetsPlugin) for the library keeping the
+/// prefabs, the [`SelectionPlugin`](crate::SelectionPlugin) for the elements to save, and the
+/// [`HistoryPlugin`](crate::HistoryPlugin) to undo the placements.
+pub struct PrefabsPlugin;
+
+impl Plugin for PrefabsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<SavePrefab>()
+            .add_message::<PrefabSaved>()
+            .add_message::<PlacePrefab>()
+            .add_message::<PrefabPlaced>()
+            .add_message::<PrefabFailed>()
+            .add_message::<UngroupSelection>()
+            .add_systems(Update, (save_prefabs, place_prefabs, ungroup_selection));
+    }
+}
+
+/// Saves the selected elements, and the elements of the selected groups, as a prefab.
+///
+/// A prefab with the same name is replaced.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct SavePrefab {
+    /// The name of the prefab.
+    pub name: String,
+}
+
+impl SavePrefab {
+    /// Saves the selection as a prefab named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+/// Written once a prefab was saved to the library.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct PrefabSaved {
+    /// The name of the prefab.
+    pub name: String,
+    /// The number of elements in the prefab.
+    pub elements: usize,
+}
+
+/// Requests placing a prefab of the library on a layer, as a single [`Group`].
+#[derive(Message, Debug, Clone, PartialEq)]
+pub struct PlacePrefab {
+    /// The name of the prefab.
+    pub name: String,
+    /// The layer the prefab is placed on.
+    pub layer: Entity,
+    /// Where the center of the prefab is placed, in world units.
+    pub position: Vec2,
+}
+
+impl PlacePrefab {
+    /// Places the prefab named `name` on `layer`, centered on `position`.
+    pub fn new(name: impl Into<String>, layer: Entity, position: Vec2) -> Self {
+        Self {
+            name: name.into(),
+            layer,
+            position,
+        }
+    }
+}
+
+/// Written once a prefab was placed.
+///
+/// The placement is recorded in the [`History`] as a single edit.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PrefabPlaced {
+    /// The layer the prefab was placed on.
+    pub layer: Entity,
+    /// The placed [`Group`].
+    pub group: Entity,
+}
+
+/// Errors that can occur while saving or placing a prefab.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PrefabError {
+    /// No elements were selected to save.
+    #[error("no elements are selected")]
+    EmptySelection,
+    /// The library has no prefab with the name.
+    #[error("there is no prefab named {0}")]
+    Unknown(String),
+    /// The layer doesn't exist (anymore).
+    #[error("{0} is not a layer")]
+    NotALayer(Entity),
+    /// The layer is locked.
+    #[error("the layer is locked")]
+    Locked,
+}
+
+/// Written when a prefab couldn't be saved or placed.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct PrefabFailed {
+    /// The name of the prefab.
+    pub name: String,
+    /// The reason the prefab couldn't be saved or placed.
+    pub error: PrefabError,
+}
+
+/// Dissolves the selected groups, putting their elements back on their layer.
+///
+/// Every dissolved group is recorded in the [`History`] as a single edit.
+#[derive(Message, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct UngroupSelection;
+
+/// Saves the selection to the library for each [`SavePrefab`] request.
+#[bevy_system]
+fn save_prefabs(
+    mut commands: Commands,
+    mut requests: MessageReader<SavePrefab>,
+    mut failed: MessageWriter<PrefabFailed>,
+    selection: Res<Selection>,
+    snapshot: Res<HierarchySnapshot>,
+) {
+    for request in requests.read() {
+        // The elements are saved in drawing order, so placing them keeps them stacked the same.
+        let selected: Vec<_> = snapshot
+            .layers()
+            .flat_map(|layer| {
+                layer
+                    .elements
+                    .iter()
+                    .copied()
+                    .chain(layer.groups.iter().map(|group| group.entity))
+            })
+            .filter(|entity| selection.contains(*entity))
+            .collect();
+        if selected.is_empty() {
+            failed.write(PrefabFailed {
+                name: request.name.clone(),
+                error: PrefabError::EmptySelection,
+            });
+            continue;
+        }
+
+        let name = request.name.clone();
+        commands.queue(move |world: &mut World| {
+            let mut elements: Vec<_> = selected
+                .into_iter()
+                .flat_map(|entity| match ElementData::capture(world, entity) {
+                    Some(element) => vec![element],
+                    // Grouped elements are saved where they are on the layer.
+                    None => GroupData::capture(world, entity)
+                        .into_iter()
+                        .flat_map(|group| {
+                            group.elements.into_iter().map(move |mut element| {
+                                element.transform =
+                                    group.transform.mul_transform(element.transform);
+                                element
+                            })
+                        })
+                        .collect(),
+                })
+                .map(|element| PrefabElement {
+                    asset: element.asset,
+                    transform: element.transform,
+                })
+                .collect();
+            #[allow(
+                clippy::cast_precision_loss,
+                reason = "prefabs are far smaller than the precision of f32"
+            )]
+            let center = elements
+                .iter()
+                .map(|element| element.transform.translation.truncate())
+                .sum::<Vec2>()
+                / elements.len().max(1) as f32;
+            for element in &mut elements {
+                element.transform.translation -= center.extend(0.0);
+            }
+
+            let count = elements.len();
+            world
+                .resource_mut::<AssetLibrary>()
+                .prefabs
+                .add(Prefab::new(name.clone(), elements));
+            world.write_message(PrefabSaved {
+                name,
+                elements: count,
+            });
+        });
+    }
+}
+
+/// Places a group of the prefab's elements for each [`PlacePrefab`] request.
+#[bevy_system]
+fn place_prefabs(
+    mut commands: Commands,
+    mut requests: MessageReader<PlacePrefab>,
+    mut failed: MessageWriter<PrefabFailed>,
+    library: Res<AssetLibrary>,
+    layers: Query<(&Layer, &PersistentId)>,
+) {
+    for request in requests.read() {
+        let layer = request.layer;
+        let result = match layers.get(layer) {
+            Ok((Layer { locked: true, .. }, _)) => Err(PrefabError::Locked),
+            Ok((_, id)) => library
+                .prefabs
+                .get(&request.name)
+                .map(|prefab| (*id, prefab))
+                .ok_or_else(|| PrefabError::Unknown(request.name.clone())),
+            Err(_) => Err(PrefabError::NotALayer(layer)),
+        };
+        let (layer_id, prefab) = match result {
+            Ok(found) => found,
+            Err(error) => {
+                failed.write(PrefabFailed {
+                    name: request.name.clone(),
+                    error,
+                });
+                continue;
+            }
+        };
+
+        // Every placement gets new ids, so the same prefab can be placed again.
+        let group = GroupData {
+            id: Uuid::new_v4(),
+            name: prefab.name.clone(),
+            transform: Transform::from_translation(request.position.extend(0.0)),
+            elements: prefab
+                .elements
+                .iter()
+                .map(|element| ElementData {
+                    id: Uuid::new_v4(),
+                    asset: element.asset.clone(),
+                    transform: element.transform,
+                    animation: None,
+                })
+                .collect(),
+        };
+        let group_id = PersistentId(group.id);
+
+        commands.queue(move |world: &mut World| {
+            if !History::record(world, PlaceGroup::new(layer_id, group)) {
+                return;
+            }
+
+            let placed = world
+                .get::<Children>(layer)
+                .into_iter()
+                .flat_map(RelationshipTarget::iter)
+                .find(|child| world.get::<PersistentId>(*child) == Some(&group_id));
+            if let Some(group) = placed {
+                world.write_message(PrefabPlaced { layer, group });
+            }
+        });
+    }
+}
+
+/// Dissolves the selected groups for each [`UngroupSelection`] request.
+#[bevy_system]
+fn ungroup_selection(
+    mut commands: Commands,
+    mut requests: MessageReader<UngroupSelection>,
+    selection: Res<Selection>,
+    groups: Query<&PersistentId, With<Group>>,
+) {
+    for _ in requests.read() {
+        let edits: Vec<Box<dyn Edit>> = groups
+            .iter_many(selection.iter())
+            .map(|id| Box::new(Ungroup::new(*id)) as Box<dyn Edit>)
+            .collect();
+        if edits.is_empty() {
+            continue;
+        }
+
+        commands.queue(move |world: &mut World| {
+            History::record(world, EditGroup::new("Ungroup", edits));
+        });
+    }
+}
@@ -0,0 +1,224 @@
+//! Streams low resolution renders of the map over WebSocket while it's being edited.
+//!
+//! Whenever the map changes (debounced), the viewport or a chosen region is exported through the
+//! regular export pipeline at a low resolution, as a background export waiting behind the user's
+//! exports, and the resulting PNG is sent to every connected client. This lets a GM keep a view
+//! of the work-in-progress map on a second device, or embed it in a virtual tabletop.
+
+mod server;
+
+use crate::export::ViewportCamera;
+use crate::{
+    ExportCapabilities, ExportError, ExportInput, ExportOutput, ExportRegistry, ExportRequest,
+    ExportSettings, Exporter,
+};
+use bevy::prelude::*;
+use dungeonrs_data::{Element, Layer, MapProjection};
+use dungeonrs_macros::bevy_system;
+use dungeonrs_utils::debounced;
+use image::ImageFormat;
+use server::Clients;
+use std::io::{self, Cursor};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+/// How long the map has to stay unchanged before a new preview is rendered.
+const PREVIEW_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Starts the preview server and streams a new preview whenever the map changes.
+///
+/// Requires the [`ExportPlugin`](crate::ExportPlugin) to render the previews and the
+/// [`UtilsPlugin`](dungeonrs_utils::UtilsPlugin) to encode them in the background.
+pub struct PreviewPlugin;
+
+impl Plugin for PreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PreviewSettings>()
+            .init_resource::<ExportRegistry>()
+            .add_message::<PreviewServerFailed>()
+            .add_systems(Startup, start_preview_server)
+            .add_systems(
+                Update,
+                request_preview.run_if(
+                    resource_exists::<PreviewServer>
+                        .and(debounced(PREVIEW_DEBOUNCE, map_changed).or(preview_wanted)),
+                ),
+            );
+    }
+}
+
+/// Configures the preview server, read when it starts.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct PreviewSettings {
+    /// The address the server listens on, only the local machine by default.
+    pub address: SocketAddr,
+    /// The world-space area to preview, the viewport of the editor's camera when `None`.
+    pub region: Option<Rect>,
+    /// The size of the longest side of the preview, in pixels.
+    pub max_size: u32,
+}
+
+impl Default for PreviewSettings {
+    fn default() -> Self {
+        Self {
+            address: SocketAddr::from((Ipv4Addr::LOCALHOST, 9740)),
+            region: None,
+            max_size: 1024,
+        }
+    }
+}
+
+/// Present while the preview server is running.
+#[derive(Resource)]
+pub struct PreviewServer {
+    /// The address the server listens on.
+    address: SocketAddr,
+    /// The connected clients.
+    clients: Clients,
+}
+
+impl PreviewServer {
+    /// The address the server listens on.
+    #[must_use]
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Returns whether any client is connected.
+    #[must_use]
+    pub fn has_clients(&self) -> bool {
+        !self.clients.is_empty()
+    }
+}
+
+/// Written when the preview server couldn't be started.
+#[derive(Message, Debug)]
+pub struct PreviewServerFailed {
+    /// The address the server tried to listen on.
+    pub address: SocketAddr,
+    /// The reason the server couldn't be started.
+    pub error: io::Error,
+}
+
+/// Sends the exported preview to the clients of the preview server instead of writing a file.
+struct PreviewExporter(Clients);
+
+impl PreviewExporter {
+    /// The id the exporter is registered as.
+    const ID: &'static str = "preview";
+}
+
+impl Exporter for PreviewExporter {
+    fn id(&self) -> &'static str {
+        Self::ID
+    }
+
+    fn name(&self) -> String {
+        "Live preview".into()
+    }
+
+    fn capabilities(&self) -> ExportCapabilities {
+        ExportCapabilities {
+            output: ExportOutput::File,
+            extensions: &[],
+        }
+    }
+
+    fn run(&self, input: ExportInput, _settings: &ExportSettings) -> Result<(), ExportError> {
+        let mut png = Cursor::new(Vec::new());
+        input.image.write_to(&mut png, ImageFormat::Png)?;
+        self.0.broadcast(png.into_inner());
+
+        Ok(())
+    }
+}
+
+/// Starts listening for preview clients.
+#[bevy_system]
+fn start_preview_server(
+    mut commands: Commands,
+    mut registry: ResMut<ExportRegistry>,
+    mut failed: MessageWriter<PreviewServerFailed>,
+    settings: Res<PreviewSettings>,
+) {
+    let address = settings.address;
+    match server::listen(address) {
+        Ok((clients, address)) => {
+            registry.register(PreviewExporter(clients.clone()));
+            commands.insert_resource(PreviewServer { address, clients });
+        }
+        Err(error) => {
+            failed.write(PreviewServerFailed { address, error });
+        }
+    }
+}
+
+/// Matches elements that were added or moved, or whose asset changed.
+type ElementChanged = (With<Element>, Or<(Changed<Element>, Changed<Transform>)>);
+
+/// Run condition that's true when an element or layer changed, or an element was removed.
+pub(crate) fn map_changed(
+    elements: Query<(), ElementChanged>,
+    layers: Query<(), Changed<Layer>>,
+    mut removed: RemovedComponents<Element>,
+) -> bool {
+    // Every removal has to be read, or it's reported again next time.
+    let removed = removed.read().count() > 0;
+
+    removed || !elements.is_empty() || !layers.is_empty()
+}
+
+/// Run condition that's true when a client connected and is waiting for a fresh preview.
+fn preview_wanted(server: Option<Res<PreviewServer>>) -> bool {
+    server.is_some_and(|server| server.clients.take_wanted())
+}
+
+/// Requests exporting the preview region for the clients of the preview server.
+#[bevy_system]
+fn request_preview(
+    mut requests: MessageWriter<ExportRequest>,
+    server: Res<PreviewServer>,
+    settings: Res<PreviewSettings>,
+    map_projection: Res<MapProjection>,
+    cameras: Query<(&Camera, &Projection, &GlobalTransform), ViewportCamera>,
+) {
+    if !server.has_clients() {
+        return;
+    }
+
+    let viewport = || {
+        cameras
+            .iter()
+            .filter(|(camera, ..)| camera.is_active)
+            .find_map(|(_, projection, transform)| match projection {
+                Projection::Orthographic(projection) => {
+                    // The viewport shows the map through the map projection, so the area of
+                    // the screen it shows is turned back into an area of the map.
+                    let center = map_projection.project(transform.translation().truncate());
+                    Some(map_projection.unproject_rect(Rect::from_corners(
+                        projection.area.min + center,
+                        projection.area.max + center,
+                    )))
+                }
+                _ => None,
+            })
+    };
+    let Some(area) = settings.region.or_else(viewport) else {
+        return;
+    };
+    let longest_side = area.size().max_element();
+    if longest_side <= 0.0 {
+        return;
+    }
+
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "preview sizes are far below the range where f32 loses precision"
+    )]
+    let pixels_per_unit = settings.max_size.max(1) as f32 / longest_side;
+    requests.write(
+        ExportRequest::new("preview.png", area, pixels_per_unit)
+            .with_exporter(PreviewExporter::ID, ExportSettings::default())
+            .in_background(),
+    );
+}
@@ -0,0 +1,411 @@
+//! A minimal WebSocket server broadcasting preview images to every connected client.
+//!
+//! Clients only receive, so after the opening handshake nothing is read from them; a client that
+//! went away is dropped the next time a preview fails to reach it. The number of connections is
+//! capped, and previews are written without holding the lock on the clients, so a slow client
+//! never holds up a connecting one. Requests that don't ask for a WebSocket upgrade are answered
+//! with a page showing the preview, so a browser or a tabletop's iframe can point straight at the
+//! server.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use sha1::{Digest, Sha1};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// The GUID the WebSocket protocol appends to the client's key when answering the handshake.
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The largest handshake request accepted, in bytes.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// How long a client may take to send its handshake or accept a preview.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The largest number of clients connected or completing their handshake at once, further
+/// connections are closed right away.
+const MAX_CONNECTIONS: usize = 16;
+
+/// The page served to clients that don't ask for a WebSocket.
+const VIEWER_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>DungeonRS preview</title>
+<style>html, body { margin: 0; height: 100%; background: #111; } img { width: 100%; height: 100%; object-fit: contain; }</style>
+</head>
+<body>
+<img id="preview" alt="">
+<script>
+const image = document.getElementById("preview");
+function connect() {
+    const socket = new WebSocket(`ws://${location.host}/`);
+    socket.binaryType = "blob";
+    socket.onmessage = (event) => {
+        const previous = image.src;
+        image.src = URL.createObjectURL(event.data);
+        if (previous) URL.revokeObjectURL(previous);
+    };
+    socket.onclose = () => setTimeout(connect, 1000);
+}
+connect();
+</script>
+</body>
+</html>
+"#;
+
+/// The state shared between the listener thread and the exporter broadcasting previews.
+#[derive(Default)]
+struct Shared {
+    /// The clients that completed the handshake.
+    clients: Vec<Arc<TcpStream>>,
+    /// The number of clients completing their handshake.
+    handshakes: usize,
+    /// The most recent preview, sent to clients as soon as they connect.
+    latest: Option<Arc<Vec<u8>>>,
+    /// Whether a client connected since the last preview was requested.
+    wanted: bool,
+}
+
+/// The clients of a running preview server.
+#[derive(Clone, Default)]
+pub(crate) struct Clients(Arc<Mutex<Shared>>);
+
+impl Clients {
+    /// Returns whether any client is connected.
+    pub fn is_empty(&self) -> bool {
+        self.lock().clients.is_empty()
+    }
+
+    /// Returns whether a client connected since the last call, and should receive a fresh
+    /// preview.
+    pub fn take_wanted(&self) -> bool {
+        std::mem::take(&mut self.lock().wanted)
+    }
+
+    /// Sends `image` to every client and keeps it for clients connecting later.
+    ///
+    /// The clients are written to without holding the lock, clients the image didn't reach are
+    /// dropped afterwards.
+    pub fn broadcast(&self, image: Vec<u8>) {
+        let frame = frame(&image);
+        let clients = {
+            let mut shared = self.lock();
+            shared.latest = Some(Arc::new(image));
+            shared.clients.clone()
+        };
+
+        let failed: Vec<_> = clients
+            .into_iter()
+            .filter(|client| (&**client).write_all(&frame).is_err())
+            .collect();
+        if !failed.is_empty() {
+            self.lock()
+                .clients
+                .retain(|client| !failed.iter().any(|failed| Arc::ptr_eq(client, failed)));
+        }
+    }
+
+    /// Reserves a connection for a client about to complete its handshake.
+    ///
+    /// Returns `false` if the server already has [`MAX_CONNECTIONS`].
+    fn reserve(&self) -> bool {
+        let mut shared = self.lock();
+        if shared.clients.len() + shared.handshakes >= MAX_CONNECTIONS {
+            return false;
+        }
+
+        shared.handshakes += 1;
+        true
+    }
+
+    /// Completes the handshake of `stream` on a connection reserved through
+    /// [`Clients::reserve`], and gives the reservation back once done.
+    fn handshake(&self, stream: TcpStream) {
+        let _ = self.accept(stream);
+        self.lock().handshakes -= 1;
+    }
+
+    /// Completes the handshake of `stream` and registers it as client.
+    ///
+    /// # Errors
+    /// Returns an error if the handshake fails.
+    fn accept(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+        let request = read_request(&mut stream)?;
+        let Some(key) = header(&request, "sec-websocket-key") else {
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{VIEWER_PAGE}",
+                VIEWER_PAGE.len()
+            );
+            return stream.write_all(response.as_bytes());
+        };
+
+        let accept = accept_key(key);
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+        );
+        stream.write_all(response.as_bytes())?;
+
+        // A preview broadcast while the latest one is written is missed, the fresh preview the
+        // client asks for makes up for it.
+        let latest = self.lock().latest.clone();
+        if let Some(latest) = latest {
+            stream.write_all(&frame(&latest))?;
+        }
+        let mut shared = self.lock();
+        shared.clients.push(Arc::new(stream));
+        shared.wanted = true;
+
+        Ok(())
+    }
+
+    /// Locks the shared state, a panic while it was locked can't leave it inconsistent.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Shared> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Starts accepting clients on `address` in a background thread.
+///
+/// Returns the clients and the address the server is bound to, which tells the port picked by
+/// the system when `address` asked for any port.
+///
+/// # Errors
+/// Returns an error if the address can't be bound.
+pub(crate) fn listen(address: SocketAddr) -> io::Result<(Clients, SocketAddr)> {
+    let listener = TcpListener::bind(address)?;
+    let address = listener.local_addr()?;
+    let clients = Clients::default();
+
+    let accepting = clients.clone();
+    thread::Builder::new()
+        .name("preview server".into())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // Connections beyond the limit are closed by dropping them.
+                if !accepting.reserve() {
+                    continue;
+                }
+
+                let clients = accepting.clone();
+                // A slow client shouldn't hold up the others, so each handshake gets a thread.
+                let spawned = thread::Builder::new()
+                    .name("preview handshake".into())
+                    .spawn(move || clients.handshake(stream));
+                if spawned.is_err() {
+                    accepting.lock().handshakes -= 1;
+                }
+            }
+        })?;
+
+    Ok((clients, address))
+}
+
+/// Reads the HTTP request head sent by a client.
+///
+/// # Errors
+/// Returns an error if the stream fails, or the request is too large.
+fn read_request(stream: &mut TcpStream) -> io::Result<String> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.ends_with(b"\r\n\r\n") {
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        request.extend_from_slice(&buffer[..read]);
+        if request.len() > MAX_REQUEST_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "request too large",
+            ));
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&request).into_owned())
+}
+
+/// Finds the value of the header `name` in `request`, ignoring its case.
+fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+    request.lines().skip(1).find_map(|line| {
+        let (header, value) = line.split_once(':')?;
+        header
+            .trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim())
+    })
+}
+
+/// The `Sec-WebSocket-Accept` value answering the handshake of a client sending `key`.
+fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(HANDSHAKE_GUID.as_bytes());
+
+    STANDARD.encode(hasher.finalize())
+}
+
+/// Wraps `payload` in a single, unmasked binary WebSocket frame.
+#[allow(
+    clippy::cast_possible_truncation,
+    reason = "the length is checked against the range of each encoding"
+)]
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    // The final fragment of a binary message.
+    frame.push(0x82);
+    match payload.len() {
+        length @ 0..126 => frame.push(length as u8),
+        length @ 126..65536 => {
+            frame.push(126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    //! Connects to a server on the loopback interface and receives the previews it broadcasts.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::time::Instant;
+
+    /// Opens a WebSocket to the server at `address` and returns the answer to the handshake.
+    fn connect(address: SocketAddr) -> (TcpStream, String) {
+        let mut stream = TcpStream::connect(address).expect("the server accepts connections");
+        stream
+            .set_read_timeout(Some(CLIENT_TIMEOUT))
+            .expect("the timeout is valid");
+        stream
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .expect("the handshake is sent");
+        // The response is read a byte at a time, so the frames following it stay in the stream.
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            let mut byte = [0];
+            stream
+                .read_exact(&mut byte)
+                .expect("the handshake is answered");
+            response.push(byte[0]);
+        }
+
+        (
+            stream,
+            String::from_utf8(response).expect("the response is text"),
+        )
+    }
+
+    /// Reads a binary frame from `stream` and returns its payload.
+    fn read_frame(stream: &mut TcpStream) -> Vec<u8> {
+        let mut head = [0; 2];
+        stream.read_exact(&mut head).expect("a frame is received");
+        assert_eq!(head[0], 0x82);
+
+        let mut payload = vec![0; usize::from(head[1])];
+        stream
+            .read_exact(&mut payload)
+            .expect("the payload is received");
+        payload
+    }
+
+    /// Waits until `count` clients completed their handshake.
+    fn wait_for_clients(clients: &Clients, count: usize) {
+        let started = Instant::now();
+        while clients.lock().clients.len() < count {
+            assert!(started.elapsed() < CLIENT_TIMEOUT, "the client connects");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// A client completes the handshake, receives the latest preview and the ones broadcast
+    /// afterwards.
+    #[test]
+    fn streams_previews() {
+        let (clients, address) =
+            listen(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).expect("the server starts");
+        clients.broadcast(b"first".to_vec());
+
+        let (mut stream, response) = connect(address);
+        assert!(response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert_eq!(
+            header(&response, "sec-websocket-accept"),
+            Some("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=")
+        );
+        assert_eq!(read_frame(&mut stream), b"first");
+
+        wait_for_clients(&clients, 1);
+        assert!(clients.take_wanted());
+        clients.broadcast(b"second".to_vec());
+        assert_eq!(read_frame(&mut stream), b"second");
+    }
+
+    /// Clients that went away are dropped by the next broadcast.
+    #[test]
+    fn drops_disconnected_clients() {
+        let (clients, address) =
+            listen(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).expect("the server starts");
+        let (stream, _) = connect(address);
+        wait_for_clients(&clients, 1);
+        drop(stream);
+
+        // The first write after the peer closed may still succeed, the next one fails.
+        let started = Instant::now();
+        while !clients.is_empty() {
+            assert!(started.elapsed() < CLIENT_TIMEOUT, "the client is dropped");
+            clients.broadcast(vec![0; 1024]);
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Connections beyond the limit are closed without a handshake.
+    #[test]
+    fn caps_connections() {
+        let (clients, address) =
+            listen(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).expect("the server starts");
+        let _connected: Vec<_> = (0..MAX_CONNECTIONS).map(|_| connect(address)).collect();
+        wait_for_clients(&clients, MAX_CONNECTIONS);
+
+        let mut rejected = TcpStream::connect(address).expect("the connection is made");
+        rejected
+            .set_read_timeout(Some(CLIENT_TIMEOUT))
+            .expect("the timeout is valid");
+        let mut buffer = [0; 1];
+        assert!(matches!(rejected.read(&mut buffer), Ok(0) | Err(_)));
+    }
+
+    /// The sample handshake of RFC 6455 is answered with the key it documents.
+    #[test]
+    fn answers_rfc_6455_handshake() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    /// Payloads are prefixed with the length encoding matching their size.
+    #[test]
+    fn encodes_frame_lengths() {
+        assert_eq!(frame(&[7; 125])[..2], [0x82, 125]);
+        assert_eq!(frame(&[7; 126])[..4], [0x82, 126, 0, 126]);
+        let large = frame(&vec![7; 65536]);
+        assert_eq!(large[..10], [0x82, 127, 0, 0, 0, 0, 0, 1, 0, 0]);
+        assert_eq!(large.len(), 65536 + 10);
+    }
+}
@@ -0,0 +1,125 @@
+//! Draws the levels through the [`MapProjection`], straight from above or in isometric view.
+//!
+//! Every 2D camera, those of the viewport and those capturing exports alike, is turned and
+//! stretched by [`MapProjection::camera_transform`], so the map is drawn projected while it's
+//! still laid out as seen from above.
+//!
+//! In isometric view, elements further back are drawn first, so the ones in front overlap them.
+//! The elements of each layer are stacked within the layer's depth by their position on screen,
+//! which only changes how they're drawn: their [`Transform`] and the saved map are left as is.
+
+use crate::layers::LAYER_DEPTH;
+use bevy::prelude::*;
+use dungeonrs_data::{Element, Group, Layer, Level, MapProjection};
+use dungeonrs_macros::bevy_system;
+
+/// Registers the systems that draw the levels through the [`MapProjection`].
+pub struct ProjectionPlugin;
+
+impl Plugin for ProjectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (
+                (project_cameras, restore_element_depths).before(TransformSystems::Propagate),
+                sort_isometric_elements.after(TransformSystems::Propagate),
+            ),
+        );
+    }
+}
+
+/// The nodes whose changes move elements on screen or across layers.
+type MovedNodes = Or<(Changed<Transform>, Changed<ChildOf>, Added<Element>)>;
+
+/// The nodes elements are placed in, whose transforms move the elements along.
+type ElementNodes = Or<(With<Element>, With<Group>, With<Layer>, With<Level>)>;
+
+/// The layers with their depth and their children.
+type LayerContents<'w, 's> =
+    Query<'w, 's, (&'static GlobalTransform, &'static Children), (With<Layer>, Without<Element>)>;
+
+/// Turns and stretches the 2D cameras to draw the map through the [`MapProjection`].
+#[bevy_system]
+fn project_cameras(
+    projection: Res<MapProjection>,
+    mut cameras: Query<(&mut Transform, Ref<Camera2d>)>,
+) {
+    let (rotation, scale) = projection.camera_transform();
+    for (mut transform, camera) in &mut cameras {
+        if !projection.is_changed() && !camera.is_added() {
+            continue;
+        }
+
+        transform.rotation = rotation;
+        transform.scale = scale;
+    }
+}
+
+/// Has the transforms of the elements propagated again when leaving the isometric view, which
+/// puts them back at their own depth.
+#[bevy_system]
+fn restore_element_depths(
+    projection: Res<MapProjection>,
+    mut elements: Query<&mut Transform, With<Element>>,
+) {
+    if !projection.is_changed() || *projection != MapProjection::TopDown {
+        return;
+    }
+
+    for mut transform in &mut elements {
+        transform.set_changed();
+    }
+}
+
+/// Stacks the elements of each layer by their position on screen in isometric view, so the
+/// elements in front are drawn over the ones behind them.
+///
+/// The elements are only stacked again when something moved, as only the moved elements had
+/// their depth propagated from their transform again.
+#[bevy_system]
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "the number of elements on a layer is far below 2^23"
+)]
+fn sort_isometric_elements(
+    projection: Res<MapProjection>,
+    moved: Query<(), (ElementNodes, MovedNodes)>,
+    mut elements: Query<&mut GlobalTransform, With<Element>>,
+    layers: LayerContents,
+    children: Query<&Children>,
+) {
+    if *projection == MapProjection::TopDown || (!projection.is_changed() && moved.is_empty()) {
+        return;
+    }
+
+    for (layer_transform, layer_children) in &layers {
+        let mut stacked: Vec<(Entity, f32)> = layer_children
+            .iter()
+            .flat_map(|child| std::iter::once(child).chain(children.iter_descendants(child)))
+            .filter_map(|entity| {
+                let global = elements.get(entity).ok()?;
+                Some((
+                    entity,
+                    projection.project(global.translation().truncate()).y,
+                ))
+            })
+            .collect();
+        // Higher up the screen is further back, so those elements are drawn first.
+        stacked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        let base = layer_transform.translation().z;
+        let step = LAYER_DEPTH / (stacked.len() + 1) as f32;
+        for (index, (entity, _)) in stacked.into_iter().enumerate() {
+            let Ok(mut global) = elements.get_mut(entity) else {
+                continue;
+            };
+
+            let depth = base + step * (index + 1) as f32;
+            let mut affine = global.affine();
+            if (affine.translation.z - depth).abs() > f32::EPSILON {
+                affine.translation.z = depth;
+                *global = GlobalTransform::from(affine);
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Covers the [`Region`]s hidden from the players.
+//!
+//! Each region gets a [`RegionCover`] sprite drawn over the layers and the lighting of its level,
+//! a mask of its outline tinted with the region's cover colour. While editing the GM sees through
+//! a translucent cover, exports for the [`ExportAudience::Players`] cover the hidden regions fully
+//! and exports for the GM don't cover them at all.
+
+use crate::export::{ExportAudience, ExportCapture};
+use bevy::asset::RenderAssetUsages;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use dungeonrs_data::Region;
+use dungeonrs_macros::bevy_system;
+
+/// The depth of the region covers within their region, above the light maps.
+const REGION_COVER_Z: f32 = 950.0;
+
+/// Covers the [`Region`]s hidden from the players, in the viewport and in player exports.
+pub struct RegionsPlugin;
+
+impl Plugin for RegionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RegionSettings>().add_systems(
+            PostUpdate,
+            (build_region_covers, show_region_covers).chain(),
+        );
+    }
+}
+
+/// Configures how the [`Region`]s are covered.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct RegionSettings {
+    /// The size of a texel of the region masks, in world units.
+    pub texel_size: f32,
+    /// The maximum width and height of a region mask, in texels. Larger regions use larger
+    /// texels.
+    pub max_resolution: u32,
+    /// The opacity of the covers of the hidden regions while editing, so the GM can see through.
+    pub preview_opacity: f32,
+}
+
+impl Default for RegionSettings {
+    fn default() -> Self {
+        Self {
+            texel_size: 4.0,
+            max_resolution: 2048,
+            preview_opacity: 0.5,
+        }
+    }
+}
+
+/// The cover of a [`Region`], drawn over it as a sprite.
+#[derive(Component, Debug, Clone)]
+#[require(Sprite)]
+pub struct RegionCover {
+    /// The region this covers, the cover is one of its children.
+    pub region: Entity,
+}
+
+/// The mask of `region` covering `area` with `resolution` texels, opaque white inside the region
+/// and transparent outside.
+///
+/// Each texel is sampled four times, so the outline of the region is smoothed.
+#[must_use]
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss,
+    reason = "texel indexes are far below 2^23 and the coverage is at most a byte"
+)]
+pub fn region_mask(region: &Region, area: Rect, resolution: UVec2) -> Image {
+    const SAMPLES: [Vec2; 4] = [
+        Vec2::new(0.25, 0.25),
+        Vec2::new(0.75, 0.25),
+        Vec2::new(0.25, 0.75),
+        Vec2::new(0.75, 0.75),
+    ];
+
+    let resolution = resolution.max(UVec2::ONE);
+    let texel = area.size() / resolution.as_vec2();
+    let mut data = Vec::with_capacity(resolution.element_product() as usize * 4);
+    // Rows go from the top of the area to its bottom.
+    for y in 0..resolution.y {
+        for x in 0..resolution.x {
+            let inside = SAMPLES
+                .iter()
+                .filter(|sample| {
+                    region.contains(Vec2::new(
+                        area.min.x + (x as f32 + sample.x) * texel.x,
+                        area.max.y - (y as f32 + sample.y) * texel.y,
+                    ))
+                })
+                .count();
+            let alpha = (inside * 255 / SAMPLES.len()) as u8;
+            data.extend([255, 255, 255, alpha]);
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: resolution.x,
+            height: resolution.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+/// Builds the masks of the regions that are new or whose outline changed, and drops the covers
+/// of entities that are no longer regions.
+#[bevy_system]
+fn build_region_covers(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    settings: Res<RegionSettings>,
+    regions: Query<(Entity, Ref<Region>)>,
+    mut removed: RemovedComponents<Region>,
+    mut covers: Query<(Entity, &RegionCover, &mut Sprite, &mut Transform)>,
+) {
+    let mut existing: HashMap<Entity, Entity> = covers
+        .iter()
+        .map(|(entity, cover, ..)| (cover.region, entity))
+        .collect();
+    for region in removed.read() {
+        if let Some(cover) = existing.remove(&region) {
+            commands.entity(cover).despawn();
+        }
+    }
+
+    for (region, data) in &regions {
+        let cover = existing
+            .get(&region)
+            .and_then(|cover| covers.get_mut(*cover).ok());
+        let Some((_, _, mut sprite, mut transform)) = cover else {
+            let (image, area) = mask(&data, &settings);
+            commands.spawn((
+                Sprite {
+                    image: images.add(image),
+                    custom_size: Some(area.size()),
+                    ..default()
+                },
+                RegionCover { region },
+                Transform::from_translation(area.center().extend(REGION_COVER_Z)),
+                Visibility::Hidden,
+                ChildOf(region),
+            ));
+            continue;
+        };
+        if !data.is_changed() && !settings.is_changed() {
+            continue;
+        }
+
+        // Covers are spawned with their own image, replacing it keeps the handle.
+        let (image, area) = mask(&data, &settings);
+        if let Some(existing) = images.get_mut(&sprite.image) {
+            *existing = image;
+        }
+        sprite.custom_size = Some(area.size());
+        transform.translation = area.center().extend(REGION_COVER_Z);
+    }
+}
+
+/// The mask of `region` at the resolution of the `settings`, along with the area it covers.
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "the resolution of the masks is far below 2^23"
+)]
+fn mask(region: &Region, settings: &RegionSettings) -> (Image, Rect) {
+    let area = region
+        .points
+        .iter()
+        .fold(Rect::EMPTY, |area, point| area.union_point(*point));
+    let area = if area.is_empty() {
+        Rect::default()
+    } else {
+        area
+    };
+    let texel_size = settings
+        .texel_size
+        .max(area.width().max(area.height()) / settings.max_resolution.max(1) as f32)
+        .max(f32::EPSILON);
+    let resolution = (area.size() / texel_size).ceil().as_uvec2();
+
+    (region_mask(region, area, resolution), area)
+}
+
+/// Shows the covers of the hidden regions: translucent while editing, opaque while a map is
+/// exported for the players and not at all while it's exported for the GM.
+#[bevy_system]
+fn show_region_covers(
+    settings: Res<RegionSettings>,
+    capture: Option<Res<ExportCapture>>,
+    regions: Query<&Region>,
+    mut covers: Query<(&RegionCover, &mut Sprite, &mut Visibility)>,
+) {
+    let opacity = match capture.map(|capture| capture.audience()) {
+        None => Some(settings.preview_opacity),
+        Some(ExportAudience::Players) => Some(1.0),
+        Some(ExportAudience::Gm) => None,
+    };
+
+    for (cover, mut sprite, mut visibility) in &mut covers {
+        let Ok(region) = regions.get(cover.region) else {
+            continue;
+        };
+        let color = opacity
+            .filter(|_| region.hidden)
+            .map(|opacity| region.cover.with_alpha(region.cover.alpha() * opacity));
+
+        // Covers are only touched when they change, so they aren't flagged as changed every frame.
+        let shown = match color {
+            Some(color) => {
+                if sprite.color != color {
+                    sprite.color = color;
+                }
+                Visibility::Inherited
+            }
+            None => Visibility::Hidden,
+        };
+        if *visibility != shown {
+            *visibility = shown;
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Tracks the selected elements, for the inspector panel and the transform gizmos.
+//!
+//! The user interface turns clicks and drags into world positions and hands them over as
+//! [`SelectAt`] and [`SelectArea`] requests. Elements on locked or hidden layers can't be
+//! selected, and elements that are removed leave the selection. Hitting an element of a
+//! [`Group`] selects the whole group instead, so it's moved and rotated as one.
+
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use dungeonrs_data::{Element, Group, HierarchySnapshot, Layer};
+use dungeonrs_macros::bevy_system;
+
+/// Registers the messages and systems that select elements.
+///
+/// Requires the [`DataPlugin`](dungeonrs_data::DataPlugin), whose [`HierarchySnapshot`] decides
+/// which of the overlapping elements is on top.
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Selection>()
+            .add_message::<SelectAt>()
+            .add_message::<SelectArea>()
+            .add_message::<ClearSelection>()
+            .add_message::<SelectionChanged>()
+            .add_systems(Update, update_selection);
+    }
+}
+
+/// Marks the selected elements, kept in sync with the [`Selection`].
+#[derive(Component, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Selected;
+
+/// The selected elements and groups, in the order they were selected.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    /// The selected element and group entities, oldest first.
+    entities: Vec<Entity>,
+}
+
+impl Selection {
+    /// Iterates over the selected elements, in the order they were selected.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter().copied()
+    }
+
+    /// Whether `entity` is selected.
+    #[must_use]
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+
+    /// The number of selected elements.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Whether nothing is selected.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// The most recently selected element, which the inspector panel shows.
+    #[must_use]
+    pub fn primary(&self) -> Option<Entity> {
+        self.entities.last().copied()
+    }
+}
+
+/// Selects the topmost element at a position, such as the one clicked.
+#[derive(Message, Debug, Copy, Clone, PartialEq)]
+pub struct SelectAt {
+    /// The position, in world units.
+    pub position: Vec2,
+    /// Whether the element is added to or removed from the selection instead of replacing it, as
+    /// when Shift is held.
+    pub extend: bool,
+}
+
+/// Selects every element whose position is within an area, such as a rubber band drawn by the
+/// user.
+#[derive(Message, Debug, Copy, Clone, PartialEq)]
+pub struct SelectArea {
+    /// The area, in world units.
+    pub area: Rect,
+    /// Whether the elements are added to the selection instead of replacing it, as when Shift is
+    /// held.
+    pub extend: bool,
+}
+
+/// Deselects every element.
+#[derive(Message, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ClearSelection;
+
+/// Written when the selection changed.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct SelectionChanged {
+    /// The selected elements, in the order they were selected.
+    pub selected: Vec<Entity>,
+}
+
+/// The elements that can be selected, with what's needed to hit-test them.
+type SelectableElements<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Sprite,
+        &'static Anchor,
+        &'static GlobalTransform,
+        &'static InheritedVisibility,
+        &'static ChildOf,
+    ),
+    With<Element>,
+>;
+
+/// Applies the selection requests, and keeps the [`Selected`] markers and the [`Selection`] in
+/// sync with the elements that still exist.
+#[bevy_system]
+#[allow(
+    clippy::too_many_arguments,
+    reason = "each kind of request is read separately"
+)]
+fn update_selection(
+    mut commands: Commands,
+    mut selection: ResMut<Selection>,
+    mut clears: MessageReader<ClearSelection>,
+    mut clicks: MessageReader<SelectAt>,
+    mut areas: MessageReader<SelectArea>,
+    mut changed: MessageWriter<SelectionChanged>,
+    snapshot: Res<HierarchySnapshot>,
+    elements: SelectableElements,
+    layers: Query<&Layer>,
+    groups: Query<&ChildOf, With<Group>>,
+    images: Res<Assets<Image>>,
+    atlases: Res<Assets<TextureAtlasLayout>>,
+) {
+    let mut selected = selection.entities.clone();
+    selected.retain(|entity| elements.contains(*entity) || groups.contains(*entity));
+
+    if clears.read().count() > 0 {
+        selected.clear();
+    }
+
+    let selectable = |entity: Entity| {
+        elements
+            .get(entity)
+            .ok()
+            .filter(|(_, _, _, visibility, parent)| {
+                // Grouped elements are on the layer of their group.
+                let layer = groups
+                    .get(parent.parent())
+                    .map_or(parent.parent(), ChildOf::parent);
+                visibility.get() && layers.get(layer).is_ok_and(|layer| !layer.locked)
+            })
+    };
+    // The entity selected by hitting `entity`, its group if it's grouped.
+    let target = |entity: Entity| {
+        elements
+            .get(entity)
+            .ok()
+            .map(|(.., parent)| parent.parent())
+            .filter(|parent| groups.contains(*parent))
+            .unwrap_or(entity)
+    };
+
+    for click in clicks.read() {
+        // Elements are drawn in hierarchy order, so the last one hit is on top.
+        let hit = snapshot
+            .elements()
+            .filter(|entity| {
+                selectable(*entity).is_some_and(|(sprite, anchor, transform, ..)| {
+                    let local = transform
+                        .affine()
+                        .inverse()
+                        .transform_point3(click.position.extend(0.0))
+                        .truncate();
+                    sprite
+                        .compute_pixel_space_point(local, *anchor, &images, &atlases)
+                        .is_ok()
+                })
+            })
+            .last()
+            .map(target);
+
+        match (hit, click.extend) {
+            (Some(hit), true) => {
+                if let Some(index) = selected.iter().position(|entity| *entity == hit) {
+                    selected.remove(index);
+                } else {
+                    selected.push(hit);
+                }
+            }
+            (hit, false) => selected = hit.into_iter().collect(),
+            (None, true) => {}
+        }
+    }
+
+    for area in areas.read() {
+        if !area.extend {
+            selected.clear();
+        }
+        for entity in snapshot.elements() {
+            let inside = selectable(entity).is_some_and(|(_, _, transform, ..)| {
+                area.area.contains(transform.translation().truncate())
+            });
+            let entity = target(entity);
+            if inside && !selected.contains(&entity) {
+                selected.push(entity);
+            }
+        }
+    }
+
+    if selected == selection.entities {
+        return;
+    }
+
+    for entity in &selection.entities {
+        if !selected.contains(entity)
+            && let Ok(mut entity) = commands.get_entity(*entity)
+        {
+            entity.remove::<Selected>();
+        }
+    }
+    for entity in &selected {
+        commands.entity(*entity).insert(Selected);
+    }
+    selection.entities.clone_from(&selected);
+    changed.write(SelectionChanged { selected });
+}
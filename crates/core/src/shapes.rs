@@ -0,0 +1,192 @@
+//! Builds the meshes of [`Shape`]s.
+//!
+//! A shape's mesh holds its fill and its stroke, coloured per vertex, so a single mesh draws the
+//! whole shape. The stroke is built like the walls, as a band along the outline.
+
+use crate::walls::{Strip, strip};
+use bevy::asset::RenderAssetUsages;
+use bevy::mesh::{Indices, PrimitiveTopology};
+use bevy::prelude::*;
+use dungeonrs_data::{Shape, ShapeKind};
+use dungeonrs_macros::bevy_system;
+use std::f32::consts::TAU;
+
+/// The number of corners of the polygon standing in for an ellipse.
+const ELLIPSE_SEGMENTS: u16 = 64;
+
+/// Builds the meshes of [`Shape`]s whenever they change.
+pub struct ShapesPlugin;
+
+impl Plugin for ShapesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, build_shape_meshes);
+    }
+}
+
+/// Builds the mesh of `shape`: its fill, then its stroke drawn over it.
+///
+/// The colours are stored per vertex, with their alpha scaled by the opacity of the shape.
+/// Polygons are filled even when they're concave, as long as their sides don't cross. Lines are
+/// never filled, and shapes with nothing to draw produce an empty mesh.
+#[must_use]
+pub fn shape_mesh(shape: &Shape) -> Mesh {
+    let (outline, closed) = outline(&shape.kind);
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut colors: Vec<[f32; 4]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    if let Some(fill) = shape.fill.filter(|_| closed) {
+        let color = fill.with_alpha(fill.alpha() * shape.opacity).to_linear();
+        indices.extend(triangulate(&outline));
+        positions.extend(outline.iter().map(|point| point.extend(0.0).to_array()));
+        uvs.extend(std::iter::repeat_n([0.0, 0.0], outline.len()));
+        colors.extend(std::iter::repeat_n(color.to_f32_array(), outline.len()));
+    }
+
+    if let Some(stroke) = shape.stroke {
+        let color = stroke
+            .color
+            .with_alpha(stroke.color.alpha() * shape.opacity)
+            .to_linear();
+        let Strip {
+            positions: strip_positions,
+            uvs: strip_uvs,
+            indices: strip_indices,
+        } = strip(&outline, closed, stroke.width);
+        let offset = u32::try_from(positions.len()).unwrap_or(u32::MAX);
+        indices.extend(strip_indices.into_iter().map(|index| index + offset));
+        colors.extend(std::iter::repeat_n(
+            color.to_f32_array(),
+            strip_positions.len(),
+        ));
+        positions.extend(strip_positions);
+        uvs.extend(strip_uvs);
+    }
+
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// The points along the outline of `kind`, and whether the outline closes back on its first
+/// point.
+fn outline(kind: &ShapeKind) -> (Vec<Vec2>, bool) {
+    match kind {
+        ShapeKind::Rectangle(size) => {
+            let half = *size / 2.0;
+            let corners = vec![
+                Vec2::new(-half.x, -half.y),
+                Vec2::new(half.x, -half.y),
+                Vec2::new(half.x, half.y),
+                Vec2::new(-half.x, half.y),
+            ];
+            (corners, true)
+        }
+        ShapeKind::Ellipse(radius) => {
+            let corners = (0..ELLIPSE_SEGMENTS)
+                .map(|segment| {
+                    let angle = TAU * f32::from(segment) / f32::from(ELLIPSE_SEGMENTS);
+                    Vec2::from_angle(angle) * *radius
+                })
+                .collect();
+            (corners, true)
+        }
+        ShapeKind::Polygon(corners) => {
+            let mut corners = corners.clone();
+            if corners.len() > 1 && corners.first() == corners.last() {
+                corners.pop();
+            }
+            (corners, true)
+        }
+        ShapeKind::Line(points) => (points.clone(), false),
+    }
+}
+
+/// The triangles filling the polygon through `corners`, as indices into `corners`.
+///
+/// The polygon is cut by ear clipping, which handles concave polygons as long as their sides
+/// don't cross. Self-intersecting polygons are still filled, though not exactly.
+fn triangulate(corners: &[Vec2]) -> Vec<u32> {
+    if corners.len() < 3 {
+        return Vec::new();
+    }
+
+    let mut remaining: Vec<usize> = (0..corners.len()).collect();
+    // Ears are found by walking the polygon counter-clockwise.
+    if signed_area(corners) < 0.0 {
+        remaining.reverse();
+    }
+
+    let index = |corner: usize| u32::try_from(corner).unwrap_or(u32::MAX);
+    let mut indices = Vec::with_capacity((corners.len() - 2) * 3);
+    while remaining.len() >= 3 {
+        let count = remaining.len();
+        let triangle = |at: usize| {
+            (
+                remaining[(at + count - 1) % count],
+                remaining[at],
+                remaining[(at + 1) % count],
+            )
+        };
+        let is_ear = |at: usize| {
+            let (a, b, c) = triangle(at);
+            let (a, b, c) = (corners[a], corners[b], corners[c]);
+            (b - a).perp_dot(c - b) > 0.0
+                && remaining.iter().all(|&other| {
+                    let point = corners[other];
+                    point == a || point == b || point == c || !contains(a, b, c, point)
+                })
+        };
+        // Degenerate polygons may have no ear left, a corner is clipped anyway so the loop ends.
+        let ear = (0..count).find(|&at| is_ear(at)).unwrap_or(0);
+        let (a, b, c) = triangle(ear);
+        indices.extend([index(a), index(b), index(c)]);
+        remaining.remove(ear);
+    }
+
+    indices
+}
+
+/// Twice the area of the polygon through `corners`, positive when they go counter-clockwise.
+fn signed_area(corners: &[Vec2]) -> f32 {
+    corners
+        .iter()
+        .zip(corners.iter().cycle().skip(1))
+        .map(|(from, to)| from.perp_dot(*to))
+        .sum()
+}
+
+/// Whether `point` is inside or on the edge of the counter-clockwise triangle `a`, `b`, `c`.
+fn contains(a: Vec2, b: Vec2, c: Vec2, point: Vec2) -> bool {
+    (b - a).perp_dot(point - a) >= 0.0
+        && (c - b).perp_dot(point - b) >= 0.0
+        && (a - c).perp_dot(point - c) >= 0.0
+}
+
+/// Rebuilds the mesh of the shapes whose outline, colours or opacity changed.
+#[bevy_system]
+fn build_shape_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    shapes: Query<(Entity, &Shape, Option<&Mesh2d>), Changed<Shape>>,
+) {
+    for (entity, shape, mesh) in &shapes {
+        let handle = match mesh.and_then(|Mesh2d(handle)| Some((handle, meshes.get_mut(handle)?))) {
+            Some((handle, mesh)) => {
+                *mesh = shape_mesh(shape);
+                handle.clone()
+            }
+            None => meshes.add(shape_mesh(shape)),
+        };
+
+        commands.entity(entity).insert(Mesh2d(handle));
+    }
+}
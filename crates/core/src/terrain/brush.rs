@@ -0,0 +1,223 @@
+//! Paints the splat map of a terrain with a soft, round brush.
+
+use crate::{DragPhase, HistoryCommandsExt, SetTerrainWeights};
+use bevy::math::Affine3A;
+use bevy::prelude::*;
+use dungeonrs_data::{Layer, PersistentId, Terrain};
+use dungeonrs_macros::bevy_system;
+
+/// The state of the terrain brush, configured by the user interface.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct TerrainBrush {
+    /// The layer whose terrain is painted.
+    pub layer: Option<Entity>,
+    /// The index of the painted texture in the terrain's [`Terrain::textures`].
+    pub texture: usize,
+    /// The radius of the brush, in world units.
+    pub radius: f32,
+    /// How much of the brush fades out towards its edge, between `0.0` for a hard edge and `1.0`
+    /// to fade out from its center.
+    pub softness: f32,
+    /// How much a single dab of the brush replaces the other textures, between `0.0` and `1.0`.
+    pub strength: f32,
+    /// The stroke in progress.
+    stroke: Option<Stroke>,
+}
+
+impl Default for TerrainBrush {
+    fn default() -> Self {
+        Self {
+            layer: None,
+            texture: 0,
+            radius: 50.0,
+            softness: 0.5,
+            strength: 0.25,
+            stroke: None,
+        }
+    }
+}
+
+impl TerrainBrush {
+    /// Whether a stroke is being painted.
+    #[must_use]
+    pub fn is_painting(&self) -> bool {
+        self.stroke.is_some()
+    }
+}
+
+/// Paints with the terrain brush, such as while the user drags the cursor over a terrain.
+#[derive(Message, Debug, Copy, Clone, PartialEq)]
+pub struct PaintTerrain {
+    /// The step of the stroke, ending it records it in the history.
+    pub phase: DragPhase,
+    /// The position of the cursor, in world units.
+    pub position: Vec2,
+}
+
+/// A stroke of the brush in progress.
+#[derive(Debug, Clone, PartialEq)]
+struct Stroke {
+    /// The painted terrain entity.
+    terrain: Entity,
+    /// Converts world units to the space of the terrain.
+    to_terrain: Affine3A,
+    /// The splat map before the stroke.
+    before: Vec<[u8; 4]>,
+    /// The position of the last dab, in the space of the terrain.
+    last: Vec2,
+}
+
+/// Applies the [`PaintTerrain`] requests, recording each stroke as a single edit once it ends.
+#[bevy_system]
+pub(crate) fn paint_terrain(
+    mut commands: Commands,
+    mut brush: ResMut<TerrainBrush>,
+    mut paints: MessageReader<PaintTerrain>,
+    layers: Query<(&Layer, &Children)>,
+    mut terrains: Query<(&mut Terrain, &PersistentId, &GlobalTransform)>,
+) {
+    for paint in paints.read() {
+        match paint.phase {
+            DragPhase::Start => {
+                restore(&mut brush, &mut terrains);
+                let Some((layer, children)) = brush.layer.and_then(|layer| layers.get(layer).ok())
+                else {
+                    continue;
+                };
+                if layer.locked {
+                    continue;
+                }
+
+                // The topmost terrain under the cursor is painted.
+                brush.stroke = children.iter().rev().find_map(|entity| {
+                    let (terrain, _, global) = terrains.get(entity).ok()?;
+                    let to_terrain = global.affine().inverse();
+                    let position = to_terrain
+                        .transform_point3(paint.position.extend(0.0))
+                        .truncate();
+                    terrain.texel_at(position)?;
+                    Some(Stroke {
+                        terrain: entity,
+                        to_terrain,
+                        before: terrain.weights.clone(),
+                        last: position,
+                    })
+                });
+                let brush = &mut *brush;
+                if let Some(stroke) = &brush.stroke
+                    && let Ok((mut terrain, ..)) = terrains.get_mut(stroke.terrain)
+                {
+                    dab(&mut terrain, stroke.last, brush);
+                }
+            }
+            DragPhase::Move => {
+                let brush = &mut *brush;
+                let Some(stroke) = &brush.stroke else {
+                    continue;
+                };
+                let Ok((mut terrain, ..)) = terrains.get_mut(stroke.terrain) else {
+                    continue;
+                };
+
+                // Dabs are spaced along the cursor's path, so fast strokes don't leave gaps.
+                let position = stroke
+                    .to_terrain
+                    .transform_point3(paint.position.extend(0.0))
+                    .truncate();
+                let spacing = (brush.radius / 4.0).max(f32::EPSILON);
+                let distance = stroke.last.distance(position);
+                let mut travelled = spacing;
+                while travelled < distance {
+                    dab(
+                        &mut terrain,
+                        stroke.last.lerp(position, travelled / distance),
+                        brush,
+                    );
+                    travelled += spacing;
+                }
+                dab(&mut terrain, position, brush);
+                if let Some(stroke) = &mut brush.stroke {
+                    stroke.last = position;
+                }
+            }
+            DragPhase::End => {
+                let Some(stroke) = brush.stroke.clone() else {
+                    continue;
+                };
+                let painted = terrains
+                    .get(stroke.terrain)
+                    .map(|(terrain, id, _)| (terrain.weights.clone(), *id));
+                restore(&mut brush, &mut terrains);
+
+                if let Ok((weights, id)) = painted
+                    && weights != stroke.before
+                {
+                    commands.edit(SetTerrainWeights::new(id, weights));
+                }
+            }
+            DragPhase::Cancel => restore(&mut brush, &mut terrains),
+        }
+    }
+}
+
+/// Ends the stroke in progress, restoring the splat map from before it.
+fn restore(
+    brush: &mut TerrainBrush,
+    terrains: &mut Query<(&mut Terrain, &PersistentId, &GlobalTransform)>,
+) {
+    if let Some(stroke) = brush.stroke.take()
+        && let Ok((mut terrain, ..)) = terrains.get_mut(stroke.terrain)
+        && terrain.weights != stroke.before
+    {
+        terrain.weights = stroke.before;
+    }
+}
+
+/// Paints a single dab of `brush` centered on `center`, in the space of `terrain`.
+fn dab(terrain: &mut Terrain, center: Vec2, brush: &TerrainBrush) {
+    if brush.texture >= terrain.textures.len().min(Terrain::MAX_TEXTURES) {
+        return;
+    }
+
+    let area = Rect::from_center_half_size(center, Vec2::splat(brush.radius));
+    for texel in terrain.texels_in(area) {
+        let amount = brush.strength.clamp(0.0, 1.0)
+            * falloff(terrain.texel_center(texel).distance(center), brush);
+        if amount <= 0.0 {
+            continue;
+        }
+
+        let index = terrain.index(texel);
+        for (channel, weight) in terrain.weights[index].iter_mut().enumerate() {
+            let current = f32::from(*weight);
+            let target = if channel == brush.texture { 255.0 } else { 0.0 };
+            let value = current + (target - current) * amount;
+            // Rounding towards the target lets weak brushes reach it eventually.
+            let value = if channel == brush.texture {
+                value.ceil()
+            } else {
+                value.floor()
+            };
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss,
+                reason = "the value lies between the current weight and the target"
+            )]
+            let value = value as u8;
+            *weight = value;
+        }
+    }
+}
+
+/// How much of the brush is applied at `distance` from its center, between `0.0` and `1.0`.
+fn falloff(distance: f32, brush: &TerrainBrush) -> f32 {
+    let inner = brush.radius * (1.0 - brush.softness.clamp(0.0, 1.0));
+    if distance >= brush.radius {
+        0.0
+    } else if distance <= inner {
+        1.0
+    } else {
+        let t = (brush.radius - distance) / (brush.radius - inner);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
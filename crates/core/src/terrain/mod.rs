@@ -0,0 +1,31 @@
+//! Paints ground textures onto terrains with a brush.
+//!
+//! The user interface hands the strokes of the brush over as [`PaintTerrain`] requests, in world
+//! units. The splat map follows the stroke as it happens, and the whole stroke is recorded in the
+//! [`History`](crate::History) as a single edit once it ends.
+
+mod brush;
+mod splat;
+
+pub use brush::{PaintTerrain, TerrainBrush};
+pub use splat::{TerrainSplat, TerrainTextures, splat_image};
+
+use bevy::prelude::{App, IntoScheduleConfigs, Plugin, Update};
+
+/// Registers the terrain brush and uploads the splat maps of
+/// [`Terrain`](dungeonrs_data::Terrain)s.
+///
+/// Requires the [`HistoryPlugin`](crate::HistoryPlugin) to undo strokes, and the
+/// [`AssetsPlugin`](dungeonrs_assets::AssetsPlugin) for the blended textures.
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TerrainBrush>()
+            .add_message::<PaintTerrain>()
+            .add_systems(
+                Update,
+                (brush::paint_terrain, splat::build_splat_maps).chain(),
+            );
+    }
+}
@@ -0,0 +1,70 @@
+//! Uploads the splat map of a terrain as a texture.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use dungeonrs_assets::HandleCache;
+use dungeonrs_data::Terrain;
+use dungeonrs_macros::bevy_system;
+
+/// The splat map of a [`Terrain`] as a texture, each channel holding the weight of a texture.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct TerrainSplat(pub Handle<Image>);
+
+/// The textures a [`Terrain`] blends, in the order of the channels of its [`TerrainSplat`].
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq)]
+pub struct TerrainTextures(pub Vec<Handle<Image>>);
+
+/// The splat map of `terrain` as an image.
+///
+/// The weights are stored linearly rather than as sRGB, so they blend as painted.
+#[must_use]
+pub fn splat_image(terrain: &Terrain) -> Image {
+    let mut data = terrain.weights.as_flattened().to_vec();
+    data.resize(terrain.resolution.element_product() as usize * 4, 0);
+    Image::new(
+        Extent3d {
+            width: terrain.resolution.x.max(1),
+            height: terrain.resolution.y.max(1),
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::default(),
+    )
+}
+
+/// Uploads the splat map of the terrains that were painted, and gives new terrains the quad
+/// they're drawn on and their textures.
+#[bevy_system]
+pub(crate) fn build_splat_maps(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut cache: ResMut<HandleCache>,
+    asset_server: Res<AssetServer>,
+    terrains: Query<(Entity, &Terrain, Option<&TerrainSplat>), Changed<Terrain>>,
+) {
+    for (entity, terrain, splat) in &terrains {
+        let handle =
+            match splat.and_then(|TerrainSplat(handle)| Some((handle, images.get_mut(handle)?))) {
+                Some((handle, image)) => {
+                    *image = splat_image(terrain);
+                    handle.clone()
+                }
+                None => images.add(splat_image(terrain)),
+            };
+
+        let textures = terrain
+            .textures
+            .iter()
+            .map(|texture| cache.image(&asset_server, texture.clone()))
+            .collect();
+        let mut entity = commands.entity(entity);
+        entity.insert((TerrainSplat(handle), TerrainTextures(textures)));
+        if splat.is_none() {
+            entity.insert(Mesh2d(meshes.add(Rectangle::from_size(terrain.size))));
+        }
+    }
+}
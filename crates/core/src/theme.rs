@@ -0,0 +1,300 @@
+//! Colours the editor through a [`Theme`] of semantic colours, such as the background of panels or
+//! the accent of selected items.
+//!
+//! The [`Themes`] hold the built-in light and dark themes along with the user's own, read from the
+//! RON files in the `themes` directory of the configuration. The theme picked in the
+//! [`Configuration`] becomes the current [`Theme`] as soon as it changes, for the user interface to
+//! style its widgets with.
+
+use crate::{Configuration, ConfigurationChanged, ConfigurationSetting};
+use bevy::prelude::*;
+use dungeonrs_macros::bevy_system;
+use dungeonrs_serialization::{Error, Format, deserialize};
+use dungeonrs_utils::Directory;
+use serde::{Deserialize, Serialize};
+use std::fs::{read, read_dir};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The file extension of user themes.
+const THEME_EXTENSION: &str = "ron";
+
+/// Reads the user themes when the app starts and switches the [`Theme`] to the one picked in the
+/// [`Configuration`].
+///
+/// Add the [`ConfigurationPlugin`](crate::ConfigurationPlugin) to keep the picked theme across
+/// sessions.
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Themes>()
+            .init_resource::<Theme>()
+            .add_message::<ConfigurationChanged>()
+            .add_message::<ThemeLoadFailed>()
+            .add_systems(Startup, load_themes)
+            .add_systems(Update, (switch_theme, apply_theme).chain());
+    }
+}
+
+/// The colours of a [`Theme`], named after what they're used for rather than what they look like.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Palette {
+    /// The background of the viewport behind the map.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub background: Color,
+    /// The background of panels and menus.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub panel: Color,
+    /// The background of widgets within panels, such as buttons and text fields.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub widget: Color,
+    /// The borders and separators between widgets.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub border: Color,
+    /// The text.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub text: Color,
+    /// Text of less importance, such as hints and disabled items.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub muted_text: Color,
+    /// The accent of focused and active items.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub accent: Color,
+    /// The highlight of selected items, such as selected elements and layers.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub selection: Color,
+    /// Warnings, such as license conflicts.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub warning: Color,
+    /// Errors, such as failed exports.
+    #[serde(with = "dungeonrs_serialization::compact::color")]
+    pub error: Color,
+}
+
+/// The colours the editor is drawn with.
+///
+/// User themes are RON files holding a theme, such as
+/// `(name: "Parchment", dark: false, palette: (background: [0.93, 0.88, 0.78], ...))`, with every
+/// colour of the [`Palette`] as `[r, g, b]` or `[r, g, b, a]` in sRGB.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    /// The name of the theme, which identifies it in the [`Configuration`].
+    pub name: String,
+    /// Whether the theme is dark, for the widgets the palette doesn't cover.
+    pub dark: bool,
+    /// The colours of the theme.
+    pub palette: Palette,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// The name of the built-in dark theme.
+    pub const DARK: &'static str = "Dark";
+    /// The name of the built-in light theme.
+    pub const LIGHT: &'static str = "Light";
+
+    /// The built-in dark theme, the default one.
+    #[must_use]
+    pub fn dark() -> Self {
+        Self {
+            name: Self::DARK.into(),
+            dark: true,
+            palette: Palette {
+                background: Color::srgb(0.11, 0.11, 0.12),
+                panel: Color::srgb(0.16, 0.16, 0.18),
+                widget: Color::srgb(0.23, 0.23, 0.26),
+                border: Color::srgb(0.3, 0.3, 0.34),
+                text: Color::srgb(0.9, 0.9, 0.9),
+                muted_text: Color::srgb(0.58, 0.58, 0.62),
+                accent: Color::srgb(0.35, 0.6, 0.95),
+                selection: Color::srgba(0.35, 0.6, 0.95, 0.35),
+                warning: Color::srgb(0.95, 0.75, 0.3),
+                error: Color::srgb(0.92, 0.35, 0.35),
+            },
+        }
+    }
+
+    /// The built-in light theme.
+    #[must_use]
+    pub fn light() -> Self {
+        Self {
+            name: Self::LIGHT.into(),
+            dark: false,
+            palette: Palette {
+                background: Color::srgb(0.86, 0.86, 0.88),
+                panel: Color::srgb(0.96, 0.96, 0.97),
+                widget: Color::srgb(0.9, 0.9, 0.92),
+                border: Color::srgb(0.75, 0.75, 0.78),
+                text: Color::srgb(0.1, 0.1, 0.12),
+                muted_text: Color::srgb(0.42, 0.42, 0.46),
+                accent: Color::srgb(0.15, 0.42, 0.85),
+                selection: Color::srgba(0.15, 0.42, 0.85, 0.3),
+                warning: Color::srgb(0.75, 0.5, 0.0),
+                error: Color::srgb(0.78, 0.15, 0.15),
+            },
+        }
+    }
+}
+
+/// The themes the user can pick from: the built-in ones followed by the user's own.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Themes {
+    /// The directory user themes are read from.
+    pub directory: PathBuf,
+    /// The available themes, a user theme replacing a built-in theme of the same name.
+    themes: Vec<Theme>,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        Self::new(Directory::Config.join("themes"))
+    }
+}
+
+impl Themes {
+    /// Reads user themes from `directory` instead of the default location.
+    ///
+    /// Insert it before adding the [`ThemePlugin`].
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            themes: vec![Theme::dark(), Theme::light()],
+        }
+    }
+
+    /// Iterates over the available themes, the built-in ones first.
+    pub fn iter(&self) -> impl Iterator<Item = &Theme> {
+        self.themes.iter()
+    }
+
+    /// Returns the theme named `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Theme> {
+        self.themes.iter().find(|theme| theme.name == name)
+    }
+
+    /// Adds `theme`, replacing the theme of the same name if there was one.
+    pub fn add(&mut self, theme: Theme) {
+        match self
+            .themes
+            .iter_mut()
+            .find(|existing| existing.name == theme.name)
+        {
+            Some(existing) => *existing = theme,
+            None => self.themes.push(theme),
+        }
+    }
+
+    /// Reads the user themes from [`Themes::directory`], keeping the built-in themes.
+    ///
+    /// Themes that can't be read are skipped and returned along with their file, so the others
+    /// are still available.
+    pub fn reload(&mut self) -> Vec<(PathBuf, Error)> {
+        self.themes = vec![Theme::dark(), Theme::light()];
+        let mut failures = Vec::new();
+        let paths = match theme_files(&self.directory) {
+            Ok(paths) => paths,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return failures,
+            Err(error) => {
+                failures.push((self.directory.clone(), error.into()));
+                return failures;
+            }
+        };
+
+        for path in paths {
+            let theme = read(&path)
+                .map_err(Error::from)
+                .and_then(|bytes| deserialize::<Theme>(&bytes, Format::Ron));
+            match theme {
+                Ok(theme) => self.add(theme),
+                Err(error) => failures.push((path, error)),
+            }
+        }
+
+        failures
+    }
+}
+
+/// Written when a user theme couldn't be read.
+#[derive(Message, Debug)]
+pub struct ThemeLoadFailed {
+    /// The file of the theme.
+    pub path: PathBuf,
+    /// The reason the theme couldn't be read.
+    pub error: Error,
+}
+
+/// Returns the theme files in `directory`, sorted by name.
+///
+/// # Errors
+/// Returns an error if the directory can't be read.
+fn theme_files(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in read_dir(directory)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == THEME_EXTENSION)
+        {
+            paths.push(path);
+        }
+    }
+
+    // Themes with the same name replace each other, sorting keeps which one wins predictable.
+    paths.sort_unstable();
+    Ok(paths)
+}
+
+/// Reads the user themes.
+#[bevy_system]
+fn load_themes(mut themes: ResMut<Themes>, mut failures: MessageWriter<ThemeLoadFailed>) {
+    failures.write_batch(
+        themes
+            .reload()
+            .into_iter()
+            .map(|(path, error)| ThemeLoadFailed { path, error }),
+    );
+}
+
+/// Switches to the theme picked in the [`Configuration`] once it or the available themes changed.
+///
+/// An unknown theme, such as a user theme that was removed, falls back to the default theme.
+#[bevy_system]
+fn switch_theme(
+    mut changes: MessageReader<ConfigurationChanged>,
+    configuration: Option<Res<Configuration>>,
+    themes: Res<Themes>,
+    mut theme: ResMut<Theme>,
+) {
+    let picked = changes
+        .read()
+        .any(|change| change.setting == ConfigurationSetting::Theme);
+    if !picked && !themes.is_changed() {
+        return;
+    }
+
+    let name = configuration
+        .as_deref()
+        .and_then(Configuration::theme)
+        .unwrap_or(Theme::DARK);
+    let next = themes.get(name).cloned().unwrap_or_default();
+    theme.set_if_neq(next);
+}
+
+/// Clears the viewport with the background of the current [`Theme`].
+#[bevy_system]
+fn apply_theme(theme: Res<Theme>, clear_color: Option<ResMut<ClearColor>>) {
+    if !theme.is_changed() {
+        return;
+    }
+
+    if let Some(mut clear_color) = clear_color {
+        clear_color.0 = theme.palette.background;
+    }
+}
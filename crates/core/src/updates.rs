@@ -0,0 +1,210 @@
+//! Checks whether a newer release of the editor is available.
+//!
+//! The check is opt-in: when enabled in the [`UpdateSettings`], the latest release is fetched
+//! from the GitHub releases API in the background at startup and compared against the running
+//! [`version`]. The user interface shows the [`UpdateAvailable`] as a notification.
+//!
+//! Disabling the check or going offline while it runs cancels it.
+
+use bevy::prelude::*;
+use dungeonrs_macros::bevy_system;
+use dungeonrs_utils::{
+    AsyncCommandsExt, CancellationToken, Cancelled, NetworkSettings, Retry, is_transient,
+    report_progress, version,
+};
+use semver::Version;
+use serde::Deserialize;
+use thiserror::Error;
+use ureq::{Agent, Proxy};
+
+/// Checks for a newer release at startup, when enabled in the [`UpdateSettings`].
+///
+/// Requires the [`UtilsPlugin`](dungeonrs_utils::UtilsPlugin) to run the check in the
+/// background.
+pub struct UpdatePlugin;
+
+impl Plugin for UpdatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UpdateSettings>()
+            .init_resource::<NetworkSettings>()
+            .add_message::<UpdateAvailable>()
+            .add_message::<UpdateCheckFailed>()
+            .add_systems(Startup, check_for_updates)
+            .add_systems(
+                Update,
+                cancel_update_check.run_if(
+                    resource_changed::<UpdateSettings>.or(resource_changed::<NetworkSettings>),
+                ),
+            );
+    }
+}
+
+/// Configures the update check.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct UpdateSettings {
+    /// Whether to check for updates at startup, disabled until the user opts in.
+    pub enabled: bool,
+    /// The GitHub repository releases are published in, as `owner/name`.
+    pub repository: String,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            repository: "dungeon-rs/dungeon-rs".into(),
+        }
+    }
+}
+
+/// Written when a release newer than the running version is available.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct UpdateAvailable {
+    /// The version of the release.
+    pub version: String,
+    /// The release notes, in Markdown.
+    pub notes: String,
+    /// The page the release can be downloaded from.
+    pub url: String,
+}
+
+/// Written when the update check failed.
+#[derive(Message, Debug)]
+pub struct UpdateCheckFailed {
+    /// The error that occurred.
+    pub error: UpdateError,
+}
+
+/// Errors that can occur while checking for updates.
+#[derive(Error, Debug)]
+pub enum UpdateError {
+    /// The releases couldn't be fetched.
+    #[error("failed to fetch the latest release: {0}")]
+    Http(#[from] ureq::Error),
+    /// The response isn't a valid release.
+    #[error("invalid release: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The version of the release or the editor isn't a semantic version.
+    #[error("invalid version: {0}")]
+    Version(#[from] semver::Error),
+    /// The check was cancelled before the release was fetched.
+    #[error(transparent)]
+    Cancelled(#[from] Cancelled),
+}
+
+/// The token cancelling the update check while it runs.
+#[derive(Resource, Debug)]
+struct RunningUpdateCheck(CancellationToken);
+
+/// The fields of a GitHub release that are used.
+#[derive(Deserialize, Debug)]
+struct Release {
+    /// The tag of the release, its version optionally prefixed with `v`.
+    tag_name: String,
+    /// The release notes.
+    #[serde(default)]
+    body: Option<String>,
+    /// The page of the release.
+    html_url: String,
+}
+
+/// Fetches the latest release of `repository` and returns it when it's newer than the running
+/// version.
+///
+/// Prereleases and drafts are never considered. Transient failures, such as timeouts or the
+/// server being unavailable, are retried until `token` is cancelled. This blocks until the
+/// request completes, so call it from a background task.
+///
+/// # Errors
+/// Returns an error if the release can't be fetched or its version isn't a semantic version.
+pub fn check_for_update(
+    repository: &str,
+    network: &NetworkSettings,
+    token: &CancellationToken,
+) -> Result<Option<UpdateAvailable>, UpdateError> {
+    let mut config = Agent::config_builder()
+        .timeout_global(Some(network.timeout))
+        .user_agent(format!("DungeonRS/{}", version()));
+    if let Some(proxy) = &network.proxy {
+        config = config.proxy(Some(Proxy::new(proxy)?));
+    }
+
+    let agent = config.build().new_agent();
+    let url = format!("https://api.github.com/repos/{repository}/releases/latest");
+    let body = Retry::default().run_if(
+        |_| {
+            token.check()?;
+            let body = agent
+                .get(&url)
+                .header("Accept", "application/vnd.github+json")
+                .call()?
+                .body_mut()
+                .read_to_string()?;
+
+            Ok::<_, UpdateError>(body)
+        },
+        |error| matches!(error, UpdateError::Http(error) if is_transient_http(error)),
+    )?;
+    let release: Release = serde_json::from_str(&body)?;
+
+    let latest = Version::parse(release.tag_name.trim_start_matches('v'))?;
+    if latest <= Version::parse(version())? {
+        return Ok(None);
+    }
+
+    Ok(Some(UpdateAvailable {
+        version: latest.to_string(),
+        notes: release.body.unwrap_or_default(),
+        url: release.html_url,
+    }))
+}
+
+/// Returns whether the request may succeed when made again, such as after a timeout or while the
+/// server is overloaded.
+fn is_transient_http(error: &ureq::Error) -> bool {
+    match error {
+        ureq::Error::StatusCode(status) => *status == 429 || *status >= 500,
+        ureq::Error::Io(error) => is_transient(error),
+        ureq::Error::Timeout(_) | ureq::Error::ConnectionFailed => true,
+        _ => false,
+    }
+}
+
+/// Checks for updates in the background when enabled and the network may be used.
+#[bevy_system]
+fn check_for_updates(
+    mut commands: Commands,
+    settings: Res<UpdateSettings>,
+    network: Res<NetworkSettings>,
+) {
+    if !settings.enabled || network.offline {
+        return;
+    }
+
+    let repository = settings.repository.clone();
+    let network = network.clone();
+    let token = commands.spawn_async(move |context| async move {
+        match check_for_update(&repository, &network, context.token()) {
+            Ok(Some(update)) => report_progress(&context, update),
+            Ok(None) | Err(UpdateError::Cancelled(_)) => {}
+            Err(error) => report_progress(&context, UpdateCheckFailed { error }),
+        }
+    });
+    commands.insert_resource(RunningUpdateCheck(token));
+}
+
+/// Cancels the running update check once it's disabled or the network may no longer be used.
+#[bevy_system]
+fn cancel_update_check(
+    mut commands: Commands,
+    settings: Res<UpdateSettings>,
+    network: Res<NetworkSettings>,
+    running: Option<Res<RunningUpdateCheck>>,
+) {
+    if let Some(running) = running
+        && (!settings.enabled || network.offline)
+    {
+        commands.cancel(&running.0);
+        commands.remove_resource::<RunningUpdateCheck>();
+    }
+}
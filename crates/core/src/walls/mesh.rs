@@ -0,0 +1,156 @@
+//! Builds the mesh of a [`WallPath`].
+
+use bevy::asset::RenderAssetUsages;
+use bevy::mesh::{Indices, PrimitiveTopology};
+use bevy::prelude::*;
+use dungeonrs_assets::HandleCache;
+use dungeonrs_data::WallPath;
+use dungeonrs_macros::bevy_system;
+
+/// How far a mitered corner may stick out, in multiples of half the wall's width.
+///
+/// Sharper corners are clipped, so a wall doubling back on itself doesn't produce a spike.
+const MITER_LIMIT: f32 = 4.0;
+
+/// The texture of a [`WallPath`], repeated along its mesh.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct WallTexture(pub Handle<Image>);
+
+/// Builds the mesh of `path`, a strip `path.width` thick along its control points.
+///
+/// The texture coordinates repeat the texture every `path.width` along the path, so square wall
+/// textures tile without being stretched. Corners are mitered, up to [`MITER_LIMIT`]. Paths with
+/// fewer than two distinct points produce an empty mesh.
+#[must_use]
+pub fn wall_mesh(path: &WallPath) -> Mesh {
+    let Strip {
+        positions,
+        uvs,
+        indices,
+    } = strip(&path.points, path.closed, path.width);
+
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// The triangles of a band along a path, see [`strip`].
+pub(crate) struct Strip {
+    /// The corners of the triangles, two per point of the path.
+    pub(crate) positions: Vec<[f32; 3]>,
+    /// The texture coordinates of the corners, `u` growing by one every `width` along the path.
+    pub(crate) uvs: Vec<[f32; 2]>,
+    /// The corners of each triangle.
+    pub(crate) indices: Vec<u32>,
+}
+
+/// The triangles of a band `width` thick along `path`, connecting the last point back to the
+/// first when `closed`.
+///
+/// Corners are mitered, up to [`MITER_LIMIT`]. Paths with fewer than two distinct points produce
+/// no triangles.
+pub(crate) fn strip(path: &[Vec2], closed: bool, width: f32) -> Strip {
+    let mut points: Vec<Vec2> = Vec::with_capacity(path.len() + 1);
+    for point in path {
+        if points.last() != Some(point) {
+            points.push(*point);
+        }
+    }
+    let closed = closed && points.len() > 2;
+    if closed && points.first() == points.last() {
+        points.pop();
+    }
+
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+    if points.len() >= 2 && width > 0.0 {
+        let half_width = width / 2.0;
+        let count = points.len();
+        let direction = |from: usize, to: usize| (points[to] - points[from]).normalize_or_zero();
+        let mut distance = 0.0;
+        // Closed paths end where they started, repeating the first corner with the full length.
+        let corners = if closed { count + 1 } else { count };
+        for corner in 0..corners {
+            let index = corner % count;
+            let incoming =
+                (closed || index > 0).then(|| direction((index + count - 1) % count, index));
+            let outgoing =
+                (closed || index + 1 < count).then(|| direction(index, (index + 1) % count));
+            let offset = miter(incoming, outgoing, half_width);
+
+            if corner > 0 {
+                distance += points[index].distance(points[(index + count - 1) % count]);
+            }
+            let point = points[index];
+            let u = distance / width;
+            positions.push((point + offset).extend(0.0).to_array());
+            positions.push((point - offset).extend(0.0).to_array());
+            uvs.push([u, 0.0]);
+            uvs.push([u, 1.0]);
+        }
+
+        for segment in 0..corners - 1 {
+            let left = u32::try_from(segment * 2).unwrap_or(u32::MAX);
+            indices.extend([left, left + 1, left + 2, left + 1, left + 3, left + 2]);
+        }
+    }
+
+    Strip {
+        positions,
+        uvs,
+        indices,
+    }
+}
+
+/// The offset from a corner to the left edge of the wall, given the directions of the segments
+/// entering and leaving the corner.
+fn miter(incoming: Option<Vec2>, outgoing: Option<Vec2>, half_width: f32) -> Vec2 {
+    let normal = |direction: Vec2| direction.perp();
+    match (incoming, outgoing) {
+        (Some(incoming), Some(outgoing)) => {
+            let miter = (normal(incoming) + normal(outgoing)).normalize_or_zero();
+            let cosine = miter.dot(normal(incoming));
+            if miter == Vec2::ZERO || cosine <= f32::EPSILON {
+                // The path doubles back on itself, the corner is squared off.
+                return normal(incoming) * half_width;
+            }
+
+            miter * (half_width / cosine).min(half_width * MITER_LIMIT)
+        }
+        (Some(direction), None) | (None, Some(direction)) => normal(direction) * half_width,
+        (None, None) => Vec2::ZERO,
+    }
+}
+
+/// Rebuilds the mesh of the wall paths whose control points or width changed, and loads their
+/// texture.
+#[bevy_system]
+pub(crate) fn build_wall_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut cache: ResMut<HandleCache>,
+    asset_server: Res<AssetServer>,
+    paths: Query<(Entity, &WallPath, Option<&Mesh2d>), Changed<WallPath>>,
+) {
+    for (entity, path, mesh) in &paths {
+        let handle = match mesh.and_then(|Mesh2d(handle)| Some((handle, meshes.get_mut(handle)?))) {
+            Some((handle, mesh)) => {
+                *mesh = wall_mesh(path);
+                handle.clone()
+            }
+            None => meshes.add(wall_mesh(path)),
+        };
+
+        let texture = cache.image(&asset_server, path.texture.clone());
+        commands
+            .entity(entity)
+            .insert((Mesh2d(handle), WallTexture(texture)));
+    }
+}
@@ -0,0 +1,30 @@
+//! Draws textured walls along paths and builds their meshes.
+
+mod mesh;
+mod tool;
+
+pub use mesh::{WallTexture, wall_mesh};
+
+pub(crate) use mesh::{Strip, strip};
+pub use tool::{AddWallPoint, CancelWallPath, FinishWallPath, WallTool};
+
+use bevy::prelude::{App, IntoScheduleConfigs, Plugin, Update};
+
+/// Registers the wall tool and builds the meshes of [`WallPath`](dungeonrs_data::WallPath)s.
+///
+/// Requires the [`HistoryPlugin`](crate::HistoryPlugin) to undo drawn walls, and the
+/// [`AssetsPlugin`](dungeonrs_assets::AssetsPlugin) for their textures.
+pub struct WallsPlugin;
+
+impl Plugin for WallsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WallTool>()
+            .add_message::<AddWallPoint>()
+            .add_message::<FinishWallPath>()
+            .add_message::<CancelWallPath>()
+            .add_systems(
+                Update,
+                (tool::draw_wall_paths, mesh::build_wall_meshes).chain(),
+            );
+    }
+}
@@ -0,0 +1,125 @@
+//! Draws wall paths by clicking their control points.
+
+use crate::{HistoryCommandsExt, PlaceWallPath, WallPathData};
+use bevy::asset::uuid::Uuid;
+use bevy::prelude::*;
+use dungeonrs_data::{Grid, Layer, PersistentId};
+use dungeonrs_macros::bevy_system;
+use std::path::PathBuf;
+
+/// The state of the wall tool, configured by the user interface.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct WallTool {
+    /// The layer drawn wall paths are placed on.
+    pub layer: Option<Entity>,
+    /// The thickness of drawn walls, in world units.
+    pub width: f32,
+    /// The path of the texture of drawn walls, usually a wall texture from an asset pack.
+    pub texture: PathBuf,
+    /// Whether the control points snap to the [`Grid`].
+    pub snap: bool,
+    /// The control points clicked so far, in world units.
+    points: Vec<Vec2>,
+}
+
+impl Default for WallTool {
+    fn default() -> Self {
+        Self {
+            layer: None,
+            width: 20.0,
+            texture: PathBuf::new(),
+            snap: true,
+            points: Vec::new(),
+        }
+    }
+}
+
+impl WallTool {
+    /// The control points clicked so far, in world units, so the user interface can preview the
+    /// wall being drawn.
+    #[must_use]
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+}
+
+/// Adds a control point to the wall being drawn, such as where the user clicked.
+#[derive(Message, Debug, Copy, Clone, PartialEq)]
+pub struct AddWallPoint {
+    /// The position of the point, in world units.
+    pub position: Vec2,
+}
+
+/// Finishes the wall being drawn, placing it on the [`WallTool::layer`].
+#[derive(Message, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct FinishWallPath {
+    /// Whether the last point connects back to the first.
+    pub closed: bool,
+}
+
+/// Discards the wall being drawn.
+#[derive(Message, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CancelWallPath;
+
+/// Applies the requests of the wall tool, recording finished walls in the history.
+#[bevy_system]
+pub(crate) fn draw_wall_paths(
+    mut commands: Commands,
+    mut tool: ResMut<WallTool>,
+    mut points: MessageReader<AddWallPoint>,
+    mut finishes: MessageReader<FinishWallPath>,
+    mut cancels: MessageReader<CancelWallPath>,
+    grid: Option<Res<Grid>>,
+    layers: Query<(&Layer, &PersistentId, &GlobalTransform)>,
+) {
+    for point in points.read() {
+        let position = match &grid {
+            Some(grid) if tool.snap => grid.snap_position(point.position),
+            _ => point.position,
+        };
+        if tool.points.last() != Some(&position) {
+            tool.points.push(position);
+        }
+    }
+
+    if cancels.read().count() > 0 {
+        tool.points.clear();
+    }
+
+    let Some(finish) = finishes.read().last() else {
+        return;
+    };
+    let points = std::mem::take(&mut tool.points);
+    let Some((_, layer, global)) = tool
+        .layer
+        .and_then(|layer| layers.get(layer).ok())
+        .filter(|(layer, ..)| !layer.locked)
+    else {
+        return;
+    };
+    if points.len() < 2 {
+        return;
+    }
+
+    // The wall is positioned at its first point, with the points relative to it.
+    let to_layer = global.affine().inverse();
+    let local: Vec<Vec2> = points
+        .iter()
+        .map(|point| to_layer.transform_point3(point.extend(0.0)).truncate())
+        .collect();
+    let origin = local[0];
+    commands.edit(PlaceWallPath::new(
+        *layer,
+        WallPathData {
+            id: Uuid::new_v4(),
+            points: local
+                .iter()
+                .map(|point| (*point - origin).to_array())
+                .collect(),
+            closed: finish.closed,
+            width: tool.width,
+            texture: tool.texture.clone(),
+            transform: Transform::from_translation(origin.extend(0.0)),
+        },
+    ));
+}
@@ -0,0 +1,111 @@
+//! Contains the [`AnimatedTexture`] component.
+
+use bevy::prelude::*;
+
+/// How the frames of an [`AnimatedTexture`] are stored.
+#[derive(Reflect, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AnimationFrames {
+    /// The frames are the cells of a spritesheet, the element's asset, read row by row. Holds the
+    /// number of columns and rows of the sheet.
+    Sheet(UVec2),
+    /// The frames are the images of a directory, the element's asset, in file name order.
+    Folder,
+}
+
+/// Whether an [`AnimatedTexture`] plays its frames.
+#[derive(Reflect, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AnimationPlayback {
+    /// The frames play in a loop.
+    Animated,
+    /// The frame at the given index is displayed.
+    Frozen(u32),
+}
+
+/// Displays the asset of an [`Element`](crate::Element) as an animation, such as animated water,
+/// fire or a portal.
+///
+/// The element's asset is either a spritesheet or a directory of frames, as set by
+/// [`AnimatedTexture::frames`].
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+pub struct AnimatedTexture {
+    /// How the frames are stored.
+    pub frames: AnimationFrames,
+    /// The number of frames displayed per second.
+    pub fps: f32,
+    /// Whether the frames play or a single frame is displayed.
+    pub playback: AnimationPlayback,
+    /// The frame displayed when the map is exported, as an exported image can't play.
+    pub export_frame: u32,
+}
+
+impl AnimatedTexture {
+    /// Animates the cells of a spritesheet of `columns` by `rows` frames at `fps`.
+    #[must_use]
+    pub fn sheet(columns: u32, rows: u32, fps: f32) -> Self {
+        Self::new(AnimationFrames::Sheet(UVec2::new(columns, rows)), fps)
+    }
+
+    /// Animates the images of a directory at `fps`.
+    #[must_use]
+    pub fn folder(fps: f32) -> Self {
+        Self::new(AnimationFrames::Folder, fps)
+    }
+
+    /// Plays `frames` at `fps`, exporting the first frame.
+    #[must_use]
+    pub fn new(frames: AnimationFrames, fps: f32) -> Self {
+        Self {
+            frames,
+            fps,
+            playback: AnimationPlayback::Animated,
+            export_frame: 0,
+        }
+    }
+
+    /// Displays the frame at `frame` instead of playing the frames.
+    #[must_use]
+    pub fn with_frozen(mut self, frame: u32) -> Self {
+        self.playback = AnimationPlayback::Frozen(frame);
+        self
+    }
+
+    /// Exports the frame at `frame`.
+    #[must_use]
+    pub fn with_export_frame(mut self, frame: u32) -> Self {
+        self.export_frame = frame;
+        self
+    }
+
+    /// The index of the frame displayed `seconds` after the animation started, out of `count`
+    /// frames.
+    ///
+    /// Frame indexes past the last frame wrap around.
+    #[must_use]
+    pub fn frame_at(&self, seconds: f32, count: usize) -> usize {
+        if count == 0 {
+            return 0;
+        }
+
+        let frame = match self.playback {
+            AnimationPlayback::Frozen(frame) => frame as usize,
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss,
+                reason = "the frame is positive and rounded down"
+            )]
+            AnimationPlayback::Animated => (seconds * self.fps.max(0.0)).floor() as usize,
+        };
+        frame % count
+    }
+
+    /// The index of the frame exported out of `count` frames.
+    #[must_use]
+    pub fn exported_frame(&self, count: usize) -> usize {
+        if count == 0 {
+            return 0;
+        }
+
+        self.export_frame as usize % count
+    }
+}
@@ -0,0 +1,42 @@
+//! Contains the [`LevelBackground`] component.
+
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// What a [`Level`](crate::Level) shows below its layers, such as parchment or a plain colour,
+/// instead of leaving the map transparent where nothing is placed.
+///
+/// The background covers the contents of the level and the area of its exports, so exported
+/// maps have no void around the placed textures.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+pub enum LevelBackground {
+    /// A solid colour.
+    Color(Color),
+    /// A texture repeated across the level, such as paper or grass. Holds the path of the asset
+    /// and the width of a single repetition, in world units.
+    Texture(PathBuf, f32),
+    /// A single image stretched across the level. Holds the path of the asset.
+    Image(PathBuf),
+}
+
+impl LevelBackground {
+    /// A background repeating the asset at `asset` every `tile_size` world units.
+    pub fn texture(asset: impl Into<PathBuf>, tile_size: f32) -> Self {
+        Self::Texture(asset.into(), tile_size)
+    }
+
+    /// A background stretching the asset at `asset` across the level.
+    pub fn image(asset: impl Into<PathBuf>) -> Self {
+        Self::Image(asset.into())
+    }
+
+    /// The path of the asset shown by the background, `None` for a solid colour.
+    #[must_use]
+    pub fn asset(&self) -> Option<&Path> {
+        match self {
+            Self::Color(_) => None,
+            Self::Texture(asset, _) | Self::Image(asset) => Some(asset),
+        }
+    }
+}
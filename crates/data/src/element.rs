@@ -0,0 +1,23 @@
+//! Contains the [`Element`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+/// An asset placed on a [`Layer`](crate::Layer), positioned by its [`Transform`].
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct Element {
+    /// The path of the asset displayed by this element.
+    pub asset: PathBuf,
+}
+
+impl Element {
+    /// Creates an element displaying the asset at `asset`.
+    pub fn new(asset: impl Into<PathBuf>) -> Self {
+        Self {
+            asset: asset.into(),
+        }
+    }
+}
@@ -0,0 +1,352 @@
+//! Contains the [`Grid`] elements are snapped to.
+
+use bevy::prelude::*;
+
+/// The grid elements are snapped to while they're placed or moved.
+///
+/// The UI passes the position and rotation of dragged elements through [`Grid::snap`] before
+/// applying them, so they land on the grid instead of arbitrary positions.
+#[derive(Resource, Reflect, Debug, Copy, Clone, PartialEq)]
+#[reflect(Resource)]
+pub struct Grid {
+    /// The shape of the grid cells.
+    pub shape: GridShape,
+    /// The size of a grid cell, in world units: the width of a square cell, or the distance
+    /// between the centers of neighbouring hexagons, across their flat sides.
+    pub cell_size: f32,
+    /// The position of a cell corner of a square grid or a cell center of a hexagonal grid,
+    /// shifting the whole grid.
+    pub offset: Vec2,
+    /// The points of the cells positions snap to, positions aren't snapped when none is enabled.
+    pub targets: SnapTargets,
+    /// The angle rotations snap to multiples of, in radians, rotations aren't snapped when `None`.
+    pub rotation_step: Option<f32>,
+}
+
+/// The shape of the cells of a [`Grid`].
+#[derive(Reflect, Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum GridShape {
+    /// Square cells, aligned with the axes.
+    #[default]
+    Square,
+    /// Hexagons with a corner at the top, laid out in rows.
+    PointyHex,
+    /// Hexagons with a flat side at the top, laid out in columns.
+    FlatHex,
+}
+
+/// The points of the grid cells positions snap to, the nearest enabled one is used.
+#[derive(Reflect, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SnapTargets {
+    /// Snap to the corners of the cells.
+    pub corners: bool,
+    /// Snap to the centers of the cells.
+    pub centers: bool,
+    /// Snap to the middle of the edges of the cells.
+    pub edges: bool,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self {
+            shape: GridShape::Square,
+            cell_size: 100.0,
+            offset: Vec2::ZERO,
+            targets: SnapTargets::default(),
+            rotation_step: Some(std::f32::consts::FRAC_PI_4),
+        }
+    }
+}
+
+impl Default for SnapTargets {
+    fn default() -> Self {
+        Self {
+            corners: true,
+            centers: true,
+            edges: false,
+        }
+    }
+}
+
+impl SnapTargets {
+    /// Whether any target is enabled.
+    #[must_use]
+    pub fn any(self) -> bool {
+        self.corners || self.centers || self.edges
+    }
+}
+
+impl Grid {
+    /// Creates a grid of `cell_size` with the default snapping.
+    #[must_use]
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            ..Self::default()
+        }
+    }
+
+    /// Makes a grid of `shape` cells instead of squares.
+    #[must_use]
+    pub fn with_shape(mut self, shape: GridShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Moves `position` to the nearest enabled [`SnapTargets`].
+    ///
+    /// Returns `position` as is when no target is enabled or the cell size isn't positive.
+    #[must_use]
+    pub fn snap_position(&self, position: Vec2) -> Vec2 {
+        if !self.targets.any() || self.cell_size <= 0.0 {
+            return position;
+        }
+        if self.shape != GridShape::Square {
+            return self.snap_hex_position(position);
+        }
+
+        // In cells, relative to a corner.
+        let cell = (position - self.offset) / self.cell_size;
+        let mut candidates = Vec::with_capacity(4);
+        if self.targets.corners {
+            candidates.push(cell.round());
+        }
+        if self.targets.centers {
+            candidates.push(cell.floor() + 0.5);
+        }
+        if self.targets.edges {
+            candidates.push(Vec2::new(cell.x.round(), cell.y.floor() + 0.5));
+            candidates.push(Vec2::new(cell.x.floor() + 0.5, cell.y.round()));
+        }
+
+        candidates
+            .into_iter()
+            .min_by(|a, b| {
+                a.distance_squared(cell)
+                    .total_cmp(&b.distance_squared(cell))
+            })
+            .map_or(position, |nearest| nearest * self.cell_size + self.offset)
+    }
+
+    /// The center of the cell containing `position`.
+    #[must_use]
+    pub fn cell_center(&self, position: Vec2) -> Vec2 {
+        let size = self.cell_size.max(f32::EPSILON);
+        if self.shape == GridShape::Square {
+            return ((position - self.offset) / size).floor() * size + size / 2.0 + self.offset;
+        }
+
+        let local = self.pointy_space(position - self.offset) / size;
+        // Axial coordinates of a pointy grid whose neighbouring centers are one unit apart.
+        let row = local.y * 2.0 / 3.0_f32.sqrt();
+        let column = local.x - row / 2.0;
+        let (column, row) = round_hex(column, row);
+        let center = Vec2::new(column + row / 2.0, row * 3.0_f32.sqrt() / 2.0) * size;
+
+        self.grid_space(center) + self.offset
+    }
+
+    /// The distance from `position` to the nearest edge of its cell, in world units.
+    #[must_use]
+    pub fn edge_distance(&self, position: Vec2) -> f32 {
+        let from_center = position - self.cell_center(position);
+        let half = self.cell_size.max(0.0) / 2.0;
+        if self.shape == GridShape::Square {
+            return half - from_center.x.abs().max(from_center.y.abs());
+        }
+
+        // The flat sides of the hexagon face its neighbours, along three directions.
+        let local = self.pointy_space(from_center);
+        let farthest = hex_directions()
+            .into_iter()
+            .map(|direction| local.dot(direction).abs())
+            .fold(0.0, f32::max);
+        half - farthest
+    }
+
+    /// The size of the smallest rectangle whose copies tile the grid, with a cell corner of a
+    /// square grid or a cell center of a hexagonal grid at each of its corners.
+    #[must_use]
+    pub fn tile_size(&self) -> Vec2 {
+        let size = self.cell_size.max(f32::EPSILON);
+        match self.shape {
+            GridShape::Square => Vec2::splat(size),
+            GridShape::PointyHex => Vec2::new(size, size * 3.0_f32.sqrt()),
+            GridShape::FlatHex => Vec2::new(size * 3.0_f32.sqrt(), size),
+        }
+    }
+
+    /// Moves `position` to the nearest enabled [`SnapTargets`] of a hexagonal grid: the center,
+    /// the corners and the middle of the sides of its cell.
+    fn snap_hex_position(&self, position: Vec2) -> Vec2 {
+        let center = self.cell_center(position);
+        let radius = self.cell_size / 3.0_f32.sqrt();
+        let mut candidates = Vec::with_capacity(13);
+        if self.targets.centers {
+            candidates.push(center);
+        }
+        for direction in hex_directions() {
+            let direction = self.grid_space(direction);
+            if self.targets.edges {
+                candidates.push(center + direction * self.cell_size / 2.0);
+                candidates.push(center - direction * self.cell_size / 2.0);
+            }
+            if self.targets.corners {
+                // The corners are between the sides, rotated by a twelfth of a turn.
+                let corner = Vec2::from_angle(std::f32::consts::FRAC_PI_6).rotate(direction);
+                candidates.push(center + corner * radius);
+                candidates.push(center - corner * radius);
+            }
+        }
+
+        candidates
+            .into_iter()
+            .min_by(|a, b| {
+                a.distance_squared(position)
+                    .total_cmp(&b.distance_squared(position))
+            })
+            .unwrap_or(position)
+    }
+
+    /// Turns `vector` into the space of a pointy grid, swapping its axes for a flat grid.
+    fn pointy_space(&self, vector: Vec2) -> Vec2 {
+        match self.shape {
+            GridShape::FlatHex => vector.yx(),
+            GridShape::Square | GridShape::PointyHex => vector,
+        }
+    }
+
+    /// Turns `vector` back from the space of a pointy grid.
+    fn grid_space(&self, vector: Vec2) -> Vec2 {
+        self.pointy_space(vector)
+    }
+
+    /// Rounds the rotation of `rotation` around the Z axis to the nearest multiple of
+    /// [`Grid::rotation_step`].
+    #[must_use]
+    pub fn snap_rotation(&self, rotation: Quat) -> Quat {
+        let Some(step) = self.rotation_step.filter(|step| *step > 0.0) else {
+            return rotation;
+        };
+
+        let (angle, _, _) = rotation.to_euler(EulerRot::ZYX);
+        Quat::from_rotation_z((angle / step).round() * step)
+    }
+
+    /// Snaps the position and rotation of `transform`, keeping its depth and scale.
+    #[must_use]
+    pub fn snap(&self, transform: Transform) -> Transform {
+        let position = self.snap_position(transform.translation.truncate());
+        Transform {
+            translation: position.extend(transform.translation.z),
+            rotation: self.snap_rotation(transform.rotation),
+            scale: transform.scale,
+        }
+    }
+}
+
+/// The directions from the center of a pointy hexagon to three of its neighbours, the others
+/// being their opposites.
+fn hex_directions() -> [Vec2; 3] {
+    [
+        Vec2::X,
+        Vec2::from_angle(std::f32::consts::FRAC_PI_3),
+        Vec2::from_angle(2.0 * std::f32::consts::FRAC_PI_3),
+    ]
+}
+
+/// Rounds the axial hex coordinates `column` and `row` to the hexagon containing them.
+fn round_hex(column: f32, row: f32) -> (f32, f32) {
+    // The third cube coordinate keeps the three of them summing to zero.
+    let third = -column - row;
+    let (rounded_column, rounded_row, rounded_third) = (column.round(), row.round(), third.round());
+    let (column_error, row_error, third_error) = (
+        (rounded_column - column).abs(),
+        (rounded_row - row).abs(),
+        (rounded_third - third).abs(),
+    );
+
+    if column_error > row_error && column_error > third_error {
+        (-rounded_row - rounded_third, rounded_row)
+    } else if row_error > third_error {
+        (rounded_column, -rounded_column - rounded_third)
+    } else {
+        (rounded_column, rounded_row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Finds the cells of hexagonal grids.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+
+    /// A hexagonal grid of `shape` with an offset, so the tests don't only pass at the origin.
+    fn hex_grid(shape: GridShape) -> Grid {
+        Grid {
+            offset: Vec2::new(10.0, -5.0),
+            ..Grid::new(64.0).with_shape(shape)
+        }
+    }
+
+    /// The centers of the cells of `grid` around the origin, computed from their axial
+    /// coordinates.
+    fn centers(grid: &Grid) -> Vec<Vec2> {
+        let mut centers = Vec::new();
+        for column in -3..=3_i8 {
+            for row in -3..=3_i8 {
+                let (column, row) = (f32::from(column), f32::from(row));
+                let pointy = Vec2::new(column + row / 2.0, row * 3.0_f32.sqrt() / 2.0);
+                centers.push(grid.grid_space(pointy * grid.cell_size) + grid.offset);
+            }
+        }
+
+        centers
+    }
+
+    /// Every point of a cell, up to its sides, finds the center of that cell, which finds itself.
+    #[test]
+    fn hex_cell_centers_round_trip() {
+        for shape in [GridShape::PointyHex, GridShape::FlatHex] {
+            let grid = hex_grid(shape);
+            for center in centers(&grid) {
+                assert!(
+                    grid.cell_center(center).abs_diff_eq(center, 1e-3),
+                    "{shape:?}: {center} isn't its own center"
+                );
+
+                for direction in hex_directions() {
+                    let direction = grid.grid_space(direction) * grid.cell_size * 0.45;
+                    for point in [center + direction, center - direction] {
+                        assert!(
+                            grid.cell_center(point).abs_diff_eq(center, 1e-3),
+                            "{shape:?}: {point} isn't in the cell of {center}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// The center of a hexagon is half a cell from its sides, and positions snap to it.
+    #[test]
+    fn snaps_to_hex_centers() {
+        for shape in [GridShape::PointyHex, GridShape::FlatHex] {
+            let grid = Grid {
+                targets: SnapTargets {
+                    corners: false,
+                    centers: true,
+                    edges: false,
+                },
+                ..hex_grid(shape)
+            };
+            for center in centers(&grid) {
+                assert!((grid.edge_distance(center) - 32.0).abs() < 1e-3);
+
+                let nearby = center + Vec2::new(7.0, -9.0);
+                assert!(grid.snap_position(nearby).abs_diff_eq(center, 1e-3));
+            }
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! Contains the [`Group`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+
+/// Elements on a [`Layer`](crate::Layer) that are moved, rotated and scaled as one, such as an
+/// instance of a prefab. Its children are the grouped [`Element`](crate::Element)s, positioned
+/// relative to the group's [`Transform`].
+#[derive(Component, Reflect, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct Group {
+    /// The name shown to the user, such as the name of the prefab the group was placed from.
+    pub name: String,
+}
+
+impl Group {
+    /// Creates a group named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
@@ -0,0 +1,18 @@
+//! Contains the [`PersistentId`] component.
+
+use bevy::asset::uuid::Uuid;
+use bevy::prelude::*;
+
+/// Identifies a node of the project hierarchy across saves.
+///
+/// Unlike [`Entity`], which is only meaningful for the lifetime of the world, the id is stored in
+/// the save file and restored with the node. Nodes spawned without one get a random id.
+#[derive(Component, Reflect, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub struct PersistentId(pub Uuid);
+
+impl Default for PersistentId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
@@ -0,0 +1,69 @@
+//! Contains the [`Label`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+/// A piece of text placed on the map, such as the name of a town or a room number.
+///
+/// Labels are children of a [`Layer`](crate::Layer), like [`Element`](crate::Element)s, and are
+/// positioned and rotated by their [`Transform`].
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct Label {
+    /// The text shown on the map.
+    pub text: String,
+    /// The font size, in world units.
+    pub size: f32,
+    /// The path of the font asset the text is written in, the default font when `None`.
+    pub font: Option<PathBuf>,
+    /// The colour of the text.
+    pub color: Color,
+    /// The outline drawn around the letters, so the text stays readable on busy maps.
+    pub outline: Option<LabelOutline>,
+}
+
+/// The outline around the letters of a [`Label`].
+#[derive(Reflect, Debug, Copy, Clone, PartialEq)]
+pub struct LabelOutline {
+    /// The colour of the outline.
+    pub color: Color,
+    /// The width of the outline, in world units.
+    pub width: f32,
+}
+
+impl Label {
+    /// Creates a label showing `text` in black at font `size`, in the default font and without
+    /// outline.
+    pub fn new(text: impl Into<String>, size: f32) -> Self {
+        Self {
+            text: text.into(),
+            size,
+            font: None,
+            color: Color::BLACK,
+            outline: None,
+        }
+    }
+
+    /// Writes the text in the font asset at `font`.
+    #[must_use]
+    pub fn with_font(mut self, font: impl Into<PathBuf>) -> Self {
+        self.font = Some(font.into());
+        self
+    }
+
+    /// Writes the text in `color`.
+    #[must_use]
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Outlines the letters with `width` world units of `color`.
+    #[must_use]
+    pub fn with_outline(mut self, color: Color, width: f32) -> Self {
+        self.outline = Some(LabelOutline { color, width });
+        self
+    }
+}
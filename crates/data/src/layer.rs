@@ -0,0 +1,54 @@
+//! Contains the [`Layer`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+
+/// A group of elements drawn together, its children are the layer's [`Element`](crate::Element)s.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct Layer {
+    /// The name shown to the user.
+    pub name: String,
+    /// Whether the contents of the layer are protected from being selected and edited, for
+    /// example for a reference image being traced over.
+    pub locked: bool,
+    /// Whether the layer is hidden from the viewport and the exports, such as a layer of notes
+    /// for the GM.
+    pub hidden: bool,
+    /// The opacity the contents of the layer are drawn with, between `0.0` and `1.0`.
+    pub opacity: f32,
+}
+
+impl Layer {
+    /// Creates an unlocked, visible and fully opaque layer named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            locked: false,
+            hidden: false,
+            opacity: 1.0,
+        }
+    }
+
+    /// Sets whether the layer is locked.
+    #[must_use]
+    pub fn with_locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+
+    /// Sets whether the layer is hidden.
+    #[must_use]
+    pub fn with_hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Sets the opacity of the layer, clamped between `0.0` and `1.0`.
+    #[must_use]
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self
+    }
+}
@@ -0,0 +1,20 @@
+//! Contains the [`Level`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+
+/// A single map within a project, its children are the level's [`Layer`](crate::Layer)s.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct Level {
+    /// The name shown to the user.
+    pub name: String,
+}
+
+impl Level {
+    /// Creates a level named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
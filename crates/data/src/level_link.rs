@@ -0,0 +1,58 @@
+//! Contains the [`LevelLink`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+
+/// How a [`LevelLink`] takes tokens from one level to the other.
+#[derive(Reflect, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum LinkKind {
+    /// A staircase, walked up or down.
+    #[default]
+    Stairs,
+    /// A ladder, climbed up or down.
+    Ladder,
+    /// A teleporter, such as a magic circle or a portal.
+    Teleporter,
+}
+
+impl LinkKind {
+    /// The name of the kind in lowercase, as written by exports.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Stairs => "stairs",
+            Self::Ladder => "ladder",
+            Self::Teleporter => "teleporter",
+        }
+    }
+}
+
+/// Connects a point of a [`Level`](crate::Level) to a point of another level, such as the stairs
+/// leading down to the cellar.
+///
+/// Links are children of the level they start on, next to its [`Layer`](crate::Layer)s, and
+/// start at the origin of their [`Transform`]. Virtual tabletop exports write them as portals, so
+/// tokens can be moved to the other level.
+#[derive(Component, Reflect, Debug, Copy, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct LevelLink {
+    /// How the link takes tokens to the other level.
+    pub kind: LinkKind,
+    /// The [`PersistentId`] of the level the link leads to.
+    pub level: PersistentId,
+    /// Where the link arrives on the other level, relative to that level.
+    pub destination: Vec2,
+}
+
+impl LevelLink {
+    /// Creates a link of `kind` arriving at `destination` on the level identified by `level`.
+    #[must_use]
+    pub fn new(kind: LinkKind, level: PersistentId, destination: Vec2) -> Self {
+        Self {
+            kind,
+            level,
+            destination,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+#![doc = include_str!("../README.md")]
+
+mod animated_texture;
+mod background;
+mod element;
+mod grid;
+mod group;
+mod id;
+mod label;
+mod layer;
+mod level;
+mod level_link;
+mod light;
+mod plugin;
+mod portal;
+mod project;
+mod projection;
+mod region;
+mod scale;
+mod shape;
+mod snapshot;
+mod terrain;
+mod wall;
+mod wall_path;
+
+pub use animated_texture::{AnimatedTexture, AnimationFrames, AnimationPlayback};
+pub use background::LevelBackground;
+pub use element::Element;
+pub use grid::{Grid, GridShape, SnapTargets};
+pub use group::Group;
+pub use id::PersistentId;
+pub use label::{Label, LabelOutline};
+pub use layer::Layer;
+pub use level::Level;
+pub use level_link::{LevelLink, LinkKind};
+pub use light::{LevelLighting, LightSource};
+pub use plugin::DataPlugin;
+pub use portal::Portal;
+pub use project::Project;
+pub use projection::MapProjection;
+pub use region::Region;
+pub use scale::{DistanceUnit, MapScale};
+pub use shape::{Shape, ShapeKind, ShapeStroke};
+pub use snapshot::{GroupNode, HierarchySnapshot, LayerNode, LevelNode, ProjectNode};
+pub use terrain::Terrain;
+pub use wall::Wall;
+pub use wall_path::WallPath;
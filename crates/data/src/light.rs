@@ -0,0 +1,80 @@
+//! Contains the [`LightSource`] and [`LevelLighting`] components.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+
+/// A light illuminating the map around its [`Transform`], such as a torch or a campfire.
+///
+/// Light sources are children of a [`Layer`](crate::Layer), like [`Element`](crate::Element)s.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct LightSource {
+    /// The distance the light reaches, in world units.
+    pub radius: f32,
+    /// The colour of the light.
+    pub color: Color,
+    /// The brightness of the light, `1.0` being fully lit.
+    pub intensity: f32,
+    /// How much the brightness varies over time like a flame, from `0.0` for a steady light to
+    /// `1.0` for a light flickering down to darkness.
+    pub flicker: f32,
+}
+
+impl LightSource {
+    /// Creates a white light reaching `radius` at full intensity.
+    #[must_use]
+    pub fn new(radius: f32) -> Self {
+        Self {
+            radius,
+            color: Color::WHITE,
+            intensity: 1.0,
+            flicker: 0.0,
+        }
+    }
+
+    /// Makes the light flicker by `flicker`, see [`LightSource::flicker`].
+    #[must_use]
+    pub fn with_flicker(mut self, flicker: f32) -> Self {
+        self.flicker = flicker;
+        self
+    }
+}
+
+/// The darkness of a [`Level`](crate::Level) that its [`LightSource`]s light up, such as for a
+/// night-time map.
+///
+/// Levels without it are fully lit, their light sources then have no effect.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+pub struct LevelLighting {
+    /// How dark the unlit parts of the level are, from `0.0` for daylight to `1.0` for pitch
+    /// black.
+    pub darkness: f32,
+    /// The colour of the darkness, such as a deep blue for moonlight.
+    pub ambient: Color,
+}
+
+impl Default for LevelLighting {
+    fn default() -> Self {
+        Self::new(0.0)
+    }
+}
+
+impl LevelLighting {
+    /// Darkens the level by `darkness` in black.
+    #[must_use]
+    pub fn new(darkness: f32) -> Self {
+        Self {
+            darkness,
+            ambient: Color::BLACK,
+        }
+    }
+
+    /// Tints the darkness with `ambient`.
+    #[must_use]
+    pub fn with_ambient(mut self, ambient: Color) -> Self {
+        self.ambient = ambient;
+        self
+    }
+}
@@ -0,0 +1,44 @@
+//! Contains the [`DataPlugin`].
+
+use crate::snapshot::{HierarchySnapshot, update_hierarchy_snapshot};
+use crate::{
+    AnimatedTexture, Element, Grid, Group, Label, Layer, Level, LevelBackground, LevelLighting,
+    LevelLink, LightSource, MapProjection, MapScale, PersistentId, Portal, Project, Region, Shape,
+    Terrain, Wall, WallPath,
+};
+use bevy::prelude::{App, Plugin, PostUpdate};
+
+/// Registers the project components, the [`Grid`], the [`MapProjection`] and the [`MapScale`], and
+/// keeps the [`HierarchySnapshot`] up to date.
+pub struct DataPlugin;
+
+impl Plugin for DataPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Project>()
+            .register_type::<Level>()
+            .register_type::<Layer>()
+            .register_type::<Element>()
+            .register_type::<AnimatedTexture>()
+            .register_type::<Group>()
+            .register_type::<Label>()
+            .register_type::<Shape>()
+            .register_type::<Wall>()
+            .register_type::<WallPath>()
+            .register_type::<Terrain>()
+            .register_type::<Portal>()
+            .register_type::<LightSource>()
+            .register_type::<LevelLighting>()
+            .register_type::<LevelBackground>()
+            .register_type::<Region>()
+            .register_type::<LevelLink>()
+            .register_type::<PersistentId>()
+            .register_type::<Grid>()
+            .init_resource::<Grid>()
+            .register_type::<MapProjection>()
+            .init_resource::<MapProjection>()
+            .register_type::<MapScale>()
+            .init_resource::<MapScale>()
+            .init_resource::<HierarchySnapshot>()
+            .add_systems(PostUpdate, update_hierarchy_snapshot);
+    }
+}
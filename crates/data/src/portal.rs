@@ -0,0 +1,27 @@
+//! Contains the [`Portal`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+
+/// An opening in a wall that can be opened and closed, such as a door or a window.
+///
+/// Portals are children of a [`Layer`](crate::Layer), like [`Element`](crate::Element)s.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct Portal {
+    /// One end of the portal, relative to its [`Transform`].
+    pub start: Vec2,
+    /// The other end of the portal, relative to its [`Transform`].
+    pub end: Vec2,
+    /// Whether the portal blocks line of sight.
+    pub closed: bool,
+}
+
+impl Portal {
+    /// Creates a portal from `start` to `end`.
+    #[must_use]
+    pub fn new(start: Vec2, end: Vec2, closed: bool) -> Self {
+        Self { start, end, closed }
+    }
+}
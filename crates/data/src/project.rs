@@ -0,0 +1,20 @@
+//! Contains the [`Project`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+
+/// The root of a project, its children are the project's [`Level`](crate::Level)s.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct Project {
+    /// The name shown to the user.
+    pub name: String,
+}
+
+impl Project {
+    /// Creates a project named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
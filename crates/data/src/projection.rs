@@ -0,0 +1,91 @@
+//! Contains the [`MapProjection`] the levels are viewed through.
+
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_4;
+
+/// How the map is projected onto the screen, as seen from above or in isometric view.
+///
+/// The map itself is always laid out as seen from above: positions, the [`Grid`](crate::Grid)
+/// and snapping are in map units whatever the projection. Cameras apply the projection when
+/// drawing the map, so the same hierarchy makes both a top-down and an isometric battle map.
+#[derive(Resource, Reflect, Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[reflect(Resource)]
+pub enum MapProjection {
+    /// The map is seen straight from above.
+    #[default]
+    TopDown,
+    /// The map is turned an eighth of a turn and squashed to half its height, the 2:1 isometric
+    /// view of pixel art and most isometric assets.
+    Isometric,
+}
+
+impl MapProjection {
+    /// Where the map position `position` is drawn on the screen, in world units.
+    #[must_use]
+    pub fn project(self, position: Vec2) -> Vec2 {
+        match self {
+            Self::TopDown => position,
+            Self::Isometric => {
+                let turned = Vec2::from_angle(FRAC_PI_4).rotate(position);
+                Vec2::new(turned.x, turned.y / 2.0)
+            }
+        }
+    }
+
+    /// The map position drawn at `position` on the screen, the opposite of
+    /// [`MapProjection::project`].
+    ///
+    /// Positions of the cursor are turned into map positions this way, so they snap to the grid
+    /// as seen on screen.
+    #[must_use]
+    pub fn unproject(self, position: Vec2) -> Vec2 {
+        match self {
+            Self::TopDown => position,
+            Self::Isometric => {
+                Vec2::from_angle(-FRAC_PI_4).rotate(Vec2::new(position.x, position.y * 2.0))
+            }
+        }
+    }
+
+    /// The smallest area of the screen showing the map area `area`.
+    #[must_use]
+    pub fn project_rect(self, area: Rect) -> Rect {
+        bounds(corners(area).map(|corner| self.project(corner)))
+    }
+
+    /// The smallest area of the map containing everything drawn in the screen area `area`.
+    #[must_use]
+    pub fn unproject_rect(self, area: Rect) -> Rect {
+        bounds(corners(area).map(|corner| self.unproject(corner)))
+    }
+
+    /// The rotation and scale of a camera drawing the map through the projection.
+    ///
+    /// A camera's view undoes its transform, so the camera is turned and stretched the opposite
+    /// way the map is.
+    #[must_use]
+    pub fn camera_transform(self) -> (Quat, Vec3) {
+        match self {
+            Self::TopDown => (Quat::IDENTITY, Vec3::ONE),
+            Self::Isometric => (Quat::from_rotation_z(-FRAC_PI_4), Vec3::new(1.0, 2.0, 1.0)),
+        }
+    }
+}
+
+/// The four corners of `area`.
+fn corners(area: Rect) -> [Vec2; 4] {
+    [
+        area.min,
+        Vec2::new(area.max.x, area.min.y),
+        area.max,
+        Vec2::new(area.min.x, area.max.y),
+    ]
+}
+
+/// The smallest rectangle containing `points`.
+fn bounds(points: [Vec2; 4]) -> Rect {
+    let min = points.into_iter().reduce(Vec2::min).unwrap_or_default();
+    let max = points.into_iter().reduce(Vec2::max).unwrap_or_default();
+
+    Rect::from_corners(min, max)
+}
@@ -0,0 +1,70 @@
+//! Contains the [`Region`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+
+/// A polygonal area of a [`Level`](crate::Level) that can be hidden from the players, such as a
+/// secret room or the parts of a dungeon that weren't explored yet.
+///
+/// Regions are children of a level, next to its [`Layer`](crate::Layer)s. Hidden regions are
+/// covered in the player version of an exported map, the GM version shows everything.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct Region {
+    /// The corners of the region, relative to its [`Transform`].
+    pub points: Vec<Vec2>,
+    /// Whether the region is hidden from the players.
+    pub hidden: bool,
+    /// The colour covering the region where it's hidden.
+    pub cover: Color,
+}
+
+impl Region {
+    /// Creates a region hidden from the players, blacked out along `points`.
+    pub fn new(points: impl Into<Vec<Vec2>>) -> Self {
+        Self {
+            points: points.into(),
+            hidden: true,
+            cover: Color::BLACK,
+        }
+    }
+
+    /// Sets whether the region is hidden from the players.
+    #[must_use]
+    pub fn with_hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Covers the region with `cover` instead of black.
+    #[must_use]
+    pub fn with_cover(mut self, cover: Color) -> Self {
+        self.cover = cover;
+        self
+    }
+
+    /// Returns whether `point`, relative to the region's [`Transform`], is inside the region.
+    ///
+    /// Regions whose outline crosses itself follow the even-odd rule.
+    #[must_use]
+    pub fn contains(&self, point: Vec2) -> bool {
+        let mut inside = false;
+        let mut previous = match self.points.last() {
+            Some(last) => *last,
+            None => return false,
+        };
+        for &current in &self.points {
+            if (current.y > point.y) != (previous.y > point.y)
+                && point.x
+                    < (previous.x - current.x) * (point.y - current.y) / (previous.y - current.y)
+                        + current.x
+            {
+                inside = !inside;
+            }
+            previous = current;
+        }
+
+        inside
+    }
+}
@@ -0,0 +1,99 @@
+//! Contains the [`MapScale`] converting world units into real-world distances.
+
+use crate::Grid;
+use bevy::prelude::*;
+
+/// A unit distances and areas on the map are measured in.
+#[derive(Reflect, Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum DistanceUnit {
+    /// Imperial feet.
+    #[default]
+    Feet,
+    /// Metres.
+    Metres,
+    /// Grid cells, the size of a cell being the unit.
+    Squares,
+}
+
+impl DistanceUnit {
+    /// The short symbol written after values in this unit, such as `ft`.
+    #[must_use]
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Feet => "ft",
+            Self::Metres => "m",
+            Self::Squares => "sq",
+        }
+    }
+
+    /// The length of this unit in metres, `None` for grid cells.
+    fn metres(self) -> Option<f32> {
+        match self {
+            Self::Feet => Some(0.3048),
+            Self::Metres => Some(1.0),
+            Self::Squares => None,
+        }
+    }
+}
+
+/// The real-world distance a cell of the [`Grid`] stands for, such as the usual 5 feet of tabletop
+/// battle maps.
+///
+/// Distances in world units are converted through the cell size of the grid, so resizing the grid
+/// rescales the whole map.
+#[derive(Resource, Reflect, Debug, Copy, Clone, PartialEq)]
+#[reflect(Resource)]
+pub struct MapScale {
+    /// The distance across a grid cell, in `unit`.
+    pub cell_distance: f32,
+    /// The unit of `cell_distance`.
+    pub unit: DistanceUnit,
+}
+
+impl Default for MapScale {
+    fn default() -> Self {
+        Self::new(5.0, DistanceUnit::Feet)
+    }
+}
+
+impl MapScale {
+    /// Creates a scale where a grid cell is `cell_distance` `unit` across.
+    #[must_use]
+    pub fn new(cell_distance: f32, unit: DistanceUnit) -> Self {
+        Self {
+            cell_distance,
+            unit,
+        }
+    }
+
+    /// Converts `distance`, in world units, into `unit` on the cells of `grid`.
+    ///
+    /// A scale in [`DistanceUnit::Squares`] can't be converted into feet or metres, such
+    /// distances are given in squares instead. Returns `0.0` when the cell size of `grid` isn't
+    /// positive.
+    #[must_use]
+    pub fn distance(&self, grid: &Grid, distance: f32, unit: DistanceUnit) -> f32 {
+        if grid.cell_size <= 0.0 {
+            return 0.0;
+        }
+
+        distance / grid.cell_size * self.cell_factor(unit)
+    }
+
+    /// Converts `area`, in square world units, into square `unit` on the cells of `grid`.
+    ///
+    /// See [`MapScale::distance`].
+    #[must_use]
+    pub fn area(&self, grid: &Grid, area: f32, unit: DistanceUnit) -> f32 {
+        let side = self.distance(grid, 1.0, unit);
+        area * side * side
+    }
+
+    /// The distance across a grid cell in `unit`.
+    fn cell_factor(self, unit: DistanceUnit) -> f32 {
+        match (self.unit.metres(), unit.metres()) {
+            (Some(from), Some(to)) => self.cell_distance * from / to,
+            _ => 1.0,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Contains the [`Shape`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+
+/// The outline of a [`Shape`], relative to its [`Transform`].
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub enum ShapeKind {
+    /// A rectangle centered on the origin. Holds its width and height.
+    Rectangle(Vec2),
+    /// An ellipse centered on the origin. Holds its horizontal and vertical radius.
+    Ellipse(Vec2),
+    /// A closed polygon through its corners, in order.
+    Polygon(Vec<Vec2>),
+    /// An open line through its points, in order. Lines are never filled.
+    Line(Vec<Vec2>),
+}
+
+/// The line drawn along the outline of a [`Shape`].
+#[derive(Reflect, Debug, Copy, Clone, PartialEq)]
+pub struct ShapeStroke {
+    /// The colour of the line.
+    pub color: Color,
+    /// The thickness of the line, in world units.
+    pub width: f32,
+}
+
+/// A vector shape drawn on the map, such as a zone marker, a body of water or the blocked out
+/// outline of a room.
+///
+/// Shapes are children of a [`Layer`](crate::Layer), like [`Element`](crate::Element)s. They're
+/// built into a mesh whenever they change.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct Shape {
+    /// The outline of the shape.
+    pub kind: ShapeKind,
+    /// The colour filling the shape, not filled when `None`.
+    pub fill: Option<Color>,
+    /// The line along the outline, not outlined when `None`.
+    pub stroke: Option<ShapeStroke>,
+    /// The opacity of the whole shape, from `0.0` for invisible to `1.0` for opaque.
+    pub opacity: f32,
+}
+
+impl Shape {
+    /// Creates an opaque shape of `kind`, neither filled nor outlined.
+    #[must_use]
+    pub fn new(kind: ShapeKind) -> Self {
+        Self {
+            kind,
+            fill: None,
+            stroke: None,
+            opacity: 1.0,
+        }
+    }
+
+    /// Fills the shape with `color`.
+    #[must_use]
+    pub fn with_fill(mut self, color: Color) -> Self {
+        self.fill = Some(color);
+        self
+    }
+
+    /// Outlines the shape with a line `width` thick of `color`.
+    #[must_use]
+    pub fn with_stroke(mut self, color: Color, width: f32) -> Self {
+        self.stroke = Some(ShapeStroke { color, width });
+        self
+    }
+
+    /// Draws the shape at `opacity`, see [`Shape::opacity`].
+    #[must_use]
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+}
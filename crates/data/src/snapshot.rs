@@ -0,0 +1,215 @@
+//! Contains the [`HierarchySnapshot`], a cached view of the project hierarchies.
+//!
+//! The layers panel, the minimap, autosave, the exporters, the selection and the clipboard all
+//! need to walk the hierarchy. Doing so through queries means chasing [`Children`] for every node
+//! each frame, even though the hierarchy rarely changes. The snapshot is rebuilt only when a node
+//! is added, removed, renamed or reparented, and can be read cheaply in between.
+
+use crate::{Element, Group, Label, Layer, Level, Project};
+use bevy::prelude::*;
+
+/// A cached copy of the structure of every project in the world.
+///
+/// Only the structure is captured: transforms and other frequently changing data have to be
+/// queried from the entities. Use `Res::is_changed` to find out whether the snapshot was rebuilt.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct HierarchySnapshot {
+    /// The projects in the world, ordered by entity.
+    pub projects: Vec<ProjectNode>,
+}
+
+/// A [`Project`] in the [`HierarchySnapshot`].
+#[derive(Debug, Clone)]
+pub struct ProjectNode {
+    /// The project entity.
+    pub entity: Entity,
+    /// The name of the project.
+    pub name: String,
+    /// The levels of the project, in order.
+    pub levels: Vec<LevelNode>,
+}
+
+/// A [`Level`] in the [`HierarchySnapshot`].
+#[derive(Debug, Clone)]
+pub struct LevelNode {
+    /// The level entity.
+    pub entity: Entity,
+    /// The name of the level.
+    pub name: String,
+    /// The layers of the level, in drawing order.
+    pub layers: Vec<LayerNode>,
+}
+
+/// A [`Layer`] in the [`HierarchySnapshot`].
+#[derive(Debug, Clone)]
+pub struct LayerNode {
+    /// The layer entity.
+    pub entity: Entity,
+    /// The name of the layer.
+    pub name: String,
+    /// The element entities on the layer, in order.
+    pub elements: Vec<Entity>,
+    /// The label entities on the layer, in order.
+    pub labels: Vec<Entity>,
+    /// The groups on the layer, in order.
+    pub groups: Vec<GroupNode>,
+}
+
+impl LayerNode {
+    /// Iterates over the elements on the layer, the elements of its groups following the elements
+    /// directly on the layer.
+    pub fn all_elements(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.elements.iter().copied().chain(
+            self.groups
+                .iter()
+                .flat_map(|group| group.elements.iter().copied()),
+        )
+    }
+}
+
+/// A [`Group`] in the [`HierarchySnapshot`].
+#[derive(Debug, Clone)]
+pub struct GroupNode {
+    /// The group entity.
+    pub entity: Entity,
+    /// The name of the group.
+    pub name: String,
+    /// The element entities of the group, in order.
+    pub elements: Vec<Entity>,
+}
+
+impl HierarchySnapshot {
+    /// Iterates over every layer of every project.
+    pub fn layers(&self) -> impl Iterator<Item = &LayerNode> {
+        self.levels().flat_map(|level| &level.layers)
+    }
+
+    /// Iterates over every level of every project.
+    pub fn levels(&self) -> impl Iterator<Item = &LevelNode> {
+        self.projects.iter().flat_map(|project| &project.levels)
+    }
+
+    /// Returns the level `entity`.
+    #[must_use]
+    pub fn level(&self, entity: Entity) -> Option<&LevelNode> {
+        self.levels().find(|level| level.entity == entity)
+    }
+
+    /// Iterates over every element of every project, see [`LayerNode::all_elements`].
+    pub fn elements(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.layers().flat_map(LayerNode::all_elements)
+    }
+
+    /// Iterates over every group of every project.
+    pub fn groups(&self) -> impl Iterator<Item = &GroupNode> {
+        self.layers().flat_map(|layer| &layer.groups)
+    }
+}
+
+/// Matches the nodes whose change affects the structure of the hierarchy.
+type StructureChanged = Or<(
+    Changed<Project>,
+    Changed<Level>,
+    Changed<Layer>,
+    Changed<Group>,
+    Added<Element>,
+    Added<Label>,
+    Changed<Children>,
+    Changed<ChildOf>,
+)>;
+
+/// Rebuilds the [`HierarchySnapshot`] when the structure of a hierarchy changed.
+#[allow(
+    clippy::too_many_arguments,
+    reason = "each node kind needs its own removal reader"
+)]
+pub(crate) fn update_hierarchy_snapshot(
+    mut snapshot: ResMut<HierarchySnapshot>,
+    changed: Query<(), StructureChanged>,
+    mut removed_projects: RemovedComponents<Project>,
+    mut removed_levels: RemovedComponents<Level>,
+    mut removed_layers: RemovedComponents<Layer>,
+    mut removed_groups: RemovedComponents<Group>,
+    mut removed_elements: RemovedComponents<Element>,
+    mut removed_labels: RemovedComponents<Label>,
+    projects: Query<(Entity, &Project, Option<&Children>)>,
+    levels: Query<(&Level, Option<&Children>)>,
+    layers: Query<(&Layer, Option<&Children>)>,
+    groups: Query<(&Group, Option<&Children>)>,
+    elements: Query<(), With<Element>>,
+    labels: Query<(), With<Label>>,
+) {
+    // Every reader has to be drained, otherwise the removals are seen again next frame.
+    let removed = removed_projects.read().count()
+        + removed_levels.read().count()
+        + removed_layers.read().count()
+        + removed_groups.read().count()
+        + removed_elements.read().count()
+        + removed_labels.read().count();
+    if removed == 0 && changed.is_empty() {
+        return;
+    }
+
+    let children = |children: Option<&Children>| {
+        children
+            .map(|children| children.to_vec())
+            .unwrap_or_default()
+    };
+
+    let mut nodes: Vec<ProjectNode> = projects
+        .iter()
+        .map(|(entity, project, project_children)| ProjectNode {
+            entity,
+            name: project.name.clone(),
+            levels: children(project_children)
+                .into_iter()
+                .filter_map(|entity| {
+                    let (level, level_children) = levels.get(entity).ok()?;
+                    Some(LevelNode {
+                        entity,
+                        name: level.name.clone(),
+                        layers: children(level_children)
+                            .into_iter()
+                            .filter_map(|entity| {
+                                let (layer, layer_children) = layers.get(entity).ok()?;
+                                let layer_children = children(layer_children);
+                                Some(LayerNode {
+                                    entity,
+                                    name: layer.name.clone(),
+                                    elements: layer_children
+                                        .iter()
+                                        .copied()
+                                        .filter(|entity| elements.contains(*entity))
+                                        .collect(),
+                                    labels: layer_children
+                                        .iter()
+                                        .copied()
+                                        .filter(|entity| labels.contains(*entity))
+                                        .collect(),
+                                    groups: layer_children
+                                        .into_iter()
+                                        .filter_map(|entity| {
+                                            let (group, group_children) =
+                                                groups.get(entity).ok()?;
+                                            Some(GroupNode {
+                                                entity,
+                                                name: group.name.clone(),
+                                                elements: children(group_children)
+                                                    .into_iter()
+                                                    .filter(|entity| elements.contains(*entity))
+                                                    .collect(),
+                                            })
+                                        })
+                                        .collect(),
+                                })
+                            })
+                            .collect(),
+                    })
+                })
+                .collect(),
+        })
+        .collect();
+    nodes.sort_by_key(|project| project.entity);
+
+    snapshot.projects = nodes;
+}
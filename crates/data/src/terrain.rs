@@ -0,0 +1,90 @@
+//! Contains the [`Terrain`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+/// Ground textures painted onto a layer, blended by a splat map.
+///
+/// Terrains are children of a [`Layer`](crate::Layer), like [`Element`](crate::Element)s. The
+/// splat map holds the weight of each of the (up to [`Terrain::MAX_TEXTURES`]) textures for every
+/// texel, and covers `size` centered on the terrain's [`Transform`].
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct Terrain {
+    /// The area covered by the terrain, in world units.
+    pub size: Vec2,
+    /// The number of texels of the splat map, horizontally and vertically.
+    pub resolution: UVec2,
+    /// The paths of the blended textures, the first one covers the whole terrain initially.
+    pub textures: Vec<PathBuf>,
+    /// The weight of each texture for every texel of the splat map, row by row from the top.
+    pub weights: Vec<[u8; 4]>,
+}
+
+impl Terrain {
+    /// The number of textures a terrain blends.
+    pub const MAX_TEXTURES: usize = 4;
+
+    /// Creates a terrain covering `size` with a splat map of `resolution` texels, entirely
+    /// covered by the first of `textures`.
+    pub fn new(size: Vec2, resolution: UVec2, textures: impl Into<Vec<PathBuf>>) -> Self {
+        let mut textures = textures.into();
+        textures.truncate(Self::MAX_TEXTURES);
+        Self {
+            size,
+            resolution,
+            textures,
+            weights: vec![[u8::MAX, 0, 0, 0]; resolution.element_product() as usize],
+        }
+    }
+
+    /// The texel of the splat map at `position`, relative to the terrain's [`Transform`].
+    ///
+    /// Returns `None` if `position` lies outside the terrain.
+    #[must_use]
+    pub fn texel_at(&self, position: Vec2) -> Option<UVec2> {
+        let uv = self.uv(position);
+        if !(0.0..1.0).contains(&uv.x) || !(0.0..1.0).contains(&uv.y) {
+            return None;
+        }
+
+        Some((uv * self.resolution.as_vec2()).as_uvec2())
+    }
+
+    /// The texels of the splat map overlapping `area`, relative to the terrain's [`Transform`].
+    pub fn texels_in(&self, area: Rect) -> impl Iterator<Item = UVec2> + use<> {
+        let resolution = self.resolution.as_vec2();
+        let start = (self.uv(Vec2::new(area.min.x, area.max.y)) * resolution)
+            .floor()
+            .max(Vec2::ZERO)
+            .as_uvec2();
+        let end = (self.uv(Vec2::new(area.max.x, area.min.y)) * resolution)
+            .ceil()
+            .min(resolution)
+            .as_uvec2();
+
+        (start.y..end.y).flat_map(move |y| (start.x..end.x).map(move |x| UVec2::new(x, y)))
+    }
+
+    /// The center of `texel`, relative to the terrain's [`Transform`].
+    #[must_use]
+    pub fn texel_center(&self, texel: UVec2) -> Vec2 {
+        let uv = (texel.as_vec2() + 0.5) / self.resolution.as_vec2();
+        Vec2::new(uv.x - 0.5, 0.5 - uv.y) * self.size
+    }
+
+    /// The index of `texel` in [`Terrain::weights`].
+    #[must_use]
+    pub fn index(&self, texel: UVec2) -> usize {
+        (texel.y * self.resolution.x + texel.x) as usize
+    }
+
+    /// The position of `position` on the splat map, `(0, 0)` at its top left and `(1, 1)` at its
+    /// bottom right.
+    fn uv(&self, position: Vec2) -> Vec2 {
+        let uv = position / self.size;
+        Vec2::new(uv.x + 0.5, 0.5 - uv.y)
+    }
+}
@@ -0,0 +1,24 @@
+//! Contains the [`Wall`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+
+/// A line blocking movement and line of sight, used by virtual tabletops for dynamic lighting.
+///
+/// Walls are children of a [`Layer`](crate::Layer), like [`Element`](crate::Element)s.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct Wall {
+    /// The points of the wall, relative to its [`Transform`].
+    pub points: Vec<Vec2>,
+}
+
+impl Wall {
+    /// Creates a wall along `points`.
+    pub fn new(points: impl Into<Vec<Vec2>>) -> Self {
+        Self {
+            points: points.into(),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Contains the [`WallPath`] component.
+
+use crate::PersistentId;
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+/// A textured wall drawn along a path, such as the outline of a room.
+///
+/// Wall paths are children of a [`Layer`](crate::Layer), like [`Element`](crate::Element)s. The
+/// texture is repeated along the path, and the path is built into a mesh whenever its control
+/// points change.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+#[require(PersistentId, Transform, Visibility)]
+pub struct WallPath {
+    /// The control points of the path, relative to its [`Transform`].
+    pub points: Vec<Vec2>,
+    /// Whether the last point connects back to the first.
+    pub closed: bool,
+    /// The thickness of the wall, in world units.
+    pub width: f32,
+    /// The path of the wall texture, repeated along the path.
+    pub texture: PathBuf,
+}
+
+impl WallPath {
+    /// Creates an open wall path along `points`, `width` thick and textured with `texture`.
+    pub fn new(points: impl Into<Vec<Vec2>>, width: f32, texture: impl Into<PathBuf>) -> Self {
+        Self {
+            points: points.into(),
+            closed: false,
+            width,
+            texture: texture.into(),
+        }
+    }
+
+    /// Sets whether the last point connects back to the first.
+    #[must_use]
+    pub fn with_closed(mut self, closed: bool) -> Self {
+        self.closed = closed;
+        self
+    }
+}
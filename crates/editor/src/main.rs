@@ -1,7 +1,14 @@
 #![doc = include_str!("../README.md")]
 
 use bevy::prelude::App;
+use dungeonrs_utils::{UtilsPlugin, resources_flag, set_resource_path};
 
 fn main() {
-    App::new().run();
+    if let Some(path) = resources_flag(std::env::args_os().skip(1)) {
+        // Nothing can have set the resource path this early.
+        let _ = set_resource_path(path);
+    }
+
+    // Opening the temporary workspace at startup removes what crashed sessions left behind.
+    App::new().add_plugins(UtilsPlugin).run();
 }
@@ -0,0 +1,58 @@
+//! Contains the [`ExtensionError`] type.
+
+#[cfg(feature = "scripting")]
+use rhai::{EvalAltResult, Position};
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Errors that can occur while loading or running an extension.
+#[derive(Error, Debug)]
+pub enum ExtensionError {
+    /// A file couldn't be read or written.
+    #[error("failed to access '{path}': {source}")]
+    Io {
+        /// The file that couldn't be accessed.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+    /// The manifest or settings file isn't valid.
+    #[error("'{path}' is invalid: {source}")]
+    Invalid {
+        /// The file that couldn't be parsed.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: dungeonrs_serialization::Error,
+    },
+    /// The script failed to compile or raised an error.
+    #[cfg(feature = "scripting")]
+    #[error("script error: {0}")]
+    Script(#[from] Box<EvalAltResult>),
+    /// The script registered a contribution with a function it doesn't define.
+    #[cfg(feature = "scripting")]
+    #[error("the script has no function '{function}' taking {arity} parameters ({position})")]
+    MissingFunction {
+        /// The name of the function.
+        function: String,
+        /// The number of parameters the function should take.
+        arity: usize,
+        /// Where the script registered the contribution.
+        position: Position,
+    },
+    /// Scripts can't be run, the `scripting` feature is disabled.
+    #[cfg(not(feature = "scripting"))]
+    #[error("extensions can't be loaded without the scripting feature")]
+    ScriptingDisabled,
+    /// No extension with this ID was found.
+    #[error("there's no extension '{0}'")]
+    UnknownExtension(String),
+    /// The extension is disabled or its script failed to load.
+    #[error("the extension '{0}' isn't loaded")]
+    NotLoaded(String),
+    /// The extension doesn't register a contribution with this ID, or not of the requested kind.
+    #[error("the extension doesn't provide '{0}'")]
+    UnknownContribution(String),
+}
@@ -0,0 +1,519 @@
+//! Contains the [`Extensions`] resource and the extensions it discovers.
+
+#[cfg(feature = "scripting")]
+use crate::script::Script;
+use crate::{ExtensionError, ExtensionFailed, ExtensionManifest};
+use bevy::prelude::*;
+use dungeonrs_serialization::{Format, deserialize, serialize};
+use dungeonrs_utils::Directory;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What an extension adds to the editor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ContributionKind {
+    /// A toolbar button, running `fn(map)` when clicked.
+    Button,
+    /// A placement tool, running `fn(map, layer, x, y)` where it's used.
+    Tool,
+    /// A batch operation over the hierarchy, running `fn(map)`.
+    Operation,
+}
+
+impl ContributionKind {
+    /// Every kind of contribution.
+    pub const ALL: [ContributionKind; 3] = [
+        ContributionKind::Button,
+        ContributionKind::Tool,
+        ContributionKind::Operation,
+    ];
+
+    /// The function scripts call to register a contribution of this kind.
+    #[must_use]
+    pub const fn register_function(self) -> &'static str {
+        match self {
+            ContributionKind::Button => "register_button",
+            ContributionKind::Tool => "register_tool",
+            ContributionKind::Operation => "register_operation",
+        }
+    }
+
+    /// The number of parameters the function of a contribution of this kind takes.
+    #[must_use]
+    pub const fn arity(self) -> usize {
+        match self {
+            ContributionKind::Button | ContributionKind::Operation => 1,
+            ContributionKind::Tool => 4,
+        }
+    }
+}
+
+/// A button, tool or operation registered by an extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contribution {
+    /// Identifies the contribution within its extension.
+    pub id: String,
+    /// The label shown to the user.
+    pub label: String,
+    /// What the contribution adds to the editor.
+    pub kind: ContributionKind,
+    /// The script function run by the contribution.
+    pub(crate) function: String,
+}
+
+/// An extension found in the extensions directory.
+#[derive(Debug)]
+pub struct Extension {
+    /// The name of the extension's directory, identifying it.
+    id: String,
+    /// The manifest of the extension.
+    manifest: ExtensionManifest,
+    /// Whether the user enabled the extension.
+    enabled: bool,
+    /// The loaded script, `None` when the extension is disabled or failed to load.
+    #[cfg(feature = "scripting")]
+    script: Option<Script>,
+    /// The contributions registered by the script.
+    contributions: Vec<Contribution>,
+}
+
+impl Extension {
+    /// The name of the extension's directory, identifying it.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The manifest of the extension.
+    #[must_use]
+    pub fn manifest(&self) -> &ExtensionManifest {
+        &self.manifest
+    }
+
+    /// Whether the user enabled the extension.
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether the extension's script is loaded, `false` when it's disabled or failed to load.
+    #[must_use]
+    pub fn is_loaded(&self) -> bool {
+        #[cfg(feature = "scripting")]
+        return self.script.is_some();
+        #[cfg(not(feature = "scripting"))]
+        false
+    }
+
+    /// The buttons, tools and operations the extension registered.
+    #[must_use]
+    pub fn contributions(&self) -> &[Contribution] {
+        &self.contributions
+    }
+
+    /// The loaded script.
+    #[cfg(feature = "scripting")]
+    pub(crate) fn script(&self) -> Option<&Script> {
+        self.script.as_ref()
+    }
+
+    /// Loads the script of the extension in `directory`, or only its manifest when it's disabled.
+    ///
+    /// # Errors
+    /// Returns an error if the manifest can't be read. Script errors are returned alongside the
+    /// extension, which is kept so the user can still see and disable it.
+    fn load(
+        id: String,
+        directory: &Path,
+        enabled: bool,
+    ) -> Result<(Self, Option<ExtensionError>), ExtensionError> {
+        let manifest = ExtensionManifest::read(directory)?;
+        let mut extension = Self {
+            id,
+            enabled,
+            #[cfg(feature = "scripting")]
+            script: None,
+            contributions: Vec::new(),
+            manifest,
+        };
+        if !enabled {
+            return Ok((extension, None));
+        }
+
+        let error = extension.load_script(directory).err();
+        Ok((extension, error))
+    }
+
+    /// Loads the entry script of the extension in `directory` and the contributions it registers.
+    ///
+    /// # Errors
+    /// Returns an error if the script fails to load.
+    #[cfg(feature = "scripting")]
+    fn load_script(&mut self, directory: &Path) -> Result<(), ExtensionError> {
+        let (script, contributions) = Script::load(&directory.join(&self.manifest.entry))?;
+        self.script = Some(script);
+        self.contributions = contributions;
+        Ok(())
+    }
+
+    /// Scripts can't be loaded without the `scripting` feature.
+    ///
+    /// # Errors
+    /// Always returns [`ExtensionError::ScriptingDisabled`].
+    #[cfg(not(feature = "scripting"))]
+    #[allow(
+        clippy::unused_self,
+        reason = "mirrors the signature used with the scripting feature"
+    )]
+    fn load_script(&mut self, _directory: &Path) -> Result<(), ExtensionError> {
+        Err(ExtensionError::ScriptingDisabled)
+    }
+
+    /// Reads the manifest of the extension in `directory` and compiles its entry script, without
+    /// running it.
+    ///
+    /// # Errors
+    /// Returns an error if the manifest can't be read or the script doesn't compile.
+    fn check(directory: &Path) -> Result<(), ExtensionError> {
+        let manifest = ExtensionManifest::read(directory)?;
+        #[cfg(feature = "scripting")]
+        return Script::check(&directory.join(&manifest.entry));
+        #[cfg(not(feature = "scripting"))]
+        {
+            let _ = manifest;
+            Err(ExtensionError::ScriptingDisabled)
+        }
+    }
+}
+
+/// The enabled state of the extensions, saved to the settings file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Settings {
+    /// The IDs of the extensions the user disabled.
+    #[serde(default)]
+    disabled: BTreeSet<String>,
+}
+
+/// The extensions installed in the extensions directory.
+///
+/// Every directory containing a [`MANIFEST_FILE`](crate::MANIFEST_FILE) is an extension,
+/// extensions are enabled unless the user disabled them through a
+/// [`SetExtensionEnabled`](crate::SetExtensionEnabled).
+#[derive(Resource, Debug)]
+pub struct Extensions {
+    /// The directory extensions are discovered in.
+    pub directory: PathBuf,
+    /// The file the enabled state of the extensions is saved to.
+    pub settings: PathBuf,
+    /// The discovered extensions, ordered by ID.
+    loaded: Vec<Extension>,
+}
+
+impl Default for Extensions {
+    fn default() -> Self {
+        Self::new(
+            Directory::Data.join("extensions"),
+            Directory::Config.join("extensions.toml"),
+        )
+    }
+}
+
+impl Extensions {
+    /// Discovers extensions in `directory` and saves their enabled state to `settings`, instead
+    /// of the default locations.
+    ///
+    /// Insert it before adding the [`ExtensionsPlugin`](crate::ExtensionsPlugin).
+    pub fn new(directory: impl Into<PathBuf>, settings: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            settings: settings.into(),
+            loaded: Vec::new(),
+        }
+    }
+
+    /// Iterates over the discovered extensions, ordered by ID.
+    pub fn iter(&self) -> impl Iterator<Item = &Extension> {
+        self.loaded.iter()
+    }
+
+    /// Looks up the extension `id`.
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<&Extension> {
+        self.loaded.iter().find(|extension| extension.id == id)
+    }
+
+    /// Iterates over the contributions of `kind` of every loaded extension, with the extension
+    /// registering them.
+    pub fn contributions(
+        &self,
+        kind: ContributionKind,
+    ) -> impl Iterator<Item = (&Extension, &Contribution)> {
+        self.loaded.iter().flat_map(move |extension| {
+            extension
+                .contributions
+                .iter()
+                .filter(move |contribution| contribution.kind == kind)
+                .map(move |contribution| (extension, contribution))
+        })
+    }
+
+    /// Discovers the extensions in the extensions directory (creating it when missing) and loads
+    /// the enabled ones, replacing the extensions loaded before.
+    ///
+    /// Returns the errors of the extensions that failed to load.
+    pub fn reload(&mut self) -> Vec<ExtensionFailed> {
+        self.loaded.clear();
+        let mut failures = Vec::new();
+        let settings = match self.read_settings() {
+            Ok(settings) => settings,
+            Err(error) => {
+                failures.push(ExtensionFailed {
+                    extension: None,
+                    error,
+                });
+                Settings::default()
+            }
+        };
+
+        let directories = fs::create_dir_all(&self.directory)
+            .map_err(|source| ExtensionError::Io {
+                path: self.directory.clone(),
+                source,
+            })
+            .and_then(|()| self.discover());
+        let directories = match directories {
+            Ok(directories) => directories,
+            Err(error) => {
+                failures.push(ExtensionFailed {
+                    extension: None,
+                    error,
+                });
+                return failures;
+            }
+        };
+
+        for (id, directory) in directories {
+            let enabled = !settings.disabled.contains(&id);
+            match Extension::load(id.clone(), &directory, enabled) {
+                Ok((extension, error)) => {
+                    failures.extend(error.map(|error| ExtensionFailed {
+                        extension: Some(id),
+                        error,
+                    }));
+                    self.loaded.push(extension);
+                }
+                Err(error) => failures.push(ExtensionFailed {
+                    extension: Some(id),
+                    error,
+                }),
+            }
+        }
+
+        failures
+    }
+
+    /// Checks every extension in the extensions directory, including disabled ones, without
+    /// loading them, for example to check extensions in CI before shipping them.
+    ///
+    /// Every manifest is parsed and every entry script is compiled, but never run, and nothing is
+    /// written to disk. Script errors carry the line and column they occurred at. Returns the
+    /// errors of the extensions that aren't valid, none when the extensions directory is missing.
+    #[must_use]
+    pub fn validate(&self) -> Vec<ExtensionFailed> {
+        if !self.directory.exists() {
+            return Vec::new();
+        }
+
+        let directories = match self.discover() {
+            Ok(directories) => directories,
+            Err(error) => {
+                return vec![ExtensionFailed {
+                    extension: None,
+                    error,
+                }];
+            }
+        };
+
+        directories
+            .into_iter()
+            .filter_map(|(id, directory)| {
+                Extension::check(&directory)
+                    .err()
+                    .map(|error| ExtensionFailed {
+                        extension: Some(id),
+                        error,
+                    })
+            })
+            .collect()
+    }
+
+    /// Enables or disables the extension `id`, loading or unloading its script, and saves the
+    /// choice to the settings file.
+    ///
+    /// # Errors
+    /// Returns an error if there's no such extension, the settings can't be saved or the script
+    /// fails to load. The extension stays enabled when its script fails to load.
+    pub fn set_enabled(&mut self, id: &str, enabled: bool) -> Result<(), ExtensionError> {
+        let index = self
+            .loaded
+            .iter()
+            .position(|extension| extension.id == id)
+            .ok_or_else(|| ExtensionError::UnknownExtension(id.to_owned()))?;
+
+        let mut settings = self.read_settings()?;
+        if enabled {
+            settings.disabled.remove(id);
+        } else {
+            settings.disabled.insert(id.to_owned());
+        }
+        self.write_settings(&settings)?;
+
+        let (extension, error) = Extension::load(id.to_owned(), &self.directory.join(id), enabled)?;
+        self.loaded[index] = extension;
+        error.map_or(Ok(()), Err)
+    }
+
+    /// Finds the extensions in the extensions directory and returns their IDs and directories
+    /// ordered by ID.
+    ///
+    /// # Errors
+    /// Returns an error if the extensions directory can't be read.
+    fn discover(&self) -> Result<Vec<(String, PathBuf)>, ExtensionError> {
+        let entries = fs::read_dir(&self.directory).map_err(|source| ExtensionError::Io {
+            path: self.directory.clone(),
+            source,
+        })?;
+
+        let mut directories: Vec<_> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.join(crate::MANIFEST_FILE).is_file())
+            .map(|path| {
+                let id = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                (id, path)
+            })
+            .collect();
+        directories.sort();
+
+        Ok(directories)
+    }
+
+    /// Reads the settings file, which doesn't exist until an extension was disabled.
+    ///
+    /// # Errors
+    /// Returns an error if the settings can't be read or aren't valid.
+    fn read_settings(&self) -> Result<Settings, ExtensionError> {
+        let bytes = match fs::read(&self.settings) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(source) => {
+                return Err(ExtensionError::Io {
+                    path: self.settings.clone(),
+                    source,
+                });
+            }
+        };
+
+        deserialize(&bytes, Format::Toml).map_err(|source| ExtensionError::Invalid {
+            path: self.settings.clone(),
+            source,
+        })
+    }
+
+    /// Writes `settings` to the settings file.
+    ///
+    /// # Errors
+    /// Returns an error if the settings can't be written.
+    fn write_settings(&self, settings: &Settings) -> Result<(), ExtensionError> {
+        let bytes =
+            serialize(settings, Format::Toml).map_err(|source| ExtensionError::Invalid {
+                path: self.settings.clone(),
+                source,
+            })?;
+        let io_error = |source| ExtensionError::Io {
+            path: self.settings.clone(),
+            source,
+        };
+        if let Some(parent) = self.settings.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+
+        fs::write(&self.settings, bytes).map_err(io_error)
+    }
+}
+
+#[cfg(all(test, feature = "scripting"))]
+mod tests {
+    //! Validates extensions written to a temporary extensions directory.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+    use dungeonrs_utils::TempWorkspace;
+
+    /// Writes the extension `id` with `manifest` and a `main.rhai` entry script of `script`.
+    fn write_extension(extensions: &Extensions, id: &str, manifest: &str, script: &str) {
+        let directory = extensions.directory.join(id);
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join(crate::MANIFEST_FILE), manifest).unwrap();
+        fs::write(directory.join("main.rhai"), script).unwrap();
+    }
+
+    /// Extensions in `workspace`, whose directory isn't created.
+    fn extensions(workspace: &TempWorkspace) -> Extensions {
+        Extensions::new(
+            workspace.path().join("extensions"),
+            workspace.path().join("extensions.toml"),
+        )
+    }
+
+    /// A valid extension passes without its script running, even when it fails at runtime.
+    #[test]
+    fn accepts_valid_extensions_without_running_them() {
+        let workspace = TempWorkspace::open().unwrap();
+        let extensions = extensions(&workspace);
+        write_extension(
+            &extensions,
+            "scatter",
+            "name = \"Scatter\"",
+            "register_button(\"scatter\", \"Scatter\", \"scatter\");\n\
+             fn scatter(map) {}\n\
+             throw \"the script ran\";",
+        );
+
+        assert!(extensions.validate().is_empty());
+    }
+
+    /// Malformed manifests and scripts that don't compile are reported for their extension.
+    #[test]
+    fn reports_invalid_extensions() {
+        let workspace = TempWorkspace::open().unwrap();
+        let extensions = extensions(&workspace);
+        write_extension(&extensions, "manifest", "name = ", "");
+        write_extension(&extensions, "syntax", "name = \"Syntax\"", "fn broken( {");
+
+        let failures = extensions.validate();
+        let [manifest, syntax] = failures.as_slice() else {
+            panic!("both extensions are invalid, got {failures:?}");
+        };
+        assert_eq!(manifest.extension.as_deref(), Some("manifest"));
+        assert!(matches!(manifest.error, ExtensionError::Invalid { .. }));
+        assert_eq!(syntax.extension.as_deref(), Some("syntax"));
+        assert!(matches!(syntax.error, ExtensionError::Script(_)));
+    }
+
+    /// A missing extensions directory has nothing to validate and isn't created.
+    #[test]
+    fn leaves_missing_directory_alone() {
+        let workspace = TempWorkspace::open().unwrap();
+        let extensions = extensions(&workspace);
+
+        assert!(extensions.validate().is_empty());
+        assert!(!extensions.directory.exists());
+    }
+}
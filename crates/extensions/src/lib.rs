@@ -0,0 +1,15 @@
+#![doc = include_str!("../README.md")]
+
+mod error;
+mod extension;
+mod manifest;
+mod plugin;
+#[cfg(feature = "scripting")]
+mod script;
+
+pub use error::ExtensionError;
+pub use extension::{Contribution, ContributionKind, Extension, Extensions};
+pub use manifest::{ExtensionManifest, MANIFEST_FILE};
+pub use plugin::{
+    ExtensionFailed, ExtensionsPlugin, RunExtensionCommand, SetExtensionEnabled, UseExtensionTool,
+};
@@ -0,0 +1,54 @@
+//! Contains the [`ExtensionManifest`] describing an extension.
+
+use crate::ExtensionError;
+use dungeonrs_serialization::{Format, deserialize};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the file describing an extension, in the root of its directory.
+pub const MANIFEST_FILE: &str = "extension.toml";
+
+/// Describes an extension, read from the [`MANIFEST_FILE`] in its directory.
+///
+/// ```toml
+/// name = "Scatter"
+/// version = "1.0.0"
+/// description = "Scatters props around the cursor."
+/// entry = "scatter.rhai"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtensionManifest {
+    /// The name shown to the user.
+    pub name: String,
+    /// The version of the extension, informational only.
+    #[serde(default)]
+    pub version: String,
+    /// What the extension does.
+    #[serde(default)]
+    pub description: String,
+    /// The script loaded at startup, relative to the extension's directory.
+    #[serde(default = "default_entry")]
+    pub entry: PathBuf,
+}
+
+impl ExtensionManifest {
+    /// Reads the manifest of the extension in `directory`.
+    ///
+    /// # Errors
+    /// Returns an error if the manifest can't be read or isn't valid.
+    pub fn read(directory: &Path) -> Result<Self, ExtensionError> {
+        let path = directory.join(MANIFEST_FILE);
+        let bytes = fs::read(&path).map_err(|source| ExtensionError::Io {
+            path: path.clone(),
+            source,
+        })?;
+
+        deserialize(&bytes, Format::Toml).map_err(|source| ExtensionError::Invalid { path, source })
+    }
+}
+
+/// The script loaded when the manifest doesn't name one.
+fn default_entry() -> PathBuf {
+    PathBuf::from("main.rhai")
+}
@@ -0,0 +1,206 @@
+//! Contains the [`ExtensionsPlugin`] and the messages running extensions.
+
+#[cfg(feature = "scripting")]
+use crate::ContributionKind;
+#[cfg(feature = "scripting")]
+use crate::script::ScriptMap;
+use crate::{ExtensionError, Extensions};
+use bevy::prelude::*;
+use dungeonrs_data::HierarchySnapshot;
+#[cfg(feature = "scripting")]
+use dungeonrs_data::{Element, Layer};
+#[cfg(feature = "scripting")]
+use dungeonrs_macros::bevy_system;
+
+/// Loads the enabled extensions at startup and runs their contributions on request.
+///
+/// Requires the [`DataPlugin`](dungeonrs_data::DataPlugin), whose hierarchy snapshot is handed
+/// to the scripts. Without the `scripting` feature the extensions are listed but never loaded, so
+/// there are no contributions to run.
+pub struct ExtensionsPlugin;
+
+impl Plugin for ExtensionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Extensions>()
+            .init_resource::<HierarchySnapshot>()
+            .add_message::<RunExtensionCommand>()
+            .add_message::<UseExtensionTool>()
+            .add_message::<SetExtensionEnabled>()
+            .add_message::<ExtensionFailed>()
+            .add_systems(Startup, load_extensions)
+            .add_systems(Update, set_extensions_enabled);
+
+        #[cfg(feature = "scripting")]
+        app.add_systems(
+            Update,
+            run_extension_contributions.after(set_extensions_enabled),
+        );
+    }
+}
+
+/// Runs a toolbar button or batch operation of an extension.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct RunExtensionCommand {
+    /// The ID of the extension.
+    pub extension: String,
+    /// The ID of the button or operation.
+    pub command: String,
+}
+
+/// Uses a placement tool of an extension at a position on a layer.
+#[derive(Message, Debug, Clone, PartialEq)]
+pub struct UseExtensionTool {
+    /// The ID of the extension.
+    pub extension: String,
+    /// The ID of the tool.
+    pub tool: String,
+    /// The layer the tool is used on.
+    pub layer: Entity,
+    /// The position the tool is used at, within the layer.
+    pub position: Vec2,
+}
+
+/// Enables or disables an extension, the choice is remembered across sessions.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct SetExtensionEnabled {
+    /// The ID of the extension.
+    pub extension: String,
+    /// Whether the extension should be enabled.
+    pub enabled: bool,
+}
+
+/// Written when an extension fails to load or run.
+#[derive(Message, Debug)]
+pub struct ExtensionFailed {
+    /// The ID of the extension, `None` when the extensions couldn't be discovered at all.
+    pub extension: Option<String>,
+    /// The error that occurred.
+    pub error: ExtensionError,
+}
+
+/// Discovers and loads the extensions.
+fn load_extensions(
+    mut extensions: ResMut<Extensions>,
+    mut failures: MessageWriter<ExtensionFailed>,
+) {
+    failures.write_batch(extensions.reload());
+}
+
+/// Enables or disables the requested extensions.
+fn set_extensions_enabled(
+    mut requests: MessageReader<SetExtensionEnabled>,
+    mut extensions: ResMut<Extensions>,
+    mut failures: MessageWriter<ExtensionFailed>,
+) {
+    for request in requests.read() {
+        if let Err(error) = extensions.set_enabled(&request.extension, request.enabled) {
+            failures.write(ExtensionFailed {
+                extension: Some(request.extension.clone()),
+                error,
+            });
+        }
+    }
+}
+
+/// Runs the requested buttons, operations and tools, and applies the changes they made.
+#[cfg(feature = "scripting")]
+#[bevy_system]
+#[allow(
+    clippy::too_many_arguments,
+    reason = "scripts can read and change every part of the hierarchy"
+)]
+fn run_extension_contributions(
+    mut commands: Commands,
+    mut runs: MessageReader<RunExtensionCommand>,
+    mut tools: MessageReader<UseExtensionTool>,
+    mut failures: MessageWriter<ExtensionFailed>,
+    extensions: Res<Extensions>,
+    snapshot: Res<HierarchySnapshot>,
+    layers: Query<&Layer>,
+    mut elements: Query<(&Element, &mut Transform)>,
+) {
+    let requests = runs
+        .read()
+        .map(|run| (&run.extension, &run.command, None))
+        .chain(tools.read().map(|tool| {
+            (
+                &tool.extension,
+                &tool.tool,
+                Some((tool.layer, tool.position)),
+            )
+        }));
+
+    for (extension, contribution, target) in requests {
+        let map = ScriptMap::default();
+        for level in snapshot.levels() {
+            map.add_level(level.entity, &level.name);
+            for layer in &level.layers {
+                let locked = layers.get(layer.entity).is_ok_and(|layer| layer.locked);
+                map.add_layer(layer.entity, &layer.name, locked);
+                for &entity in &layer.elements {
+                    if let Ok((element, transform)) = elements.get(entity) {
+                        map.add_element(layer.entity, entity, element.asset.clone(), *transform);
+                    }
+                }
+            }
+        }
+
+        if let Err(error) = run_contribution(&extensions, extension, contribution, &map, target) {
+            failures.write(ExtensionFailed {
+                extension: Some(extension.clone()),
+                error,
+            });
+            continue;
+        }
+
+        let changes = map.take_changes();
+        for (entity, transform) in changes.transforms {
+            if let Ok((_, mut current)) = elements.get_mut(entity) {
+                *current = transform;
+            }
+        }
+        for entity in changes.deleted {
+            commands.entity(entity).despawn();
+        }
+        for placed in changes.placed {
+            commands.spawn((
+                Element::new(placed.asset),
+                placed.transform,
+                ChildOf(placed.layer),
+            ));
+        }
+    }
+}
+
+/// Runs the contribution `id` of the extension `extension` on `map`.
+///
+/// Tools are run when a `target` is given, buttons and operations otherwise.
+///
+/// # Errors
+/// Returns an error if there's no such contribution, the extension isn't loaded or the script
+/// fails.
+#[cfg(feature = "scripting")]
+fn run_contribution(
+    extensions: &Extensions,
+    extension: &str,
+    id: &str,
+    map: &ScriptMap,
+    target: Option<(Entity, Vec2)>,
+) -> Result<(), ExtensionError> {
+    let extension = extensions
+        .get(extension)
+        .ok_or_else(|| ExtensionError::UnknownExtension(extension.to_owned()))?;
+    let script = extension
+        .script()
+        .ok_or_else(|| ExtensionError::NotLoaded(extension.id().to_owned()))?;
+    let contribution = extension
+        .contributions()
+        .iter()
+        .find(|contribution| {
+            contribution.id == id
+                && (contribution.kind == ContributionKind::Tool) == target.is_some()
+        })
+        .ok_or_else(|| ExtensionError::UnknownContribution(id.to_owned()))?;
+
+    script.call(contribution, map, target)
+}
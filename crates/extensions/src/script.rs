@@ -0,0 +1,489 @@
+//! The scripting surface extensions are written against.
+//!
+//! Scripts never touch the world directly: they receive a [`ScriptMap`], a copy of the project
+//! hierarchy that records the changes made through it. Those changes are applied to the world
+//! once the script returns, so a script that fails halfway leaves the map untouched.
+
+use crate::{Contribution, ContributionKind, ExtensionError};
+use bevy::prelude::*;
+use rhai::{
+    AST, Array, CallFnOptions, Dynamic, Engine, EvalAltResult, FLOAT, INT, Map, NativeCallContext,
+    Position, Scope,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// The most operations a single call into a script may perform, so a script stuck in a loop
+/// doesn't freeze the editor.
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+/// How deeply expressions may nest at the top level and within functions. Batch operations
+/// commonly nest loops over levels, layers and elements, which the debug defaults don't allow.
+const MAX_EXPRESSION_DEPTHS: (usize, usize) = (128, 64);
+
+/// A compiled extension script and the engine exposing the scripting surface to it.
+#[derive(Debug)]
+pub(crate) struct Script {
+    /// The engine the script runs in.
+    engine: Engine,
+    /// The compiled script.
+    ast: AST,
+}
+
+impl Script {
+    /// Compiles the script at `path` and runs its top level, which registers the contributions
+    /// of the extension.
+    ///
+    /// # Errors
+    /// Returns an error if the script doesn't compile, fails while registering or registers a
+    /// contribution whose function it doesn't define.
+    pub(crate) fn load(path: &Path) -> Result<(Self, Vec<Contribution>), ExtensionError> {
+        let registered = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = engine();
+        register_map(&mut engine);
+        for kind in ContributionKind::ALL {
+            let registered = Arc::clone(&registered);
+            engine.register_fn(
+                kind.register_function(),
+                move |context: NativeCallContext, id: &str, label: &str, function: &str| {
+                    lock(&registered).push((
+                        Contribution {
+                            id: id.to_owned(),
+                            label: label.to_owned(),
+                            kind,
+                            function: function.to_owned(),
+                        },
+                        context.call_position(),
+                    ));
+                },
+            );
+        }
+
+        let ast = engine.compile_file(path.to_path_buf())?;
+        engine.run_ast(&ast)?;
+        let registered: Vec<(Contribution, Position)> = std::mem::take(&mut *lock(&registered));
+        for (contribution, position) in &registered {
+            let arity = contribution.kind.arity();
+            let defined = ast.iter_functions().any(|function| {
+                function.name == contribution.function && function.params.len() == arity
+            });
+            if !defined {
+                return Err(ExtensionError::MissingFunction {
+                    function: contribution.function.clone(),
+                    arity,
+                    position: *position,
+                });
+            }
+        }
+
+        let contributions = registered
+            .into_iter()
+            .map(|(contribution, _)| contribution)
+            .collect();
+
+        Ok((Self { engine, ast }, contributions))
+    }
+
+    /// Compiles the script at `path` without running it.
+    ///
+    /// # Errors
+    /// Returns an error if the script can't be read or doesn't compile.
+    pub(crate) fn check(path: &Path) -> Result<(), ExtensionError> {
+        engine().compile_file(path.to_path_buf())?;
+        Ok(())
+    }
+
+    /// Calls the function of `contribution` on `map`.
+    ///
+    /// Tools are also passed the layer and position they're used at.
+    ///
+    /// # Errors
+    /// Returns an error if the script raises one.
+    pub(crate) fn call(
+        &self,
+        contribution: &Contribution,
+        map: &ScriptMap,
+        target: Option<(Entity, Vec2)>,
+    ) -> Result<(), ExtensionError> {
+        let options = CallFnOptions::new().eval_ast(false);
+        let mut scope = Scope::new();
+        let function = contribution.function.as_str();
+        // Whatever the function returns is ignored, only its changes to the map matter.
+        let _returned: Dynamic = match target {
+            Some((layer, position)) => self.engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut scope,
+                &self.ast,
+                function,
+                (
+                    map.clone(),
+                    script_id(layer),
+                    FLOAT::from(position.x),
+                    FLOAT::from(position.y),
+                ),
+            ),
+            None => self.engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut scope,
+                &self.ast,
+                function,
+                (map.clone(),),
+            ),
+        }?;
+
+        Ok(())
+    }
+}
+
+/// Creates an engine with the limits scripts run under.
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_expr_depths(MAX_EXPRESSION_DEPTHS.0, MAX_EXPRESSION_DEPTHS.1);
+    engine.disable_symbol("eval");
+    engine
+}
+
+/// A copy of the project hierarchy handed to scripts, which records the changes made through it.
+///
+/// Exposed to scripts as the `Map` type.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ScriptMap(Arc<Mutex<MapState>>);
+
+/// The contents of a [`ScriptMap`].
+#[derive(Debug, Default)]
+struct MapState {
+    /// The levels of every project, in order.
+    levels: Vec<LevelState>,
+    /// The layers, by script ID.
+    layers: HashMap<INT, LayerState>,
+    /// The elements, by script ID.
+    elements: HashMap<INT, ElementState>,
+    /// The elements placed by the script.
+    placed: Vec<PlacedElement>,
+}
+
+/// A level in a [`ScriptMap`].
+#[derive(Debug)]
+struct LevelState {
+    /// The script ID of the level.
+    id: INT,
+    /// The name of the level.
+    name: String,
+    /// The script IDs of the layers, in drawing order.
+    layers: Vec<INT>,
+}
+
+/// A layer in a [`ScriptMap`].
+#[derive(Debug)]
+struct LayerState {
+    /// The layer entity.
+    entity: Entity,
+    /// The name of the layer.
+    name: String,
+    /// Whether the layer is locked, scripts can't change locked layers.
+    locked: bool,
+    /// The script IDs of the elements, in order.
+    elements: Vec<INT>,
+}
+
+/// An element in a [`ScriptMap`].
+#[derive(Debug)]
+struct ElementState {
+    /// The element entity.
+    entity: Entity,
+    /// The script ID of the layer the element is on.
+    layer: INT,
+    /// The path of the asset displayed by the element.
+    asset: PathBuf,
+    /// The transform of the element, including the changes made by the script.
+    transform: Transform,
+    /// Whether the script changed the transform.
+    changed: bool,
+    /// Whether the script deleted the element.
+    deleted: bool,
+}
+
+/// An element placed by a script.
+#[derive(Debug)]
+pub(crate) struct PlacedElement {
+    /// The layer to place the element on.
+    pub(crate) layer: Entity,
+    /// The path of the asset displayed by the element.
+    pub(crate) asset: PathBuf,
+    /// The transform of the element.
+    pub(crate) transform: Transform,
+}
+
+/// The changes a script made to a [`ScriptMap`].
+#[derive(Debug, Default)]
+pub(crate) struct MapChanges {
+    /// The new transforms of the moved, rotated or scaled elements.
+    pub(crate) transforms: Vec<(Entity, Transform)>,
+    /// The deleted elements.
+    pub(crate) deleted: Vec<Entity>,
+    /// The placed elements.
+    pub(crate) placed: Vec<PlacedElement>,
+}
+
+impl ScriptMap {
+    /// Adds a level named `name`.
+    pub(crate) fn add_level(&self, level: Entity, name: &str) {
+        lock(&self.0).levels.push(LevelState {
+            id: script_id(level),
+            name: name.to_owned(),
+            layers: Vec::new(),
+        });
+    }
+
+    /// Adds a layer to the most recently added level.
+    pub(crate) fn add_layer(&self, layer: Entity, name: &str, locked: bool) {
+        let mut state = lock(&self.0);
+        let id = script_id(layer);
+        if let Some(level) = state.levels.last_mut() {
+            level.layers.push(id);
+        }
+        state.layers.insert(
+            id,
+            LayerState {
+                entity: layer,
+                name: name.to_owned(),
+                locked,
+                elements: Vec::new(),
+            },
+        );
+    }
+
+    /// Adds an element to `layer`, which has to be added first.
+    pub(crate) fn add_element(
+        &self,
+        layer: Entity,
+        element: Entity,
+        asset: PathBuf,
+        transform: Transform,
+    ) {
+        let mut state = lock(&self.0);
+        let (layer, id) = (script_id(layer), script_id(element));
+        let Some(layer_state) = state.layers.get_mut(&layer) else {
+            return;
+        };
+        layer_state.elements.push(id);
+        state.elements.insert(
+            id,
+            ElementState {
+                entity: element,
+                layer,
+                asset,
+                transform,
+                changed: false,
+                deleted: false,
+            },
+        );
+    }
+
+    /// Takes the changes the script made.
+    pub(crate) fn take_changes(&self) -> MapChanges {
+        let mut state = lock(&self.0);
+        let mut changes = MapChanges {
+            placed: std::mem::take(&mut state.placed),
+            ..default()
+        };
+        for element in state.elements.values() {
+            if element.deleted {
+                changes.deleted.push(element.entity);
+            } else if element.changed {
+                changes.transforms.push((element.entity, element.transform));
+            }
+        }
+
+        changes
+    }
+
+    /// The levels, as `#{ id, name }` maps.
+    fn levels(&mut self) -> Array {
+        lock(&self.0)
+            .levels
+            .iter()
+            .map(|level| {
+                let mut map = Map::new();
+                map.insert("id".into(), Dynamic::from_int(level.id));
+                map.insert("name".into(), level.name.clone().into());
+                Dynamic::from_map(map)
+            })
+            .collect()
+    }
+
+    /// The layers of `level`, as `#{ id, name, locked }` maps.
+    fn layers(&mut self, level: INT) -> Array {
+        let state = lock(&self.0);
+        let Some(level) = state.levels.iter().find(|candidate| candidate.id == level) else {
+            return Array::new();
+        };
+
+        level
+            .layers
+            .iter()
+            .filter_map(|id| {
+                let layer = state.layers.get(id)?;
+                let mut map = Map::new();
+                map.insert("id".into(), Dynamic::from_int(*id));
+                map.insert("name".into(), layer.name.clone().into());
+                map.insert("locked".into(), Dynamic::from_bool(layer.locked));
+                Some(Dynamic::from_map(map))
+            })
+            .collect()
+    }
+
+    /// The elements of `layer`, as `#{ id, asset, x, y, rotation, scale }` maps.
+    fn elements(&mut self, layer: INT) -> Array {
+        let state = lock(&self.0);
+        let Some(layer) = state.layers.get(&layer) else {
+            return Array::new();
+        };
+
+        layer
+            .elements
+            .iter()
+            .filter_map(|id| {
+                let element = state.elements.get(id).filter(|element| !element.deleted)?;
+                let transform = element.transform;
+                let mut map = Map::new();
+                map.insert("id".into(), Dynamic::from_int(*id));
+                map.insert(
+                    "asset".into(),
+                    element.asset.to_string_lossy().into_owned().into(),
+                );
+                map.insert("x".into(), float(transform.translation.x));
+                map.insert("y".into(), float(transform.translation.y));
+                map.insert(
+                    "rotation".into(),
+                    float(transform.rotation.to_euler(EulerRot::XYZ).2),
+                );
+                map.insert("scale".into(), float(transform.scale.x));
+                Some(Dynamic::from_map(map))
+            })
+            .collect()
+    }
+
+    /// Changes the element `id` with `change`.
+    ///
+    /// # Errors
+    /// Returns an error if there's no such element or its layer is locked.
+    fn change_element(
+        &mut self,
+        id: INT,
+        change: impl FnOnce(&mut ElementState),
+    ) -> Result<(), Box<EvalAltResult>> {
+        let mut state = lock(&self.0);
+        let layer = state
+            .elements
+            .get(&id)
+            .filter(|element| !element.deleted)
+            .ok_or_else(|| format!("there's no element {id}"))?
+            .layer;
+        if state.layers.get(&layer).is_some_and(|layer| layer.locked) {
+            return Err(format!("the layer of element {id} is locked").into());
+        }
+
+        if let Some(element) = state.elements.get_mut(&id) {
+            change(element);
+        }
+        Ok(())
+    }
+
+    /// Places a new element displaying `asset` on `layer`.
+    ///
+    /// # Errors
+    /// Returns an error if there's no such layer or it's locked.
+    fn place(
+        &mut self,
+        layer: INT,
+        asset: &str,
+        x: FLOAT,
+        y: FLOAT,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let mut state = lock(&self.0);
+        let entity = match state.layers.get(&layer) {
+            Some(layer) if !layer.locked => layer.entity,
+            Some(_) => return Err(format!("layer {layer} is locked").into()),
+            None => return Err(format!("there's no layer {layer}").into()),
+        };
+
+        state.placed.push(PlacedElement {
+            layer: entity,
+            asset: PathBuf::from(asset),
+            transform: Transform::from_xyz(single(x), single(y), 0.0),
+        });
+        Ok(())
+    }
+}
+
+/// Registers the `Map` type and its functions with `engine`.
+fn register_map(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<ScriptMap>("Map")
+        .register_fn("levels", ScriptMap::levels)
+        .register_fn("layers", ScriptMap::layers)
+        .register_fn("elements", ScriptMap::elements)
+        .register_fn("place", ScriptMap::place)
+        .register_fn(
+            "move_element",
+            |map: &mut ScriptMap, id: INT, x: FLOAT, y: FLOAT| {
+                map.change_element(id, |element| {
+                    element.transform.translation.x = single(x);
+                    element.transform.translation.y = single(y);
+                    element.changed = true;
+                })
+            },
+        )
+        .register_fn(
+            "rotate_element",
+            |map: &mut ScriptMap, id: INT, rotation: FLOAT| {
+                map.change_element(id, |element| {
+                    element.transform.rotation = Quat::from_rotation_z(single(rotation));
+                    element.changed = true;
+                })
+            },
+        )
+        .register_fn(
+            "scale_element",
+            |map: &mut ScriptMap, id: INT, scale: FLOAT| {
+                map.change_element(id, |element| {
+                    element.transform.scale = Vec3::new(single(scale), single(scale), 1.0);
+                    element.changed = true;
+                })
+            },
+        )
+        .register_fn("delete_element", |map: &mut ScriptMap, id: INT| {
+            map.change_element(id, |element| element.deleted = true)
+        });
+}
+
+/// The ID `entity` is known by in scripts.
+#[allow(
+    clippy::cast_possible_wrap,
+    reason = "the ID only has to be unique, not to keep its sign"
+)]
+fn script_id(entity: Entity) -> INT {
+    entity.to_bits() as INT
+}
+
+/// Converts `value` to a script number.
+fn float(value: f32) -> Dynamic {
+    Dynamic::from_float(FLOAT::from(value))
+}
+
+/// Converts a script number to `f32`.
+#[allow(
+    clippy::cast_possible_truncation,
+    reason = "map coordinates are small enough for f32"
+)]
+fn single(value: FLOAT) -> f32 {
+    value as f32
+}
+
+/// Locks `mutex`, recovering the data if a panic poisoned it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,8 @@
+#![doc = include_str!("../README.md")]
+
+mod locale;
+mod macros;
+
+pub use fluent_bundle::{FluentArgs, FluentValue};
+pub use locale::{Locale, LocaleError};
+pub use unic_langid::LanguageIdentifier;
@@ -0,0 +1,225 @@
+//! Contains the [`Locale`] resource used to translate messages.
+
+use bevy::prelude::Resource;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentError, FluentResource};
+use std::fs::{read_dir, read_to_string};
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use unic_langid::LanguageIdentifier;
+
+/// The file extension used by Fluent translation files.
+const FLUENT_EXTENSION: &str = "ftl";
+
+/// Errors that can occur while loading translations into a [`Locale`].
+#[derive(Error, Debug)]
+pub enum LocaleError {
+    /// Reading a translation file (or its directory) failed.
+    #[error("failed to read translations from {0}")]
+    Io(PathBuf, #[source] io::Error),
+    /// A translation file contained invalid Fluent syntax.
+    #[error("failed to parse translations: {0:?}")]
+    Parse(Vec<fluent_bundle::FluentError>),
+}
+
+/// Holds the translations for the currently active language.
+#[derive(Resource)]
+pub struct Locale {
+    /// The language this locale translates into.
+    language: LanguageIdentifier,
+    /// The Fluent bundle containing all loaded messages.
+    bundle: FluentBundle<FluentResource>,
+    /// The locale translating the messages this one is missing, usually the default language.
+    fallback: Option<Box<Locale>>,
+}
+
+impl Locale {
+    /// Creates an empty [`Locale`] for the given language.
+    ///
+    /// Translations can be added using [`Locale::add_source`].
+    #[must_use]
+    pub fn new(language: LanguageIdentifier) -> Self {
+        let mut bundle = FluentBundle::new_concurrent(vec![language.clone()]);
+        // Isolating wraps every argument in Unicode directional isolates, which the UI's fonts
+        // render as boxes. The UI doesn't mix text directions within a message.
+        bundle.set_use_isolating(false);
+
+        Self {
+            language,
+            bundle,
+            fallback: None,
+        }
+    }
+
+    /// Translates the messages this locale is missing with `fallback` instead, usually the
+    /// locale of the default language.
+    #[must_use]
+    pub fn with_fallback(mut self, fallback: Locale) -> Self {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+
+    /// Creates a [`Locale`] for `language` and loads every `.ftl` file in `directory/<language>`.
+    ///
+    /// # Errors
+    /// Returns an error if the directory can't be read or one of the files contains invalid Fluent.
+    pub fn load(
+        language: LanguageIdentifier,
+        directory: impl AsRef<Path>,
+    ) -> Result<Self, LocaleError> {
+        let directory = directory.as_ref().join(language.to_string());
+        let mut locale = Self::new(language);
+
+        let entries =
+            read_dir(&directory).map_err(|error| LocaleError::Io(directory.clone(), error))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|error| LocaleError::Io(directory.clone(), error))?
+                .path();
+            if path
+                .extension()
+                .is_none_or(|extension| extension != FLUENT_EXTENSION)
+            {
+                continue;
+            }
+
+            let source =
+                read_to_string(&path).map_err(|error| LocaleError::Io(path.clone(), error))?;
+            locale.add_source(source)?;
+        }
+
+        Ok(locale)
+    }
+
+    /// Parses `source` as Fluent and adds its messages to this locale.
+    ///
+    /// # Errors
+    /// Returns an error if `source` contains invalid syntax or redefines an existing message.
+    pub fn add_source(&mut self, source: String) -> Result<(), LocaleError> {
+        let resource = FluentResource::try_new(source).map_err(|(_, errors)| {
+            LocaleError::Parse(errors.into_iter().map(FluentError::ParserError).collect())
+        })?;
+
+        self.bundle
+            .add_resource(resource)
+            .map_err(LocaleError::Parse)
+    }
+
+    /// The language this locale translates into.
+    #[must_use]
+    pub fn language(&self) -> &LanguageIdentifier {
+        &self.language
+    }
+
+    /// Translates `key` using the (optional) `args`.
+    ///
+    /// The key is either a message identifier (`save-button`) or a message identifier followed by
+    /// one of its attributes (`save-button.tooltip`). Fluent identifiers can't contain dots, so
+    /// the first dot always separates the message from the attribute.
+    ///
+    /// When the message (or attribute) doesn't exist, or has no value, it's translated by the
+    /// [fallback](Locale::with_fallback) locale. Without one, the key itself is returned so missing
+    /// translations remain visible in the UI rather than rendering as empty text.
+    #[must_use]
+    pub fn translate(&self, key: &str, args: Option<&FluentArgs>) -> String {
+        self.try_translate(key, args)
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// Translates `key` like [`Locale::translate`], returning `None` when neither this locale nor
+    /// its fallback has the message.
+    fn try_translate(&self, key: &str, args: Option<&FluentArgs>) -> Option<String> {
+        let (id, attribute) = match key.split_once('.') {
+            Some((id, attribute)) => (id, Some(attribute)),
+            None => (key, None),
+        };
+
+        let pattern = self
+            .bundle
+            .get_message(id)
+            .and_then(|message| match attribute {
+                Some(attribute) => message
+                    .get_attribute(attribute)
+                    .map(|attribute| attribute.value()),
+                None => message.value(),
+            });
+        let Some(pattern) = pattern else {
+            return self.fallback.as_ref()?.try_translate(key, args);
+        };
+
+        let mut errors = vec![];
+        Some(
+            self.bundle
+                .format_pattern(pattern, args, &mut errors)
+                .into_owned(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Checks message and attribute lookups, argument interpolation and the fallback locale.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+
+    /// A locale for `language` holding the messages in `source`.
+    fn locale(language: &str, source: &str) -> Locale {
+        let mut locale = Locale::new(language.parse().expect("the language is valid"));
+        locale
+            .add_source(source.to_owned())
+            .expect("the source is valid");
+        locale
+    }
+
+    /// An English locale with a label and its tooltip.
+    fn english() -> Locale {
+        locale(
+            "en-US",
+            "save-button = Save\n    .tooltip = Save { $name } to disk\nquit = Quit\n",
+        )
+    }
+
+    /// Plain keys translate to the message's value, `key.attribute` keys to the attribute.
+    #[test]
+    fn looks_up_attributes() {
+        let locale = english();
+
+        assert_eq!(locale.translate("save-button", None), "Save");
+        assert_eq!(
+            locale.translate("save-button.tooltip", None),
+            "Save {$name} to disk"
+        );
+        assert_eq!(
+            locale.translate("save-button.aria", None),
+            "save-button.aria"
+        );
+        assert_eq!(locale.translate("missing", None), "missing");
+    }
+
+    /// Arguments are interpolated without directional isolates around them.
+    #[test]
+    fn interpolates_arguments() {
+        let locale = english();
+
+        assert_eq!(
+            crate::t!(locale, "save-button.tooltip", name = "my-dungeon"),
+            "Save my-dungeon to disk"
+        );
+    }
+
+    /// Messages and attributes missing from a locale are translated by its fallback.
+    #[test]
+    fn falls_back_to_default_locale() {
+        let locale = locale("nl-BE", "save-button = Opslaan\n").with_fallback(english());
+
+        assert_eq!(locale.translate("save-button", None), "Opslaan");
+        assert_eq!(locale.translate("quit", None), "Quit");
+        assert_eq!(
+            crate::t!(locale, "save-button.tooltip", name = "kerker"),
+            "Save kerker to disk"
+        );
+        assert_eq!(locale.translate("missing", None), "missing");
+    }
+}
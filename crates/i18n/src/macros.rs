@@ -0,0 +1,29 @@
+//! Convenience macros for looking up translations.
+
+/// Translates a message using the given [`Locale`](crate::Locale).
+///
+/// The key is either a plain message identifier or a `key.attribute` pair, allowing a single
+/// Fluent message to provide both a label and its tooltip:
+///
+/// ```ftl
+/// save-button = Save
+///     .tooltip = Save { $name } to disk
+/// ```
+///
+/// Named arguments are passed as `name = value` pairs after the key:
+///
+/// ```ignore
+/// let label = t!(locale, "save-button");
+/// let tooltip = t!(locale, "save-button.tooltip", name = "my-dungeon");
+/// ```
+#[macro_export]
+macro_rules! t {
+    ($locale:expr, $key:expr $(,)?) => {
+        $locale.translate($key, None)
+    };
+    ($locale:expr, $key:expr, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = $crate::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $locale.translate($key, Some(&args))
+    }};
+}
@@ -0,0 +1,128 @@
+//! A minimal writer for the zip archives some virtual tabletops use as package format.
+//!
+//! Entries are deflated and written in memory. Archives larger than 4 GiB (which need the zip64
+//! extensions) aren't supported, which is far beyond what a tabletop accepts anyway.
+
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use std::io::{self, Write};
+
+/// The DOS date written for every entry, 1980-01-01, as the timestamps aren't meaningful.
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// An entry already written to the archive, kept for the central directory.
+struct Entry {
+    /// The path of the entry within the archive.
+    name: String,
+    /// The CRC-32 of the uncompressed data.
+    crc: u32,
+    /// The size of the compressed data.
+    compressed_size: u32,
+    /// The size of the uncompressed data.
+    size: u32,
+    /// The offset of the entry's local header.
+    offset: u32,
+}
+
+/// Writes a zip archive in memory.
+#[derive(Default)]
+pub(crate) struct ZipWriter {
+    /// The archive written so far.
+    bytes: Vec<u8>,
+    /// The entries written so far.
+    entries: Vec<Entry>,
+}
+
+impl ZipWriter {
+    /// Adds a file named `name` containing `data`.
+    ///
+    /// # Errors
+    /// Returns an error if the data can't be compressed or the archive exceeds 4 GiB.
+    pub fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+
+        let entry = Entry {
+            name: name.to_owned(),
+            crc: crc32fast::hash(data),
+            compressed_size: to_u32(compressed.len())?,
+            size: to_u32(data.len())?,
+            offset: to_u32(self.bytes.len())?,
+        };
+        self.bytes.extend_from_slice(&0x0403_4b50_u32.to_le_bytes());
+        self.write_entry_fields(&entry);
+        self.bytes.extend_from_slice(&0_u16.to_le_bytes());
+        self.bytes.extend_from_slice(entry.name.as_bytes());
+        self.bytes.extend_from_slice(&compressed);
+        self.entries.push(entry);
+
+        Ok(())
+    }
+
+    /// Writes the central directory and returns the archive.
+    ///
+    /// # Errors
+    /// Returns an error if the archive exceeds 4 GiB or holds more than 65535 entries.
+    pub fn finish(mut self) -> io::Result<Vec<u8>> {
+        let directory_offset = to_u32(self.bytes.len())?;
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            self.bytes.extend_from_slice(&0x0201_4b50_u32.to_le_bytes());
+            // Made by version 2.0.
+            self.bytes.extend_from_slice(&20_u16.to_le_bytes());
+            self.write_entry_fields(entry);
+            // Extra field, comment, disk number, internal and external attributes.
+            self.bytes.extend_from_slice(&[0; 12]);
+            self.bytes.extend_from_slice(&entry.offset.to_le_bytes());
+            self.bytes.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = to_u32(self.bytes.len())? - directory_offset;
+        let count = u16::try_from(entries.len())
+            .map_err(|_| io::Error::other("too many entries for a zip archive"))?;
+
+        self.bytes.extend_from_slice(&0x0605_4b50_u32.to_le_bytes());
+        // The number of this disk and the disk the directory starts on.
+        self.bytes.extend_from_slice(&[0; 4]);
+        self.bytes.extend_from_slice(&count.to_le_bytes());
+        self.bytes.extend_from_slice(&count.to_le_bytes());
+        self.bytes.extend_from_slice(&directory_size.to_le_bytes());
+        self.bytes
+            .extend_from_slice(&directory_offset.to_le_bytes());
+        // The archive comment.
+        self.bytes.extend_from_slice(&0_u16.to_le_bytes());
+
+        Ok(self.bytes)
+    }
+
+    /// Writes the fields shared by the local header and the central directory, up to and
+    /// including the length of the name.
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "entry names are far shorter than 65535 bytes"
+    )]
+    fn write_entry_fields(&mut self, entry: &Entry) {
+        // Version 2.0 is needed to extract deflated entries.
+        self.bytes.extend_from_slice(&20_u16.to_le_bytes());
+        // The name is encoded as UTF-8.
+        self.bytes.extend_from_slice(&0x0800_u16.to_le_bytes());
+        // Deflate.
+        self.bytes.extend_from_slice(&8_u16.to_le_bytes());
+        self.bytes.extend_from_slice(&0_u16.to_le_bytes());
+        self.bytes.extend_from_slice(&DOS_DATE.to_le_bytes());
+        self.bytes.extend_from_slice(&entry.crc.to_le_bytes());
+        self.bytes
+            .extend_from_slice(&entry.compressed_size.to_le_bytes());
+        self.bytes.extend_from_slice(&entry.size.to_le_bytes());
+        self.bytes
+            .extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+    }
+}
+
+/// Converts a size or offset to the 32 bits available in the archive.
+///
+/// # Errors
+/// Returns an error if `value` doesn't fit.
+fn to_u32(value: usize) -> io::Result<u32> {
+    u32::try_from(value).map_err(|_| io::Error::other("zip archives are limited to 4 GiB"))
+}
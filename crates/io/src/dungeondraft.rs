@@ -0,0 +1,580 @@
+//! Imports maps made with [Dungeondraft](https://dungeondraft.net/) (`.dungeondraft_map`).
+//!
+//! A Dungeondraft map is JSON written by the Godot engine: positions are strings such as
+//! `Vector2( 256, 512 )` and lines are `PoolVector2Array( … )` strings, in pixels with 256 pixels
+//! per grid cell and the Y axis pointing down. Every level of the map becomes a level, its objects
+//! are grouped into layers by their Dungeondraft layer, and its walls, doors, lights and texts
+//! are imported into layers of their own.
+//!
+//! Dungeondraft's textures aren't distributed with the map, so each one is matched by name
+//! against the indexes of the registered asset packs. The match is best-effort: the asset whose
+//! file name is the texture's is preferred, the best search result is used otherwise, and objects
+//! whose texture matches no asset are left out and reported.
+
+use crate::uvtt::argb;
+use bevy::asset::uuid::Uuid;
+use bevy::color::Color;
+use bevy::math::{Quat, Vec2};
+use bevy::transform::components::Transform;
+use dungeonrs_assets::{AssetLibrary, PackIndexes};
+use dungeonrs_core::{
+    ElementData, LabelData, LayerData, LevelData, LightData, PortalData, SaveFile, WallData,
+    WallPathData,
+};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The size of a Dungeondraft grid cell, in pixels.
+const CELL_SIZE: f32 = 256.0;
+
+/// The thickness of imported walls, which Dungeondraft doesn't store.
+const WALL_WIDTH: f32 = 32.0;
+
+/// The number of search results considered when matching a texture to an asset.
+const MATCH_CANDIDATES: usize = 8;
+
+/// Errors that can occur while importing a Dungeondraft map.
+#[derive(Error, Debug)]
+pub enum DungeondraftError {
+    /// The map couldn't be read.
+    #[error("failed to read '{path}': {source}")]
+    Io {
+        /// The file that couldn't be read.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+    /// The file isn't a valid Dungeondraft map.
+    #[error("invalid Dungeondraft map: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The result of importing a Dungeondraft map.
+#[derive(Debug, Clone)]
+pub struct DungeondraftImport {
+    /// The imported project, with a level per level of the map.
+    pub project: SaveFile,
+    /// The Dungeondraft textures no asset was found for, whose objects were left out.
+    pub unmatched: Vec<String>,
+}
+
+/// The parts of a Dungeondraft map that are imported.
+#[derive(Deserialize, Debug)]
+struct MapFile {
+    /// The contents of the map.
+    world: World,
+}
+
+/// The `world` object of a Dungeondraft map.
+#[derive(Deserialize, Debug)]
+struct World {
+    /// The levels of the map, by index.
+    #[serde(default)]
+    levels: BTreeMap<String, DdLevel>,
+}
+
+/// A level of a Dungeondraft map.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct DdLevel {
+    /// The name of the level.
+    label: String,
+    /// The names of the layers, by layer number.
+    layers: BTreeMap<String, String>,
+    /// The objects placed on the level.
+    objects: Vec<DdObject>,
+    /// The walls of the level.
+    walls: Vec<DdWall>,
+    /// The doors and windows that aren't part of a wall.
+    portals: Vec<DdPortal>,
+    /// The light sources.
+    lights: Vec<DdLight>,
+    /// The texts written on the map.
+    texts: Vec<DdText>,
+    /// The textured paths, such as roads and rivers.
+    paths: Vec<DdPath>,
+}
+
+/// An object placed on a Dungeondraft level.
+#[derive(Deserialize, Debug)]
+struct DdObject {
+    /// The center of the object, as a `Vector2`.
+    position: String,
+    /// The clockwise rotation of the object, in radians.
+    #[serde(default)]
+    rotation: f32,
+    /// The scale of the object, as a `Vector2`.
+    #[serde(default)]
+    scale: Option<String>,
+    /// Whether the object is flipped horizontally.
+    #[serde(default)]
+    mirror: bool,
+    /// The texture of the object, as a `res://` path.
+    texture: String,
+    /// The number of the layer the object is on.
+    #[serde(default)]
+    layer: i32,
+}
+
+/// A wall of a Dungeondraft level.
+#[derive(Deserialize, Debug)]
+struct DdWall {
+    /// The points of the wall, as a `PoolVector2Array`.
+    points: String,
+    /// The texture of the wall, as a `res://` path.
+    #[serde(default)]
+    texture: Option<String>,
+    /// Whether the last point connects back to the first.
+    #[serde(rename = "loop", default)]
+    closed: bool,
+    /// The doors and windows set into the wall.
+    #[serde(default)]
+    portals: Vec<DdPortal>,
+}
+
+/// A door or window of a Dungeondraft level.
+#[derive(Deserialize, Debug)]
+struct DdPortal {
+    /// The center of the portal, as a `Vector2`.
+    position: String,
+    /// The direction along the portal, as a `Vector2`.
+    #[serde(default)]
+    direction: Option<String>,
+    /// Half the width of the portal, in pixels.
+    #[serde(default = "default_portal_radius")]
+    radius: f32,
+    /// Whether the portal is closed.
+    #[serde(default = "default_closed")]
+    closed: bool,
+}
+
+/// A light of a Dungeondraft level.
+#[derive(Deserialize, Debug)]
+struct DdLight {
+    /// The center of the light, as a `Vector2`.
+    position: String,
+    /// The distance the light reaches, in grid cells.
+    #[serde(default = "default_light_range")]
+    range: f32,
+    /// The brightness of the light.
+    #[serde(default = "default_intensity")]
+    intensity: f32,
+    /// The colour of the light, as `AARRGGBB` hexadecimal.
+    #[serde(default)]
+    color: Option<String>,
+}
+
+/// A text written on a Dungeondraft level.
+#[derive(Deserialize, Debug)]
+struct DdText {
+    /// The position of the text, as a `Vector2`.
+    position: String,
+    /// The text.
+    text: String,
+    /// The font size of the text.
+    #[serde(default = "default_font_size")]
+    font_size: f32,
+    /// The colour of the text, as hexadecimal ARGB.
+    #[serde(default)]
+    font_color: Option<String>,
+}
+
+/// A textured path of a Dungeondraft level.
+#[derive(Deserialize, Debug)]
+struct DdPath {
+    /// The origin of the path's points, as a `Vector2`.
+    #[serde(default)]
+    position: Option<String>,
+    /// The points of the path, as a `PoolVector2Array`.
+    edit_points: String,
+    /// The texture of the path, as a `res://` path.
+    texture: String,
+    /// The thickness of the path, in pixels.
+    #[serde(default = "default_path_width")]
+    width: f32,
+    /// Whether the last point connects back to the first.
+    #[serde(rename = "loop", default)]
+    closed: bool,
+}
+
+/// The half width of portals that don't specify one: a whole cell wide.
+fn default_portal_radius() -> f32 {
+    CELL_SIZE / 2.0
+}
+
+/// Whether portals that don't specify it are closed.
+fn default_closed() -> bool {
+    true
+}
+
+/// The range of lights that don't specify one, in grid cells.
+fn default_light_range() -> f32 {
+    4.0
+}
+
+/// The intensity of lights that don't specify one.
+fn default_intensity() -> f32 {
+    1.0
+}
+
+/// The font size of texts that don't specify one.
+fn default_font_size() -> f32 {
+    32.0
+}
+
+/// The width of paths that don't specify one.
+fn default_path_width() -> f32 {
+    CELL_SIZE / 2.0
+}
+
+/// Imports the Dungeondraft map at `path`, matching its textures against the assets of the
+/// packs in `library` whose index is open in `indexes`.
+///
+/// Elements are positioned in pixels, with the top left corner of the map at the origin and the
+/// Y axis pointing up.
+///
+/// # Errors
+/// Returns an error if the map can't be read or isn't a valid Dungeondraft map.
+pub fn import_dungeondraft(
+    path: &Path,
+    library: &AssetLibrary,
+    indexes: &PackIndexes,
+) -> Result<DungeondraftImport, DungeondraftError> {
+    let bytes = fs::read(path).map_err(|source| DungeondraftError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let map: MapFile = serde_json::from_slice(&bytes)?;
+
+    let mut matcher = AssetMatcher {
+        library,
+        indexes,
+        matches: HashMap::new(),
+    };
+    let mut levels: Vec<_> = map.world.levels.into_iter().collect();
+    levels.sort_by_key(|(index, _)| index.parse::<i32>().unwrap_or(i32::MAX));
+    let levels = levels
+        .into_iter()
+        .map(|(index, level)| import_level(&index, level, &mut matcher))
+        .collect();
+
+    let mut unmatched: Vec<_> = matcher
+        .matches
+        .into_iter()
+        .filter(|(_, asset)| asset.is_none())
+        .map(|(texture, _)| texture)
+        .collect();
+    unmatched.sort_unstable();
+
+    Ok(DungeondraftImport {
+        project: SaveFile {
+            id: Uuid::new_v4(),
+            name: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            levels,
+        },
+        unmatched,
+    })
+}
+
+/// Imports `level`, the level at `index` of the map.
+fn import_level(index: &str, level: DdLevel, matcher: &mut AssetMatcher) -> LevelData {
+    // Objects are grouped by layer, drawn in the order of their layer number.
+    let mut objects: BTreeMap<i32, Vec<ElementData>> = BTreeMap::new();
+    for object in &level.objects {
+        let Some(asset) = matcher.asset(&object.texture) else {
+            continue;
+        };
+        objects.entry(object.layer).or_default().push(ElementData {
+            id: Uuid::new_v4(),
+            asset,
+            transform: object_transform(object),
+            animation: None,
+        });
+    }
+    let mut layers: Vec<_> = objects
+        .into_iter()
+        .map(|(number, elements)| LayerData {
+            elements,
+            ..LayerData::new(
+                level
+                    .layers
+                    .get(&number.to_string())
+                    .cloned()
+                    .unwrap_or_else(|| format!("Layer {number}")),
+            )
+        })
+        .collect();
+
+    let paths = level
+        .paths
+        .iter()
+        .filter_map(|path| {
+            let origin = path
+                .position
+                .as_deref()
+                .and_then(vector)
+                .unwrap_or_default();
+            let points = vectors(&path.edit_points)
+                .into_iter()
+                .map(|point| position(origin + point).to_array())
+                .collect();
+            Some(WallPathData {
+                id: Uuid::new_v4(),
+                points,
+                closed: path.closed,
+                width: path.width,
+                texture: matcher.asset(&path.texture)?,
+                transform: Transform::IDENTITY,
+            })
+        })
+        .collect();
+    layers.push(LayerData {
+        paths,
+        ..LayerData::new("Paths")
+    });
+    layers.push(walls_layer(&level, matcher));
+    layers.push(LayerData {
+        lights: level.lights.iter().map(light).collect(),
+        ..LayerData::new("Lights")
+    });
+    layers.push(LayerData {
+        labels: level.texts.iter().filter_map(label).collect(),
+        ..LayerData::new("Labels")
+    });
+    layers.retain(|layer| layer.child_count() > 0);
+
+    LevelData {
+        id: Uuid::new_v4(),
+        name: if level.label.is_empty() {
+            format!("Level {index}")
+        } else {
+            level.label
+        },
+        lighting: None,
+        background: None,
+        regions: Vec::new(),
+        links: Vec::new(),
+        layers,
+    }
+}
+
+/// The layer holding the walls of `level` and their doors, with a textured wall path along each
+/// wall whose texture matches an asset.
+fn walls_layer(level: &DdLevel, matcher: &mut AssetMatcher) -> LayerData {
+    let mut walls = Vec::new();
+    let mut paths = Vec::new();
+    for wall in &level.walls {
+        let points: Vec<_> = vectors(&wall.points)
+            .into_iter()
+            .map(|point| position(point).to_array())
+            .collect();
+        if points.len() < 2 {
+            continue;
+        }
+
+        if let Some(texture) = wall
+            .texture
+            .as_deref()
+            .and_then(|texture| matcher.asset(texture))
+        {
+            paths.push(WallPathData {
+                id: Uuid::new_v4(),
+                points: points.clone(),
+                closed: wall.closed,
+                width: WALL_WIDTH,
+                texture,
+                transform: Transform::IDENTITY,
+            });
+        }
+        let mut points = points;
+        if wall.closed {
+            points.push(points[0]);
+        }
+        walls.push(WallData {
+            id: Uuid::new_v4(),
+            points,
+            transform: Transform::IDENTITY,
+        });
+    }
+
+    LayerData {
+        walls,
+        paths,
+        portals: level
+            .walls
+            .iter()
+            .flat_map(|wall| &wall.portals)
+            .chain(&level.portals)
+            .filter_map(portal)
+            .collect(),
+        ..LayerData::new("Walls")
+    }
+}
+
+/// The transform of `object`, converted to the Y axis pointing up.
+fn object_transform(object: &DdObject) -> Transform {
+    let mut scale = object
+        .scale
+        .as_deref()
+        .and_then(vector)
+        .unwrap_or(Vec2::ONE);
+    if object.mirror {
+        scale.x = -scale.x;
+    }
+
+    Transform {
+        translation: position(vector(&object.position).unwrap_or_default()).extend(0.0),
+        // Dungeondraft rotates clockwise, as its Y axis points down.
+        rotation: Quat::from_rotation_z(-object.rotation),
+        scale: scale.extend(1.0),
+    }
+}
+
+/// Imports `portal`, spanning its radius on both sides of its center.
+fn portal(portal: &DdPortal) -> Option<PortalData> {
+    let center = vector(&portal.position)?;
+    let direction = portal
+        .direction
+        .as_deref()
+        .and_then(vector)
+        .unwrap_or(Vec2::X)
+        .normalize_or(Vec2::X);
+
+    Some(PortalData {
+        id: Uuid::new_v4(),
+        start: position(center - direction * portal.radius),
+        end: position(center + direction * portal.radius),
+        closed: portal.closed,
+        transform: Transform::IDENTITY,
+    })
+}
+
+/// Imports `light`, whose range is in grid cells.
+fn light(light: &DdLight) -> LightData {
+    LightData {
+        id: Uuid::new_v4(),
+        radius: light.range * CELL_SIZE,
+        color: light
+            .color
+            .as_deref()
+            .and_then(argb)
+            .unwrap_or(Color::WHITE),
+        intensity: light.intensity,
+        flicker: 0.0,
+        transform: Transform::from_translation(
+            position(vector(&light.position).unwrap_or_default()).extend(0.0),
+        ),
+    }
+}
+
+/// Imports `text` as a label, unless it's empty.
+fn label(text: &DdText) -> Option<LabelData> {
+    if text.text.trim().is_empty() {
+        return None;
+    }
+
+    let mut label = LabelData::new(
+        text.text.clone(),
+        text.font_size,
+        Transform::from_translation(
+            position(vector(&text.position).unwrap_or_default()).extend(0.0),
+        ),
+    );
+    if let Some(color) = text.font_color.as_deref().and_then(argb) {
+        label.color = color;
+    }
+    Some(label)
+}
+
+/// Converts a Dungeondraft position, whose Y axis points down, to one whose Y axis points up.
+fn position(point: Vec2) -> Vec2 {
+    Vec2::new(point.x, -point.y)
+}
+
+/// Parses a Godot `Vector2( x, y )`.
+fn vector(text: &str) -> Option<Vec2> {
+    match numbers(text).as_slice() {
+        [x, y] => Some(Vec2::new(*x, *y)),
+        _ => None,
+    }
+}
+
+/// Parses a Godot `PoolVector2Array( x0, y0, x1, y1, … )`.
+fn vectors(text: &str) -> Vec<Vec2> {
+    numbers(text)
+        .chunks_exact(2)
+        .map(|pair| Vec2::new(pair[0], pair[1]))
+        .collect()
+}
+
+/// The numbers between the parentheses of a Godot value.
+fn numbers(text: &str) -> Vec<f32> {
+    let inner = text
+        .split_once('(')
+        .and_then(|(_, rest)| rest.rsplit_once(')'))
+        .map_or(text, |(inner, _)| inner);
+
+    inner
+        .split(',')
+        .filter_map(|number| number.trim().parse().ok())
+        .collect()
+}
+
+/// Matches Dungeondraft textures to the assets of the registered packs.
+struct AssetMatcher<'a> {
+    /// The packs the assets are looked up in.
+    library: &'a AssetLibrary,
+    /// The indexes the assets are searched with.
+    indexes: &'a PackIndexes,
+    /// The asset each texture was matched to, `None` if none was found.
+    matches: HashMap<String, Option<PathBuf>>,
+}
+
+impl AssetMatcher<'_> {
+    /// The asset matching `texture`, a `res://` path.
+    fn asset(&mut self, texture: &str) -> Option<PathBuf> {
+        if let Some(asset) = self.matches.get(texture) {
+            return asset.clone();
+        }
+
+        let asset = self.find(texture);
+        self.matches.insert(texture.to_owned(), asset.clone());
+        asset
+    }
+
+    /// Searches the packs for the asset named like `texture`, falling back to the best match.
+    fn find(&self, texture: &str) -> Option<PathBuf> {
+        let stem = Path::new(texture).file_stem()?.to_string_lossy();
+        let query = stem.replace(['_', '-'], " ");
+
+        let mut best = None;
+        for (id, index) in self.indexes.iter() {
+            let Some(pack) = self.library.pack(id) else {
+                continue;
+            };
+            let Ok(results) = index.search(&query, MATCH_CANDIDATES) else {
+                continue;
+            };
+
+            if let Some(exact) = results.iter().find(|path| {
+                path.file_stem()
+                    .is_some_and(|candidate| candidate.eq_ignore_ascii_case(stem.as_ref()))
+            }) {
+                return Some(pack.root.join(exact));
+            }
+            if best.is_none() {
+                best = results.first().map(|path| pack.root.join(path));
+            }
+        }
+
+        best
+    }
+}
@@ -0,0 +1,398 @@
+//! Registers the tabletop exporters of this crate with the [`ExportRegistry`].
+
+use crate::{
+    FguSettings, FoundrySettings, OwlbearSettings, PackageError, PdfPage, PdfSettings,
+    Roll20Settings, UvttSettings, export_fgu, export_foundry, export_owlbear, export_pdf,
+    export_roll20, export_uvtt,
+};
+use bevy::prelude::{App, Plugin};
+use dungeonrs_core::{
+    ExportCapabilities, ExportError, ExportInput, ExportOutput, ExportRegistry, ExportSetting,
+    ExportSettingKind, ExportSettings, Exporter,
+};
+
+/// Registers the Roll20, Owlbear Rodeo, Fantasy Grounds Unity, Foundry VTT, Universal VTT and PDF
+/// exporters.
+pub struct IoPlugin;
+
+impl Plugin for IoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ExportRegistry>();
+        app.world_mut()
+            .resource_mut::<ExportRegistry>()
+            .register(Roll20Exporter)
+            .register(OwlbearExporter)
+            .register(FguExporter)
+            .register(FoundryExporter)
+            .register(UvttExporter)
+            .register(PdfExporter);
+    }
+}
+
+/// Exports through [`export_roll20`].
+pub struct Roll20Exporter;
+
+/// Exports through [`export_owlbear`].
+pub struct OwlbearExporter;
+
+/// Exports through [`export_fgu`].
+pub struct FguExporter;
+
+/// Exports through [`export_foundry`], with the walls, portals and lights of the export.
+pub struct FoundryExporter;
+
+/// Exports through [`export_uvtt`], with the walls, portals and lights of the export.
+pub struct UvttExporter;
+
+/// Exports through [`export_pdf`], as a single page named after the file.
+pub struct PdfExporter;
+
+impl Exporter for Roll20Exporter {
+    fn id(&self) -> &'static str {
+        "roll20"
+    }
+
+    fn name(&self) -> String {
+        "Roll20".into()
+    }
+
+    fn capabilities(&self) -> ExportCapabilities {
+        ExportCapabilities {
+            output: ExportOutput::Directory,
+            extensions: &[],
+        }
+    }
+
+    fn settings(&self) -> Vec<ExportSetting> {
+        let defaults = Roll20Settings::default();
+        vec![
+            cell_size_setting(defaults.cell_size),
+            number_setting("scale", "Cell distance", f64::from(defaults.scale)),
+            text_setting("units", "Distance unit", defaults.units),
+        ]
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "distances are small enough for f32"
+    )]
+    fn run(&self, input: ExportInput, settings: &ExportSettings) -> Result<(), ExportError> {
+        let defaults = Roll20Settings::default();
+        let settings = Roll20Settings {
+            cell_size: cell_size(settings, defaults.cell_size),
+            scale: settings
+                .number("scale")
+                .map_or(defaults.scale, |scale| scale as f32),
+            units: settings.text("units").map_or(defaults.units, Into::into),
+            ..defaults
+        };
+
+        export_roll20(&input.image, &[], &settings, &input.path)
+            .map(drop)
+            .map_err(exporter_error)
+    }
+}
+
+impl Exporter for OwlbearExporter {
+    fn id(&self) -> &'static str {
+        "owlbear"
+    }
+
+    fn name(&self) -> String {
+        "Owlbear Rodeo".into()
+    }
+
+    fn capabilities(&self) -> ExportCapabilities {
+        ExportCapabilities {
+            output: ExportOutput::Directory,
+            extensions: &[],
+        }
+    }
+
+    fn settings(&self) -> Vec<ExportSetting> {
+        let defaults = OwlbearSettings::default();
+        vec![
+            cell_size_setting(defaults.cell_size),
+            text_setting("scale", "Cell distance", defaults.scale),
+        ]
+    }
+
+    fn run(&self, input: ExportInput, settings: &ExportSettings) -> Result<(), ExportError> {
+        let defaults = OwlbearSettings::default();
+        let settings = OwlbearSettings {
+            cell_size: cell_size(settings, defaults.cell_size),
+            scale: settings.text("scale").map_or(defaults.scale, Into::into),
+        };
+
+        export_owlbear(&input.image, &[], &settings, &input.path)
+            .map(drop)
+            .map_err(exporter_error)
+    }
+}
+
+impl Exporter for FguExporter {
+    fn id(&self) -> &'static str {
+        "fgu"
+    }
+
+    fn name(&self) -> String {
+        "Fantasy Grounds Unity".into()
+    }
+
+    fn capabilities(&self) -> ExportCapabilities {
+        ExportCapabilities {
+            output: ExportOutput::File,
+            extensions: &["mod"],
+        }
+    }
+
+    fn settings(&self) -> Vec<ExportSetting> {
+        let defaults = FguSettings::default();
+        vec![
+            text_setting("name", "Module name", defaults.name),
+            text_setting("author", "Author", defaults.author),
+            cell_size_setting(defaults.cell_size),
+            number_setting("distance", "Cell distance", f64::from(defaults.distance)),
+            text_setting("units", "Distance unit", defaults.units),
+        ]
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "distances are small enough for f32"
+    )]
+    fn run(&self, input: ExportInput, settings: &ExportSettings) -> Result<(), ExportError> {
+        let defaults = FguSettings::default();
+        let settings = FguSettings {
+            name: settings.text("name").map_or(defaults.name, Into::into),
+            author: settings.text("author").map_or(defaults.author, Into::into),
+            cell_size: cell_size(settings, defaults.cell_size),
+            distance: settings
+                .number("distance")
+                .map_or(defaults.distance, |distance| distance as f32),
+            units: settings.text("units").map_or(defaults.units, Into::into),
+        };
+
+        export_fgu(&input.image, &[], &settings, &input.path).map_err(exporter_error)
+    }
+}
+
+impl Exporter for FoundryExporter {
+    fn id(&self) -> &'static str {
+        "foundry"
+    }
+
+    fn name(&self) -> String {
+        "Foundry VTT".into()
+    }
+
+    fn capabilities(&self) -> ExportCapabilities {
+        ExportCapabilities {
+            output: ExportOutput::File,
+            extensions: &["json", "zip"],
+        }
+    }
+
+    fn settings(&self) -> Vec<ExportSetting> {
+        let defaults = FoundrySettings::default();
+        vec![
+            text_setting("name", "Scene name", defaults.name),
+            cell_size_setting(defaults.cell_size),
+            number_setting("distance", "Cell distance", f64::from(defaults.distance)),
+            text_setting("units", "Distance unit", defaults.units),
+            ExportSetting {
+                key: "padding",
+                label: "Padding".into(),
+                kind: ExportSettingKind::Number {
+                    default: f64::from(defaults.padding),
+                    min: 0.0,
+                    max: 1.0,
+                },
+            },
+            ExportSetting {
+                key: "package",
+                label: "Zip as a module".into(),
+                kind: ExportSettingKind::Bool(defaults.package),
+            },
+        ]
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "distances and padding are small enough for f32"
+    )]
+    fn run(&self, input: ExportInput, settings: &ExportSettings) -> Result<(), ExportError> {
+        let defaults = FoundrySettings::default();
+        let settings = FoundrySettings {
+            name: settings.text("name").map_or(defaults.name, Into::into),
+            cell_size: cell_size(settings, defaults.cell_size),
+            distance: settings
+                .number("distance")
+                .map_or(defaults.distance, |distance| distance as f32),
+            units: settings.text("units").map_or(defaults.units, Into::into),
+            padding: settings
+                .number("padding")
+                .map_or(defaults.padding, |padding| padding.clamp(0.0, 1.0) as f32),
+            package: settings.bool("package").unwrap_or(defaults.package),
+        };
+
+        export_foundry(&input.image, &input.scene, &settings, &input.path)
+            .map(drop)
+            .map_err(exporter_error)
+    }
+}
+
+impl Exporter for UvttExporter {
+    fn id(&self) -> &'static str {
+        "uvtt"
+    }
+
+    fn name(&self) -> String {
+        "Universal VTT".into()
+    }
+
+    fn capabilities(&self) -> ExportCapabilities {
+        ExportCapabilities {
+            output: ExportOutput::File,
+            extensions: &["dd2vtt", "uvtt"],
+        }
+    }
+
+    fn settings(&self) -> Vec<ExportSetting> {
+        let defaults = UvttSettings::default();
+        vec![
+            cell_size_setting(defaults.cell_size),
+            ExportSetting {
+                key: "baked_lighting",
+                label: "Lighting rendered into the map".into(),
+                kind: ExportSettingKind::Bool(defaults.baked_lighting),
+            },
+        ]
+    }
+
+    fn run(&self, input: ExportInput, settings: &ExportSettings) -> Result<(), ExportError> {
+        let defaults = UvttSettings::default();
+        let settings = UvttSettings {
+            cell_size: cell_size(settings, defaults.cell_size),
+            baked_lighting: settings
+                .bool("baked_lighting")
+                .unwrap_or(defaults.baked_lighting),
+        };
+
+        export_uvtt(&input.image, &input.scene, &settings, &input.path).map_err(exporter_error)
+    }
+}
+
+impl Exporter for PdfExporter {
+    fn id(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn name(&self) -> String {
+        "PDF".into()
+    }
+
+    fn capabilities(&self) -> ExportCapabilities {
+        ExportCapabilities {
+            output: ExportOutput::File,
+            extensions: &["pdf"],
+        }
+    }
+
+    fn settings(&self) -> Vec<ExportSetting> {
+        let defaults = PdfSettings::default();
+        vec![
+            text_setting("title", "Title", defaults.title),
+            ExportSetting {
+                key: "dpi",
+                label: "Pixels per inch".into(),
+                kind: ExportSettingKind::Number {
+                    default: f64::from(defaults.dpi),
+                    min: 1.0,
+                    max: MAX_DPI,
+                },
+            },
+        ]
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "the value is clamped to the setting's range"
+    )]
+    fn run(&self, input: ExportInput, settings: &ExportSettings) -> Result<(), ExportError> {
+        let defaults = PdfSettings::default();
+        let settings = PdfSettings {
+            dpi: settings
+                .number("dpi")
+                .map_or(defaults.dpi, |dpi| dpi.clamp(1.0, MAX_DPI) as f32),
+            title: settings.text("title").map_or(defaults.title, Into::into),
+        };
+        let page = PdfPage {
+            name: input.path.file_stem().map_or_else(
+                || settings.title.clone(),
+                |stem| stem.to_string_lossy().into_owned(),
+            ),
+            image: input.image,
+        };
+
+        export_pdf(&[page], &settings, &input.path).map_err(exporter_error)
+    }
+}
+
+/// The highest resolution the PDF exporter accepts, in pixels per inch.
+const MAX_DPI: f64 = 2400.0;
+
+/// The largest grid cell the exporters accept, in pixels.
+const MAX_CELL_SIZE: f64 = 1024.0;
+
+/// The `cell_size` setting shared by every exporter.
+fn cell_size_setting(default: u32) -> ExportSetting {
+    ExportSetting {
+        key: "cell_size",
+        label: "Cell size (pixels)".into(),
+        kind: ExportSettingKind::Number {
+            default: f64::from(default),
+            min: 1.0,
+            max: MAX_CELL_SIZE,
+        },
+    }
+}
+
+/// Reads the `cell_size` setting, falling back to `default`.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "the value is clamped to the setting's range"
+)]
+fn cell_size(settings: &ExportSettings, default: u32) -> u32 {
+    settings.number("cell_size").map_or(default, |cell_size| {
+        cell_size.round().clamp(1.0, MAX_CELL_SIZE) as u32
+    })
+}
+
+/// A non-negative numeric setting.
+fn number_setting(key: &'static str, label: &str, default: f64) -> ExportSetting {
+    ExportSetting {
+        key,
+        label: label.into(),
+        kind: ExportSettingKind::Number {
+            default,
+            min: 0.0,
+            max: f64::MAX,
+        },
+    }
+}
+
+/// A free text setting.
+fn text_setting(key: &'static str, label: &str, default: String) -> ExportSetting {
+    ExportSetting {
+        key,
+        label: label.into(),
+        kind: ExportSettingKind::Text(default),
+    }
+}
+
+/// Wraps a [`PackageError`] in an [`ExportError`].
+fn exporter_error(error: PackageError) -> ExportError {
+    ExportError::Exporter(Box::new(error))
+}
@@ -0,0 +1,174 @@
+//! Exports a rendered map as a Fantasy Grounds Unity module.
+//!
+//! A module is a zip archive renamed to `.mod`, holding a `definition.xml` describing the module
+//! and a `db.xml` with its records. The map becomes a single image record, with its grid and the
+//! line-of-sight occluders Fantasy Grounds uses for its lighting and vision.
+
+use crate::archive::ZipWriter;
+use crate::package::{PackageError, encode_png, write_file};
+use crate::xml::escape;
+use image::RgbaImage;
+use std::fmt::Write;
+use std::path::Path;
+
+/// The ruleset version the module is written for, supported by every ruleset built on `CoreRPG`.
+const RELEASE: &str = "8.1|CoreRPG:4.1";
+
+/// Configures the Fantasy Grounds Unity export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FguSettings {
+    /// The name of the module and of the image record.
+    pub name: String,
+    /// The author shown in the module list.
+    pub author: String,
+    /// The size of a grid cell in the rendered map, in pixels.
+    pub cell_size: u32,
+    /// The distance a grid cell represents.
+    pub distance: f32,
+    /// The unit of [`distance`](Self::distance).
+    pub units: String,
+}
+
+impl Default for FguSettings {
+    fn default() -> Self {
+        Self {
+            name: "Map".into(),
+            author: String::new(),
+            cell_size: 50,
+            distance: 5.0,
+            units: "ft".into(),
+        }
+    }
+}
+
+/// How an [`FguOccluder`] blocks line of sight.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FguOccluderKind {
+    /// Always blocks line of sight.
+    Wall,
+    /// Blocks line of sight until opened.
+    Door,
+    /// Blocks line of sight of tokens outside of it, such as a forest.
+    Terrain,
+}
+
+/// A line blocking line of sight, usually one wall of the map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FguOccluder {
+    /// The points of the line, in map pixels from the top left.
+    pub points: Vec<[f32; 2]>,
+    /// How the occluder blocks line of sight.
+    pub kind: FguOccluderKind,
+}
+
+/// Writes `map` and its `occluders` as a Fantasy Grounds Unity module to `path`.
+///
+/// # Errors
+/// Returns an error if the map can't be encoded or the module can't be written.
+pub fn export_fgu(
+    map: &RgbaImage,
+    occluders: &[FguOccluder],
+    settings: &FguSettings,
+    path: &Path,
+) -> Result<(), PackageError> {
+    let io_error = |source| PackageError::Io {
+        path: path.to_owned(),
+        source,
+    };
+
+    let mut archive = ZipWriter::default();
+    archive
+        .add("definition.xml", definition(settings).as_bytes())
+        .map_err(io_error)?;
+    archive
+        .add("db.xml", database(map, occluders, settings).as_bytes())
+        .map_err(io_error)?;
+    archive
+        .add("images/map.png", &encode_png(map)?)
+        .map_err(io_error)?;
+
+    write_file(path, &archive.finish().map_err(io_error)?)
+}
+
+/// Writes the `definition.xml` of the module.
+fn definition(settings: &FguSettings) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<root version="4.1" release="{RELEASE}">
+	<name>{}</name>
+	<category></category>
+	<author>{}</author>
+	<ruleset>Any</ruleset>
+</root>
+"#,
+        escape(&settings.name),
+        escape(&settings.author),
+    )
+}
+
+/// Writes the `db.xml` of the module, holding the image record of `map`.
+///
+/// Fantasy Grounds positions occluders relative to the center of the image, with the Y axis
+/// pointing up.
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "image dimensions are far below the range where f32 loses precision"
+)]
+fn database(map: &RgbaImage, occluders: &[FguOccluder], settings: &FguSettings) -> String {
+    let center_x = map.width() as f32 / 2.0;
+    let center_y = map.height() as f32 / 2.0;
+
+    let mut records = String::new();
+    for (id, occluder) in occluders.iter().enumerate() {
+        let points = occluder
+            .points
+            .iter()
+            .map(|[x, y]| format!("{},{}", x - center_x, center_y - y))
+            .collect::<Vec<_>>()
+            .join(",");
+        let kind = match occluder.kind {
+            FguOccluderKind::Wall => "",
+            FguOccluderKind::Door => "\n\t\t\t\t\t\t<door />",
+            FguOccluderKind::Terrain => "\n\t\t\t\t\t\t<terrain />",
+        };
+        // Writing into a `String` can't fail.
+        let _ = write!(
+            records,
+            "\n\t\t\t\t\t<occluder>\n\t\t\t\t\t\t<id>{id}</id>\n\t\t\t\t\t\t<points>{points}</points>{kind}\n\t\t\t\t\t</occluder>"
+        );
+    }
+
+    let cell_size = settings.cell_size.max(1);
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<root version="4.1" release="{RELEASE}">
+	<image>
+		<id-00001>
+			<image type="image">
+				<layers>
+					<layer>
+						<name>map.png</name>
+						<id>0</id>
+						<parentid>-1</parentid>
+						<bitmap>images/map.png</bitmap>
+					</layer>
+				</layers>
+				<grid>on</grid>
+				<gridsize>{cell_size},{cell_size}</gridsize>
+				<gridoffset>0,0</gridoffset>
+				<gridsnap>on</gridsnap>
+				<distancebaseunit>{}</distancebaseunit>
+				<distancesuffix>{}</distancesuffix>
+				<occluders>{records}
+				</occluders>
+			</image>
+			<name type="string">{}</name>
+		</id-00001>
+	</image>
+</root>
+"#,
+        settings.distance,
+        escape(&settings.units),
+        escape(&settings.name),
+    )
+}
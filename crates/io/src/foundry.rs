@@ -0,0 +1,383 @@
+//! Exports a rendered map as a Foundry VTT scene.
+//!
+//! The scene is the JSON Foundry VTT reads through "Import Data" on a scene: its size, padding and
+//! grid, the walls and doors of the map and its lights, with the map image as background. The
+//! stairs, ladders and teleporters leading to other levels are kept in the flags of the scene, for
+//! modules moving tokens between scenes. It's
+//! written next to the map image, or zipped along with it into a module package so the
+//! background is found once the module is installed.
+//!
+//! Foundry VTT adds the padding around the map, so walls and lights are offset by it.
+
+use crate::archive::ZipWriter;
+use crate::package::{PackageError, encode_png, write_file, write_json};
+use bevy::color::{Color, ColorToPacked};
+use bevy::math::Vec2;
+use dungeonrs_core::ExportScene;
+use image::RgbaImage;
+use serde::Serialize;
+use std::path::Path;
+
+/// The Foundry VTT version the scenes are written for.
+const COMPATIBILITY: &str = "12";
+
+/// The way walls and closed doors restrict movement, sight, light and sound: normally.
+const RESTRICTION: u8 = 20;
+
+/// Configures the Foundry VTT export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoundrySettings {
+    /// The name of the scene, and of the module when packaged.
+    pub name: String,
+    /// The size of a grid cell in the rendered map, in pixels.
+    pub cell_size: u32,
+    /// The distance a grid cell represents.
+    pub distance: f32,
+    /// The unit of [`distance`](Self::distance).
+    pub units: String,
+    /// The empty space Foundry VTT adds around the map, as a fraction of its size.
+    pub padding: f32,
+    /// Whether the scene and the map are zipped into a module package instead of written next to
+    /// each other.
+    pub package: bool,
+}
+
+impl Default for FoundrySettings {
+    fn default() -> Self {
+        Self {
+            name: "Map".into(),
+            cell_size: 100,
+            distance: 5.0,
+            units: "ft".into(),
+            padding: 0.25,
+            package: false,
+        }
+    }
+}
+
+/// An exported Foundry VTT scene.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FoundryScene {
+    /// The name of the scene.
+    pub name: String,
+    /// The width of the map, in pixels.
+    pub width: u32,
+    /// The height of the map, in pixels.
+    pub height: u32,
+    /// The empty space around the map, as a fraction of its size.
+    pub padding: f32,
+    /// The map image.
+    pub background: FoundryBackground,
+    /// The grid of the scene.
+    pub grid: FoundryGrid,
+    /// The walls and doors of the scene.
+    pub walls: Vec<FoundryWall>,
+    /// The lights of the scene.
+    pub lights: Vec<FoundryLight>,
+    /// The data Foundry VTT keeps for modules, by module.
+    #[serde(skip_serializing_if = "FoundryFlags::is_empty")]
+    pub flags: FoundryFlags,
+}
+
+/// The flags of a [`FoundryScene`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FoundryFlags {
+    /// The data written by `DungeonRS`.
+    pub dungeonrs: FoundryLinks,
+}
+
+impl FoundryFlags {
+    /// Returns whether there's nothing to keep in the flags.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.dungeonrs.links.is_empty()
+    }
+}
+
+/// The links of a [`FoundryScene`] to the scenes of other levels.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FoundryLinks {
+    /// The stairs, ladders and teleporters of the scene.
+    pub links: Vec<FoundryLink>,
+}
+
+/// A link of a [`FoundryScene`] to the scene of another level.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FoundryLink {
+    /// The horizontal position of the link, in scene pixels.
+    pub x: f32,
+    /// The vertical position of the link, in scene pixels.
+    pub y: f32,
+    /// The kind of link: `stairs`, `ladder` or `teleporter`.
+    pub kind: String,
+    /// The name of the level the link leads to.
+    pub level: String,
+    /// Where the link arrives on the scene of the other level, as `[x, y]` in scene pixels.
+    pub destination: [f32; 2],
+}
+
+/// The background of a [`FoundryScene`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FoundryBackground {
+    /// The path of the map image, as Foundry VTT resolves it.
+    pub src: String,
+}
+
+/// The grid of a [`FoundryScene`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FoundryGrid {
+    /// The type of grid, always `1` for square cells.
+    #[serde(rename = "type")]
+    pub kind: u8,
+    /// The size of a cell, in pixels.
+    pub size: u32,
+    /// The distance a cell represents.
+    pub distance: f32,
+    /// The unit of the distance.
+    pub units: String,
+}
+
+/// A wall segment, or a door, of a [`FoundryScene`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FoundryWall {
+    /// The ends of the segment as `[x0, y0, x1, y1]`, in scene pixels.
+    pub c: [f32; 4],
+    /// How the wall restricts movement.
+    #[serde(rename = "move")]
+    pub movement: u8,
+    /// How the wall restricts sight.
+    pub sight: u8,
+    /// How the wall restricts light.
+    pub light: u8,
+    /// How the wall restricts sound.
+    pub sound: u8,
+    /// `0` for a wall, `1` for a door.
+    pub door: u8,
+    /// The state of a door: `0` closed, `1` open.
+    pub ds: u8,
+}
+
+/// A light of a [`FoundryScene`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FoundryLight {
+    /// The horizontal position of the light, in scene pixels.
+    pub x: f32,
+    /// The vertical position of the light, in scene pixels.
+    pub y: f32,
+    /// How far the light reaches and its colour.
+    pub config: FoundryLightConfig,
+}
+
+/// The appearance of a [`FoundryLight`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FoundryLightConfig {
+    /// The distance the dim light reaches, in grid units.
+    pub dim: f32,
+    /// The distance the bright light reaches, in grid units.
+    pub bright: f32,
+    /// The colour of the light, as `#RRGGBB`.
+    pub color: String,
+    /// The strength of the colour tint.
+    pub alpha: f32,
+}
+
+/// The `module.json` of a packaged scene.
+#[derive(Serialize)]
+struct Manifest<'a> {
+    /// The identifier of the module, also its directory name once installed.
+    id: &'a str,
+    /// The name shown in the module list.
+    title: &'a str,
+    /// The version of the module.
+    version: &'static str,
+    /// The Foundry VTT versions the module works with.
+    compatibility: Compatibility,
+}
+
+/// The `compatibility` object of a [`Manifest`].
+#[derive(Serialize)]
+struct Compatibility {
+    /// The oldest supported version.
+    minimum: &'static str,
+    /// The version the module was made for.
+    verified: &'static str,
+}
+
+/// Writes `map` and the walls, doors, lights and level links of its `scene` as a Foundry VTT scene
+/// to `path`.
+///
+/// The map is written as a PNG next to the scene, or zipped along with it into a module package
+/// when [`FoundrySettings::package`] is set.
+///
+/// # Errors
+/// Returns an error if the map can't be encoded or the files can't be written.
+pub fn export_foundry(
+    map: &RgbaImage,
+    scene: &ExportScene,
+    settings: &FoundrySettings,
+    path: &Path,
+) -> Result<FoundryScene, PackageError> {
+    let image = encode_png(map)?;
+    if !settings.package {
+        let image_path = path.with_extension("png");
+        write_file(&image_path, &image)?;
+        let src = image_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let foundry = foundry_scene(map, scene, settings, src);
+        write_json(path, &foundry)?;
+        return Ok(foundry);
+    }
+
+    let io_error = |source| PackageError::Io {
+        path: path.to_owned(),
+        source,
+    };
+    let id = module_id(&settings.name);
+    let foundry = foundry_scene(map, scene, settings, format!("modules/{id}/maps/map.png"));
+    let manifest = Manifest {
+        id: &id,
+        title: &settings.name,
+        version: "1.0.0",
+        compatibility: Compatibility {
+            minimum: COMPATIBILITY,
+            verified: COMPATIBILITY,
+        },
+    };
+
+    let mut archive = ZipWriter::default();
+    archive
+        .add(
+            &format!("{id}/module.json"),
+            &serde_json::to_vec_pretty(&manifest)?,
+        )
+        .map_err(io_error)?;
+    archive
+        .add(
+            &format!("{id}/scenes/{id}.json"),
+            &serde_json::to_vec_pretty(&foundry)?,
+        )
+        .map_err(io_error)?;
+    archive
+        .add(&format!("{id}/maps/map.png"), &image)
+        .map_err(io_error)?;
+    write_file(path, &archive.finish().map_err(io_error)?)?;
+
+    Ok(foundry)
+}
+
+/// The scene of `map` and its `scene`, with the map image at `src`.
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "map sizes are far below 2^23 pixels"
+)]
+fn foundry_scene(
+    map: &RgbaImage,
+    scene: &ExportScene,
+    settings: &FoundrySettings,
+    src: String,
+) -> FoundryScene {
+    let cell_size = settings.cell_size.max(1);
+    // Foundry VTT rounds the padding up to whole cells.
+    let padding = settings.padding.max(0.0);
+    let offset = Vec2::new(
+        (map.width() as f32 * padding / cell_size as f32).ceil(),
+        (map.height() as f32 * padding / cell_size as f32).ceil(),
+    ) * cell_size as f32;
+    let wall = |start: Vec2, end: Vec2, door: u8, ds: u8| {
+        let (start, end) = (start + offset, end + offset);
+        FoundryWall {
+            c: [start.x, start.y, end.x, end.y],
+            movement: RESTRICTION,
+            sight: RESTRICTION,
+            light: RESTRICTION,
+            sound: RESTRICTION,
+            door,
+            ds,
+        }
+    };
+
+    let walls = scene
+        .walls
+        .iter()
+        .flat_map(|points| points.windows(2))
+        .map(|segment| wall(segment[0], segment[1], 0, 0))
+        .chain(
+            scene
+                .portals
+                .iter()
+                .map(|portal| wall(portal.start, portal.end, 1, u8::from(!portal.closed))),
+        )
+        .collect();
+    let lights = scene
+        .lights
+        .iter()
+        .map(|light| {
+            let position = light.position + offset;
+            let dim = light.radius / cell_size as f32 * settings.distance;
+            FoundryLight {
+                x: position.x,
+                y: position.y,
+                config: FoundryLightConfig {
+                    dim,
+                    bright: dim / 2.0,
+                    color: hex_rgb(light.color),
+                    alpha: (light.intensity * 0.5).clamp(0.0, 1.0),
+                },
+            }
+        })
+        .collect();
+    let links = scene
+        .links
+        .iter()
+        .map(|link| {
+            // The scene of the other level is padded the same, as long as its map is as large.
+            let (position, destination) = (link.position + offset, link.destination + offset);
+            FoundryLink {
+                x: position.x,
+                y: position.y,
+                kind: link.kind.name().into(),
+                level: link.level.clone(),
+                destination: destination.to_array(),
+            }
+        })
+        .collect();
+
+    FoundryScene {
+        name: settings.name.clone(),
+        width: map.width(),
+        height: map.height(),
+        padding,
+        background: FoundryBackground { src },
+        grid: FoundryGrid {
+            kind: 1,
+            size: cell_size,
+            distance: settings.distance,
+            units: settings.units.clone(),
+        },
+        walls,
+        lights,
+        flags: FoundryFlags {
+            dungeonrs: FoundryLinks { links },
+        },
+    }
+}
+
+/// Turns `name` into a module identifier: lowercase letters, digits and dashes.
+fn module_id(name: &str) -> String {
+    let id = name
+        .to_lowercase()
+        .split(|character: char| !character.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if id.is_empty() { "map".into() } else { id }
+}
+
+/// Writes `color` as `#RRGGBB`.
+fn hex_rgb(color: Color) -> String {
+    let [red, green, blue, _] = color.to_srgba().to_u8_array();
+
+    format!("#{red:02x}{green:02x}{blue:02x}")
+}
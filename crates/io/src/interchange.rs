@@ -0,0 +1,109 @@
+//! Reads and writes projects as plain JSON, so external tools and scripts can generate or consume
+//! maps without going through the binary save format.
+//!
+//! The structure of an interchange file is described in the crate documentation.
+
+use dungeonrs_core::SaveFile;
+use dungeonrs_serialization::{MigrationRegistry, Versioned};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The value of the `format` field identifying an interchange file.
+pub const INTERCHANGE_FORMAT: &str = "dungeonrs-map";
+
+/// Errors that can occur while reading or writing an interchange file.
+#[derive(Error, Debug)]
+pub enum InterchangeError {
+    /// The file couldn't be read or written.
+    #[error("failed to access '{path}': {source}")]
+    Io {
+        /// The file that couldn't be accessed.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+    /// The file isn't valid JSON, or doesn't describe a valid project.
+    #[error("invalid interchange file: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The file is JSON, but not an interchange file.
+    #[error("not a DungeonRS interchange file")]
+    UnknownFormat,
+    /// The file was written by a newer version, or couldn't be upgraded.
+    #[error(transparent)]
+    Version(#[from] dungeonrs_serialization::Error),
+}
+
+/// The top-level object of an interchange file being written.
+#[derive(Serialize)]
+struct Document<'a> {
+    /// Always [`INTERCHANGE_FORMAT`].
+    format: &'static str,
+    /// The version of the project structure.
+    version: u32,
+    /// The project.
+    project: &'a SaveFile,
+}
+
+/// Writes `project` as an interchange file to `path`.
+///
+/// # Errors
+/// Returns an error if the file can't be written.
+pub fn export_interchange(project: &SaveFile, path: &Path) -> Result<(), InterchangeError> {
+    let document = Document {
+        format: INTERCHANGE_FORMAT,
+        version: SaveFile::VERSION,
+        project,
+    };
+    let json = serde_json::to_vec_pretty(&document)?;
+
+    fs::write(path, json).map_err(|source| InterchangeError::Io {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+/// Reads the interchange file at `path`, upgrading it with the migrations in `migrations` when it
+/// was written by an older version.
+///
+/// # Errors
+/// Returns an error if the file can't be read, isn't an interchange file or can't be upgraded.
+pub fn import_interchange(
+    path: &Path,
+    migrations: &MigrationRegistry,
+) -> Result<SaveFile, InterchangeError> {
+    let bytes = fs::read(path).map_err(|source| InterchangeError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let mut document: Value = serde_json::from_slice(&bytes)?;
+
+    if document.get("format").and_then(Value::as_str) != Some(INTERCHANGE_FORMAT) {
+        return Err(InterchangeError::UnknownFormat);
+    }
+    let version = document
+        .get("version")
+        .and_then(Value::as_u64)
+        .and_then(|version| u32::try_from(version).ok())
+        .ok_or(InterchangeError::UnknownFormat)?;
+    if version > SaveFile::VERSION {
+        return Err(dungeonrs_serialization::Error::UnsupportedVersion {
+            kind: SaveFile::KIND,
+            found: version,
+            supported: SaveFile::VERSION,
+        }
+        .into());
+    }
+
+    let project = document
+        .get_mut("project")
+        .map(Value::take)
+        .ok_or(InterchangeError::UnknownFormat)?;
+    let project = migrations.migrate(SaveFile::KIND, version, SaveFile::VERSION, project)?;
+
+    Ok(serde_json::from_value(project)?)
+}
@@ -0,0 +1,44 @@
+#![doc = include_str!("../README.md")]
+
+mod archive;
+mod dungeondraft;
+mod exporters;
+mod fgu;
+mod foundry;
+mod interchange;
+mod owlbear;
+mod package;
+mod pdf;
+mod roll20;
+mod tiled;
+mod uvtt;
+mod wonderdraft;
+mod xml;
+
+pub use dungeondraft::{DungeondraftError, DungeondraftImport, import_dungeondraft};
+pub use exporters::{
+    FguExporter, FoundryExporter, IoPlugin, OwlbearExporter, PdfExporter, Roll20Exporter,
+    UvttExporter,
+};
+pub use fgu::{FguOccluder, FguOccluderKind, FguSettings, export_fgu};
+pub use foundry::{
+    FoundryBackground, FoundryFlags, FoundryGrid, FoundryLight, FoundryLightConfig, FoundryLink,
+    FoundryLinks, FoundryScene, FoundrySettings, FoundryWall, export_foundry,
+};
+pub use interchange::{
+    INTERCHANGE_FORMAT, InterchangeError, export_interchange, import_interchange,
+};
+pub use owlbear::{
+    OwlbearAttachment, OwlbearGrid, OwlbearMap, OwlbearPlacement, OwlbearScene, OwlbearSettings,
+    export_owlbear,
+};
+pub use package::PackageError;
+pub use pdf::{PdfPage, PdfSettings, export_pdf};
+pub use roll20::{
+    GmOverlay, ROLL20_CELL_SIZE, Roll20Grid, Roll20Manifest, Roll20Overlay, Roll20Page,
+    Roll20Settings, Roll20Slice, export_roll20,
+};
+pub use tiled::{TileRef, TiledError, TiledImport, TilesetMapping, import_tiled};
+pub use uvtt::{UvttError, UvttOptions, UvttSettings, export_uvtt, import_uvtt};
+pub use wonderdraft::{WonderdraftError, WonderdraftOptions, import_wonderdraft};
+pub use xml::XmlError;
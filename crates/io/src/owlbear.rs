@@ -0,0 +1,162 @@
+//! Exports a rendered map as an Owlbear Rodeo scene bundle.
+//!
+//! The bundle is a directory holding the map image, the images of its attachments and a
+//! `scene.json` describing the grid of the map and where each attachment is placed, matching the
+//! properties Owlbear Rodeo asks for when adding a map to a room.
+
+use crate::package::{PackageError, encode_png, write_file, write_json};
+use image::RgbaImage;
+use serde::Serialize;
+use std::path::Path;
+
+/// Configures the Owlbear Rodeo export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwlbearSettings {
+    /// The size of a grid cell in the rendered map, in pixels.
+    pub cell_size: u32,
+    /// The distance a grid cell represents, including its unit (for example `5ft`).
+    pub scale: String,
+}
+
+impl Default for OwlbearSettings {
+    fn default() -> Self {
+        Self {
+            cell_size: 150,
+            scale: "5ft".into(),
+        }
+    }
+}
+
+/// An image attached to the map, such as a door or a prop that should remain movable.
+#[derive(Debug, Clone)]
+pub struct OwlbearAttachment {
+    /// The name shown in Owlbear Rodeo, also used for its file name.
+    pub name: String,
+    /// The image of the attachment.
+    pub image: RgbaImage,
+    /// The position of the attachment's center on the map, in map pixels.
+    pub position: [f32; 2],
+    /// The clockwise rotation of the attachment, in degrees.
+    pub rotation: f32,
+    /// The scale of the attachment relative to its image size.
+    pub scale: f32,
+}
+
+/// The `scene.json` of an exported Owlbear Rodeo bundle.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OwlbearScene {
+    /// The version of the scene format.
+    pub version: u32,
+    /// The map of the scene.
+    pub map: OwlbearMap,
+    /// The attachments placed on the map.
+    pub attachments: Vec<OwlbearPlacement>,
+}
+
+/// The map image of an [`OwlbearScene`] and its grid.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OwlbearMap {
+    /// The file name of the map image, relative to the bundle directory.
+    pub file: String,
+    /// The width of the map image, in pixels.
+    pub width: u32,
+    /// The height of the map image, in pixels.
+    pub height: u32,
+    /// The grid of the map.
+    pub grid: OwlbearGrid,
+}
+
+/// The grid of an [`OwlbearMap`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OwlbearGrid {
+    /// The type of grid, always `SQUARE`.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// The size of a cell in the map image, in pixels.
+    pub dpi: u32,
+    /// The number of cells horizontally.
+    pub columns: u32,
+    /// The number of cells vertically.
+    pub rows: u32,
+    /// The distance a cell represents.
+    pub scale: String,
+}
+
+/// The placement of an attachment in an [`OwlbearScene`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OwlbearPlacement {
+    /// The name of the attachment.
+    pub name: String,
+    /// The file name of the attachment image, relative to the bundle directory.
+    pub file: String,
+    /// The position of the attachment's center, in map pixels from the top left.
+    pub position: [f32; 2],
+    /// The clockwise rotation, in degrees.
+    pub rotation: f32,
+    /// The scale relative to the image size.
+    pub scale: f32,
+}
+
+/// Writes `map` and its `attachments` as an Owlbear Rodeo scene bundle into `directory`.
+///
+/// # Errors
+/// Returns an error if an image can't be encoded or a file can't be written.
+pub fn export_owlbear(
+    map: &RgbaImage,
+    attachments: &[OwlbearAttachment],
+    settings: &OwlbearSettings,
+    directory: &Path,
+) -> Result<OwlbearScene, PackageError> {
+    let cell_size = settings.cell_size.max(1);
+    write_file(&directory.join("map.png"), &encode_png(map)?)?;
+
+    let attachments = attachments
+        .iter()
+        .enumerate()
+        .map(|(index, attachment)| {
+            let file = format!("attachments/{index}-{}.png", file_name(&attachment.name));
+            write_file(&directory.join(&file), &encode_png(&attachment.image)?)?;
+
+            Ok(OwlbearPlacement {
+                name: attachment.name.clone(),
+                file,
+                position: attachment.position,
+                rotation: attachment.rotation,
+                scale: attachment.scale,
+            })
+        })
+        .collect::<Result<_, PackageError>>()?;
+
+    let scene = OwlbearScene {
+        version: 1,
+        map: OwlbearMap {
+            file: "map.png".into(),
+            width: map.width(),
+            height: map.height(),
+            grid: OwlbearGrid {
+                kind: "SQUARE",
+                dpi: cell_size,
+                columns: map.width().div_ceil(cell_size),
+                rows: map.height().div_ceil(cell_size),
+                scale: settings.scale.clone(),
+            },
+        },
+        attachments,
+    };
+    write_json(&directory.join("scene.json"), &scene)?;
+
+    Ok(scene)
+}
+
+/// Turns `name` into a file name that's valid on every platform.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|character| {
+            if character.is_alphanumeric() || matches!(character, '-' | '_') {
+                character
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
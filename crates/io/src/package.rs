@@ -0,0 +1,66 @@
+//! Helpers shared by the exporters that write a package of files for a virtual tabletop.
+
+use image::{ImageError, ImageFormat, RgbaImage};
+use serde::Serialize;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Errors that can occur while writing an export package.
+#[derive(Error, Debug)]
+pub enum PackageError {
+    /// A file of the package couldn't be written.
+    #[error("failed to write '{path}': {source}")]
+    Io {
+        /// The file that couldn't be written.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+    /// An image couldn't be encoded.
+    #[error("failed to encode an image: {0}")]
+    Image(#[from] ImageError),
+    /// The metadata of the package couldn't be serialized.
+    #[error("failed to serialize the package metadata: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The requested DPI isn't a positive number, so pages can't be sized.
+    #[error("the DPI must be a positive number, got {0}")]
+    InvalidDpi(f32),
+}
+
+/// Encodes `image` as PNG.
+///
+/// # Errors
+/// Returns an error if the image can't be encoded.
+pub(crate) fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, PackageError> {
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Png)?;
+
+    Ok(bytes.into_inner())
+}
+
+/// Writes `bytes` to `path`, creating its parent directories.
+///
+/// # Errors
+/// Returns an error if the file can't be written.
+pub(crate) fn write_file(path: &Path, bytes: &[u8]) -> Result<(), PackageError> {
+    let io_error = |source| PackageError::Io {
+        path: path.to_owned(),
+        source,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+
+    fs::write(path, bytes).map_err(io_error)
+}
+
+/// Writes `value` as pretty printed JSON to `path`.
+///
+/// # Errors
+/// Returns an error if `value` can't be serialized or the file can't be written.
+pub(crate) fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), PackageError> {
+    write_file(path, &serde_json::to_vec_pretty(value)?)
+}
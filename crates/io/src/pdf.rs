@@ -0,0 +1,264 @@
+//! Exports rendered maps as a PDF document, ready to be sent to a print service.
+//!
+//! Each [`PdfPage`] (usually a level, or a single layer of one) becomes a page of the document,
+//! sized so the map prints at the requested DPI. The pages are labelled with their name, which
+//! PDF viewers show instead of the page number.
+
+use crate::package::{PackageError, write_file};
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use image::RgbaImage;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::Path;
+
+/// The number of PDF units (points) per inch.
+const POINTS_PER_INCH: f32 = 72.0;
+
+/// Configures the PDF export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfSettings {
+    /// The number of map pixels printed per inch, which determines the size of the pages.
+    ///
+    /// Setting it to the size of a grid cell in pixels prints the grid at one inch per cell, the
+    /// usual scale for miniatures.
+    pub dpi: f32,
+    /// The title of the document.
+    pub title: String,
+}
+
+impl Default for PdfSettings {
+    fn default() -> Self {
+        Self {
+            dpi: 150.0,
+            title: "Map".into(),
+        }
+    }
+}
+
+/// A page of an exported PDF document.
+#[derive(Debug, Clone)]
+pub struct PdfPage {
+    /// The label of the page, such as the name of the level or layer.
+    pub name: String,
+    /// The rendered level or layer printed on the page.
+    pub image: RgbaImage,
+}
+
+/// Writes `pages` as a PDF document to `path`, one page per [`PdfPage`].
+///
+/// Transparent parts of the images are kept transparent, so pages holding a single layer can be
+/// printed on top of each other.
+///
+/// # Errors
+/// Returns an error if the DPI isn't a positive number or the document can't be written.
+pub fn export_pdf(
+    pages: &[PdfPage],
+    settings: &PdfSettings,
+    path: &Path,
+) -> Result<(), PackageError> {
+    let mut document = Document::default();
+    let catalog = document.reserve();
+    let tree = document.reserve();
+    let mut kids = Vec::with_capacity(pages.len());
+    for page in pages {
+        kids.push(write_page(&mut document, tree, page, settings.dpi, path)?);
+    }
+
+    let kids = kids
+        .iter()
+        .map(|id| format!("{id} 0 R"))
+        .collect::<Vec<_>>();
+    document.set(
+        tree,
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            kids.len()
+        ),
+    );
+    let labels = pages
+        .iter()
+        .enumerate()
+        .fold(String::new(), |mut labels, (index, page)| {
+            let _ = write!(labels, "{index} << /P {} >> ", text(&page.name));
+            labels
+        });
+    document.set(
+        catalog,
+        format!("<< /Type /Catalog /Pages {tree} 0 R /PageLabels << /Nums [{labels}] >> >>"),
+    );
+    let info = document.reserve();
+    document.set(
+        info,
+        format!(
+            "<< /Title {} /Producer (DungeonRS) >>",
+            text(&settings.title)
+        ),
+    );
+
+    write_file(path, &document.finish(catalog, info))
+}
+
+/// Writes the objects of `page` (its image, transparency mask and contents) to `document` and
+/// returns the ID of the page object.
+///
+/// # Errors
+/// Returns an error if `dpi` isn't a positive number, or the image can't be compressed for the
+/// document written to `path`.
+fn write_page(
+    document: &mut Document,
+    tree: usize,
+    page: &PdfPage,
+    dpi: f32,
+    path: &Path,
+) -> Result<usize, PackageError> {
+    if !dpi.is_finite() || dpi <= 0.0 {
+        return Err(PackageError::InvalidDpi(dpi));
+    }
+    let deflate = |data: &[u8]| {
+        deflate(data).map_err(|source| PackageError::Io {
+            path: path.to_owned(),
+            source,
+        })
+    };
+
+    let (width, height) = page.image.dimensions();
+    let pixels = page.image.pixels();
+    let color: Vec<u8> = pixels
+        .clone()
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    let alpha: Vec<u8> = pixels.map(|pixel| pixel[3]).collect();
+
+    let mask = if alpha.iter().all(|alpha| *alpha == u8::MAX) {
+        String::new()
+    } else {
+        let mask = document.reserve();
+        document.set_stream(
+            mask,
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {width} /Height {height} \
+                 /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode"
+            ),
+            &deflate(&alpha)?,
+        );
+        format!(" /SMask {mask} 0 R")
+    };
+
+    let image = document.reserve();
+    document.set_stream(
+        image,
+        &format!(
+            "/Type /XObject /Subtype /Image /Width {width} /Height {height} \
+             /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode{mask}"
+        ),
+        &deflate(&color)?,
+    );
+
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "images are far smaller than f32 can represent exactly"
+    )]
+    let size = (
+        width as f32 / dpi * POINTS_PER_INCH,
+        height as f32 / dpi * POINTS_PER_INCH,
+    );
+    let contents = document.reserve();
+    document.set_stream(
+        contents,
+        "",
+        format!("q {} 0 0 {} 0 0 cm /Im0 Do Q", size.0, size.1).as_bytes(),
+    );
+
+    let id = document.reserve();
+    document.set(
+        id,
+        format!(
+            "<< /Type /Page /Parent {tree} 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /XObject << /Im0 {image} 0 R >> >> /Contents {contents} 0 R >>",
+            size.0, size.1
+        ),
+    );
+
+    Ok(id)
+}
+
+/// The objects of a PDF document, numbered from 1 in the order they're reserved.
+#[derive(Default)]
+struct Document {
+    /// The serialized objects, without their `obj` and `endobj` keywords.
+    objects: Vec<Vec<u8>>,
+}
+
+impl Document {
+    /// Reserves the ID of an object whose contents are set later, so objects can refer to each
+    /// other.
+    fn reserve(&mut self) -> usize {
+        self.objects.push(Vec::new());
+        self.objects.len()
+    }
+
+    /// Sets the contents of the object `id` to `body`.
+    fn set(&mut self, id: usize, body: String) {
+        self.objects[id - 1] = body.into_bytes();
+    }
+
+    /// Sets the object `id` to a stream holding `data`, `dictionary` lists the entries of its
+    /// dictionary besides its length.
+    fn set_stream(&mut self, id: usize, dictionary: &str, data: &[u8]) {
+        let mut body = format!("<< {dictionary} /Length {} >>\nstream\n", data.len()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.objects[id - 1] = body;
+    }
+
+    /// Serializes the document, with `catalog` as its root and `info` as its metadata.
+    fn finish(self, catalog: usize, info: usize) -> Vec<u8> {
+        // The binary comment tells tools the file holds binary data.
+        let mut bytes = b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(self.objects.len());
+        for (index, object) in self.objects.iter().enumerate() {
+            offsets.push(bytes.len());
+            bytes.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            bytes.extend_from_slice(object);
+            bytes.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = bytes.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {catalog} 0 R /Info {info} 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            self.objects.len() + 1
+        );
+        bytes.extend_from_slice(table.as_bytes());
+
+        bytes
+    }
+}
+
+/// Compresses `data` for a `FlateDecode` stream.
+///
+/// # Errors
+/// Returns an error if the data can't be compressed.
+fn deflate(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+
+    encoder.finish()
+}
+
+/// Encodes `value` as a PDF text string, in UTF-16 so any name can be used.
+fn text(value: &str) -> String {
+    value
+        .encode_utf16()
+        .fold(String::from("<FEFF"), |mut hex, unit| {
+            let _ = write!(hex, "{unit:04X}");
+            hex
+        })
+        + ">"
+}
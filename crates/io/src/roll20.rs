@@ -0,0 +1,288 @@
+//! Exports a rendered map as a package ready to be uploaded to Roll20.
+//!
+//! Roll20 rejects images above its upload limit, so the map is sliced along grid lines into
+//! images that each fit under the limit. A JSON sidecar describes the grid, suggests page
+//! settings and lists where each slice goes on the page. Optional GM overlays (secret doors,
+//! traps, notes) are sliced along the same lines so they can be placed on the GM layer.
+
+use crate::package::{PackageError, encode_png, write_file, write_json};
+use dungeonrs_utils::sanitize_file_name;
+use image::RgbaImage;
+use image::imageops::crop_imm;
+use serde::Serialize;
+use std::path::Path;
+
+/// The size of a grid cell on a Roll20 page, in Roll20 pixels.
+pub const ROLL20_CELL_SIZE: u32 = 70;
+
+/// Configures the Roll20 export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Roll20Settings {
+    /// The size of a grid cell in the rendered map, in pixels.
+    pub cell_size: u32,
+    /// The largest file Roll20 accepts, in bytes.
+    pub max_file_size: usize,
+    /// The distance a grid cell represents.
+    pub scale: f32,
+    /// The unit of [`scale`](Self::scale).
+    pub units: String,
+}
+
+impl Default for Roll20Settings {
+    fn default() -> Self {
+        Self {
+            cell_size: ROLL20_CELL_SIZE,
+            max_file_size: 10 * 1024 * 1024,
+            scale: 5.0,
+            units: "ft".into(),
+        }
+    }
+}
+
+/// An image shown only to the GM, covering the same area as the map.
+#[derive(Debug, Clone)]
+pub struct GmOverlay {
+    /// The name of the overlay, used in its file names once sanitized.
+    pub name: String,
+    /// The overlay image, the same size as the map.
+    pub image: RgbaImage,
+}
+
+/// The sidecar describing an exported Roll20 package, written as `roll20.json`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Roll20Manifest {
+    /// The version of the sidecar format.
+    pub version: u32,
+    /// The grid of the rendered map.
+    pub grid: Roll20Grid,
+    /// The suggested settings of the Roll20 page.
+    pub page: Roll20Page,
+    /// The slices of the map, to be placed on the map layer.
+    pub map: Vec<Roll20Slice>,
+    /// The slices of each GM overlay, to be placed on the GM layer.
+    pub gm_overlays: Vec<Roll20Overlay>,
+}
+
+/// The grid of an exported map.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Roll20Grid {
+    /// The size of a cell in the exported images, in pixels.
+    pub cell_size: u32,
+    /// The number of cells horizontally, the last one may be partial.
+    pub columns: u32,
+    /// The number of cells vertically, the last one may be partial.
+    pub rows: u32,
+}
+
+/// The suggested settings of the page the map is placed on.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Roll20Page {
+    /// The width of the page, in cells.
+    pub width: u32,
+    /// The height of the page, in cells.
+    pub height: u32,
+    /// The type of grid, always `square`.
+    pub grid_type: &'static str,
+    /// The distance a cell represents.
+    pub scale_number: f32,
+    /// The unit of the distance a cell represents.
+    pub scale_units: String,
+    /// The snapping increment, in cells.
+    pub snapping_increment: f32,
+}
+
+/// A slice of an exported image and its position on the page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Roll20Slice {
+    /// The file name of the slice, relative to the package directory.
+    pub file: String,
+    /// The distance from the left of the page to the left of the slice, in Roll20 pixels.
+    pub left: f32,
+    /// The distance from the top of the page to the top of the slice, in Roll20 pixels.
+    pub top: f32,
+    /// The width of the slice on the page, in Roll20 pixels.
+    pub width: f32,
+    /// The height of the slice on the page, in Roll20 pixels.
+    pub height: f32,
+}
+
+/// The slices of a GM overlay.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Roll20Overlay {
+    /// The name of the overlay.
+    pub name: String,
+    /// The slices of the overlay.
+    pub slices: Vec<Roll20Slice>,
+}
+
+/// An area of an image, in pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Area {
+    /// The left edge.
+    x: u32,
+    /// The top edge.
+    y: u32,
+    /// The width.
+    width: u32,
+    /// The height.
+    height: u32,
+}
+
+/// Writes `map` and its `overlays` as a Roll20 package into `directory`.
+///
+/// # Errors
+/// Returns an error if an image can't be encoded or a file can't be written.
+pub fn export_roll20(
+    map: &RgbaImage,
+    overlays: &[GmOverlay],
+    settings: &Roll20Settings,
+    directory: &Path,
+) -> Result<Roll20Manifest, PackageError> {
+    let cell_size = settings.cell_size.max(1);
+    let whole = Area {
+        x: 0,
+        y: 0,
+        width: map.width(),
+        height: map.height(),
+    };
+
+    let mut areas = Vec::new();
+    let map = write_slices(map, whole, "map", settings, directory, &mut areas)?;
+    let gm_overlays = overlays
+        .iter()
+        .map(|overlay| {
+            let name = sanitize_file_name(&format!("gm-{}", overlay.name));
+            let mut slices = Vec::new();
+            // Overlays start out sliced like the map so the slices line up on the page.
+            for &area in &areas {
+                slices.extend(write_slices(
+                    &overlay.image,
+                    area,
+                    &name,
+                    settings,
+                    directory,
+                    &mut Vec::new(),
+                )?);
+            }
+
+            Ok(Roll20Overlay {
+                name: overlay.name.clone(),
+                slices,
+            })
+        })
+        .collect::<Result<_, PackageError>>()?;
+
+    let columns = whole.width.div_ceil(cell_size);
+    let rows = whole.height.div_ceil(cell_size);
+    let manifest = Roll20Manifest {
+        version: 1,
+        grid: Roll20Grid {
+            cell_size,
+            columns,
+            rows,
+        },
+        page: Roll20Page {
+            width: columns,
+            height: rows,
+            grid_type: "square",
+            scale_number: settings.scale,
+            scale_units: settings.units.clone(),
+            snapping_increment: 1.0,
+        },
+        map,
+        gm_overlays,
+    };
+    write_json(&directory.join("roll20.json"), &manifest)?;
+
+    Ok(manifest)
+}
+
+/// Writes `area` of `image`, splitting it along grid lines until every slice fits under the
+/// upload limit.
+///
+/// The areas of the written slices are appended to `areas`.
+///
+/// # Errors
+/// Returns an error if a slice can't be encoded or written.
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "image dimensions are far below the range where f32 loses precision"
+)]
+fn write_slices(
+    image: &RgbaImage,
+    area: Area,
+    prefix: &str,
+    settings: &Roll20Settings,
+    directory: &Path,
+    areas: &mut Vec<Area>,
+) -> Result<Vec<Roll20Slice>, PackageError> {
+    let bytes = encode_png(&crop_imm(image, area.x, area.y, area.width, area.height).to_image())?;
+    if bytes.len() > settings.max_file_size
+        && let Some((first, second)) = split(area, settings.cell_size.max(1))
+    {
+        let mut slices = write_slices(image, first, prefix, settings, directory, areas)?;
+        slices.extend(write_slices(
+            image, second, prefix, settings, directory, areas,
+        )?);
+        return Ok(slices);
+    }
+
+    let file = format!("{prefix}-{}-{}.png", area.x, area.y);
+    write_file(&directory.join(&file), &bytes)?;
+    areas.push(area);
+
+    let scale = ROLL20_CELL_SIZE as f32 / settings.cell_size.max(1) as f32;
+    Ok(vec![Roll20Slice {
+        file,
+        left: area.x as f32 * scale,
+        top: area.y as f32 * scale,
+        width: area.width as f32 * scale,
+        height: area.height as f32 * scale,
+    }])
+}
+
+/// Splits `area` in two along the grid line closest to the middle of its longest side.
+///
+/// Returns `None` if the area is a single cell, which can't be split any further.
+fn split(area: Area, cell_size: u32) -> Option<(Area, Area)> {
+    let split_at = |length: u32| {
+        let cells = length.div_ceil(cell_size);
+        (cells > 1).then(|| cells / 2 * cell_size)
+    };
+    let vertically = || {
+        split_at(area.width).map(|offset| {
+            (
+                Area {
+                    width: offset,
+                    ..area
+                },
+                Area {
+                    x: area.x + offset,
+                    width: area.width - offset,
+                    ..area
+                },
+            )
+        })
+    };
+    let horizontally = || {
+        split_at(area.height).map(|offset| {
+            (
+                Area {
+                    height: offset,
+                    ..area
+                },
+                Area {
+                    y: area.y + offset,
+                    height: area.height - offset,
+                    ..area
+                },
+            )
+        })
+    };
+
+    if area.width >= area.height {
+        vertically().or_else(horizontally)
+    } else {
+        horizontally().or_else(vertically)
+    }
+}
@@ -0,0 +1,383 @@
+//! Reads the parts of Tiled maps and tilesets that can be represented in a project.
+
+use crate::tiled::TiledError;
+use crate::xml::{self, Element};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Set on a global tile ID when the tile is flipped horizontally.
+pub(crate) const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+/// Set on a global tile ID when the tile is flipped vertically.
+pub(crate) const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+/// Set on a global tile ID when the tile is flipped diagonally.
+pub(crate) const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+/// Set on a global tile ID when a hexagonal tile is rotated by 120°, which is ignored.
+const ROTATED_HEXAGONAL: u32 = 0x1000_0000;
+/// The bits of a global tile ID that encode flipping rather than the tile.
+pub(crate) const FLAGS: u32 =
+    FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL;
+
+/// A Tiled map.
+pub(crate) struct Map {
+    /// The width of a grid cell, in pixels.
+    pub tile_width: u32,
+    /// The height of a grid cell, in pixels.
+    pub tile_height: u32,
+    /// The tilesets used by the map, ordered by their first global tile ID.
+    pub tilesets: Vec<Tileset>,
+    /// The tile layers of the map, in drawing order.
+    pub layers: Vec<TileLayer>,
+}
+
+/// An image referenced by a tileset.
+pub(crate) struct TilesetImage {
+    /// The path of the image file.
+    pub source: PathBuf,
+    /// The width of the image, in pixels.
+    pub width: u32,
+    /// The height of the image, in pixels.
+    pub height: u32,
+}
+
+/// A Tiled tileset, either embedded in the map or read from a `.tsx` file.
+pub(crate) struct Tileset {
+    /// The global tile ID of the first tile in the tileset.
+    pub first_gid: u32,
+    /// The name of the tileset.
+    pub name: String,
+    /// The width of the tiles, in pixels.
+    pub tile_width: u32,
+    /// The height of the tiles, in pixels.
+    pub tile_height: u32,
+    /// The number of tiles in the tileset.
+    pub tile_count: u32,
+    /// The number of tile columns in the tileset image.
+    pub columns: u32,
+    /// The space between tiles in the tileset image, in pixels.
+    pub spacing: u32,
+    /// The space around the tiles in the tileset image, in pixels.
+    pub margin: u32,
+    /// The image containing all tiles, for tilesets based on a single image.
+    pub image: Option<TilesetImage>,
+    /// The image of each tile, for tilesets that are a collection of images.
+    pub tiles: HashMap<u32, TilesetImage>,
+}
+
+impl Tileset {
+    /// The size of the tile with local ID `tile`, in pixels.
+    pub fn tile_size(&self, tile: u32) -> (u32, u32) {
+        self.tiles
+            .get(&tile)
+            .map_or((self.tile_width, self.tile_height), |image| {
+                (image.width, image.height)
+            })
+    }
+
+    /// The top left corner of the tile with local ID `tile` in the tileset image.
+    pub fn tile_origin(&self, tile: u32) -> (u32, u32) {
+        let columns = self.columns.max(1);
+        (
+            self.margin + (tile % columns) * (self.tile_width + self.spacing),
+            self.margin + (tile / columns) * (self.tile_height + self.spacing),
+        )
+    }
+}
+
+/// A tile placed on a [`TileLayer`].
+pub(crate) struct PlacedTile {
+    /// The column of the grid cell.
+    pub x: i32,
+    /// The row of the grid cell, counting downwards.
+    pub y: i32,
+    /// The global tile ID, including the flip flags.
+    pub gid: u32,
+}
+
+/// A layer of tiles.
+pub(crate) struct TileLayer {
+    /// The name of the layer.
+    pub name: String,
+    /// The non-empty cells of the layer.
+    pub tiles: Vec<PlacedTile>,
+}
+
+impl Map {
+    /// Reads the map at `path` along with the external tilesets it references.
+    ///
+    /// # Errors
+    /// Returns an error if a file can't be read or isn't a supported Tiled document.
+    pub fn read(path: &Path) -> Result<Self, TiledError> {
+        let root = read_document(path)?;
+        if root.name != "map" {
+            return Err(TiledError::Invalid("the document isn't a Tiled map".into()));
+        }
+        let orientation = root.attribute("orientation").unwrap_or("orthogonal");
+        if orientation != "orthogonal" {
+            return Err(TiledError::UnsupportedOrientation(orientation.to_owned()));
+        }
+
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let mut tilesets = root
+            .children_named("tileset")
+            .map(|element| {
+                let first_gid = number(element, "firstgid")?;
+                match element.attribute("source") {
+                    Some(source) => {
+                        let path = directory.join(source);
+                        let document = read_document(&path)?;
+                        Tileset::read(&document, first_gid, path.parent().unwrap_or(Path::new("")))
+                    }
+                    None => Tileset::read(element, first_gid, directory),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        tilesets.sort_by_key(|tileset| tileset.first_gid);
+
+        let mut layers = Vec::new();
+        read_layers(&root, &mut layers)?;
+
+        Ok(Self {
+            tile_width: number(&root, "tilewidth")?,
+            tile_height: number(&root, "tileheight")?,
+            tilesets,
+            layers,
+        })
+    }
+
+    /// Returns the tileset containing the global tile ID `gid` along with the tile's local ID.
+    pub fn tile(&self, gid: u32) -> Option<(&Tileset, u32)> {
+        let gid = gid & !FLAGS;
+        let tileset = self
+            .tilesets
+            .iter()
+            .rev()
+            .find(|tileset| tileset.first_gid <= gid)?;
+
+        Some((tileset, gid - tileset.first_gid))
+    }
+}
+
+impl Tileset {
+    /// Reads a tileset from its `element`, resolving image paths relative to `directory`.
+    ///
+    /// # Errors
+    /// Returns an error if a required attribute is missing or invalid.
+    fn read(element: &Element, first_gid: u32, directory: &Path) -> Result<Self, TiledError> {
+        let tiles = element
+            .children_named("tile")
+            .filter_map(|tile| {
+                let image = tile.child("image")?;
+                Some(
+                    number(tile, "id")
+                        .and_then(|id| Ok((id, TilesetImage::read(image, directory)?))),
+                )
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        let image = element
+            .child("image")
+            .map(|image| TilesetImage::read(image, directory))
+            .transpose()?;
+
+        Ok(Self {
+            first_gid,
+            name: element.attribute("name").unwrap_or_default().to_owned(),
+            tile_width: number(element, "tilewidth")?,
+            tile_height: number(element, "tileheight")?,
+            tile_count: number(element, "tilecount")?,
+            columns: optional_number(element, "columns")?.unwrap_or(0),
+            spacing: optional_number(element, "spacing")?.unwrap_or(0),
+            margin: optional_number(element, "margin")?.unwrap_or(0),
+            image,
+            tiles,
+        })
+    }
+}
+
+impl TilesetImage {
+    /// Reads an `<image>` element, resolving its source relative to `directory`.
+    ///
+    /// # Errors
+    /// Returns an error if the source is missing or the size is invalid.
+    fn read(element: &Element, directory: &Path) -> Result<Self, TiledError> {
+        let source = element
+            .attribute("source")
+            .ok_or_else(|| TiledError::Invalid("image without a source".into()))?;
+
+        Ok(Self {
+            source: directory.join(source),
+            width: optional_number(element, "width")?.unwrap_or(0),
+            height: optional_number(element, "height")?.unwrap_or(0),
+        })
+    }
+}
+
+/// Reads the tile layers in `parent`, descending into group layers.
+///
+/// # Errors
+/// Returns an error if the data of a layer can't be decoded.
+fn read_layers(parent: &Element, layers: &mut Vec<TileLayer>) -> Result<(), TiledError> {
+    for element in &parent.children {
+        match element.name.as_str() {
+            "layer" => {
+                let mut tiles = Vec::new();
+                if let Some(data) = element.child("data") {
+                    let chunks: Vec<&Element> = data.children_named("chunk").collect();
+                    if chunks.is_empty() {
+                        let width = number(element, "width")?;
+                        read_tiles(data, data, 0, 0, width, &mut tiles)?;
+                    } else {
+                        for chunk in chunks {
+                            read_tiles(
+                                data,
+                                chunk,
+                                number(chunk, "x")?,
+                                number(chunk, "y")?,
+                                number(chunk, "width")?,
+                                &mut tiles,
+                            )?;
+                        }
+                    }
+                }
+
+                layers.push(TileLayer {
+                    name: element.attribute("name").unwrap_or_default().to_owned(),
+                    tiles,
+                });
+            }
+            "group" => read_layers(element, layers)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes the tiles in `content` (either the `<data>` element or one of its chunks) using the
+/// encoding declared on `data`.
+///
+/// # Errors
+/// Returns an error if the encoding or compression is unsupported or the data is invalid.
+#[allow(
+    clippy::cast_possible_wrap,
+    clippy::cast_possible_truncation,
+    reason = "map dimensions are far below i32::MAX"
+)]
+fn read_tiles(
+    data: &Element,
+    content: &Element,
+    x: i32,
+    y: i32,
+    width: u32,
+    tiles: &mut Vec<PlacedTile>,
+) -> Result<(), TiledError> {
+    let gids: Vec<u32> = match data.attribute("encoding") {
+        None => content
+            .children_named("tile")
+            .map(|tile| optional_number(tile, "gid").map(Option::unwrap_or_default))
+            .collect::<Result<_, _>>()?,
+        Some("csv") => content
+            .text
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| TiledError::Invalid(format!("invalid tile '{value}'")))
+            })
+            .collect::<Result<_, _>>()?,
+        Some("base64") => {
+            let bytes = STANDARD
+                .decode(content.text.trim())
+                .map_err(|error| TiledError::Invalid(error.to_string()))?;
+            let bytes = decompress(bytes, data.attribute("compression"))?;
+            bytes
+                .chunks_exact(4)
+                .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                .collect()
+        }
+        Some(encoding) => return Err(TiledError::UnsupportedEncoding(encoding.to_owned())),
+    };
+
+    let width = width.max(1) as usize;
+    tiles.extend(
+        gids.into_iter()
+            .enumerate()
+            .filter(|(_, gid)| gid & !FLAGS != 0)
+            .map(|(index, gid)| PlacedTile {
+                x: x + (index % width) as i32,
+                y: y + (index / width) as i32,
+                gid,
+            }),
+    );
+
+    Ok(())
+}
+
+/// Decompresses base64 decoded tile data.
+///
+/// # Errors
+/// Returns an error if the compression is unsupported or the data is corrupt.
+fn decompress(bytes: Vec<u8>, compression: Option<&str>) -> Result<Vec<u8>, TiledError> {
+    let mut decompressed = Vec::new();
+    let result = match compression {
+        None | Some("") => return Ok(bytes),
+        Some("zlib") => ZlibDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed),
+        Some("gzip") => GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed),
+        Some("zstd") => zstd::stream::copy_decode(bytes.as_slice(), &mut decompressed).map(|()| 0),
+        Some(compression) => {
+            return Err(TiledError::UnsupportedEncoding(compression.to_owned()));
+        }
+    };
+    result.map_err(|error| TiledError::Invalid(format!("corrupt tile data: {error}")))?;
+
+    Ok(decompressed)
+}
+
+/// Reads and parses the XML document at `path`.
+///
+/// # Errors
+/// Returns an error if the file can't be read or isn't well-formed XML.
+fn read_document(path: &Path) -> Result<Element, TiledError> {
+    let contents = fs::read_to_string(path).map_err(|source| TiledError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+
+    xml::parse(&contents).map_err(|source| TiledError::Xml {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+/// Reads the required numeric attribute `name` of `element`.
+///
+/// # Errors
+/// Returns an error if the attribute is missing or not a number.
+fn number<T: std::str::FromStr>(element: &Element, name: &str) -> Result<T, TiledError> {
+    optional_number(element, name)?
+        .ok_or_else(|| TiledError::Invalid(format!("<{}> is missing '{name}'", element.name)))
+}
+
+/// Reads the optional numeric attribute `name` of `element`.
+///
+/// # Errors
+/// Returns an error if the attribute is present but not a number.
+fn optional_number<T: std::str::FromStr>(
+    element: &Element,
+    name: &str,
+) -> Result<Option<T>, TiledError> {
+    element
+        .attribute(name)
+        .map(|value| {
+            value.parse().map_err(|_| {
+                TiledError::Invalid(format!("<{}> has an invalid '{name}'", element.name))
+            })
+        })
+        .transpose()
+}
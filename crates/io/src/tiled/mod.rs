@@ -0,0 +1,281 @@
+//! Imports maps made with [Tiled](https://www.mapeditor.org/).
+//!
+//! Only orthogonal maps are supported. Every tile layer (including those nested in groups)
+//! becomes a layer of elements, one element per non-empty cell. Object and image layers have no
+//! equivalent in a project and are skipped.
+
+mod map;
+
+use crate::XmlError;
+use bevy::asset::uuid::Uuid;
+use bevy::math::{Quat, Vec3};
+use bevy::transform::components::Transform;
+use dungeonrs_assets::AssetPack;
+use dungeonrs_core::{ElementData, LayerData, LevelData, SaveFile};
+use dungeonrs_utils::{ensure_within, sanitize_file_name};
+use image::ImageError;
+use map::{FLIPPED_DIAGONALLY, FLIPPED_HORIZONTALLY, FLIPPED_VERTICALLY, Map, Tileset};
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_2;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Errors that can occur while importing a Tiled map.
+#[derive(Error, Debug)]
+pub enum TiledError {
+    /// A map, tileset or image file couldn't be read or written.
+    #[error("failed to access '{path}': {source}")]
+    Io {
+        /// The file that couldn't be accessed.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+    /// A map or tileset file isn't well-formed XML.
+    #[error("'{path}' is not valid XML: {source}")]
+    Xml {
+        /// The file that couldn't be parsed.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: XmlError,
+    },
+    /// The document isn't a valid Tiled map or tileset.
+    #[error("invalid Tiled map: {0}")]
+    Invalid(String),
+    /// The map isn't orthogonal.
+    #[error("{0} maps are not supported")]
+    UnsupportedOrientation(String),
+    /// The tile data uses an encoding or compression that isn't supported.
+    #[error("tile data encoded as '{0}' is not supported")]
+    UnsupportedEncoding(String),
+    /// A tileset image couldn't be sliced into tiles.
+    #[error("failed to slice a tileset image: {0}")]
+    Image(#[from] ImageError),
+}
+
+/// Identifies a tile within a named tileset.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TileRef {
+    /// The name of the tileset.
+    pub tileset: String,
+    /// The local ID of the tile within the tileset.
+    pub tile: u32,
+}
+
+/// Determines which asset each tile of an imported map is shown with.
+#[derive(Debug, Clone)]
+pub enum TilesetMapping {
+    /// Maps tiles onto existing assets, for example from a pack containing the same art.
+    ///
+    /// Tiles without a mapping are skipped.
+    Assets(HashMap<TileRef, PathBuf>),
+    /// Writes every tile of every tileset as an image into the root of the given pack, which is
+    /// returned in the [`TiledImport`] so it can be registered with the library.
+    ImportAsPack(AssetPack),
+}
+
+/// The result of importing a Tiled map.
+#[derive(Debug, Clone)]
+pub struct TiledImport {
+    /// The imported project, containing a single level.
+    pub project: SaveFile,
+    /// The pack the tilesets were imported into, when using [`TilesetMapping::ImportAsPack`].
+    pub pack: Option<AssetPack>,
+}
+
+/// Imports the Tiled map at `path`.
+///
+/// Elements are positioned in pixels, with the top left corner of the map at the origin and the
+/// Y axis pointing up.
+///
+/// # Errors
+/// Returns an error if the map or its tilesets can't be read, or the tilesets can't be written
+/// into the pack.
+pub fn import_tiled(path: &Path, mapping: &TilesetMapping) -> Result<TiledImport, TiledError> {
+    let map = Map::read(path)?;
+
+    let assets = match mapping {
+        TilesetMapping::Assets(assets) => assets.clone(),
+        TilesetMapping::ImportAsPack(pack) => map
+            .tilesets
+            .iter()
+            .map(|tileset| write_tileset(tileset, &pack.root))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect(),
+    };
+
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let layers = map
+        .layers
+        .iter()
+        .map(|layer| LayerData {
+            elements: layer
+                .tiles
+                .iter()
+                .filter_map(|placed| {
+                    let (tileset, tile) = map.tile(placed.gid)?;
+                    let asset = assets.get(&TileRef {
+                        tileset: tileset.name.clone(),
+                        tile,
+                    })?;
+
+                    Some(ElementData {
+                        id: Uuid::new_v4(),
+                        asset: asset.clone(),
+                        transform: tile_transform(
+                            &map, tileset, tile, placed.x, placed.y, placed.gid,
+                        ),
+                        animation: None,
+                    })
+                })
+                .collect(),
+            ..LayerData::new(layer.name.clone())
+        })
+        .collect();
+
+    Ok(TiledImport {
+        project: SaveFile {
+            id: Uuid::new_v4(),
+            name: name.clone(),
+            levels: vec![LevelData {
+                id: Uuid::new_v4(),
+                name,
+                lighting: None,
+                background: None,
+                regions: Vec::new(),
+                links: Vec::new(),
+                layers,
+            }],
+        },
+        pack: match mapping {
+            TilesetMapping::Assets(_) => None,
+            TilesetMapping::ImportAsPack(pack) => Some(pack.clone()),
+        },
+    })
+}
+
+/// Positions a tile placed in the cell at `x`, `y`.
+///
+/// Tiles larger than a cell extend upwards and to the right from the bottom left of their cell,
+/// the flip flags of `gid` are converted into a rotation and scale.
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "map coordinates are far below the range where f32 loses precision"
+)]
+fn tile_transform(map: &Map, tileset: &Tileset, tile: u32, x: i32, y: i32, gid: u32) -> Transform {
+    let (width, height) = match tileset.tile_size(tile) {
+        (0, _) | (_, 0) => (tileset.tile_width, tileset.tile_height),
+        size => size,
+    };
+    let center_x = x as f32 * map.tile_width as f32 + width as f32 / 2.0;
+    let bottom = (y + 1) as f32 * map.tile_height as f32;
+    let center_y = -(bottom - height as f32 / 2.0);
+
+    let horizontal = if gid & FLIPPED_HORIZONTALLY == 0 {
+        1.0
+    } else {
+        -1.0
+    };
+    let vertical = if gid & FLIPPED_VERTICALLY == 0 {
+        1.0
+    } else {
+        -1.0
+    };
+    // Tiled applies the diagonal flip (swapping the axes) before the other flips. With the Y axis
+    // pointing up, swapping the axes equals a quarter turn of the tile mirrored along X.
+    let (rotation, scale) = if gid & FLIPPED_DIAGONALLY == 0 {
+        (Quat::IDENTITY, Vec3::new(horizontal, vertical, 1.0))
+    } else {
+        (
+            Quat::from_rotation_z(FRAC_PI_2),
+            Vec3::new(-vertical, horizontal, 1.0),
+        )
+    };
+
+    Transform {
+        translation: Vec3::new(center_x, center_y, 0.0),
+        rotation,
+        scale,
+    }
+}
+
+/// Writes every tile of `tileset` as an image into a directory named after it in `root`.
+///
+/// Returns the asset of each tile.
+///
+/// # Errors
+/// Returns an error if an image can't be read or written.
+fn write_tileset(tileset: &Tileset, root: &Path) -> Result<Vec<(TileRef, PathBuf)>, TiledError> {
+    let directory =
+        ensure_within(root, sanitize(&tileset.name)).map_err(|error| TiledError::Io {
+            path: root.to_owned(),
+            source: io::Error::new(io::ErrorKind::InvalidInput, error),
+        })?;
+    fs::create_dir_all(&directory).map_err(|source| TiledError::Io {
+        path: directory.clone(),
+        source,
+    })?;
+
+    let reference = |tile| TileRef {
+        tileset: tileset.name.clone(),
+        tile,
+    };
+    let mut assets = Vec::new();
+    if let Some(image) = &tileset.image {
+        let atlas = image::open(&image.source)?;
+        for tile in 0..tileset.tile_count {
+            let (x, y) = tileset.tile_origin(tile);
+            if x + tileset.tile_width > atlas.width() || y + tileset.tile_height > atlas.height() {
+                continue;
+            }
+
+            let path = directory.join(format!("{tile}.png"));
+            atlas
+                .crop_imm(x, y, tileset.tile_width, tileset.tile_height)
+                .save(&path)?;
+            assets.push((reference(tile), path));
+        }
+    }
+
+    for (&tile, image) in &tileset.tiles {
+        let mut file_name = tile.to_string();
+        if let Some(extension) = image.source.extension() {
+            file_name = format!("{file_name}.{}", extension.to_string_lossy());
+        }
+        let path = directory.join(file_name);
+        fs::copy(&image.source, &path).map_err(|source| TiledError::Io {
+            path: image.source.clone(),
+            source,
+        })?;
+        assets.push((reference(tile), path));
+    }
+
+    Ok(assets)
+}
+
+/// Replaces the characters of `name` that aren't valid in file names on every platform, and
+/// renames names reserved by the operating system.
+fn sanitize(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|character| match character {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            character => character,
+        })
+        .collect();
+
+    if sanitized.trim().is_empty() {
+        "tileset".to_owned()
+    } else {
+        sanitize_file_name(&sanitized)
+    }
+}
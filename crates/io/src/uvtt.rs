@@ -0,0 +1,439 @@
+//! Imports and exports Universal VTT files (`.dd2vtt`, `.uvtt`), as exported by Dungeondraft and
+//! other map makers and imported by most virtual tabletops.
+//!
+//! A Universal VTT file is JSON holding the map image (base64 encoded) and its line of sight
+//! walls, portals and lights, positioned in grid cells. They're converted to pixels of the image
+//! with the top left corner of the map at the origin and the Y axis pointing up, like the other
+//! importers.
+
+use crate::package::{PackageError, encode_png, write_file, write_json};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bevy::asset::uuid::Uuid;
+use bevy::color::{Color, ColorToPacked, Srgba};
+use bevy::math::{Vec2, Vec3};
+use bevy::transform::components::Transform;
+use dungeonrs_core::{
+    ElementData, ExportScene, LayerData, LevelData, LightData, PortalData, SaveFile, WallData,
+};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Errors that can occur while importing a Universal VTT file.
+#[derive(Error, Debug)]
+pub enum UvttError {
+    /// A file couldn't be read or written.
+    #[error("failed to access '{path}': {source}")]
+    Io {
+        /// The file that couldn't be accessed.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+    /// The file isn't a valid Universal VTT file.
+    #[error("invalid Universal VTT file: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The embedded image isn't valid base64, or isn't a PNG or WebP image.
+    #[error("the embedded map image is invalid")]
+    InvalidImage,
+}
+
+/// Configures the Universal VTT import.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UvttOptions {
+    /// The directory the embedded map image is written to.
+    pub directory: PathBuf,
+}
+
+/// Configures the Universal VTT export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UvttSettings {
+    /// The size of a grid cell in the rendered map, in pixels.
+    pub cell_size: u32,
+    /// Whether the lighting of the map is rendered into the image, so virtual tabletops don't
+    /// light it again.
+    pub baked_lighting: bool,
+}
+
+impl Default for UvttSettings {
+    fn default() -> Self {
+        Self {
+            cell_size: 70,
+            baked_lighting: false,
+        }
+    }
+}
+
+/// The version of the Universal VTT format written by [`export_uvtt`].
+const FORMAT: f32 = 0.3;
+
+/// A point in grid cells.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default)]
+struct Point {
+    /// The horizontal position.
+    x: f32,
+    /// The vertical position, pointing down.
+    y: f32,
+}
+
+/// The `resolution` object of a Universal VTT file.
+#[derive(Serialize, Deserialize, Debug)]
+struct Resolution {
+    /// The grid cell the map starts at.
+    map_origin: Point,
+    /// The size of the map, in grid cells.
+    map_size: Point,
+    /// The size of a grid cell in the image, in pixels.
+    pixels_per_grid: f32,
+}
+
+/// A portal in a Universal VTT file.
+#[derive(Serialize, Deserialize, Debug)]
+struct UvttPortal {
+    /// The center of the portal.
+    #[serde(default)]
+    position: Point,
+    /// The ends of the portal.
+    bounds: [Point; 2],
+    /// The angle of the portal, in radians.
+    #[serde(default)]
+    rotation: f32,
+    /// Whether the portal is closed.
+    #[serde(default)]
+    closed: bool,
+    /// Whether the portal stands on its own rather than in a wall.
+    #[serde(default)]
+    freestanding: bool,
+    /// The level the portal leads to, for the stairs, ladders and teleporters of the map.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    link: Option<UvttLink>,
+}
+
+/// Where a [`UvttPortal`] leads, an extension of the format read by the tabletops that support
+/// moving tokens between maps.
+#[derive(Serialize, Deserialize, Debug)]
+struct UvttLink {
+    /// The kind of link: `stairs`, `ladder` or `teleporter`.
+    kind: String,
+    /// The name of the level the link leads to.
+    level: String,
+    /// Where the link arrives on the map of the other level.
+    destination: Point,
+}
+
+/// A light in a Universal VTT file.
+#[derive(Serialize, Deserialize, Debug)]
+struct UvttLight {
+    /// The center of the light.
+    position: Point,
+    /// The distance the light reaches, in grid cells.
+    range: f32,
+    /// The brightness of the light.
+    #[serde(default = "full_intensity")]
+    intensity: f32,
+    /// The colour of the light, as `AARRGGBB` hexadecimal.
+    #[serde(default)]
+    color: Option<String>,
+    /// Whether walls cast shadows from the light.
+    #[serde(default = "casts_shadows")]
+    shadows: bool,
+}
+
+/// The `environment` object of a Universal VTT file.
+#[derive(Serialize, Deserialize, Debug)]
+struct Environment {
+    /// Whether the lighting is rendered into the map image.
+    #[serde(default)]
+    baked_lighting: bool,
+    /// The colour of the unlit parts of the map, as `AARRGGBB` hexadecimal.
+    #[serde(default)]
+    ambient_light: Option<String>,
+}
+
+/// The parts of a Universal VTT file that are imported and exported.
+#[derive(Serialize, Deserialize, Debug)]
+struct UvttFile {
+    /// The version of the format.
+    #[serde(default)]
+    format: f32,
+    /// The size of the map and its grid.
+    resolution: Resolution,
+    /// The walls blocking line of sight.
+    #[serde(default)]
+    line_of_sight: Vec<Vec<Point>>,
+    /// The walls of objects, which only block line of sight at their edges.
+    #[serde(default)]
+    objects_line_of_sight: Vec<Vec<Point>>,
+    /// The doors and windows.
+    #[serde(default)]
+    portals: Vec<UvttPortal>,
+    /// The light sources.
+    #[serde(default)]
+    lights: Vec<UvttLight>,
+    /// The lighting of the whole map.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    environment: Option<Environment>,
+    /// The map image, base64 encoded.
+    image: String,
+}
+
+/// The intensity of lights that don't specify one.
+fn full_intensity() -> f32 {
+    1.0
+}
+
+/// Whether lights that don't specify it cast shadows.
+fn casts_shadows() -> bool {
+    true
+}
+
+/// Maps an I/O error on `path` to a [`UvttError`].
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> UvttError {
+    let path = path.to_owned();
+    move |source| UvttError::Io { path, source }
+}
+
+/// Writes the map image of `file` to the directory of the imported images, named `name` with the
+/// extension of its format.
+///
+/// Returns the path of the written image.
+///
+/// # Errors
+/// Returns an error if the image isn't a valid PNG or WebP image, or can't be written.
+fn write_map_image(
+    file: &UvttFile,
+    name: &str,
+    options: &UvttOptions,
+) -> Result<PathBuf, UvttError> {
+    let image = STANDARD
+        .decode(file.image.trim())
+        .map_err(|_| UvttError::InvalidImage)?;
+    let extension = if image.starts_with(b"\x89PNG") {
+        "png"
+    } else if image.get(8..12) == Some(b"WEBP") {
+        "webp"
+    } else {
+        return Err(UvttError::InvalidImage);
+    };
+
+    let background = options.directory.join(format!("{name}.{extension}"));
+    fs::create_dir_all(&options.directory).map_err(io_error(&options.directory))?;
+    fs::write(&background, &image).map_err(io_error(&background))?;
+    Ok(background)
+}
+
+/// Imports the Universal VTT file at `path` as a project with a background, a walls and a lights
+/// layer.
+///
+/// # Errors
+/// Returns an error if the file can't be read or isn't valid, or the map image can't be written.
+pub fn import_uvtt(path: &Path, options: &UvttOptions) -> Result<SaveFile, UvttError> {
+    let file: UvttFile = serde_json::from_slice(&fs::read(path).map_err(io_error(path))?)?;
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let background = write_map_image(&file, &name, options)?;
+
+    let resolution = &file.resolution;
+    let scale = resolution.pixels_per_grid;
+    let origin = resolution.map_origin;
+    let position =
+        |point: Point| Vec2::new((point.x - origin.x) * scale, -(point.y - origin.y) * scale);
+    let size = Vec2::new(resolution.map_size.x, resolution.map_size.y) * scale;
+
+    let walls = file
+        .line_of_sight
+        .iter()
+        .chain(&file.objects_line_of_sight)
+        .filter(|points| points.len() > 1)
+        .map(|points| WallData {
+            id: Uuid::new_v4(),
+            points: points
+                .iter()
+                .map(|point| position(*point).to_array())
+                .collect(),
+            transform: Transform::IDENTITY,
+        })
+        .collect();
+    let portals = file
+        .portals
+        .iter()
+        .map(|portal| PortalData {
+            id: Uuid::new_v4(),
+            start: position(portal.bounds[0]),
+            end: position(portal.bounds[1]),
+            closed: portal.closed,
+            transform: Transform::IDENTITY,
+        })
+        .collect();
+    let lights = file
+        .lights
+        .iter()
+        .map(|light| LightData {
+            id: Uuid::new_v4(),
+            radius: light.range * scale,
+            color: light
+                .color
+                .as_deref()
+                .and_then(argb)
+                .unwrap_or(Color::WHITE),
+            intensity: light.intensity,
+            flicker: 0.0,
+            transform: Transform::from_translation(position(light.position).extend(0.0)),
+        })
+        .collect();
+
+    Ok(SaveFile {
+        id: Uuid::new_v4(),
+        name: name.clone(),
+        levels: vec![LevelData {
+            id: Uuid::new_v4(),
+            name,
+            lighting: None,
+            background: None,
+            regions: Vec::new(),
+            links: Vec::new(),
+            layers: vec![
+                LayerData {
+                    elements: vec![ElementData {
+                        id: Uuid::new_v4(),
+                        asset: background,
+                        transform: Transform::from_translation(Vec3::new(
+                            size.x / 2.0,
+                            -size.y / 2.0,
+                            0.0,
+                        )),
+                        animation: None,
+                    }],
+                    ..LayerData::new("Background")
+                },
+                LayerData {
+                    walls,
+                    portals,
+                    ..LayerData::new("Walls")
+                },
+                LayerData {
+                    lights,
+                    ..LayerData::new("Lights")
+                },
+            ],
+        }],
+    })
+}
+
+/// Parses a colour written as `AARRGGBB` (or `RRGGBB`) hexadecimal.
+pub(crate) fn argb(hex: &str) -> Option<Color> {
+    let hex = hex.trim_start_matches('#');
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let [alpha, red, green, blue] = match hex.len() {
+        8 => value.to_be_bytes(),
+        6 => (value | 0xFF00_0000).to_be_bytes(),
+        _ => return None,
+    };
+
+    Some(Color::Srgba(Srgba::rgba_u8(red, green, blue, alpha)))
+}
+
+/// Writes `map` and the walls, portals and lights of its `scene` as a Universal VTT file to
+/// `path`, and the map alone as a PNG image next to it.
+///
+/// The links to other levels are written as freestanding portals, along with the level they lead
+/// to.
+///
+/// # Errors
+/// Returns an error if the map can't be encoded or the files can't be written.
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "map sizes are far below 2^23 pixels"
+)]
+pub fn export_uvtt(
+    map: &RgbaImage,
+    scene: &ExportScene,
+    settings: &UvttSettings,
+    path: &Path,
+) -> Result<(), PackageError> {
+    let image = encode_png(map)?;
+    write_file(&path.with_extension("png"), &image)?;
+
+    let scale = settings.cell_size.max(1) as f32;
+    let cell = |point: Vec2| Point {
+        x: point.x / scale,
+        y: point.y / scale,
+    };
+    let file = UvttFile {
+        format: FORMAT,
+        resolution: Resolution {
+            map_origin: Point::default(),
+            map_size: cell(Vec2::new(map.width() as f32, map.height() as f32)),
+            pixels_per_grid: scale,
+        },
+        line_of_sight: scene
+            .walls
+            .iter()
+            .map(|points| points.iter().copied().map(cell).collect())
+            .collect(),
+        objects_line_of_sight: Vec::new(),
+        portals: scene
+            .portals
+            .iter()
+            .map(|portal| {
+                let direction = portal.end - portal.start;
+                UvttPortal {
+                    position: cell(portal.start.midpoint(portal.end)),
+                    bounds: [cell(portal.start), cell(portal.end)],
+                    rotation: direction.y.atan2(direction.x),
+                    closed: portal.closed,
+                    freestanding: false,
+                    link: None,
+                }
+            })
+            .chain(scene.links.iter().map(|link| {
+                // Links are a cell wide, standing on their own where they start.
+                let half = Vec2::new(scale / 2.0, 0.0);
+                UvttPortal {
+                    position: cell(link.position),
+                    bounds: [cell(link.position - half), cell(link.position + half)],
+                    rotation: 0.0,
+                    closed: false,
+                    freestanding: true,
+                    link: Some(UvttLink {
+                        kind: link.kind.name().into(),
+                        level: link.level.clone(),
+                        destination: cell(link.destination),
+                    }),
+                }
+            }))
+            .collect(),
+        lights: scene
+            .lights
+            .iter()
+            .map(|light| UvttLight {
+                position: cell(light.position),
+                range: light.radius / scale,
+                intensity: light.intensity,
+                color: Some(hex_argb(light.color)),
+                shadows: true,
+            })
+            .collect(),
+        environment: Some(Environment {
+            baked_lighting: settings.baked_lighting,
+            ambient_light: Some(hex_argb(Color::WHITE)),
+        }),
+        image: STANDARD.encode(&image),
+    };
+
+    write_json(path, &file)
+}
+
+/// Writes `color` as `AARRGGBB` hexadecimal.
+fn hex_argb(color: Color) -> String {
+    let [red, green, blue, alpha] = color.to_srgba().to_u8_array();
+
+    format!("{alpha:02x}{red:02x}{green:02x}{blue:02x}")
+}
@@ -0,0 +1,283 @@
+//! Imports Wonderdraft overland maps as the backdrop of a project.
+//!
+//! The `.wonderdraft_map` format isn't documented and changes between Wonderdraft releases, so
+//! the import is best-effort. The file may be gzip compressed. The largest PNG image embedded in
+//! it, either as raw bytes or base64 encoded in JSON, becomes the background. A PNG exported from
+//! Wonderdraft can be provided instead when the file doesn't embed one. When the map data is
+//! JSON, every object with a `text` and a position is imported as a label.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bevy::asset::uuid::Uuid;
+use bevy::math::Vec3;
+use bevy::transform::components::Transform;
+use dungeonrs_core::{ElementData, LabelData, LayerData, LevelData, SaveFile};
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The bytes every PNG file starts with.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The font size of labels that don't specify one.
+const DEFAULT_LABEL_SIZE: f32 = 32.0;
+
+/// Errors that can occur while importing a Wonderdraft map.
+#[derive(Error, Debug)]
+pub enum WonderdraftError {
+    /// A file couldn't be read or written.
+    #[error("failed to access '{path}': {source}")]
+    Io {
+        /// The file that couldn't be accessed.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+    /// The map doesn't embed a background image and none was provided.
+    #[error("the map doesn't contain a background image, export it as PNG from Wonderdraft")]
+    MissingBackground,
+    /// The background image isn't a valid PNG.
+    #[error("the background image is not a valid PNG")]
+    InvalidBackground,
+}
+
+/// Configures the Wonderdraft import.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WonderdraftOptions {
+    /// The directory the background image extracted from the map is written to.
+    pub directory: PathBuf,
+    /// A PNG exported from Wonderdraft, used as background instead of the embedded image.
+    pub background: Option<PathBuf>,
+}
+
+/// Imports the Wonderdraft map at `path` as a project with a background and a labels layer.
+///
+/// Elements are positioned in pixels of the background image, with its top left corner at the
+/// origin and the Y axis pointing up.
+///
+/// # Errors
+/// Returns an error if the map can't be read, or no background image is available.
+pub fn import_wonderdraft(
+    path: &Path,
+    options: &WonderdraftOptions,
+) -> Result<SaveFile, WonderdraftError> {
+    let io_error = |path: &Path| {
+        let path = path.to_owned();
+        move |source| WonderdraftError::Io { path, source }
+    };
+
+    let mut bytes = fs::read(path).map_err(io_error(path))?;
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut decompressed)
+            .map_err(io_error(path))?;
+        bytes = decompressed;
+    }
+
+    let document = serde_json::from_slice::<Value>(&bytes).ok();
+    let (background, png) = if let Some(background) = &options.background {
+        let png = fs::read(background).map_err(io_error(background))?;
+        (background.clone(), png)
+    } else {
+        let mut images = embedded_pngs(&bytes);
+        if let Some(document) = &document {
+            collect_encoded_pngs(document, &mut images);
+        }
+        let png = images
+            .into_iter()
+            .max_by_key(|png| {
+                png_size(png).map(|(width, height)| u64::from(width) * u64::from(height))
+            })
+            .ok_or(WonderdraftError::MissingBackground)?;
+        let background = options.directory.join("background.png");
+        fs::create_dir_all(&options.directory).map_err(io_error(&options.directory))?;
+        fs::write(&background, &png).map_err(io_error(&background))?;
+        (background, png)
+    };
+    let (width, height) = png_size(&png).ok_or(WonderdraftError::InvalidBackground)?;
+
+    let mut labels = Vec::new();
+    if let Some(document) = &document {
+        collect_labels(document, &mut labels);
+    }
+
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    #[allow(
+        clippy::cast_precision_loss,
+        reason = "image dimensions are far below the range where f32 loses precision"
+    )]
+    let center = Vec3::new(width as f32 / 2.0, -(height as f32) / 2.0, 0.0);
+
+    Ok(SaveFile {
+        id: Uuid::new_v4(),
+        name: name.clone(),
+        levels: vec![LevelData {
+            id: Uuid::new_v4(),
+            name,
+            lighting: None,
+            background: None,
+            regions: Vec::new(),
+            links: Vec::new(),
+            layers: vec![
+                LayerData {
+                    elements: vec![ElementData {
+                        id: Uuid::new_v4(),
+                        asset: background,
+                        transform: Transform::from_translation(center),
+                        animation: None,
+                    }],
+                    ..LayerData::new("Background")
+                },
+                LayerData {
+                    labels,
+                    ..LayerData::new("Labels")
+                },
+            ],
+        }],
+    })
+}
+
+/// Finds the PNG images embedded as raw bytes in `bytes`.
+fn embedded_pngs(bytes: &[u8]) -> Vec<Vec<u8>> {
+    let mut images = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = find(&bytes[offset..], PNG_SIGNATURE).map(|start| offset + start) {
+        match png_length(&bytes[start..]) {
+            Some(length) => {
+                images.push(bytes[start..start + length].to_vec());
+                offset = start + length;
+            }
+            None => offset = start + PNG_SIGNATURE.len(),
+        }
+    }
+
+    images
+}
+
+/// Collects the PNG images stored as base64 strings in `value`.
+fn collect_encoded_pngs(value: &Value, images: &mut Vec<Vec<u8>>) {
+    match value {
+        // The base64 encoding of the PNG signature.
+        Value::String(text) if text.starts_with("iVBORw0KGgo") => {
+            if let Ok(png) = STANDARD.decode(text) {
+                images.push(png);
+            }
+        }
+        Value::Object(object) => object
+            .values()
+            .for_each(|value| collect_encoded_pngs(value, images)),
+        Value::Array(values) => values
+            .iter()
+            .for_each(|value| collect_encoded_pngs(value, images)),
+        _ => {}
+    }
+}
+
+/// Returns the length of the PNG file at the start of `bytes`, by walking its chunks until the
+/// `IEND` chunk.
+fn png_length(bytes: &[u8]) -> Option<usize> {
+    let mut offset = PNG_SIGNATURE.len();
+    loop {
+        let header = bytes.get(offset..offset + 8)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        // The chunk header, its data and the trailing CRC.
+        offset = offset.checked_add(length)?.checked_add(12)?;
+        if offset > bytes.len() {
+            return None;
+        }
+        if &header[4..8] == b"IEND" {
+            return Some(offset);
+        }
+    }
+}
+
+/// Reads the dimensions from the `IHDR` chunk of the PNG file `png`.
+fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+    if !png.starts_with(PNG_SIGNATURE) || png.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let dimension = |offset: usize| {
+        png.get(offset..offset + 4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+
+    Some((dimension(16)?, dimension(20)?))
+}
+
+/// Returns the offset of the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Collects every object in `value` that looks like a label.
+fn collect_labels(value: &Value, labels: &mut Vec<LabelData>) {
+    match value {
+        Value::Object(object) => {
+            if let (Some(Value::String(text)), Some((x, y))) =
+                (object.get("text"), label_position(value))
+            {
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    reason = "font sizes are small enough for f32"
+                )]
+                let size = ["font_size", "fontSize", "size"]
+                    .iter()
+                    .find_map(|key| object.get(*key).and_then(Value::as_f64))
+                    .map_or(DEFAULT_LABEL_SIZE, |size| size as f32);
+                labels.push(LabelData::new(
+                    text.clone(),
+                    size,
+                    Transform::from_xyz(x, -y, 0.0),
+                ));
+            } else {
+                object
+                    .values()
+                    .for_each(|value| collect_labels(value, labels));
+            }
+        }
+        Value::Array(values) => values
+            .iter()
+            .for_each(|value| collect_labels(value, labels)),
+        _ => {}
+    }
+}
+
+/// Reads the position of a label object, which Wonderdraft has stored as `x`/`y` fields, a
+/// `position` array or object, or a Godot `Vector2( x, y )` string.
+#[allow(
+    clippy::cast_possible_truncation,
+    reason = "map coordinates are small enough for f32"
+)]
+fn label_position(value: &Value) -> Option<(f32, f32)> {
+    let pair = |x: &Value, y: &Value| Some((x.as_f64()? as f32, y.as_f64()? as f32));
+    if let (Some(x), Some(y)) = (value.get("x"), value.get("y")) {
+        return pair(x, y);
+    }
+
+    match value.get("position")? {
+        Value::Array(values) if values.len() == 2 => pair(&values[0], &values[1]),
+        position @ Value::Object(_) => pair(position.get("x")?, position.get("y")?),
+        Value::String(position) => {
+            let inner = position
+                .trim()
+                .trim_start_matches("Vector2")
+                .trim()
+                .strip_prefix('(')?
+                .strip_suffix(')')?;
+            let (x, y) = inner.split_once(',')?;
+
+            Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,406 @@
+//! A minimal XML reader for the documents written by other map editors, and escaping for the
+//! documents written by the exporters.
+//!
+//! Only the subset of XML those editors produce is supported: elements, attributes, text, CDATA
+//! sections and the predefined and numeric entities. Comments, processing instructions and the
+//! document type declaration are skipped.
+
+use thiserror::Error;
+
+/// How deeply elements can be nested, deeper documents are rejected rather than overflowing the
+/// stack.
+pub(crate) const MAX_DEPTH: usize = 256;
+
+/// The document isn't well-formed XML.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid XML at byte {position}: {message}")]
+pub struct XmlError {
+    /// The byte offset in the document where the problem was found.
+    pub position: usize,
+    /// Describes the problem.
+    pub message: &'static str,
+}
+
+/// An element of an XML document.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Element {
+    /// The name of the element.
+    pub name: String,
+    /// The attributes of the element, in document order.
+    pub attributes: Vec<(String, String)>,
+    /// The child elements, in document order.
+    pub children: Vec<Element>,
+    /// The text directly contained in the element, with entities decoded.
+    pub text: String,
+}
+
+impl Element {
+    /// Returns the value of the attribute called `name`.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the first child element called `name`.
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Iterates over the child elements called `name`.
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+/// Parses `input` and returns its root element.
+///
+/// # Errors
+/// Returns an error if `input` isn't a well-formed document.
+pub(crate) fn parse(input: &str) -> Result<Element, XmlError> {
+    let mut parser = Parser {
+        input,
+        position: 0,
+        depth: 0,
+    };
+    parser.skip_misc()?;
+    let root = parser.element()?;
+    parser.skip_misc()?;
+    if parser.position < input.len() {
+        return Err(parser.error("unexpected content after the root element"));
+    }
+
+    Ok(root)
+}
+
+/// Reads a document from front to back.
+struct Parser<'a> {
+    /// The whole document.
+    input: &'a str,
+    /// The byte offset of the next character to read.
+    position: usize,
+    /// How many elements enclose the one being read.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// The part of the document that wasn't read yet.
+    fn rest(&self) -> &str {
+        &self.input[self.position..]
+    }
+
+    /// Creates an error at the current position.
+    fn error(&self, message: &'static str) -> XmlError {
+        XmlError {
+            position: self.position,
+            message,
+        }
+    }
+
+    /// Skips whitespace.
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips everything up to and including `terminator`.
+    ///
+    /// # Errors
+    /// Returns an error if the document ends before `terminator`.
+    fn skip_past(&mut self, terminator: &str) -> Result<&str, XmlError> {
+        let Some(end) = self.rest().find(terminator) else {
+            return Err(self.error("unterminated markup"));
+        };
+        let skipped = &self.input[self.position..self.position + end];
+        self.position += end + terminator.len();
+
+        Ok(skipped)
+    }
+
+    /// Skips whitespace, comments, processing instructions and document type declarations.
+    ///
+    /// # Errors
+    /// Returns an error if any of them is unterminated.
+    fn skip_misc(&mut self) -> Result<(), XmlError> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Consumes `expected` or returns an error.
+    ///
+    /// # Errors
+    /// Returns an error if the document doesn't continue with `expected`.
+    fn expect(&mut self, expected: &str, message: &'static str) -> Result<(), XmlError> {
+        if self.rest().starts_with(expected) {
+            self.position += expected.len();
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    /// Reads an element or attribute name.
+    ///
+    /// # Errors
+    /// Returns an error if no name starts at the current position.
+    fn name(&mut self) -> Result<String, XmlError> {
+        let length = self
+            .rest()
+            .find(|character: char| {
+                character.is_whitespace() || matches!(character, '=' | '>' | '/' | '<')
+            })
+            .unwrap_or(self.rest().len());
+        if length == 0 {
+            return Err(self.error("expected a name"));
+        }
+
+        let name = self.rest()[..length].to_owned();
+        self.position += length;
+
+        Ok(name)
+    }
+
+    /// Reads an element, including its attributes and content.
+    ///
+    /// # Errors
+    /// Returns an error if the element isn't well-formed or is nested deeper than [`MAX_DEPTH`].
+    fn element(&mut self) -> Result<Element, XmlError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("elements are nested too deeply"));
+        }
+        self.depth += 1;
+        let element = self.element_content();
+        self.depth -= 1;
+
+        element
+    }
+
+    /// Reads the element at the current position, see [`Parser::element`].
+    ///
+    /// # Errors
+    /// Returns an error if the element isn't well-formed or is nested too deeply.
+    fn element_content(&mut self) -> Result<Element, XmlError> {
+        self.expect("<", "expected an element")?;
+        let mut element = Element {
+            name: self.name()?,
+            ..Element::default()
+        };
+
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.position += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.position += 1;
+                break;
+            }
+
+            let key = self.name()?;
+            self.skip_whitespace();
+            self.expect("=", "expected '=' after the attribute name")?;
+            self.skip_whitespace();
+            let Some(quote @ ('"' | '\'')) = self.rest().chars().next() else {
+                return Err(self.error("expected a quoted attribute value"));
+            };
+            self.position += 1;
+            let start = self.position;
+            let raw = self.skip_past(if quote == '"' { "\"" } else { "'" })?;
+            let value = decode(raw).map_err(|message| XmlError {
+                position: start,
+                message,
+            })?;
+            element.attributes.push((key, value));
+        }
+
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.position += 2;
+                if self.name()? != element.name {
+                    return Err(self.error("mismatched closing tag"));
+                }
+                self.skip_whitespace();
+                self.expect(">", "expected '>' after the closing tag")?;
+                return Ok(element);
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.position += "<![CDATA[".len();
+                let text = self.skip_past("]]>")?;
+                element.text.push_str(text);
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with('<') {
+                element.children.push(self.element()?);
+            } else if rest.is_empty() {
+                return Err(self.error("unclosed element"));
+            } else {
+                let start = self.position;
+                let length = rest.find('<').unwrap_or(rest.len());
+                let text = decode(&rest[..length]).map_err(|message| XmlError {
+                    position: start,
+                    message,
+                })?;
+                element.text.push_str(&text);
+                self.position += length;
+            }
+        }
+    }
+}
+
+/// Replaces the entities in `raw` by the characters they represent.
+///
+/// # Errors
+/// Returns a description of the problem if `raw` contains an unknown or unterminated entity.
+fn decode(raw: &str) -> Result<String, &'static str> {
+    if !raw.contains('&') {
+        return Ok(raw.to_owned());
+    }
+
+    let mut decoded = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find(';') else {
+            return Err("unterminated entity");
+        };
+        let entity = &rest[start + 1..start + end];
+        let character = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse::<u32>))
+                .and_then(Result::ok)
+                .and_then(char::from_u32)
+                .ok_or("unknown entity")?,
+        };
+        decoded.push(character);
+        rest = &rest[start + end + 1..];
+    }
+    decoded.push_str(rest);
+
+    Ok(decoded)
+}
+
+/// Replaces the characters of `text` that can't appear literally in XML text or attribute values.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            character => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    //! Parses the subset of XML written by other map editors.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+
+    /// Attributes, nested elements, text, CDATA and entities are read, while the prolog, comments
+    /// and processing instructions are skipped.
+    #[test]
+    fn parses_documents() {
+        let root = parse(
+            r#"<?xml version="1.0"?>
+<!DOCTYPE map>
+<!-- exported -->
+<map name='Crypt &amp; catacombs' width="2">
+  <layer id="1"/>
+  <layer id="2"><data>a &lt;b&gt; &#65;&#x42;<![CDATA[<raw>]]></data><?skip?></layer>
+</map>
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(root.name, "map");
+        assert_eq!(root.attribute("name"), Some("Crypt & catacombs"));
+        assert_eq!(root.attribute("height"), None);
+        let ids: Vec<_> = root
+            .children_named("layer")
+            .filter_map(|layer| layer.attribute("id"))
+            .collect();
+        assert_eq!(ids, ["1", "2"]);
+        assert_eq!(
+            root.children[1].child("data").unwrap().text,
+            "a <b> AB<raw>"
+        );
+    }
+
+    /// Escaped text reads back as the original.
+    #[test]
+    fn escapes_text() {
+        let text = r#"<"Tom" & 'Jerry'>"#;
+        let root = parse(&format!("<label text=\"{0}\">{0}</label>", escape(text))).unwrap();
+
+        assert_eq!(root.attribute("text"), Some(text));
+        assert_eq!(root.text, text);
+    }
+
+    /// Malformed documents are rejected with the position of the problem.
+    #[test]
+    fn rejects_malformed_documents() {
+        let error = |input| parse(input).unwrap_err();
+
+        assert_eq!(
+            error("<map></layer>"),
+            XmlError {
+                position: 12,
+                message: "mismatched closing tag"
+            }
+        );
+        assert_eq!(error("<map>").message, "unclosed element");
+        assert_eq!(error("<map>&nbsp;</map>").message, "unknown entity");
+        assert_eq!(
+            error("<map width=2/>").message,
+            "expected a quoted attribute value"
+        );
+        assert_eq!(
+            error("<map/><map/>").message,
+            "unexpected content after the root element"
+        );
+    }
+
+    /// Elements can be nested up to the limit, deeper documents are rejected.
+    #[test]
+    fn limits_nesting() {
+        let nested = |depth| format!("{}{}", "<a>".repeat(depth), "</a>".repeat(depth));
+
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            parse(&nested(MAX_DEPTH + 1)).unwrap_err(),
+            XmlError {
+                position: MAX_DEPTH * 3,
+                message: "elements are nested too deeply"
+            }
+        );
+        assert!(parse(&"<a>".repeat(100_000)).is_err());
+    }
+}
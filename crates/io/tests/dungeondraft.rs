@@ -0,0 +1,95 @@
+//! Imports Dungeondraft maps from the fixtures, matching their textures against a fixture pack.
+#![allow(clippy::missing_panics_doc)]
+
+use bevy::math::Vec3;
+use dungeonrs_assets::{AssetLibrary, AssetPack, AssetPackIndex, IndexSettings, PackIndexes};
+use dungeonrs_io::{DungeondraftError, import_dungeondraft};
+use dungeonrs_utils::{CancellationToken, TempWorkspace};
+use std::path::{Path, PathBuf};
+
+/// The path of the Dungeondraft fixture `name`.
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/dungeondraft")
+        .join(name)
+}
+
+/// A library holding the fixture pack, and its index built in `workspace`.
+fn library(workspace: &TempWorkspace) -> (AssetLibrary, PackIndexes) {
+    let pack = AssetPack::new("fixture", "Fixture", fixture("pack"));
+    let index = AssetPackIndex::open_in(&workspace.path().join("index")).unwrap();
+    index
+        .rebuild(
+            &pack,
+            &IndexSettings::default(),
+            &CancellationToken::default(),
+            |_, _| {},
+        )
+        .unwrap();
+
+    let mut indexes = PackIndexes::default();
+    indexes.insert(pack.id.clone(), index);
+    let mut library = AssetLibrary::default();
+    library.add_pack(pack);
+
+    (library, indexes)
+}
+
+/// Objects are grouped into their layer, walls, lights and texts get layers of their own, and
+/// objects whose texture matches no asset are reported.
+#[test]
+fn imports_layers_elements_and_walls() {
+    let workspace = TempWorkspace::open().unwrap();
+    let (library, indexes) = library(&workspace);
+
+    let import =
+        import_dungeondraft(&fixture("cellar.dungeondraft_map"), &library, &indexes).unwrap();
+    assert_eq!(
+        import.unmatched,
+        ["res://packs/fixture/textures/objects/zz_obelisk.png"]
+    );
+
+    let [level] = import.project.levels.as_slice() else {
+        panic!("the map has a single level");
+    };
+    assert_eq!(level.name, "Cellar");
+    let names: Vec<_> = level
+        .layers
+        .iter()
+        .map(|layer| layer.name.as_str())
+        .collect();
+    assert_eq!(names, ["Props", "Walls", "Lights", "Labels"]);
+
+    let [barrel] = level.layers[0].elements.as_slice() else {
+        panic!("only the barrel matches an asset");
+    };
+    assert_eq!(barrel.asset, fixture("pack").join("objects/barrel.png"));
+    assert_eq!(barrel.transform.translation, Vec3::new(128.0, -128.0, 0.0));
+    assert_eq!(barrel.transform.scale, Vec3::new(-1.0, 1.0, 1.0));
+
+    let walls = &level.layers[1];
+    let [wall] = walls.walls.as_slice() else {
+        panic!("the map has a single wall");
+    };
+    assert_eq!(wall.points, [[0.0, 0.0], [512.0, 0.0], [512.0, -512.0]]);
+    assert_eq!(walls.paths.len(), 1);
+    assert_eq!(
+        walls.paths[0].texture,
+        fixture("pack").join("walls/stone.png")
+    );
+    assert_eq!(walls.portals.len(), 1);
+    assert!((level.layers[2].lights[0].radius - 512.0).abs() < f32::EPSILON);
+    assert_eq!(level.layers[3].labels[0].text, "Wine racks");
+}
+
+/// A map that isn't valid JSON is rejected.
+#[test]
+fn rejects_malformed_map() {
+    let result = import_dungeondraft(
+        &fixture("malformed.dungeondraft_map"),
+        &AssetLibrary::default(),
+        &PackIndexes::default(),
+    );
+
+    assert!(matches!(result, Err(DungeondraftError::Json(_))));
+}
@@ -0,0 +1,88 @@
+//! Reads and writes interchange files, starting from the example documented in the README.
+#![allow(clippy::missing_panics_doc)]
+
+use bevy::math::{Vec2, Vec3};
+use dungeonrs_io::{InterchangeError, export_interchange, import_interchange};
+use dungeonrs_serialization::{Error, MigrationRegistry};
+use dungeonrs_utils::TempWorkspace;
+use std::path::{Path, PathBuf};
+
+/// The path of the interchange fixture `name`.
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/interchange")
+        .join(name)
+}
+
+/// The documented structure reads into the project it describes, with the omitted fields at
+/// their defaults.
+#[test]
+fn imports_documented_example() {
+    let project =
+        import_interchange(&fixture("crypt.json"), &MigrationRegistry::default()).unwrap();
+    assert_eq!(project.name, "Crypt");
+
+    let [level] = project.levels.as_slice() else {
+        panic!("the example has a single level");
+    };
+    assert_eq!(level.name, "Ground floor");
+    let [floor, walls] = level.layers.as_slice() else {
+        panic!("the example has a floor and a walls layer");
+    };
+
+    assert!(floor.locked);
+    assert!(!floor.hidden);
+    assert!((floor.opacity - 0.5).abs() < f32::EPSILON);
+    assert_eq!(floor.elements[0].asset, Path::new("tiles/floor.png"));
+    assert_eq!(floor.labels[0].text, "Entrance");
+    assert_eq!(
+        floor.labels[0].transform.translation,
+        Vec3::new(0.0, 64.0, 0.0)
+    );
+
+    assert!(!walls.locked);
+    assert!((walls.opacity - 1.0).abs() < f32::EPSILON);
+    assert_eq!(walls.walls[0].points, [[0.0, 0.0], [256.0, 0.0]]);
+    assert_eq!(
+        (walls.portals[0].start, walls.portals[0].end),
+        (Vec2::new(64.0, 0.0), Vec2::new(128.0, 0.0))
+    );
+    assert_eq!(
+        walls.lights[0].transform.translation,
+        Vec3::new(96.0, -96.0, 0.0)
+    );
+}
+
+/// An exported project reads back unchanged.
+#[test]
+fn round_trips() {
+    let workspace = TempWorkspace::open().unwrap();
+    let migrations = MigrationRegistry::default();
+    let project = import_interchange(&fixture("crypt.json"), &migrations).unwrap();
+
+    let path = workspace.path().join("crypt.json");
+    export_interchange(&project, &path).unwrap();
+
+    assert_eq!(import_interchange(&path, &migrations).unwrap(), project);
+}
+
+/// Other JSON documents, invalid JSON and files written by newer versions are rejected.
+#[test]
+fn rejects_other_documents() {
+    let migrations = MigrationRegistry::default();
+
+    let result = import_interchange(&fixture("geojson.json"), &migrations);
+    assert!(matches!(result, Err(InterchangeError::UnknownFormat)));
+
+    let result = import_interchange(&fixture("malformed.json"), &migrations);
+    assert!(matches!(result, Err(InterchangeError::Json(_))));
+
+    let result = import_interchange(&fixture("future.json"), &migrations);
+    assert!(matches!(
+        result,
+        Err(InterchangeError::Version(Error::UnsupportedVersion {
+            found: 99,
+            ..
+        }))
+    ));
+}
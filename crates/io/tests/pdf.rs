@@ -0,0 +1,101 @@
+//! Exports PDF documents and reads back their structure.
+#![allow(clippy::missing_panics_doc)]
+
+use dungeonrs_io::{PackageError, PdfPage, PdfSettings, export_pdf};
+use dungeonrs_utils::TempWorkspace;
+use image::{Rgba, RgbaImage};
+use std::fs;
+
+/// A page called `name` holding an image of `width` by `height` pixels.
+fn page(name: &str, width: u32, height: u32, alpha: u8) -> PdfPage {
+    PdfPage {
+        name: name.into(),
+        image: RgbaImage::from_pixel(width, height, Rgba([32, 64, 128, alpha])),
+    }
+}
+
+/// The text following the last occurrence of `marker` in `document`, up to the end of its line.
+fn after<'a>(document: &'a str, marker: &str) -> &'a str {
+    let start = document.rfind(marker).expect("the marker is present") + marker.len();
+    document[start..].lines().next().unwrap_or_default()
+}
+
+/// The cross-reference table points at every object, and the pages are sized to print the
+/// images at the requested DPI.
+#[test]
+fn writes_valid_cross_references_and_page_sizes() {
+    let workspace = TempWorkspace::open().unwrap();
+    let path = workspace.path().join("map.pdf");
+    let settings = PdfSettings {
+        dpi: 150.0,
+        title: "Crypt".into(),
+    };
+    export_pdf(
+        &[page("Ground", 300, 150, 255), page("Walls", 75, 75, 0)],
+        &settings,
+        &path,
+    )
+    .unwrap();
+
+    let bytes = fs::read(&path).unwrap();
+    let document = String::from_utf8_lossy(&bytes);
+    assert!(document.starts_with("%PDF-1.7\n"));
+    assert!(document.ends_with("%%EOF\n"));
+
+    let xref: usize = after(&document, "startxref\n").parse().unwrap();
+    assert!(bytes[xref..].starts_with(b"xref\n"));
+    let mut table = bytes[xref..].split(|byte| *byte == b'\n').skip(1);
+    let header = String::from_utf8_lossy(table.next().unwrap()).into_owned();
+    let count: usize = header.strip_prefix("0 ").unwrap().parse().unwrap();
+    assert_eq!(
+        table.next().unwrap(),
+        b"0000000000 65535 f ",
+        "the free entry comes first"
+    );
+    for id in 1..count {
+        let entry = String::from_utf8_lossy(table.next().unwrap()).into_owned();
+        let offset: usize = entry[..10].parse().unwrap();
+        assert!(entry.ends_with(" 00000 n "));
+        assert!(
+            bytes[offset..].starts_with(format!("{id} 0 obj\n").as_bytes()),
+            "object {id} is at {offset}"
+        );
+    }
+    assert_eq!(
+        after(&document, "/Size "),
+        format!("{count} /Root 1 0 R /Info {} 0 R >>", count - 1)
+    );
+
+    let media_boxes: Vec<_> = document
+        .match_indices("/MediaBox [")
+        .map(|(index, _)| {
+            let rest = &document[index + "/MediaBox [".len()..];
+            &rest[..rest.find(']').unwrap()]
+        })
+        .collect();
+    assert_eq!(media_boxes, ["0 0 144 72", "0 0 36 36"]);
+    assert_eq!(
+        document.matches("/SMask").count(),
+        1,
+        "only the transparent page is masked"
+    );
+    assert!(!document.contains("/DPI"));
+}
+
+/// A DPI that can't size the pages is rejected before anything is written.
+#[test]
+fn rejects_invalid_dpi() {
+    let workspace = TempWorkspace::open().unwrap();
+    let path = workspace.path().join("map.pdf");
+
+    for dpi in [0.0, -150.0, f32::NAN, f32::INFINITY] {
+        let settings = PdfSettings {
+            dpi,
+            ..PdfSettings::default()
+        };
+        let result = export_pdf(&[page("Ground", 8, 8, 255)], &settings, &path);
+
+        assert!(matches!(result, Err(PackageError::InvalidDpi(_))));
+    }
+    assert!(!path.exists());
+}
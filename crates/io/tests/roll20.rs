@@ -0,0 +1,105 @@
+//! Exports Roll20 packages of generated maps and checks their slices.
+#![allow(clippy::missing_panics_doc)]
+
+use dungeonrs_io::{GmOverlay, ROLL20_CELL_SIZE, Roll20Settings, Roll20Slice, export_roll20};
+use dungeonrs_utils::TempWorkspace;
+use image::{Rgba, RgbaImage};
+use std::fs;
+
+/// An image of `width` by `height` pixels filled with noise, which PNG can barely compress.
+fn noise(width: u32, height: u32) -> RgbaImage {
+    let mut state = 0x2545_f491_u32;
+    RgbaImage::from_fn(width, height, |_, _| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        Rgba(state.to_le_bytes())
+    })
+}
+
+/// Settings slicing maps with 20 pixel cells into files of at most `max_file_size` bytes.
+fn settings(max_file_size: usize) -> Roll20Settings {
+    Roll20Settings {
+        cell_size: 20,
+        max_file_size,
+        ..Roll20Settings::default()
+    }
+}
+
+/// The position and size of `slice` on the page.
+fn bounds(slice: &Roll20Slice) -> [f32; 4] {
+    [slice.left, slice.top, slice.width, slice.height]
+}
+
+/// A map too large for a single file is sliced along grid lines until every slice fits, and
+/// the slices cover the page without gaps.
+#[test]
+fn slices_maps_under_the_file_size_limit() {
+    let workspace = TempWorkspace::open().unwrap();
+    let max_file_size = 8 * 1024;
+    let manifest = export_roll20(
+        &noise(210, 130),
+        &[],
+        &settings(max_file_size),
+        workspace.path(),
+    )
+    .unwrap();
+
+    assert!(manifest.map.len() > 1, "the map is split");
+    let mut area = 0.0_f64;
+    for slice in &manifest.map {
+        let size = fs::metadata(workspace.path().join(&slice.file))
+            .unwrap()
+            .len();
+        assert!(
+            size <= max_file_size as u64,
+            "{} is {size} bytes",
+            slice.file
+        );
+        for edge in [slice.left, slice.top] {
+            assert!(
+                f64::from(edge) % f64::from(ROLL20_CELL_SIZE) == 0.0,
+                "{} is off the grid",
+                slice.file
+            );
+        }
+        area += f64::from(slice.width) * f64::from(slice.height);
+    }
+    let scale = f64::from(ROLL20_CELL_SIZE) / 20.0;
+    assert!((area - 210.0 * 130.0 * scale * scale).abs() < 1.0);
+    assert_eq!((manifest.grid.columns, manifest.grid.rows), (11, 7));
+}
+
+/// GM overlays are sliced like the map, so their slices line up with the map's on the page,
+/// and their names are sanitized before being used in file names.
+#[test]
+fn aligns_overlays_with_the_map() {
+    let workspace = TempWorkspace::open().unwrap();
+    let overlay = GmOverlay {
+        name: "../secret doors".into(),
+        image: RgbaImage::from_pixel(210, 130, Rgba([255, 0, 0, 128])),
+    };
+    let manifest = export_roll20(
+        &noise(210, 130),
+        &[overlay],
+        &settings(8 * 1024),
+        workspace.path(),
+    )
+    .unwrap();
+
+    let [overlay] = manifest.gm_overlays.as_slice() else {
+        panic!("the overlay is exported");
+    };
+    assert_eq!(overlay.name, "../secret doors");
+    let map: Vec<_> = manifest.map.iter().map(bounds).collect();
+    let slices: Vec<_> = overlay.slices.iter().map(bounds).collect();
+    assert_eq!(slices, map);
+    for slice in &overlay.slices {
+        assert!(
+            slice.file.starts_with("gm-.._secret doors-"),
+            "{}",
+            slice.file
+        );
+        assert!(workspace.path().join(&slice.file).is_file());
+    }
+}
@@ -0,0 +1,76 @@
+//! Imports Tiled maps from the fixtures.
+#![allow(clippy::missing_panics_doc)]
+
+use bevy::math::Vec3;
+use dungeonrs_io::{TileRef, TiledError, TilesetMapping, import_tiled};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The path of the Tiled fixture `name`.
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/tiled")
+        .join(name)
+}
+
+/// Maps the floor and barrel tiles of the fixtures' tileset onto assets.
+fn mapping() -> TilesetMapping {
+    let tile = |tile| TileRef {
+        tileset: "dungeon".into(),
+        tile,
+    };
+
+    TilesetMapping::Assets(HashMap::from([
+        (tile(0), PathBuf::from("floor/stone.png")),
+        (tile(1), PathBuf::from("props/barrel.png")),
+    ]))
+}
+
+/// Every tile layer becomes a layer of elements, nested ones included, object layers are
+/// skipped.
+#[test]
+fn imports_layers_and_tiles() {
+    let import = import_tiled(&fixture("dungeon.tmx"), &mapping()).unwrap();
+    let project = import.project;
+    assert!(import.pack.is_none());
+    assert_eq!(project.name, "dungeon");
+    assert_eq!(project.levels.len(), 1);
+
+    let layers = &project.levels[0].layers;
+    let names: Vec<_> = layers.iter().map(|layer| layer.name.as_str()).collect();
+    assert_eq!(names, ["Floor", "Props"]);
+    assert!(layers.iter().all(|layer| layer.walls.is_empty()));
+
+    let floor: Vec<_> = layers[0]
+        .elements
+        .iter()
+        .map(|element| {
+            (
+                element.asset.to_string_lossy().into_owned(),
+                element.transform.translation,
+            )
+        })
+        .collect();
+    assert_eq!(
+        floor,
+        [
+            ("floor/stone.png".into(), Vec3::new(32.0, -32.0, 0.0)),
+            ("floor/stone.png".into(), Vec3::new(96.0, -32.0, 0.0)),
+            ("props/barrel.png".into(), Vec3::new(96.0, -96.0, 0.0)),
+        ]
+    );
+
+    let [barrel] = layers[1].elements.as_slice() else {
+        panic!("the props layer holds a single barrel");
+    };
+    assert_eq!(barrel.asset, Path::new("props/barrel.png"));
+    assert_eq!(barrel.transform.scale, Vec3::new(-1.0, 1.0, 1.0));
+}
+
+/// A map that isn't well-formed XML is rejected.
+#[test]
+fn rejects_malformed_map() {
+    let result = import_tiled(&fixture("malformed.tmx"), &mapping());
+
+    assert!(matches!(result, Err(TiledError::Xml { .. })));
+}
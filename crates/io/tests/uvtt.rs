@@ -0,0 +1,88 @@
+//! Imports Universal VTT files from the fixtures.
+#![allow(clippy::missing_panics_doc)]
+
+use bevy::color::{Color, Srgba};
+use bevy::math::{Vec2, Vec3};
+use dungeonrs_io::{UvttError, UvttOptions, import_uvtt};
+use dungeonrs_utils::TempWorkspace;
+use std::path::{Path, PathBuf};
+
+/// The path of the Universal VTT fixture `name`.
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/uvtt")
+        .join(name)
+}
+
+/// Writes the imported map images to `workspace`.
+fn options(workspace: &TempWorkspace) -> UvttOptions {
+    UvttOptions {
+        directory: workspace.path().join("images"),
+    }
+}
+
+/// The map image becomes the background, walls and portals are converted from grid cells to
+/// pixels with the Y axis pointing up, and walls of a single point are dropped.
+#[test]
+fn imports_background_walls_and_lights() {
+    let workspace = TempWorkspace::open().unwrap();
+    let project = import_uvtt(&fixture("tower.dd2vtt"), &options(&workspace)).unwrap();
+    assert_eq!(project.name, "tower");
+
+    let [level] = project.levels.as_slice() else {
+        panic!("the file holds a single map");
+    };
+    let names: Vec<_> = level
+        .layers
+        .iter()
+        .map(|layer| layer.name.as_str())
+        .collect();
+    assert_eq!(names, ["Background", "Walls", "Lights"]);
+
+    let [background] = level.layers[0].elements.as_slice() else {
+        panic!("the background holds the map image");
+    };
+    assert_eq!(background.asset, workspace.path().join("images/tower.png"));
+    assert!(background.asset.is_file());
+    assert_eq!(
+        background.transform.translation,
+        Vec3::new(64.0, -64.0, 0.0)
+    );
+
+    let walls = &level.layers[1];
+    let points: Vec<_> = walls.walls.iter().map(|wall| wall.points.clone()).collect();
+    assert_eq!(
+        points,
+        [
+            vec![[0.0, 0.0], [128.0, 0.0], [128.0, -128.0]],
+            vec![[64.0, -64.0], [64.0, -96.0]],
+        ]
+    );
+    let [portal] = walls.portals.as_slice() else {
+        panic!("the file holds a single door");
+    };
+    assert_eq!(
+        (portal.start, portal.end),
+        (Vec2::new(32.0, 0.0), Vec2::new(64.0, 0.0))
+    );
+    assert!(portal.closed);
+
+    let [light] = level.layers[2].lights.as_slice() else {
+        panic!("the file holds a single light");
+    };
+    assert!((light.radius - 128.0).abs() < f32::EPSILON);
+    assert_eq!(light.color, Color::Srgba(Srgba::rgb_u8(255, 0, 0)));
+    assert_eq!(light.transform.translation, Vec3::new(64.0, -64.0, 0.0));
+}
+
+/// A file that isn't valid JSON is rejected, as is one whose map isn't a PNG or WebP image.
+#[test]
+fn rejects_malformed_files() {
+    let workspace = TempWorkspace::open().unwrap();
+
+    let result = import_uvtt(&fixture("malformed.dd2vtt"), &options(&workspace));
+    assert!(matches!(result, Err(UvttError::Json(_))));
+
+    let result = import_uvtt(&fixture("no_image.dd2vtt"), &options(&workspace));
+    assert!(matches!(result, Err(UvttError::InvalidImage)));
+}
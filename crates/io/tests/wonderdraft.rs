@@ -0,0 +1,87 @@
+//! Imports Wonderdraft maps from the fixtures.
+#![allow(clippy::missing_panics_doc)]
+
+use bevy::math::Vec3;
+use dungeonrs_io::{WonderdraftError, WonderdraftOptions, import_wonderdraft};
+use dungeonrs_utils::TempWorkspace;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The path of the Wonderdraft fixture `name`.
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/wonderdraft")
+        .join(name)
+}
+
+/// Extracts the background to `workspace`, unless `background` is provided.
+fn options(workspace: &TempWorkspace, background: Option<PathBuf>) -> WonderdraftOptions {
+    WonderdraftOptions {
+        directory: workspace.path().join("images"),
+        background,
+    }
+}
+
+/// The largest embedded image becomes the background, centered on the map, and every object with
+/// a text and a position becomes a label.
+#[test]
+fn imports_background_and_labels() {
+    let workspace = TempWorkspace::open().unwrap();
+    let project = import_wonderdraft(
+        &fixture("coast.wonderdraft_map"),
+        &options(&workspace, None),
+    )
+    .unwrap();
+    assert_eq!(project.name, "coast");
+
+    let [level] = project.levels.as_slice() else {
+        panic!("the map has a single level");
+    };
+    let names: Vec<_> = level
+        .layers
+        .iter()
+        .map(|layer| layer.name.as_str())
+        .collect();
+    assert_eq!(names, ["Background", "Labels"]);
+
+    let [background] = level.layers[0].elements.as_slice() else {
+        panic!("the background holds the map image");
+    };
+    assert_eq!(
+        background.asset,
+        workspace.path().join("images/background.png")
+    );
+    assert_eq!(
+        background.transform.translation,
+        Vec3::new(2.0, -1.0, 0.0),
+        "the 4x2 map image wasn't picked over the 1x1 preview"
+    );
+
+    let labels: Vec<_> = level.layers[1]
+        .labels
+        .iter()
+        .map(|label| (label.text.as_str(), label.size, label.transform.translation))
+        .collect();
+    assert_eq!(
+        labels,
+        [
+            ("Rivermouth", 48.0, Vec3::new(1.0, -1.0, 0.0)),
+            ("Old Keep", 32.0, Vec3::new(3.0, -0.5, 0.0)),
+        ]
+    );
+}
+
+/// A map embedding no image is rejected unless a background is provided, which must be a PNG.
+#[test]
+fn rejects_missing_background() {
+    let workspace = TempWorkspace::open().unwrap();
+    let map = fixture("blank.wonderdraft_map");
+
+    let result = import_wonderdraft(&map, &options(&workspace, None));
+    assert!(matches!(result, Err(WonderdraftError::MissingBackground)));
+
+    let background = workspace.path().join("background.png");
+    fs::write(&background, b"not a png").unwrap();
+    let result = import_wonderdraft(&map, &options(&workspace, Some(background)));
+    assert!(matches!(result, Err(WonderdraftError::InvalidBackground)));
+}
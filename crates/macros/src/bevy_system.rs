@@ -0,0 +1,132 @@
+//! Implementation of the [`macro@crate::bevy_system`] attribute.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::{ItemFn, LitStr, parse2};
+
+/// The options passed to `#[bevy_system(...)]`.
+#[derive(Default)]
+struct Options {
+    /// The span to wrap the system in, if `instrument` was passed.
+    instrument: Option<Instrument>,
+}
+
+/// The options passed to `instrument(...)`.
+struct Instrument {
+    /// The level of the span, as an identifier of `tracing::Level`.
+    level: syn::Ident,
+    /// The fields to record on the span, passed verbatim to `tracing::span!`.
+    fields: TokenStream,
+}
+
+impl Default for Instrument {
+    fn default() -> Self {
+        Self {
+            level: format_ident!("INFO"),
+            fields: TokenStream::new(),
+        }
+    }
+}
+
+impl Options {
+    /// Parses the arguments of the attribute.
+    ///
+    /// # Errors
+    /// Returns an error if an unknown or malformed option is passed.
+    fn parse(attr: TokenStream) -> syn::Result<Self> {
+        let mut options = Options::default();
+        let parser = syn::meta::parser(|meta| {
+            if meta.path.is_ident("instrument") {
+                options.instrument = Some(Instrument::parse(&meta)?);
+
+                Ok(())
+            } else {
+                Err(meta.error("unsupported bevy_system option, expected `instrument`"))
+            }
+        });
+        syn::parse::Parser::parse2(parser, attr)?;
+
+        Ok(options)
+    }
+}
+
+impl Instrument {
+    /// Parses the (optional) arguments of `instrument`.
+    ///
+    /// # Errors
+    /// Returns an error if an unknown option or level is passed.
+    fn parse(meta: &ParseNestedMeta) -> syn::Result<Self> {
+        let mut instrument = Instrument::default();
+        if meta.input.is_empty() || !meta.input.peek(syn::token::Paren) {
+            return Ok(instrument);
+        }
+
+        meta.parse_nested_meta(|nested| {
+            if nested.path.is_ident("level") {
+                let level: LitStr = nested.value()?.parse()?;
+                instrument.level = match level.value().to_lowercase().as_str() {
+                    "trace" => format_ident!("TRACE"),
+                    "debug" => format_ident!("DEBUG"),
+                    "info" => format_ident!("INFO"),
+                    "warn" => format_ident!("WARN"),
+                    "error" => format_ident!("ERROR"),
+                    _ => {
+                        return Err(syn::Error::new(
+                            level.span(),
+                            "expected one of `trace`, `debug`, `info`, `warn` or `error`",
+                        ));
+                    }
+                };
+
+                Ok(())
+            } else if nested.path.is_ident("fields") {
+                let content;
+                syn::parenthesized!(content in nested.input);
+                instrument.fields = content.parse()?;
+
+                Ok(())
+            } else {
+                Err(nested.error("unsupported instrument option, expected `level` or `fields`"))
+            }
+        })?;
+
+        Ok(instrument)
+    }
+}
+
+/// Expands `#[bevy_system(attr)]` applied to `item`.
+///
+/// # Errors
+/// Returns an error if the attribute arguments are invalid or `item` isn't a function.
+pub(crate) fn expand(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let options = Options::parse(attr)?;
+    let mut function: ItemFn = parse2(item)?;
+
+    if let Some(instrument) = options.instrument {
+        let name = LitStr::new(&function.sig.ident.to_string(), Span::call_site());
+        let level = instrument.level;
+        let fields = instrument.fields;
+        let fields = if fields.is_empty() {
+            fields
+        } else {
+            quote! { , #fields }
+        };
+        let body = &function.block.stmts;
+
+        function.block = parse2(quote! {{
+            let __bevy_system_span = ::tracing::span!(::tracing::Level::#level, #name #fields);
+            let __bevy_system_guard = __bevy_system_span.enter();
+
+            #(#body)*
+        }})?;
+    }
+
+    Ok(quote! {
+        #[allow(
+            clippy::needless_pass_by_value,
+            reason = "Bevy systems receive their parameters by value"
+        )]
+        #function
+    })
+}
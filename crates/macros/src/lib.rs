@@ -0,0 +1,29 @@
+#![doc = include_str!("../README.md")]
+
+mod bevy_system;
+
+use proc_macro::TokenStream;
+
+/// Marks a function as a Bevy system.
+///
+/// Systems receive their parameters (`Res`, `Query`, ...) by value, so the
+/// `clippy::needless_pass_by_value` lint is silenced for the annotated function.
+///
+/// Passing `instrument` wraps the body of the system in a tracing span named after the function:
+///
+/// ```ignore
+/// #[bevy_system(instrument)]
+/// fn move_elements(query: Query<&mut Transform>) { /* ... */ }
+///
+/// #[bevy_system(instrument(level = "trace", fields(count = query.iter().len())))]
+/// fn count_elements(query: Query<&Element>) { /* ... */ }
+/// ```
+///
+/// `level` is one of `trace`, `debug`, `info` (the default), `warn` or `error`, and `fields`
+/// accepts anything the `tracing::span!` macro accepts after the span name.
+#[proc_macro_attribute]
+pub fn bevy_system(attr: TokenStream, item: TokenStream) -> TokenStream {
+    bevy_system::expand(attr.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,161 @@
+//! Compares the supported formats and codecs on generated project fixtures.
+//!
+//! Besides the (de)serialization time, the throughput reported by criterion is based on the
+//! size of the serialized payload and the payload sizes are printed before each group runs,
+//! making it easy to compare both speed and size of every combination.
+#![allow(
+    missing_docs,
+    reason = "`criterion_group!` generates an undocumented public function"
+)]
+
+use bevy::prelude::Transform;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use dungeonrs_serialization::{
+    Codec, Format, MigrationRegistry, Versioned, compact, deserialize_versioned,
+    serialize_versioned,
+};
+use serde::{Deserialize, Serialize};
+use std::hint::black_box;
+
+/// The number of elements generated for each layer of the fixture.
+const ELEMENTS_PER_LAYER: usize = 2_500;
+
+/// A generated project, shaped like the hierarchy the editor saves.
+#[derive(Serialize, Deserialize)]
+struct Project {
+    /// The name of the project.
+    name: String,
+    /// The levels in the project.
+    levels: Vec<Level>,
+}
+
+/// A level within the [`Project`].
+#[derive(Serialize, Deserialize)]
+struct Level {
+    /// The name of the level.
+    name: String,
+    /// The layers in the level.
+    layers: Vec<Layer>,
+}
+
+/// A layer within a [`Level`].
+#[derive(Serialize, Deserialize)]
+struct Layer {
+    /// The name of the layer.
+    name: String,
+    /// The elements placed on the layer.
+    elements: Vec<Element>,
+}
+
+/// An element placed on a [`Layer`].
+#[derive(Serialize, Deserialize)]
+struct Element {
+    /// The name of the element.
+    name: String,
+    /// The asset the element renders.
+    asset: String,
+    /// Where the element is placed.
+    #[serde(with = "compact::transform")]
+    transform: Transform,
+}
+
+impl Versioned for Project {
+    const KIND: &'static str = "benchmark";
+    const VERSION: u32 = 1;
+}
+
+/// Generates a project with two levels of two layers each.
+#[allow(clippy::cast_precision_loss, reason = "element indices are small")]
+fn project() -> Project {
+    let layer = |name: &str| Layer {
+        name: name.to_string(),
+        elements: (0..ELEMENTS_PER_LAYER)
+            .map(|index| Element {
+                name: format!("{name} {index}"),
+                asset: format!("packs/dungeon/props/prop_{}.png", index % 64),
+                transform: Transform::from_xyz(index as f32 * 1.5, index as f32 * -0.5, 0.0),
+            })
+            .collect(),
+    };
+    let level = |name: &str| Level {
+        name: name.to_string(),
+        layers: vec![layer("floor"), layer("props")],
+    };
+
+    Project {
+        name: String::from("Benchmark"),
+        levels: vec![level("ground floor"), level("cellar")],
+    }
+}
+
+/// Every codec available in this build.
+fn codecs() -> Vec<Codec> {
+    let mut codecs = vec![Codec::None];
+    if cfg!(feature = "zstd") {
+        codecs.push(Codec::Zstd {
+            level: Codec::DEFAULT_ZSTD_LEVEL,
+        });
+    }
+    if cfg!(feature = "lz4") {
+        codecs.push(Codec::Lz4);
+    }
+
+    codecs
+}
+
+/// Benchmarks serializing the fixture in every format and codec combination.
+fn serialize(criterion: &mut Criterion) {
+    let project = project();
+    let mut group = criterion.benchmark_group("serialize");
+
+    for format in Format::ALL {
+        for codec in codecs() {
+            let Ok(bytes) = serialize_versioned(&project, format, codec) else {
+                continue;
+            };
+            println!("{format:?}/{codec:?}: {} bytes", bytes.len());
+
+            group.throughput(Throughput::Bytes(bytes.len() as u64));
+            group.bench_function(
+                BenchmarkId::new(format!("{format:?}"), format!("{codec:?}")),
+                |bencher| {
+                    bencher.iter(|| serialize_versioned(black_box(&project), format, codec));
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+/// Benchmarks deserializing the fixture in every format and codec combination.
+fn deserialize(criterion: &mut Criterion) {
+    let project = project();
+    let registry = MigrationRegistry::default();
+    let mut group = criterion.benchmark_group("deserialize");
+
+    for format in Format::ALL {
+        for codec in codecs() {
+            let Ok(bytes) = serialize_versioned(&project, format, codec) else {
+                continue;
+            };
+
+            group.throughput(Throughput::Bytes(bytes.len() as u64));
+            group.bench_function(
+                BenchmarkId::new(format!("{format:?}"), format!("{codec:?}")),
+                |bencher| {
+                    bencher.iter(|| deserialize_versioned::<Project>(black_box(&bytes), &registry));
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = serialize, deserialize
+}
+criterion_main!(benches);
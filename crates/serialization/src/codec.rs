@@ -0,0 +1,144 @@
+//! Contains the [`Codec`]s used to (optionally) compress serialized payloads.
+
+use crate::Error;
+#[cfg(feature = "zstd")]
+use std::io::Read;
+
+/// The largest payload [`Codec::decompress`] inflates, in bytes.
+///
+/// A few kilobytes of compressed data can claim (or inflate to) gigabytes, a corrupted or
+/// malicious payload could otherwise exhaust the available memory.
+pub const MAX_DECOMPRESSED_SIZE: usize = 1024 * 1024 * 1024;
+
+/// Compression applied to a serialized payload.
+///
+/// Every codec is always known to the envelope header, but the backends are only compiled in
+/// when their feature is enabled. Using (or reading data written with) a codec whose feature is
+/// disabled results in [`Error::CodecUnavailable`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Codec {
+    /// The payload is stored as-is.
+    #[default]
+    None,
+    /// [Zstandard](https://facebook.github.io/zstd/) compression, good ratios for large artifacts.
+    ///
+    /// Requires the `zstd` feature.
+    Zstd {
+        /// The compression level, ranging from 1 (fastest) to 22 (smallest).
+        level: i32,
+    },
+    /// [LZ4](https://lz4.org/) compression, trades compression ratio for speed.
+    ///
+    /// Requires the `lz4` feature.
+    Lz4,
+}
+
+impl Codec {
+    /// The compression level used for [`Codec::Zstd`] when none is specified.
+    pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+    /// The identifier used to record this codec in an envelope header.
+    #[must_use]
+    pub const fn id(self) -> u8 {
+        match self {
+            Codec::None => 0,
+            Codec::Zstd { .. } => 1,
+            Codec::Lz4 => 2,
+        }
+    }
+
+    /// Looks up the codec for an identifier previously returned by [`Codec::id`].
+    ///
+    /// The compression level isn't needed to decompress, so [`Codec::Zstd`] is returned with
+    /// [`Codec::DEFAULT_ZSTD_LEVEL`].
+    ///
+    /// # Errors
+    /// Returns [`Error::UnknownCodec`] if `id` doesn't match any known codec.
+    pub fn from_id(id: u8) -> Result<Self, Error> {
+        match id {
+            0 => Ok(Codec::None),
+            1 => Ok(Codec::Zstd {
+                level: Self::DEFAULT_ZSTD_LEVEL,
+            }),
+            2 => Ok(Codec::Lz4),
+            id => Err(Error::UnknownCodec(id)),
+        }
+    }
+
+    /// Compresses `data` using this codec.
+    ///
+    /// # Errors
+    /// Returns [`Error::CodecUnavailable`] if the backend for this codec isn't compiled in, or
+    /// [`Error::Compression`] if compression fails.
+    pub fn compress(self, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match self {
+            Codec::None => Ok(data),
+            #[cfg(feature = "zstd")]
+            Codec::Zstd { level } => {
+                zstd::bulk::compress(&data, level).map_err(|error| Error::Compression(error.into()))
+            }
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => Ok(lz4_flex::compress_prepend_size(&data)),
+            #[allow(unreachable_patterns)]
+            codec => Err(Error::CodecUnavailable(codec)),
+        }
+    }
+
+    /// Decompresses `data` previously compressed with this codec, up to
+    /// [`MAX_DECOMPRESSED_SIZE`] bytes.
+    ///
+    /// # Errors
+    /// Returns [`Error::CodecUnavailable`] if the backend for this codec isn't compiled in,
+    /// [`Error::Decompression`] if `data` isn't valid for this codec, or
+    /// [`Error::PayloadTooLarge`] if it decompresses to more than [`MAX_DECOMPRESSED_SIZE`].
+    pub fn decompress(self, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+        self.decompress_limited(data, MAX_DECOMPRESSED_SIZE)
+    }
+
+    /// Decompresses `data` previously compressed with this codec, up to `max` bytes.
+    ///
+    /// Uncompressed data is returned as-is, whatever its size.
+    ///
+    /// # Errors
+    /// Returns [`Error::CodecUnavailable`] if the backend for this codec isn't compiled in,
+    /// [`Error::Decompression`] if `data` isn't valid for this codec, or
+    /// [`Error::PayloadTooLarge`] if it decompresses to more than `max` bytes.
+    #[cfg_attr(
+        not(any(feature = "zstd", feature = "lz4")),
+        allow(unused_variables, reason = "only compressed payloads are limited")
+    )]
+    pub fn decompress_limited(self, data: Vec<u8>, max: usize) -> Result<Vec<u8>, Error> {
+        match self {
+            Codec::None => Ok(data),
+            #[cfg(feature = "zstd")]
+            Codec::Zstd { .. } => {
+                // Reading one byte past the limit tells a payload of exactly `max` bytes apart
+                // from a larger one, without inflating the rest.
+                let limit = u64::try_from(max).map_or(u64::MAX, |max| max.saturating_add(1));
+                let mut decompressed = Vec::new();
+                zstd::stream::read::Decoder::with_buffer(data.as_slice())
+                    .and_then(|decoder| decoder.take(limit).read_to_end(&mut decompressed))
+                    .map_err(|error| Error::Decompression(error.into()))?;
+                if decompressed.len() > max {
+                    return Err(Error::PayloadTooLarge { max });
+                }
+
+                Ok(decompressed)
+            }
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => {
+                // The size is read from the payload, so it's checked before allocating for it.
+                let (size, compressed) = lz4_flex::block::uncompressed_size(&data)
+                    .map_err(|error| Error::Decompression(error.into()))?;
+                if size > max {
+                    return Err(Error::PayloadTooLarge { max });
+                }
+
+                lz4_flex::block::decompress(compressed, size)
+                    .map_err(|error| Error::Decompression(error.into()))
+            }
+            #[allow(unreachable_patterns)]
+            codec => Err(Error::CodecUnavailable(codec)),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Serializes a [`Color`] as `[r, g, b]` or `[r, g, b, a]` in the sRGB colour space.
+
+use bevy::color::{Color, Srgba};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Serializes `value` as `[r, g, b, a]`, omitting the alpha channel when the colour is opaque.
+///
+/// Colours in other colour spaces are converted to sRGB first. The channels are written as a
+/// sequence rather than a fixed-size array, which formats like RON would write as a tuple.
+///
+/// # Errors
+/// Returns an error if the serializer fails.
+pub fn serialize<S: Serializer>(value: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+    let Srgba {
+        red,
+        green,
+        blue,
+        alpha,
+    } = value.to_srgba();
+
+    #[allow(
+        clippy::float_cmp,
+        reason = "only exactly opaque colours can omit their alpha"
+    )]
+    let alpha = (alpha != 1.0).then_some(alpha);
+
+    serializer.collect_seq([red, green, blue].into_iter().chain(alpha))
+}
+
+/// Deserializes a [`Color`] from `[r, g, b]` or `[r, g, b, a]`.
+///
+/// # Errors
+/// Returns an error if the input isn't an array of three or four numbers.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    match Vec::<f32>::deserialize(deserializer)?.as_slice() {
+        &[red, green, blue] => Ok(Color::srgb(red, green, blue)),
+        &[red, green, blue, alpha] => Ok(Color::srgba(red, green, blue, alpha)),
+        values => Err(D::Error::invalid_length(
+            values.len(),
+            &"3 or 4 colour channels",
+        )),
+    }
+}
+
+/// Describes a [`Color`] as `[r, g, b]` or `[r, g, b, a]`.
+#[cfg(feature = "schema")]
+#[must_use]
+pub fn schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    schemars::json_schema!({"type": "array", "items": {"type": "number"}, "minItems": 3, "maxItems": 4})
+}
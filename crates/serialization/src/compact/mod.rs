@@ -0,0 +1,23 @@
+//! Compact serde representations for frequently serialized math types.
+//!
+//! By default these types serialize as structs with named fields, which adds a lot of overhead
+//! when a project contains thousands of elements. The modules in here serialize them as plain
+//! arrays instead and are meant to be used with serde's `with` attribute:
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Element {
+//!     #[serde(with = "dungeonrs_serialization::compact::transform")]
+//!     transform: Transform,
+//!     #[serde(with = "dungeonrs_serialization::compact::color")]
+//!     tint: Color,
+//! }
+//! ```
+//!
+//! With the `schema` feature enabled, each module also provides a `schema` function describing
+//! the representation, for use with `#[schemars(schema_with = "...::schema")]`.
+
+pub mod color;
+pub mod rect;
+pub mod transform;
+pub mod vec2;
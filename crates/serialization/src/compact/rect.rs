@@ -0,0 +1,29 @@
+//! Serializes a [`Rect`] as `[min_x, min_y, max_x, max_y]`.
+
+use bevy::math::Rect;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serializes `value` as `[min_x, min_y, max_x, max_y]`.
+///
+/// # Errors
+/// Returns an error if the serializer fails.
+pub fn serialize<S: Serializer>(value: &Rect, serializer: S) -> Result<S::Ok, S::Error> {
+    [value.min.x, value.min.y, value.max.x, value.max.y].serialize(serializer)
+}
+
+/// Deserializes a [`Rect`] from `[min_x, min_y, max_x, max_y]`.
+///
+/// # Errors
+/// Returns an error if the input isn't an array of four numbers.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rect, D::Error> {
+    let [min_x, min_y, max_x, max_y] = <[f32; 4]>::deserialize(deserializer)?;
+
+    Ok(Rect::new(min_x, min_y, max_x, max_y))
+}
+
+/// Describes a [`Rect`] as `[min_x, min_y, max_x, max_y]`.
+#[cfg(feature = "schema")]
+#[must_use]
+pub fn schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    schemars::json_schema!({"type": "array", "items": {"type": "number"}, "minItems": 4, "maxItems": 4})
+}
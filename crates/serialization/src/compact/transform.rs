@@ -0,0 +1,73 @@
+//! Serializes a [`Transform`] as a flat array, omitting identity rotation and scale.
+//!
+//! The length of the array determines which components are present:
+//!
+//! | length | content                                  |
+//! |--------|------------------------------------------|
+//! | 3      | translation                              |
+//! | 6      | translation, scale                       |
+//! | 7      | translation, rotation                    |
+//! | 10     | translation, rotation, scale             |
+//!
+//! Most elements on a map are only translated, so they serialize as just three numbers.
+
+use bevy::math::{Quat, Vec3};
+use bevy::transform::components::Transform;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Serializes `value` as a flat array, omitting rotation and scale when they're the identity.
+///
+/// # Errors
+/// Returns an error if the serializer fails.
+pub fn serialize<S: Serializer>(value: &Transform, serializer: S) -> Result<S::Ok, S::Error> {
+    let translation = value.translation.to_array();
+    let rotation = (value.rotation != Quat::IDENTITY).then(|| value.rotation.to_array());
+    let scale = (value.scale != Vec3::ONE).then(|| value.scale.to_array());
+
+    serializer.collect_seq(
+        translation
+            .into_iter()
+            .chain(rotation.into_iter().flatten())
+            .chain(scale.into_iter().flatten()),
+    )
+}
+
+/// Deserializes a [`Transform`] from a flat array of 3, 6, 7 or 10 numbers.
+///
+/// # Errors
+/// Returns an error if the input isn't an array of a supported length.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Transform, D::Error> {
+    let values = Vec::<f32>::deserialize(deserializer)?;
+    let invalid_length = || D::Error::invalid_length(values.len(), &"3, 6, 7 or 10 components");
+
+    let (&[x, y, z], remaining) = values.split_first_chunk().ok_or_else(invalid_length)?;
+    let (rotation, scale) = match *remaining {
+        [] => (Quat::IDENTITY, Vec3::ONE),
+        [sx, sy, sz] => (Quat::IDENTITY, Vec3::new(sx, sy, sz)),
+        [rx, ry, rz, rw] => (Quat::from_xyzw(rx, ry, rz, rw), Vec3::ONE),
+        [rx, ry, rz, rw, sx, sy, sz] => (Quat::from_xyzw(rx, ry, rz, rw), Vec3::new(sx, sy, sz)),
+        _ => return Err(invalid_length()),
+    };
+
+    Ok(Transform {
+        translation: Vec3::new(x, y, z),
+        rotation,
+        scale,
+    })
+}
+
+/// Describes a [`Transform`] as a flat array of 3, 6, 7 or 10 numbers.
+#[cfg(feature = "schema")]
+#[must_use]
+pub fn schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    schemars::json_schema!({
+        "type": "array",
+        "items": {"type": "number"},
+        "oneOf": [
+            {"minItems": 3, "maxItems": 3},
+            {"minItems": 6, "maxItems": 7},
+            {"minItems": 10, "maxItems": 10},
+        ],
+    })
+}
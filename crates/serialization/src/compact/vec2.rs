@@ -0,0 +1,27 @@
+//! Serializes a [`Vec2`] as `[x, y]`.
+
+use bevy::math::Vec2;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serializes `value` as `[x, y]`.
+///
+/// # Errors
+/// Returns an error if the serializer fails.
+pub fn serialize<S: Serializer>(value: &Vec2, serializer: S) -> Result<S::Ok, S::Error> {
+    value.to_array().serialize(serializer)
+}
+
+/// Deserializes a [`Vec2`] from `[x, y]`.
+///
+/// # Errors
+/// Returns an error if the input isn't an array of two numbers.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec2, D::Error> {
+    <[f32; 2]>::deserialize(deserializer).map(Vec2::from_array)
+}
+
+/// Describes a [`Vec2`] as `[x, y]`.
+#[cfg(feature = "schema")]
+#[must_use]
+pub fn schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    schemars::json_schema!({"type": "array", "items": {"type": "number"}, "minItems": 2, "maxItems": 2})
+}
@@ -0,0 +1,137 @@
+//! Contains the [`Encrypted`] wrapper used to protect serialized payloads with a passphrase.
+
+use crate::Error;
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+
+/// The magic bytes every encrypted payload starts with.
+pub const ENCRYPTED_MAGIC: [u8; 4] = *b"DRS\x1C";
+
+/// The length of the random salt used for key derivation.
+const SALT_LENGTH: usize = 16;
+
+/// The length of the `XChaCha20` nonce.
+const NONCE_LENGTH: usize = 24;
+
+/// A payload encrypted with a key derived from a passphrase.
+///
+/// The key is derived from the passphrase using Argon2id with a random salt, the payload is
+/// then encrypted and authenticated using `XChaCha20-Poly1305`. Because the cipher is
+/// authenticated, decrypting with the wrong passphrase (or decrypting a corrupted payload) is
+/// detected and reported as [`Error::WrongPassphrase`] instead of producing garbage.
+///
+/// This wraps arbitrary bytes, typically the output of
+/// [`serialize_versioned`](crate::serialize_versioned).
+///
+/// The binary layout is:
+///
+/// | bytes       | content                   |
+/// |-------------|---------------------------|
+/// | 4           | [`ENCRYPTED_MAGIC`]       |
+/// | 16          | key derivation salt       |
+/// | 24          | nonce                     |
+/// | *remaining* | ciphertext and tag        |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Encrypted {
+    /// The salt used to derive the key from the passphrase.
+    salt: [u8; SALT_LENGTH],
+    /// The nonce used to encrypt the payload.
+    nonce: [u8; NONCE_LENGTH],
+    /// The encrypted payload, including the authentication tag.
+    ciphertext: Vec<u8>,
+}
+
+impl Encrypted {
+    /// Encrypts `plaintext` with a key derived from `passphrase`.
+    ///
+    /// # Errors
+    /// Returns [`Error::Encryption`] if the key can't be derived or encryption fails.
+    pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Self, Error> {
+        let mut salt = [0; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let cipher = cipher(passphrase, &salt)?;
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| Error::Encryption("failed to encrypt payload".into()))?;
+
+        Ok(Self {
+            salt,
+            nonce: nonce.into(),
+            ciphertext,
+        })
+    }
+
+    /// Decrypts the payload with a key derived from `passphrase`.
+    ///
+    /// # Errors
+    /// Returns [`Error::WrongPassphrase`] if `passphrase` doesn't match the one used to encrypt,
+    /// or the payload was modified after encryption.
+    pub fn decrypt(&self, passphrase: &str) -> Result<Vec<u8>, Error> {
+        let cipher = cipher(passphrase, &self.salt)?;
+
+        cipher
+            .decrypt(XNonce::from_slice(&self.nonce), self.ciphertext.as_slice())
+            .map_err(|_| Error::WrongPassphrase)
+    }
+
+    /// Returns whether `bytes` looks like an encrypted payload.
+    ///
+    /// Useful to decide whether the user needs to be prompted for a passphrase.
+    #[must_use]
+    pub fn is_encrypted(bytes: &[u8]) -> bool {
+        bytes.starts_with(&ENCRYPTED_MAGIC)
+    }
+
+    /// Writes the header followed by the ciphertext.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            ENCRYPTED_MAGIC.len() + SALT_LENGTH + NONCE_LENGTH + self.ciphertext.len(),
+        );
+        bytes.extend_from_slice(&ENCRYPTED_MAGIC);
+        bytes.extend_from_slice(&self.salt);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&self.ciphertext);
+
+        bytes
+    }
+
+    /// Parses an encrypted payload previously written by [`Encrypted::to_bytes`].
+    ///
+    /// # Errors
+    /// Returns an error if `bytes` doesn't start with a valid header.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let bytes = bytes
+            .strip_prefix(&ENCRYPTED_MAGIC)
+            .ok_or(Error::InvalidMagic)?;
+        let (salt, bytes) = bytes
+            .split_first_chunk::<SALT_LENGTH>()
+            .ok_or(Error::Truncated)?;
+        let (nonce, ciphertext) = bytes
+            .split_first_chunk::<NONCE_LENGTH>()
+            .ok_or(Error::Truncated)?;
+
+        Ok(Self {
+            salt: *salt,
+            nonce: *nonce,
+            ciphertext: ciphertext.to_vec(),
+        })
+    }
+}
+
+/// Derives the key for `passphrase` and `salt` and builds the cipher using it.
+///
+/// # Errors
+/// Returns [`Error::Encryption`] if the key derivation fails.
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<XChaCha20Poly1305, Error> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|error| Error::Encryption(error.to_string().into()))?;
+
+    Ok(XChaCha20Poly1305::new(&key))
+}
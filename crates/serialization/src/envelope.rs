@@ -0,0 +1,173 @@
+//! Contains the [`Envelope`] that wraps [`Versioned`] artifacts with a self-describing header.
+
+use crate::{Codec, Error, Format, MigrationRegistry, deserialize, deserialize_value, serialize};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// The magic bytes every envelope starts with.
+pub const MAGIC: [u8; 4] = *b"DRS\x1A";
+
+/// An artifact with a schema version, allowing older versions to be migrated when read.
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// Identifies the kind of artifact (e.g. `"save"`), migrations are registered per kind.
+    const KIND: &'static str;
+
+    /// The current schema version, bump this whenever the serialized structure changes.
+    const VERSION: u32;
+}
+
+/// A serialized artifact together with the metadata needed to read it back.
+///
+/// The binary layout is:
+///
+/// | bytes       | content                              |
+/// |-------------|--------------------------------------|
+/// | 4           | [`MAGIC`]                            |
+/// | 1           | [`Format::id`] of the payload        |
+/// | 1           | [`Codec::id`] of the payload         |
+/// | 4           | schema version (little endian)       |
+/// | 8           | payload checksum (little endian)     |
+/// | 1           | length of the kind                   |
+/// | *n*         | kind (UTF-8)                         |
+/// | *remaining* | payload                              |
+///
+/// The checksum is the XXH3 hash of the payload as stored (so after compression), which allows
+/// corruption to be detected before attempting to decompress or deserialize the payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    /// The kind of artifact contained in the payload, see [`Versioned::KIND`].
+    pub kind: String,
+    /// The format the payload was serialized in.
+    pub format: Format,
+    /// The codec the payload was compressed with.
+    pub codec: Codec,
+    /// The schema version the payload was serialized with.
+    pub version: u32,
+    /// The serialized (and compressed) artifact.
+    pub payload: Vec<u8>,
+}
+
+impl Envelope {
+    /// Serializes `value` in `format`, compresses it with `codec` and wraps it in an envelope
+    /// with the current version of `T`.
+    ///
+    /// # Errors
+    /// Returns an error if `value` fails to serialize or compress.
+    pub fn seal<T: Versioned>(value: &T, format: Format, codec: Codec) -> Result<Self, Error> {
+        Ok(Self {
+            kind: T::KIND.to_string(),
+            format,
+            codec,
+            version: T::VERSION,
+            payload: codec.compress(serialize(value, format)?)?,
+        })
+    }
+
+    /// Deserializes the payload as `T`, running any migrations needed to bring it up to date.
+    ///
+    /// # Errors
+    /// Returns an error if the envelope contains a different kind, was written by a newer
+    /// version, can't be decompressed, can't be migrated or fails to deserialize.
+    pub fn open<T: Versioned>(self, registry: &MigrationRegistry) -> Result<T, Error> {
+        if self.kind != T::KIND {
+            return Err(Error::KindMismatch {
+                expected: T::KIND,
+                found: self.kind,
+            });
+        }
+
+        if self.version > T::VERSION {
+            return Err(Error::UnsupportedVersion {
+                kind: T::KIND,
+                found: self.version,
+                supported: T::VERSION,
+            });
+        }
+
+        let payload = self.codec.decompress(self.payload)?;
+        if self.version == T::VERSION {
+            return deserialize(&payload, self.format);
+        }
+
+        let value = deserialize_value(&payload, self.format)?;
+
+        registry.upgrade(self.version, value, self.format)
+    }
+
+    /// Writes the envelope header followed by the payload.
+    ///
+    /// # Panics
+    /// Panics if `kind` is longer than 255 bytes.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let kind = self.kind.as_bytes();
+        let kind_length = u8::try_from(kind.len()).expect("envelope kinds are at most 255 bytes");
+
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 15 + kind.len() + self.payload.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(self.format.id());
+        bytes.push(self.codec.id());
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&xxh3_64(&self.payload).to_le_bytes());
+        bytes.push(kind_length);
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(&self.payload);
+
+        bytes
+    }
+
+    /// Parses an envelope previously written by [`Envelope::to_bytes`].
+    ///
+    /// # Errors
+    /// Returns an error if `bytes` doesn't start with a valid envelope header, or
+    /// [`Error::ChecksumMismatch`] if the payload was corrupted.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let bytes = bytes.strip_prefix(&MAGIC).ok_or(Error::InvalidMagic)?;
+        let (&format, bytes) = bytes.split_first().ok_or(Error::Truncated)?;
+        let (&codec, bytes) = bytes.split_first().ok_or(Error::Truncated)?;
+        let (version, bytes) = bytes.split_first_chunk::<4>().ok_or(Error::Truncated)?;
+        let (checksum, bytes) = bytes.split_first_chunk::<8>().ok_or(Error::Truncated)?;
+        let (&kind_length, bytes) = bytes.split_first().ok_or(Error::Truncated)?;
+        let (kind, payload) = bytes
+            .split_at_checked(usize::from(kind_length))
+            .ok_or(Error::Truncated)?;
+
+        let expected = u64::from_le_bytes(*checksum);
+        let actual = xxh3_64(payload);
+        if expected != actual {
+            return Err(Error::ChecksumMismatch { expected, actual });
+        }
+
+        Ok(Self {
+            kind: String::from_utf8_lossy(kind).into_owned(),
+            format: Format::from_id(format)?,
+            codec: Codec::from_id(codec)?,
+            version: u32::from_le_bytes(*version),
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+/// Serializes `value` in `format`, compresses it using `codec` and wraps it in an [`Envelope`].
+///
+/// # Errors
+/// Returns an error if `value` fails to serialize or compress.
+pub fn serialize_versioned<T: Versioned>(
+    value: &T,
+    format: Format,
+    codec: Codec,
+) -> Result<Vec<u8>, Error> {
+    Envelope::seal(value, format, codec).map(|envelope| envelope.to_bytes())
+}
+
+/// Reads a `T` from an [`Envelope`], decompressing and migrating it to the current version if needed.
+///
+/// # Errors
+/// Returns an error if `bytes` isn't a valid envelope containing a (migratable) `T`.
+pub fn deserialize_versioned<T: Versioned>(
+    bytes: &[u8],
+    registry: &MigrationRegistry,
+) -> Result<T, Error> {
+    Envelope::from_bytes(bytes)?.open(registry)
+}
@@ -0,0 +1,110 @@
+//! Contains the [`Error`] type returned by all serialization operations.
+
+use crate::Codec;
+use thiserror::Error;
+
+/// A boxed error originating from one of the underlying format implementations.
+type FormatError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Errors that can occur while serializing or deserializing.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// The underlying format failed to serialize the value.
+    #[error("failed to serialize: {0}")]
+    Serialize(#[source] FormatError),
+    /// The underlying format failed to deserialize the value.
+    #[error("failed to deserialize: {0}")]
+    Deserialize(#[source] FormatError),
+    /// The codec failed to compress the payload.
+    #[error("failed to compress: {0}")]
+    Compression(#[source] FormatError),
+    /// The codec failed to decompress the payload.
+    #[error("failed to decompress: {0}")]
+    Decompression(#[source] FormatError),
+    /// The codec's backend isn't compiled into this build.
+    #[error("the {0:?} codec is not available in this build")]
+    CodecUnavailable(Codec),
+    /// Deriving the key or encrypting the payload failed.
+    #[error("failed to encrypt: {0}")]
+    Encryption(#[source] FormatError),
+    /// The payload couldn't be decrypted, either the passphrase is wrong or the data was modified.
+    #[error("wrong passphrase or corrupted data")]
+    WrongPassphrase,
+    /// Reading or writing the underlying stream failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// The data does not start with the expected magic bytes.
+    #[error("data is not a DungeonRS envelope or stream")]
+    InvalidMagic,
+    /// The data ended before a header or record was complete.
+    #[error("data is truncated")]
+    Truncated,
+    /// A record of a stream is larger than [`MAX_RECORD_SIZE`](crate::MAX_RECORD_SIZE), the
+    /// stream is corrupted or wasn't written by a [`StreamWriter`](crate::StreamWriter).
+    #[error("record of {size} bytes exceeds the maximum record size of {max} bytes")]
+    RecordTooLarge {
+        /// The size of the record, in bytes.
+        size: u64,
+        /// The maximum size of a record, in bytes.
+        max: usize,
+    },
+    /// A compressed payload inflates to more than the allowed size, usually
+    /// [`MAX_DECOMPRESSED_SIZE`](crate::MAX_DECOMPRESSED_SIZE), the payload is corrupted or
+    /// malicious.
+    #[error("payload decompresses to more than the maximum size of {max} bytes")]
+    PayloadTooLarge {
+        /// The maximum size of a decompressed payload, in bytes.
+        max: usize,
+    },
+    /// The envelope header references a format this build doesn't know.
+    #[error("unknown serialization format identifier {0}")]
+    UnknownFormat(u8),
+    /// The payload doesn't match the checksum recorded in the envelope header, the data was
+    /// corrupted after it was written.
+    #[error("checksum mismatch: expected {expected:#018x} but payload hashes to {actual:#018x}")]
+    ChecksumMismatch {
+        /// The checksum recorded in the envelope header.
+        expected: u64,
+        /// The checksum of the payload as read.
+        actual: u64,
+    },
+    /// The envelope header references a codec this build doesn't know.
+    #[error("unknown codec identifier {0}")]
+    UnknownCodec(u8),
+    /// The envelope contains a different kind of artifact than was requested.
+    #[error("expected a '{expected}' envelope but found '{found}'")]
+    KindMismatch {
+        /// The kind that was requested.
+        expected: &'static str,
+        /// The kind recorded in the envelope.
+        found: String,
+    },
+    /// The envelope was written by a newer build using a schema version this build can't read.
+    #[error("'{kind}' version {found} is newer than the supported version {supported}")]
+    UnsupportedVersion {
+        /// The kind of artifact.
+        kind: &'static str,
+        /// The version recorded in the envelope.
+        found: u32,
+        /// The newest version this build supports.
+        supported: u32,
+    },
+    /// No migration was registered to upgrade from the given version.
+    #[error("no migration registered to upgrade '{kind}' from version {from}")]
+    MissingMigration {
+        /// The kind of artifact.
+        kind: String,
+        /// The version for which no upgrade function exists.
+        from: u32,
+    },
+    /// A migration function failed to upgrade the payload.
+    #[error("failed to migrate '{kind}' from version {from}: {reason}")]
+    Migration {
+        /// The kind of artifact.
+        kind: String,
+        /// The version the migration was upgrading from.
+        from: u32,
+        /// A human-readable description of what went wrong.
+        reason: String,
+    },
+}
@@ -0,0 +1,182 @@
+//! Contains the [`Format`] enum and the format-dispatching [`serialize`] and [`deserialize`].
+
+use crate::Error;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// The formats values can be serialized into.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// Compact binary `MessagePack`, used for artifacts that aren't meant to be edited by hand.
+    #[default]
+    MessagePack,
+    /// Human-readable JSON.
+    Json,
+    /// Human-readable [RON](https://github.com/ron-rs/ron).
+    Ron,
+    /// Human-editable [TOML](https://toml.io/), intended for small artifacts such as
+    /// configuration fragments, pack manifests and export presets.
+    ///
+    /// TOML requires the top-level value to be a table (struct or map).
+    Toml,
+}
+
+impl Format {
+    /// Every format supported by this build.
+    pub const ALL: [Format; 4] = [Format::MessagePack, Format::Json, Format::Ron, Format::Toml];
+
+    /// The human-readable formats, in the order [`deserialize_auto`] tries them.
+    const TEXT: [Format; 3] = [Format::Json, Format::Ron, Format::Toml];
+
+    /// The identifier used to record this format in an envelope header.
+    #[must_use]
+    pub const fn id(self) -> u8 {
+        match self {
+            Format::MessagePack => 0,
+            Format::Json => 1,
+            Format::Ron => 2,
+            Format::Toml => 3,
+        }
+    }
+
+    /// Looks up the format for an identifier previously returned by [`Format::id`].
+    ///
+    /// # Errors
+    /// Returns [`Error::UnknownFormat`] if `id` doesn't match any known format.
+    pub fn from_id(id: u8) -> Result<Self, Error> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.id() == id)
+            .ok_or(Error::UnknownFormat(id))
+    }
+
+    /// Guesses the format of `bytes` by inspecting the leading characters.
+    ///
+    /// Text formats are recognised by their first non-whitespace characters: `key = value` pairs,
+    /// `[table]` headers and `#` comments indicate TOML, objects, arrays and strings indicate JSON
+    /// while anything else (struct names, tuples, `//` comments, ...) indicates RON. Data that
+    /// doesn't start with printable ASCII is assumed to be `MessagePack`.
+    ///
+    /// Some inputs are valid in several text formats (JSON objects are also valid RON for
+    /// example), [`deserialize_auto`] accounts for this by trying the other text formats when
+    /// the detected one fails.
+    #[must_use]
+    pub fn detect(bytes: &[u8]) -> Self {
+        let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+        let Some(start) = bytes.iter().position(|byte| !byte.is_ascii_whitespace()) else {
+            return Format::Json;
+        };
+        let bytes = &bytes[start..];
+        let line = bytes.split(|byte| *byte == b'\n').next().unwrap_or(bytes);
+
+        match bytes[0] {
+            byte if !byte.is_ascii() || byte.is_ascii_control() => Format::MessagePack,
+            b'#' if bytes.get(1) != Some(&b'!') => Format::Toml,
+            b'[' if is_toml_table_header(line) => Format::Toml,
+            b'{' | b'[' | b'"' => Format::Json,
+            _ if is_toml_key_value(line) => Format::Toml,
+            _ => Format::Ron,
+        }
+    }
+}
+
+/// Returns whether `line` is a TOML table header such as `[table]` or `[[array.of.tables]]`.
+fn is_toml_table_header(line: &[u8]) -> bool {
+    let line = line.trim_ascii();
+    let name = line
+        .strip_prefix(b"[[")
+        .and_then(|line| line.strip_suffix(b"]]"))
+        .or_else(|| {
+            line.strip_prefix(b"[")
+                .and_then(|line| line.strip_suffix(b"]"))
+        });
+
+    name.is_some_and(|name| !name.is_empty() && name.iter().all(|byte| is_toml_key_byte(*byte)))
+}
+
+/// Returns whether `line` is a TOML `key = value` pair.
+fn is_toml_key_value(line: &[u8]) -> bool {
+    line.iter()
+        .position(|byte| *byte == b'=')
+        .map(|index| line[..index].trim_ascii())
+        .is_some_and(|key| !key.is_empty() && key.iter().all(|byte| is_toml_key_byte(*byte)))
+}
+
+/// Returns whether `byte` can appear in a (dotted, bare) TOML key.
+fn is_toml_key_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'.' | b' ')
+}
+
+/// The byte order mark some editors prepend to UTF-8 text files.
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// Serializes `value` into `format`.
+///
+/// # Errors
+/// Returns [`Error::Serialize`] if the underlying format fails to serialize `value`.
+pub fn serialize<T>(value: &T, format: Format) -> Result<Vec<u8>, Error>
+where
+    T: Serialize + ?Sized,
+{
+    match format {
+        Format::MessagePack => {
+            rmp_serde::to_vec_named(value).map_err(|error| Error::Serialize(error.into()))
+        }
+        Format::Json => serde_json::to_vec(value).map_err(|error| Error::Serialize(error.into())),
+        Format::Ron => ron::to_string(value)
+            .map(String::into_bytes)
+            .map_err(|error| Error::Serialize(error.into())),
+        Format::Toml => toml::to_string(value)
+            .map(String::into_bytes)
+            .map_err(|error| Error::Serialize(error.into())),
+    }
+}
+
+/// Deserializes a `T` from `bytes` previously serialized in `format`.
+///
+/// # Errors
+/// Returns [`Error::Deserialize`] if `bytes` isn't a valid `T` in `format`.
+pub fn deserialize<T>(bytes: &[u8], format: Format) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    match format {
+        Format::MessagePack => {
+            rmp_serde::from_slice(bytes).map_err(|error| Error::Deserialize(error.into()))
+        }
+        Format::Json => {
+            serde_json::from_slice(bytes).map_err(|error| Error::Deserialize(error.into()))
+        }
+        Format::Ron => ron::de::from_bytes(bytes).map_err(|error| Error::Deserialize(error.into())),
+        Format::Toml => std::str::from_utf8(bytes)
+            .map_err(|error| Error::Deserialize(error.into()))
+            .and_then(|text| {
+                toml::from_str(text).map_err(|error| Error::Deserialize(error.into()))
+            }),
+    }
+}
+
+/// Deserializes a `T` from `bytes` without knowing the format up front.
+///
+/// The format is picked using [`Format::detect`], so readers no longer need to know which format
+/// the writer used. Because some inputs are valid in several text formats, data detected as text
+/// that fails to deserialize is retried using the other text formats before giving up.
+///
+/// # Errors
+/// Returns [`Error::Deserialize`] if `bytes` isn't a valid `T` in the detected format.
+pub fn deserialize_auto<T>(bytes: &[u8]) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+
+    let detected = Format::detect(bytes);
+    match deserialize(bytes, detected) {
+        Err(error) if detected != Format::MessagePack => Format::TEXT
+            .into_iter()
+            .filter(|format| *format != detected)
+            .find_map(|format| deserialize(bytes, format).ok())
+            .ok_or(error),
+        result => result,
+    }
+}
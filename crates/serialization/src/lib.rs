@@ -0,0 +1,25 @@
+#![doc = include_str!("../README.md")]
+
+mod codec;
+#[cfg(feature = "compact")]
+pub mod compact;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod envelope;
+mod error;
+mod format;
+mod migration;
+#[cfg(feature = "schema")]
+mod schema;
+mod stream;
+
+pub use codec::{Codec, MAX_DECOMPRESSED_SIZE};
+#[cfg(feature = "encryption")]
+pub use encryption::{ENCRYPTED_MAGIC, Encrypted};
+pub use envelope::{Envelope, MAGIC, Versioned, deserialize_versioned, serialize_versioned};
+pub use error::Error;
+pub use format::{Format, deserialize, deserialize_auto, serialize};
+pub use migration::{Migration, MigrationRegistry, deserialize_value, from_value};
+#[cfg(feature = "schema")]
+pub use schema::json_schema;
+pub use stream::{MAX_RECORD_SIZE, STREAM_MAGIC, StreamReader, StreamWriter};
@@ -0,0 +1,338 @@
+//! Contains the [`MigrationRegistry`] used to upgrade outdated envelopes.
+
+use crate::{Error, Format, Versioned, deserialize};
+use bevy::prelude::Resource;
+use serde::de::value::{
+    Error as ValueError, MapAccessDeserializer, MapDeserializer, SeqDeserializer,
+};
+use serde::de::{
+    self, DeserializeOwned, IntoDeserializer, MapAccess, SeqAccess, Unexpected, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Number, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Upgrades a payload from one schema version to the next.
+///
+/// Migrations operate on a format-agnostic [`Value`] so the same upgrade function works
+/// regardless of the format the artifact was written in.
+pub type Migration = Box<dyn Fn(Value) -> Result<Value, String> + Send + Sync>;
+
+/// Collects the upgrade functions for every kind of [`Versioned`] artifact.
+///
+/// Each migration upgrades a payload from version `from` to `from + 1`; reading an envelope
+/// several versions behind runs each step in order.
+#[derive(Resource, Default)]
+pub struct MigrationRegistry {
+    /// The registered migrations, grouped by artifact kind and keyed by the version they upgrade from.
+    migrations: HashMap<&'static str, BTreeMap<u32, Migration>>,
+}
+
+impl MigrationRegistry {
+    /// Registers a migration upgrading `T` from version `from` to `from + 1`.
+    ///
+    /// Registering a second migration for the same version replaces the previous one.
+    pub fn register<T, F>(&mut self, from: u32, migration: F) -> &mut Self
+    where
+        T: Versioned,
+        F: Fn(Value) -> Result<Value, String> + Send + Sync + 'static,
+    {
+        self.migrations
+            .entry(T::KIND)
+            .or_default()
+            .insert(from, Box::new(migration));
+
+        self
+    }
+
+    /// Upgrades `value` of the given `kind` from version `from` up to version `to`.
+    ///
+    /// # Errors
+    /// Returns [`Error::MissingMigration`] if one of the intermediate steps wasn't registered,
+    /// or [`Error::Migration`] if one of the migrations failed.
+    pub fn migrate(
+        &self,
+        kind: &str,
+        from: u32,
+        to: u32,
+        mut value: Value,
+    ) -> Result<Value, Error> {
+        let migrations = self.migrations.get(kind);
+
+        for version in from..to {
+            let migration = migrations
+                .and_then(|migrations| migrations.get(&version))
+                .ok_or_else(|| Error::MissingMigration {
+                    kind: kind.to_string(),
+                    from: version,
+                })?;
+
+            value = migration(value).map_err(|reason| Error::Migration {
+                kind: kind.to_string(),
+                from: version,
+                reason,
+            })?;
+        }
+
+        Ok(value)
+    }
+
+    /// Upgrades `value`, read from a `T` of version `from` serialized in `format`, and
+    /// deserializes the result as the current version of `T` with [`from_value`].
+    ///
+    /// # Errors
+    /// Returns an error if `value` can't be migrated or the result isn't a valid `T`.
+    pub fn upgrade<T: Versioned>(
+        &self,
+        from: u32,
+        value: Value,
+        format: Format,
+    ) -> Result<T, Error> {
+        let value = self.migrate(T::KIND, from, T::VERSION, value)?;
+
+        from_value(value, format)
+    }
+}
+
+/// Deserializes a `T` from `value`, read with [`deserialize_value`] from data serialized in
+/// `format`.
+///
+/// Values read from `MessagePack` are deserialized the way binary formats are, so values with a
+/// binary representation (such as IDs written as byte arrays) are read back the way they were
+/// written.
+///
+/// # Errors
+/// Returns [`Error::Deserialize`] if `value` isn't a valid `T`.
+pub fn from_value<T: DeserializeOwned>(value: Value, format: Format) -> Result<T, Error> {
+    if format == Format::MessagePack {
+        return T::deserialize(BinaryValue(value))
+            .map_err(|error| Error::Deserialize(error.into()));
+    }
+
+    serde_json::from_value(value).map_err(|error| Error::Deserialize(error.into()))
+}
+
+/// Deserializes `bytes`, serialized in `format`, as a [`Value`] that migrations can operate on.
+///
+/// Unlike deserializing a [`Value`] directly, this also accepts byte arrays (such as the IDs
+/// `MessagePack` writes in binary), which are represented as arrays of numbers.
+///
+/// # Errors
+/// Returns [`Error::Deserialize`] if `bytes` isn't valid in `format`.
+pub fn deserialize_value(bytes: &[u8], format: Format) -> Result<Value, Error> {
+    deserialize::<MigrationValue>(bytes, format).map(|value| value.0)
+}
+
+/// A [`Value`] that also accepts byte arrays when deserialized.
+struct MigrationValue(Value);
+
+impl<'de> Deserialize<'de> for MigrationValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_any(MigrationValueVisitor)
+            .map(Self)
+    }
+}
+
+/// Builds a [`Value`] out of whatever the deserializer holds.
+struct MigrationValueVisitor;
+
+impl<'de> Visitor<'de> for MigrationValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Number(value.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::Number(value.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Value, E> {
+        Ok(Number::from_f64(value).map_or(Value::Null, Value::Number))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Value, E> {
+        Ok(Value::Array(
+            value.iter().map(|byte| Value::from(*byte)).collect(),
+        ))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(MigrationValue(value)) = seq.next_element()? {
+            values.push(value);
+        }
+
+        Ok(Value::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut values = Map::new();
+        while let Some((MigrationValue(key), MigrationValue(value))) = map.next_entry()? {
+            // Keys that aren't strings (such as numeric IDs) are kept in their JSON form.
+            let key = match key {
+                Value::String(key) => key,
+                key => key.to_string(),
+            };
+            values.insert(key, value);
+        }
+
+        Ok(Value::Object(values))
+    }
+}
+
+/// Deserializes a [`Value`] as if it was read from a binary format.
+///
+/// Binary formats represent some values differently than human-readable ones (IDs as byte arrays
+/// rather than strings), which the [`Value`] deserializer doesn't expect.
+struct BinaryValue(Value);
+
+impl IntoDeserializer<'_, ValueError> for BinaryValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for BinaryValue {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.0 {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(value) => visitor.visit_bool(value),
+            Value::Number(number) => {
+                if let Some(value) = number.as_u64() {
+                    visitor.visit_u64(value)
+                } else if let Some(value) = number.as_i64() {
+                    visitor.visit_i64(value)
+                } else {
+                    visitor.visit_f64(number.as_f64().unwrap_or_default())
+                }
+            }
+            Value::String(value) => visitor.visit_string(value),
+            Value::Array(values) => {
+                visitor.visit_seq(SeqDeserializer::new(values.into_iter().map(BinaryValue)))
+            }
+            Value::Object(values) => visitor.visit_map(MapDeserializer::new(
+                values
+                    .into_iter()
+                    .map(|(key, value)| (key, BinaryValue(value))),
+            )),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        let Value::Array(values) = &self.0 else {
+            return self.deserialize_any(visitor);
+        };
+        let bytes: Option<Vec<u8>> = values
+            .iter()
+            .map(|value| value.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+            .collect();
+
+        match bytes {
+            Some(bytes) => visitor.visit_byte_buf(bytes),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        match self.0 {
+            // Unit variants are written as their name, other variants as a single entry map.
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Object(values) => {
+                visitor.visit_enum(MapAccessDeserializer::new(MapDeserializer::new(
+                    values
+                        .into_iter()
+                        .map(|(key, value)| (key, BinaryValue(value))),
+                )))
+            }
+            value => Err(de::Error::invalid_type(unexpected(&value), &"an enum")),
+        }
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Describes `value` for an invalid type error.
+fn unexpected(value: &Value) -> Unexpected<'_> {
+    match value {
+        Value::Null => Unexpected::Unit,
+        Value::Bool(value) => Unexpected::Bool(*value),
+        Value::Number(_) => Unexpected::Other("number"),
+        Value::String(value) => Unexpected::Str(value),
+        Value::Array(_) => Unexpected::Seq,
+        Value::Object(_) => Unexpected::Map,
+    }
+}
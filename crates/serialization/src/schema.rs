@@ -0,0 +1,24 @@
+//! Contains [`json_schema`], used to describe serialized artifacts to third-party tools.
+
+use crate::Versioned;
+use schemars::{JsonSchema, Schema, schema_for};
+
+/// Generates a JSON Schema describing the JSON representation of `T`.
+///
+/// This allows third-party tools (and the validation commands of our own CLI) to check files
+/// without embedding this crate. The schema describes the payload, not the [`Envelope`] around
+/// it, so it applies to artifacts serialized in [`Format::Json`] (or any format after conversion).
+///
+/// The kind and version of the artifact are recorded in the `x-dungeonrs-kind` and
+/// `x-dungeonrs-version` keywords, so a tool can verify it validates against the right schema.
+///
+/// [`Envelope`]: crate::Envelope
+/// [`Format::Json`]: crate::Format::Json
+#[must_use]
+pub fn json_schema<T: Versioned + JsonSchema>() -> Schema {
+    let mut schema = schema_for!(T);
+    schema.insert(String::from("x-dungeonrs-kind"), T::KIND.into());
+    schema.insert(String::from("x-dungeonrs-version"), T::VERSION.into());
+
+    schema
+}
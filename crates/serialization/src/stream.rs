@@ -0,0 +1,246 @@
+//! Contains the [`StreamWriter`] and [`StreamReader`] used to (de)serialize large collections
+//! one record at a time.
+
+use crate::{Error, Format, deserialize, deserialize_value, serialize};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+
+/// The magic bytes every stream starts with.
+pub const STREAM_MAGIC: [u8; 4] = *b"DRS\x1B";
+
+/// The largest record a stream holds, in bytes.
+///
+/// The length of a record is read from the stream before the record itself, a corrupted or
+/// malicious stream could otherwise claim records of up to 4 GiB.
+pub const MAX_RECORD_SIZE: usize = 256 * 1024 * 1024;
+
+/// Writes a stream of individually serialized records.
+///
+/// Rather than serializing a whole collection (and thus holding both the collection and its
+/// serialized form in memory), each record is serialized and written on its own. This keeps
+/// peak memory proportional to the largest record rather than the entire collection, which
+/// matters for projects containing hundreds of thousands of elements.
+///
+/// The stream starts with [`STREAM_MAGIC`] and the [`Format::id`], followed by records that are
+/// each prefixed with their length as a little endian `u32`. Records don't need to share a type,
+/// a common pattern is to write a header record followed by the items it describes.
+pub struct StreamWriter<W: Write> {
+    /// The buffered writer records are written to.
+    writer: BufWriter<W>,
+    /// The format each record is serialized in.
+    format: Format,
+}
+
+impl<W: Write> StreamWriter<W> {
+    /// Starts a new stream of records serialized in `format`.
+    ///
+    /// # Errors
+    /// Returns [`Error::Io`] if the stream header can't be written.
+    pub fn new(writer: W, format: Format) -> Result<Self, Error> {
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(&STREAM_MAGIC)?;
+        writer.write_all(&[format.id()])?;
+
+        Ok(Self { writer, format })
+    }
+
+    /// Serializes and writes a single record.
+    ///
+    /// # Errors
+    /// Returns an error if `record` fails to serialize, exceeds [`MAX_RECORD_SIZE`] or can't be
+    /// written.
+    pub fn write<T>(&mut self, record: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let bytes = serialize(record, self.format)?;
+        self.write_serialized(&bytes)
+    }
+
+    /// Writes a record that was already serialized in this stream's [`format`](Self::format).
+    ///
+    /// This allows reusing the bytes of records that haven't changed since they were last
+    /// serialized.
+    ///
+    /// # Errors
+    /// Returns an error if `bytes` exceeds [`MAX_RECORD_SIZE`] or can't be written.
+    pub fn write_serialized(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let length = u32::try_from(bytes.len())
+            .ok()
+            .filter(|_| bytes.len() <= MAX_RECORD_SIZE)
+            .ok_or(Error::RecordTooLarge {
+                size: bytes.len() as u64,
+                max: MAX_RECORD_SIZE,
+            })?;
+
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(bytes)?;
+
+        Ok(())
+    }
+
+    /// The format each record in this stream is serialized in.
+    #[must_use]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Serializes and writes every record yielded by `records`.
+    ///
+    /// # Errors
+    /// Returns an error as soon as one of the records fails to be written.
+    pub fn write_all<I>(&mut self, records: I) -> Result<(), Error>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        records
+            .into_iter()
+            .try_for_each(|record| self.write(&record))
+    }
+
+    /// Flushes any buffered records and returns the underlying writer.
+    ///
+    /// # Errors
+    /// Returns [`Error::Io`] if flushing fails.
+    pub fn finish(self) -> Result<W, Error> {
+        self.writer
+            .into_inner()
+            .map_err(|error| Error::Io(error.into_error()))
+    }
+}
+
+/// Reads a stream of records previously written by a [`StreamWriter`].
+pub struct StreamReader<R: Read> {
+    /// The buffered reader records are read from.
+    reader: BufReader<R>,
+    /// The format each record is serialized in.
+    format: Format,
+    /// Scratch buffer reused for every record to avoid an allocation per record.
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> StreamReader<R> {
+    /// Opens a stream of records, reading the format from the stream header.
+    ///
+    /// # Errors
+    /// Returns an error if `reader` doesn't start with a valid stream header.
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let mut reader = BufReader::new(reader);
+        let mut header = [0; STREAM_MAGIC.len() + 1];
+        read_exact(&mut reader, &mut header)?;
+
+        let (magic, format) = header.split_at(STREAM_MAGIC.len());
+        if magic != STREAM_MAGIC {
+            return Err(Error::InvalidMagic);
+        }
+
+        Ok(Self {
+            reader,
+            format: Format::from_id(format[0])?,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// The format the records in this stream are serialized in.
+    #[must_use]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Reads the next record, returning `None` once the stream is exhausted.
+    ///
+    /// # Errors
+    /// Returns an error if the stream ends in the middle of a record or the record isn't a valid `T`.
+    pub fn read<T>(&mut self) -> Result<Option<T>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        if !self.next_record()? {
+            return Ok(None);
+        }
+
+        deserialize(&self.buffer, self.format).map(Some)
+    }
+
+    /// Reads the next record as a [`Value`], to be upgraded by the [`MigrationRegistry`] when it
+    /// was written by an older version.
+    ///
+    /// Returns `None` once the stream is exhausted.
+    ///
+    /// [`MigrationRegistry`]: crate::MigrationRegistry
+    ///
+    /// # Errors
+    /// Returns an error if the stream ends in the middle of a record or the record is invalid.
+    pub fn read_value(&mut self) -> Result<Option<Value>, Error> {
+        if !self.next_record()? {
+            return Ok(None);
+        }
+
+        deserialize_value(&self.buffer, self.format).map(Some)
+    }
+
+    /// Reads the next record into the buffer, returning `false` once the stream is exhausted.
+    ///
+    /// # Errors
+    /// Returns [`Error::Truncated`] if the stream ends in the middle of a record or of its length,
+    /// and [`Error::RecordTooLarge`] if the record exceeds [`MAX_RECORD_SIZE`].
+    fn next_record(&mut self) -> Result<bool, Error> {
+        // Only a stream ending right before a record ends cleanly.
+        let mut length = [0; 4];
+        let read = loop {
+            match self.reader.read(&mut length) {
+                Ok(read) => break read,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error.into()),
+            }
+        };
+        if read == 0 {
+            return Ok(false);
+        }
+        read_exact(&mut self.reader, &mut length[read..])?;
+
+        let length = u32::from_le_bytes(length);
+        let size = usize::try_from(length)
+            .ok()
+            .filter(|size| *size <= MAX_RECORD_SIZE)
+            .ok_or(Error::RecordTooLarge {
+                size: u64::from(length),
+                max: MAX_RECORD_SIZE,
+            })?;
+
+        // The buffer only grows as the record's bytes arrive, not to the length it claims.
+        self.buffer.clear();
+        let read = (&mut self.reader)
+            .take(u64::from(length))
+            .read_to_end(&mut self.buffer)?;
+        if read < size {
+            return Err(Error::Truncated);
+        }
+
+        Ok(true)
+    }
+
+    /// Returns an iterator reading the remaining records as `T`.
+    pub fn records<T>(&mut self) -> impl Iterator<Item = Result<T, Error>> + '_
+    where
+        T: DeserializeOwned,
+    {
+        std::iter::from_fn(|| self.read().transpose())
+    }
+}
+
+/// Fills `buffer` from `reader`.
+///
+/// # Errors
+/// Returns [`Error::Truncated`] if `reader` ends before `buffer` is full.
+fn read_exact(reader: &mut impl Read, buffer: &mut [u8]) -> Result<(), Error> {
+    reader
+        .read_exact(buffer)
+        .map_err(|error| match error.kind() {
+            ErrorKind::UnexpectedEof => Error::Truncated,
+            _ => Error::Io(error),
+        })
+}
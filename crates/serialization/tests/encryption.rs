@@ -0,0 +1,110 @@
+//! Protects serialized artifacts with a passphrase through [`Encrypted`].
+#![cfg(feature = "encryption")]
+#![allow(clippy::missing_panics_doc)]
+
+use dungeonrs_serialization::{
+    Codec, ENCRYPTED_MAGIC, Encrypted, Error, Format, MigrationRegistry, Versioned,
+    deserialize_versioned, serialize_versioned,
+};
+use serde::{Deserialize, Serialize};
+
+/// The passphrase the fixtures are encrypted with.
+const PASSPHRASE: &str = "correct horse battery staple";
+
+/// A small artifact to encrypt.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Secret {
+    /// The name of the map.
+    name: String,
+    /// The notes only the game master should read.
+    notes: Vec<String>,
+}
+
+impl Versioned for Secret {
+    const KIND: &'static str = "secret";
+    const VERSION: u32 = 1;
+}
+
+/// The artifact encrypted by the tests.
+fn secret() -> Secret {
+    Secret {
+        name: "Crypt".into(),
+        notes: vec!["The lich hides behind the altar.".into()],
+    }
+}
+
+/// The bytes of [`secret`] encrypted with [`PASSPHRASE`].
+fn encrypted() -> Vec<u8> {
+    let plaintext = serialize_versioned(&secret(), Format::MessagePack, Codec::None).unwrap();
+
+    Encrypted::encrypt(&plaintext, PASSPHRASE)
+        .unwrap()
+        .to_bytes()
+}
+
+/// An encrypted artifact reads back with the passphrase it was encrypted with.
+#[test]
+fn round_trips() {
+    let bytes = encrypted();
+    assert!(Encrypted::is_encrypted(&bytes));
+
+    let plaintext = Encrypted::from_bytes(&bytes)
+        .unwrap()
+        .decrypt(PASSPHRASE)
+        .unwrap();
+    let value: Secret = deserialize_versioned(&plaintext, &MigrationRegistry::default()).unwrap();
+
+    assert_eq!(value, secret());
+}
+
+/// Decrypting with another passphrase is reported instead of producing garbage.
+#[test]
+fn rejects_wrong_passphrase() {
+    let encrypted = Encrypted::from_bytes(&encrypted()).unwrap();
+
+    let result = encrypted.decrypt("incorrect horse battery staple");
+    assert!(matches!(result, Err(Error::WrongPassphrase)));
+}
+
+/// Changing a single bit of the ciphertext, or of the nonce it was encrypted with, fails
+/// authentication.
+#[test]
+fn rejects_tampered_ciphertext() {
+    let bytes = encrypted();
+    let nonce = ENCRYPTED_MAGIC.len() + 16;
+
+    for index in [nonce, nonce + 24, bytes.len() - 1] {
+        let mut tampered = bytes.clone();
+        tampered[index] ^= 0x01;
+
+        let result = Encrypted::from_bytes(&tampered)
+            .unwrap()
+            .decrypt(PASSPHRASE);
+        assert!(
+            matches!(result, Err(Error::WrongPassphrase)),
+            "changing byte {index} went unnoticed"
+        );
+    }
+}
+
+/// Payloads ending within the salt or nonce are truncated, other data isn't encrypted at all.
+#[test]
+fn rejects_truncated_headers() {
+    let bytes = encrypted();
+
+    for length in [
+        ENCRYPTED_MAGIC.len(),
+        ENCRYPTED_MAGIC.len() + 16,
+        ENCRYPTED_MAGIC.len() + 39,
+    ] {
+        let result = Encrypted::from_bytes(&bytes[..length]);
+        assert!(
+            matches!(result, Err(Error::Truncated)),
+            "a header of {length} bytes was accepted"
+        );
+    }
+    assert!(matches!(
+        Encrypted::from_bytes(&bytes[..2]),
+        Err(Error::InvalidMagic)
+    ));
+}
@@ -0,0 +1,333 @@
+//! Round-trips values through every supported [`Format`].
+#![allow(clippy::missing_panics_doc)]
+
+use dungeonrs_serialization::{
+    Codec, Error, Format, MigrationRegistry, Versioned, deserialize, deserialize_auto,
+    deserialize_versioned, serialize, serialize_versioned,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A fixture covering the serde data model features our artifacts rely on.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Fixture {
+    /// A plain string.
+    name: String,
+    /// An integer.
+    version: u32,
+    /// A floating point number.
+    scale: f32,
+    /// A boolean.
+    enabled: bool,
+    /// An optional value that is present.
+    description: Option<String>,
+    /// An optional value that is absent.
+    missing: Option<String>,
+    /// A unit-only enum.
+    kind: Kind,
+    /// A sequence of primitives.
+    tags: Vec<String>,
+    /// A sequence of tuples.
+    points: Vec<(f32, f32)>,
+    /// A map.
+    layers: BTreeMap<String, Layer>,
+    /// A nested struct.
+    layer: Layer,
+}
+
+/// A unit-only enum.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    /// The first variant.
+    Dungeon,
+    /// The second variant.
+    Overworld,
+}
+
+/// A nested struct.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Layer {
+    /// A plain string.
+    name: String,
+    /// An integer.
+    order: i32,
+}
+
+impl Versioned for Fixture {
+    const KIND: &'static str = "fixture";
+    const VERSION: u32 = 1;
+}
+
+/// Builds a [`Fixture`] with every field populated.
+fn fixture() -> Fixture {
+    let layer = Layer {
+        name: String::from("background"),
+        order: -1,
+    };
+
+    Fixture {
+        name: String::from("The Sunless Citadel"),
+        version: 3,
+        scale: 1.5,
+        enabled: true,
+        description: Some(String::from("A ruined fortress")),
+        missing: None,
+        kind: Kind::Dungeon,
+        tags: vec![String::from("ruins"), String::from("underdark")],
+        points: vec![(0.0, 0.0), (10.5, -3.25)],
+        layers: BTreeMap::from([
+            (String::from("floor"), layer.clone()),
+            (
+                String::from("walls"),
+                Layer {
+                    name: String::from("walls"),
+                    order: 2,
+                },
+            ),
+        ]),
+        layer,
+    }
+}
+
+/// Every format deserializes what it serialized.
+#[test]
+fn round_trips_every_format() {
+    let fixture = fixture();
+
+    for format in Format::ALL {
+        let bytes = serialize(&fixture, format).unwrap();
+        let value: Fixture = deserialize(&bytes, format).unwrap();
+
+        assert_eq!(value, fixture, "{format:?} did not round-trip");
+    }
+}
+
+/// The format is detected correctly for everything we write.
+#[test]
+fn detects_every_format() {
+    let fixture = fixture();
+
+    for format in Format::ALL {
+        let bytes = serialize(&fixture, format).unwrap();
+        let value: Fixture = deserialize_auto(&bytes).unwrap();
+
+        assert_eq!(Format::detect(&bytes), format);
+        assert_eq!(value, fixture, "{format:?} did not round-trip");
+    }
+}
+
+/// Hand-written TOML is detected as TOML.
+#[test]
+fn detects_hand_written_toml() {
+    assert_eq!(Format::detect(b"# comment\nname = \"x\""), Format::Toml);
+    assert_eq!(Format::detect(b"[layer]\nname = \"x\""), Format::Toml);
+    assert_eq!(Format::detect(b"  name=\"x\""), Format::Toml);
+    assert_eq!(Format::detect(b"[1, 2]"), Format::Json);
+    assert_eq!(Format::detect(b"Layer(name: \"x\")"), Format::Ron);
+}
+
+/// Every format round-trips through an envelope, with every codec available in this build.
+#[test]
+fn round_trips_every_format_in_envelope() {
+    let fixture = fixture();
+    let registry = MigrationRegistry::default();
+    let codecs = [
+        Some(Codec::None),
+        cfg!(feature = "zstd").then_some(Codec::Zstd { level: 3 }),
+        cfg!(feature = "lz4").then_some(Codec::Lz4),
+    ];
+
+    for format in Format::ALL {
+        for codec in codecs.into_iter().flatten() {
+            let bytes = serialize_versioned(&fixture, format, codec).unwrap();
+            let value: Fixture = deserialize_versioned(&bytes, &registry).unwrap();
+
+            assert_eq!(
+                value, fixture,
+                "{format:?} with {codec:?} did not round-trip"
+            );
+        }
+    }
+}
+
+/// Corrupting the payload of an envelope is reported as a checksum mismatch.
+#[test]
+fn detects_corrupted_envelope() {
+    let registry = MigrationRegistry::default();
+    let mut bytes = serialize_versioned(&fixture(), Format::MessagePack, Codec::None).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xFF;
+
+    let result = deserialize_versioned::<Fixture>(&bytes, &registry);
+    assert!(matches!(result, Err(Error::ChecksumMismatch { .. })));
+}
+
+/// Payloads decompressing to more than the limit are rejected, payloads of exactly the limit
+/// aren't.
+#[test]
+fn limits_decompressed_size() {
+    let data = vec![0; 64 * 1024];
+    let codecs = [
+        cfg!(feature = "zstd").then_some(Codec::Zstd { level: 3 }),
+        cfg!(feature = "lz4").then_some(Codec::Lz4),
+    ];
+
+    for codec in codecs.into_iter().flatten() {
+        let compressed = codec.compress(data.clone()).unwrap();
+
+        let result = codec.decompress_limited(compressed.clone(), data.len() - 1);
+        assert!(
+            matches!(result, Err(Error::PayloadTooLarge { max }) if max == data.len() - 1),
+            "{codec:?} inflated past the limit"
+        );
+        assert_eq!(
+            codec.decompress_limited(compressed, data.len()).unwrap(),
+            data
+        );
+    }
+}
+
+/// An LZ4 payload claiming to inflate to 4 GiB is rejected before allocating for it.
+#[cfg(feature = "lz4")]
+#[test]
+fn rejects_oversized_lz4_prefix() {
+    let mut compressed = Codec::Lz4.compress(b"tiny".to_vec()).unwrap();
+    compressed[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    let result = Codec::Lz4.decompress(compressed);
+    assert!(matches!(
+        result,
+        Err(Error::PayloadTooLarge { max })
+            if max == dungeonrs_serialization::MAX_DECOMPRESSED_SIZE
+    ));
+}
+
+#[cfg(feature = "compact")]
+mod compact {
+    //! Round-trips the compact representations of math types and rejects malformed ones.
+
+    use super::*;
+    use bevy::color::Color;
+    use bevy::math::{Quat, Rect, Vec2, Vec3};
+    use bevy::transform::components::Transform;
+    use serde_json::json;
+
+    /// Every math type with a compact representation.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Compact {
+        /// A transform.
+        #[serde(with = "dungeonrs_serialization::compact::transform")]
+        transform: Transform,
+        /// A colour.
+        #[serde(with = "dungeonrs_serialization::compact::color")]
+        color: Color,
+        /// A rectangle.
+        #[serde(with = "dungeonrs_serialization::compact::rect")]
+        rect: Rect,
+        /// A point.
+        #[serde(with = "dungeonrs_serialization::compact::vec2")]
+        point: Vec2,
+    }
+
+    /// A [`Compact`] with the given `transform` and `color`.
+    fn compact(transform: Transform, color: Color) -> Compact {
+        Compact {
+            transform,
+            color,
+            rect: Rect::new(-8.0, -4.0, 8.0, 4.0),
+            point: Vec2::new(1.5, -2.5),
+        }
+    }
+
+    /// Transforms with and without a rotation and scale, with opaque and translucent sRGB colours,
+    /// round-trip through every format.
+    #[test]
+    fn round_trips_every_format() {
+        let translation = Transform::from_xyz(64.0, -32.0, 2.0);
+        let rotation = Quat::from_rotation_z(0.5);
+        let values = [
+            compact(Transform::IDENTITY, Color::srgb(1.0, 1.0, 1.0)),
+            compact(translation, Color::srgb(0.25, 0.5, 0.75)),
+            compact(
+                translation.with_scale(Vec3::splat(2.0)),
+                Color::srgba(0.0, 0.0, 0.0, 0.0),
+            ),
+            compact(
+                translation.with_rotation(rotation),
+                Color::srgba(1.0, 0.0, 0.0, 0.5),
+            ),
+            compact(
+                translation
+                    .with_rotation(rotation)
+                    .with_scale(Vec3::new(0.5, 2.0, 1.0)),
+                Color::srgb(0.0, 0.0, 0.0),
+            ),
+        ];
+
+        for format in Format::ALL {
+            for value in &values {
+                let bytes = serialize(value, format).unwrap();
+                let read: Compact = deserialize(&bytes, format).unwrap();
+
+                assert_eq!(&read, value, "{format:?} did not round-trip");
+            }
+        }
+    }
+
+    /// Identity rotations, identity scales and opaque alpha channels are left out.
+    #[test]
+    fn omits_defaults() {
+        let value = compact(
+            Transform::from_xyz(1.0, 2.0, 3.0),
+            Color::srgb(1.0, 1.0, 1.0),
+        );
+        let bytes = serialize(&value, Format::Json).unwrap();
+        let json: serde_json::Value = deserialize(&bytes, Format::Json).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "transform": [1.0, 2.0, 3.0],
+                "color": [1.0, 1.0, 1.0],
+                "rect": [-8.0, -4.0, 8.0, 4.0],
+                "point": [1.5, -2.5],
+            })
+        );
+    }
+
+    /// Arrays of unsupported lengths and values that aren't numbers are rejected.
+    #[test]
+    fn rejects_malformed_input() {
+        let valid = json!({
+            "transform": [1.0, 2.0, 3.0],
+            "color": [1.0, 1.0, 1.0],
+            "rect": [0.0, 0.0, 1.0, 1.0],
+            "point": [0.0, 0.0],
+        });
+        let malformed = [
+            ("transform", json!([1.0, 2.0])),
+            ("transform", json!([1.0, 2.0, 3.0, 4.0])),
+            ("transform", json!([1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0])),
+            ("transform", json!({"translation": [1.0, 2.0, 3.0]})),
+            ("color", json!([1.0, 1.0])),
+            ("color", json!([1.0, 1.0, 1.0, 1.0, 1.0])),
+            ("color", json!("#ffffff")),
+            ("rect", json!([0.0, 0.0, 1.0])),
+            ("point", json!([0.0, 0.0, 0.0])),
+            ("point", json!(["x", "y"])),
+        ];
+        let bytes = serialize(&valid, Format::Json).unwrap();
+        assert!(deserialize::<Compact>(&bytes, Format::Json).is_ok());
+
+        for (field, value) in malformed {
+            let mut input = valid.clone();
+            input[field] = value.clone();
+            let bytes = serialize(&input, Format::Json).unwrap();
+
+            assert!(
+                deserialize::<Compact>(&bytes, Format::Json).is_err(),
+                "{field} {value} was accepted"
+            );
+        }
+    }
+}
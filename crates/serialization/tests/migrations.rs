@@ -0,0 +1,173 @@
+//! Upgrades artifacts written by older schema versions.
+#![allow(clippy::missing_panics_doc)]
+
+use dungeonrs_serialization::{
+    Codec, Envelope, Error, Format, MigrationRegistry, StreamReader, StreamWriter, Versioned,
+    deserialize_versioned,
+};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Value, json};
+use std::fmt;
+
+/// An ID written as a byte array by binary formats, like the UUIDs of save files.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Id([u8; 4]);
+
+impl Serialize for Id {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_u32(u32::from_be_bytes(self.0))
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            return u32::deserialize(deserializer).map(|id| Self(id.to_be_bytes()));
+        }
+
+        deserializer.deserialize_bytes(IdVisitor)
+    }
+}
+
+/// Reads an [`Id`] from its bytes.
+struct IdVisitor;
+
+impl Visitor<'_> for IdVisitor {
+    type Value = Id;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("4 bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Id, E> {
+        value
+            .try_into()
+            .map(Id)
+            .map_err(|_| E::invalid_length(value.len(), &self))
+    }
+}
+
+/// The first version of the artifact, with a single name.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct MapV1 {
+    /// Identifies the map.
+    id: Id,
+    /// The name of the map.
+    name: String,
+}
+
+/// The current version of the artifact, after splitting the name (version 2) and renaming it to
+/// a title (version 3).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Map {
+    /// Identifies the map.
+    id: Id,
+    /// The title of the map.
+    title: String,
+    /// The subtitle of the map.
+    subtitle: String,
+}
+
+impl Versioned for MapV1 {
+    const KIND: &'static str = "map";
+    const VERSION: u32 = 1;
+}
+
+impl Versioned for Map {
+    const KIND: &'static str = "map";
+    const VERSION: u32 = 3;
+}
+
+/// The migrations from version 1 of the map to version 3.
+fn registry() -> MigrationRegistry {
+    let mut registry = MigrationRegistry::default();
+    registry
+        .register::<Map, _>(1, |mut value| {
+            let name = value["name"].as_str().ok_or("missing name")?.to_owned();
+            let (name, subtitle) = name.split_once(": ").unwrap_or((&name, ""));
+            value["name"] = json!(name);
+            value["subtitle"] = json!(subtitle);
+            Ok(value)
+        })
+        .register::<Map, _>(2, |mut value| {
+            let name = value
+                .as_object_mut()
+                .and_then(|value| value.remove("name"))
+                .ok_or("missing name")?;
+            value["title"] = name;
+            Ok(value)
+        });
+
+    registry
+}
+
+/// The map as written by the first version.
+fn map_v1() -> MapV1 {
+    MapV1 {
+        id: Id([1, 2, 3, 4]),
+        name: "Crypt: Lower Level".into(),
+    }
+}
+
+/// An envelope runs every migration up to the current version, in every format.
+#[test]
+fn migrates_envelope_in_every_format() {
+    let registry = registry();
+    for format in Format::ALL {
+        let bytes = Envelope::seal(&map_v1(), format, Codec::None)
+            .unwrap()
+            .to_bytes();
+        let map: Map = deserialize_versioned(&bytes, &registry).unwrap();
+
+        assert_eq!(
+            map,
+            Map {
+                id: Id([1, 2, 3, 4]),
+                title: "Crypt".into(),
+                subtitle: "Lower Level".into(),
+            },
+            "{format:?} did not migrate"
+        );
+    }
+}
+
+/// A record of a stream can be read as a value and upgraded, keeping the binary IDs of
+/// `MessagePack`.
+#[test]
+fn migrates_stream_record() {
+    let mut writer = StreamWriter::new(Vec::new(), Format::MessagePack).unwrap();
+    writer.write(&map_v1()).unwrap();
+    let bytes = writer.finish().unwrap();
+
+    let mut reader = StreamReader::new(bytes.as_slice()).unwrap();
+    let value = reader.read_value().unwrap().unwrap();
+    assert_eq!(value["id"], json!([1, 2, 3, 4]));
+
+    let map: Map = registry()
+        .upgrade(MapV1::VERSION, value, reader.format())
+        .unwrap();
+    assert_eq!(map.id, Id([1, 2, 3, 4]));
+    assert_eq!(map.title, "Crypt");
+}
+
+/// A version without a registered migration can't be read.
+#[test]
+fn reports_missing_migration() {
+    let mut registry = MigrationRegistry::default();
+    registry.register::<Map, _>(1, Ok::<Value, String>);
+
+    let bytes = Envelope::seal(&map_v1(), Format::Json, Codec::None)
+        .unwrap()
+        .to_bytes();
+    let result = deserialize_versioned::<Map>(&bytes, &registry);
+
+    assert!(matches!(
+        result,
+        Err(Error::MissingMigration { from: 2, .. })
+    ));
+}
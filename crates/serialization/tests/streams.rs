@@ -0,0 +1,86 @@
+//! Reads streams that were cut short or corrupted.
+#![allow(clippy::missing_panics_doc)]
+
+use dungeonrs_serialization::{
+    Error, Format, MAX_RECORD_SIZE, STREAM_MAGIC, StreamReader, StreamWriter,
+};
+
+/// The header of a stream of JSON records.
+fn header() -> Vec<u8> {
+    let mut bytes = STREAM_MAGIC.to_vec();
+    bytes.push(Format::Json.id());
+    bytes
+}
+
+/// A stream holding `records`, serialized as JSON.
+fn stream(records: &[&str]) -> Vec<u8> {
+    let mut writer = StreamWriter::new(Vec::new(), Format::Json).unwrap();
+    writer.write_all(records).unwrap();
+    writer.finish().unwrap()
+}
+
+/// A stream ending right after a record ends cleanly.
+#[test]
+fn ends_at_record_boundary() {
+    let bytes = stream(&["first", "second"]);
+    let mut reader = StreamReader::new(bytes.as_slice()).unwrap();
+
+    assert_eq!(reader.read::<String>().unwrap().as_deref(), Some("first"));
+    assert_eq!(reader.read::<String>().unwrap().as_deref(), Some("second"));
+    assert_eq!(reader.read::<String>().unwrap(), None);
+}
+
+/// A stream ending within the length of a record is truncated, not exhausted.
+#[test]
+fn truncated_length() {
+    for cut in 1..4 {
+        let mut bytes = stream(&["first", "second"]);
+        let second = bytes.len() - "\"second\"".len() - 4;
+        bytes.truncate(second + cut);
+        let mut reader = StreamReader::new(bytes.as_slice()).unwrap();
+
+        assert_eq!(reader.read::<String>().unwrap().as_deref(), Some("first"));
+        assert!(
+            matches!(reader.read::<String>(), Err(Error::Truncated)),
+            "{cut} bytes of the length"
+        );
+    }
+}
+
+/// A stream ending within a record is truncated.
+#[test]
+fn truncated_record() {
+    let mut bytes = stream(&["first"]);
+    bytes.pop();
+    let mut reader = StreamReader::new(bytes.as_slice()).unwrap();
+
+    assert!(matches!(reader.read::<String>(), Err(Error::Truncated)));
+}
+
+/// A record claiming to be larger than the largest record is rejected before it's read.
+#[test]
+fn record_too_large() {
+    let mut bytes = header();
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    bytes.extend_from_slice(b"\"tiny\"");
+    let mut reader = StreamReader::new(bytes.as_slice()).unwrap();
+
+    assert!(matches!(
+        reader.read::<String>(),
+        Err(Error::RecordTooLarge { size, max })
+            if size == u64::from(u32::MAX) && max == MAX_RECORD_SIZE
+    ));
+}
+
+/// A record claiming more bytes than the stream holds is truncated, without allocating what it
+/// claims.
+#[test]
+fn record_longer_than_stream() {
+    let mut bytes = header();
+    let claimed = u32::try_from(MAX_RECORD_SIZE).unwrap();
+    bytes.extend_from_slice(&claimed.to_le_bytes());
+    bytes.extend_from_slice(b"\"tiny\"");
+    let mut reader = StreamReader::new(bytes.as_slice()).unwrap();
+
+    assert!(matches!(reader.read::<String>(), Err(Error::Truncated)));
+}
@@ -0,0 +1,264 @@
+//! Helpers for running work on the [`AsyncComputeTaskPool`] and feeding the results back into
+//! the ECS.
+//!
+//! Work is started by queueing an [`AsyncCommand`], which runs a future on the task pool. The
+//! future receives an [`AsyncContext`] it can use to send commands back to the world while it
+//! runs (for example to report progress) and to check whether it was cancelled.
+
+use bevy::ecs::world::CommandQueue;
+use bevy::prelude::{Command, Commands, Component, Entity, Message, Query, World};
+use bevy::tasks::futures::check_ready;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use crossbeam_channel::{Receiver, Sender, unbounded};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use thiserror::Error;
+
+/// Returned by the cooperative cancellation checks once a task was cancelled.
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+#[error("the task was cancelled")]
+pub struct Cancelled;
+
+/// Shared flag used to request cancellation of an [`AsyncCommand`].
+///
+/// Cancelling drops the task the next time the tasks are polled, which stops the future at its
+/// next `.await`. Long-running synchronous work between awaits should periodically call
+/// [`CancellationToken::check`] so it can bail out early as well.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Requests cancellation of the task(s) holding this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether cancellation was requested.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Cooperative cancellation check, intended to be used with `?` inside loops.
+    ///
+    /// # Errors
+    /// Returns [`Cancelled`] if cancellation was requested.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Handed to the future of an [`AsyncCommand`] to communicate with the world while it runs.
+#[derive(Clone)]
+pub struct AsyncContext {
+    /// Sends command queues back to the world, they're applied when the tasks are polled.
+    sender: Sender<CommandQueue>,
+    /// The token used to cancel this task.
+    token: CancellationToken,
+}
+
+impl AsyncContext {
+    /// Sends `queue` to be applied to the world the next time the tasks are polled.
+    pub fn send(&self, mut queue: CommandQueue) {
+        // When the receiver is gone the task was cancelled (or the app is shutting down), so
+        // there's nobody left to apply the commands to.
+        let _ = self.sender.send(std::mem::take(&mut queue));
+    }
+
+    /// Queues a single `command` to be applied to the world.
+    pub fn queue(&self, command: impl Command) {
+        let mut queue = CommandQueue::default();
+        queue.push(command);
+
+        self.send(queue);
+    }
+
+    /// The token used to cancel this task.
+    #[must_use]
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Returns whether cancellation of this task was requested.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Cooperative cancellation check, see [`CancellationToken::check`].
+    ///
+    /// # Errors
+    /// Returns [`Cancelled`] if cancellation was requested.
+    pub fn check_cancelled(&self) -> Result<(), Cancelled> {
+        self.token.check()
+    }
+}
+
+/// Writes `message` to the world from within an async task.
+///
+/// Typically used to notify the UI about the progress of a long-running task. Tasks reporting
+/// progress at a high rate should use a [`ProgressReporter`](crate::ProgressReporter) instead,
+/// which rate-limits and coalesces the updates.
+pub fn report_progress<M: Message>(context: &AsyncContext, message: M) {
+    context.queue(move |world: &mut World| {
+        world.write_message(message);
+    });
+}
+
+/// The boxed future run by an [`AsyncCommand`].
+type BoxedFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// A [`Command`] that spawns a future on the [`AsyncComputeTaskPool`].
+///
+/// ```ignore
+/// let command = AsyncCommand::new(|context| async move {
+///     for file in files {
+///         context.check_cancelled()?;
+///         // ...
+///         report_progress(&context, IndexProgress { file });
+///     }
+/// });
+/// let token = command.token();
+/// commands.queue(command);
+/// ```
+pub struct AsyncCommand {
+    /// The future to run.
+    future: BoxedFuture,
+    /// The receiving end of the context's channel.
+    receiver: Receiver<CommandQueue>,
+    /// The token used to cancel this task.
+    token: CancellationToken,
+}
+
+impl AsyncCommand {
+    /// Creates a command that runs the future returned by `task` when applied.
+    pub fn new<F, Fut>(task: F) -> Self
+    where
+        F: FnOnce(AsyncContext) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self::with_token(CancellationToken::default(), task)
+    }
+
+    /// Creates a command cancelled by the existing `token`.
+    ///
+    /// Sharing a token between commands allows cancelling a group of tasks at once.
+    pub fn with_token<F, Fut>(token: CancellationToken, task: F) -> Self
+    where
+        F: FnOnce(AsyncContext) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (sender, receiver) = unbounded();
+        let context = AsyncContext {
+            sender,
+            token: token.clone(),
+        };
+
+        Self {
+            future: Box::pin(task(context)),
+            receiver,
+            token,
+        }
+    }
+
+    /// Returns the token that cancels this command.
+    #[must_use]
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
+
+impl Command for AsyncCommand {
+    fn apply(self, world: &mut World) {
+        let task = AsyncComputeTaskPool::get().spawn(self.future);
+
+        world.spawn(AsyncTask {
+            task,
+            receiver: self.receiver,
+            token: self.token,
+        });
+    }
+}
+
+/// Extends [`Commands`] with helpers to spawn and cancel [`AsyncCommand`]s.
+pub trait AsyncCommandsExt {
+    /// Queues an [`AsyncCommand`] running `task` and returns the token to cancel it.
+    fn spawn_async<F, Fut>(&mut self, task: F) -> CancellationToken
+    where
+        F: FnOnce(AsyncContext) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static;
+
+    /// Cancels the task(s) associated with `token`.
+    fn cancel(&mut self, token: &CancellationToken);
+}
+
+impl AsyncCommandsExt for Commands<'_, '_> {
+    fn spawn_async<F, Fut>(&mut self, task: F) -> CancellationToken
+    where
+        F: FnOnce(AsyncContext) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let command = AsyncCommand::new(task);
+        let token = command.token();
+        self.queue(command);
+
+        token
+    }
+
+    fn cancel(&mut self, token: &CancellationToken) {
+        token.cancel();
+    }
+}
+
+/// Tracks a running [`AsyncCommand`], present on its own entity until the task finishes.
+///
+/// Query `With<AsyncTask>` to find out how many tasks are running.
+#[derive(Component)]
+pub struct AsyncTask {
+    /// The running task, dropping it cancels the future.
+    task: Task<()>,
+    /// Receives the command queues sent by the task.
+    receiver: Receiver<CommandQueue>,
+    /// The token used to cancel this task.
+    token: CancellationToken,
+}
+
+/// Applies the commands sent by running tasks and cleans up finished or cancelled tasks.
+pub(crate) fn poll_async_tasks(mut commands: Commands, mut tasks: Query<(Entity, &mut AsyncTask)>) {
+    for (entity, mut task) in &mut tasks {
+        if task.token.is_cancelled() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let finished = check_ready(&mut task.task).is_some();
+        for mut queue in task.receiver.try_iter() {
+            commands.append(&mut queue);
+        }
+
+        if finished {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+impl AsyncContext {
+    /// Creates a context outside of an [`AsyncCommand`], along with the receiving end of its
+    /// channel.
+    pub(crate) fn detached() -> (Self, Receiver<CommandQueue>) {
+        let (sender, receiver) = unbounded();
+        let context = Self {
+            sender,
+            token: CancellationToken::default(),
+        };
+
+        (context, receiver)
+    }
+}
@@ -0,0 +1,165 @@
+//! Run conditions limiting how often systems run, measured in real (wall-clock) time.
+//!
+//! Real time keeps advancing while the virtual clock is paused, which is what reactions to user
+//! input or the file system (autosave, configuration reloads, ...) expect.
+
+use bevy::ecs::schedule::SystemCondition;
+use bevy::ecs::system::ReadOnlySystem;
+use bevy::prelude::{In, IntoSystem, Message, Res, Time, on_message};
+use bevy::time::Real;
+use std::time::Duration;
+
+/// Run condition that's true at most once per `duration`.
+///
+/// The first evaluation is always true, after which it's false until `duration` has passed.
+///
+/// ```ignore
+/// app.add_systems(Update, refresh_thumbnails.run_if(throttled(Duration::from_millis(250))));
+/// ```
+pub fn throttled(duration: Duration) -> impl FnMut(Res<Time<Real>>) -> bool + Clone {
+    let mut last_run = None;
+    move |time: Res<Time<Real>>| {
+        let now = time.elapsed();
+        let due = last_run.is_none_or(|last_run| now.saturating_sub(last_run) >= duration);
+        if due {
+            last_run = Some(now);
+        }
+
+        due
+    }
+}
+
+/// Run condition that's true once `condition` has stopped being true for `duration`.
+///
+/// Every time `condition` is true the countdown restarts, so a burst of changes results in a
+/// single run after the burst has settled.
+///
+/// ```ignore
+/// app.add_systems(
+///     Update,
+///     autosave.run_if(debounced(Duration::from_secs(5), resource_changed::<Project>)),
+/// );
+/// ```
+pub fn debounced<M, C: SystemCondition<M>>(
+    duration: Duration,
+    condition: C,
+) -> impl ReadOnlySystem<In = (), Out = bool> {
+    let mut triggered_at = None;
+    let debounce = move |In(triggered): In<bool>, time: Res<Time<Real>>| {
+        let now = time.elapsed();
+        if triggered {
+            triggered_at = Some(now);
+            return false;
+        }
+
+        let settled = triggered_at.is_some_and(|at| now.saturating_sub(at) >= duration);
+        if settled {
+            triggered_at = None;
+        }
+
+        settled
+    };
+
+    IntoSystem::into_system(condition.pipe(debounce))
+}
+
+/// Run condition that's true once no message of type `M` has been written for `duration`.
+///
+/// This is [`debounced`] applied to [`on_message`]. The condition reads the messages through its
+/// own cursor, but since messages expire after two updates they're usually gone by the time the
+/// condition becomes true, so the system shouldn't rely on reading them.
+pub fn on_event_debounced<M: Message>(
+    duration: Duration,
+) -> impl ReadOnlySystem<In = (), Out = bool> {
+    debounced(duration, on_message::<M>)
+}
+
+#[cfg(test)]
+mod tests {
+    //! Drives the run conditions with a manually advanced clock.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+    use bevy::ecs::message::MessageRegistry;
+    use bevy::ecs::system::SystemId;
+    use bevy::prelude::{Resource, World, resource_changed};
+
+    /// A message triggering the debounced condition.
+    #[derive(Message)]
+    struct Changed;
+
+    /// A resource triggering the debounced condition when it changes.
+    #[derive(Resource, Default)]
+    struct Counter(u32);
+
+    /// Creates a world with a real-time clock at zero.
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Time<Real>>();
+
+        world
+    }
+
+    /// Advances the real-time clock by `millis` and evaluates `condition`.
+    fn evaluate(world: &mut World, condition: SystemId<(), bool>, millis: u64) -> bool {
+        world
+            .resource_mut::<Time<Real>>()
+            .advance_by(Duration::from_millis(millis));
+        world.run_system(condition).expect("the condition runs")
+    }
+
+    /// The first evaluation is true, after which it's only true once the duration passed.
+    #[test]
+    fn throttles_to_the_duration() {
+        let mut world = world();
+        let condition = world.register_system(throttled(Duration::from_millis(100)));
+
+        assert!(evaluate(&mut world, condition, 0));
+        assert!(!evaluate(&mut world, condition, 50));
+        assert!(!evaluate(&mut world, condition, 49));
+        assert!(evaluate(&mut world, condition, 1));
+        assert!(!evaluate(&mut world, condition, 99));
+        assert!(evaluate(&mut world, condition, 250));
+    }
+
+    /// The condition is true once, after the wrapped condition stayed false for the duration.
+    #[test]
+    fn debounces_bursts() {
+        let mut world = world();
+        world.init_resource::<Counter>();
+        let condition = world.register_system(debounced(
+            Duration::from_millis(100),
+            resource_changed::<Counter>,
+        ));
+
+        // The resource counts as changed when it's first seen.
+        assert!(!evaluate(&mut world, condition, 0));
+        for _ in 0..3 {
+            world.resource_mut::<Counter>().0 += 1;
+            assert!(!evaluate(&mut world, condition, 60));
+        }
+        assert!(!evaluate(&mut world, condition, 60));
+        assert!(evaluate(&mut world, condition, 40));
+        assert!(!evaluate(&mut world, condition, 500));
+    }
+
+    /// Without messages the condition never becomes true, and each message restarts the countdown.
+    #[test]
+    fn debounces_messages() {
+        let mut world = world();
+        MessageRegistry::register_message::<Changed>(&mut world);
+        let condition =
+            world.register_system(on_event_debounced::<Changed>(Duration::from_millis(100)));
+
+        assert!(!evaluate(&mut world, condition, 0));
+        assert!(!evaluate(&mut world, condition, 500));
+
+        world.write_message(Changed);
+        assert!(!evaluate(&mut world, condition, 0));
+        world.write_message(Changed);
+        assert!(!evaluate(&mut world, condition, 90));
+        assert!(!evaluate(&mut world, condition, 90));
+        assert!(evaluate(&mut world, condition, 10));
+        assert!(!evaluate(&mut world, condition, 500));
+    }
+}
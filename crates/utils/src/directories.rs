@@ -0,0 +1,163 @@
+//! Platform-specific directories where the editor stores its files.
+//!
+//! Files are split in tiers with different lifetimes, following the conventions of each
+//! platform (XDG on Linux, `~/Library` on macOS and `%APPDATA%` on Windows):
+//!
+//! - [`Directory::Config`]: user preferences, small and worth backing up.
+//! - [`Directory::Data`]: user content such as asset libraries and prefabs.
+//! - [`Directory::Cache`]: thumbnails, search indexes and other data that can be regenerated.
+//! - [`Directory::Logs`]: log files.
+
+use ::directories::ProjectDirs;
+use std::fs::{create_dir_all, read_dir, remove_dir_all, remove_file};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime};
+
+/// The name of the application, used as the directory name on every platform.
+const APPLICATION: &str = "DungeonRS";
+
+/// The platform's project directories, `None` when no home directory could be determined.
+static PROJECT_DIRS: LazyLock<Option<ProjectDirs>> =
+    LazyLock::new(|| ProjectDirs::from("be", "dealloc", APPLICATION));
+
+/// The directories in which the editor stores its files.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Directory {
+    /// User preferences.
+    Config,
+    /// User content, such as asset libraries and prefabs.
+    Data,
+    /// Data that can be regenerated, such as thumbnails and search indexes.
+    Cache,
+    /// Log files.
+    Logs,
+}
+
+impl Directory {
+    /// Returns the path of this directory, without creating it.
+    ///
+    /// When the platform directories can't be determined (for example when there's no home
+    /// directory), a directory in the system's temporary directory is used instead.
+    #[must_use]
+    pub fn path(self) -> PathBuf {
+        let Some(dirs) = PROJECT_DIRS.as_ref() else {
+            return std::env::temp_dir().join(APPLICATION).join(self.name());
+        };
+
+        match self {
+            Directory::Config => dirs.config_dir().to_path_buf(),
+            Directory::Data => dirs.data_dir().to_path_buf(),
+            Directory::Cache => dirs.cache_dir().to_path_buf(),
+            Directory::Logs => log_dir(dirs),
+        }
+    }
+
+    /// Returns the path of `relative` within this directory, without creating it.
+    #[must_use]
+    pub fn join(self, relative: impl AsRef<Path>) -> PathBuf {
+        self.path().join(relative)
+    }
+
+    /// Returns the path of this directory, creating it if it doesn't exist yet.
+    ///
+    /// # Errors
+    /// Returns an error if the directory doesn't exist and can't be created.
+    pub fn ensure(self) -> io::Result<PathBuf> {
+        let path = self.path();
+        create_dir_all(&path)?;
+
+        Ok(path)
+    }
+
+    /// Returns the path of the `relative` subdirectory, creating it if it doesn't exist yet.
+    ///
+    /// # Errors
+    /// Returns an error if the directory doesn't exist and can't be created.
+    pub fn ensure_subdirectory(self, relative: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let path = self.join(relative);
+        create_dir_all(&path)?;
+
+        Ok(path)
+    }
+
+    /// A short name for this directory, used for the fallback location.
+    fn name(self) -> &'static str {
+        match self {
+            Directory::Config => "config",
+            Directory::Data => "data",
+            Directory::Cache => "cache",
+            Directory::Logs => "logs",
+        }
+    }
+}
+
+/// Returns the platform's conventional location for log files.
+#[cfg(target_os = "macos")]
+fn log_dir(dirs: &ProjectDirs) -> PathBuf {
+    ::directories::BaseDirs::new()
+        .map(|base| base.home_dir().join("Library/Logs").join(APPLICATION))
+        .unwrap_or_else(|| dirs.data_local_dir().join("logs"))
+}
+
+/// Returns the platform's conventional location for log files.
+#[cfg(not(target_os = "macos"))]
+fn log_dir(dirs: &ProjectDirs) -> PathBuf {
+    dirs.state_dir()
+        .unwrap_or_else(|| dirs.data_local_dir())
+        .join("logs")
+}
+
+/// Removes everything in the [`Directory::Cache`].
+///
+/// # Errors
+/// Returns an error if (part of) the cache couldn't be removed.
+pub fn clear_cache() -> io::Result<()> {
+    match remove_dir_all(Directory::Cache.path()) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// Removes files in `directory` of the [`Directory::Cache`] that weren't modified within
+/// `max_age`.
+///
+/// Only prune directories whose files are independent of each other, such as extracted assets:
+/// removing part of a search index leaves the rest of it unreadable.
+///
+/// Returns the number of bytes that were freed.
+///
+/// # Errors
+/// Returns an error if the cache couldn't be traversed or a file couldn't be removed.
+pub fn prune_cache(directory: impl AsRef<Path>, max_age: Duration) -> io::Result<u64> {
+    let cutoff = SystemTime::now()
+        .checked_sub(max_age)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    match prune_directory(&Directory::Cache.join(directory), cutoff) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(0),
+        result => result,
+    }
+}
+
+/// Recursively removes files in `path` last modified before `cutoff`.
+///
+/// # Errors
+/// Returns an error if `path` couldn't be traversed or a file couldn't be removed.
+fn prune_directory(path: &Path, cutoff: SystemTime) -> io::Result<u64> {
+    let mut freed = 0;
+    for entry in read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        if metadata.is_dir() {
+            freed += prune_directory(&entry.path(), cutoff)?;
+        } else if metadata.modified()? < cutoff {
+            remove_file(entry.path())?;
+            freed += metadata.len();
+        }
+    }
+
+    Ok(freed)
+}
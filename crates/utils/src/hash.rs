@@ -0,0 +1,248 @@
+//! Streaming content hashes for files and directories.
+//!
+//! Files are read in fixed-size chunks so hashing large assets or save files never requires
+//! loading them into memory entirely.
+
+use rayon::prelude::*;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use xxhash_rust::xxh3::Xxh3;
+
+/// The size of the chunks in which input is fed to the hasher.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The algorithms available to hash content with.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    /// 128-bit XXH3, fast and suitable for change detection and deduplication.
+    #[default]
+    Xxh3,
+    /// 256-bit BLAKE3, cryptographically secure.
+    Blake3,
+}
+
+/// The hash of some content, computed with one of the [`HashAlgorithm`]s.
+///
+/// Hashes computed with different algorithms never compare equal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ContentHash {
+    /// A hash computed with [`HashAlgorithm::Xxh3`].
+    Xxh3(u128),
+    /// A hash computed with [`HashAlgorithm::Blake3`].
+    Blake3([u8; 32]),
+}
+
+impl ContentHash {
+    /// The algorithm this hash was computed with.
+    #[must_use]
+    pub fn algorithm(&self) -> HashAlgorithm {
+        match self {
+            ContentHash::Xxh3(_) => HashAlgorithm::Xxh3,
+            ContentHash::Blake3(_) => HashAlgorithm::Blake3,
+        }
+    }
+}
+
+impl Display for ContentHash {
+    /// Formats the hash as lowercase hexadecimal.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentHash::Xxh3(hash) => write!(f, "{hash:032x}"),
+            ContentHash::Blake3(hash) => hash.iter().try_for_each(|byte| write!(f, "{byte:02x}")),
+        }
+    }
+}
+
+/// Incrementally computes a [`ContentHash`].
+enum Hasher {
+    /// Computes a [`ContentHash::Xxh3`].
+    Xxh3(Box<Xxh3>),
+    /// Computes a [`ContentHash::Blake3`].
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    /// Creates a new hasher for `algorithm`.
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Xxh3 => Hasher::Xxh3(Box::default()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::default()),
+        }
+    }
+
+    /// Feeds `bytes` into the hasher.
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Xxh3(hasher) => hasher.update(bytes),
+            Hasher::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+        }
+    }
+
+    /// Consumes the hasher and returns the hash of everything fed into it.
+    fn finish(self) -> ContentHash {
+        match self {
+            Hasher::Xxh3(hasher) => ContentHash::Xxh3(hasher.digest128()),
+            Hasher::Blake3(hasher) => ContentHash::Blake3(*hasher.finalize().as_bytes()),
+        }
+    }
+}
+
+/// Hashes everything `reader` yields, reading it in chunks.
+///
+/// # Errors
+/// Returns an error if reading from `reader` fails.
+pub fn hash_reader(mut reader: impl Read, algorithm: HashAlgorithm) -> io::Result<ContentHash> {
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0; CHUNK_SIZE];
+
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return Ok(hasher.finish()),
+            Ok(read) => hasher.update(&buffer[..read]),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Hashes the contents of the file at `path` without loading it into memory.
+///
+/// # Errors
+/// Returns an error if the file can't be opened or read.
+pub fn hash_file(path: impl AsRef<Path>, algorithm: HashAlgorithm) -> io::Result<ContentHash> {
+    hash_reader(File::open(path)?, algorithm)
+}
+
+/// Hashes every file in `path` and its subdirectories in parallel.
+///
+/// The returned paths are relative to `path` and sorted, so the result is deterministic
+/// regardless of the order in which files were hashed.
+///
+/// # Errors
+/// Returns an error if the directory can't be traversed or any of the files can't be read.
+pub fn hash_directory(
+    path: impl AsRef<Path>,
+    algorithm: HashAlgorithm,
+) -> io::Result<Vec<(PathBuf, ContentHash)>> {
+    let root = path.as_ref();
+    let files = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Ok(entry) if !entry.file_type().is_file() => None,
+            entry => Some(entry.map(walkdir::DirEntry::into_path)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    files
+        .into_par_iter()
+        .map(|file| {
+            let hash = hash_file(&file, algorithm)?;
+            let relative = file
+                .strip_prefix(root)
+                .map_or(file.clone(), Path::to_path_buf);
+
+            Ok((relative, hash))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    //! Hashes known inputs with both algorithms.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+
+    /// Hashes `bytes` with `algorithm` and formats the hash.
+    fn hash(bytes: &[u8], algorithm: HashAlgorithm) -> String {
+        hash_reader(bytes, algorithm)
+            .expect("reading from memory succeeds")
+            .to_string()
+    }
+
+    /// The hashes match the reference vectors of both algorithms.
+    #[test]
+    fn hashes_known_values() {
+        assert_eq!(
+            hash(b"", HashAlgorithm::Xxh3),
+            "99aa06d3014798d86001c324468d497f"
+        );
+        assert_eq!(
+            hash(b"", HashAlgorithm::Blake3),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(
+            hash(b"abc", HashAlgorithm::Blake3),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+
+    /// Input spanning several chunks hashes the same as hashing it at once.
+    #[test]
+    fn hashes_across_chunks() {
+        let bytes: Vec<u8> = (0..=u8::MAX).cycle().take(CHUNK_SIZE * 3 + 17).collect();
+
+        assert_eq!(
+            hash_reader(bytes.as_slice(), HashAlgorithm::Xxh3).ok(),
+            Some(ContentHash::Xxh3(xxhash_rust::xxh3::xxh3_128(&bytes)))
+        );
+        assert_eq!(
+            hash_reader(bytes.as_slice(), HashAlgorithm::Blake3).ok(),
+            Some(ContentHash::Blake3(*blake3::hash(&bytes).as_bytes()))
+        );
+    }
+
+    /// Hashes of different algorithms never compare equal, and tell which algorithm they used.
+    #[test]
+    fn distinguishes_algorithms() {
+        let xxh3 = hash_reader(&b"abc"[..], HashAlgorithm::Xxh3).ok();
+        let blake3 = hash_reader(&b"abc"[..], HashAlgorithm::Blake3).ok();
+
+        assert_ne!(xxh3, blake3);
+        assert_eq!(xxh3.map(|hash| hash.algorithm()), Some(HashAlgorithm::Xxh3));
+        assert_eq!(
+            blake3.map(|hash| hash.algorithm()),
+            Some(HashAlgorithm::Blake3)
+        );
+    }
+
+    /// Files hash the same as their contents, and directories list every file they contain,
+    /// relative to the directory and sorted.
+    #[test]
+    fn hashes_files_and_directories() {
+        let workspace = crate::TempWorkspace::open().expect("the workspace opens");
+        let root = workspace.path().join("pack");
+        for (file, contents) in [("b.txt", "b"), ("a/c.txt", "c"), ("a/b.txt", "")] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().expect("files lie in the pack"))
+                .expect("the directory is created");
+            std::fs::write(path, contents).expect("the file is written");
+        }
+
+        let hash = |bytes: &[u8]| hash_reader(bytes, HashAlgorithm::Blake3).ok();
+        assert_eq!(
+            hash_file(root.join("a/c.txt"), HashAlgorithm::Blake3).ok(),
+            hash(b"c")
+        );
+        let hashes = hash_directory(&root, HashAlgorithm::Blake3)
+            .expect("the directory is readable")
+            .into_iter()
+            .map(|(path, hash)| (path, Some(hash)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hashes,
+            [
+                (PathBuf::from("a/b.txt"), hash(b"")),
+                (PathBuf::from("a/c.txt"), hash(b"c")),
+                (PathBuf::from("b.txt"), hash(b"b")),
+            ]
+        );
+        assert!(hash_file(root.join("missing.txt"), HashAlgorithm::Xxh3).is_err());
+    }
+}
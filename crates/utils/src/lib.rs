@@ -0,0 +1,39 @@
+#![doc = include_str!("../README.md")]
+
+mod async_ecs;
+mod conditions;
+mod directories;
+mod hash;
+mod network;
+mod pathbuf;
+mod plugin;
+mod progress;
+mod resources;
+mod retry;
+mod span;
+mod version;
+mod workspace;
+
+pub use async_ecs::{
+    AsyncCommand, AsyncCommandsExt, AsyncContext, AsyncTask, CancellationToken, Cancelled,
+    report_progress,
+};
+pub use conditions::{debounced, on_event_debounced, throttled};
+pub use directories::{Directory, clear_cache, prune_cache};
+pub use hash::{ContentHash, HashAlgorithm, hash_directory, hash_file, hash_reader};
+pub use network::NetworkSettings;
+pub use pathbuf::{
+    PathError, ensure_within, sanitize_file_name, validate_file_name, validate_output_path,
+    with_extension,
+};
+pub use plugin::UtilsPlugin;
+pub use progress::ProgressReporter;
+pub use resources::{
+    RESOURCES_ENV, RESOURCES_FLAG, resource_path, resources_flag, set_resource_path,
+};
+pub use retry::{Retry, is_transient};
+pub use version::version;
+pub use workspace::{TempKind, TempWorkspace};
+
+#[doc(hidden)]
+pub use tracing;
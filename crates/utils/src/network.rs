@@ -0,0 +1,30 @@
+//! Contains the [`NetworkSettings`] honoured by every feature that reaches the network.
+
+use bevy::prelude::Resource;
+use std::time::Duration;
+
+/// Configures how the editor reaches the network, for example to check for updates.
+///
+/// Local servers, such as the live preview, aren't affected.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct NetworkSettings {
+    /// Prevents every request to the network when set.
+    pub offline: bool,
+    /// The proxy requests go through, such as `http://proxy.example:8080`.
+    ///
+    /// When `None`, the proxy set in the `HTTPS_PROXY`, `HTTP_PROXY` or `ALL_PROXY` environment
+    /// variables is used.
+    pub proxy: Option<String>,
+    /// How long a request may take in total.
+    pub timeout: Duration,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            offline: false,
+            proxy: None,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
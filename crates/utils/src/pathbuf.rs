@@ -0,0 +1,292 @@
+//! Helpers for validating and sanitising user-supplied paths.
+//!
+//! Paths entered by the user (export targets, "save as" destinations, pack imports, ...) are
+//! validated before use so problems are reported up front rather than as an obscure IO error
+//! halfway through an operation.
+
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+/// Characters that can't be used in file names on the current platform.
+#[cfg(windows)]
+const ILLEGAL_CHARACTERS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Characters that can't be used in file names on the current platform.
+#[cfg(not(windows))]
+const ILLEGAL_CHARACTERS: &[char] = &['/'];
+
+/// File names (ignoring extension and case) reserved by Windows.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Reasons a user-supplied path can be rejected.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PathError {
+    /// The path doesn't contain a file name.
+    #[error("no file name was given")]
+    Empty,
+    /// The file name contains a character that isn't allowed on this platform.
+    #[error("'{0}' is not allowed in file names")]
+    IllegalCharacter(char),
+    /// The file name is reserved by the operating system.
+    #[error("'{0}' is a reserved file name")]
+    ReservedName(String),
+    /// The file name ends with a character that's silently dropped by the operating system.
+    #[error("file names can't end with '{0}'")]
+    IllegalTrailingCharacter(char),
+    /// The path resolves to a location outside the directory it must stay in.
+    #[error("{} is outside of {}", path.display(), root.display())]
+    OutsideRoot {
+        /// The offending path.
+        path: PathBuf,
+        /// The directory the path must stay in.
+        root: PathBuf,
+    },
+}
+
+/// Validates that `name` can be used as a file name on the current platform.
+///
+/// # Errors
+/// Returns the first problem found in `name`.
+pub fn validate_file_name(name: &str) -> Result<(), PathError> {
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(PathError::Empty);
+    }
+
+    if let Some(character) = name.chars().find(|character| is_illegal(*character)) {
+        return Err(PathError::IllegalCharacter(character));
+    }
+
+    if cfg!(windows) {
+        if let Some(character) = name.chars().last().filter(|c| matches!(c, '.' | ' ')) {
+            return Err(PathError::IllegalTrailingCharacter(character));
+        }
+
+        if is_reserved(name) {
+            return Err(PathError::ReservedName(name.to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Turns `name` into a valid file name by replacing illegal characters with `_`.
+///
+/// Unlike [`validate_file_name`], this never fails, making it suitable for deriving file names
+/// from user content such as project or level names.
+#[must_use]
+pub fn sanitize_file_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|character| {
+            if is_illegal(character) {
+                '_'
+            } else {
+                character
+            }
+        })
+        .collect();
+
+    // Windows silently strips trailing dots and spaces, causing surprising names.
+    sanitized.truncate(sanitized.trim_end_matches(['.', ' ']).len());
+    if sanitized.is_empty() {
+        sanitized.push('_');
+    }
+
+    if is_reserved(&sanitized) {
+        sanitized.insert(0, '_');
+    }
+
+    sanitized
+}
+
+/// Appends `extension` to `path` unless it already has it (compared case-insensitively).
+///
+/// Other extensions are kept, so `map.v2` becomes `map.v2.png` rather than `map.png`.
+#[must_use]
+pub fn with_extension(path: impl Into<PathBuf>, extension: &str) -> PathBuf {
+    let mut path = path.into();
+    let has_extension = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|current| current.eq_ignore_ascii_case(extension));
+
+    if !has_extension {
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".");
+        file_name.push(extension);
+        path.set_file_name(file_name);
+    }
+
+    path
+}
+
+/// Resolves `path` relative to `root` and ensures the result stays within `root`.
+///
+/// The path is normalised lexically (without touching the filesystem), so it works for paths
+/// that don't exist yet. Absolute paths are accepted as long as they point inside `root`.
+///
+/// # Errors
+/// Returns [`PathError::OutsideRoot`] if `path` escapes `root`, for example through `..`.
+pub fn ensure_within(root: impl AsRef<Path>, path: impl AsRef<Path>) -> Result<PathBuf, PathError> {
+    let root = normalize(root.as_ref());
+    let resolved = normalize(&root.join(path.as_ref()));
+
+    if resolved.starts_with(&root) {
+        Ok(resolved)
+    } else {
+        Err(PathError::OutsideRoot {
+            path: path.as_ref().to_path_buf(),
+            root,
+        })
+    }
+}
+
+/// Validates a user-supplied output `path`, appending `extension` when missing.
+///
+/// # Errors
+/// Returns an error if the file name of `path` isn't valid on this platform.
+pub fn validate_output_path(path: impl AsRef<Path>, extension: &str) -> Result<PathBuf, PathError> {
+    let path = path.as_ref();
+    let file_name = path.file_name().ok_or(PathError::Empty)?.to_string_lossy();
+    validate_file_name(&file_name)?;
+
+    Ok(with_extension(path, extension))
+}
+
+/// Lexically resolves `.` and `..` components in `path`.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Returns whether `character` can't be used in a file name.
+fn is_illegal(character: char) -> bool {
+    character.is_control() || ILLEGAL_CHARACTERS.contains(&character)
+}
+
+/// Returns whether `name` (ignoring its extension) is reserved on Windows.
+fn is_reserved(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+#[cfg(test)]
+mod tests {
+    //! Validates and sanitises file names, and keeps paths within their root.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+
+    /// Empty names, and names only pointing at a directory, are rejected.
+    #[test]
+    fn rejects_empty_names() {
+        for name in ["", ".", ".."] {
+            assert_eq!(validate_file_name(name), Err(PathError::Empty));
+        }
+        assert_eq!(validate_output_path("..", "png"), Err(PathError::Empty));
+        assert_eq!(validate_output_path("/", "png"), Err(PathError::Empty));
+    }
+
+    /// Separators and control characters are rejected in file names.
+    #[test]
+    fn rejects_illegal_characters() {
+        assert_eq!(
+            validate_file_name("maps/cave"),
+            Err(PathError::IllegalCharacter('/'))
+        );
+        assert_eq!(
+            validate_file_name("cave\n"),
+            Err(PathError::IllegalCharacter('\n'))
+        );
+        assert_eq!(validate_file_name("Cave of wonders.png"), Ok(()));
+    }
+
+    /// Windows rejects its reserved names whatever their case or extension, and names ending
+    /// with a dot or space.
+    #[test]
+    #[cfg(windows)]
+    fn rejects_reserved_names() {
+        for name in ["CON", "con.png", "Lpt1.tar.gz", "nul "] {
+            assert_eq!(
+                validate_file_name(name),
+                Err(PathError::ReservedName(name.to_owned()))
+            );
+        }
+        assert_eq!(
+            validate_file_name("cave."),
+            Err(PathError::IllegalTrailingCharacter('.'))
+        );
+        assert_eq!(validate_file_name("console.png"), Ok(()));
+    }
+
+    /// Sanitised names are valid on every platform, even when the input is reserved or empty.
+    #[test]
+    fn sanitizes_names() {
+        assert_eq!(sanitize_file_name("maps/cave"), "maps_cave");
+        assert_eq!(sanitize_file_name("CON"), "_CON");
+        assert_eq!(sanitize_file_name("com1.png"), "_com1.png");
+        assert_eq!(sanitize_file_name("console"), "console");
+        assert_eq!(sanitize_file_name("cave. . "), "cave");
+        assert_eq!(sanitize_file_name(""), "_");
+        assert_eq!(sanitize_file_name(".."), "_");
+
+        for name in ["CON", "a/b", "", "..", "trailing. "] {
+            assert_eq!(validate_file_name(&sanitize_file_name(name)), Ok(()));
+        }
+    }
+
+    /// Extensions are appended unless the path already has them, in any case.
+    #[test]
+    fn appends_extensions() {
+        assert_eq!(with_extension("map", "png"), PathBuf::from("map.png"));
+        assert_eq!(with_extension("map.PNG", "png"), PathBuf::from("map.PNG"));
+        assert_eq!(with_extension("map.v2", "png"), PathBuf::from("map.v2.png"));
+        assert_eq!(
+            validate_output_path("exports/map", "png"),
+            Ok(PathBuf::from("exports/map.png"))
+        );
+    }
+
+    /// Paths are resolved within the root, escaping it through `..` or an absolute path fails.
+    #[test]
+    fn keeps_paths_within_root() {
+        let root = Path::new("/packs/dungeon");
+        assert_eq!(
+            ensure_within(root, "walls/../doors/oak.png"),
+            Ok(PathBuf::from("/packs/dungeon/doors/oak.png"))
+        );
+        assert_eq!(
+            ensure_within(root, "/packs/dungeon/floors"),
+            Ok(PathBuf::from("/packs/dungeon/floors"))
+        );
+
+        for path in ["../other/oak.png", "walls/../../other", "/etc/passwd", ".."] {
+            assert_eq!(
+                ensure_within(root, path),
+                Err(PathError::OutsideRoot {
+                    path: path.into(),
+                    root: root.to_path_buf(),
+                })
+            );
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Contains the [`UtilsPlugin`].
+
+use crate::TempWorkspace;
+use crate::async_ecs::poll_async_tasks;
+use bevy::prelude::{
+    App, Commands, IntoScheduleConfigs, Plugin, PreStartup, PreUpdate, not, resource_exists,
+};
+
+/// Registers the systems backing the ECS-facing utilities in this crate.
+pub struct UtilsPlugin;
+
+impl Plugin for UtilsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreStartup,
+            open_temp_workspace.run_if(not(resource_exists::<TempWorkspace>)),
+        )
+        .add_systems(PreUpdate, poll_async_tasks);
+    }
+}
+
+/// Opens the [`TempWorkspace`] of the session, unless one was inserted before startup.
+///
+/// The editor runs without one when the temporary directory isn't writable, features needing
+/// temporary files then fail when they're used instead of at startup.
+fn open_temp_workspace(mut commands: Commands) {
+    if let Ok(workspace) = TempWorkspace::open() {
+        commands.insert_resource(workspace);
+    }
+}
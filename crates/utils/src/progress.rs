@@ -0,0 +1,179 @@
+//! Contains the [`ProgressReporter`] used to report progress from async tasks without flooding
+//! the world with messages.
+
+use crate::{AsyncContext, report_progress};
+use bevy::prelude::Message;
+use std::time::{Duration, Instant};
+
+/// Rate-limited progress reporting for tasks spawned through an [`AsyncCommand`].
+///
+/// Tasks processing many small items (such as indexing tens of thousands of files) would
+/// otherwise write a message per item. The reporter sends at most one update per interval,
+/// coalescing the updates in between so only the most recent one is delivered. Completion is
+/// reported through [`ProgressReporter::complete`], which is never throttled.
+///
+/// Any update still pending when the reporter is dropped is flushed, so the last known progress
+/// is never lost.
+///
+/// [`AsyncCommand`]: crate::AsyncCommand
+pub struct ProgressReporter<M: Message> {
+    /// The context of the task reporting progress.
+    context: AsyncContext,
+    /// The minimum time between two updates.
+    interval: Duration,
+    /// When the last update was sent.
+    last_sent: Option<Instant>,
+    /// The most recent update that hasn't been sent yet.
+    pending: Option<M>,
+}
+
+impl<M: Message> ProgressReporter<M> {
+    /// The number of updates per second a reporter sends by default.
+    pub const DEFAULT_RATE: u32 = 20;
+
+    /// Creates a reporter sending at most [`ProgressReporter::DEFAULT_RATE`] updates per second.
+    #[must_use]
+    pub fn new(context: &AsyncContext) -> Self {
+        Self::with_rate(context, Self::DEFAULT_RATE)
+    }
+
+    /// Creates a reporter sending at most `per_second` updates per second.
+    #[must_use]
+    pub fn with_rate(context: &AsyncContext, per_second: u32) -> Self {
+        Self {
+            context: context.clone(),
+            interval: Duration::from_secs(1) / per_second.max(1),
+            last_sent: None,
+            pending: None,
+        }
+    }
+
+    /// Reports a progress update.
+    ///
+    /// The update is sent immediately if the interval since the previous update has passed,
+    /// otherwise it replaces any pending update and is sent with the next report or flush.
+    pub fn report(&mut self, message: M) {
+        let now = Instant::now();
+        let due = self
+            .last_sent
+            .is_none_or(|last_sent| now.duration_since(last_sent) >= self.interval);
+
+        if due {
+            self.pending = None;
+            self.last_sent = Some(now);
+            report_progress(&self.context, message);
+        } else {
+            self.pending = Some(message);
+        }
+    }
+
+    /// Sends the pending update (if any), regardless of the rate limit.
+    pub fn flush(&mut self) {
+        if let Some(message) = self.pending.take() {
+            self.last_sent = Some(Instant::now());
+            report_progress(&self.context, message);
+        }
+    }
+
+    /// Reports completion, discarding any pending update.
+    ///
+    /// The completion message can be of a different type than the progress updates, and is
+    /// always delivered.
+    pub fn complete<C: Message>(mut self, message: C) {
+        self.pending = None;
+        report_progress(&self.context, message);
+    }
+}
+
+impl<M: Message> Drop for ProgressReporter<M> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Rate-limits, coalesces and flushes progress updates.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+    use bevy::ecs::world::CommandQueue;
+    use bevy::prelude::{Messages, World};
+    use crossbeam_channel::Receiver;
+
+    /// A progress update.
+    #[derive(Message, Debug, PartialEq, Eq)]
+    struct Progress(usize);
+
+    /// A completion message.
+    #[derive(Message, Debug, PartialEq, Eq)]
+    struct Done;
+
+    /// Applies the queues sent by the reporter and returns the updates and completions written.
+    fn delivered(receiver: &Receiver<CommandQueue>) -> (Vec<usize>, usize) {
+        let mut world = World::new();
+        world.init_resource::<Messages<Progress>>();
+        world.init_resource::<Messages<Done>>();
+        for mut queue in receiver.try_iter() {
+            queue.apply(&mut world);
+        }
+
+        let updates = world
+            .resource_mut::<Messages<Progress>>()
+            .drain()
+            .map(|Progress(value)| value)
+            .collect();
+        let completions = world.resource_mut::<Messages<Done>>().drain().count();
+
+        (updates, completions)
+    }
+
+    /// Updates within the interval only deliver the first and the most recent one.
+    #[test]
+    fn coalesces_updates() {
+        let (context, receiver) = AsyncContext::detached();
+        let mut reporter = ProgressReporter::with_rate(&context, 1);
+        for value in 1..=1000 {
+            reporter.report(Progress(value));
+        }
+        reporter.flush();
+
+        assert_eq!(delivered(&receiver), (vec![1, 1000], 0));
+    }
+
+    /// Updates are sent right away once the interval has passed.
+    #[test]
+    fn sends_updates_once_due() {
+        let (context, receiver) = AsyncContext::detached();
+        let mut reporter = ProgressReporter::with_rate(&context, 1000);
+        reporter.report(Progress(1));
+        std::thread::sleep(Duration::from_millis(5));
+        reporter.report(Progress(2));
+
+        assert_eq!(delivered(&receiver), (vec![1, 2], 0));
+    }
+
+    /// The pending update is delivered when the reporter is dropped.
+    #[test]
+    fn flushes_when_dropped() {
+        let (context, receiver) = AsyncContext::detached();
+        let mut reporter = ProgressReporter::with_rate(&context, 1);
+        reporter.report(Progress(1));
+        reporter.report(Progress(2));
+        drop(reporter);
+
+        assert_eq!(delivered(&receiver), (vec![1, 2], 0));
+    }
+
+    /// Completion is delivered regardless of the rate limit, discarding the pending update.
+    #[test]
+    fn always_delivers_completion() {
+        let (context, receiver) = AsyncContext::detached();
+        let mut reporter = ProgressReporter::with_rate(&context, 1);
+        reporter.report(Progress(1));
+        reporter.report(Progress(2));
+        reporter.complete(Done);
+
+        assert_eq!(delivered(&receiver), (vec![1], 1));
+    }
+}
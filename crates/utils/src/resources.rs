@@ -0,0 +1,70 @@
+//! Locates the directory containing the bundled resources (`locales/`, `assets/`, ...).
+
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// The environment variable that overrides the resource directory.
+pub const RESOURCES_ENV: &str = "DRS_RESOURCES";
+
+/// The command line flag that overrides the resource directory.
+pub const RESOURCES_FLAG: &str = "--resources";
+
+/// The resource directory set through [`set_resource_path`], takes precedence over everything.
+static RESOURCE_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Returns the directory containing the bundled resources.
+///
+/// The directory is resolved in the following order:
+/// 1. the path set through [`set_resource_path`] (typically from the `--resources` flag)
+/// 2. the [`RESOURCES_ENV`] environment variable
+/// 3. the directory containing the executable
+///
+/// The overrides allow portable installs, tests and packagers to relocate the resources.
+#[must_use]
+pub fn resource_path() -> PathBuf {
+    if let Some(path) = RESOURCE_PATH.get() {
+        return path.clone();
+    }
+
+    if let Some(path) = env::var_os(RESOURCES_ENV).filter(|path| !path.is_empty()) {
+        return PathBuf::from(path);
+    }
+
+    env::current_exe()
+        .ok()
+        .and_then(|executable| executable.parent().map(PathBuf::from))
+        .unwrap_or_default()
+}
+
+/// Overrides the directory returned by [`resource_path`] for the rest of the process.
+///
+/// # Errors
+/// The resource path can only be set once, if it was already set `path` is returned.
+pub fn set_resource_path(path: impl Into<PathBuf>) -> Result<(), PathBuf> {
+    RESOURCE_PATH.set(path.into())
+}
+
+/// Extracts the value of the [`RESOURCES_FLAG`] from the command line `args`.
+///
+/// Both `--resources <path>` and `--resources=<path>` are supported.
+#[must_use]
+pub fn resources_flag(args: impl IntoIterator<Item = OsString>) -> Option<PathBuf> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == RESOURCES_FLAG {
+            return args.next().map(PathBuf::from);
+        }
+
+        let value = arg
+            .to_str()
+            .and_then(|arg| arg.strip_prefix(RESOURCES_FLAG))
+            .and_then(|arg| arg.strip_prefix('='));
+        if let Some(value) = value {
+            return Some(PathBuf::from(value));
+        }
+    }
+
+    None
+}
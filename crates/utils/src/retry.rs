@@ -0,0 +1,231 @@
+//! Contains [`Retry`], which retries fallible operations with exponential backoff.
+
+use std::io;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Retries a fallible operation with exponential backoff.
+///
+/// Intended for IO that can fail transiently, such as writes to network drives or downloads,
+/// where giving up on the first error would be needlessly fragile. The delay between attempts
+/// starts at the initial delay and is multiplied after every failed attempt, capped at the
+/// maximum delay. A random jitter is applied to every delay so concurrent retries spread out.
+///
+/// The delay is a blocking sleep, so only use this from async tasks or other threads than the
+/// main thread.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Retry {
+    /// The maximum number of attempts, including the first one.
+    attempts: u32,
+    /// The delay after the first failed attempt.
+    initial_delay: Duration,
+    /// The upper bound of the delay between attempts.
+    max_delay: Duration,
+    /// The factor the delay is multiplied by after every failed attempt.
+    multiplier: f32,
+    /// The fraction by which a delay is randomly shortened or lengthened.
+    jitter: f32,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl Retry {
+    /// Creates a policy making at most `attempts` attempts with the default backoff.
+    #[must_use]
+    pub fn new(attempts: u32) -> Self {
+        Self {
+            attempts: attempts.max(1),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the delay after the first failed attempt and the upper bound of the delay.
+    #[must_use]
+    pub fn with_backoff(mut self, initial_delay: Duration, max_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self.max_delay = max_delay.max(initial_delay);
+        self
+    }
+
+    /// Sets the factor the delay is multiplied by after every failed attempt.
+    #[must_use]
+    pub fn with_multiplier(mut self, multiplier: f32) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Sets the fraction (between `0.0` and `1.0`) by which delays are randomly varied.
+    #[must_use]
+    pub fn with_jitter(mut self, jitter: f32) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Runs `operation` until it succeeds or the attempts run out, retrying on any error.
+    ///
+    /// `operation` receives the (zero-based) number of the attempt.
+    ///
+    /// # Errors
+    /// Returns the error of the last attempt if none of the attempts succeeded.
+    pub fn run<T, E>(&self, operation: impl FnMut(u32) -> Result<T, E>) -> Result<T, E> {
+        self.run_if(operation, |_| true)
+    }
+
+    /// Runs `operation` until it succeeds, the attempts run out or it fails with an error for
+    /// which `should_retry` returns `false`.
+    ///
+    /// `operation` receives the (zero-based) number of the attempt.
+    ///
+    /// # Errors
+    /// Returns the error of the last attempt if none of the attempts succeeded.
+    pub fn run_if<T, E>(
+        &self,
+        mut operation: impl FnMut(u32) -> Result<T, E>,
+        should_retry: impl Fn(&E) -> bool,
+    ) -> Result<T, E> {
+        let mut attempt = 0;
+        loop {
+            match operation(attempt) {
+                Err(error) if attempt + 1 < self.attempts && should_retry(&error) => {
+                    sleep(self.delay(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns the delay to wait after the (zero-based) `attempt` failed, including jitter.
+    ///
+    /// The delay never exceeds the maximum delay, even once jitter is applied or when it grows
+    /// too large to be represented.
+    #[must_use]
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = i32::try_from(attempt).unwrap_or(i32::MAX);
+        let delay = (self.initial_delay.as_secs_f32() * self.multiplier.powi(exponent))
+            .min(self.max_delay.as_secs_f32());
+        let jitter = self.jitter * random_unit(attempt).mul_add(2.0, -1.0);
+
+        Duration::try_from_secs_f32((delay * (1.0 + jitter)).max(0.0))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+/// Returns whether `error` is likely transient, in which case retrying may succeed.
+#[must_use]
+pub fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::ResourceBusy
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NetworkDown
+            | io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::StaleNetworkFileHandle
+    )
+}
+
+/// Returns a pseudo-random number between `0.0` and `1.0`.
+///
+/// Jitter doesn't need good randomness, only enough to keep concurrent retries from lining up.
+#[allow(clippy::cast_precision_loss, reason = "only the leading bits matter")]
+fn random_unit(seed: u32) -> f32 {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let hash = xxh3_64(&[nanos.to_le_bytes(), seed.to_le_bytes()].concat());
+
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    //! Checks the backoff delays and the number of attempts made.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+
+    /// Without jitter, the delay is multiplied after every attempt until it reaches the maximum.
+    #[test]
+    fn grows_exponentially_up_to_the_maximum() {
+        let retry = Retry::new(10)
+            .with_backoff(Duration::from_millis(100), Duration::from_secs(1))
+            .with_jitter(0.0);
+
+        let delays: Vec<_> = (0..6)
+            .map(|attempt| retry.delay(attempt).as_millis())
+            .collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(retry.delay(u32::MAX), Duration::from_secs(1));
+    }
+
+    /// Delays too large to be represented are clamped instead of panicking.
+    #[test]
+    fn clamps_overflowing_delays() {
+        let retry = Retry::new(2)
+            .with_backoff(Duration::from_secs(1), Duration::MAX)
+            .with_multiplier(f32::MAX);
+
+        assert_eq!(retry.delay(4), Duration::MAX);
+    }
+
+    /// Jitter varies the delay by at most the configured fraction, and never past the maximum.
+    #[test]
+    fn bounds_jitter() {
+        let retry = Retry::new(10)
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(300))
+            .with_jitter(0.5);
+
+        for _ in 0..100 {
+            let delay = retry.delay(0);
+            assert!((Duration::from_millis(50)..=Duration::from_millis(150)).contains(&delay));
+            assert!(retry.delay(5) <= Duration::from_millis(300));
+        }
+    }
+
+    /// Operations are attempted until they succeed or the attempts run out, and aren't retried
+    /// on errors that shouldn't be.
+    #[test]
+    fn limits_attempts() {
+        let retry = Retry::new(3).with_backoff(Duration::ZERO, Duration::ZERO);
+
+        let mut attempts = Vec::new();
+        let result: Result<(), u32> = retry.run(|attempt| {
+            attempts.push(attempt);
+            Err(attempt)
+        });
+        assert_eq!(result, Err(2));
+        assert_eq!(attempts, [0, 1, 2]);
+
+        let result = retry.run(|attempt| if attempt == 1 { Ok(attempt) } else { Err(()) });
+        assert_eq!(result, Ok(1));
+
+        let mut calls = 0;
+        let result: Result<(), ()> = retry.run_if(
+            |_| {
+                calls += 1;
+                Err(())
+            },
+            |()| false,
+        );
+        assert_eq!((result, calls), (Err(()), 1));
+        assert_eq!(Retry::new(0).run(Err::<(), _>), Err(0));
+    }
+}
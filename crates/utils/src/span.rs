@@ -0,0 +1,97 @@
+//! Wrappers around the `tracing` span macros, see [`info_span!`](crate::info_span) for the syntax.
+
+/// Builds a span at the given level, translating the extensions of the wrapper macros.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __span {
+    (@fields [$($parent:tt)*] $level:expr, $name:expr; [$($out:tt)*]) => {
+        $crate::tracing::span!($($parent)* $level, $name $($out)*)
+    };
+    (@fields $parent:tt $level:expr, $name:expr; [$($out:tt)*] length = $value:expr $(, $($rest:tt)*)?) => {
+        $crate::__span!(@fields $parent $level, $name; [$($out)*, length = ($value).len()] $($($rest)*)?)
+    };
+    (@fields $parent:tt $level:expr, $name:expr; [$($out:tt)*] $key:ident = _ $(, $($rest:tt)*)?) => {
+        $crate::__span!(@fields $parent $level, $name; [$($out)*, $key = $crate::tracing::field::Empty] $($($rest)*)?)
+    };
+    (@fields $parent:tt $level:expr, $name:expr; [$($out:tt)*] $key:ident = % $value:expr $(, $($rest:tt)*)?) => {
+        $crate::__span!(@fields $parent $level, $name; [$($out)*, $key = %$value] $($($rest)*)?)
+    };
+    (@fields $parent:tt $level:expr, $name:expr; [$($out:tt)*] $key:ident = ? $value:expr $(, $($rest:tt)*)?) => {
+        $crate::__span!(@fields $parent $level, $name; [$($out)*, $key = ?$value] $($($rest)*)?)
+    };
+    (@fields $parent:tt $level:expr, $name:expr; [$($out:tt)*] $key:ident = $value:expr $(, $($rest:tt)*)?) => {
+        $crate::__span!(@fields $parent $level, $name; [$($out)*, $key = $value] $($($rest)*)?)
+    };
+    (@fields $parent:tt $level:expr, $name:expr; [$($out:tt)*] % $key:ident $(, $($rest:tt)*)?) => {
+        $crate::__span!(@fields $parent $level, $name; [$($out)*, %$key] $($($rest)*)?)
+    };
+    (@fields $parent:tt $level:expr, $name:expr; [$($out:tt)*] ? $key:ident $(, $($rest:tt)*)?) => {
+        $crate::__span!(@fields $parent $level, $name; [$($out)*, ?$key] $($($rest)*)?)
+    };
+    (@fields $parent:tt $level:expr, $name:expr; [$($out:tt)*] $key:ident $(, $($rest:tt)*)?) => {
+        $crate::__span!(@fields $parent $level, $name; [$($out)*, $key] $($($rest)*)?)
+    };
+    ($level:expr, parent = $parent:expr, $name:expr $(, $($fields:tt)*)?) => {
+        $crate::__span!(@fields [parent: $parent,] $level, $name; [] $($($fields)*)?)
+    };
+    ($level:expr, $name:expr $(, $($fields:tt)*)?) => {
+        $crate::__span!(@fields [] $level, $name; [] $($($fields)*)?)
+    };
+}
+
+/// Creates a span at the `TRACE` level, accepting the same arguments as [`info_span!`](crate::info_span).
+#[macro_export]
+macro_rules! trace_span {
+    ($($arguments:tt)*) => {
+        $crate::__span!($crate::tracing::Level::TRACE, $($arguments)*)
+    };
+}
+
+/// Creates a span at the `DEBUG` level, accepting the same arguments as [`info_span!`](crate::info_span).
+#[macro_export]
+macro_rules! debug_span {
+    ($($arguments:tt)*) => {
+        $crate::__span!($crate::tracing::Level::DEBUG, $($arguments)*)
+    };
+}
+
+/// Creates a span at the `INFO` level.
+///
+/// The span macros accept everything the `tracing` macros do after the span name (`key = value`,
+/// `key = %display`, `key = ?debug` and shorthand `key`, `%key` or `?key` fields), along with a
+/// few additions:
+///
+/// - `parent = span` as the first argument sets the span's parent explicitly, `None` creates a
+///   root span.
+/// - `length = collection` records `collection.len()` as the `length` field.
+/// - `key = _` declares a field without a value, so it can be recorded later with
+///   `span.record("key", value)`.
+///
+/// ```ignore
+/// let span = info_span!("import", length = files, path = %path.display(), imported = _);
+/// let _guard = span.enter();
+/// // ...
+/// span.record("imported", count);
+/// ```
+#[macro_export]
+macro_rules! info_span {
+    ($($arguments:tt)*) => {
+        $crate::__span!($crate::tracing::Level::INFO, $($arguments)*)
+    };
+}
+
+/// Creates a span at the `WARN` level, accepting the same arguments as [`info_span!`](crate::info_span).
+#[macro_export]
+macro_rules! warn_span {
+    ($($arguments:tt)*) => {
+        $crate::__span!($crate::tracing::Level::WARN, $($arguments)*)
+    };
+}
+
+/// Creates a span at the `ERROR` level, accepting the same arguments as [`info_span!`](crate::info_span).
+#[macro_export]
+macro_rules! error_span {
+    ($($arguments:tt)*) => {
+        $crate::__span!($crate::tracing::Level::ERROR, $($arguments)*)
+    };
+}
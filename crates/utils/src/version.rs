@@ -0,0 +1,7 @@
+//! Exposes the version of the editor.
+
+/// The version of the editor, shared by every crate of the workspace.
+#[must_use]
+pub const fn version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
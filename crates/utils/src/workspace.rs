@@ -0,0 +1,312 @@
+//! Contains the [`TempWorkspace`], the temporary directories of the running session.
+//!
+//! Every session gets its own directory in the workspace root, holding a lock file that stays
+//! locked until the session ends. The sessions are listed in a registry file in the root, so the
+//! next session can find the directories of sessions that crashed (whose lock file is no longer
+//! held) and remove them.
+
+use bevy::prelude::Resource;
+use std::fs::{File, OpenOptions, create_dir_all, read_dir, read_to_string, remove_dir_all, write};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The name of the file listing the sessions, in the workspace root.
+const REGISTRY_FILE: &str = "sessions";
+
+/// The name of the file locked by a session for as long as it runs, in its directory.
+const LOCK_FILE: &str = ".lock";
+
+/// What a temporary directory is used for, each kind gets its own directory within the session.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TempKind {
+    /// Files written by an export before they're moved to their destination.
+    Exports,
+    /// Data that doesn't fit in memory, such as large undo histories.
+    Spill,
+    /// Partially downloaded files.
+    Downloads,
+    /// Journals of unsaved changes, written in case the editor crashes.
+    CrashJournals,
+}
+
+impl TempKind {
+    /// The name of the directory of this kind within the session.
+    fn name(self) -> &'static str {
+        match self {
+            TempKind::Exports => "exports",
+            TempKind::Spill => "spill",
+            TempKind::Downloads => "downloads",
+            TempKind::CrashJournals => "crash-journals",
+        }
+    }
+}
+
+/// The temporary directories of the running session, removed when it's dropped.
+///
+/// Opening a workspace removes the directories left behind by sessions that crashed.
+#[derive(Resource, Debug)]
+pub struct TempWorkspace {
+    /// The directory containing the registry and the directories of every session.
+    root: PathBuf,
+    /// The directory of this session.
+    session: PathBuf,
+    /// The lock file of this session, locked for as long as the workspace lives.
+    lock: File,
+}
+
+impl TempWorkspace {
+    /// Opens a workspace in the default root, in the system's temporary directory.
+    ///
+    /// # Errors
+    /// Returns an error if the session directory can't be created or registered.
+    pub fn open() -> io::Result<Self> {
+        Self::open_in(Self::default_root())
+    }
+
+    /// Opens a workspace in `root`, removing the directories of sessions that crashed.
+    ///
+    /// # Errors
+    /// Returns an error if the session directory can't be created or registered.
+    pub fn open_in(root: impl Into<PathBuf>) -> io::Result<Self> {
+        let root = root.into();
+        create_dir_all(&root)?;
+        // Cleaning up is best effort, whatever is left behind is retried on the next startup.
+        let _ = Self::clean_orphans(&root);
+
+        let name = session_name();
+        let session = root.join(&name);
+        create_dir_all(&session)?;
+        let lock = File::create(session.join(LOCK_FILE))?;
+        lock.try_lock().map_err(io::Error::from)?;
+        update_registry(&root, |sessions| sessions.push(name))?;
+
+        Ok(Self {
+            root,
+            session,
+            lock,
+        })
+    }
+
+    /// The root workspaces are opened in by default.
+    #[must_use]
+    pub fn default_root() -> PathBuf {
+        std::env::temp_dir().join("DungeonRS").join("workspace")
+    }
+
+    /// The directory of this session.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.session
+    }
+
+    /// Returns the directory for files of `kind`, creating it if it doesn't exist yet.
+    ///
+    /// # Errors
+    /// Returns an error if the directory doesn't exist and can't be created.
+    pub fn directory(&self, kind: TempKind) -> io::Result<PathBuf> {
+        let path = self.session.join(kind.name());
+        create_dir_all(&path)?;
+
+        Ok(path)
+    }
+
+    /// Removes the directories in `root` of sessions that are no longer running, along with their
+    /// registrations.
+    ///
+    /// Directories that aren't registered are removed as well, a session can crash between
+    /// creating its directory and registering it.
+    ///
+    /// Returns the number of directories that were removed.
+    ///
+    /// # Errors
+    /// Returns an error if `root` or the registry can't be read or updated.
+    pub fn clean_orphans(root: &Path) -> io::Result<usize> {
+        let mut removed = 0;
+        for entry in read_dir(root)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() && !is_running(&entry.path()) {
+                remove_dir_all(entry.path())?;
+                removed += 1;
+            }
+        }
+
+        update_registry(root, |sessions| {
+            sessions.retain(|session| root.join(session).is_dir());
+        })?;
+
+        Ok(removed)
+    }
+}
+
+impl Drop for TempWorkspace {
+    fn drop(&mut self) {
+        // Whatever can't be removed now is removed as an orphan by the next session.
+        let _ = self.lock.unlock();
+        let _ = remove_dir_all(&self.session);
+        let name = self.session.file_name().map(|name| name.to_string_lossy());
+        let _ = update_registry(&self.root, |sessions| {
+            sessions.retain(|session| Some(session.as_str()) != name.as_deref());
+        });
+    }
+}
+
+/// A name for the directory of this session that's unique across sessions.
+fn session_name() -> String {
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    format!("{}-{started}", std::process::id())
+}
+
+/// Whether the session in `directory` is still running, that is whether its lock file is held.
+///
+/// Sessions whose lock file exists but can't be opened are assumed to be running, so their files
+/// are kept.
+fn is_running(directory: &Path) -> bool {
+    match OpenOptions::new()
+        .write(true)
+        .open(directory.join(LOCK_FILE))
+    {
+        Ok(lock) => lock.try_lock().is_err(),
+        // The lock file is created right after the directory, without it the session crashed.
+        Err(error) => error.kind() != io::ErrorKind::NotFound,
+    }
+}
+
+/// Reads the sessions listed in the registry of `root`, changes them with `update` and writes
+/// them back.
+///
+/// The registry is locked while it's updated, so sessions starting at the same time don't
+/// overwrite each other's changes.
+///
+/// # Errors
+/// Returns an error if the registry can't be locked, read or written.
+fn update_registry(root: &Path, update: impl FnOnce(&mut Vec<String>)) -> io::Result<()> {
+    let path = root.join(REGISTRY_FILE);
+    let registry = OpenOptions::new().create(true).append(true).open(&path)?;
+    registry.lock()?;
+
+    let mut sessions: Vec<String> = read_to_string(&path)?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(ToOwned::to_owned)
+        .collect();
+    update(&mut sessions);
+    let mut contents = sessions.join("\n");
+    if !contents.is_empty() {
+        contents.push('\n');
+    }
+
+    write(&path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    //! Removes the directories of crashed sessions while keeping those of running ones.
+    #![allow(clippy::missing_panics_doc)]
+
+    use super::*;
+    use crate::UtilsPlugin;
+    use bevy::prelude::App;
+
+    /// Creates an empty workspace root, unique to `test`.
+    fn root(test: &str) -> PathBuf {
+        let root = std::env::temp_dir()
+            .join("DungeonRS-tests")
+            .join(format!("{test}-{}", session_name()));
+        create_dir_all(&root).expect("the root is created");
+
+        root
+    }
+
+    /// Creates the directory of a session that crashed after `registered` it, leaving its lock
+    /// file behind when `locked`.
+    fn crashed_session(root: &Path, name: &str, registered: bool, locked: bool) -> PathBuf {
+        let session = root.join(name);
+        create_dir_all(session.join(TempKind::Exports.name())).expect("the session is created");
+        if locked {
+            File::create(session.join(LOCK_FILE)).expect("the lock file is created");
+        }
+        if registered {
+            update_registry(root, |sessions| sessions.push(name.into()))
+                .expect("the session is registered");
+        }
+
+        session
+    }
+
+    /// Reads the sessions listed in the registry of `root`.
+    fn registered(root: &Path) -> Vec<String> {
+        read_to_string(root.join(REGISTRY_FILE))
+            .expect("the registry is readable")
+            .lines()
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    /// Opening a workspace removes the sessions that crashed, and keeps the running ones.
+    #[test]
+    fn removes_crashed_sessions() {
+        let root = root("crashed");
+        let running = TempWorkspace::open_in(&root).expect("the workspace opens");
+        let crashed = crashed_session(&root, "crashed", true, true);
+        let unregistered = crashed_session(&root, "unregistered", false, true);
+        let unlocked = crashed_session(&root, "unlocked", true, false);
+
+        let workspace = TempWorkspace::open_in(&root).expect("the workspace opens");
+        assert!(running.path().is_dir());
+        assert!(workspace.path().is_dir());
+        for session in [crashed, unregistered, unlocked] {
+            assert!(!session.exists(), "{} was kept", session.display());
+        }
+
+        let mut sessions = registered(&root);
+        sessions.sort_unstable();
+        let mut expected: Vec<_> = [running.path(), workspace.path()]
+            .iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(sessions, expected);
+
+        drop((running, workspace));
+        let _ = remove_dir_all(root);
+    }
+
+    /// A workspace removes its own directory and registration when it's dropped.
+    #[test]
+    fn removes_itself_when_dropped() {
+        let root = root("dropped");
+        let workspace = TempWorkspace::open_in(&root).expect("the workspace opens");
+        let session = workspace.path().to_path_buf();
+        assert!(
+            workspace
+                .directory(TempKind::Spill)
+                .is_ok_and(|path| path.is_dir())
+        );
+
+        drop(workspace);
+        assert!(!session.exists());
+        assert!(registered(&root).is_empty());
+        let _ = remove_dir_all(root);
+    }
+
+    /// The [`UtilsPlugin`] opens the workspace in the default root at startup.
+    #[test]
+    fn opens_at_startup() {
+        let mut app = App::new();
+        app.add_plugins(UtilsPlugin);
+        app.update();
+
+        let workspace = app.world().get_resource::<TempWorkspace>();
+        assert!(
+            workspace.is_some_and(|workspace| workspace
+                .path()
+                .starts_with(TempWorkspace::default_root()))
+        );
+    }
+}